@@ -3,6 +3,7 @@ use exchange_rs::{
     matching_engine::MatchingEngine,
     optimizations::OrderProcessorPool,
     order::{Order, OrderType, Side},
+    Price,
 };
 use parking_lot::Mutex;
 use std::sync::Arc;
@@ -23,7 +24,7 @@ pub fn bench_concurrent_order_processing(c: &mut Criterion) {
 
             for i in 0..1000 {
                 let side = if i % 2 == 0 { Side::Buy } else { Side::Sell };
-                let price = 100 + (i % 10);
+                let price = 100 + (i % 10) as Price;
 
                 let order = Order::new("AAPL".to_string(), side, OrderType::Limit, price, 1, i);
 
@@ -32,6 +33,29 @@ pub fn bench_concurrent_order_processing(c: &mut Criterion) {
         })
     });
 
+    group.bench_function("process_1000_orders_batch", |b| {
+        b.iter(|| {
+            let engine = Arc::new(Mutex::new(MatchingEngine::new()));
+
+            {
+                let mut engine_ref = engine.lock();
+                engine_ref.add_symbol("AAPL");
+            }
+
+            let pool = OrderProcessorPool::new(4, Arc::clone(&engine));
+
+            let orders = (0..1000)
+                .map(|i| {
+                    let side = if i % 2 == 0 { Side::Buy } else { Side::Sell };
+                    let price = 100 + (i % 10) as Price;
+                    Order::new("AAPL".to_string(), side, OrderType::Limit, price, 1, i)
+                })
+                .collect();
+
+            let _ = pool.submit_batch(orders);
+        })
+    });
+
     group.bench_function("concurrent_market_data", |b| {
         b.iter(|| {
             let engine = Arc::new(Mutex::new(MatchingEngine::new()));
@@ -42,7 +66,7 @@ pub fn bench_concurrent_order_processing(c: &mut Criterion) {
                     "AAPL".to_string(),
                     Side::Sell,
                     OrderType::Limit,
-                    100 + (i % 5),
+                    100 + (i % 5) as Price,
                     10,
                     i,
                 );
@@ -0,0 +1,52 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use exchange_rs::order::{Order, OrderType, Side, TimeInForce};
+use exchange_rs::orderbook::OrderBook;
+use exchange_rs::Price;
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+const GTC_ORDERS: u64 = 100_000;
+const GTD_ORDERS: u64 = 100;
+const DEADLINE: i64 = 1_000;
+
+/// 100,000 GTC orders and 100 GTD orders that all expire at `DEADLINE`.
+fn book() -> OrderBook {
+    let mut book = OrderBook::new("BENCH");
+    for id in 1..=GTC_ORDERS + GTD_ORDERS {
+        let price = 100_000_000 - (id % 1_000) as Price * 10_000;
+        let mut order = Order::new("BENCH".to_string(), Side::Buy, OrderType::Limit, price, 10, 1);
+        order.id = id;
+        if id > GTC_ORDERS {
+            order.time_in_force = TimeInForce::GTD;
+            order.expiration_time = DEADLINE;
+        }
+        book.add_order(Arc::new(RwLock::new(order))).unwrap();
+    }
+    book
+}
+
+fn expiry_scan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("expiry");
+    group.sample_size(20);
+
+    let mut idle = book();
+    group.bench_function("expire_orders_none_due", |b| {
+        b.iter(|| idle.expire_orders(black_box(DEADLINE - 1)))
+    });
+    // What the scan cost when it locked every resting order.
+    group.bench_function("full_scan_none_due", |b| {
+        b.iter(|| {
+            idle.orders()
+                .filter(|order| order.read().is_expired(black_box(DEADLINE - 1)))
+                .count()
+        })
+    });
+    group.bench_function("expire_orders_100_due", |b| {
+        b.iter_batched_ref(book, |book| book.expire_orders(black_box(DEADLINE)), BatchSize::PerIteration)
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, expiry_scan);
+criterion_main!(benches);
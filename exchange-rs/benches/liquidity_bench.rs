@@ -0,0 +1,63 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use exchange_rs::order::{Order, OrderType, Side};
+use exchange_rs::orderbook::{OrderBook, VolumeView};
+use exchange_rs::Price;
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+const LEVELS: u64 = 5_000;
+const TICK: Price = 10_000;
+const MID: Price = 100_000_000;
+
+/// 5,000 levels a side, each with two orders, one of them an iceberg.
+fn deep_book() -> OrderBook {
+    let mut book = OrderBook::new("BENCH");
+    let mut id = 0;
+    for level in 1..=LEVELS {
+        for (side, price) in [
+            (Side::Buy, MID - level as Price * TICK),
+            (Side::Sell, MID + level as Price * TICK),
+        ] {
+            id += 1;
+            let mut order = Order::new("BENCH".to_string(), side, OrderType::Limit, price, 10, 1);
+            order.id = id;
+            book.add_order(Arc::new(RwLock::new(order))).unwrap();
+
+            id += 1;
+            let mut iceberg =
+                Order::new("BENCH".to_string(), side, OrderType::Iceberg, price, 50, 2);
+            iceberg.id = id;
+            iceberg.display_quantity = Some(5);
+            book.add_order(Arc::new(RwLock::new(iceberg))).unwrap();
+        }
+    }
+    book
+}
+
+fn liquidity_queries(c: &mut Criterion) {
+    let book = deep_book();
+    // Visible volume is 15 a level, so this sweeps the whole side.
+    let whole_side = 15 * LEVELS;
+    let mut group = c.benchmark_group("liquidity");
+
+    group.bench_function("cumulative_depth_5k_levels", |b| {
+        b.iter(|| {
+            book.cumulative_depth(black_box(Side::Sell), LEVELS as usize, VolumeView::Visible)
+                .last()
+        })
+    });
+    group.bench_function("vwap_for_quantity_5k_levels", |b| {
+        b.iter(|| book.vwap_for_quantity(black_box(Side::Buy), whole_side, VolumeView::Visible))
+    });
+    group.bench_function("impact_price_5k_levels", |b| {
+        b.iter(|| book.impact_price(black_box(Side::Sell), whole_side, VolumeView::Visible))
+    });
+    group.bench_function("vwap_for_quantity_total_100_lots", |b| {
+        b.iter(|| book.vwap_for_quantity(black_box(Side::Sell), 100, VolumeView::Total))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, liquidity_queries);
+criterion_main!(benches);
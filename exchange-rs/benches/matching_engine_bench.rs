@@ -92,7 +92,7 @@ fn market_order_execution(c: &mut Criterion) {
                             "DEPTH_TEST".to_string(),
                             Side::Sell,
                             OrderType::Limit,
-                            50000000000 + (i as u64 * 1000),
+                            50000000000 + (i as Price * 1000),
                             100,
                             i as u64,
                         );
@@ -137,7 +137,7 @@ fn orderbook_depth_performance(c: &mut Criterion) {
                             "DEPTH_SYMBOL".to_string(),
                             Side::Buy,
                             OrderType::Limit,
-                            49999000000 - (i as u64 * 1000),
+                            49999000000 - (i as Price * 1000),
                             100,
                             i as u64,
                         );
@@ -147,7 +147,7 @@ fn orderbook_depth_performance(c: &mut Criterion) {
                             "DEPTH_SYMBOL".to_string(),
                             Side::Sell,
                             OrderType::Limit,
-                            50001000000 + (i as u64 * 1000),
+                            50001000000 + (i as Price * 1000),
                             100,
                             (i + 100) as u64,
                         );
@@ -183,7 +183,7 @@ fn concurrent_order_processing(c: &mut Criterion) {
                         "CONCURRENT_TEST".to_string(),
                         if i % 2 == 0 { Side::Buy } else { Side::Sell },
                         OrderType::Limit,
-                        if i % 2 == 0 { 49000000000 - (i as u64 * 1000) } else { 51000000000 + (i as u64 * 1000) },
+                        if i % 2 == 0 { 49000000000 - (i as Price * 1000) } else { 51000000000 + (i as Price * 1000) },
                         100,
                         i as u64,
                     )
@@ -276,7 +276,7 @@ fn stop_order_triggering(c: &mut Criterion) {
                 stop_order
             },
             |order| {
-                for price in [50000000000u64, 50500000000, 51000000000, 51500000000, 52000000000].iter() {
+                for price in [50000000000, 50500000000, 51000000000, 51500000000, 52000000000].iter() {
                     black_box(order.is_stop_triggered(*price));
                 }
             },
@@ -303,7 +303,7 @@ fn large_orderbook_stress(c: &mut Criterion) {
                         "STRESS_TEST".to_string(),
                         Side::Buy,
                         OrderType::Limit,
-                        49000000000 - (i as u64 * 100),
+                        49000000000 - (i as Price * 100),
                         100,
                         i as u64,
                     );
@@ -313,7 +313,7 @@ fn large_orderbook_stress(c: &mut Criterion) {
                         "STRESS_TEST".to_string(),
                         Side::Sell,
                         OrderType::Limit,
-                        51000000000 + (i as u64 * 100),
+                        51000000000 + (i as Price * 100),
                         100,
                         (i + 10000) as u64,
                     );
@@ -340,6 +340,165 @@ fn large_orderbook_stress(c: &mut Criterion) {
     group.finish();
 }
 
+fn deep_book_best_price(c: &mut Criterion) {
+    let mut group = c.benchmark_group("deep_book");
+
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("DEEP_BOOK");
+
+    for i in 0..5000 {
+        let buy_order = Order::new(
+            "DEEP_BOOK".to_string(),
+            Side::Buy,
+            OrderType::Limit,
+            49999000000 - (i as Price * 1000),
+            100,
+            i as u64,
+        );
+        engine.place_order(buy_order).unwrap();
+
+        let sell_order = Order::new(
+            "DEEP_BOOK".to_string(),
+            Side::Sell,
+            OrderType::Limit,
+            50001000000 + (i as Price * 1000),
+            100,
+            (i + 5000) as u64,
+        );
+        engine.place_order(sell_order).unwrap();
+    }
+
+    let orderbook = engine.order_books.get("DEEP_BOOK").unwrap();
+
+    group.bench_function("best_bid_5000_levels", |b| {
+        b.iter(|| black_box(orderbook.get_best_bid_price()))
+    });
+
+    group.bench_function("best_ask_5000_levels", |b| {
+        b.iter(|| black_box(orderbook.get_best_ask_price()))
+    });
+
+    group.finish();
+}
+
+/// Best price, a bounded FOK walk and the depth snapshot on a book with
+/// 10k levels per side, none of which should scale with the book size.
+fn ten_thousand_level_book(c: &mut Criterion) {
+    let mut group = c.benchmark_group("deep_book_10k");
+    const LEVELS: u64 = 10_000;
+
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("DEEP_BOOK");
+
+    for i in 0..LEVELS {
+        let buy_order = Order::new(
+            "DEEP_BOOK".to_string(),
+            Side::Buy,
+            OrderType::Limit,
+            49999000000 - (i as Price * 1000),
+            100,
+            i,
+        );
+        engine.place_order(buy_order).unwrap();
+
+        let sell_order = Order::new(
+            "DEEP_BOOK".to_string(),
+            Side::Sell,
+            OrderType::Limit,
+            50001000000 + (i as Price * 1000),
+            100,
+            i + LEVELS,
+        );
+        engine.place_order(sell_order).unwrap();
+    }
+
+    {
+        let orderbook = engine.order_books.get("DEEP_BOOK").unwrap();
+
+        group.bench_function("best_bid_10000_levels", |b| {
+            b.iter(|| black_box(orderbook.get_best_bid_price()))
+        });
+
+        group.bench_function("best_ask_10000_levels", |b| {
+            b.iter(|| black_box(orderbook.get_best_ask_price()))
+        });
+
+        group.bench_function("market_depth_10000_levels", |b| {
+            b.iter(|| black_box(orderbook.get_market_depth()))
+        });
+    }
+
+    // Ten levels are in range but hold too little, so the check walks them
+    // and stops at the first price beyond the limit.
+    group.bench_function("fok_check_10000_levels", |b| {
+        b.iter(|| {
+            let mut fok_order = Order::new(
+                "DEEP_BOOK".to_string(),
+                Side::Buy,
+                OrderType::Limit,
+                50001000000 + 9 * 1000,
+                1_001,
+                2 * LEVELS,
+            );
+            fok_order.time_in_force = TimeInForce::FOK;
+            black_box(engine.place_order(fok_order).is_err())
+        })
+    });
+
+    group.finish();
+}
+
+/// Cancels every order resting on one crowded price level, oldest first,
+/// newest first and alternating from the middle outwards.
+fn cancel_heavy_level(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cancel_heavy");
+    const ORDERS: u64 = 10_000;
+
+    let orders: Vec<u64> = (1..=ORDERS).collect();
+    let middle_out: Vec<u64> = {
+        let (front, back) = orders.split_at(orders.len() / 2);
+        back.iter().zip(front.iter().rev()).flat_map(|(&a, &b)| [a, b]).collect()
+    };
+    let newest_first: Vec<u64> = orders.iter().rev().copied().collect();
+
+    for (name, sequence) in [
+        ("oldest_first", &orders),
+        ("newest_first", &newest_first),
+        ("middle_out", &middle_out),
+    ] {
+        group.throughput(Throughput::Elements(ORDERS));
+        group.bench_function(BenchmarkId::new("cancel_10000_orders_one_level", name), |b| {
+            b.iter_batched(
+                || {
+                    let mut engine = MatchingEngine::new();
+                    engine.add_symbol("CROWDED");
+                    for user_id in 0..ORDERS {
+                        let order = Order::new(
+                            "CROWDED".to_string(),
+                            Side::Buy,
+                            OrderType::Limit,
+                            49999000000,
+                            100,
+                            user_id,
+                        );
+                        engine.place_order(order).unwrap();
+                    }
+                    engine
+                },
+                |mut engine| {
+                    for &order_id in sequence {
+                        black_box(engine.cancel_order("CROWDED", order_id));
+                    }
+                    engine
+                },
+                criterion::BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     single_order_placement,
@@ -350,6 +509,9 @@ criterion_group!(
     price_utils_performance,
     iceberg_order_processing,
     stop_order_triggering,
-    large_orderbook_stress
+    large_orderbook_stress,
+    deep_book_best_price,
+    ten_thousand_level_book,
+    cancel_heavy_level
 );
 criterion_main!(benches);
\ No newline at end of file
@@ -2,6 +2,7 @@ use criterion::Criterion;
 use exchange_rs::{
     matching_engine::MatchingEngine,
     order::{Order, OrderType, Side},
+    Price,
 };
 
 pub fn bench_limit_order_matching(c: &mut Criterion) {
@@ -14,7 +15,7 @@ pub fn bench_limit_order_matching(c: &mut Criterion) {
 
             for i in 0..1000 {
                 let side = if i % 2 == 0 { Side::Buy } else { Side::Sell };
-                let price = 100 + (i % 10);
+                let price = 100 + (i % 10) as Price;
 
                 let order = Order::new("AAPL".to_string(), side, OrderType::Limit, price, 1, i);
 
@@ -33,7 +34,7 @@ pub fn bench_limit_order_matching(c: &mut Criterion) {
                     "AAPL".to_string(),
                     Side::Sell,
                     OrderType::Limit,
-                    100 + i,
+                    100 + i as Price,
                     10,
                     i,
                 );
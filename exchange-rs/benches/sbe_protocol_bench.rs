@@ -89,8 +89,8 @@ fn create_sample_instrument_data() -> Vec<u8> {
     let mut buffer = vec![0u8; 256];
     
     buffer[0..2].copy_from_slice(&120u16.to_le_bytes());
-    buffer[2..4].copy_from_slice(&0u16.to_le_bytes());
-    buffer[4..6].copy_from_slice(&1000u16.to_le_bytes());
+    buffer[2..4].copy_from_slice(&1000u16.to_le_bytes());
+    buffer[4..6].copy_from_slice(&1u16.to_le_bytes());
     buffer[6..8].copy_from_slice(&3u16.to_le_bytes());
     buffer[8..12].copy_from_slice(&0u32.to_le_bytes());
     
@@ -118,8 +118,8 @@ fn create_sample_book_data() -> Vec<u8> {
     let mut buffer = vec![0u8; 256];
     
     buffer[0..2].copy_from_slice(&29u16.to_le_bytes());
-    buffer[2..4].copy_from_slice(&0u16.to_le_bytes());
-    buffer[4..6].copy_from_slice(&1001u16.to_le_bytes());
+    buffer[2..4].copy_from_slice(&1001u16.to_le_bytes());
+    buffer[4..6].copy_from_slice(&1u16.to_le_bytes());
     buffer[6..8].copy_from_slice(&3u16.to_le_bytes());
     buffer[8..12].copy_from_slice(&0u32.to_le_bytes());
     
@@ -137,8 +137,8 @@ fn create_sample_trades_data() -> Vec<u8> {
     let mut buffer = vec![0u8; 256];
     
     buffer[0..2].copy_from_slice(&4u16.to_le_bytes());
-    buffer[2..4].copy_from_slice(&0u16.to_le_bytes());
-    buffer[4..6].copy_from_slice(&1002u16.to_le_bytes());
+    buffer[2..4].copy_from_slice(&1002u16.to_le_bytes());
+    buffer[4..6].copy_from_slice(&1u16.to_le_bytes());
     buffer[6..8].copy_from_slice(&3u16.to_le_bytes());
     buffer[8..12].copy_from_slice(&0u32.to_le_bytes());
     
@@ -152,8 +152,8 @@ fn create_sample_ticker_data() -> Vec<u8> {
     let mut buffer = vec![0u8; 256];
     
     buffer[0..2].copy_from_slice(&120u16.to_le_bytes());
-    buffer[2..4].copy_from_slice(&0u16.to_le_bytes());
-    buffer[4..6].copy_from_slice(&1003u16.to_le_bytes());
+    buffer[2..4].copy_from_slice(&1003u16.to_le_bytes());
+    buffer[4..6].copy_from_slice(&1u16.to_le_bytes());
     buffer[6..8].copy_from_slice(&3u16.to_le_bytes());
     buffer[8..12].copy_from_slice(&0u32.to_le_bytes());
     
@@ -173,8 +173,8 @@ fn create_sample_snapshot_data() -> Vec<u8> {
     let mut buffer = vec![0u8; 256];
     
     buffer[0..2].copy_from_slice(&20u16.to_le_bytes());
-    buffer[2..4].copy_from_slice(&0u16.to_le_bytes());
-    buffer[4..6].copy_from_slice(&1004u16.to_le_bytes());
+    buffer[2..4].copy_from_slice(&1004u16.to_le_bytes());
+    buffer[4..6].copy_from_slice(&1u16.to_le_bytes());
     buffer[6..8].copy_from_slice(&3u16.to_le_bytes());
     buffer[8..12].copy_from_slice(&0u32.to_le_bytes());
     
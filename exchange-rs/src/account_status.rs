@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use thiserror::Error;
+
+use crate::matching_engine::{MatchingEngine, TradeExecutionResult};
+use crate::settlement::trading_day;
+
+/// WebSocket method answered with an `AccountStatus`.
+pub const WS_METHOD: &str = "account.status";
+
+/// User-defined FIX MsgType of the status request and its reply.
+pub const FIX_REQUEST_MSG_TYPE: &str = "U1";
+pub const FIX_REPORT_MSG_TYPE: &str = "U2";
+
+/// Tags of the FIX status report. User id goes in Account (1).
+pub const TAG_OPEN_ORDERS: u32 = 20001;
+pub const TAG_OPEN_ORDER_HEADROOM: u32 = 20002;
+pub const TAG_MESSAGES_TODAY: u32 = 20003;
+pub const TAG_ORDERS_TODAY: u32 = 20004;
+pub const TAG_CANCELS_TODAY: u32 = 20005;
+pub const TAG_FILLS_TODAY: u32 = 20006;
+pub const TAG_FILL_RATIO: u32 = 20007;
+
+/// Shortest gap between two status queries of one user by default.
+pub const DEFAULT_QUERY_INTERVAL_NS: i64 = 1_000_000_000;
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountStatusError {
+    #[error("Account status queried too often, retry in {retry_after_ns}ns")]
+    Throttled { retry_after_ns: i64 },
+}
+
+/// Inbound message kinds counted against a user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    Order,
+    Cancel,
+    Other,
+}
+
+/// A user's view of their own account for the current trading day.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountStatus {
+    pub user_id: u64,
+    pub open_orders: usize,
+    /// Orders the user may still open before `max_open_orders`. `None`
+    /// when no limit applies.
+    pub open_order_headroom: Option<usize>,
+    pub messages_today: u64,
+    pub orders_today: u64,
+    pub cancels_today: u64,
+    pub fills_today: u64,
+    /// Fills per order entered today, zero before the first order.
+    pub fill_ratio: f64,
+}
+
+impl AccountStatus {
+    /// JSON-RPC style reply to a WS `account.status` request.
+    pub fn to_ws_response(&self, request_id: u64) -> Value {
+        json!({
+            "id": request_id,
+            "method": WS_METHOD,
+            "result": self,
+        })
+    }
+
+    /// Body fields of the FIX status report, in tag order.
+    pub fn to_fix_fields(&self) -> Vec<(u32, String)> {
+        let mut fields = vec![
+            (1, self.user_id.to_string()),
+            (TAG_OPEN_ORDERS, self.open_orders.to_string()),
+        ];
+        if let Some(headroom) = self.open_order_headroom {
+            fields.push((TAG_OPEN_ORDER_HEADROOM, headroom.to_string()));
+        }
+        fields.extend([
+            (TAG_MESSAGES_TODAY, self.messages_today.to_string()),
+            (TAG_ORDERS_TODAY, self.orders_today.to_string()),
+            (TAG_CANCELS_TODAY, self.cancels_today.to_string()),
+            (TAG_FILLS_TODAY, self.fills_today.to_string()),
+            (TAG_FILL_RATIO, format!("{:.4}", self.fill_ratio)),
+        ]);
+        fields
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct DailyActivity {
+    trading_day: i64,
+    messages: u64,
+    orders: u64,
+    cancels: u64,
+    fills: u64,
+}
+
+/// Per-user message counts for the trading day, kept by the gateways a
+/// user talks to, and the rate limit on status queries. Counters start
+/// over at the first message of a new trading day.
+pub struct AccountMonitor {
+    query_interval_ns: i64,
+    activity: HashMap<u64, DailyActivity>,
+    last_query: HashMap<u64, i64>,
+}
+
+impl Default for AccountMonitor {
+    fn default() -> Self {
+        Self::new(DEFAULT_QUERY_INTERVAL_NS)
+    }
+}
+
+impl AccountMonitor {
+    pub fn new(query_interval_ns: i64) -> Self {
+        Self {
+            query_interval_ns,
+            activity: HashMap::new(),
+            last_query: HashMap::new(),
+        }
+    }
+
+    fn today(&mut self, user_id: u64, now: i64) -> &mut DailyActivity {
+        let day = trading_day(now);
+        let activity = self.activity.entry(user_id).or_default();
+        if activity.trading_day != day {
+            *activity = DailyActivity {
+                trading_day: day,
+                ..DailyActivity::default()
+            };
+        }
+        activity
+    }
+
+    pub fn record_message(&mut self, user_id: u64, kind: MessageKind, now: i64) {
+        let activity = self.today(user_id, now);
+        activity.messages += 1;
+        match kind {
+            MessageKind::Order => activity.orders += 1,
+            MessageKind::Cancel => activity.cancels += 1,
+            MessageKind::Other => {}
+        }
+    }
+
+    /// Counts the trades reported back on an order `user_id` entered.
+    pub fn record_execution(&mut self, user_id: u64, result: &TradeExecutionResult, now: i64) {
+        self.today(user_id, now).fills += result.trades.len() as u64;
+    }
+
+    /// Status of `user_id` as of `now`. The query counts as a message and
+    /// is refused when it comes sooner than the query interval after the
+    /// previous one.
+    pub fn query(
+        &mut self,
+        engine: &MatchingEngine,
+        user_id: u64,
+        now: i64,
+    ) -> Result<AccountStatus, AccountStatusError> {
+        if let Some(&last) = self.last_query.get(&user_id) {
+            let retry_after_ns = last + self.query_interval_ns - now;
+            if retry_after_ns > 0 {
+                return Err(AccountStatusError::Throttled { retry_after_ns });
+            }
+        }
+        self.last_query.insert(user_id, now);
+        self.record_message(user_id, MessageKind::Other, now);
+
+        let activity = *self.today(user_id, now);
+        let open_orders = engine.open_order_count(user_id);
+        let fill_ratio = if activity.orders == 0 {
+            0.0
+        } else {
+            activity.fills as f64 / activity.orders as f64
+        };
+
+        Ok(AccountStatus {
+            user_id,
+            open_orders,
+            open_order_headroom: engine
+                .risk_limits(user_id)
+                .max_open_orders
+                .map(|limit| limit.saturating_sub(open_orders)),
+            messages_today: activity.messages,
+            orders_today: activity.orders,
+            cancels_today: activity.cancels,
+            fills_today: activity.fills,
+            fill_ratio,
+        })
+    }
+}
@@ -0,0 +1,270 @@
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{error, info, warn};
+
+use crate::credit::CreditLedger;
+use crate::events::EventRing;
+use crate::logging::{LogLevelController, LoggingError, SetLogLevel};
+use crate::matching_engine::MatchingEngine;
+use crate::positions::Positions;
+use crate::projection::{EventSourced, ProjectionError};
+use crate::tasks::TaskRegistry;
+
+/// Largest request head or body the admin server reads.
+const MAX_REQUEST_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdminResponse {
+    pub status: u16,
+    /// JSON body.
+    pub body: String,
+}
+
+impl AdminResponse {
+    fn json<T: Serialize>(value: &T) -> Self {
+        match serde_json::to_string(value) {
+            Ok(body) => Self { status: 200, body },
+            Err(e) => Self::error(500, &e.to_string()),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            body: serde_json::json!({ "error": message }).to_string(),
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            409 => "Conflict",
+            413 => "Payload Too Large",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+/// The event-sourced positions and credit ledgers, with the event ring
+/// they are rebuilt from.
+#[derive(Clone)]
+pub struct Ledgers {
+    pub positions: Arc<Mutex<EventSourced<Positions>>>,
+    pub credit: Arc<Mutex<EventSourced<CreditLedger>>>,
+    pub events: EventRing,
+}
+
+/// Operator endpoints over HTTP, one request per connection:
+///
+/// - `GET /logging` returns the current log levels.
+/// - `PUT /logging` changes one, taking a `SetLogLevel` body.
+/// - `GET /tasks` returns the state and health of each background task.
+/// - `POST /positions/rebuild` rebuilds positions and credit from the
+///   event ring, repairs them if they drifted, and returns both reports.
+/// - `GET /books/validate` checks every live book's invariants and lists
+///   the ones that fail.
+///
+/// Requests are answered by `handle`, so the TCP server and tests drive
+/// the endpoints alike.
+#[derive(Clone, Default)]
+pub struct AdminServer {
+    logging: Option<Arc<LogLevelController>>,
+    tasks: Option<TaskRegistry>,
+    ledgers: Option<Ledgers>,
+    engine: Option<Arc<Mutex<MatchingEngine>>>,
+}
+
+impl AdminServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_logging(mut self, controller: Arc<LogLevelController>) -> Self {
+        self.logging = Some(controller);
+        self
+    }
+
+    pub fn with_tasks(mut self, registry: TaskRegistry) -> Self {
+        self.tasks = Some(registry);
+        self
+    }
+
+    pub fn with_ledgers(mut self, ledgers: Ledgers) -> Self {
+        self.ledgers = Some(ledgers);
+        self
+    }
+
+    pub fn with_engine(mut self, engine: Arc<Mutex<MatchingEngine>>) -> Self {
+        self.engine = Some(engine);
+        self
+    }
+
+    pub fn handle(&self, method: &str, path: &str, body: &[u8]) -> AdminResponse {
+        match path {
+            "/logging" => match &self.logging {
+                Some(logging) => Self::logging(logging, method, body),
+                None => AdminResponse::error(404, "logging is not managed by this process"),
+            },
+            "/tasks" => match (&self.tasks, method) {
+                (Some(tasks), "GET") => AdminResponse::json(&tasks.statuses()),
+                (Some(_), _) => {
+                    AdminResponse::error(405, &format!("{} is not allowed on /tasks", method))
+                }
+                (None, _) => AdminResponse::error(404, "tasks are not managed by this process"),
+            },
+            "/positions/rebuild" => match (&self.ledgers, method) {
+                (Some(ledgers), "POST") => Self::rebuild(ledgers),
+                (Some(_), _) => AdminResponse::error(
+                    405,
+                    &format!("{} is not allowed on /positions/rebuild", method),
+                ),
+                (None, _) => AdminResponse::error(404, "positions are not managed by this process"),
+            },
+            "/books/validate" => match (&self.engine, method) {
+                (Some(engine), "GET") => Self::validate_books(&engine.lock()),
+                (Some(_), _) => AdminResponse::error(
+                    405,
+                    &format!("{} is not allowed on /books/validate", method),
+                ),
+                (None, _) => AdminResponse::error(404, "books are not managed by this process"),
+            },
+            _ => AdminResponse::error(404, &format!("no endpoint at {}", path)),
+        }
+    }
+
+    fn logging(controller: &LogLevelController, method: &str, body: &[u8]) -> AdminResponse {
+        match method {
+            "GET" => AdminResponse::json(&controller.levels()),
+            "PUT" => {
+                let change: SetLogLevel = match serde_json::from_slice(body) {
+                    Ok(change) => change,
+                    Err(e) => return AdminResponse::error(400, &e.to_string()),
+                };
+                match controller.set_level(&change) {
+                    Ok(audit) => AdminResponse::json(&audit),
+                    Err(e @ LoggingError::InvalidLevel { .. }) => {
+                        AdminResponse::error(400, &e.to_string())
+                    }
+                    Err(e @ LoggingError::HotPathLimit { .. }) => {
+                        AdminResponse::error(409, &e.to_string())
+                    }
+                    Err(e @ LoggingError::Reload { .. }) => {
+                        AdminResponse::error(500, &e.to_string())
+                    }
+                }
+            }
+            _ => AdminResponse::error(405, &format!("{} is not allowed on /logging", method)),
+        }
+    }
+
+    fn rebuild(ledgers: &Ledgers) -> AdminResponse {
+        let events = ledgers.events.events();
+        let rebuilt = ledgers.positions.lock().rebuild(&events).and_then(|positions| {
+            let credit = ledgers.credit.lock().rebuild(&events)?;
+            Ok(serde_json::json!({ "positions": positions, "credit": credit }))
+        });
+        match rebuilt {
+            Ok(reports) => AdminResponse::json(&reports),
+            Err(e @ ProjectionError::Unavailable { .. }) => AdminResponse::error(409, &e.to_string()),
+            Err(e @ ProjectionError::Gap { .. }) => AdminResponse::error(500, &e.to_string()),
+        }
+    }
+
+    fn validate_books(engine: &MatchingEngine) -> AdminResponse {
+        let violations: Vec<serde_json::Value> = engine
+            .validate_books()
+            .into_iter()
+            .map(|(symbol, e)| serde_json::json!({ "symbol": symbol, "error": e.to_string() }))
+            .collect();
+        AdminResponse::json(&serde_json::json!({
+            "books": engine.order_books.len(),
+            "violations": violations,
+        }))
+    }
+
+    /// Serves admin requests on `listener` until it fails.
+    pub async fn serve(self, listener: TcpListener) {
+        loop {
+            match listener.accept().await {
+                Ok((stream, addr)) => {
+                    let server = self.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = server.handle_connection(stream).await {
+                            warn!("Admin request from {} failed: {}", addr, e);
+                        }
+                    });
+                }
+                Err(e) => {
+                    error!("Failed to accept admin connection: {}", e);
+                }
+            }
+        }
+    }
+
+    async fn handle_connection(&self, mut stream: TcpStream) -> std::io::Result<()> {
+        let mut request = Vec::new();
+        let mut buffer = [0u8; 4096];
+        let head_end = loop {
+            if let Some(end) = request.windows(4).position(|window| window == b"\r\n\r\n") {
+                break end + 4;
+            }
+            if request.len() > MAX_REQUEST_BYTES {
+                return Self::respond(&mut stream, &AdminResponse::error(413, "request too large"))
+                    .await;
+            }
+            let read = stream.read(&mut buffer).await?;
+            if read == 0 {
+                return Ok(());
+            }
+            request.extend_from_slice(&buffer[..read]);
+        };
+
+        let head = String::from_utf8_lossy(&request[..head_end]).into_owned();
+        let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+        let method = request_line.next().unwrap_or_default();
+        let path = request_line.next().unwrap_or_default();
+        let content_length = head
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+            .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+            .unwrap_or(0);
+        if content_length > MAX_REQUEST_BYTES {
+            return Self::respond(&mut stream, &AdminResponse::error(413, "request too large"))
+                .await;
+        }
+
+        let mut body = request.split_off(head_end);
+        while body.len() < content_length {
+            let read = stream.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            body.extend_from_slice(&buffer[..read]);
+        }
+        body.truncate(content_length);
+
+        let response = self.handle(method, path, &body);
+        info!("Admin {} {} -> {}", method, path, response.status);
+        Self::respond(&mut stream, &response).await
+    }
+
+    async fn respond(stream: &mut TcpStream, response: &AdminResponse) -> std::io::Result<()> {
+        let message = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            response.status,
+            response.reason(),
+            response.body.len(),
+            response.body
+        );
+        stream.write_all(message.as_bytes()).await?;
+        stream.shutdown().await
+    }
+}
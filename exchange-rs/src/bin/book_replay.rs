@@ -0,0 +1,48 @@
+//! Offline tools for engine snapshot files.
+//!
+//! Usage: book_replay diff <before> <after> [--json]
+
+use std::process::ExitCode;
+
+use exchange_rs::matching_engine::EngineSnapshot;
+
+const USAGE: &str = "usage: book_replay diff <before> <after> [--json]";
+
+fn load_snapshot(path: &str) -> Result<EngineSnapshot, String> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|error| format!("cannot read {}: {}", path, error))?;
+    serde_json::from_str(&json).map_err(|error| format!("cannot parse {}: {}", path, error))
+}
+
+fn diff(args: &[String]) -> Result<(), String> {
+    let json = args.iter().any(|arg| arg == "--json");
+    let paths: Vec<&String> = args.iter().filter(|arg| *arg != "--json").collect();
+    let [before, after] = paths.as_slice() else {
+        return Err(USAGE.to_string());
+    };
+
+    let diff = load_snapshot(before)?.diff(&load_snapshot(after)?);
+    if json {
+        let rendered = serde_json::to_string_pretty(&diff).map_err(|error| error.to_string())?;
+        println!("{}", rendered);
+    } else {
+        print!("{}", diff);
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("diff") => diff(&args[1..]),
+        _ => Err(USAGE.to_string()),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
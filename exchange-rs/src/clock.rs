@@ -0,0 +1,120 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Source of wall-clock time in nanoseconds since the Unix epoch. The engine
+/// and the gateways share one instance so tests and replays can pin time.
+pub trait Clock: Send + Sync {
+    fn now_nanos(&self) -> i64;
+}
+
+pub type SharedClock = Arc<dyn Clock>;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_nanos(&self) -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as i64)
+            .unwrap_or(0)
+    }
+}
+
+/// Wall-clock time read once at start-up, advanced by a monotonic counter so
+/// readings never go backwards when the system clock is adjusted.
+#[derive(Debug, Clone, Copy)]
+pub struct MonotonicClock {
+    epoch_nanos: i64,
+    origin: Instant,
+}
+
+impl MonotonicClock {
+    pub fn new() -> Self {
+        Self {
+            epoch_nanos: SystemClock.now_nanos(),
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now_nanos(&self) -> i64 {
+        self.epoch_nanos + self.origin.elapsed().as_nanos() as i64
+    }
+}
+
+/// Hands out strictly increasing timestamps: each one is the given clock
+/// reading, or one nanosecond past the previous timestamp if that is later.
+#[derive(Debug, Default)]
+pub struct TimestampSequencer {
+    last: AtomicI64,
+}
+
+impl TimestampSequencer {
+    pub fn next(&self, now: i64) -> i64 {
+        let previous = self
+            .last
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| {
+                Some(now.max(last + 1))
+            })
+            .unwrap_or(now);
+        now.max(previous + 1)
+    }
+
+    pub fn last(&self) -> i64 {
+        self.last.load(Ordering::SeqCst)
+    }
+
+    /// Makes every later timestamp come after `timestamp`.
+    pub fn advance_to(&self, timestamp: i64) {
+        self.last.fetch_max(timestamp, Ordering::SeqCst);
+    }
+}
+
+impl Clone for TimestampSequencer {
+    fn clone(&self) -> Self {
+        Self {
+            last: AtomicI64::new(self.last()),
+        }
+    }
+}
+
+/// A clock that only moves when told to.
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    nanos: AtomicI64,
+}
+
+impl ManualClock {
+    pub fn new(nanos: i64) -> Self {
+        Self {
+            nanos: AtomicI64::new(nanos),
+        }
+    }
+
+    pub fn set(&self, nanos: i64) {
+        self.nanos.store(nanos, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, nanos: i64) {
+        self.nanos.fetch_add(nanos, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_nanos(&self) -> i64 {
+        self.nanos.load(Ordering::SeqCst)
+    }
+}
+
+pub fn system_clock() -> SharedClock {
+    Arc::new(MonotonicClock::new())
+}
@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::order::Order;
+use crate::price_utils::Price;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TriggerDirection {
+    AtOrAbove,
+    AtOrBelow,
+}
+
+/// Price condition on a reference symbol, evaluated against that symbol's
+/// last trade price.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContingentTrigger {
+    pub symbol: String,
+    pub direction: TriggerDirection,
+    pub price: Price,
+}
+
+impl ContingentTrigger {
+    pub fn new(symbol: &str, direction: TriggerDirection, price: Price) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            direction,
+            price,
+        }
+    }
+
+    pub fn is_triggered(&self, last_price: Price) -> bool {
+        match self.direction {
+            TriggerDirection::AtOrAbove => last_price >= self.price,
+            TriggerDirection::AtOrBelow => last_price <= self.price,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ContingentOrder {
+    pub id: u64,
+    pub user_id: u64,
+    pub trigger: ContingentTrigger,
+    pub order: Order,
+}
+
+/// Engine-level registry of pending contingent orders. Orders are kept in id
+/// order so that several contingents firing on the same trade are submitted
+/// in the order they were registered.
+#[derive(Default)]
+pub struct ContingentOrderBook {
+    orders: BTreeMap<u64, ContingentOrder>,
+}
+
+impl ContingentOrderBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, contingent: ContingentOrder) {
+        self.orders.insert(contingent.id, contingent);
+    }
+
+    pub fn remove(&mut self, id: u64) -> Option<ContingentOrder> {
+        self.orders.remove(&id)
+    }
+
+    pub fn get(&self, id: u64) -> Option<&ContingentOrder> {
+        self.orders.get(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ContingentOrder> {
+        self.orders.values()
+    }
+
+    pub fn take_triggered(&mut self, symbol: &str, last_price: Price) -> Vec<ContingentOrder> {
+        let triggered_ids: Vec<u64> = self
+            .orders
+            .values()
+            .filter(|c| c.trigger.symbol == symbol && c.trigger.is_triggered(last_price))
+            .map(|c| c.id)
+            .collect();
+
+        triggered_ids
+            .into_iter()
+            .filter_map(|id| self.orders.remove(&id))
+            .collect()
+    }
+}
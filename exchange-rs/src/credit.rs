@@ -0,0 +1,99 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::events::EngineEvent;
+use crate::order::Side;
+use crate::price_utils::{notional, Notional};
+use crate::projection::{OrderOwners, Projection};
+
+/// Cash side of a user's trading across every symbol, in scaled price
+/// units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct CreditExposure {
+    pub buy_notional: Notional,
+    pub sell_notional: Notional,
+    pub fees: u128,
+}
+
+impl CreditExposure {
+    /// What the user owes for its trades: purchases and fees less sales.
+    /// Negative when it is owed.
+    #[allow(clippy::unnecessary_cast)]
+    pub fn net(&self) -> i128 {
+        self.buy_notional as i128 + self.fees as i128 - self.sell_notional as i128
+    }
+}
+
+/// Credit exposure by user, built from trades and busts alone. Keep it
+/// current with `EventSourced<CreditLedger>`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CreditLedger {
+    exposures: BTreeMap<u64, CreditExposure>,
+    owners: OrderOwners,
+}
+
+impl CreditLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, user_id: u64) -> Option<&CreditExposure> {
+        self.exposures.get(&user_id)
+    }
+
+    fn value(&self, user_id: u64) -> CreditExposure {
+        self.exposures.get(&user_id).copied().unwrap_or_default()
+    }
+}
+
+impl Projection for CreditLedger {
+    type Key = u64;
+
+    const NAME: &'static str = "credit";
+
+    fn apply(&mut self, event: &EngineEvent) -> Vec<Self::Key> {
+        let (trade, busted) = match event {
+            EngineEvent::Trade { trade, .. } => (trade, false),
+            EngineEvent::TradeBusted(bust) => (&bust.trade, true),
+            _ => {
+                self.owners.observe(event);
+                return Vec::new();
+            }
+        };
+
+        let notional = notional(trade.price, trade.quantity);
+        let mut changed = Vec::new();
+        for (user_id, side, fee) in self.owners.parties(trade) {
+            let exposure = self.exposures.entry(user_id).or_default();
+            let traded = match side {
+                Side::Buy => &mut exposure.buy_notional,
+                Side::Sell => &mut exposure.sell_notional,
+            };
+            if busted {
+                *traded = traded.saturating_sub(notional);
+                exposure.fees = exposure.fees.saturating_sub(fee as u128);
+            } else {
+                *traded += notional;
+                exposure.fees += fee as u128;
+            }
+            if !changed.contains(&user_id) {
+                changed.push(user_id);
+            }
+        }
+        changed
+    }
+
+    fn differences(&self, other: &Self) -> Vec<Self::Key> {
+        let users: BTreeSet<u64> = self
+            .exposures
+            .keys()
+            .chain(other.exposures.keys())
+            .copied()
+            .collect();
+        users
+            .into_iter()
+            .filter(|&user_id| self.value(user_id) != other.value(user_id))
+            .collect()
+    }
+}
@@ -0,0 +1,283 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::events::EngineEvent;
+use crate::journal::JournalEntry;
+use crate::matching_engine::{MatchingEngine, Trade};
+use crate::price_utils::Price;
+
+#[derive(Debug, Clone)]
+pub struct AuditConfig {
+    /// Symbols to cross-check. `None` audits every symbol; naming a few
+    /// keeps the shadow engine's overhead bounded.
+    pub symbols: Option<BTreeSet<String>>,
+    /// Book digests are compared after every command whose sequence is a
+    /// multiple of this.
+    pub digest_interval: u64,
+    /// Commands the shadow may fall behind before `tee` applies the oldest
+    /// ones itself.
+    pub max_lag: usize,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            symbols: None,
+            digest_interval: 100,
+            max_lag: 10_000,
+        }
+    }
+}
+
+/// What two engines must agree on about a trade. Trade ids and timestamps
+/// are assigned per engine and left out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TradeFingerprint {
+    pub buy_order_id: u64,
+    pub sell_order_id: u64,
+    pub price: Price,
+    pub quantity: u32,
+}
+
+impl From<&Trade> for TradeFingerprint {
+    fn from(trade: &Trade) -> Self {
+        Self {
+            buy_order_id: trade.buy_order_id,
+            sell_order_id: trade.sell_order_id,
+            price: trade.price,
+            quantity: trade.quantity,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Divergence {
+    /// The command produced different trades.
+    Trades {
+        live: Vec<TradeFingerprint>,
+        shadow: Vec<TradeFingerprint>,
+    },
+    /// The books differ at a digest checkpoint. `None` where the engine has
+    /// no book for the symbol.
+    Digest {
+        live: Option<u64>,
+        shadow: Option<u64>,
+    },
+}
+
+/// High-severity alert raised once per symbol when the live engine and the
+/// reference engine first disagree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DivergenceAlert {
+    pub symbol: String,
+    /// Sequence of the first command whose results differ. A difference that
+    /// only shows in the book is caught at the next digest checkpoint, and
+    /// reported with that checkpoint's sequence.
+    pub sequence: u64,
+    pub divergence: Divergence,
+}
+
+/// Runs against the shadow engine before each command is applied to it,
+/// with the command's sequence. Lets tests make the shadow diverge.
+pub type ShadowHook = Box<dyn FnMut(u64, &mut MatchingEngine) + Send>;
+
+struct TeedCommand {
+    entry: JournalEntry,
+    live_trades: Vec<TradeFingerprint>,
+    /// Live book digests of audited symbols, taken at checkpoints only.
+    live_digests: Option<BTreeMap<String, Option<u64>>>,
+}
+
+/// Determinism audit for staging: every command the live sharded engine
+/// processes is teed, with its results, to a single-threaded reference
+/// engine that may lag behind. Per-symbol trade streams are compared
+/// command by command and book digests at every checkpoint.
+pub struct DeterminismAudit {
+    config: AuditConfig,
+    shadow: MatchingEngine,
+    shadow_events: Receiver<EngineEvent>,
+    shadow_hook: Option<ShadowHook>,
+    pending: VecDeque<TeedCommand>,
+    symbols: BTreeSet<String>,
+    divergences: BTreeMap<String, DivergenceAlert>,
+    subscribers: Vec<Sender<DivergenceAlert>>,
+}
+
+impl DeterminismAudit {
+    /// `shadow` must be configured like the live engine and hold the same
+    /// state, usually by starting both empty.
+    pub fn new(config: AuditConfig, mut shadow: MatchingEngine) -> Self {
+        let shadow_events = shadow.subscribe_all();
+        Self {
+            config,
+            shadow,
+            shadow_events,
+            shadow_hook: None,
+            pending: VecDeque::new(),
+            symbols: BTreeSet::new(),
+            divergences: BTreeMap::new(),
+            subscribers: Vec::new(),
+        }
+    }
+
+    pub fn shadow(&self) -> &MatchingEngine {
+        &self.shadow
+    }
+
+    pub fn set_shadow_hook(&mut self, hook: impl FnMut(u64, &mut MatchingEngine) + Send + 'static) {
+        self.shadow_hook = Some(Box::new(hook));
+    }
+
+    pub fn subscribe(&mut self) -> Receiver<DivergenceAlert> {
+        let (sender, receiver) = unbounded();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    pub fn is_audited(&self, symbol: &str) -> bool {
+        self.config
+            .symbols
+            .as_ref()
+            .is_none_or(|symbols| symbols.contains(symbol))
+    }
+
+    /// Commands teed but not yet applied to the shadow.
+    pub fn lag(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// First divergence of each symbol that has diverged, by symbol.
+    pub fn divergences(&self) -> impl Iterator<Item = &DivergenceAlert> {
+        self.divergences.values()
+    }
+
+    /// Queues a command the live engine processed, with the trades it
+    /// produced. `entry.order_id` must be the id the live engine assigned.
+    /// At checkpoints `live_digest` is asked for the live digest of each
+    /// audited symbol. Commands on symbols outside the sample are dropped.
+    pub fn tee(
+        &mut self,
+        entry: JournalEntry,
+        live_trades: &[Trade],
+        live_digest: impl Fn(&str) -> Option<u64>,
+    ) {
+        if let Some(symbol) = entry.command.symbol() {
+            if !self.is_audited(symbol) {
+                return;
+            }
+            self.symbols.insert(symbol.to_string());
+        }
+
+        let checkpoint = entry
+            .sequence
+            .is_multiple_of(self.config.digest_interval.max(1));
+        let live_digests = checkpoint.then(|| {
+            self.symbols
+                .iter()
+                .map(|symbol| (symbol.clone(), live_digest(symbol)))
+                .collect()
+        });
+        self.pending.push_back(TeedCommand {
+            entry,
+            live_trades: live_trades.iter().map(TradeFingerprint::from).collect(),
+            live_digests,
+        });
+
+        let excess = self.pending.len().saturating_sub(self.config.max_lag);
+        self.catch_up(excess);
+    }
+
+    /// Applies up to `max_commands` queued commands to the shadow and
+    /// compares their results. Returns how many were applied.
+    pub fn catch_up(&mut self, max_commands: usize) -> usize {
+        let mut applied = 0;
+        while applied < max_commands {
+            let Some(teed) = self.pending.pop_front() else {
+                break;
+            };
+            self.apply(teed);
+            applied += 1;
+        }
+        applied
+    }
+
+    fn apply(&mut self, teed: TeedCommand) {
+        let TeedCommand {
+            entry,
+            live_trades,
+            live_digests,
+        } = teed;
+
+        if let Some(hook) = &mut self.shadow_hook {
+            hook(entry.sequence, &mut self.shadow);
+        }
+        if let Some(order_id) = entry.order_id {
+            self.shadow.set_next_order_id(order_id);
+        }
+        self.shadow_events.try_iter().for_each(drop);
+        entry.command.apply(&mut self.shadow);
+
+        let mut shadow_trades: BTreeMap<String, Vec<TradeFingerprint>> = BTreeMap::new();
+        for event in self.shadow_events.try_iter() {
+            if let EngineEvent::Trade { symbol, trade } = event {
+                shadow_trades
+                    .entry(symbol)
+                    .or_default()
+                    .push(TradeFingerprint::from(&trade));
+            }
+        }
+
+        let mut live_trades_by_symbol = BTreeMap::new();
+        if let Some(symbol) = entry.command.symbol() {
+            live_trades_by_symbol.insert(symbol.to_string(), live_trades);
+        }
+        let symbols: BTreeSet<String> = live_trades_by_symbol
+            .keys()
+            .chain(shadow_trades.keys())
+            .cloned()
+            .collect();
+        for symbol in symbols {
+            let live = live_trades_by_symbol.remove(&symbol).unwrap_or_default();
+            let shadow = shadow_trades.remove(&symbol).unwrap_or_default();
+            if live != shadow {
+                self.diverged(symbol, entry.sequence, Divergence::Trades { live, shadow });
+            }
+        }
+
+        for (symbol, live) in live_digests.unwrap_or_default() {
+            let shadow = self
+                .shadow
+                .order_books
+                .get(&symbol)
+                .map(|book| book.digest());
+            if live != shadow {
+                self.diverged(symbol, entry.sequence, Divergence::Digest { live, shadow });
+            }
+        }
+    }
+
+    /// Records the first divergence of `symbol` and alerts subscribers.
+    /// Later differences on a diverged symbol follow from the first one and
+    /// are not reported again.
+    fn diverged(&mut self, symbol: String, sequence: u64, divergence: Divergence) {
+        if !self.is_audited(&symbol) || self.divergences.contains_key(&symbol) {
+            return;
+        }
+
+        error!(
+            "Determinism audit: {} diverged from the reference engine at command {}: {:?}",
+            symbol, sequence, divergence
+        );
+        let alert = DivergenceAlert {
+            symbol: symbol.clone(),
+            sequence,
+            divergence,
+        };
+        self.subscribers
+            .retain(|subscriber| subscriber.send(alert.clone()).is_ok());
+        self.divergences.insert(symbol, alert);
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use parking_lot::Mutex;
+
+use crate::clock::TimestampSequencer;
+use crate::matching_engine::{Trade, TradeBust};
+use crate::order::{Order, OrderStatus};
+use crate::orderbook::{BookFeatures, IndicativeUncross};
+
+pub type TradeCallback = Arc<dyn Fn(&Trade) + Send + Sync>;
+
+/// Called with the order after the transition and its previous status.
+pub type OrderStatusCallback = Arc<dyn Fn(&Order, OrderStatus) + Send + Sync>;
+
+/// Synchronous hooks run on the matching thread. Each is a single branch
+/// when unset.
+#[derive(Clone, Default)]
+pub struct EngineCallbacks {
+    on_trade: Option<TradeCallback>,
+    on_order_status: Option<OrderStatusCallback>,
+    /// Clock reading taken when the current engine command started. Every
+    /// trade and status change in the command is stamped with it so a
+    /// replay reproduces the same timestamps.
+    now: i64,
+    trade_timestamps: TimestampSequencer,
+}
+
+impl EngineCallbacks {
+    pub fn set_now(&mut self, now: i64) {
+        self.now = now;
+    }
+
+    #[inline]
+    pub fn now(&self) -> i64 {
+        self.now
+    }
+
+    /// Timestamp for the next trade: the command time, bumped past the
+    /// previous trade so trades in one batch stay strictly ordered.
+    #[inline]
+    pub fn next_trade_timestamp(&self) -> i64 {
+        self.trade_timestamps.next(self.now)
+    }
+
+    pub fn last_trade_timestamp(&self) -> i64 {
+        self.trade_timestamps.last()
+    }
+
+    /// Continues trade timestamps after `timestamp`, as on a restored engine.
+    pub fn resume_trade_timestamps(&self, timestamp: i64) {
+        self.trade_timestamps.advance_to(timestamp);
+    }
+
+    pub fn set_on_trade(&mut self, callback: TradeCallback) {
+        self.on_trade = Some(callback);
+    }
+
+    pub fn set_on_order_status(&mut self, callback: OrderStatusCallback) {
+        self.on_order_status = Some(callback);
+    }
+
+    #[inline]
+    pub fn trade(&self, trade: &Trade) {
+        if let Some(callback) = &self.on_trade {
+            callback(trade);
+        }
+    }
+
+    /// Moves `order` to `status`, notifying the status hook if it changed.
+    #[inline]
+    pub fn set_status(&self, order: &mut Order, status: OrderStatus) {
+        let previous = order.status;
+        order.status = status;
+
+        if previous != status {
+            order.last_update = self.now;
+            if let Some(callback) = &self.on_order_status {
+                callback(order, previous);
+            }
+        }
+    }
+
+    /// Notifies the status hook of a transition already applied elsewhere.
+    #[inline]
+    pub fn status_changed(&self, order: &Order, previous: OrderStatus) {
+        if let Some(callback) = &self.on_order_status {
+            if previous != order.status {
+                callback(order, previous);
+            }
+        }
+    }
+}
+
+/// Engine output published to subscribers in execution order. Orders are
+/// copies of their state at the time of the event.
+///
+/// The events of one execution, such as a `place_order` call, follow a
+/// fixed order consumers may rely on:
+///
+/// 1. `OrderAccepted` for the incoming order, before anything else.
+/// 2. `Trade`s in execution order, including those of stops the
+///    execution set off.
+/// 3. Each `StopTriggered` after the trade that triggered it and before
+///    the stop's own trades. A stop triggered on entry comes right after
+///    its `OrderAccepted`.
+/// 4. Status events of resting and stop orders, after every trade.
+/// 5. The incoming order's terminal status event, last.
+///
+/// Contingent orders fired by the execution follow as executions of
+/// their own.
+///
+/// During an auction call an `IndicativeUncross` follows the execution
+/// whenever it moved the indicative uncross, after the incoming order's
+/// status event.
+#[derive(Debug, Clone)]
+pub enum EngineEvent {
+    OrderAccepted(Order),
+    Trade { symbol: String, trade: Trade },
+    StopTriggered(Order),
+    OrderCancelled(Order),
+    OrderExpired(Order),
+    TradeBusted(TradeBust),
+    BookFeatures { symbol: String, features: BookFeatures },
+    /// Indicative uncross of a symbol in its auction call. `None` once no
+    /// uncross is possible or the call has ended.
+    IndicativeUncross {
+        symbol: String,
+        uncross: Option<IndicativeUncross>,
+    },
+}
+
+impl EngineEvent {
+    pub fn symbol(&self) -> &str {
+        match self {
+            EngineEvent::Trade { symbol, .. }
+            | EngineEvent::BookFeatures { symbol, .. }
+            | EngineEvent::IndicativeUncross { symbol, .. } => symbol,
+            EngineEvent::TradeBusted(bust) => &bust.symbol,
+            EngineEvent::OrderAccepted(order)
+            | EngineEvent::StopTriggered(order)
+            | EngineEvent::OrderCancelled(order)
+            | EngineEvent::OrderExpired(order) => &order.symbol,
+        }
+    }
+}
+
+/// An engine event numbered in publication order. Numbers start at 1 and
+/// leave no gaps, so a consumer can persist the last one it applied and
+/// resume right after it. `BookFeatures` is market data sampled outside
+/// any command and is not numbered.
+#[derive(Debug, Clone)]
+pub struct SequencedEvent {
+    pub sequence: u64,
+    pub event: EngineEvent,
+}
+
+/// The most recent sequenced events, shared with consumers so they can
+/// rebuild their state without calling into the engine.
+#[derive(Clone)]
+pub struct EventRing {
+    events: Arc<Mutex<VecDeque<SequencedEvent>>>,
+    capacity: usize,
+}
+
+impl EventRing {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity: capacity.max(1),
+        }
+    }
+
+    fn push(&self, event: SequencedEvent) {
+        let mut events = self.events.lock();
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    /// Sequence of the oldest event still held.
+    pub fn first_sequence(&self) -> Option<u64> {
+        self.events.lock().front().map(|event| event.sequence)
+    }
+
+    /// Every event the ring holds, oldest first.
+    pub fn events(&self) -> Vec<SequencedEvent> {
+        self.events.lock().iter().cloned().collect()
+    }
+
+    /// Every event numbered above `sequence`, or `None` if the ring has
+    /// already dropped some of them.
+    pub fn events_after(&self, sequence: u64) -> Option<Vec<SequencedEvent>> {
+        let events = self.events.lock();
+        if events.front().is_some_and(|first| first.sequence > sequence + 1) {
+            return None;
+        }
+        let skip = events.partition_point(|event| event.sequence <= sequence);
+        Some(events.range(skip..).cloned().collect())
+    }
+}
+
+struct Subscriber {
+    symbol: Option<String>,
+    sender: Sender<EngineEvent>,
+}
+
+/// Fan-out of engine events over unbounded channels, so consumers never
+/// hold the engine lock while reading. Subscribers whose receiver has been
+/// dropped are removed on the next publish.
+///
+/// Events are numbered as they are published. The engine only builds
+/// events while something listens, so numbers match across replays only
+/// when a sequenced subscriber or ring was in place from the first
+/// command.
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Vec<Subscriber>,
+    sequenced: Vec<Sender<SequencedEvent>>,
+    ring: Option<EventRing>,
+    sequence: u64,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&mut self, symbol: Option<&str>) -> Receiver<EngineEvent> {
+        let (sender, receiver) = unbounded();
+        self.subscribers.push(Subscriber {
+            symbol: symbol.map(str::to_string),
+            sender,
+        });
+        receiver
+    }
+
+    /// Receives every event from now on, with its sequence number.
+    pub fn subscribe_sequenced(&mut self) -> Receiver<SequencedEvent> {
+        let (sender, receiver) = unbounded();
+        self.sequenced.push(sender);
+        receiver
+    }
+
+    /// Keeps the last `capacity` sequenced events in a ring, returning a
+    /// handle to it. Enabling it again returns the existing ring.
+    pub fn enable_ring(&mut self, capacity: usize) -> EventRing {
+        self.ring.get_or_insert_with(|| EventRing::new(capacity)).clone()
+    }
+
+    /// Sequence of the last numbered event published.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    pub fn has_subscribers(&self) -> bool {
+        !self.subscribers.is_empty() || !self.sequenced.is_empty() || self.ring.is_some()
+    }
+
+    pub fn publish(&mut self, event: EngineEvent) {
+        if !matches!(event, EngineEvent::BookFeatures { .. }) {
+            self.sequence += 1;
+            if self.ring.is_some() || !self.sequenced.is_empty() {
+                let sequenced = SequencedEvent {
+                    sequence: self.sequence,
+                    event: event.clone(),
+                };
+                self.sequenced
+                    .retain(|sender| sender.send(sequenced.clone()).is_ok());
+                if let Some(ring) = &self.ring {
+                    ring.push(sequenced);
+                }
+            }
+        }
+
+        self.subscribers.retain(|subscriber| {
+            let wanted = subscriber
+                .symbol
+                .as_deref()
+                .is_none_or(|symbol| symbol == event.symbol());
+
+            !wanted || subscriber.sender.send(event.clone()).is_ok()
+        });
+    }
+}
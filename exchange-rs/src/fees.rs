@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+use crate::price_utils::{price_magnitude, Price, QUANTITY_SCALE_FACTOR};
+
+const BPS_DENOMINATOR: u128 = 10_000;
+
+/// Per-symbol trading fees in basis points of notional. Fees are in the same
+/// scaled units as prices (`PRICE_SCALE_FACTOR` per unit of currency).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct FeeSchedule {
+    pub maker_bps: u32,
+    pub taker_bps: u32,
+    /// Floor applied to every non-zero fee.
+    pub min_fee: Option<u64>,
+}
+
+impl FeeSchedule {
+    pub fn maker_fee(&self, price: Price, quantity: u32) -> u64 {
+        self.fee(self.maker_bps, price, quantity)
+    }
+
+    pub fn taker_fee(&self, price: Price, quantity: u32) -> u64 {
+        self.fee(self.taker_bps, price, quantity)
+    }
+
+    fn fee(&self, bps: u32, price: Price, quantity: u32) -> u64 {
+        let fee = compute_fee(price, quantity, bps);
+        match self.min_fee {
+            Some(min_fee) if fee > 0 => fee.max(min_fee),
+            _ => fee,
+        }
+    }
+}
+
+/// Fee on `quantity` (scaled by `QUANTITY_SCALE_FACTOR`) at `price`, rounded
+/// up to the next price unit. Computed in 128-bit integers so the product of
+/// price, quantity and rate cannot overflow. A price below zero is charged
+/// on its magnitude.
+pub fn compute_fee(price: Price, quantity: u32, bps: u32) -> u64 {
+    let numerator = price_magnitude(price) as u128 * quantity as u128 * bps as u128;
+    let denominator = QUANTITY_SCALE_FACTOR as u128 * BPS_DENOMINATOR;
+
+    u64::try_from(numerator.div_ceil(denominator)).unwrap_or(u64::MAX)
+}
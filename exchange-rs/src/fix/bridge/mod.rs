@@ -5,33 +5,39 @@ pub use order_converter::FixOrderConverter;
 pub use response_converter::FixResponseConverter;
 
 use crate::fix::error::{FixError, BusinessError};
-use crate::fix::messages::{NewOrderSingle, FixMessage};
+use crate::fix::messages::{ExecutionReport, FixMessage, NewOrderSingle, OrderCancelRequest, OrderStatusRequest};
 use crate::fix::validation::BusinessValidator;
 use crate::order::{Order, OrderType, Side, TimeInForce};
-use crate::matching_engine::TradeExecutionResult;
+use crate::matching_engine::{MatchingEngine, TradeExecutionResult};
+use crate::price_utils::Price;
+use std::collections::HashMap;
 
 pub struct FixOrderBridge {
     converter: FixOrderConverter,
     response_converter: FixResponseConverter,
     validator: BusinessValidator,
+    /// Engine order id of each ClOrdID an order was placed with, for status
+    /// requests that carry no OrderID.
+    order_ids: HashMap<String, u64>,
 }
 
 impl FixOrderBridge {
     pub fn new() -> Self {
+        Self::with_validator(BusinessValidator::new())
+    }
+
+    pub fn with_validator(validator: BusinessValidator) -> Self {
         Self {
             converter: FixOrderConverter::new(),
             response_converter: FixResponseConverter::new(),
-            validator: BusinessValidator::new(),
+            validator,
+            order_ids: HashMap::new(),
         }
     }
 
     pub fn process_fix_message(&mut self, message: FixMessage) -> Result<Option<Order>, FixError> {
         match message {
-            FixMessage::NewOrderSingle(order) => {
-                self.validator.validate_new_order(&order)?;
-                let internal_order = self.converter.convert_new_order_single(order)?;
-                Ok(Some(internal_order))
-            }
+            FixMessage::NewOrderSingle(order) => self.process_new_order(order, None).map(Some),
             FixMessage::OrderCancelRequest(cancel) => {
                 self.validator.validate_cancel_request(&cancel.orig_cl_ord_id, &cancel.cl_ord_id)?;
                 Ok(None)
@@ -40,14 +46,79 @@ impl FixOrderBridge {
         }
     }
 
+    /// Validates `order` and converts it for the engine, holding its limit
+    /// price to the symbol's band around `last_trade_price`.
+    pub fn process_new_order(&mut self, order: NewOrderSingle, last_trade_price: Option<Price>) -> Result<Order, FixError> {
+        self.validator.validate_new_order(&order, last_trade_price)?;
+        self.converter.convert_new_order_single(order)
+    }
+
     pub fn convert_trade_result(&mut self, result: &TradeExecutionResult, cl_ord_id: &str) -> Result<FixMessage, FixError> {
-        self.response_converter.convert_trade_result(result, cl_ord_id)
+        let message = self.response_converter.convert_trade_result(result, cl_ord_id)?;
+        if let FixMessage::ExecutionReport(report) = &message {
+            self.remember_order_id(cl_ord_id, &report.order_id);
+        }
+        Ok(message)
+    }
+
+    pub fn build_execution_reports(&mut self, result: &TradeExecutionResult, cl_ord_id: &str) -> Vec<ExecutionReport> {
+        let reports = self.response_converter.build_execution_reports(result, cl_ord_id);
+        if let Some(report) = reports.first() {
+            self.remember_order_id(cl_ord_id, &report.order_id);
+        }
+        reports
+    }
+
+    /// Answers an OrderStatusRequest from the orders `engine` remembers.
+    /// The order is found by OrderID when the request carries one and by
+    /// the ClOrdID it was placed with otherwise.
+    pub fn order_status(&mut self, request: &OrderStatusRequest, engine: &MatchingEngine) -> ExecutionReport {
+        let order_id = match &request.order_id {
+            Some(order_id) => order_id.parse().ok(),
+            None => self.order_ids.get(&request.cl_ord_id).copied(),
+        };
+        match order_id.and_then(|order_id| engine.get_order(&request.symbol, order_id)) {
+            Some(order) => self.response_converter.order_status_report(&order, &request.cl_ord_id),
+            None => self.response_converter.unknown_order_report(request),
+        }
+    }
+
+    /// Cancels the live order the sender placed with the request's
+    /// OrigClOrdID, found through the engine's client order id index.
+    pub fn cancel_order(&mut self, request: &OrderCancelRequest, engine: &mut MatchingEngine) -> ExecutionReport {
+        let user_id = self.converter.user_id(&request.header.sender_comp_id, request.account.as_deref());
+        match engine.cancel_order_by_client_id(&request.symbol, user_id, &request.orig_cl_ord_id) {
+            Some(order) => {
+                self.validator.complete_order(&request.orig_cl_ord_id);
+                self.response_converter.cancel_report(&order.read(), request)
+            }
+            None => self.response_converter.unknown_cancel_report(request),
+        }
+    }
+
+    /// Rejections carry OrderID 0 and name no order.
+    fn remember_order_id(&mut self, cl_ord_id: &str, order_id: &str) {
+        if let Ok(order_id @ 1..) = order_id.parse::<u64>() {
+            self.order_ids.insert(cl_ord_id.to_string(), order_id);
+        }
+    }
+
+    pub fn convert_business_reject(&mut self, cl_ord_id: &str, symbol: &str, error: &BusinessError) -> Result<FixMessage, FixError> {
+        self.response_converter.convert_business_reject(cl_ord_id, symbol, error)
     }
 
     pub fn add_symbol(&mut self, symbol: String) {
         self.validator.add_symbol(symbol);
     }
 
+    pub fn validator(&self) -> &BusinessValidator {
+        &self.validator
+    }
+
+    pub fn validator_mut(&mut self) -> &mut BusinessValidator {
+        &mut self.validator
+    }
+
     pub fn complete_order(&mut self, cl_ord_id: &str) {
         self.validator.complete_order(cl_ord_id);
     }
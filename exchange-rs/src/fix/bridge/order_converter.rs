@@ -1,8 +1,19 @@
 use crate::fix::error::{FixError, BusinessError};
+use crate::fix::mapping;
 use crate::fix::messages::NewOrderSingle;
-use crate::order::{Order, OrderType, Side, TimeInForce};
+use crate::order::{Order, OrderType};
+use crate::price_utils::{price_to_scaled, Price, PriceError, SIGNED_PRICES};
 
-const PRICE_SCALE_FACTOR: u64 = 1_000_000;
+/// The largest OrderQty (38) an order can carry.
+pub const MAX_ORDER_QTY: u32 = u32::MAX;
+
+/// The largest Price (44) or StopPx (99) an order can carry: the whole
+/// price units that fit a scaled `Price`. With signed prices the same
+/// magnitude is the floor.
+#[cfg(not(feature = "signed-prices"))]
+pub const MAX_ORDER_PRICE: f64 = 18_446_744_073_709.0;
+#[cfg(feature = "signed-prices")]
+pub const MAX_ORDER_PRICE: f64 = 9_223_372_036_854.0;
 
 pub struct FixOrderConverter;
 
@@ -12,101 +23,103 @@ impl FixOrderConverter {
     }
 
     pub fn convert_new_order_single(&self, fix_order: NewOrderSingle) -> Result<Order, FixError> {
-        let side = self.convert_side(fix_order.side)?;
-        let order_type = self.convert_order_type(fix_order.ord_type)?;
-        let time_in_force = self.convert_time_in_force(fix_order.time_in_force);
+        let side = mapping::fix_to_side(fix_order.side)?;
+        let order_type = mapping::fix_to_order_type(fix_order.ord_type)?;
+        let time_in_force = mapping::fix_to_time_in_force_or_default(fix_order.time_in_force)?;
         
-        let price = self.convert_price(fix_order.price, fix_order.ord_type)?;
-        let stop_price = self.convert_stop_price(fix_order.stop_px, fix_order.ord_type)?;
+        let quantity = Self::quantity_from_fix(fix_order.order_qty)?;
+        let price = self.convert_price(fix_order.price, order_type)?;
+        let stop_price = self.convert_stop_price(fix_order.stop_px, order_type)?;
         
-        let user_id = self.extract_user_id(&fix_order.header.sender_comp_id);
+        let user_id = self.user_id(&fix_order.header.sender_comp_id, fix_order.account.as_deref());
         
         let mut order = Order::new(
             fix_order.symbol,
             side,
             order_type,
             price,
-            fix_order.order_qty,
+            quantity,
             user_id,
         );
 
         order.time_in_force = time_in_force;
         order.stop_price = stop_price;
-        
-        if let Some(account) = fix_order.account {
-            if !account.is_empty() {
-                order.user_id = self.extract_user_id(&account);
-            }
-        }
+        order.client_order_id = Some(fix_order.cl_ord_id);
 
         Ok(order)
     }
 
-    fn convert_side(&self, fix_side: char) -> Result<Side, BusinessError> {
-        match fix_side {
-            '1' => Ok(Side::Buy),
-            '2' => Ok(Side::Sell),
-            _ => Err(BusinessError::InvalidSymbol {
-                symbol: format!("Invalid side: {}", fix_side),
-            }),
+    /// The engine user a message is sent for: the digits of its Account
+    /// when it carries one, else those of its SenderCompID.
+    pub fn user_id(&self, sender_comp_id: &str, account: Option<&str>) -> u64 {
+        match account {
+            Some(account) if !account.is_empty() => self.extract_user_id(account),
+            _ => self.extract_user_id(sender_comp_id),
         }
     }
 
-    fn convert_order_type(&self, fix_ord_type: char) -> Result<OrderType, BusinessError> {
-        match fix_ord_type {
-            '1' => Ok(OrderType::Market),
-            '2' => Ok(OrderType::Limit),
-            '3' => Ok(OrderType::StopMarket),
-            '4' => Ok(OrderType::StopLimit),
-            _ => Err(BusinessError::InvalidSymbol {
-                symbol: format!("Invalid order type: {}", fix_ord_type),
-            }),
-        }
+    /// OrderQty as an order quantity. Quantities that are not positive or
+    /// exceed `MAX_ORDER_QTY` are refused rather than wrapped.
+    pub fn quantity_from_fix(order_qty: i64) -> Result<u32, BusinessError> {
+        let reason = if order_qty <= 0 {
+            "quantity must be positive".to_string()
+        } else {
+            match u32::try_from(order_qty) {
+                Ok(quantity) => return Ok(quantity),
+                Err(_) => format!("quantity exceeds maximum supported {}", MAX_ORDER_QTY),
+            }
+        };
+        Err(BusinessError::QuantityOutOfRange { quantity: order_qty, reason })
+    }
+
+    /// A FIX price as a scaled price. Prices beyond `MAX_ORDER_PRICE` either
+    /// way are refused, as are negative ones unless prices are signed.
+    pub fn price_from_fix(price: f64) -> Result<Price, BusinessError> {
+        let scaled = if price.abs() > MAX_ORDER_PRICE {
+            Err(PriceError::OutOfRange(price))
+        } else {
+            price_to_scaled(price)
+        };
+        scaled.map_err(|error| {
+            let reason = match error {
+                PriceError::NotFinite(_) => "price must be finite".to_string(),
+                PriceError::Negative(_) => "price must not be negative".to_string(),
+                PriceError::OutOfRange(_) => format!("price exceeds maximum supported {}", MAX_ORDER_PRICE),
+            };
+            BusinessError::PriceOutOfRange { price, reason }
+        })
     }
 
-    fn convert_time_in_force(&self, fix_tif: Option<char>) -> TimeInForce {
-        match fix_tif {
-            Some('0') => TimeInForce::Day,
-            Some('1') => TimeInForce::GTC,
-            Some('3') => TimeInForce::IOC,
-            Some('4') => TimeInForce::FOK,
-            _ => TimeInForce::GTC, 
+    /// As `price_from_fix`, also refusing prices that are or round to zero
+    /// unless prices are signed, where zero is a price like any other.
+    fn limit_price_from_fix(price: f64) -> Result<Price, BusinessError> {
+        match Self::price_from_fix(price)? {
+            0 if !SIGNED_PRICES => Err(BusinessError::InvalidPrice { price: 0 }),
+            scaled => Ok(scaled),
         }
     }
 
-    fn convert_price(&self, fix_price: Option<f64>, ord_type: char) -> Result<u64, BusinessError> {
-        match ord_type {
-            '2' | '4' => {
+    fn convert_price(&self, fix_price: Option<f64>, order_type: OrderType) -> Result<Price, BusinessError> {
+        match order_type {
+            OrderType::Limit | OrderType::StopLimit | OrderType::Iceberg => {
                 match fix_price {
-                    Some(price) => {
-                        if price <= 0.0 || !price.is_finite() {
-                            Err(BusinessError::InvalidPrice { price: 0 })
-                        } else {
-                            Ok((price * PRICE_SCALE_FACTOR as f64) as u64)
-                        }
-                    }
+                    Some(price) => Self::limit_price_from_fix(price),
                     None => Err(BusinessError::InvalidPrice { price: 0 }),
                 }
             }
-            _ => Ok(0),
+            OrderType::Market | OrderType::StopMarket => Ok(0),
         }
     }
 
-    fn convert_stop_price(&self, fix_stop_px: Option<f64>, ord_type: char) -> Result<Option<u64>, BusinessError> {
-        match ord_type {
-            '3' | '4' => {
+    fn convert_stop_price(&self, fix_stop_px: Option<f64>, order_type: OrderType) -> Result<Option<Price>, BusinessError> {
+        match order_type {
+            OrderType::StopMarket | OrderType::StopLimit => {
                 match fix_stop_px {
-                    Some(price) => {
-                        if price <= 0.0 || !price.is_finite() {
-                            Err(BusinessError::InvalidPrice { price: 0 })
-                        } else {
-                            Ok(Some((price * PRICE_SCALE_FACTOR as f64) as u64))
-                        }
-                    }
+                    Some(price) => Self::limit_price_from_fix(price).map(Some),
                     None => Err(BusinessError::InvalidPrice { price: 0 }),
                 }
             }
-            _ => Ok(None),
+            OrderType::Market | OrderType::Limit | OrderType::Iceberg => Ok(None),
         }
     }
 
@@ -130,6 +143,7 @@ impl Default for FixOrderConverter {
 mod tests {
     use super::*;
     use crate::fix::messages::{StandardHeader, Trailer, MessageType};
+    use crate::order::{Side, TimeInForce};
 
     #[test]
     fn test_convert_limit_buy_order() {
@@ -145,6 +159,7 @@ mod tests {
             sending_time: "20240101-12:00:00".to_string(),
             poss_dup_flag: None,
             poss_resend: None,
+            orig_sending_time: None,
             secure_data_len: None,
             secure_data: None,
         };
@@ -193,6 +208,7 @@ mod tests {
             sending_time: "20240101-12:00:00".to_string(),
             poss_dup_flag: None,
             poss_resend: None,
+            orig_sending_time: None,
             secure_data_len: None,
             secure_data: None,
         };
@@ -1,10 +1,12 @@
-use crate::fix::error::FixError;
-use crate::fix::messages::{
-    FixMessage, ExecutionReport, StandardHeader, Trailer, MessageType,
-    execution_report::{ExecType, OrdStatus},
-};
-use crate::matching_engine::TradeExecutionResult;
-use crate::order::{OrderStatus, OrderType, Side};
+use crate::fix::error::{BusinessError, FixError};
+use crate::fix::mapping;
+use crate::fix::messages::{FixMessage, ExecutionReport, OrderCancelRequest, OrderStatusRequest, StandardHeader, Trailer, MessageType};
+use crate::fix::messages::execution_report::{ExecType, OrdRejReason};
+use crate::matching_engine::{Trade, TradeExecutionResult};
+use crate::order::{Order, OrderStatus, OrderType, Side};
+use crate::price_utils::{notional, scaled_price_to_float, scaled_to_price, Notional, Price};
+use parking_lot::RwLock;
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 pub struct FixResponseConverter {
@@ -35,25 +37,20 @@ impl FixResponseConverter {
     fn create_trade_execution_report(&mut self, result: &TradeExecutionResult, cl_ord_id: &str) -> Result<FixMessage, FixError> {
         let trade = &result.trades[0];
         
-        let remaining_order = result.remaining_order.as_ref()
-            .or_else(|| result.filled_orders.first())
-            .ok_or_else(|| FixError::Parse(crate::fix::error::ParseError::InvalidFormat))?;
+        let remaining_order = Self::executed_order(result)
+            .ok_or(FixError::Parse(crate::fix::error::ParseError::InvalidFormat))?;
         
         let order = remaining_order.read();
         
-        let exec_type = if order.is_filled() { 
-            ExecType::Fill 
-        } else { 
-            ExecType::PartialFill 
-        };
-        
-        let ord_status = if order.is_filled() { 
-            OrdStatus::Filled 
-        } else { 
-            OrdStatus::PartiallyFilled 
+        let status = if order.is_filled() {
+            OrderStatus::Filled
+        } else {
+            OrderStatus::PartiallyFilled
         };
+        let exec_type = mapping::order_status_to_exec_type(status);
+        let ord_status = mapping::order_status_to_fix(status);
 
-        let header = self.create_standard_header(MessageType::ExecutionReport)?;
+        let header = self.create_standard_header(MessageType::ExecutionReport);
         let trailer = Trailer { checksum: 0 };
 
         let execution_report = ExecutionReport {
@@ -66,22 +63,24 @@ impl FixResponseConverter {
             ord_status: ord_status.to_char(),
             account: None,
             symbol: order.symbol.clone(),
-            side: self.convert_side_to_char(order.side),
+            side: mapping::side_to_fix(order.side),
             order_qty: order.quantity,
-            ord_type: self.convert_order_type_to_char(order.order_type),
+            ord_type: mapping::order_type_to_fix(order.order_type),
             price: if matches!(order.order_type, OrderType::Limit | OrderType::StopLimit) {
-                Some(order.price as f64 / 10000.0)
+                Some(scaled_to_price(order.price))
             } else {
                 None
             },
-            stop_px: order.stop_price.map(|p| p as f64 / 10000.0),
-            time_in_force: Some(self.convert_time_in_force_to_char(order.time_in_force)),
+            stop_px: order.stop_price.map(scaled_to_price),
+            time_in_force: Some(mapping::time_in_force_to_fix(order.time_in_force)),
             last_qty: Some(trade.quantity),
-            last_px: Some(trade.price as f64 / 10000.0),
+            last_px: Some(scaled_to_price(trade.price)),
             leaves_qty: order.remaining_quantity(),
             cum_qty: order.filled_quantity,
-            avg_px: Some(trade.price as f64 / 10000.0),
+            avg_px: order.average_fill_price().map(scaled_to_price),
+            commission: Some(scaled_price_to_float(Self::order_commission(result, order.id, order.side) as Price)),
             transact_time: self.get_utc_timestamp(),
+            ord_rej_reason: None,
             text: None,
             trailer,
         };
@@ -89,13 +88,173 @@ impl FixResponseConverter {
         Ok(FixMessage::ExecutionReport(execution_report))
     }
 
+    /// Every report a client expects for the order `result` executed, in
+    /// the order it expects them: New on acceptance, a PartiallyFilled or
+    /// Filled report per fill, and Canceled if the remainder was cancelled
+    /// rather than left resting. CumQty, LeavesQty and AvgPx are those
+    /// after each report's own fill. A rejected result gives one Rejected
+    /// report.
+    pub fn build_execution_reports(&mut self, result: &TradeExecutionResult, cl_ord_id: &str) -> Vec<ExecutionReport> {
+        if result.rejected {
+            return vec![self.rejection_report(cl_ord_id, "Order rejected")];
+        }
+        let Some(order) = Self::executed_order(result) else {
+            return vec![self.rejection_report(cl_ord_id, "No action taken")];
+        };
+        let order = order.read();
+
+        let fills: Vec<&Trade> = result
+            .trades
+            .iter()
+            .filter(|t| t.buy_order_id == order.id || t.sell_order_id == order.id)
+            .collect();
+        // Fills from before this execution, as for a triggered stop, are
+        // already reported.
+        let mut cum_qty = order.filled_quantity - fills.iter().map(|t| t.quantity).sum::<u32>();
+        let mut filled_notional = order.fill_notional
+            - fills.iter().map(|t| notional(t.price, t.quantity)).sum::<Notional>();
+
+        let mut reports = vec![self.order_report(&order, cl_ord_id, OrderStatus::New, cum_qty, filled_notional, None)];
+        for trade in fills {
+            cum_qty += trade.quantity;
+            filled_notional += notional(trade.price, trade.quantity);
+            let status = if cum_qty >= order.quantity {
+                OrderStatus::Filled
+            } else {
+                OrderStatus::PartiallyFilled
+            };
+            let mut report = self.order_report(&order, cl_ord_id, status, cum_qty, filled_notional, Some(trade));
+            let fee = if trade.aggressor == Some(order.side) { trade.taker_fee } else { trade.maker_fee };
+            report.commission = Some(scaled_price_to_float(fee as Price));
+            reports.push(report);
+        }
+        if order.status == OrderStatus::Canceled {
+            let mut report = self.order_report(&order, cl_ord_id, OrderStatus::Canceled, cum_qty, filled_notional, None);
+            report.leaves_qty = 0;
+            reports.push(report);
+        }
+        reports
+    }
+
+    /// A report on `order` as of a point in its execution, `cum_qty` filled
+    /// for `notional`, carrying `fill` as the last fill if it is for one.
+    fn order_report(
+        &mut self,
+        order: &Order,
+        cl_ord_id: &str,
+        status: OrderStatus,
+        cum_qty: u32,
+        notional: Notional,
+        fill: Option<&Trade>,
+    ) -> ExecutionReport {
+        ExecutionReport {
+            header: self.create_standard_header(MessageType::ExecutionReport),
+            order_id: order.id.to_string(),
+            cl_ord_id: cl_ord_id.to_string(),
+            orig_cl_ord_id: None,
+            exec_id: self.next_exec_id().to_string(),
+            exec_type: mapping::order_status_to_exec_type(status).to_char(),
+            ord_status: mapping::order_status_to_fix(status).to_char(),
+            account: None,
+            symbol: order.symbol.clone(),
+            side: mapping::side_to_fix(order.side),
+            order_qty: order.quantity,
+            ord_type: mapping::order_type_to_fix(order.order_type),
+            price: if matches!(order.order_type, OrderType::Limit | OrderType::StopLimit) {
+                Some(scaled_to_price(order.price))
+            } else {
+                None
+            },
+            stop_px: order.stop_price.map(scaled_to_price),
+            time_in_force: Some(mapping::time_in_force_to_fix(order.time_in_force)),
+            last_qty: fill.map(|t| t.quantity),
+            last_px: fill.map(|t| scaled_to_price(t.price)),
+            leaves_qty: order.quantity - cum_qty,
+            cum_qty,
+            avg_px: (cum_qty > 0).then(|| scaled_to_price((notional / cum_qty as Notional) as Price)),
+            commission: None,
+            transact_time: self.get_utc_timestamp(),
+            ord_rej_reason: None,
+            text: None,
+            trailer: Trailer { checksum: 0 },
+        }
+    }
+
+    /// Answers an OrderStatusRequest for `order` with its CumQty, LeavesQty
+    /// and OrdStatus as they stand.
+    pub fn order_status_report(&mut self, order: &Order, cl_ord_id: &str) -> ExecutionReport {
+        let mut report = self.order_report(order, cl_ord_id, order.status, order.filled_quantity, order.fill_notional, None);
+        report.exec_type = ExecType::OrderStatus.to_char();
+        if order.status.is_terminal() {
+            report.leaves_qty = 0;
+        }
+        report
+    }
+
+    /// Answers an OrderStatusRequest for an order the engine does not know
+    /// with a Rejected status and OrdRejReason UnknownOrder.
+    pub fn unknown_order_report(&mut self, request: &OrderStatusRequest) -> ExecutionReport {
+        let mut report = self.rejection_report(&request.cl_ord_id, "Unknown order");
+        report.exec_type = ExecType::OrderStatus.to_char();
+        if let Some(order_id) = &request.order_id {
+            report.order_id = order_id.clone();
+        }
+        report.symbol = request.symbol.clone();
+        report.side = request.side;
+        report.ord_rej_reason = Some(OrdRejReason::UnknownOrder.to_code());
+        report
+    }
+
+    /// Confirms the cancel of `order` that `request` asked for.
+    pub fn cancel_report(&mut self, order: &Order, request: &OrderCancelRequest) -> ExecutionReport {
+        let mut report = self.order_report(order, &request.cl_ord_id, OrderStatus::Canceled, order.filled_quantity, order.fill_notional, None);
+        report.orig_cl_ord_id = Some(request.orig_cl_ord_id.clone());
+        report.leaves_qty = 0;
+        report
+    }
+
+    /// Refuses a cancel of an order the user has no live order for, with
+    /// OrdRejReason UnknownOrder.
+    pub fn unknown_cancel_report(&mut self, request: &OrderCancelRequest) -> ExecutionReport {
+        let mut report = self.rejection_report(&request.cl_ord_id, "Unknown order");
+        report.orig_cl_ord_id = Some(request.orig_cl_ord_id.clone());
+        report.symbol = request.symbol.clone();
+        report.side = request.side;
+        report.ord_rej_reason = Some(OrdRejReason::UnknownOrder.to_code());
+        report
+    }
+
+    /// The order `result` executed: the one left resting, else the
+    /// aggressor of its first trade, else the last order it finished.
+    fn executed_order(result: &TradeExecutionResult) -> Option<&Arc<RwLock<Order>>> {
+        let aggressor_id = result.trades.first().and_then(|trade| match trade.aggressor {
+            Some(Side::Buy) => Some(trade.buy_order_id),
+            Some(Side::Sell) => Some(trade.sell_order_id),
+            None => None,
+        });
+        result.remaining_order.as_ref()
+            .or_else(|| result.filled_orders.iter().find(|o| Some(o.read().id) == aggressor_id))
+            .or_else(|| result.filled_orders.last())
+    }
+
+    /// Total fees charged to `order_id` across the trades in `result`, at
+    /// the taker rate where it was the aggressor and the maker rate otherwise.
+    fn order_commission(result: &TradeExecutionResult, order_id: u64, side: Side) -> u64 {
+        result
+            .trades
+            .iter()
+            .filter(|t| t.buy_order_id == order_id || t.sell_order_id == order_id)
+            .map(|t| if t.aggressor == Some(side) { t.taker_fee } else { t.maker_fee })
+            .sum()
+    }
+
     fn create_new_execution_report(&mut self, result: &TradeExecutionResult, cl_ord_id: &str) -> Result<FixMessage, FixError> {
         let remaining_order = result.remaining_order.as_ref()
             .ok_or_else(|| FixError::Parse(crate::fix::error::ParseError::InvalidFormat))?;
         
         let order = remaining_order.read();
 
-        let header = self.create_standard_header(MessageType::ExecutionReport)?;
+        let header = self.create_standard_header(MessageType::ExecutionReport);
         let trailer = Trailer { checksum: 0 };
 
         let execution_report = ExecutionReport {
@@ -104,26 +263,28 @@ impl FixResponseConverter {
             cl_ord_id: cl_ord_id.to_string(),
             orig_cl_ord_id: None,
             exec_id: self.next_exec_id().to_string(),
-            exec_type: ExecType::New.to_char(),
-            ord_status: OrdStatus::New.to_char(),
+            exec_type: mapping::order_status_to_exec_type(OrderStatus::New).to_char(),
+            ord_status: mapping::order_status_to_fix(OrderStatus::New).to_char(),
             account: None,
             symbol: order.symbol.clone(),
-            side: self.convert_side_to_char(order.side),
+            side: mapping::side_to_fix(order.side),
             order_qty: order.quantity,
-            ord_type: self.convert_order_type_to_char(order.order_type),
+            ord_type: mapping::order_type_to_fix(order.order_type),
             price: if matches!(order.order_type, OrderType::Limit | OrderType::StopLimit) {
-                Some(order.price as f64 / 10000.0)
+                Some(scaled_to_price(order.price))
             } else {
                 None
             },
-            stop_px: order.stop_price.map(|p| p as f64 / 10000.0),
-            time_in_force: Some(self.convert_time_in_force_to_char(order.time_in_force)),
+            stop_px: order.stop_price.map(scaled_to_price),
+            time_in_force: Some(mapping::time_in_force_to_fix(order.time_in_force)),
             last_qty: None,
             last_px: None,
             leaves_qty: order.remaining_quantity(),
             cum_qty: order.filled_quantity,
             avg_px: None,
+            commission: None,
             transact_time: self.get_utc_timestamp(),
+            ord_rej_reason: None,
             text: None,
             trailer,
         };
@@ -131,23 +292,38 @@ impl FixResponseConverter {
         Ok(FixMessage::ExecutionReport(execution_report))
     }
 
+    /// Rejects a NewOrderSingle the engine refused for a business reason.
+    pub fn convert_business_reject(&mut self, cl_ord_id: &str, symbol: &str, error: &BusinessError) -> Result<FixMessage, FixError> {
+        let message = self.create_rejection_execution_report(cl_ord_id, &error.to_string())?;
+        let FixMessage::ExecutionReport(mut report) = message else {
+            unreachable!("rejections are execution reports")
+        };
+        report.symbol = symbol.to_string();
+        report.ord_rej_reason = Some(mapping::business_error_to_ord_rej_reason(error).to_code());
+        Ok(FixMessage::ExecutionReport(report))
+    }
+
     fn create_rejection_execution_report(&mut self, cl_ord_id: &str, reason: &str) -> Result<FixMessage, FixError> {
-        let header = self.create_standard_header(MessageType::ExecutionReport)?;
+        Ok(FixMessage::ExecutionReport(self.rejection_report(cl_ord_id, reason)))
+    }
+
+    fn rejection_report(&mut self, cl_ord_id: &str, reason: &str) -> ExecutionReport {
+        let header = self.create_standard_header(MessageType::ExecutionReport);
         let trailer = Trailer { checksum: 0 };
 
-        let execution_report = ExecutionReport {
+        ExecutionReport {
             header,
             order_id: "0".to_string(),
             cl_ord_id: cl_ord_id.to_string(),
             orig_cl_ord_id: None,
             exec_id: self.next_exec_id().to_string(),
-            exec_type: ExecType::Rejected.to_char(),
-            ord_status: OrdStatus::Rejected.to_char(),
+            exec_type: mapping::order_status_to_exec_type(OrderStatus::Rejected).to_char(),
+            ord_status: mapping::order_status_to_fix(OrderStatus::Rejected).to_char(),
             account: None,
             symbol: "".to_string(),
-            side: '1',
+            side: mapping::side_to_fix(Side::Buy),
             order_qty: 0,
-            ord_type: '2',
+            ord_type: mapping::order_type_to_fix(OrderType::Limit),
             price: None,
             stop_px: None,
             time_in_force: None,
@@ -156,16 +332,16 @@ impl FixResponseConverter {
             leaves_qty: 0,
             cum_qty: 0,
             avg_px: None,
+            commission: None,
             transact_time: self.get_utc_timestamp(),
+            ord_rej_reason: None,
             text: Some(reason.to_string()),
             trailer,
-        };
-
-        Ok(FixMessage::ExecutionReport(execution_report))
+        }
     }
 
-    fn create_standard_header(&self, msg_type: MessageType) -> Result<StandardHeader, FixError> {
-        Ok(StandardHeader {
+    fn create_standard_header(&self, msg_type: MessageType) -> StandardHeader {
+        StandardHeader {
             begin_string: "FIX.4.4".to_string(),
             body_length: 0, 
             msg_type,
@@ -175,35 +351,9 @@ impl FixResponseConverter {
             sending_time: self.get_utc_timestamp(),
             poss_dup_flag: None,
             poss_resend: None,
+            orig_sending_time: None,
             secure_data_len: None,
             secure_data: None,
-        })
-    }
-
-    fn convert_side_to_char(&self, side: Side) -> char {
-        match side {
-            Side::Buy => '1',
-            Side::Sell => '2',
-        }
-    }
-
-    fn convert_order_type_to_char(&self, order_type: OrderType) -> char {
-        match order_type {
-            OrderType::Market => '1',
-            OrderType::Limit => '2',
-            OrderType::StopMarket => '3',
-            OrderType::StopLimit => '4',
-            OrderType::Iceberg => '2',
-        }
-    }
-
-    fn convert_time_in_force_to_char(&self, time_in_force: crate::order::TimeInForce) -> char {
-        match time_in_force {
-            crate::order::TimeInForce::Day => '0',
-            crate::order::TimeInForce::GTC => '1',
-            crate::order::TimeInForce::IOC => '3',
-            crate::order::TimeInForce::FOK => '4',
-            crate::order::TimeInForce::GTD => '6',
         }
     }
 
@@ -0,0 +1,507 @@
+//! Order-entry conformance suite. Scenarios are scripted FIX exchanges
+//! checked field by field against what a gateway sends back, so the same
+//! suite runs against `FixGateway` in memory, against it over TCP, or
+//! against another implementation listening on a socket.
+//!
+//! Besides each scenario's own expectations, every message received must
+//! carry a valid BodyLength and CheckSum, and its MsgSeqNum must be one
+//! above the previous message on the connection unless it is a resend
+//! flagged PossDupFlag.
+
+pub mod report;
+pub mod scenarios;
+pub mod transport;
+
+pub use report::{Failure, Report, ScenarioResult};
+pub use transport::{Connection, Connector};
+
+use std::time::{Duration, Instant};
+
+use crate::fix::encoder;
+use crate::fix::parser::FixParser;
+
+const SOH: u8 = 0x01;
+
+/// What a field of a received message must hold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldCheck {
+    Equals(String),
+    Present,
+    Absent,
+}
+
+/// The next message a scenario expects on a connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expectation {
+    pub msg_type: String,
+    pub fields: Vec<(u32, FieldCheck)>,
+}
+
+impl Expectation {
+    pub fn new(msg_type: &str) -> Self {
+        Self {
+            msg_type: msg_type.to_string(),
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn field(mut self, tag: u32, value: impl ToString) -> Self {
+        self.fields
+            .push((tag, FieldCheck::Equals(value.to_string())));
+        self
+    }
+
+    pub fn present(mut self, tag: u32) -> Self {
+        self.fields.push((tag, FieldCheck::Present));
+        self
+    }
+
+    pub fn absent(mut self, tag: u32) -> Self {
+        self.fields.push((tag, FieldCheck::Absent));
+        self
+    }
+
+    /// Why `fields` does not meet the expectation, if it does not.
+    fn mismatch(&self, fields: &[(u32, String)]) -> Option<String> {
+        let msg_type = value(fields, 35).unwrap_or_default();
+        if msg_type != self.msg_type {
+            return Some(format!(
+                "expected MsgType {}, received {}",
+                self.msg_type, msg_type
+            ));
+        }
+
+        self.fields
+            .iter()
+            .find_map(|(tag, check)| match (check, value(fields, *tag)) {
+                (FieldCheck::Equals(expected), Some(actual)) if actual != expected => Some(
+                    format!("tag {tag} is \"{actual}\", expected \"{expected}\""),
+                ),
+                (FieldCheck::Equals(expected), None) => {
+                    Some(format!("tag {tag} is missing, expected \"{expected}\""))
+                }
+                (FieldCheck::Present, None) => Some(format!("tag {tag} is missing")),
+                (FieldCheck::Absent, Some(actual)) => {
+                    Some(format!("tag {tag} is \"{actual}\", expected it absent"))
+                }
+                _ => None,
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    /// Opens the next connection. Connections are numbered from zero in
+    /// the order they are opened.
+    Connect { sender_comp_id: String },
+    /// Sends a message on `connection`. The runner fills in the header from
+    /// the connection's CompIDs and next MsgSeqNum.
+    Send {
+        connection: usize,
+        msg_type: String,
+        fields: Vec<(u32, String)>,
+    },
+    /// As `Send`, with the CheckSum corrupted.
+    SendGarbled {
+        connection: usize,
+        msg_type: String,
+        fields: Vec<(u32, String)>,
+    },
+    Expect {
+        connection: usize,
+        expectation: Expectation,
+    },
+    /// Nothing may arrive on `connection` within the quiet period.
+    ExpectNothing { connection: usize },
+    /// Closes `connection`, then waits the quiet period so the gateway can
+    /// notice.
+    Disconnect { connection: usize },
+}
+
+impl Step {
+    fn describe(&self) -> String {
+        match self {
+            Step::Connect { sender_comp_id } => format!("connect as {sender_comp_id}"),
+            Step::Send {
+                connection,
+                msg_type,
+                ..
+            } => format!("send 35={msg_type} on connection {connection}"),
+            Step::SendGarbled {
+                connection,
+                msg_type,
+                ..
+            } => format!("send garbled 35={msg_type} on connection {connection}"),
+            Step::Expect {
+                connection,
+                expectation,
+            } => format!(
+                "expect 35={} on connection {connection}",
+                expectation.msg_type
+            ),
+            Step::ExpectNothing { connection } => {
+                format!("expect nothing on connection {connection}")
+            }
+            Step::Disconnect { connection } => format!("disconnect connection {connection}"),
+        }
+    }
+}
+
+/// A named, tagged script of steps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scenario {
+    pub name: String,
+    pub tags: Vec<String>,
+    pub steps: Vec<Step>,
+}
+
+impl Scenario {
+    pub fn new(name: &str, tags: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Whether any of `filters` names the scenario or one of its tags. No
+    /// filters select every scenario.
+    pub fn matches(&self, filters: &[String]) -> bool {
+        filters.is_empty()
+            || filters
+                .iter()
+                .any(|filter| self.name.contains(filter.as_str()) || self.tags.contains(filter))
+    }
+
+    pub fn connect(mut self, sender_comp_id: &str) -> Self {
+        self.steps.push(Step::Connect {
+            sender_comp_id: sender_comp_id.to_string(),
+        });
+        self
+    }
+
+    pub fn send(mut self, connection: usize, msg_type: &str, fields: &[(u32, &str)]) -> Self {
+        self.steps.push(Step::Send {
+            connection,
+            msg_type: msg_type.to_string(),
+            fields: owned(fields),
+        });
+        self
+    }
+
+    pub fn send_garbled(
+        mut self,
+        connection: usize,
+        msg_type: &str,
+        fields: &[(u32, &str)],
+    ) -> Self {
+        self.steps.push(Step::SendGarbled {
+            connection,
+            msg_type: msg_type.to_string(),
+            fields: owned(fields),
+        });
+        self
+    }
+
+    pub fn expect(mut self, connection: usize, expectation: Expectation) -> Self {
+        self.steps.push(Step::Expect {
+            connection,
+            expectation,
+        });
+        self
+    }
+
+    pub fn expect_nothing(mut self, connection: usize) -> Self {
+        self.steps.push(Step::ExpectNothing { connection });
+        self
+    }
+
+    pub fn disconnect(mut self, connection: usize) -> Self {
+        self.steps.push(Step::Disconnect { connection });
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RunnerConfig {
+    /// How long an expected message may take to arrive.
+    pub response_timeout: Duration,
+    /// How long `ExpectNothing` listens, and how long a disconnect is given
+    /// to take effect.
+    pub quiet_period: Duration,
+    /// TargetCompID of the gateway under test.
+    pub target_comp_id: String,
+}
+
+impl Default for RunnerConfig {
+    fn default() -> Self {
+        Self {
+            response_timeout: Duration::from_secs(2),
+            quiet_period: Duration::from_millis(200),
+            target_comp_id: "EXCHANGE".to_string(),
+        }
+    }
+}
+
+impl RunnerConfig {
+    /// Timings for a gateway that answers before `send` returns, as the
+    /// in-memory connector's does.
+    pub fn immediate() -> Self {
+        Self {
+            response_timeout: Duration::ZERO,
+            quiet_period: Duration::ZERO,
+            ..Self::default()
+        }
+    }
+}
+
+/// The client side of one connection.
+struct ClientSession {
+    connection: Box<dyn Connection>,
+    sender_comp_id: String,
+    next_seq_num: u32,
+    last_received_seq_num: Option<u32>,
+}
+
+/// Runs scenarios one after another, each on fresh connections.
+pub struct Runner {
+    connector: Box<dyn Connector>,
+    config: RunnerConfig,
+    parser: FixParser,
+}
+
+impl Runner {
+    pub fn new(connector: impl Connector + 'static, config: RunnerConfig) -> Self {
+        Self {
+            connector: Box::new(connector),
+            config,
+            parser: FixParser::new(),
+        }
+    }
+
+    pub fn run(&mut self, scenarios: &[Scenario]) -> Report {
+        Report {
+            results: scenarios
+                .iter()
+                .map(|scenario| self.run_scenario(scenario))
+                .collect(),
+        }
+    }
+
+    pub fn run_scenario(&mut self, scenario: &Scenario) -> ScenarioResult {
+        let started = Instant::now();
+        let mut sessions = Vec::new();
+        let failure = scenario.steps.iter().enumerate().find_map(|(index, step)| {
+            self.run_step(step, &mut sessions)
+                .err()
+                .map(|failure| Failure {
+                    step: index + 1,
+                    description: step.describe(),
+                    ..failure
+                })
+        });
+
+        for session in &mut sessions {
+            session.connection.close();
+        }
+        ScenarioResult {
+            name: scenario.name.clone(),
+            mode: self.connector.mode(),
+            tags: scenario.tags.clone(),
+            duration: started.elapsed(),
+            failure,
+        }
+    }
+
+    fn run_step(&mut self, step: &Step, sessions: &mut Vec<ClientSession>) -> Result<(), Failure> {
+        match step {
+            Step::Connect { sender_comp_id } => {
+                let connection = self
+                    .connector
+                    .connect()
+                    .map_err(|error| Failure::new(format!("could not connect: {error}")))?;
+                sessions.push(ClientSession {
+                    connection,
+                    sender_comp_id: sender_comp_id.clone(),
+                    next_seq_num: 1,
+                    last_received_seq_num: None,
+                });
+                Ok(())
+            }
+            Step::Send {
+                connection,
+                msg_type,
+                fields,
+            } => {
+                let message = self.outgoing(session(sessions, *connection)?, msg_type, fields);
+                Self::transmit(session(sessions, *connection)?, &message)
+            }
+            Step::SendGarbled {
+                connection,
+                msg_type,
+                fields,
+            } => {
+                let mut message = self.outgoing(session(sessions, *connection)?, msg_type, fields);
+                let digit = message.len() - 2;
+                message[digit] = b'0' + (message[digit] - b'0' + 1) % 10;
+                Self::transmit(session(sessions, *connection)?, &message)
+            }
+            Step::Expect {
+                connection,
+                expectation,
+            } => {
+                let timeout = self.config.response_timeout;
+                let session = session(sessions, *connection)?;
+                let Some(message) = receive(session, timeout)? else {
+                    return Err(Failure::new(format!(
+                        "no message within {timeout:?}, expected 35={}",
+                        expectation.msg_type
+                    )));
+                };
+                let fields = self.check_wire(session, &message)?;
+                match expectation.mismatch(&fields) {
+                    Some(mismatch) => Err(Failure::received(mismatch, &message)),
+                    None => Ok(()),
+                }
+            }
+            Step::ExpectNothing { connection } => {
+                let quiet_period = self.config.quiet_period;
+                match receive(session(sessions, *connection)?, quiet_period)? {
+                    Some(message) => Err(Failure::received("unexpected message", &message)),
+                    None => Ok(()),
+                }
+            }
+            Step::Disconnect { connection } => {
+                session(sessions, *connection)?.connection.close();
+                std::thread::sleep(self.config.quiet_period);
+                Ok(())
+            }
+        }
+    }
+
+    /// Frames a client message with the session's header.
+    fn outgoing(
+        &self,
+        session: &mut ClientSession,
+        msg_type: &str,
+        fields: &[(u32, String)],
+    ) -> Vec<u8> {
+        let mut message = vec![
+            (35, msg_type.to_string()),
+            (49, session.sender_comp_id.clone()),
+            (56, self.config.target_comp_id.clone()),
+            (34, session.next_seq_num.to_string()),
+            (52, sending_time()),
+        ];
+        message.extend(fields.iter().map(|(tag, value)| {
+            let value = if value == NOW {
+                sending_time()
+            } else {
+                value.clone()
+            };
+            (*tag, value)
+        }));
+        session.next_seq_num += 1;
+        encoder::frame("FIX.4.4", &message)
+    }
+
+    fn transmit(session: &mut ClientSession, message: &[u8]) -> Result<(), Failure> {
+        session
+            .connection
+            .send(message)
+            .map_err(|error| Failure::new(format!("send failed: {error}")))
+    }
+
+    /// Checks the framing and sequencing every message must get right and
+    /// returns its fields.
+    fn check_wire(
+        &self,
+        session: &mut ClientSession,
+        message: &[u8],
+    ) -> Result<Vec<(u32, String)>, Failure> {
+        if let Err(error) = self
+            .parser
+            .validate_checksum(message)
+            .and_then(|_| self.parser.validate_body_length(message))
+        {
+            return Err(Failure::received(
+                format!("malformed message: {error}"),
+                message,
+            ));
+        }
+
+        let fields = fields(message);
+        if value(&fields, 43) == Some("Y") {
+            return Ok(fields);
+        }
+        let Some(seq_num) = value(&fields, 34).and_then(|seq_num| seq_num.parse::<u32>().ok())
+        else {
+            return Err(Failure::received("MsgSeqNum is missing", message));
+        };
+        if let Some(last) = session.last_received_seq_num {
+            if seq_num != last + 1 {
+                return Err(Failure::received(
+                    format!("MsgSeqNum {seq_num} does not follow {last}"),
+                    message,
+                ));
+            }
+        }
+        session.last_received_seq_num = Some(seq_num);
+        Ok(fields)
+    }
+}
+
+/// Field value the runner replaces with the current UTC time, for
+/// TransactTime and the like.
+pub const NOW: &str = "<now>";
+
+fn sending_time() -> String {
+    chrono::Utc::now().format("%Y%m%d-%H:%M:%S%.3f").to_string()
+}
+
+fn session(
+    sessions: &mut [ClientSession],
+    connection: usize,
+) -> Result<&mut ClientSession, Failure> {
+    sessions
+        .get_mut(connection)
+        .ok_or_else(|| Failure::new(format!("connection {connection} was never opened")))
+}
+
+fn receive(session: &mut ClientSession, timeout: Duration) -> Result<Option<Vec<u8>>, Failure> {
+    session
+        .connection
+        .receive(timeout)
+        .map_err(|error| Failure::new(format!("receive failed: {error}")))
+}
+
+fn owned(fields: &[(u32, &str)]) -> Vec<(u32, String)> {
+    fields
+        .iter()
+        .map(|&(tag, value)| (tag, value.to_string()))
+        .collect()
+}
+
+/// The tag=value pairs of a framed message, in order.
+pub fn fields(message: &[u8]) -> Vec<(u32, String)> {
+    message
+        .split(|&byte| byte == SOH)
+        .filter_map(|field| {
+            let field = std::str::from_utf8(field).ok()?;
+            let (tag, value) = field.split_once('=')?;
+            Some((tag.parse().ok()?, value.to_string()))
+        })
+        .collect()
+}
+
+fn value(fields: &[(u32, String)], tag: u32) -> Option<&str> {
+    fields
+        .iter()
+        .find(|(field_tag, _)| *field_tag == tag)
+        .map(|(_, value)| value.as_str())
+}
+
+/// A message with SOH shown as `|`, for reports.
+pub fn printable(message: &[u8]) -> String {
+    String::from_utf8_lossy(message).replace('\x01', "|")
+}
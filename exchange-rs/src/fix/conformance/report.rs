@@ -0,0 +1,149 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::fix::conformance::printable;
+
+/// Why a scenario failed: the step that went wrong and what was received
+/// instead, if anything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    /// Position of the failing step, counting from 1.
+    pub step: usize,
+    pub description: String,
+    pub message: String,
+    pub received: Option<String>,
+}
+
+impl Failure {
+    pub(crate) fn new(message: impl Into<String>) -> Self {
+        Self {
+            step: 0,
+            description: String::new(),
+            message: message.into(),
+            received: None,
+        }
+    }
+
+    pub(crate) fn received(message: impl Into<String>, received: &[u8]) -> Self {
+        Self {
+            received: Some(printable(received)),
+            ..Self::new(message)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScenarioResult {
+    pub name: String,
+    /// The connector the scenario ran through, such as `memory` or `tcp`.
+    pub mode: String,
+    pub tags: Vec<String>,
+    pub duration: Duration,
+    pub failure: Option<Failure>,
+}
+
+impl ScenarioResult {
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub results: Vec<ScenarioResult>,
+}
+
+impl Report {
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(ScenarioResult::passed)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &ScenarioResult> {
+        self.results.iter().filter(|result| !result.passed())
+    }
+
+    pub fn extend(&mut self, other: Report) {
+        self.results.extend(other.results);
+    }
+
+    /// The results as a TAP version 13 stream, with a YAML block under
+    /// each failed scenario.
+    pub fn to_tap(&self) -> String {
+        let mut tap = format!("TAP version 13\n1..{}\n", self.results.len());
+        for (index, result) in self.results.iter().enumerate() {
+            let status = if result.passed() { "ok" } else { "not ok" };
+            let _ = writeln!(
+                tap,
+                "{status} {} - {}/{}",
+                index + 1,
+                result.mode,
+                result.name
+            );
+            if let Some(failure) = &result.failure {
+                let _ = writeln!(tap, "  ---");
+                let _ = writeln!(tap, "  step: {}", failure.step);
+                let _ = writeln!(tap, "  action: {:?}", failure.description);
+                let _ = writeln!(tap, "  message: {:?}", failure.message);
+                if let Some(received) = &failure.received {
+                    let _ = writeln!(tap, "  received: {received:?}");
+                }
+                let _ = writeln!(tap, "  tags: [{}]", result.tags.join(", "));
+                let _ = writeln!(tap, "  ...");
+            }
+        }
+        tap
+    }
+
+    /// The results as JUnit XML, one test case per scenario grouped by
+    /// mode.
+    pub fn to_junit(&self) -> String {
+        let failures = self.failures().count();
+        let seconds: f64 = self
+            .results
+            .iter()
+            .map(|result| result.duration.as_secs_f64())
+            .sum();
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(
+            xml,
+            "<testsuite name=\"conformance\" tests=\"{}\" failures=\"{failures}\" time=\"{seconds:.3}\">",
+            self.results.len()
+        );
+        for result in &self.results {
+            let _ = write!(
+                xml,
+                "  <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+                escape(&result.mode),
+                escape(&result.name),
+                result.duration.as_secs_f64()
+            );
+            match &result.failure {
+                None => xml.push_str("/>\n"),
+                Some(failure) => {
+                    let _ = writeln!(
+                        xml,
+                        ">\n    <failure message=\"step {}: {}\">{}: {}{}</failure>\n  </testcase>",
+                        failure.step,
+                        escape(&failure.message),
+                        escape(&failure.description),
+                        escape(&failure.message),
+                        failure
+                            .received
+                            .as_deref()
+                            .map(|received| format!("\nreceived: {}", escape(received)))
+                            .unwrap_or_default()
+                    );
+                }
+            }
+        }
+        xml.push_str("</testsuite>\n");
+        xml
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,529 @@
+//! The behaviours the gateway claims, as scenarios. They assume the
+//! fixture `fixture_gateway` sets up, which a gateway under test over TCP
+//! must reproduce:
+//!
+//! - `AAPL` trades continuously with a tick size of 0.05. Its limit
+//!   prices must be within 20% of the last trade, or of 100 before the
+//!   first, and its orders are for at most 10000.
+//! - `MSFT` is halted.
+//! - `ZZZZ` is not listed.
+//! - Market data requests are answered from `AAPL`'s visible book.
+//! - A session's orders belong to the user numbered by the digits of its
+//!   SenderCompID and are cancelled when its connection closes.
+//!
+//! Scenarios leave no orders resting once their connections close, so the
+//! suite can run repeatedly against the same gateway.
+
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use crate::fix::conformance::{Expectation, Scenario, NOW};
+use crate::fix_gateway::FixGateway;
+use crate::matching_engine::MatchingEngine;
+use crate::orderbook::SymbolSpec;
+use crate::price_utils::Price;
+
+const SYMBOL: &str = "AAPL";
+const HALTED_SYMBOL: &str = "MSFT";
+const UNLISTED_SYMBOL: &str = "ZZZZ";
+const TICK_SIZE: Price = 50_000;
+const REFERENCE_PRICE: Price = 100_000_000;
+const PRICE_BAND_PCT: f64 = 20.0;
+const MAX_QUANTITY: u32 = 10_000;
+
+/// A gateway over a fresh engine set up as the scenarios assume.
+pub fn fixture_gateway() -> FixGateway {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol(SYMBOL);
+    engine
+        .set_symbol_spec(
+            SYMBOL,
+            SymbolSpec {
+                tick_size: TICK_SIZE,
+                ..SymbolSpec::default()
+            },
+        )
+        .expect("fixture symbol is listed");
+    engine.add_symbol(HALTED_SYMBOL);
+    engine
+        .halt_symbol(HALTED_SYMBOL)
+        .expect("fixture symbol is listed");
+    let mut gateway = FixGateway::new(Arc::new(Mutex::new(engine)));
+    let validator = gateway.validator_mut();
+    validator.set_price_band(SYMBOL, PRICE_BAND_PCT);
+    validator.set_reference_price(SYMBOL, REFERENCE_PRICE);
+    validator.set_max_quantity(SYMBOL, MAX_QUANTITY);
+    gateway
+}
+
+fn logon(scenario: Scenario, connection: usize) -> Scenario {
+    scenario
+        .send(connection, "A", &[(98, "0"), (108, "30")])
+        .expect(connection, Expectation::new("A"))
+}
+
+fn new_order(
+    scenario: Scenario,
+    connection: usize,
+    cl_ord_id: &str,
+    symbol: &str,
+    side: &str,
+    quantity: &str,
+    price: &str,
+) -> Scenario {
+    scenario.send(
+        connection,
+        "D",
+        &[
+            (11, cl_ord_id),
+            (21, "1"),
+            (55, symbol),
+            (54, side),
+            (60, NOW),
+            (38, quantity),
+            (40, "2"),
+            (44, price),
+        ],
+    )
+}
+
+fn acknowledged(cl_ord_id: &str, quantity: &str) -> Expectation {
+    Expectation::new("8")
+        .field(11, cl_ord_id)
+        .field(150, "0")
+        .field(39, "0")
+        .field(151, quantity)
+        .field(14, "0")
+        .present(37)
+        .present(17)
+}
+
+fn rejected(cl_ord_id: &str, ord_rej_reason: u32) -> Expectation {
+    Expectation::new("8")
+        .field(11, cl_ord_id)
+        .field(150, "8")
+        .field(39, "8")
+        .field(103, ord_rej_reason)
+}
+
+fn session_scenarios() -> Vec<Scenario> {
+    vec![
+        Scenario::new("logon-is-answered", &["session"])
+            .connect("CLIENT1")
+            .send(0, "A", &[(98, "0"), (108, "30")])
+            .expect(
+                0,
+                Expectation::new("A")
+                    .field(34, 1)
+                    .field(49, "EXCHANGE")
+                    .field(56, "CLIENT1")
+                    .field(98, "0")
+                    .field(108, "30"),
+            ),
+        logon(
+            Scenario::new("test-request-is-echoed", &["session"]).connect("CLIENT1"),
+            0,
+        )
+        .send(0, "1", &[(112, "PROBE-1")])
+        .expect(0, Expectation::new("0").field(112, "PROBE-1")),
+        logon(
+            Scenario::new("logout-is-confirmed", &["session"]).connect("CLIENT1"),
+            0,
+        )
+        .send(0, "5", &[])
+        .expect(0, Expectation::new("5"))
+        .expect_nothing(0),
+    ]
+}
+
+fn report_scenarios() -> Vec<Scenario> {
+    let acknowledged_order = logon(
+        Scenario::new("new-order-is-acknowledged", &["reports"]).connect("CLIENT1"),
+        0,
+    );
+    let acknowledged_order = new_order(acknowledged_order, 0, "ACK-1", SYMBOL, "1", "10", "99.5")
+        .expect(
+            0,
+            acknowledged("ACK-1", "10")
+                .field(55, SYMBOL)
+                .field(54, "1")
+                .field(38, "10")
+                .field(40, "2")
+                .field(44, "99.5"),
+        );
+
+    let fill = Scenario::new("fill-reports-follow-in-sequence", &["reports"])
+        .connect("CLIENT1")
+        .connect("CLIENT2");
+    let fill = logon(logon(fill, 0), 1);
+    let fill = new_order(fill, 0, "FILL-1", SYMBOL, "2", "5", "101")
+        .expect(0, acknowledged("FILL-1", "5"));
+    let fill = new_order(fill, 1, "FILL-2", SYMBOL, "1", "5", "101").expect(
+        1,
+        Expectation::new("8")
+            .field(11, "FILL-2")
+            .field(54, "1")
+            .field(150, "2")
+            .field(39, "2")
+            .field(32, 5)
+            .field(31, "101")
+            .field(151, 0)
+            .field(14, 5),
+    );
+    let fill = new_order(fill, 1, "FILL-3", SYMBOL, "1", "1", "100")
+        .expect(1, acknowledged("FILL-3", "1"));
+
+    vec![acknowledged_order, fill]
+}
+
+fn reject_scenarios() -> Vec<Scenario> {
+    let unlisted = logon(
+        Scenario::new("unlisted-symbol-is-rejected", &["reject"]).connect("CLIENT1"),
+        0,
+    );
+    let unlisted = new_order(unlisted, 0, "REJ-1", UNLISTED_SYMBOL, "1", "1", "100")
+        .expect(0, rejected("REJ-1", 1).field(55, UNLISTED_SYMBOL));
+
+    let halted = logon(
+        Scenario::new("halted-symbol-is-rejected", &["reject"]).connect("CLIENT1"),
+        0,
+    );
+    let halted = new_order(halted, 0, "REJ-2", HALTED_SYMBOL, "1", "1", "100")
+        .expect(0, rejected("REJ-2", 2).field(55, HALTED_SYMBOL));
+
+    let duplicate = logon(
+        Scenario::new("duplicate-clordid-is-rejected", &["reject"]).connect("CLIENT1"),
+        0,
+    );
+    let duplicate = new_order(duplicate, 0, "REJ-3", SYMBOL, "1", "1", "90")
+        .expect(0, acknowledged("REJ-3", "1"));
+    let duplicate =
+        new_order(duplicate, 0, "REJ-3", SYMBOL, "1", "1", "90").expect(0, rejected("REJ-3", 6));
+
+    let off_tick = logon(
+        Scenario::new("off-tick-price-is-rejected", &["reject"]).connect("CLIENT1"),
+        0,
+    );
+    let off_tick = new_order(off_tick, 0, "REJ-4", SYMBOL, "1", "1", "100.01")
+        .expect(0, rejected("REJ-4", 18));
+
+    let oversized_quantity = logon(
+        Scenario::new("oversized-quantity-is-rejected", &["reject"]).connect("CLIENT1"),
+        0,
+    );
+    let oversized_quantity =
+        new_order(oversized_quantity, 0, "REJ-6", SYMBOL, "1", "4294967296", "100")
+            .expect(0, rejected("REJ-6", 13).present(58));
+
+    let oversized_price = logon(
+        Scenario::new("oversized-price-is-rejected", &["reject"]).connect("CLIENT1"),
+        0,
+    );
+    let oversized_price =
+        new_order(oversized_price, 0, "REJ-7", SYMBOL, "1", "1", "18446744073710")
+            .expect(0, rejected("REJ-7", 11).present(58));
+
+    let off_band = logon(
+        Scenario::new("price-outside-band-is-rejected", &["reject"]).connect("CLIENT1"),
+        0,
+    );
+    let off_band = new_order(off_band, 0, "REJ-8", SYMBOL, "1", "1", "200")
+        .expect(0, rejected("REJ-8", 3).present(58));
+
+    let fat_finger = logon(
+        Scenario::new("quantity-above-maximum-is-rejected", &["reject"]).connect("CLIENT1"),
+        0,
+    );
+    let fat_finger = new_order(fat_finger, 0, "REJ-9", SYMBOL, "1", "50000", "100")
+        .expect(0, rejected("REJ-9", 13).present(58));
+
+    let garbled = logon(
+        Scenario::new("garbled-message-is-rejected", &["reject", "session"]).connect("CLIENT1"),
+        0,
+    )
+    .send_garbled(0, "1", &[(112, "PROBE-2")])
+    .expect(0, Expectation::new("3").field(45, 2).present(58));
+
+    let incomplete = logon(
+        Scenario::new("incomplete-order-is-rejected", &["reject", "session"]).connect("CLIENT1"),
+        0,
+    )
+    .send(
+        0,
+        "D",
+        &[
+            (11, "REJ-5"),
+            (21, "1"),
+            (54, "1"),
+            (60, NOW),
+            (38, "1"),
+            (40, "2"),
+            (44, "100"),
+        ],
+    )
+    .expect(0, Expectation::new("3").field(45, 2).present(58));
+
+    vec![
+        unlisted,
+        halted,
+        duplicate,
+        off_tick,
+        oversized_quantity,
+        oversized_price,
+        off_band,
+        fat_finger,
+        garbled,
+        incomplete,
+    ]
+}
+
+fn resend_scenarios() -> Vec<Scenario> {
+    let resend = logon(
+        Scenario::new("resend-request-replays-reports", &["resend"]).connect("CLIENT1"),
+        0,
+    );
+    let resend = new_order(resend, 0, "RES-1", SYMBOL, "1", "2", "95")
+        .expect(0, acknowledged("RES-1", "2"))
+        .send(0, "2", &[(7, "1"), (16, "0")])
+        .expect(
+            0,
+            Expectation::new("4")
+                .field(34, 1)
+                .field(43, "Y")
+                .field(123, "Y")
+                .field(36, 2),
+        )
+        .expect(
+            0,
+            Expectation::new("8")
+                .field(34, 2)
+                .field(43, "Y")
+                .field(11, "RES-1")
+                .field(39, "0")
+                .present(122),
+        )
+        .expect_nothing(0);
+    let resend = new_order(resend, 0, "RES-2", SYMBOL, "1", "2", "95")
+        .expect(0, acknowledged("RES-2", "2").field(34, 3).absent(43));
+
+    vec![resend]
+}
+
+fn cancel_on_disconnect_scenarios() -> Vec<Scenario> {
+    let scenario = Scenario::new("disconnect-cancels-open-orders", &["cancel-on-disconnect"])
+        .connect("CLIENT1");
+    let scenario = new_order(logon(scenario, 0), 0, "COD-1", SYMBOL, "1", "10", "98")
+        .expect(0, acknowledged("COD-1", "10"))
+        .disconnect(0)
+        .connect("CLIENT2");
+    let scenario = new_order(logon(scenario, 1), 1, "COD-2", SYMBOL, "2", "10", "98")
+        .expect(1, acknowledged("COD-2", "10"));
+
+    vec![scenario]
+}
+
+fn security_definition_scenarios() -> Vec<Scenario> {
+    let listed = logon(
+        Scenario::new("security-definition-publishes-limits", &["reference-data"])
+            .connect("CLIENT1"),
+        0,
+    )
+    .send(0, "c", &[(320, "SEC-1"), (321, "0"), (55, SYMBOL)])
+    .expect(
+        0,
+        Expectation::new("d")
+            .field(320, "SEC-1")
+            .field(323, "1")
+            .field(55, SYMBOL)
+            .field(969, "0.05")
+            .field(1140, "4294967295")
+            .field(1149, "18446744073709"),
+    );
+
+    let unlisted = logon(
+        Scenario::new("unlisted-security-definition-is-refused", &["reference-data"])
+            .connect("CLIENT1"),
+        0,
+    )
+    .send(0, "c", &[(320, "SEC-2"), (321, "0"), (55, UNLISTED_SYMBOL)])
+    .expect(
+        0,
+        Expectation::new("d")
+            .field(320, "SEC-2")
+            .field(323, "6")
+            .absent(1140),
+    );
+
+    vec![listed, unlisted]
+}
+
+fn order_status_scenarios() -> Vec<Scenario> {
+    let known = Scenario::new("order-status-reports-fills", &["reports", "order-status"])
+        .connect("CLIENT1")
+        .connect("CLIENT2");
+    let known = logon(logon(known, 0), 1);
+    let known = new_order(known, 0, "STAT-1", SYMBOL, "2", "10", "103")
+        .expect(0, acknowledged("STAT-1", "10"));
+    let known = new_order(known, 1, "STAT-2", SYMBOL, "1", "4", "103")
+        .expect(1, Expectation::new("8").field(11, "STAT-2").field(39, "2"))
+        .send(0, "H", &[(11, "STAT-1"), (55, SYMBOL), (54, "2")])
+        .expect(
+            0,
+            Expectation::new("8")
+                .field(11, "STAT-1")
+                .field(150, "I")
+                .field(39, "1")
+                .field(151, 6)
+                .field(14, 4)
+                .field(6, "103")
+                .present(37),
+        );
+
+    let unknown = logon(
+        Scenario::new("unknown-order-status-is-rejected", &["order-status", "reject"])
+            .connect("CLIENT1"),
+        0,
+    )
+    .send(0, "H", &[(37, "999999"), (11, "STAT-3"), (55, SYMBOL), (54, "1")])
+    .expect(
+        0,
+        Expectation::new("8")
+            .field(11, "STAT-3")
+            .field(37, "999999")
+            .field(150, "I")
+            .field(39, "8")
+            .field(103, 5)
+            .present(58),
+    );
+
+    vec![known, unknown]
+}
+
+fn cancel_scenarios() -> Vec<Scenario> {
+    let cancel = |scenario: Scenario, orig_cl_ord_id: &str, cl_ord_id: &str| {
+        scenario.send(
+            0,
+            "F",
+            &[(41, orig_cl_ord_id), (11, cl_ord_id), (55, SYMBOL), (54, "2"), (60, NOW)],
+        )
+    };
+
+    let scenario = logon(
+        Scenario::new("cancel-by-orig-cl-ord-id", &["cancel", "reports"]).connect("CLIENT1"),
+        0,
+    );
+    let scenario = new_order(scenario, 0, "CXL-1", SYMBOL, "2", "10", "103")
+        .expect(0, acknowledged("CXL-1", "10"));
+    let scenario = cancel(scenario, "CXL-1", "CXL-2").expect(
+        0,
+        Expectation::new("8")
+            .field(11, "CXL-2")
+            .field(41, "CXL-1")
+            .field(150, "4")
+            .field(39, "4")
+            .field(151, 0)
+            .present(37),
+    );
+    let scenario = cancel(scenario, "CXL-1", "CXL-3").expect(
+        0,
+        Expectation::new("8")
+            .field(11, "CXL-3")
+            .field(41, "CXL-1")
+            .field(39, "8")
+            .field(103, 5),
+    );
+
+    vec![scenario]
+}
+
+fn market_data_request(
+    scenario: Scenario,
+    connection: usize,
+    md_req_id: &str,
+    subscription_request_type: &str,
+    symbol: &str,
+) -> Scenario {
+    scenario.send(
+        connection,
+        "V",
+        &[
+            (262, md_req_id),
+            (263, subscription_request_type),
+            (264, "0"),
+            (265, "1"),
+            (267, "2"),
+            (269, "0"),
+            (269, "1"),
+            (146, "1"),
+            (55, symbol),
+        ],
+    )
+}
+
+fn market_data_scenarios() -> Vec<Scenario> {
+    let subscription = Scenario::new("book-subscription-streams-updates", &["market-data"])
+        .connect("CLIENT1")
+        .connect("CLIENT2");
+    let subscription = logon(logon(subscription, 0), 1);
+    let subscription = new_order(subscription, 0, "MD-1", SYMBOL, "2", "10", "101")
+        .expect(0, acknowledged("MD-1", "10"));
+    let subscription = market_data_request(subscription, 1, "SUB-1", "1", SYMBOL).expect(
+        1,
+        Expectation::new("W")
+            .field(262, "SUB-1")
+            .field(55, SYMBOL)
+            .field(268, 1)
+            .field(269, "1")
+            .field(270, "101")
+            .field(271, 10),
+    );
+    let subscription = new_order(subscription, 0, "MD-2", SYMBOL, "1", "5", "99")
+        .expect(0, acknowledged("MD-2", "5"))
+        .expect(
+            1,
+            Expectation::new("X")
+                .field(262, "SUB-1")
+                .field(268, 1)
+                .field(279, "0")
+                .field(269, "0")
+                .field(55, SYMBOL)
+                .field(270, "99")
+                .field(271, 5),
+        );
+    let subscription = market_data_request(subscription, 1, "SUB-1", "2", SYMBOL);
+    let subscription = new_order(subscription, 0, "MD-3", SYMBOL, "1", "5", "98")
+        .expect(0, acknowledged("MD-3", "5"))
+        .expect_nothing(1);
+
+    let unlisted = logon(
+        Scenario::new("unlisted-symbol-market-data-is-rejected", &["market-data", "reject"])
+            .connect("CLIENT1"),
+        0,
+    );
+    let unlisted = market_data_request(unlisted, 0, "SUB-2", "0", UNLISTED_SYMBOL).expect(
+        0,
+        Expectation::new("Y")
+            .field(262, "SUB-2")
+            .field(281, "0")
+            .present(58),
+    );
+
+    vec![subscription, unlisted]
+}
+
+/// Every scenario of the suite.
+pub fn standard_scenarios() -> Vec<Scenario> {
+    [
+        session_scenarios(),
+        report_scenarios(),
+        reject_scenarios(),
+        resend_scenarios(),
+        cancel_on_disconnect_scenarios(),
+        security_definition_scenarios(),
+        order_status_scenarios(),
+        cancel_scenarios(),
+        market_data_scenarios(),
+    ]
+    .concat()
+}
@@ -0,0 +1,318 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use tracing::error;
+
+use crate::fix::conformance::fields;
+use crate::fix::encoder;
+use crate::fix_gateway::{FixGateway, GatewayConnection};
+
+/// Opens connections to the gateway under test.
+pub trait Connector {
+    fn connect(&mut self) -> io::Result<Box<dyn Connection>>;
+
+    /// Label of the transport in reports.
+    fn mode(&self) -> String;
+}
+
+/// One connection to the gateway, carrying framed messages.
+pub trait Connection {
+    fn send(&mut self, message: &[u8]) -> io::Result<()>;
+
+    /// The next message from the gateway, or `None` if none arrives within
+    /// `timeout` or the gateway has closed the connection.
+    fn receive(&mut self, timeout: Duration) -> io::Result<Option<Vec<u8>>>;
+
+    fn close(&mut self);
+}
+
+/// Drives a `FixGateway`'s connection state directly, without a socket.
+pub struct MemoryConnector {
+    gateway: FixGateway,
+}
+
+impl MemoryConnector {
+    pub fn new(gateway: FixGateway) -> Self {
+        Self { gateway }
+    }
+}
+
+impl Connector for MemoryConnector {
+    fn connect(&mut self) -> io::Result<Box<dyn Connection>> {
+        Ok(Box::new(MemoryConnection {
+            connection: self.gateway.connection(),
+            replies: VecDeque::new(),
+            open: true,
+        }))
+    }
+
+    fn mode(&self) -> String {
+        "memory".to_string()
+    }
+}
+
+struct MemoryConnection {
+    connection: GatewayConnection,
+    replies: VecDeque<Vec<u8>>,
+    open: bool,
+}
+
+impl Connection for MemoryConnection {
+    fn send(&mut self, message: &[u8]) -> io::Result<()> {
+        if !self.open {
+            return Err(io::ErrorKind::NotConnected.into());
+        }
+        self.replies.extend(self.connection.handle(message));
+        if self.connection.is_logged_out() {
+            self.close();
+        }
+        Ok(())
+    }
+
+    /// Replies to what was sent come first; once they are drained, the
+    /// books subscribed to are polled as the TCP server does on its timer.
+    fn receive(&mut self, _timeout: Duration) -> io::Result<Option<Vec<u8>>> {
+        if self.replies.is_empty() && self.open {
+            self.replies.extend(self.connection.poll_market_data());
+        }
+        Ok(self.replies.pop_front())
+    }
+
+    /// Closes the connection as the TCP server does when its peer goes
+    /// away, cancelling the session's orders.
+    fn close(&mut self) {
+        if std::mem::replace(&mut self.open, false) {
+            self.connection.close();
+        }
+    }
+}
+
+/// Connects over TCP to a gateway listening at `address`.
+pub struct TcpConnector {
+    address: SocketAddr,
+}
+
+impl TcpConnector {
+    pub fn new(address: SocketAddr) -> Self {
+        Self { address }
+    }
+}
+
+impl Connector for TcpConnector {
+    fn connect(&mut self) -> io::Result<Box<dyn Connection>> {
+        let stream = TcpStream::connect(self.address)?;
+        stream.set_nodelay(true)?;
+        Ok(Box::new(TcpConnection {
+            stream,
+            buffer: Vec::new(),
+        }))
+    }
+
+    fn mode(&self) -> String {
+        "tcp".to_string()
+    }
+}
+
+struct TcpConnection {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+}
+
+impl TcpConnection {
+    /// Removes the first complete message from the front of the buffer.
+    fn take_frame(&mut self) -> Option<Vec<u8>> {
+        const CHECKSUM: &[u8] = b"\x0110=";
+
+        let checksum_start = self
+            .buffer
+            .windows(CHECKSUM.len())
+            .position(|window| window == CHECKSUM)?;
+        let value_start = checksum_start + CHECKSUM.len();
+        let end = value_start
+            + self.buffer[value_start..]
+                .iter()
+                .position(|&byte| byte == 0x01)?;
+        Some(self.buffer.drain(..=end).collect())
+    }
+}
+
+impl Connection for TcpConnection {
+    fn send(&mut self, message: &[u8]) -> io::Result<()> {
+        self.stream.write_all(message)
+    }
+
+    fn receive(&mut self, timeout: Duration) -> io::Result<Option<Vec<u8>>> {
+        let deadline = Instant::now() + timeout;
+        let mut chunk = [0u8; 4096];
+        loop {
+            if let Some(message) = self.take_frame() {
+                return Ok(Some(message));
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+            self.stream.set_read_timeout(Some(remaining))?;
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Ok(None),
+                Ok(bytes_read) => self.buffer.extend_from_slice(&chunk[..bytes_read]),
+                Err(error)
+                    if matches!(
+                        error.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Ok(None)
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        let _ = self.stream.shutdown(std::net::Shutdown::Both);
+    }
+}
+
+/// Starts `gateway` on an ephemeral local port, on a thread of its own,
+/// and returns the address it listens on.
+pub fn serve_in_background(gateway: FixGateway) -> io::Result<SocketAddr> {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    listener.set_nonblocking(true)?;
+    let address = listener.local_addr()?;
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    std::thread::spawn(move || {
+        runtime.block_on(async move {
+            let served = match tokio::net::TcpListener::from_std(listener) {
+                Ok(listener) => gateway.serve(listener).await.map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            if let Err(e) = served {
+                error!("Conformance gateway stopped: {}", e);
+            }
+        })
+    });
+    Ok(address)
+}
+
+/// A deliberate defect between the suite and the gateway, used to check
+/// that the suite notices and reports it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// Loses the `n`th message received from the gateway, counting from 1
+    /// across every connection.
+    DropMessage(usize),
+    /// Sets `tag` to `value` in every received message that carries it.
+    /// The message is framed again, so it stays well formed.
+    RewriteField { tag: u32, value: String },
+    /// Keeps connections open when a scenario disconnects them, as if the
+    /// gateway never noticed the disconnect.
+    IgnoreDisconnect,
+}
+
+/// Wraps a connector and applies `faults` to its connections.
+pub struct FaultInjector<C> {
+    inner: C,
+    faults: Rc<Vec<Fault>>,
+    received: Rc<Cell<usize>>,
+    abandoned: Rc<RefCell<Vec<Box<dyn Connection>>>>,
+}
+
+impl<C: Connector> FaultInjector<C> {
+    pub fn new(inner: C, faults: Vec<Fault>) -> Self {
+        Self {
+            inner,
+            faults: Rc::new(faults),
+            received: Rc::new(Cell::new(0)),
+            abandoned: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+}
+
+impl<C: Connector> Connector for FaultInjector<C> {
+    fn connect(&mut self) -> io::Result<Box<dyn Connection>> {
+        Ok(Box::new(FaultyConnection {
+            inner: Some(self.inner.connect()?),
+            faults: Rc::clone(&self.faults),
+            received: Rc::clone(&self.received),
+            abandoned: Rc::clone(&self.abandoned),
+        }))
+    }
+
+    fn mode(&self) -> String {
+        self.inner.mode()
+    }
+}
+
+struct FaultyConnection {
+    inner: Option<Box<dyn Connection>>,
+    faults: Rc<Vec<Fault>>,
+    received: Rc<Cell<usize>>,
+    abandoned: Rc<RefCell<Vec<Box<dyn Connection>>>>,
+}
+
+impl FaultyConnection {
+    fn inner(&mut self) -> io::Result<&mut Box<dyn Connection>> {
+        self.inner
+            .as_mut()
+            .ok_or_else(|| io::ErrorKind::NotConnected.into())
+    }
+
+    fn rewrite(message: Vec<u8>, tag: u32, value: &str) -> Vec<u8> {
+        let mut fields = fields(&message);
+        let Some(field) = fields.iter_mut().find(|(field_tag, _)| *field_tag == tag) else {
+            return message;
+        };
+        field.1 = value.to_string();
+
+        let begin_string = fields[0].1.clone();
+        let body: Vec<_> = fields
+            .into_iter()
+            .filter(|(tag, _)| !matches!(tag, 8..=10))
+            .collect();
+        encoder::frame(&begin_string, &body)
+    }
+}
+
+impl Connection for FaultyConnection {
+    fn send(&mut self, message: &[u8]) -> io::Result<()> {
+        self.inner()?.send(message)
+    }
+
+    fn receive(&mut self, timeout: Duration) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            let Some(mut message) = self.inner()?.receive(timeout)? else {
+                return Ok(None);
+            };
+            let count = self.received.get() + 1;
+            self.received.set(count);
+            if self.faults.contains(&Fault::DropMessage(count)) {
+                continue;
+            }
+            for fault in self.faults.iter() {
+                if let Fault::RewriteField { tag, value } = fault {
+                    message = Self::rewrite(message, *tag, value);
+                }
+            }
+            return Ok(Some(message));
+        }
+    }
+
+    fn close(&mut self) {
+        let Some(mut inner) = self.inner.take() else {
+            return;
+        };
+        if self.faults.contains(&Fault::IgnoreDisconnect) {
+            self.abandoned.borrow_mut().push(inner);
+        } else {
+            inner.close();
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use crate::fix::messages::{FixMessage, StandardHeader};
+
+const SOH: char = '\x01';
+
+/// Encodes `message` for the wire. BodyLength and CheckSum are computed
+/// here, so the `body_length` and trailer carried by the message are
+/// ignored.
+pub fn encode(message: &FixMessage) -> Vec<u8> {
+    let header = message.header();
+    let mut fields = header_fields(header);
+    body_fields(message, &mut fields);
+    frame(&header.begin_string, &fields)
+}
+
+/// Encodes a message that has no typed model, such as a session-level
+/// Reject, from its header and body fields.
+pub fn encode_fields(header: &StandardHeader, body: &[(u32, String)]) -> Vec<u8> {
+    let mut fields = header_fields(header);
+    fields.extend_from_slice(body);
+    frame(&header.begin_string, &fields)
+}
+
+/// Frames `fields`, which start with MsgType (35), between BeginString,
+/// BodyLength and CheckSum.
+pub fn frame(begin_string: &str, fields: &[(u32, String)]) -> Vec<u8> {
+    let body: String = fields
+        .iter()
+        .map(|(tag, value)| format!("{tag}={value}{SOH}"))
+        .collect();
+    let mut message = format!("8={begin_string}{SOH}9={}{SOH}{body}", body.len());
+    let checksum = message
+        .bytes()
+        .fold(0u8, |sum, byte| sum.wrapping_add(byte));
+    message.push_str(&format!("10={checksum:03}{SOH}"));
+    message.into_bytes()
+}
+
+fn header_fields(header: &StandardHeader) -> Vec<(u32, String)> {
+    let mut fields = vec![
+        (35, header.msg_type.as_str().to_string()),
+        (49, header.sender_comp_id.clone()),
+        (56, header.target_comp_id.clone()),
+        (34, header.msg_seq_num.to_string()),
+    ];
+    push(&mut fields, 43, header.poss_dup_flag.map(flag));
+    push(&mut fields, 97, header.poss_resend.map(flag));
+    fields.push((52, header.sending_time.clone()));
+    push(&mut fields, 122, header.orig_sending_time.clone());
+    fields
+}
+
+fn body_fields(message: &FixMessage, fields: &mut Vec<(u32, String)>) {
+    match message {
+        FixMessage::NewOrderSingle(order) => {
+            fields.push((11, order.cl_ord_id.clone()));
+            push(fields, 1, order.account.clone());
+            fields.push((21, order.handl_inst.to_string()));
+            fields.push((55, order.symbol.clone()));
+            fields.push((54, order.side.to_string()));
+            fields.push((60, order.transact_time.clone()));
+            fields.push((38, order.order_qty.to_string()));
+            fields.push((40, order.ord_type.to_string()));
+            push(fields, 44, order.price);
+            push(fields, 99, order.stop_px);
+            push(fields, 59, order.time_in_force);
+            push(fields, 18, order.exec_inst.clone());
+        }
+        FixMessage::ExecutionReport(report) => {
+            fields.push((37, report.order_id.clone()));
+            fields.push((11, report.cl_ord_id.clone()));
+            push(fields, 41, report.orig_cl_ord_id.clone());
+            fields.push((17, report.exec_id.clone()));
+            fields.push((150, report.exec_type.to_string()));
+            fields.push((39, report.ord_status.to_string()));
+            push(fields, 1, report.account.clone());
+            fields.push((55, report.symbol.clone()));
+            fields.push((54, report.side.to_string()));
+            fields.push((38, report.order_qty.to_string()));
+            fields.push((40, report.ord_type.to_string()));
+            push(fields, 44, report.price);
+            push(fields, 99, report.stop_px);
+            push(fields, 59, report.time_in_force);
+            push(fields, 32, report.last_qty);
+            push(fields, 31, report.last_px);
+            fields.push((151, report.leaves_qty.to_string()));
+            fields.push((14, report.cum_qty.to_string()));
+            push(fields, 6, report.avg_px);
+            push(fields, 12, report.commission);
+            fields.push((60, report.transact_time.clone()));
+            push(fields, 103, report.ord_rej_reason);
+            push(fields, 58, report.text.clone());
+        }
+        FixMessage::OrderCancelRequest(cancel) => {
+            fields.push((41, cancel.orig_cl_ord_id.clone()));
+            fields.push((11, cancel.cl_ord_id.clone()));
+            push(fields, 1, cancel.account.clone());
+            fields.push((55, cancel.symbol.clone()));
+            fields.push((54, cancel.side.to_string()));
+            fields.push((60, cancel.transact_time.clone()));
+            push(fields, 38, cancel.order_qty);
+            push(fields, 58, cancel.text.clone());
+        }
+        FixMessage::OrderStatusRequest(request) => {
+            push(fields, 37, request.order_id.clone());
+            fields.push((11, request.cl_ord_id.clone()));
+            fields.push((55, request.symbol.clone()));
+            fields.push((54, request.side.to_string()));
+        }
+        FixMessage::Heartbeat(heartbeat) => {
+            push(fields, 112, heartbeat.test_req_id.clone());
+        }
+        FixMessage::Logon(logon) => {
+            fields.push((98, logon.encrypt_method.to_string()));
+            fields.push((108, logon.heart_bt_int.to_string()));
+            push(fields, 141, logon.reset_seq_num_flag.map(flag));
+            push(fields, 789, logon.next_expected_msg_seq_num);
+            push(fields, 553, logon.username.clone());
+            push(fields, 554, logon.password.clone());
+        }
+        FixMessage::Logout(logout) => {
+            push(fields, 58, logout.text.clone());
+        }
+        FixMessage::ResendRequest(request) => {
+            fields.push((7, request.begin_seq_no.to_string()));
+            fields.push((16, request.end_seq_no.to_string()));
+        }
+        FixMessage::SequenceReset(reset) => {
+            push(fields, 123, reset.gap_fill_flag.map(flag));
+            fields.push((36, reset.new_seq_no.to_string()));
+        }
+        FixMessage::TestRequest(request) => {
+            fields.push((112, request.test_req_id.clone()));
+        }
+        FixMessage::MarketDataRequest(request) => {
+            fields.push((262, request.md_req_id.clone()));
+            fields.push((263, request.subscription_request_type.to_string()));
+            fields.push((264, request.market_depth.to_string()));
+            push(fields, 265, request.md_update_type);
+            fields.push((267, request.md_entry_types.len().to_string()));
+            for entry_type in &request.md_entry_types {
+                fields.push((269, entry_type.to_string()));
+            }
+            fields.push((146, request.symbols.len().to_string()));
+            for symbol in &request.symbols {
+                fields.push((55, symbol.clone()));
+            }
+        }
+        FixMessage::MarketDataSnapshotFullRefresh(refresh) => {
+            push(fields, 262, refresh.md_req_id.clone());
+            fields.push((55, refresh.symbol.clone()));
+            fields.push((268, refresh.entries.len().to_string()));
+            for entry in &refresh.entries {
+                fields.push((269, entry.md_entry_type.to_string()));
+                fields.push((270, entry.md_entry_px.to_string()));
+                fields.push((271, entry.md_entry_size.to_string()));
+            }
+        }
+        FixMessage::SecurityDefinitionRequest(request) => {
+            fields.push((320, request.security_req_id.clone()));
+            fields.push((321, request.security_request_type.to_string()));
+            fields.push((55, request.symbol.clone()));
+        }
+        FixMessage::MarketDataIncrementalRefresh(refresh) => {
+            push(fields, 262, refresh.md_req_id.clone());
+            fields.push((268, refresh.entries.len().to_string()));
+            for entry in &refresh.entries {
+                fields.push((279, entry.md_update_action.to_string()));
+                fields.push((269, entry.md_entry_type.to_string()));
+                push(fields, 278, entry.md_entry_id.clone());
+                fields.push((55, entry.symbol.clone()));
+                fields.push((270, entry.md_entry_px.to_string()));
+                fields.push((271, entry.md_entry_size.to_string()));
+                push(fields, 83, entry.rpt_seq);
+            }
+        }
+    }
+}
+
+fn push(fields: &mut Vec<(u32, String)>, tag: u32, value: Option<impl ToString>) {
+    if let Some(value) = value {
+        fields.push((tag, value.to_string()));
+    }
+}
+
+fn flag(value: bool) -> char {
+    if value {
+        'Y'
+    } else {
+        'N'
+    }
+}
@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::price_utils::Price;
+
 #[derive(Error, Debug, Clone)]
 pub enum FixError {
     #[error("Parse error: {0}")]
@@ -94,6 +96,9 @@ pub enum SessionError {
     
     #[error("Duplicate session")]
     DuplicateSession,
+    
+    #[error("SendingTime accuracy problem: {skew_ns}ns from exchange clock")]
+    SendingTimeAccuracy { skew_ns: i64 },
 }
 
 #[derive(Error, Debug, Clone)]
@@ -105,7 +110,22 @@ pub enum BusinessError {
     InvalidQuantity { quantity: u32 },
     
     #[error("Invalid price: {price}")]
-    InvalidPrice { price: u64 },
+    InvalidPrice { price: Price },
+
+    #[error("Price {price} is not a multiple of tick size {tick_size}")]
+    InvalidPriceIncrement { price: Price, tick_size: Price },
+
+    #[error("Incorrect quantity {quantity}: {reason}")]
+    IncorrectQuantity { quantity: u32, reason: String },
+
+    #[error("Incorrect quantity {quantity}: {reason}")]
+    QuantityOutOfRange { quantity: i64, reason: String },
+
+    #[error("Invalid price {price}: {reason}")]
+    PriceOutOfRange { price: f64, reason: String },
+
+    #[error("Price {price} is more than {band_pct}% away from the reference price {reference}")]
+    PriceOutsideBand { price: f64, reference: f64, band_pct: f64 },
     
     #[error("Duplicate ClOrdID: {cl_ord_id}")]
     DuplicateClOrdId { cl_ord_id: String },
@@ -124,4 +144,7 @@ pub enum BusinessError {
     
     #[error("Position limit exceeded: {limit}")]
     PositionLimitExceeded { limit: u32 },
+    
+    #[error("Unsupported order characteristic: {reason}")]
+    UnsupportedOrderCharacteristic { reason: String },
 }
\ No newline at end of file
@@ -0,0 +1,206 @@
+//! Conversions between FIX enumerations and the engine's order enums.
+//!
+//! Every conversion from an internal enum matches without a wildcard arm, so
+//! adding a variant to `Side`, `OrderType`, `TimeInForce` or `OrderStatus`
+//! fails to compile until it is given a FIX representation here. Wire codes
+//! themselves are owned by the message enums in `fix::messages`.
+
+use thiserror::Error;
+
+use crate::fix::error::{BusinessError, FixError, ValidationError};
+use crate::fix::messages::execution_report::{ExecType, OrdRejReason, OrdStatus};
+use crate::fix::messages::new_order_single;
+use crate::order::{OrderStatus, OrderType, Side, TimeInForce};
+use crate::orderbook::TradingState;
+
+pub const TAG_SIDE: u32 = 54;
+pub const TAG_ORD_TYPE: u32 = 40;
+pub const TAG_TIME_IN_FORCE: u32 = 59;
+pub const TAG_EXEC_TYPE: u32 = 150;
+pub const TAG_ORD_STATUS: u32 = 39;
+pub const TAG_ORD_REJ_REASON: u32 = 103;
+pub const TAG_TRAD_SES_STATUS: u32 = 340;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MappingError {
+    #[error("Unsupported value for tag {tag}: {value}")]
+    UnsupportedValue { tag: u32, value: char },
+}
+
+impl From<MappingError> for ValidationError {
+    fn from(error: MappingError) -> Self {
+        match error {
+            MappingError::UnsupportedValue { tag, value } => ValidationError::InvalidFieldValue {
+                tag,
+                value: value.to_string(),
+            },
+        }
+    }
+}
+
+impl From<MappingError> for FixError {
+    fn from(error: MappingError) -> Self {
+        FixError::Validation(error.into())
+    }
+}
+
+fn unsupported(tag: u32, value: char) -> MappingError {
+    MappingError::UnsupportedValue { tag, value }
+}
+
+pub fn side_to_fix(side: Side) -> char {
+    match side {
+        Side::Buy => new_order_single::Side::Buy,
+        Side::Sell => new_order_single::Side::Sell,
+    }
+    .to_char()
+}
+
+pub fn fix_to_side(value: char) -> Result<Side, MappingError> {
+    match new_order_single::Side::from_char(value) {
+        Some(new_order_single::Side::Buy) => Ok(Side::Buy),
+        Some(new_order_single::Side::Sell) => Ok(Side::Sell),
+        None => Err(unsupported(TAG_SIDE, value)),
+    }
+}
+
+/// Icebergs go out as plain limit orders; the displayed size is carried
+/// separately, so `OrderType::Iceberg` does not round-trip through tag 40.
+pub fn order_type_to_fix(order_type: OrderType) -> char {
+    match order_type {
+        OrderType::Market => new_order_single::OrdType::Market,
+        OrderType::Limit | OrderType::Iceberg => new_order_single::OrdType::Limit,
+        OrderType::StopMarket => new_order_single::OrdType::Stop,
+        OrderType::StopLimit => new_order_single::OrdType::StopLimit,
+    }
+    .to_char()
+}
+
+pub fn fix_to_order_type(value: char) -> Result<OrderType, MappingError> {
+    match new_order_single::OrdType::from_char(value) {
+        Some(new_order_single::OrdType::Market) => Ok(OrderType::Market),
+        Some(new_order_single::OrdType::Limit) => Ok(OrderType::Limit),
+        Some(new_order_single::OrdType::Stop) => Ok(OrderType::StopMarket),
+        Some(new_order_single::OrdType::StopLimit) => Ok(OrderType::StopLimit),
+        None => Err(unsupported(TAG_ORD_TYPE, value)),
+    }
+}
+
+pub fn time_in_force_to_fix(time_in_force: TimeInForce) -> char {
+    match time_in_force {
+        TimeInForce::Day => new_order_single::TimeInForce::Day,
+        TimeInForce::GTC => new_order_single::TimeInForce::GoodTillCancel,
+        TimeInForce::IOC => new_order_single::TimeInForce::ImmediateOrCancel,
+        TimeInForce::FOK => new_order_single::TimeInForce::FillOrKill,
+        TimeInForce::GTD => new_order_single::TimeInForce::GoodTillDate,
+    }
+    .to_char()
+}
+
+pub fn fix_to_time_in_force(value: char) -> Result<TimeInForce, MappingError> {
+    match new_order_single::TimeInForce::from_char(value) {
+        Some(new_order_single::TimeInForce::Day) => Ok(TimeInForce::Day),
+        Some(new_order_single::TimeInForce::GoodTillCancel) => Ok(TimeInForce::GTC),
+        Some(new_order_single::TimeInForce::ImmediateOrCancel) => Ok(TimeInForce::IOC),
+        Some(new_order_single::TimeInForce::FillOrKill) => Ok(TimeInForce::FOK),
+        Some(new_order_single::TimeInForce::GoodTillDate) => Ok(TimeInForce::GTD),
+        None => Err(unsupported(TAG_TIME_IN_FORCE, value)),
+    }
+}
+
+/// Tag 59 is optional; FIX defaults an absent TimeInForce to Day, but this
+/// venue has always treated it as GTC.
+pub fn fix_to_time_in_force_or_default(value: Option<char>) -> Result<TimeInForce, MappingError> {
+    value.map_or(Ok(TimeInForce::GTC), fix_to_time_in_force)
+}
+
+pub fn order_status_to_fix(status: OrderStatus) -> OrdStatus {
+    match status {
+        OrderStatus::New => OrdStatus::New,
+        OrderStatus::PartiallyFilled => OrdStatus::PartiallyFilled,
+        OrderStatus::Filled => OrdStatus::Filled,
+        OrderStatus::Canceled => OrdStatus::Canceled,
+        OrderStatus::Rejected => OrdStatus::Rejected,
+        OrderStatus::Expired => OrdStatus::Expired,
+    }
+}
+
+pub fn fix_to_order_status(value: char) -> Result<OrderStatus, MappingError> {
+    match OrdStatus::from_char(value) {
+        Some(OrdStatus::New) => Ok(OrderStatus::New),
+        Some(OrdStatus::PartiallyFilled) => Ok(OrderStatus::PartiallyFilled),
+        Some(OrdStatus::Filled) => Ok(OrderStatus::Filled),
+        Some(OrdStatus::Canceled) => Ok(OrderStatus::Canceled),
+        Some(OrdStatus::Rejected) => Ok(OrderStatus::Rejected),
+        Some(OrdStatus::Expired) => Ok(OrderStatus::Expired),
+        _ => Err(unsupported(TAG_ORD_STATUS, value)),
+    }
+}
+
+/// The ExecType reported for a transition into `status`.
+pub fn order_status_to_exec_type(status: OrderStatus) -> ExecType {
+    match status {
+        OrderStatus::New => ExecType::New,
+        OrderStatus::PartiallyFilled => ExecType::PartialFill,
+        OrderStatus::Filled => ExecType::Fill,
+        OrderStatus::Canceled => ExecType::Canceled,
+        OrderStatus::Rejected => ExecType::Rejected,
+        OrderStatus::Expired => ExecType::Expired,
+    }
+}
+
+pub fn fix_exec_type_to_order_status(value: char) -> Result<OrderStatus, MappingError> {
+    match ExecType::from_char(value) {
+        Some(ExecType::New) => Ok(OrderStatus::New),
+        Some(ExecType::PartialFill) => Ok(OrderStatus::PartiallyFilled),
+        Some(ExecType::Fill) => Ok(OrderStatus::Filled),
+        Some(ExecType::Canceled) => Ok(OrderStatus::Canceled),
+        Some(ExecType::Rejected) => Ok(OrderStatus::Rejected),
+        Some(ExecType::Expired) => Ok(OrderStatus::Expired),
+        _ => Err(unsupported(TAG_EXEC_TYPE, value)),
+    }
+}
+
+/// TradSesStatus for a symbol's trading state: an auction call is the
+/// pre-open.
+pub fn trading_state_to_fix(state: TradingState) -> char {
+    match state {
+        TradingState::Halted => '1',
+        TradingState::Continuous => '2',
+        TradingState::Auction => '4',
+    }
+}
+
+pub fn fix_to_trading_state(value: char) -> Result<TradingState, MappingError> {
+    match value {
+        '1' => Ok(TradingState::Halted),
+        '2' => Ok(TradingState::Continuous),
+        '4' => Ok(TradingState::Auction),
+        _ => Err(unsupported(TAG_TRAD_SES_STATUS, value)),
+    }
+}
+
+/// The OrdRejReason reported when the engine refuses an order.
+pub fn business_error_to_ord_rej_reason(error: &BusinessError) -> OrdRejReason {
+    match error {
+        BusinessError::InvalidSymbol { .. } => OrdRejReason::UnknownSymbol,
+        BusinessError::MarketClosed { .. } | BusinessError::TradingHalt { .. } => {
+            OrdRejReason::ExchangeClosed
+        }
+        BusinessError::OrderNotFound { .. } => OrdRejReason::UnknownOrder,
+        BusinessError::DuplicateClOrdId { .. } => OrdRejReason::DuplicateOrder,
+        BusinessError::PositionLimitExceeded { .. } | BusinessError::PriceOutsideBand { .. } => {
+            OrdRejReason::OrderExceedsLimit
+        }
+        BusinessError::UnsupportedOrderCharacteristic { .. } | BusinessError::PriceOutOfRange { .. } => {
+            OrdRejReason::UnsupportedOrderCharacteristic
+        }
+        BusinessError::IncorrectQuantity { .. } | BusinessError::QuantityOutOfRange { .. } => {
+            OrdRejReason::IncorrectQuantity
+        }
+        BusinessError::InvalidPriceIncrement { .. } => OrdRejReason::InvalidPriceIncrement,
+        BusinessError::InvalidQuantity { .. }
+        | BusinessError::InvalidPrice { .. }
+        | BusinessError::InsufficientBalance { .. } => OrdRejReason::Other,
+    }
+}
@@ -0,0 +1,345 @@
+//! Market data the engine publishes over FIX outside the order flow: book
+//! subscriptions made with a MarketDataRequest (35=V), and entries returned
+//! as (tag, value) pairs of the MDEntries repeating group, ready for a
+//! MarketDataIncrementalRefresh (35=X).
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::fix::mapping::{side_to_fix, TAG_SIDE};
+use crate::fix::messages::market_data_request::{
+    MD_UPDATE_TYPE_FULL_REFRESH, MD_UPDATE_TYPE_INCREMENTAL_REFRESH, SUBSCRIPTION_SNAPSHOT,
+    SUBSCRIPTION_SNAPSHOT_AND_UPDATES,
+};
+use crate::fix::messages::{
+    FixMessage, MarketDataIncrementalRefresh, MarketDataRequest, MarketDataSnapshotFullRefresh,
+    MdEntry, MdIncrementalEntry, MessageType, Trailer,
+};
+use crate::fix::session::FixSessionState;
+use crate::l3_feed::{L3Batch, L3Event};
+use crate::matching_engine::MatchingEngine;
+use crate::order::Side;
+use crate::orderbook::{DepthAction, DepthUpdate, IndicativeUncross, MarketDepth};
+use crate::price_utils::{scaled_to_price, Price};
+
+pub const TAG_MD_REQ_ID: u32 = 262;
+pub const TAG_MD_REQ_REJ_REASON: u32 = 281;
+pub const TAG_NO_MD_ENTRIES: u32 = 268;
+pub const TAG_MD_ENTRY_TYPE: u32 = 269;
+pub const TAG_MD_ENTRY_PX: u32 = 270;
+pub const TAG_MD_ENTRY_SIZE: u32 = 271;
+pub const TAG_MD_UPDATE_ACTION: u32 = 279;
+pub const TAG_MD_ENTRY_ID: u32 = 278;
+pub const TAG_RPT_SEQ: u32 = 83;
+
+pub const MD_ENTRY_TYPE_BID: char = '0';
+pub const MD_ENTRY_TYPE_OFFER: char = '1';
+pub const MD_ENTRY_TYPE_TRADE: char = '2';
+/// MDEntryType of the indicative auction clearing price. Its size is the
+/// volume paired at that price.
+pub const MD_ENTRY_TYPE_AUCTION_CLEARING_PRICE: char = 'Q';
+/// MDEntryType of the auction imbalance, sized by the surplus quantity.
+pub const MD_ENTRY_TYPE_IMBALANCE: char = 'A';
+
+pub const MD_UPDATE_ACTION_NEW: char = '0';
+pub const MD_UPDATE_ACTION_CHANGE: char = '1';
+pub const MD_UPDATE_ACTION_DELETE: char = '2';
+
+/// MDEntries for an indicative uncross update. `None` deletes both the
+/// clearing price and the imbalance entries.
+pub fn indicative_uncross_entries(uncross: Option<&IndicativeUncross>) -> Vec<(u32, String)> {
+    let Some(uncross) = uncross else {
+        return vec![
+            (TAG_NO_MD_ENTRIES, "2".to_string()),
+            (TAG_MD_UPDATE_ACTION, MD_UPDATE_ACTION_DELETE.to_string()),
+            (TAG_MD_ENTRY_TYPE, MD_ENTRY_TYPE_AUCTION_CLEARING_PRICE.to_string()),
+            (TAG_MD_UPDATE_ACTION, MD_UPDATE_ACTION_DELETE.to_string()),
+            (TAG_MD_ENTRY_TYPE, MD_ENTRY_TYPE_IMBALANCE.to_string()),
+        ];
+    };
+
+    let mut fields = vec![
+        (TAG_NO_MD_ENTRIES, "2".to_string()),
+        (TAG_MD_UPDATE_ACTION, MD_UPDATE_ACTION_NEW.to_string()),
+        (TAG_MD_ENTRY_TYPE, MD_ENTRY_TYPE_AUCTION_CLEARING_PRICE.to_string()),
+        (TAG_MD_ENTRY_PX, scaled_to_price(uncross.price).to_string()),
+        (TAG_MD_ENTRY_SIZE, uncross.volume.to_string()),
+        (TAG_MD_UPDATE_ACTION, MD_UPDATE_ACTION_NEW.to_string()),
+        (TAG_MD_ENTRY_TYPE, MD_ENTRY_TYPE_IMBALANCE.to_string()),
+        (TAG_MD_ENTRY_SIZE, uncross.imbalance.to_string()),
+    ];
+    if let Some(side) = uncross.imbalance_side {
+        fields.push((TAG_SIDE, side_to_fix(side).to_string()));
+    }
+    fields
+}
+
+/// One MarketDataIncrementalRefresh carrying every change of an L3 batch,
+/// an entry per change in sequence order. Entries name the order in
+/// MDEntryID and carry the change's L3 sequence number in RptSeq: an add
+/// is a new bid or offer, a replace changes it and a cancel deletes it,
+/// each sized by the order's open quantity. An execution is a trade entry
+/// sized by the quantity traded.
+pub fn l3_incremental_refresh(
+    session: &FixSessionState,
+    symbol: &str,
+    md_req_id: Option<&str>,
+    batch: &L3Batch,
+) -> FixMessage {
+    let entries = batch
+        .events
+        .iter()
+        .map(|event| {
+            let (action, side, price, quantity) = match *event {
+                L3Event::Add { side, price, quantity, .. } => (MD_UPDATE_ACTION_NEW, Some(side), price, quantity),
+                L3Event::Replace { side, price, quantity, .. } => {
+                    (MD_UPDATE_ACTION_CHANGE, Some(side), price, quantity)
+                }
+                L3Event::Cancel { side, price, quantity, .. } => {
+                    (MD_UPDATE_ACTION_DELETE, Some(side), price, quantity)
+                }
+                L3Event::Execute { price, quantity, .. } => (MD_UPDATE_ACTION_NEW, None, price, quantity),
+            };
+            MdIncrementalEntry {
+                md_update_action: action,
+                md_entry_type: match side {
+                    Some(Side::Buy) => MD_ENTRY_TYPE_BID,
+                    Some(Side::Sell) => MD_ENTRY_TYPE_OFFER,
+                    None => MD_ENTRY_TYPE_TRADE,
+                },
+                md_entry_id: Some(event.order_id().to_string()),
+                symbol: symbol.to_string(),
+                md_entry_px: scaled_to_price(price),
+                md_entry_size: quantity as u64,
+                rpt_seq: Some(event.sequence()),
+            }
+        })
+        .collect();
+
+    FixMessage::MarketDataIncrementalRefresh(MarketDataIncrementalRefresh {
+        header: session.create_header(MessageType::MarketDataIncrementalRefresh),
+        md_req_id: md_req_id.map(str::to_string),
+        entries,
+        trailer: Trailer { checksum: 0 },
+    })
+}
+
+/// Why a MarketDataRequest is refused, answered with a
+/// MarketDataRequestReject (35=Y).
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MarketDataReject {
+    #[error("Unknown symbol: {symbol}")]
+    UnknownSymbol { symbol: String },
+
+    #[error("Duplicate MDReqID: {md_req_id}")]
+    DuplicateMdReqId { md_req_id: String },
+
+    #[error("Unsupported SubscriptionRequestType: {value}")]
+    UnsupportedSubscriptionRequestType { value: char },
+
+    #[error("Unsupported MDUpdateType: {value}")]
+    UnsupportedMdUpdateType { value: u32 },
+
+    #[error("Unsupported MDEntryType: {value}")]
+    UnsupportedMdEntryType { value: char },
+}
+
+impl MarketDataReject {
+    /// MDReqRejReason (281) value.
+    pub fn to_fix(&self) -> char {
+        match self {
+            MarketDataReject::UnknownSymbol { .. } => '0',
+            MarketDataReject::DuplicateMdReqId { .. } => '1',
+            MarketDataReject::UnsupportedSubscriptionRequestType { .. } => '4',
+            MarketDataReject::UnsupportedMdUpdateType { .. } => '6',
+            MarketDataReject::UnsupportedMdEntryType { .. } => '8',
+        }
+    }
+}
+
+/// The book of one or more symbols as a MarketDataRequest asked for it,
+/// with the depth last sent per symbol so later polls only carry changes.
+#[derive(Debug, Clone)]
+pub struct MarketDataSubscription {
+    md_req_id: String,
+    symbols: Vec<String>,
+    levels: usize,
+    bids: bool,
+    offers: bool,
+    incremental: bool,
+    sent: HashMap<String, MarketDepth>,
+}
+
+impl MarketDataSubscription {
+    /// Checks `request` against what the gateway serves: snapshots with or
+    /// without updates, of bids and offers only. MarketDepth (264) zero is
+    /// the full book.
+    pub fn new(request: &MarketDataRequest) -> Result<Self, MarketDataReject> {
+        if request.subscription_request_type != SUBSCRIPTION_SNAPSHOT
+            && request.subscription_request_type != SUBSCRIPTION_SNAPSHOT_AND_UPDATES
+        {
+            return Err(MarketDataReject::UnsupportedSubscriptionRequestType {
+                value: request.subscription_request_type,
+            });
+        }
+
+        let incremental = match request.md_update_type {
+            Some(MD_UPDATE_TYPE_INCREMENTAL_REFRESH) => true,
+            Some(MD_UPDATE_TYPE_FULL_REFRESH) | None => false,
+            Some(value) => return Err(MarketDataReject::UnsupportedMdUpdateType { value }),
+        };
+
+        if let Some(&value) = request
+            .md_entry_types
+            .iter()
+            .find(|&&entry_type| entry_type != MD_ENTRY_TYPE_BID && entry_type != MD_ENTRY_TYPE_OFFER)
+        {
+            return Err(MarketDataReject::UnsupportedMdEntryType { value });
+        }
+
+        let levels = match request.market_depth {
+            0 => usize::MAX,
+            depth => depth as usize,
+        };
+
+        Ok(Self {
+            md_req_id: request.md_req_id.clone(),
+            symbols: request.symbols.clone(),
+            levels,
+            bids: request.md_entry_types.contains(&MD_ENTRY_TYPE_BID),
+            offers: request.md_entry_types.contains(&MD_ENTRY_TYPE_OFFER),
+            incremental,
+            sent: HashMap::new(),
+        })
+    }
+
+    pub fn md_req_id(&self) -> &str {
+        &self.md_req_id
+    }
+
+    /// A full refresh of every subscribed symbol, which later polls are
+    /// relative to. Fails without sending anything if a symbol is not
+    /// listed.
+    pub fn snapshot(
+        &mut self,
+        engine: &MatchingEngine,
+        session: &FixSessionState,
+    ) -> Result<Vec<FixMessage>, MarketDataReject> {
+        let mut depths = Vec::with_capacity(self.symbols.len());
+        for symbol in &self.symbols {
+            let depth = self
+                .depth(engine, symbol)
+                .ok_or_else(|| MarketDataReject::UnknownSymbol { symbol: symbol.clone() })?;
+            depths.push((symbol.clone(), depth));
+        }
+
+        Ok(depths
+            .into_iter()
+            .map(|(symbol, depth)| {
+                let refresh = self.full_refresh(session, &symbol, &depth);
+                self.sent.insert(symbol, depth);
+                refresh
+            })
+            .collect())
+    }
+
+    /// What changed since the last snapshot or poll: a full refresh per
+    /// changed symbol, or a single incremental refresh for all of them if
+    /// the request asked for MDUpdateType incremental.
+    pub fn poll(&mut self, engine: &MatchingEngine, session: &FixSessionState) -> Vec<FixMessage> {
+        let mut changed = Vec::new();
+        for symbol in &self.symbols {
+            let Some(depth) = self.depth(engine, symbol) else {
+                continue;
+            };
+            if self.sent.get(symbol) != Some(&depth) {
+                changed.push((symbol.clone(), depth));
+            }
+        }
+        if changed.is_empty() {
+            return Vec::new();
+        }
+
+        if !self.incremental {
+            return changed
+                .into_iter()
+                .map(|(symbol, depth)| {
+                    let refresh = self.full_refresh(session, &symbol, &depth);
+                    self.sent.insert(symbol, depth);
+                    refresh
+                })
+                .collect();
+        }
+
+        let mut entries = Vec::new();
+        for (symbol, depth) in changed {
+            let previous = self.sent.remove(&symbol).unwrap_or_default();
+            let delta = depth.diff(&previous);
+            level_updates(&mut entries, &symbol, MD_ENTRY_TYPE_BID, &delta.bid_updates);
+            level_updates(&mut entries, &symbol, MD_ENTRY_TYPE_OFFER, &delta.ask_updates);
+            self.sent.insert(symbol, depth);
+        }
+        vec![FixMessage::MarketDataIncrementalRefresh(MarketDataIncrementalRefresh {
+            header: session.create_header(MessageType::MarketDataIncrementalRefresh),
+            md_req_id: Some(self.md_req_id.clone()),
+            entries,
+            trailer: Trailer { checksum: 0 },
+        })]
+    }
+
+    /// The subscribed sides of `symbol`'s book.
+    fn depth(&self, engine: &MatchingEngine, symbol: &str) -> Option<MarketDepth> {
+        let depth = engine.market_depth(symbol, self.levels)?;
+        Some(MarketDepth::new(
+            if self.bids { depth.bid_levels } else { Vec::new() },
+            if self.offers { depth.ask_levels } else { Vec::new() },
+        ))
+    }
+
+    fn full_refresh(&self, session: &FixSessionState, symbol: &str, depth: &MarketDepth) -> FixMessage {
+        let side = |entry_type: char, levels: &[(Price, u64)]| {
+            levels
+                .iter()
+                .map(move |&(price, size)| MdEntry {
+                    md_entry_type: entry_type,
+                    md_entry_px: scaled_to_price(price),
+                    md_entry_size: size,
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut entries = side(MD_ENTRY_TYPE_BID, &depth.bid_levels);
+        entries.extend(side(MD_ENTRY_TYPE_OFFER, &depth.ask_levels));
+
+        FixMessage::MarketDataSnapshotFullRefresh(MarketDataSnapshotFullRefresh {
+            header: session.create_header(MessageType::MarketDataSnapshotFullRefresh),
+            md_req_id: Some(self.md_req_id.clone()),
+            symbol: symbol.to_string(),
+            entries,
+            trailer: Trailer { checksum: 0 },
+        })
+    }
+}
+
+/// Appends one side's level updates as entries, in the order the delta
+/// lists them.
+fn level_updates(
+    entries: &mut Vec<MdIncrementalEntry>,
+    symbol: &str,
+    entry_type: char,
+    updates: &[DepthUpdate],
+) {
+    entries.extend(updates.iter().map(|update| MdIncrementalEntry {
+        md_update_action: match update.action {
+            DepthAction::New => MD_UPDATE_ACTION_NEW,
+            DepthAction::Change => MD_UPDATE_ACTION_CHANGE,
+            DepthAction::Delete => MD_UPDATE_ACTION_DELETE,
+        },
+        md_entry_type: entry_type,
+        md_entry_id: None,
+        symbol: symbol.to_string(),
+        md_entry_px: scaled_to_price(update.price),
+        md_entry_size: update.quantity,
+        rpt_seq: None,
+    }));
+}
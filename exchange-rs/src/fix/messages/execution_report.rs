@@ -25,7 +25,9 @@ pub struct ExecutionReport {
     pub leaves_qty: u32,             
     pub cum_qty: u32,                
     pub avg_px: Option<f64>,         
+    pub commission: Option<f64>,     
     pub transact_time: String,       
+    pub ord_rej_reason: Option<u32>, 
     pub text: Option<String>,        
     pub trailer: Trailer,
 }
@@ -54,7 +56,9 @@ impl ExecutionReport {
         let leaves_qty = Self::get_required_int(&fields, 151, "LeavesQty")? as u32;
         let cum_qty = Self::get_required_int(&fields, 14, "CumQty")? as u32;
         let avg_px = Self::get_optional_float(&fields, 6);
+        let commission = Self::get_optional_float(&fields, 12);
         let transact_time = Self::get_required_string(&fields, 60, "TransactTime")?;
+        let ord_rej_reason = Self::get_optional_int(&fields, 103).map(|i| i as u32);
         let text = Self::get_optional_string(&fields, 58);
 
         let execution_report = ExecutionReport {
@@ -78,7 +82,9 @@ impl ExecutionReport {
             leaves_qty,
             cum_qty,
             avg_px,
+            commission,
             transact_time,
+            ord_rej_reason,
             text,
             trailer,
         };
@@ -294,4 +300,54 @@ impl OrdStatus {
             OrdStatus::PendingReplace => 'E',
         }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrdRejReason {
+    BrokerOption,     
+    UnknownSymbol,    
+    ExchangeClosed,   
+    OrderExceedsLimit, 
+    TooLateToEnter,   
+    UnknownOrder,     
+    DuplicateOrder,   
+    UnsupportedOrderCharacteristic, 
+    IncorrectQuantity,
+    InvalidPriceIncrement,
+    Other,            
+}
+
+impl OrdRejReason {
+    pub fn from_code(code: u32) -> Option<Self> {
+        match code {
+            0 => Some(OrdRejReason::BrokerOption),
+            1 => Some(OrdRejReason::UnknownSymbol),
+            2 => Some(OrdRejReason::ExchangeClosed),
+            3 => Some(OrdRejReason::OrderExceedsLimit),
+            4 => Some(OrdRejReason::TooLateToEnter),
+            5 => Some(OrdRejReason::UnknownOrder),
+            6 => Some(OrdRejReason::DuplicateOrder),
+            11 => Some(OrdRejReason::UnsupportedOrderCharacteristic),
+            13 => Some(OrdRejReason::IncorrectQuantity),
+            18 => Some(OrdRejReason::InvalidPriceIncrement),
+            99 => Some(OrdRejReason::Other),
+            _ => None,
+        }
+    }
+
+    pub fn to_code(&self) -> u32 {
+        match self {
+            OrdRejReason::BrokerOption => 0,
+            OrdRejReason::UnknownSymbol => 1,
+            OrdRejReason::ExchangeClosed => 2,
+            OrdRejReason::OrderExceedsLimit => 3,
+            OrdRejReason::TooLateToEnter => 4,
+            OrdRejReason::UnknownOrder => 5,
+            OrdRejReason::DuplicateOrder => 6,
+            OrdRejReason::UnsupportedOrderCharacteristic => 11,
+            OrdRejReason::IncorrectQuantity => 13,
+            OrdRejReason::InvalidPriceIncrement => 18,
+            OrdRejReason::Other => 99,
+        }
+    }
+}
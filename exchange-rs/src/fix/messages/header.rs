@@ -14,6 +14,7 @@ pub struct StandardHeader {
     pub sending_time: String,     
     pub poss_dup_flag: Option<bool>, 
     pub poss_resend: Option<bool>,   
+    pub orig_sending_time: Option<String>, 
     pub secure_data_len: Option<u32>, 
     pub secure_data: Option<Vec<u8>>, 
 }
@@ -38,6 +39,7 @@ impl Header {
         
         let poss_dup_flag = Self::get_optional_bool(fields, 43);
         let poss_resend = Self::get_optional_bool(fields, 97);
+        let orig_sending_time = Self::get_optional_string(fields, 122);
         let secure_data_len = Self::get_optional_int(fields, 90).map(|i| i as u32);
         let secure_data = Self::get_optional_data(fields, 91);
 
@@ -51,6 +53,7 @@ impl Header {
             sending_time,
             poss_dup_flag,
             poss_resend,
+            orig_sending_time,
             secure_data_len,
             secure_data,
         })
@@ -69,6 +72,10 @@ impl Header {
             .ok_or_else(|| ValidationError::MissingRequiredField { tag })
     }
 
+    fn get_optional_string(fields: &HashMap<u32, FixField>, tag: u32) -> Option<String> {
+        fields.get(&tag).and_then(|f| f.as_string()).map(|s| s.to_string())
+    }
+
     fn get_optional_bool(fields: &HashMap<u32, FixField>, tag: u32) -> Option<bool> {
         fields.get(&tag).and_then(|f| f.as_bool())
     }
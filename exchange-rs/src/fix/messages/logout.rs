@@ -0,0 +1,39 @@
+use crate::fix::parser::FixField;
+use crate::fix::error::{FixError, ValidationError};
+use crate::fix::messages::{StandardHeader, Trailer, Header};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct Logout {
+    pub header: StandardHeader,
+    pub text: Option<String>,        
+    pub trailer: Trailer,
+}
+
+impl Logout {
+    pub fn parse(fields: HashMap<u32, FixField>) -> Result<Logout, FixError> {
+        let header = Header::parse(&fields)?;
+        let trailer = Trailer::parse(&fields)?;
+
+        let text = Self::get_optional_string(&fields, 58);
+
+        let logout = Logout {
+            header,
+            text,
+            trailer,
+        };
+
+        logout.validate()?;
+        Ok(logout)
+    }
+
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        self.header.validate()?;
+        self.trailer.validate()?;
+        Ok(())
+    }
+
+    fn get_optional_string(fields: &HashMap<u32, FixField>, tag: u32) -> Option<String> {
+        fields.get(&tag).and_then(|f| f.as_string()).map(|s| s.to_string())
+    }
+}
@@ -0,0 +1,80 @@
+use crate::fix::parser::{FixField, RepeatingGroup};
+use crate::fix::error::{FixError, ValidationError};
+use crate::fix::messages::{StandardHeader, Trailer, Header};
+use std::collections::HashMap;
+
+/// One changed price level. Deleted levels carry an MDEntrySize of zero.
+/// Order-by-order entries also name the order in MDEntryID and carry its
+/// L3 sequence number in RptSeq.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MdIncrementalEntry {
+    pub md_update_action: char,
+    pub md_entry_type: char,
+    pub md_entry_id: Option<String>,
+    pub symbol: String,
+    pub md_entry_px: f64,
+    pub md_entry_size: u64,
+    pub rpt_seq: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MarketDataIncrementalRefresh {
+    pub header: StandardHeader,
+    /// MDReqID of the subscription the update belongs to.
+    pub md_req_id: Option<String>,
+    pub entries: Vec<MdIncrementalEntry>,
+    pub trailer: Trailer,
+}
+
+impl MarketDataIncrementalRefresh {
+    pub fn parse(fields: HashMap<u32, FixField>, md_entries: Option<RepeatingGroup>) -> Result<MarketDataIncrementalRefresh, FixError> {
+        let header = Header::parse(&fields)?;
+        let trailer = Trailer::parse(&fields)?;
+
+        let md_req_id = fields.get(&262).and_then(|f| f.as_string()).map(|s| s.to_string());
+        let md_entries = md_entries.ok_or(ValidationError::MissingRequiredField { tag: 268 })?;
+
+        let mut entries = Vec::with_capacity(md_entries.instances.len());
+        for entry in &md_entries.instances {
+            entries.push(MdIncrementalEntry {
+                md_update_action: entry.get(&279)
+                    .and_then(|f| f.as_char())
+                    .ok_or(ValidationError::MissingRequiredField { tag: 279 })?,
+                md_entry_type: entry.get(&269)
+                    .and_then(|f| f.as_char())
+                    .ok_or(ValidationError::MissingRequiredField { tag: 269 })?,
+                md_entry_id: entry.get(&278).and_then(|f| f.as_string()).map(|s| s.to_string()),
+                symbol: entry.get(&55)
+                    .and_then(|f| f.as_string())
+                    .map(|s| s.to_string())
+                    .ok_or(ValidationError::MissingRequiredField { tag: 55 })?,
+                md_entry_px: entry.get(&270)
+                    .and_then(|f| f.as_float())
+                    .ok_or(ValidationError::MissingRequiredField { tag: 270 })?,
+                md_entry_size: entry.get(&271)
+                    .and_then(|f| f.as_int())
+                    .ok_or(ValidationError::MissingRequiredField { tag: 271 })? as u64,
+                rpt_seq: entry.get(&83).and_then(|f| f.as_int()).map(|n| n as u64),
+            });
+        }
+
+        let refresh = MarketDataIncrementalRefresh {
+            header,
+            md_req_id,
+            entries,
+            trailer,
+        };
+
+        refresh.validate()?;
+        Ok(refresh)
+    }
+
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        self.header.validate()?;
+        self.trailer.validate()?;
+        if self.entries.is_empty() {
+            return Err(ValidationError::MissingRequiredField { tag: 268 });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+use crate::fix::parser::{FixField, RepeatingGroup};
+use crate::fix::error::{FixError, ValidationError};
+use crate::fix::messages::{StandardHeader, Trailer, Header};
+use std::collections::HashMap;
+
+/// SubscriptionRequestType (263) values.
+pub const SUBSCRIPTION_SNAPSHOT: char = '0';
+pub const SUBSCRIPTION_SNAPSHOT_AND_UPDATES: char = '1';
+pub const SUBSCRIPTION_UNSUBSCRIBE: char = '2';
+
+/// MDUpdateType (265) values.
+pub const MD_UPDATE_TYPE_FULL_REFRESH: u32 = 0;
+pub const MD_UPDATE_TYPE_INCREMENTAL_REFRESH: u32 = 1;
+
+#[derive(Debug, Clone)]
+pub struct MarketDataRequest {
+    pub header: StandardHeader,
+    pub md_req_id: String,
+    pub subscription_request_type: char,
+    /// Price levels per side wanted, zero for the full book.
+    pub market_depth: u32,
+    /// Required when subscribing to updates.
+    pub md_update_type: Option<u32>,
+    pub md_entry_types: Vec<char>,
+    pub symbols: Vec<String>,
+    pub trailer: Trailer,
+}
+
+impl MarketDataRequest {
+    /// Parses the request from its fields and its NoMDEntryTypes (267) and
+    /// NoRelatedSym (146) groups, which the field map cannot hold.
+    pub fn parse(
+        fields: HashMap<u32, FixField>,
+        md_entry_types: Option<RepeatingGroup>,
+        related_symbols: Option<RepeatingGroup>,
+    ) -> Result<MarketDataRequest, FixError> {
+        let header = Header::parse(&fields)?;
+        let trailer = Trailer::parse(&fields)?;
+
+        let md_req_id = Self::get_required_string(&fields, 262, "MDReqID")?;
+        let subscription_request_type = fields.get(&263)
+            .and_then(|f| f.as_char())
+            .ok_or(ValidationError::MissingRequiredField { tag: 263 })?;
+        let market_depth = fields.get(&264)
+            .and_then(|f| f.as_int())
+            .ok_or(ValidationError::MissingRequiredField { tag: 264 })?;
+        let market_depth = u32::try_from(market_depth).map_err(|_| ValidationError::InvalidFieldValue {
+            tag: 264,
+            value: market_depth.to_string(),
+        })?;
+        let md_update_type = fields.get(&265).and_then(|f| f.as_int()).map(|i| i as u32);
+
+        let md_entry_types = md_entry_types
+            .map(|group| group.instances.iter().filter_map(|entry| entry.get(&269)?.as_char()).collect())
+            .unwrap_or_default();
+        let symbols = related_symbols
+            .map(|group| {
+                group.instances.iter()
+                    .filter_map(|entry| entry.get(&55)?.as_string().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let request = MarketDataRequest {
+            header,
+            md_req_id,
+            subscription_request_type,
+            market_depth,
+            md_update_type,
+            md_entry_types,
+            symbols,
+            trailer,
+        };
+
+        request.validate()?;
+        Ok(request)
+    }
+
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        self.header.validate()?;
+        self.trailer.validate()?;
+
+        if self.md_req_id.is_empty() {
+            return Err(ValidationError::MissingRequiredField { tag: 262 });
+        }
+
+        if self.subscription_request_type == SUBSCRIPTION_SNAPSHOT_AND_UPDATES && self.md_update_type.is_none() {
+            return Err(ValidationError::MissingRequiredField { tag: 265 });
+        }
+
+        if self.md_entry_types.is_empty() {
+            return Err(ValidationError::MissingRequiredField { tag: 267 });
+        }
+
+        if self.symbols.is_empty() {
+            return Err(ValidationError::MissingRequiredField { tag: 146 });
+        }
+
+        Ok(())
+    }
+
+    fn get_required_string(fields: &HashMap<u32, FixField>, tag: u32, _name: &str) -> Result<String, ValidationError> {
+        fields.get(&tag)
+            .and_then(|f| f.as_string())
+            .map(|s| s.to_string())
+            .ok_or(ValidationError::MissingRequiredField { tag })
+    }
+}
@@ -0,0 +1,72 @@
+use crate::fix::parser::{FixField, RepeatingGroup};
+use crate::fix::error::{FixError, ValidationError};
+use crate::fix::messages::{StandardHeader, Trailer, Header};
+use std::collections::HashMap;
+
+/// One price level of a full refresh.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MdEntry {
+    pub md_entry_type: char,
+    pub md_entry_px: f64,
+    pub md_entry_size: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct MarketDataSnapshotFullRefresh {
+    pub header: StandardHeader,
+    /// MDReqID of the request the refresh answers.
+    pub md_req_id: Option<String>,
+    pub symbol: String,
+    /// Bids best first, then offers best first.
+    pub entries: Vec<MdEntry>,
+    pub trailer: Trailer,
+}
+
+impl MarketDataSnapshotFullRefresh {
+    pub fn parse(fields: HashMap<u32, FixField>, md_entries: Option<RepeatingGroup>) -> Result<MarketDataSnapshotFullRefresh, FixError> {
+        let header = Header::parse(&fields)?;
+        let trailer = Trailer::parse(&fields)?;
+
+        let md_req_id = fields.get(&262).and_then(|f| f.as_string()).map(|s| s.to_string());
+        let symbol = fields.get(&55)
+            .and_then(|f| f.as_string())
+            .map(|s| s.to_string())
+            .ok_or(ValidationError::MissingRequiredField { tag: 55 })?;
+        let md_entries = md_entries.ok_or(ValidationError::MissingRequiredField { tag: 268 })?;
+
+        let mut entries = Vec::with_capacity(md_entries.instances.len());
+        for entry in &md_entries.instances {
+            entries.push(MdEntry {
+                md_entry_type: entry.get(&269)
+                    .and_then(|f| f.as_char())
+                    .ok_or(ValidationError::MissingRequiredField { tag: 269 })?,
+                md_entry_px: entry.get(&270)
+                    .and_then(|f| f.as_float())
+                    .ok_or(ValidationError::MissingRequiredField { tag: 270 })?,
+                md_entry_size: entry.get(&271)
+                    .and_then(|f| f.as_int())
+                    .ok_or(ValidationError::MissingRequiredField { tag: 271 })? as u64,
+            });
+        }
+
+        let refresh = MarketDataSnapshotFullRefresh {
+            header,
+            md_req_id,
+            symbol,
+            entries,
+            trailer,
+        };
+
+        refresh.validate()?;
+        Ok(refresh)
+    }
+
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        self.header.validate()?;
+        self.trailer.validate()?;
+        if self.symbol.is_empty() {
+            return Err(ValidationError::MissingRequiredField { tag: 55 });
+        }
+        Ok(())
+    }
+}
@@ -3,16 +3,34 @@ pub mod trailer;
 pub mod new_order_single;
 pub mod execution_report;
 pub mod order_cancel_request;
+pub mod order_status_request;
 pub mod heartbeat;
 pub mod logon;
+pub mod logout;
+pub mod resend_request;
+pub mod sequence_reset;
+pub mod test_request;
+pub mod market_data_request;
+pub mod market_data_snapshot_full_refresh;
+pub mod market_data_incremental_refresh;
+pub mod security_definition_request;
 
 pub use header::{Header, StandardHeader};
 pub use trailer::Trailer;
 pub use new_order_single::NewOrderSingle;
 pub use execution_report::ExecutionReport;
 pub use order_cancel_request::OrderCancelRequest;
+pub use order_status_request::OrderStatusRequest;
 pub use heartbeat::Heartbeat;
 pub use logon::Logon;
+pub use logout::Logout;
+pub use resend_request::ResendRequest;
+pub use sequence_reset::SequenceReset;
+pub use test_request::TestRequest;
+pub use market_data_request::MarketDataRequest;
+pub use market_data_snapshot_full_refresh::{MarketDataSnapshotFullRefresh, MdEntry};
+pub use market_data_incremental_refresh::{MarketDataIncrementalRefresh, MdIncrementalEntry};
+pub use security_definition_request::SecurityDefinitionRequest;
 
 use crate::fix::parser::FixField;
 use crate::fix::error::FixError;
@@ -23,8 +41,17 @@ pub enum FixMessage {
     NewOrderSingle(NewOrderSingle),
     ExecutionReport(ExecutionReport),
     OrderCancelRequest(OrderCancelRequest),
+    OrderStatusRequest(OrderStatusRequest),
     Heartbeat(Heartbeat),
     Logon(Logon),
+    Logout(Logout),
+    ResendRequest(ResendRequest),
+    SequenceReset(SequenceReset),
+    TestRequest(TestRequest),
+    MarketDataRequest(MarketDataRequest),
+    MarketDataSnapshotFullRefresh(MarketDataSnapshotFullRefresh),
+    MarketDataIncrementalRefresh(MarketDataIncrementalRefresh),
+    SecurityDefinitionRequest(SecurityDefinitionRequest),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -76,6 +103,46 @@ pub enum MessageType {
     ListStrikePrice,       
 }
 
+impl FixMessage {
+    pub fn header(&self) -> &StandardHeader {
+        match self {
+            FixMessage::NewOrderSingle(message) => &message.header,
+            FixMessage::ExecutionReport(message) => &message.header,
+            FixMessage::OrderCancelRequest(message) => &message.header,
+            FixMessage::OrderStatusRequest(message) => &message.header,
+            FixMessage::Heartbeat(message) => &message.header,
+            FixMessage::Logon(message) => &message.header,
+            FixMessage::Logout(message) => &message.header,
+            FixMessage::ResendRequest(message) => &message.header,
+            FixMessage::SequenceReset(message) => &message.header,
+            FixMessage::TestRequest(message) => &message.header,
+            FixMessage::MarketDataRequest(message) => &message.header,
+            FixMessage::MarketDataSnapshotFullRefresh(message) => &message.header,
+            FixMessage::MarketDataIncrementalRefresh(message) => &message.header,
+            FixMessage::SecurityDefinitionRequest(message) => &message.header,
+        }
+    }
+
+    pub fn header_mut(&mut self) -> &mut StandardHeader {
+        match self {
+            FixMessage::NewOrderSingle(message) => &mut message.header,
+            FixMessage::ExecutionReport(message) => &mut message.header,
+            FixMessage::OrderCancelRequest(message) => &mut message.header,
+            FixMessage::OrderStatusRequest(message) => &mut message.header,
+            FixMessage::Heartbeat(message) => &mut message.header,
+            FixMessage::Logon(message) => &mut message.header,
+            FixMessage::Logout(message) => &mut message.header,
+            FixMessage::ResendRequest(message) => &mut message.header,
+            FixMessage::SequenceReset(message) => &mut message.header,
+            FixMessage::TestRequest(message) => &mut message.header,
+            FixMessage::MarketDataRequest(message) => &mut message.header,
+            FixMessage::MarketDataSnapshotFullRefresh(message) => &mut message.header,
+            FixMessage::MarketDataIncrementalRefresh(message) => &mut message.header,
+            FixMessage::SecurityDefinitionRequest(message) => &mut message.header,
+        }
+    }
+}
+
 impl MessageType {
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
@@ -12,7 +12,8 @@ pub struct NewOrderSingle {
     pub symbol: String,              
     pub side: char,                  
     pub transact_time: String,       
-    pub order_qty: u32,              
+    /// As sent; `FixOrderConverter` checks it fits an order quantity.
+    pub order_qty: i64,
     pub ord_type: char,              
     pub price: Option<f64>,          
     pub stop_px: Option<f64>,        
@@ -32,7 +33,7 @@ impl NewOrderSingle {
         let symbol = Self::get_required_string(&fields, 55, "Symbol")?;
         let side = Self::get_required_char(&fields, 54, "Side")?;
         let transact_time = Self::get_required_string(&fields, 60, "TransactTime")?;
-        let order_qty = Self::get_required_int(&fields, 38, "OrderQty")? as u32;
+        let order_qty = Self::get_required_int(&fields, 38, "OrderQty")?;
         let ord_type = Self::get_required_char(&fields, 40, "OrdType")?;
         
         let price = Self::get_optional_float(&fields, 44);
@@ -73,14 +74,14 @@ impl NewOrderSingle {
             return Err(ValidationError::MissingRequiredField { tag: 55 });
         }
 
-        if !matches!(self.side, '1' | '2') {
+        if Side::from_char(self.side).is_none() {
             return Err(ValidationError::InvalidFieldValue {
                 tag: 54,
                 value: self.side.to_string(),
             });
         }
 
-        if !matches!(self.ord_type, '1' | '2' | '3' | '4') {
+        if OrdType::from_char(self.ord_type).is_none() {
             return Err(ValidationError::InvalidFieldValue {
                 tag: 40,
                 value: self.ord_type.to_string(),
@@ -195,6 +196,7 @@ pub enum TimeInForce {
     GoodTillCancel,   
     ImmediateOrCancel, 
     FillOrKill,       
+    GoodTillDate,     
 }
 
 impl TimeInForce {
@@ -204,6 +206,7 @@ impl TimeInForce {
             '1' => Some(TimeInForce::GoodTillCancel),
             '3' => Some(TimeInForce::ImmediateOrCancel),
             '4' => Some(TimeInForce::FillOrKill),
+            '6' => Some(TimeInForce::GoodTillDate),
             _ => None,
         }
     }
@@ -214,6 +217,7 @@ impl TimeInForce {
             TimeInForce::GoodTillCancel => '1',
             TimeInForce::ImmediateOrCancel => '3',
             TimeInForce::FillOrKill => '4',
+            TimeInForce::GoodTillDate => '6',
         }
     }
 }
\ No newline at end of file
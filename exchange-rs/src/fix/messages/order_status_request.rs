@@ -0,0 +1,77 @@
+use crate::fix::parser::FixField;
+use crate::fix::error::{FixError, ValidationError};
+use crate::fix::messages::{StandardHeader, Trailer, Header};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct OrderStatusRequest {
+    pub header: StandardHeader,
+    pub order_id: Option<String>,    
+    pub cl_ord_id: String,           
+    pub symbol: String,              
+    pub side: char,                  
+    pub trailer: Trailer,
+}
+
+impl OrderStatusRequest {
+    pub fn parse(fields: HashMap<u32, FixField>) -> Result<OrderStatusRequest, FixError> {
+        let header = Header::parse(&fields)?;
+        let trailer = Trailer::parse(&fields)?;
+
+        let order_id = Self::get_optional_string(&fields, 37);
+        let cl_ord_id = Self::get_required_string(&fields, 11, "ClOrdID")?;
+        let symbol = Self::get_required_string(&fields, 55, "Symbol")?;
+        let side = Self::get_required_char(&fields, 54, "Side")?;
+
+        let status_request = OrderStatusRequest {
+            header,
+            order_id,
+            cl_ord_id,
+            symbol,
+            side,
+            trailer,
+        };
+
+        status_request.validate()?;
+        Ok(status_request)
+    }
+
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        self.header.validate()?;
+        self.trailer.validate()?;
+
+        if self.cl_ord_id.is_empty() {
+            return Err(ValidationError::MissingRequiredField { tag: 11 });
+        }
+
+        if self.symbol.is_empty() {
+            return Err(ValidationError::MissingRequiredField { tag: 55 });
+        }
+
+        if !matches!(self.side, '1' | '2') {
+            return Err(ValidationError::InvalidFieldValue {
+                tag: 54,
+                value: self.side.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn get_required_string(fields: &HashMap<u32, FixField>, tag: u32, _name: &str) -> Result<String, ValidationError> {
+        fields.get(&tag)
+            .and_then(|f| f.as_string())
+            .map(|s| s.to_string())
+            .ok_or(ValidationError::MissingRequiredField { tag })
+    }
+
+    fn get_required_char(fields: &HashMap<u32, FixField>, tag: u32, _name: &str) -> Result<char, ValidationError> {
+        fields.get(&tag)
+            .and_then(|f| f.as_char())
+            .ok_or(ValidationError::MissingRequiredField { tag })
+    }
+
+    fn get_optional_string(fields: &HashMap<u32, FixField>, tag: u32) -> Option<String> {
+        fields.get(&tag).and_then(|f| f.as_string()).map(|s| s.to_string())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,69 @@
+use crate::fix::parser::FixField;
+use crate::fix::error::{FixError, ValidationError};
+use crate::fix::messages::{StandardHeader, Trailer, Header};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct ResendRequest {
+    pub header: StandardHeader,
+    pub begin_seq_no: u32,           
+    pub end_seq_no: u32,             
+    pub trailer: Trailer,
+}
+
+impl ResendRequest {
+    pub fn parse(fields: HashMap<u32, FixField>) -> Result<ResendRequest, FixError> {
+        let header = Header::parse(&fields)?;
+        let trailer = Trailer::parse(&fields)?;
+
+        let begin_seq_no = Self::get_required_int(&fields, 7, "BeginSeqNo")? as u32;
+        let end_seq_no = Self::get_required_int(&fields, 16, "EndSeqNo")? as u32;
+
+        let resend_request = ResendRequest {
+            header,
+            begin_seq_no,
+            end_seq_no,
+            trailer,
+        };
+
+        resend_request.validate()?;
+        Ok(resend_request)
+    }
+
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        self.header.validate()?;
+        self.trailer.validate()?;
+
+        if self.begin_seq_no == 0 {
+            return Err(ValidationError::InvalidFieldValue {
+                tag: 7,
+                value: self.begin_seq_no.to_string(),
+            });
+        }
+
+        if self.end_seq_no != 0 && self.end_seq_no < self.begin_seq_no {
+            return Err(ValidationError::InvalidFieldValue {
+                tag: 16,
+                value: self.end_seq_no.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// The last requested sequence number; EndSeqNo 0 means "everything
+    /// sent so far".
+    pub fn last_seq_no(&self, last_sent: u32) -> u32 {
+        if self.end_seq_no == 0 {
+            last_sent
+        } else {
+            self.end_seq_no.min(last_sent)
+        }
+    }
+
+    fn get_required_int(fields: &HashMap<u32, FixField>, tag: u32, _name: &str) -> Result<i64, ValidationError> {
+        fields.get(&tag)
+            .and_then(|f| f.as_int())
+            .ok_or(ValidationError::MissingRequiredField { tag })
+    }
+}
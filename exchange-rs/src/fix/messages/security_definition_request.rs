@@ -0,0 +1,57 @@
+use crate::fix::parser::FixField;
+use crate::fix::error::{FixError, ValidationError};
+use crate::fix::messages::{StandardHeader, Trailer, Header};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct SecurityDefinitionRequest {
+    pub header: StandardHeader,
+    pub security_req_id: String,     
+    pub security_request_type: i64,  
+    /// The instrument asked about.
+    pub symbol: String,
+    pub trailer: Trailer,
+}
+
+impl SecurityDefinitionRequest {
+    pub fn parse(fields: HashMap<u32, FixField>) -> Result<SecurityDefinitionRequest, FixError> {
+        let header = Header::parse(&fields)?;
+        let trailer = Trailer::parse(&fields)?;
+
+        let security_req_id = Self::get_required_string(&fields, 320, "SecurityReqID")?;
+        let security_request_type = fields.get(&321)
+            .and_then(|f| f.as_int())
+            .ok_or(ValidationError::MissingRequiredField { tag: 321 })?;
+        let symbol = Self::get_required_string(&fields, 55, "Symbol")?;
+
+        let request = SecurityDefinitionRequest {
+            header,
+            security_req_id,
+            security_request_type,
+            symbol,
+            trailer,
+        };
+
+        request.validate()?;
+        Ok(request)
+    }
+
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        self.header.validate()?;
+        self.trailer.validate()?;
+        if self.security_req_id.is_empty() {
+            return Err(ValidationError::MissingRequiredField { tag: 320 });
+        }
+        if self.symbol.is_empty() {
+            return Err(ValidationError::MissingRequiredField { tag: 55 });
+        }
+        Ok(())
+    }
+
+    fn get_required_string(fields: &HashMap<u32, FixField>, tag: u32, _name: &str) -> Result<String, ValidationError> {
+        fields.get(&tag)
+            .and_then(|f| f.as_string())
+            .map(|s| s.to_string())
+            .ok_or(ValidationError::MissingRequiredField { tag })
+    }
+}
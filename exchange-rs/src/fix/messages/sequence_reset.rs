@@ -0,0 +1,54 @@
+use crate::fix::parser::FixField;
+use crate::fix::error::{FixError, ValidationError};
+use crate::fix::messages::{StandardHeader, Trailer, Header};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct SequenceReset {
+    pub header: StandardHeader,
+    pub gap_fill_flag: Option<bool>, 
+    pub new_seq_no: u32,             
+    pub trailer: Trailer,
+}
+
+impl SequenceReset {
+    pub fn parse(fields: HashMap<u32, FixField>) -> Result<SequenceReset, FixError> {
+        let header = Header::parse(&fields)?;
+        let trailer = Trailer::parse(&fields)?;
+
+        let gap_fill_flag = fields.get(&123).and_then(|f| f.as_bool());
+        let new_seq_no = fields.get(&36)
+            .and_then(|f| f.as_int())
+            .ok_or(ValidationError::MissingRequiredField { tag: 36 })? as u32;
+
+        let sequence_reset = SequenceReset {
+            header,
+            gap_fill_flag,
+            new_seq_no,
+            trailer,
+        };
+
+        sequence_reset.validate()?;
+        Ok(sequence_reset)
+    }
+
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        self.header.validate()?;
+        self.trailer.validate()?;
+
+        if self.new_seq_no == 0 {
+            return Err(ValidationError::InvalidFieldValue {
+                tag: 36,
+                value: self.new_seq_no.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// GapFill mode: the reset covers skipped admin messages and is itself
+    /// sequenced, rather than resetting the session.
+    pub fn is_gap_fill(&self) -> bool {
+        self.gap_fill_flag == Some(true)
+    }
+}
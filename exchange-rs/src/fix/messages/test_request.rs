@@ -0,0 +1,45 @@
+use crate::fix::parser::FixField;
+use crate::fix::error::{FixError, ValidationError};
+use crate::fix::messages::{StandardHeader, Trailer, Header};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct TestRequest {
+    pub header: StandardHeader,
+    pub test_req_id: String,         
+    pub trailer: Trailer,
+}
+
+impl TestRequest {
+    pub fn parse(fields: HashMap<u32, FixField>) -> Result<TestRequest, FixError> {
+        let header = Header::parse(&fields)?;
+        let trailer = Trailer::parse(&fields)?;
+
+        let test_req_id = Self::get_required_string(&fields, 112, "TestReqID")?;
+
+        let test_request = TestRequest {
+            header,
+            test_req_id,
+            trailer,
+        };
+
+        test_request.validate()?;
+        Ok(test_request)
+    }
+
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        self.header.validate()?;
+        self.trailer.validate()?;
+        if self.test_req_id.is_empty() {
+            return Err(ValidationError::MissingRequiredField { tag: 112 });
+        }
+        Ok(())
+    }
+
+    fn get_required_string(fields: &HashMap<u32, FixField>, tag: u32, _name: &str) -> Result<String, ValidationError> {
+        fields.get(&tag)
+            .and_then(|f| f.as_string())
+            .map(|s| s.to_string())
+            .ok_or(ValidationError::MissingRequiredField { tag })
+    }
+}
@@ -3,7 +3,11 @@ pub mod messages;
 pub mod session;
 pub mod validation;
 pub mod bridge;
+pub mod mapping;
+pub mod market_data;
 pub mod error;
+pub mod encoder;
+pub mod conformance;
 
 pub use error::{FixError, ParseError, ValidationError, SessionError, BusinessError};
 pub use parser::FixParser;
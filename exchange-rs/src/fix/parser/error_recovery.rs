@@ -3,6 +3,14 @@ use crate::fix::parser::{FixParser, FixField};
 use crate::fix::messages::{FixMessage, MessageType};
 use std::collections::HashMap;
 
+/// Standard header tags in the order the FIX specification lists them.
+const HEADER_TAGS: [u32; 22] = [
+    8, 9, 35, 49, 56, 115, 128, 90, 91, 50, 142, 57, 143, 116, 144, 129, 145, 34, 43, 97, 52, 122,
+];
+
+/// Standard trailer tags; CheckSum always comes last.
+const TRAILER_TAGS: [u32; 3] = [93, 89, 10];
+
 #[derive(Debug, Clone)]
 pub struct ErrorRecovery {
     pub allow_partial_parse: bool,
@@ -317,10 +325,49 @@ impl RecoveringParser {
         }
     }
     
+    /// Rebuilds the message with the standard header first in spec order,
+    /// then the body fields in their original order, then the trailer. Body
+    /// length and checksum are recomputed for the new layout.
     fn reorder_fields(&self, data: &[u8]) -> Option<Vec<u8>> {
+        let raw_fields = self.base_parser.raw_parser.parse(data).ok()?;
+        let mut fields = Vec::with_capacity(raw_fields.len());
+        for raw_field in &raw_fields {
+            let tag: u32 = std::str::from_utf8(raw_field.tag).ok()?.parse().ok()?;
+            fields.push((tag, raw_field.value));
+        }
+        
+        fields.sort_by_key(|(tag, _)| {
+            if let Some(position) = HEADER_TAGS.iter().position(|header| header == tag) {
+                (0, position)
+            } else if let Some(position) = TRAILER_TAGS.iter().position(|trailer| trailer == tag) {
+                (2, position)
+            } else {
+                (1, 0)
+            }
+        });
         
+        let begin_string = fields.iter().find(|(tag, _)| *tag == 8)?.1;
+        let mut body = Vec::new();
+        for (tag, value) in fields.iter().filter(|(tag, _)| ![8, 9, 10].contains(tag)) {
+            body.extend_from_slice(tag.to_string().as_bytes());
+            body.push(b'=');
+            body.extend_from_slice(value);
+            body.push(0x01);
+        }
         
-        None
+        let mut reordered = Vec::with_capacity(data.len());
+        reordered.extend_from_slice(b"8=");
+        reordered.extend_from_slice(begin_string);
+        reordered.push(0x01);
+        reordered.extend_from_slice(format!("9={}", body.len()).as_bytes());
+        reordered.push(0x01);
+        reordered.extend_from_slice(&body);
+        
+        let checksum = reordered.iter().fold(0u8, |acc, &byte| acc.wrapping_add(byte));
+        reordered.extend_from_slice(format!("10={:03}", checksum).as_bytes());
+        reordered.push(0x01);
+        
+        Some(reordered)
     }
     
     fn parse_with_relaxed_validation(&mut self, data: &[u8], result: &mut RecoveryResult) {
@@ -391,4 +438,43 @@ mod tests {
         
         assert_eq!(parser.recovery_config.max_recovery_attempts, 3);
     }
+    
+    fn message(fields: &[(u32, &str)]) -> Vec<u8> {
+        let mut body = String::new();
+        for (tag, value) in fields {
+            body.push_str(&format!("{}={}\x01", tag, value));
+        }
+        let mut message = format!("8=FIX.4.4\x019={}\x01{}", body.len(), body);
+        let checksum = message.bytes().fold(0u8, |acc, byte| acc.wrapping_add(byte));
+        message.push_str(&format!("10={:03}\x01", checksum));
+        message.into_bytes()
+    }
+    
+    #[test]
+    fn test_reorders_msg_type_after_body_field() {
+        let data = message(&[
+            (49, "CLIENT"),
+            (35, "0"),
+            (56, "EXCHANGE"),
+            (34, "2"),
+            (52, "20240101-12:00:00"),
+        ]);
+        
+        let mut base_parser = FixParser::new();
+        assert!(matches!(
+            base_parser.parse(&data),
+            Err(FixError::Validation(ValidationError::FieldOrderingViolation { tag: 35, after_tag: 49 }))
+        ));
+        
+        let mut parser = RecoveringParser::new(ErrorRecovery::default());
+        let reordered = parser.reorder_fields(&data).unwrap();
+        assert_eq!(
+            reordered,
+            message(&[(35, "0"), (49, "CLIENT"), (56, "EXCHANGE"), (34, "2"), (52, "20240101-12:00:00")])
+        );
+        
+        let result = parser.parse_with_recovery(&data);
+        assert!(matches!(result.message, Some(FixMessage::Heartbeat(_))));
+        assert_eq!(result.recovery_attempts, 1);
+    }
 }
\ No newline at end of file
@@ -65,8 +65,10 @@ impl FieldParser {
                 Ok(FieldValue::Int(int_val))
             },
             FieldType::Float => {
-                let float_val = value_str.parse::<f64>()
-                    .map_err(|_| ParseError::InvalidFieldValue {
+                let float_val = Some(value_str)
+                    .filter(|value| self.is_decimal(value))
+                    .and_then(|value| value.parse::<f64>().ok())
+                    .ok_or_else(|| ParseError::InvalidFieldValue {
                         tag,
                         value: value_str.to_string(),
                     })?;
@@ -132,23 +134,25 @@ impl FieldParser {
     fn get_field_type(&self, tag: u32) -> FieldType {
         match tag {
             
-            8 | 35 | 49 | 56 | 11 | 55 | 1 | 15 | 22 | 48 | 57 | 142 | 37 | 17 | 20 | 39 => FieldType::String,
+            8 | 35 | 49 | 56 | 11 | 55 | 1 | 15 | 22 | 48 | 57 | 142 | 37 | 17 | 20 | 39 | 278 => FieldType::String,
+            
+            7 | 9 | 10 | 34 | 38 | 90 | 95 | 96 | 103 | 36 | 151 | 14 | 6 | 16 | 45 | 108 | 453 => FieldType::Int,
             
-            9 | 34 | 38 | 90 | 95 | 96 | 123 | 36 | 151 | 14 | 6 | 16 | 453 => FieldType::Int,
+            83 | 146 | 264 | 265 | 267 | 268 | 271 | 321 | 323 => FieldType::Int,
             
-            44 | 31 | 32 | 99 | 423 | 424 => FieldType::Float,
+            44 | 31 | 32 | 99 | 270 | 423 | 424 => FieldType::Float,
             
-            40 | 54 | 21 | 59 | 18 | 98 | 103 | 114 | 139 | 47 => FieldType::Char,
+            40 | 54 | 21 | 59 | 18 | 98 | 114 | 139 | 47 | 263 | 269 | 279 | 281 => FieldType::Char,
             
-            43 | 97 | 141 | 89 => FieldType::Bool,
+            43 | 97 | 123 | 141 | 89 => FieldType::Bool,
             
             91 | 212 | 213 => FieldType::Data,
             
-            52 | 60 | 122 | 273 => FieldType::UTCTimestamp,
+            52 | 60 | 122 => FieldType::UTCTimestamp,
             
-            64 | 126 => FieldType::UTCDateOnly,
+            64 | 126 | 272 => FieldType::UTCDateOnly,
             
-            271 => FieldType::UTCTimeOnly,
+            273 => FieldType::UTCTimeOnly,
             
             _ => FieldType::String,
         }
@@ -192,6 +196,17 @@ impl FieldParser {
         false
     }
     
+    /// FIX float syntax: an optional minus sign and digits with at most one
+    /// decimal point. Exponents, `inf` and `NaN`, which `f64` parsing
+    /// accepts, are not FIX values.
+    fn is_decimal(&self, value: &str) -> bool {
+        let digits = value.strip_prefix('-').unwrap_or(value);
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        !(whole.is_empty() && fraction.is_empty())
+            && whole.bytes().all(|byte| byte.is_ascii_digit())
+            && fraction.bytes().all(|byte| byte.is_ascii_digit())
+    }
+
     fn validate_utc_time_only(&self, value: &str) -> bool {
         
         if value.len() < 8 || value.len() > 12 {
@@ -266,9 +281,14 @@ enum FieldType {
 }
 
 impl FixField {
+    /// Textual value of the field. Timestamps, dates and times are typed but
+    /// still read as their wire text.
     pub fn as_string(&self) -> Option<&str> {
         match &self.value {
-            FieldValue::String(s) => Some(s),
+            FieldValue::String(s)
+            | FieldValue::UTCTimestamp(s)
+            | FieldValue::UTCDateOnly(s)
+            | FieldValue::UTCTimeOnly(s) => Some(s),
             _ => None,
         }
     }
@@ -198,6 +198,28 @@ impl GroupDefinitions {
         delimiter_tag: 269, 
         fields: &[269, 270, 15, 271, 272, 273, 274, 275, 336, 625], 
     };
+    
+    /// MDEntries of a MarketDataIncrementalRefresh, which open with
+    /// MDUpdateAction.
+    pub const MD_INCREMENTAL_ENTRIES_GROUP: GroupDef = GroupDef {
+        count_tag: 268,
+        delimiter_tag: 279,
+        fields: &[279, 269, 278, 55, 270, 271, 83],
+    };
+    
+    /// MDEntryTypes a MarketDataRequest asks for.
+    pub const MD_ENTRY_TYPES_GROUP: GroupDef = GroupDef {
+        count_tag: 267,
+        delimiter_tag: 269,
+        fields: &[269],
+    };
+    
+    /// Symbols a MarketDataRequest asks for.
+    pub const RELATED_SYM_GROUP: GroupDef = GroupDef {
+        count_tag: 146,
+        delimiter_tag: 55,
+        fields: &[55],
+    };
 }
 
 #[derive(Debug, Clone)]
@@ -2,7 +2,8 @@ use crate::fix::parser::FixField;
 use crate::fix::error::{FixError, ValidationError};
 use crate::fix::messages::{
     FixMessage, MessageType, NewOrderSingle, ExecutionReport, 
-    OrderCancelRequest, Heartbeat, Logon
+    OrderCancelRequest, Heartbeat, Logon, Logout, ResendRequest, SequenceReset, TestRequest,
+    SecurityDefinitionRequest, OrderStatusRequest
 };
 use std::collections::HashMap;
 
@@ -41,6 +42,10 @@ impl MessageBuilder {
                 let cancel_request = OrderCancelRequest::parse(fields)?;
                 Ok(FixMessage::OrderCancelRequest(cancel_request))
             }
+            MessageType::OrderStatusRequest => {
+                let status_request = OrderStatusRequest::parse(fields)?;
+                Ok(FixMessage::OrderStatusRequest(status_request))
+            }
             MessageType::Heartbeat => {
                 let heartbeat = Heartbeat::parse(fields)?;
                 Ok(FixMessage::Heartbeat(heartbeat))
@@ -49,6 +54,26 @@ impl MessageBuilder {
                 let logon = Logon::parse(fields)?;
                 Ok(FixMessage::Logon(logon))
             }
+            MessageType::Logout => {
+                let logout = Logout::parse(fields)?;
+                Ok(FixMessage::Logout(logout))
+            }
+            MessageType::TestRequest => {
+                let test_request = TestRequest::parse(fields)?;
+                Ok(FixMessage::TestRequest(test_request))
+            }
+            MessageType::ResendRequest => {
+                let resend_request = ResendRequest::parse(fields)?;
+                Ok(FixMessage::ResendRequest(resend_request))
+            }
+            MessageType::SequenceReset => {
+                let sequence_reset = SequenceReset::parse(fields)?;
+                Ok(FixMessage::SequenceReset(sequence_reset))
+            }
+            MessageType::SecurityDefinitionRequest => {
+                let request = SecurityDefinitionRequest::parse(fields)?;
+                Ok(FixMessage::SecurityDefinitionRequest(request))
+            }
             _ => Err(FixError::Validation(ValidationError::InvalidMessageType {
                 msg_type: msg_type_str.to_string(),
             }))
@@ -12,8 +12,10 @@ pub use group_parser::{GroupParser, RepeatingGroup, GroupDefinitions};
 pub use advanced_parser::{AdvancedFixParser, ParsedMessage, ParsingMetadata, SessionInfo};
 pub use error_recovery::{RecoveringParser, ErrorRecovery, RecoveryResult};
 
-use crate::fix::error::{FixError, ParseError};
-use crate::fix::messages::FixMessage;
+use crate::fix::error::{FixError, ParseError, ValidationError};
+use crate::fix::messages::{
+    FixMessage, MarketDataIncrementalRefresh, MarketDataRequest, MarketDataSnapshotFullRefresh,
+};
 use std::collections::HashMap;
 
 pub struct FixParser {
@@ -38,20 +40,36 @@ impl FixParser {
         self.raw_parser.validate_checksum(data)?;
         
         
-        self.raw_parser.validate_body_length(data)?;
+        let raw_fields = self.raw_parser.parse(data)?;
+        Self::validate_field_order(&raw_fields)?;
         
         
-        let raw_fields = self.raw_parser.parse(data)?;
+        self.raw_parser.validate_body_length(data)?;
         
         
         let mut fields = HashMap::new();
-        for raw_field in raw_fields {
-            let field = self.field_parser.parse_field(raw_field)?;
+        for raw_field in &raw_fields {
+            let field = self.field_parser.parse_field(raw_field.clone())?;
             fields.insert(field.tag, field);
         }
         
         
-        let message = self.message_builder.build_message(fields)?;
+        let message = match fields.get(&35).and_then(|field| field.as_string()) {
+            Some("V") => FixMessage::MarketDataRequest(MarketDataRequest::parse(
+                fields,
+                self.group(&raw_fields, &GroupDefinitions::MD_ENTRY_TYPES_GROUP)?,
+                self.group(&raw_fields, &GroupDefinitions::RELATED_SYM_GROUP)?,
+            )?),
+            Some("W") => FixMessage::MarketDataSnapshotFullRefresh(MarketDataSnapshotFullRefresh::parse(
+                fields,
+                self.group(&raw_fields, &GroupDefinitions::MD_ENTRIES_GROUP)?,
+            )?),
+            Some("X") => FixMessage::MarketDataIncrementalRefresh(MarketDataIncrementalRefresh::parse(
+                fields,
+                self.group(&raw_fields, &GroupDefinitions::MD_INCREMENTAL_ENTRIES_GROUP)?,
+            )?),
+            _ => self.message_builder.build_message(fields)?,
+        };
         
         
         self.validate_message(&message)?;
@@ -59,6 +77,52 @@ impl FixParser {
         Ok(message)
     }
 
+    /// BeginString, BodyLength and MsgType must open the message in that
+    /// order and CheckSum must close it. Body fields may come in any order.
+    fn validate_field_order(raw_fields: &[raw_parser::RawField<'_>]) -> Result<(), ValidationError> {
+        let tags: Vec<u32> = raw_fields
+            .iter()
+            .map(|field| std::str::from_utf8(field.tag).ok().and_then(|tag| tag.parse().ok()).unwrap_or(0))
+            .collect();
+        
+        for (position, expected) in [8, 9, 35].into_iter().enumerate() {
+            if let Some(found) = tags.iter().position(|tag| *tag == expected) {
+                if found != position {
+                    return Err(ValidationError::FieldOrderingViolation {
+                        tag: expected,
+                        after_tag: tags[found - 1],
+                    });
+                }
+            }
+        }
+        
+        if let Some(found) = tags.iter().position(|tag| *tag == 10) {
+            if found + 1 != tags.len() {
+                return Err(ValidationError::FieldOrderingViolation {
+                    tag: tags[tags.len() - 1],
+                    after_tag: 10,
+                });
+            }
+        }
+        
+        Ok(())
+    }
+
+    /// Parses the `definition` group of a message, for the messages whose
+    /// groups repeat tags the field map keeps only once.
+    fn group(
+        &self,
+        raw_fields: &[raw_parser::RawField<'_>],
+        definition: &group_parser::GroupDef,
+    ) -> Result<Option<RepeatingGroup>, FixError> {
+        self.group_parser.parse_repeating_group(
+            raw_fields,
+            definition.count_tag,
+            definition.delimiter_tag,
+            definition.fields,
+        )
+    }
+
     pub fn validate_checksum(&self, data: &[u8]) -> Result<(), ParseError> {
         self.raw_parser.validate_checksum(data)
     }
@@ -72,8 +136,17 @@ impl FixParser {
             FixMessage::NewOrderSingle(order) => Ok(order.validate()?),
             FixMessage::ExecutionReport(exec) => Ok(exec.validate()?),
             FixMessage::OrderCancelRequest(cancel) => Ok(cancel.validate()?),
+            FixMessage::OrderStatusRequest(request) => Ok(request.validate()?),
             FixMessage::Heartbeat(hb) => Ok(hb.validate()?),
             FixMessage::Logon(logon) => Ok(logon.validate()?),
+            FixMessage::Logout(logout) => Ok(logout.validate()?),
+            FixMessage::ResendRequest(resend) => Ok(resend.validate()?),
+            FixMessage::SequenceReset(reset) => Ok(reset.validate()?),
+            FixMessage::TestRequest(request) => Ok(request.validate()?),
+            FixMessage::MarketDataRequest(request) => Ok(request.validate()?),
+            FixMessage::MarketDataSnapshotFullRefresh(refresh) => Ok(refresh.validate()?),
+            FixMessage::MarketDataIncrementalRefresh(refresh) => Ok(refresh.validate()?),
+            FixMessage::SecurityDefinitionRequest(request) => Ok(request.validate()?),
         }
     }
     
@@ -85,7 +158,7 @@ impl FixParser {
         for raw_field in raw_fields {
             let field = self.field_parser.parse_field(raw_field)?;
             match field.tag {
-                8 | 9 | 35 | 49 | 56 | 34 | 52 | 43 | 97 | 90 | 91 => {
+                8 | 9 | 35 | 49 | 56 | 34 | 52 | 43 | 97 | 122 | 90 | 91 => {
                     fields.insert(field.tag, field);
                 }
                 _ => break, 
@@ -1,5 +1,6 @@
 use crate::fix::error::FixError;
-use crate::fix::messages::FixMessage;
+use crate::fix::messages::{FixMessage, MessageType, SequenceReset, Trailer};
+use crate::fix::session::FixSessionState;
 use std::collections::HashMap;
 
 pub struct MessageStore {
@@ -45,6 +46,68 @@ impl MessageStore {
         messages
     }
 
+    /// Stored outgoing messages in `begin_seq..=end_seq`, in sequence order.
+    pub fn get_range(&self, begin_seq: u32, end_seq: u32) -> Vec<FixMessage> {
+        self.get_outgoing_messages_from(begin_seq, end_seq)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    /// The messages answering a ResendRequest for `begin_seq..=end_seq`.
+    /// Application messages go out again with PossDupFlag and their original
+    /// SendingTime in OrigSendingTime; each run of admin messages or of
+    /// sequence numbers no longer stored becomes one SequenceReset-GapFill.
+    pub fn resend_response(&self, session_state: &FixSessionState, begin_seq: u32, end_seq: u32) -> Vec<FixMessage> {
+        let mut response = Vec::new();
+        let mut gap_start = None;
+
+        for seq_num in begin_seq..=end_seq {
+            match self.outgoing_messages.get(&seq_num).filter(|message| Self::is_resendable(message)) {
+                Some(message) => {
+                    if let Some(start) = gap_start.take() {
+                        response.push(Self::gap_fill(session_state, start, seq_num));
+                    }
+
+                    let mut message = message.clone();
+                    let header = message.header_mut();
+                    header.poss_dup_flag = Some(true);
+                    header.orig_sending_time = Some(header.sending_time.clone());
+                    header.sending_time = session_state.create_header(header.msg_type.clone()).sending_time;
+                    response.push(message);
+                }
+                None => {
+                    gap_start.get_or_insert(seq_num);
+                }
+            }
+        }
+
+        if let Some(start) = gap_start {
+            response.push(Self::gap_fill(session_state, start, end_seq + 1));
+        }
+        response
+    }
+
+    fn is_resendable(message: &FixMessage) -> bool {
+        matches!(
+            message,
+            FixMessage::NewOrderSingle(_) | FixMessage::ExecutionReport(_) | FixMessage::OrderCancelRequest(_)
+        )
+    }
+
+    fn gap_fill(session_state: &FixSessionState, msg_seq_num: u32, new_seq_no: u32) -> FixMessage {
+        let mut header = session_state.create_header(MessageType::SequenceReset);
+        header.msg_seq_num = msg_seq_num;
+        header.poss_dup_flag = Some(true);
+
+        FixMessage::SequenceReset(SequenceReset {
+            header,
+            gap_fill_flag: Some(true),
+            new_seq_no,
+            trailer: Trailer { checksum: 0 },
+        })
+    }
+
     pub fn clear_old_messages(&mut self, keep_last_n: usize) {
         if self.outgoing_messages.len() > keep_last_n {
             let mut seq_nums: Vec<u32> = self.outgoing_messages.keys().cloned().collect();
@@ -72,8 +135,17 @@ impl MessageStore {
             FixMessage::NewOrderSingle(order) => Ok(order.header.msg_seq_num),
             FixMessage::ExecutionReport(report) => Ok(report.header.msg_seq_num),
             FixMessage::OrderCancelRequest(cancel) => Ok(cancel.header.msg_seq_num),
+            FixMessage::OrderStatusRequest(request) => Ok(request.header.msg_seq_num),
             FixMessage::Heartbeat(heartbeat) => Ok(heartbeat.header.msg_seq_num),
             FixMessage::Logon(logon) => Ok(logon.header.msg_seq_num),
+            FixMessage::Logout(logout) => Ok(logout.header.msg_seq_num),
+            FixMessage::ResendRequest(resend) => Ok(resend.header.msg_seq_num),
+            FixMessage::SequenceReset(reset) => Ok(reset.header.msg_seq_num),
+            FixMessage::TestRequest(request) => Ok(request.header.msg_seq_num),
+            FixMessage::MarketDataRequest(request) => Ok(request.header.msg_seq_num),
+            FixMessage::MarketDataSnapshotFullRefresh(refresh) => Ok(refresh.header.msg_seq_num),
+            FixMessage::MarketDataIncrementalRefresh(refresh) => Ok(refresh.header.msg_seq_num),
+            FixMessage::SecurityDefinitionRequest(request) => Ok(request.header.msg_seq_num),
         }
     }
 }
@@ -1,17 +1,23 @@
 pub mod connection;
 pub mod session_state;
 pub mod message_store;
+pub mod skew;
 
 pub use connection::FixConnection;
-pub use session_state::{FixSessionState, SessionStatus};
+pub use session_state::{FixSessionState, SequenceCheck, SessionStatus};
 pub use message_store::MessageStore;
+pub use skew::SkewTracker;
 
 use crate::fix::error::{FixError, SessionError};
 use crate::fix::parser::FixParser;
-use crate::fix::messages::{FixMessage, MessageType, Heartbeat, Logon};
+use crate::fix::messages::{FixMessage, MessageType, Heartbeat, Logon, Logout, ResendRequest, TestRequest};
 use crate::fix::bridge::FixOrderBridge;
-use std::time::{Duration, Instant};
-use tokio::time::sleep;
+use crate::clock::system_clock;
+use crate::timers::{FiredTimer, TimerEvent, TimerHandle, TimerTarget, Timers};
+use crossbeam::channel::Receiver;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::time::timeout;
 use tracing::{info, warn, error};
 
 pub struct FixSession {
@@ -20,21 +26,110 @@ pub struct FixSession {
     bridge: FixOrderBridge,
     connection: Option<FixConnection>,
     message_store: MessageStore,
-    last_heartbeat: Instant,
+    /// Messages received ahead of a sequence gap, held until it is filled.
+    queued_messages: BTreeMap<u32, FixMessage>,
+    /// Range of the outstanding ResendRequest, if recovering.
+    resend_range: Option<(u32, u32)>,
+    /// Clock reading of the last inbound message or outbound Heartbeat.
+    last_heartbeat: i64,
     heartbeat_interval: Duration,
+    /// TestReqID of the outstanding TestRequest and the timer that times
+    /// the session out if it goes unanswered.
+    pending_test_request: Option<(String, TimerHandle)>,
+    test_requests_sent: u64,
+    timers: Timers,
+    timer_events: Receiver<Vec<FiredTimer>>,
+    /// `SENDER->TARGET`, the session's key on the timer wheel.
+    timer_key: String,
+    /// Fires once the heartbeat interval may have passed without inbound
+    /// messages.
+    probe_timer: Option<TimerHandle>,
+    /// How long `shutdown` waits for the counterparty to confirm a Logout.
+    logout_timeout: Duration,
 }
 
 impl FixSession {
     pub fn new(sender_comp_id: String, target_comp_id: String) -> Self {
-        Self {
+        let timers = Timers::new(system_clock());
+        let timer_key = format!("{}->{}", sender_comp_id, target_comp_id);
+        let timer_events = timers.subscribe(TimerTarget::Session(timer_key.clone()));
+        let mut session = Self {
             session_state: FixSessionState::new(sender_comp_id, target_comp_id),
             parser: FixParser::new(),
             bridge: FixOrderBridge::new(),
             connection: None,
             message_store: MessageStore::new(),
-            last_heartbeat: Instant::now(),
+            queued_messages: BTreeMap::new(),
+            resend_range: None,
+            last_heartbeat: timers.clock().now_nanos(),
             heartbeat_interval: Duration::from_secs(30),
+            pending_test_request: None,
+            test_requests_sent: 0,
+            timers,
+            timer_events,
+            timer_key,
+            probe_timer: None,
+            logout_timeout: Duration::from_secs(10),
+        };
+        session.arm_probe();
+        session
+    }
+
+    pub fn set_heartbeat_interval(&mut self, heartbeat_interval: Duration) {
+        self.heartbeat_interval = heartbeat_interval;
+        if self.pending_test_request.is_none() {
+            self.arm_probe();
+        }
+    }
+
+    /// Moves the session's heartbeat timers onto a shared wheel and its
+    /// clock. Sessions sharing a wheel need distinct comp ids.
+    pub fn set_timers(&mut self, timers: Timers) {
+        if let Some(handle) = self.probe_timer.take() {
+            self.timers.cancel(handle);
+        }
+        if let Some((_, handle)) = &self.pending_test_request {
+            self.timers.cancel(*handle);
+        }
+
+        self.timer_events = timers.subscribe(TimerTarget::Session(self.timer_key.clone()));
+        self.timers = timers;
+        self.last_heartbeat = self.now();
+        match self.pending_test_request.take() {
+            Some((test_req_id, _)) => {
+                let timeout = self.schedule_timeout();
+                self.pending_test_request = Some((test_req_id, timeout));
+            }
+            None => self.arm_probe(),
+        }
+    }
+
+    fn now(&self) -> i64 {
+        self.timers.clock().now_nanos()
+    }
+
+    /// Arms the probe for one heartbeat interval after the last inbound
+    /// message, replacing any probe already armed.
+    fn arm_probe(&mut self) {
+        if let Some(handle) = self.probe_timer.take() {
+            self.timers.cancel(handle);
         }
+        let deadline = self.last_heartbeat + self.heartbeat_interval.as_nanos() as i64;
+        let event = TimerEvent::TestRequestDue {
+            session: self.timer_key.clone(),
+        };
+        self.probe_timer = Some(self.timers.schedule(deadline, event));
+    }
+
+    fn schedule_timeout(&self) -> TimerHandle {
+        let event = TimerEvent::TestRequestTimeout {
+            session: self.timer_key.clone(),
+        };
+        self.timers.schedule_after(self.heartbeat_interval, event)
+    }
+
+    pub fn set_logout_timeout(&mut self, logout_timeout: Duration) {
+        self.logout_timeout = logout_timeout;
     }
 
     pub async fn start(&mut self, address: &str) -> Result<(), FixError> {
@@ -51,73 +146,333 @@ impl FixSession {
         Ok(())
     }
 
-    pub async fn process_incoming_message(&mut self, data: &[u8]) -> Result<Option<FixMessage>, FixError> {
+    /// Processes one inbound message and returns the application messages
+    /// that are now in sequence: none while a gap is outstanding, and any
+    /// queued messages once it is filled.
+    pub async fn process_incoming_message(&mut self, data: &[u8]) -> Result<Vec<FixMessage>, FixError> {
         self.parser.validate_checksum(data)?;
         
         let message = self.parser.parse(data)?;
-        
-        self.session_state.increment_incoming_seq_num();
-        self.last_heartbeat = Instant::now();
-        
+        self.last_heartbeat = self.now();
+
         match &message {
-            FixMessage::Heartbeat(heartbeat) => {
-                self.handle_heartbeat(heartbeat).await?;
-                Ok(None)
+            FixMessage::ResendRequest(request) => self.handle_resend_request(request).await?,
+            FixMessage::SequenceReset(reset) if !reset.is_gap_fill() => {
+                self.handle_sequence_reset(reset.new_seq_no);
+                let mut ready = Vec::new();
+                self.release_queued_messages(&mut ready).await?;
+                return Ok(ready);
+            }
+            _ => {}
+        }
+
+        let msg_seq_num = message.header().msg_seq_num;
+        match self.session_state.check_incoming_seq_num(message.header()) {
+            SequenceCheck::InSequence => {}
+            SequenceCheck::Duplicate => return Ok(Vec::new()),
+            SequenceCheck::TooLow { expected } => {
+                error!("MsgSeqNum {} lower than expected {}, logging out", msg_seq_num, expected);
+                self.session_state.set_status(SessionStatus::LoggedOut);
+                return Err(SessionError::InvalidSequenceNumber { expected, actual: msg_seq_num }.into());
+            }
+            SequenceCheck::Gap { begin, end } => {
+                warn!("Sequence gap: expected {}, got {}", begin, msg_seq_num);
+                self.queued_messages.insert(msg_seq_num, message);
+                if self.resend_range.is_none() {
+                    self.send_resend_request(begin, end).await?;
+                }
+                return Ok(Vec::new());
+            }
+        }
+
+        let mut ready = Vec::new();
+        self.accept_message(message, &mut ready).await?;
+        self.release_queued_messages(&mut ready).await?;
+        Ok(ready)
+    }
+
+    /// Processes queued messages that are now in sequence and requests any
+    /// gap still left in front of the queue.
+    async fn release_queued_messages(&mut self, ready: &mut Vec<FixMessage>) -> Result<(), FixError> {
+        while let Some(message) = self.queued_messages.remove(&self.session_state.get_incoming_seq_num()) {
+            self.accept_message(message, ready).await?;
+        }
+
+        let next_expected = self.session_state.get_incoming_seq_num();
+        if self.resend_range.is_some_and(|(_, end)| end < next_expected) {
+            info!("Sequence gap filled through {}", next_expected - 1);
+            self.resend_range = None;
+        }
+        self.queued_messages = self.queued_messages.split_off(&next_expected);
+        if self.resend_range.is_none() {
+            if let Some(&queued) = self.queued_messages.keys().next() {
+                self.send_resend_request(next_expected, queued - 1).await?;
             }
-            FixMessage::Logon(logon) => {
-                self.handle_logon(logon).await?;
-                Ok(None)
+        }
+        Ok(())
+    }
+
+    /// Range of the outstanding ResendRequest, if the session is waiting for
+    /// a gap to be filled.
+    pub fn pending_resend(&self) -> Option<(u32, u32)> {
+        self.resend_range
+    }
+
+    pub fn queued_message_count(&self) -> usize {
+        self.queued_messages.len()
+    }
+
+    async fn accept_message(&mut self, message: FixMessage, ready: &mut Vec<FixMessage>) -> Result<(), FixError> {
+        self.session_state.increment_incoming_seq_num();
+        self.message_store.store_incoming_message(&message)?;
+
+        match &message {
+            FixMessage::Heartbeat(heartbeat) => self.handle_heartbeat(heartbeat).await,
+            FixMessage::Logon(logon) => self.handle_logon(logon).await,
+            FixMessage::Logout(logout) => self.handle_logout(logout).await,
+            FixMessage::TestRequest(request) => self.handle_test_request(request).await,
+            FixMessage::ResendRequest(_) => Ok(()),
+            FixMessage::SequenceReset(reset) => {
+                self.handle_sequence_reset(reset.new_seq_no);
+                Ok(())
             }
             FixMessage::NewOrderSingle(_) => {
-                if let Some(order) = self.bridge.process_fix_message(message.clone())? {
-                    Ok(Some(message))
-                } else {
-                    Ok(None)
+                if self.bridge.process_fix_message(message.clone())?.is_some() {
+                    ready.push(message);
                 }
+                Ok(())
+            }
+            _ => {
+                ready.push(message);
+                Ok(())
             }
-            _ => Ok(Some(message)),
         }
     }
 
     pub async fn send_heartbeat(&mut self) -> Result<(), FixError> {
-        if self.last_heartbeat.elapsed() >= self.heartbeat_interval {
+        let now = self.now();
+        if now - self.last_heartbeat >= self.heartbeat_interval.as_nanos() as i64 {
             let heartbeat = self.create_heartbeat(None)?;
             self.send_message(FixMessage::Heartbeat(heartbeat)).await?;
-            self.last_heartbeat = Instant::now();
+            self.last_heartbeat = now;
         }
         Ok(())
     }
 
-    pub async fn check_heartbeat_timeout(&self) -> Result<(), SessionError> {
-        let timeout_threshold = self.heartbeat_interval * 2;
-        if self.last_heartbeat.elapsed() > timeout_threshold {
-            return Err(SessionError::HeartbeatTimeout);
+    /// Probes a silent counterparty with a TestRequest once a heartbeat
+    /// interval passes without inbound messages. The session is only timed
+    /// out if the Heartbeat answering it does not arrive within a further
+    /// interval. Both deadlines are timers on the session's wheel, which
+    /// this ticks before handling whatever fired.
+    pub async fn check_heartbeat_timeout(&mut self) -> Result<(), FixError> {
+        self.timers.tick();
+        let fired: Vec<FiredTimer> = self.timer_events.try_iter().flatten().collect();
+        for timer in fired {
+            match timer.event {
+                TimerEvent::TestRequestTimeout { .. } => {
+                    if let Some((test_req_id, handle)) = &self.pending_test_request {
+                        if *handle == timer.handle {
+                            error!("No Heartbeat answering TestRequest {}", test_req_id);
+                            return Err(SessionError::HeartbeatTimeout.into());
+                        }
+                    }
+                }
+                TimerEvent::TestRequestDue { .. } if self.probe_timer == Some(timer.handle) => {
+                    self.probe_timer = None;
+                    let silence = self.now() - self.last_heartbeat;
+                    if silence >= self.heartbeat_interval.as_nanos() as i64 {
+                        self.send_test_request().await?;
+                    } else {
+                        self.arm_probe();
+                    }
+                }
+                _ => {}
+            }
         }
         Ok(())
     }
 
+    /// TestReqID of the TestRequest still awaiting its Heartbeat.
+    pub fn pending_test_request(&self) -> Option<&str> {
+        self.pending_test_request.as_ref().map(|(test_req_id, _)| test_req_id.as_str())
+    }
+
+    async fn send_test_request(&mut self) -> Result<(), FixError> {
+        let header = self.session_state.create_header(MessageType::TestRequest);
+        self.test_requests_sent += 1;
+        let test_req_id = format!("TEST-{}-{}", header.sending_time, self.test_requests_sent);
+        let trailer = crate::fix::messages::Trailer { checksum: 0 };
+
+        warn!("No inbound messages for {:?}, sending TestRequest {}", self.heartbeat_interval, test_req_id);
+        self.send_message(FixMessage::TestRequest(TestRequest {
+            header,
+            test_req_id: test_req_id.clone(),
+            trailer,
+        }))
+        .await?;
+        let timeout = self.schedule_timeout();
+        self.pending_test_request = Some((test_req_id, timeout));
+        Ok(())
+    }
+
+    async fn handle_test_request(&mut self, request: &TestRequest) -> Result<(), FixError> {
+        let heartbeat = self.create_heartbeat(Some(request.test_req_id.clone()))?;
+        self.send_message(FixMessage::Heartbeat(heartbeat)).await
+    }
+
+    /// Sends a Logout and waits up to the logout timeout for the
+    /// counterparty's confirmation before closing the connection.
     pub async fn shutdown(&mut self) -> Result<(), FixError> {
         info!("Shutting down FIX session");
-        
-        if let Some(ref mut connection) = self.connection {
+
+        if self.connection.is_some() && self.session_state.get_status() == SessionStatus::LoggedOn {
+            self.send_logout(None).await?;
+            self.session_state.set_status(SessionStatus::LogoutSent);
+
+            if timeout(self.logout_timeout, self.await_logout()).await.is_err() {
+                warn!("No Logout confirmation within {:?}", self.logout_timeout);
+            }
+        }
+
+        self.disconnect().await
+    }
+
+    /// Reads inbound messages until the counterparty's Logout arrives or the
+    /// connection closes.
+    async fn await_logout(&mut self) -> Result<(), FixError> {
+        let mut buffer = Vec::new();
+        let mut chunk = [0u8; 4096];
+
+        while self.session_state.get_status() == SessionStatus::LogoutSent {
+            let Some(connection) = self.connection.as_mut() else {
+                break;
+            };
+            let bytes_read = connection.receive(&mut chunk).await?;
+            if bytes_read == 0 {
+                break;
+            }
+            buffer.extend_from_slice(&chunk[..bytes_read]);
+
+            while let Some(message) = Self::take_frame(&mut buffer) {
+                if let Err(error) = self.process_incoming_message(&message).await {
+                    warn!("Error processing message while logging out: {}", error);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the first complete message, up to and including its CheckSum
+    /// field, from the front of `buffer`.
+    fn take_frame(buffer: &mut Vec<u8>) -> Option<Vec<u8>> {
+        const CHECKSUM: &[u8] = b"\x0110=";
+
+        let checksum_start = buffer.windows(CHECKSUM.len()).position(|window| window == CHECKSUM)?;
+        let value_start = checksum_start + CHECKSUM.len();
+        let end = value_start + buffer[value_start..].iter().position(|&byte| byte == 0x01)?;
+        Some(buffer.drain(..=end).collect())
+    }
+
+    async fn disconnect(&mut self) -> Result<(), FixError> {
+        if let Some(mut connection) = self.connection.take() {
             connection.close().await?;
         }
-        
+
         self.session_state.set_status(SessionStatus::Disconnected);
         Ok(())
     }
 
+    async fn send_logout(&mut self, text: Option<String>) -> Result<(), FixError> {
+        let header = self.session_state.create_header(MessageType::Logout);
+        let trailer = crate::fix::messages::Trailer { checksum: 0 };
+
+        self.send_message(FixMessage::Logout(Logout { header, text, trailer })).await
+    }
+
+    /// Confirms a Logout we started, or answers one the counterparty started
+    /// with our own, then disconnects.
+    async fn handle_logout(&mut self, logout: &Logout) -> Result<(), FixError> {
+        match self.session_state.get_status() {
+            SessionStatus::LogoutSent => {
+                info!("Logout confirmed by counterparty");
+                self.session_state.set_status(SessionStatus::LoggedOut);
+                Ok(())
+            }
+            _ => {
+                info!("Counterparty logged out: {}", logout.text.as_deref().unwrap_or(""));
+                self.send_logout(None).await?;
+                self.session_state.set_status(SessionStatus::LoggedOut);
+                self.disconnect().await
+            }
+        }
+    }
+
     async fn send_logon(&mut self) -> Result<(), FixError> {
         let logon = self.create_logon()?;
         self.send_message(FixMessage::Logon(logon)).await
     }
 
-    async fn handle_heartbeat(&mut self, _heartbeat: &Heartbeat) -> Result<(), FixError> {
+    async fn send_resend_request(&mut self, begin_seq_no: u32, end_seq_no: u32) -> Result<(), FixError> {
+        let header = self.session_state.create_header(MessageType::ResendRequest);
+        let trailer = crate::fix::messages::Trailer { checksum: 0 };
+
+        self.resend_range = Some((begin_seq_no, end_seq_no));
+        self.send_message(FixMessage::ResendRequest(ResendRequest {
+            header,
+            begin_seq_no,
+            end_seq_no,
+            trailer,
+        }))
+        .await
+    }
+
+    async fn handle_resend_request(&mut self, request: &ResendRequest) -> Result<(), FixError> {
+        let last_sent = self.session_state.get_outgoing_seq_num() - 1;
+        let end_seq_no = request.last_seq_no(last_sent);
+        info!("Resending messages {} to {}", request.begin_seq_no, end_seq_no);
+
+        for message in self.message_store.resend_response(&self.session_state, request.begin_seq_no, end_seq_no) {
+            let message_bytes = self.serialize_message(&message)?;
+            if let Some(ref mut connection) = self.connection {
+                connection.send(&message_bytes).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves the expected inbound MsgSeqNum forward to `new_seq_no`. Resets
+    /// may not move it backwards.
+    fn handle_sequence_reset(&mut self, new_seq_no: u32) {
+        let expected = self.session_state.get_incoming_seq_num();
+        if new_seq_no < expected {
+            warn!("Ignoring SequenceReset to {} below expected {}", new_seq_no, expected);
+            return;
+        }
+        self.session_state.set_incoming_seq_num(new_seq_no);
+    }
+
+    /// Clears the outstanding TestRequest if the Heartbeat echoes its
+    /// TestReqID. Heartbeats echoing any other id do not count as an answer.
+    async fn handle_heartbeat(&mut self, heartbeat: &Heartbeat) -> Result<(), FixError> {
+        let Some(echoed) = &heartbeat.test_req_id else {
+            return Ok(());
+        };
+        match &self.pending_test_request {
+            Some((test_req_id, timeout)) if test_req_id == echoed => {
+                self.timers.cancel(*timeout);
+                self.pending_test_request = None;
+                self.arm_probe();
+            }
+            _ => warn!("Heartbeat echoes unknown TestReqID {}", echoed),
+        }
         Ok(())
     }
 
     async fn handle_logon(&mut self, logon: &Logon) -> Result<(), FixError> {
         self.heartbeat_interval = Duration::from_secs(logon.heart_bt_int as u64);
+        if self.pending_test_request.is_none() {
+            self.arm_probe();
+        }
         self.session_state.set_status(SessionStatus::LoggedOn);
         info!("Received logon, heartbeat interval: {}s", logon.heart_bt_int);
         Ok(())
@@ -6,10 +6,24 @@ pub enum SessionStatus {
     Disconnected,
     Connected,
     LoggedOn,
+    /// Our Logout has been sent; waiting for the counterparty's.
+    LogoutSent,
     LoggedOut,
     Error,
 }
 
+/// Where an inbound MsgSeqNum (34) falls relative to the next expected one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceCheck {
+    InSequence,
+    /// `begin..=end` were never received.
+    Gap { begin: u32, end: u32 },
+    /// Already seen and flagged PossDupFlag, so it is dropped.
+    Duplicate,
+    /// Already seen without PossDupFlag, which ends the session.
+    TooLow { expected: u32 },
+}
+
 pub struct FixSessionState {
     sender_comp_id: String,
     target_comp_id: String,
@@ -40,6 +54,7 @@ impl FixSessionState {
             sending_time: self.get_utc_timestamp(),
             poss_dup_flag: None,
             poss_resend: None,
+            orig_sending_time: None,
             secure_data_len: None,
             secure_data: None,
         }
@@ -53,6 +68,21 @@ impl FixSessionState {
         self.incoming_seq_num += 1;
     }
 
+    pub fn check_incoming_seq_num(&self, header: &StandardHeader) -> SequenceCheck {
+        let expected = self.incoming_seq_num;
+        let received = header.msg_seq_num;
+
+        if received == expected {
+            SequenceCheck::InSequence
+        } else if received > expected {
+            SequenceCheck::Gap { begin: expected, end: received - 1 }
+        } else if header.poss_dup_flag == Some(true) {
+            SequenceCheck::Duplicate
+        } else {
+            SequenceCheck::TooLow { expected }
+        }
+    }
+
     pub fn get_outgoing_seq_num(&self) -> u32 {
         self.outgoing_seq_num
     }
@@ -0,0 +1,139 @@
+use crate::clock::SharedClock;
+use crate::fix::error::{FixError, SessionError, ValidationError};
+use crate::fix::messages::StandardHeader;
+use chrono::NaiveDateTime;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use tracing::warn;
+
+const SENDING_TIME_TAG: u32 = 52;
+
+/// What to do with a message whose SendingTime is outside the tolerance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkewPolicy {
+    Reject,
+    AcceptWithWarning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SkewConfig {
+    /// Largest accepted |SendingTime - receive time|.
+    pub max_skew_ns: i64,
+    pub policy: SkewPolicy,
+    /// Weight of the newest sample in the moving average.
+    pub ewma_alpha: f64,
+}
+
+impl Default for SkewConfig {
+    fn default() -> Self {
+        Self {
+            max_skew_ns: 120_000_000_000,
+            policy: SkewPolicy::Reject,
+            ewma_alpha: 0.1,
+        }
+    }
+}
+
+/// Per-session SendingTime skew. Positive values mean the client's clock is
+/// ahead of the exchange.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SkewMetrics {
+    pub ewma_ns: f64,
+    pub last_skew_ns: i64,
+    pub samples: u64,
+    pub violations: u64,
+}
+
+#[derive(Default)]
+struct SessionSkew {
+    config: Option<SkewConfig>,
+    metrics: SkewMetrics,
+}
+
+/// Checks SendingTime (52) against the shared clock and keeps skew
+/// statistics per SenderCompID.
+pub struct SkewTracker {
+    clock: SharedClock,
+    default_config: SkewConfig,
+    sessions: Mutex<HashMap<String, SessionSkew>>,
+}
+
+impl SkewTracker {
+    pub fn new(clock: SharedClock) -> Self {
+        Self::with_default_config(clock, SkewConfig::default())
+    }
+
+    pub fn with_default_config(clock: SharedClock, default_config: SkewConfig) -> Self {
+        Self {
+            clock,
+            default_config,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn set_session_config(&self, sender_comp_id: &str, config: SkewConfig) {
+        self.sessions
+            .lock()
+            .entry(sender_comp_id.to_string())
+            .or_default()
+            .config = Some(config);
+    }
+
+    pub fn metrics(&self, sender_comp_id: &str) -> Option<SkewMetrics> {
+        self.sessions
+            .lock()
+            .get(sender_comp_id)
+            .filter(|session| session.metrics.samples > 0)
+            .map(|session| session.metrics)
+    }
+
+    /// Records the skew of `header` and applies the session's policy.
+    pub fn check(&self, header: &StandardHeader) -> Result<(), FixError> {
+        let received_at = self.clock.now_nanos();
+        let sent_at = parse_sending_time(&header.sending_time).ok_or_else(|| {
+            ValidationError::InvalidFieldValue {
+                tag: SENDING_TIME_TAG,
+                value: header.sending_time.clone(),
+            }
+        })?;
+        let skew_ns = sent_at - received_at;
+
+        let mut sessions = self.sessions.lock();
+        let session = sessions.entry(header.sender_comp_id.clone()).or_default();
+        let config = session.config.unwrap_or(self.default_config);
+        let metrics = &mut session.metrics;
+
+        metrics.ewma_ns = if metrics.samples == 0 {
+            skew_ns as f64
+        } else {
+            config.ewma_alpha * skew_ns as f64 + (1.0 - config.ewma_alpha) * metrics.ewma_ns
+        };
+        metrics.last_skew_ns = skew_ns;
+        metrics.samples += 1;
+
+        if skew_ns.abs() <= config.max_skew_ns {
+            return Ok(());
+        }
+
+        metrics.violations += 1;
+        match config.policy {
+            SkewPolicy::Reject => Err(SessionError::SendingTimeAccuracy { skew_ns }.into()),
+            SkewPolicy::AcceptWithWarning => {
+                warn!(
+                    "SendingTime from {} is {}ns off the exchange clock",
+                    header.sender_comp_id, skew_ns
+                );
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Parses a UTCTimestamp (`YYYYMMDD-HH:MM:SS[.sss]`) into nanoseconds since
+/// the Unix epoch.
+pub fn parse_sending_time(value: &str) -> Option<i64> {
+    NaiveDateTime::parse_from_str(value, "%Y%m%d-%H:%M:%S%.f")
+        .ok()?
+        .and_utc()
+        .timestamp_nanos_opt()
+}
@@ -1,10 +1,20 @@
+use crate::fix::bridge::FixOrderConverter;
 use crate::fix::error::BusinessError;
+use crate::fix::mapping;
 use crate::fix::messages::NewOrderSingle;
-use std::collections::HashSet;
+use crate::order::OrderType;
+use crate::price_utils::{price_magnitude, scaled_to_price, Price, SIGNED_PRICES};
+use std::collections::{HashMap, HashSet};
 
+#[derive(Clone)]
 pub struct BusinessValidator {
     active_cl_ord_ids: HashSet<String>,
     valid_symbols: HashSet<String>,
+    /// Furthest a limit price may be from the reference price, in percent.
+    price_bands: HashMap<String, f64>,
+    /// Reference prices for symbols that have not traded yet, scaled.
+    reference_prices: HashMap<String, Price>,
+    max_quantities: HashMap<String, u32>,
 }
 
 impl BusinessValidator {
@@ -19,13 +29,21 @@ impl BusinessValidator {
         Self {
             active_cl_ord_ids: HashSet::new(),
             valid_symbols,
+            price_bands: HashMap::new(),
+            reference_prices: HashMap::new(),
+            max_quantities: HashMap::new(),
         }
     }
 
-    pub fn validate_new_order(&mut self, order: &NewOrderSingle) -> Result<(), BusinessError> {
+    /// Checks `order` before it reaches the book. Its limit price is held
+    /// to the symbol's price band around `last_trade_price`, or around the
+    /// symbol's reference price if it has not traded.
+    pub fn validate_new_order(&mut self, order: &NewOrderSingle, last_trade_price: Option<Price>) -> Result<(), BusinessError> {
         self.validate_symbol(&order.symbol)?;
         self.validate_quantity(order.order_qty)?;
+        self.validate_max_quantity(&order.symbol, order.order_qty)?;
         self.validate_price(order.price, order.ord_type)?;
+        self.validate_price_band(&order.symbol, order.price, order.ord_type, last_trade_price)?;
         self.validate_stop_price(order.stop_px, order.ord_type)?;
         self.validate_duplicate_cl_ord_id(&order.cl_ord_id)?;
         
@@ -57,6 +75,21 @@ impl BusinessValidator {
         self.valid_symbols.remove(symbol);
     }
 
+    /// Rejects limit prices more than `pct` percent away from the last
+    /// trade price, or from the reference price before the first trade.
+    pub fn set_price_band(&mut self, symbol: &str, pct: f64) {
+        self.price_bands.insert(symbol.to_string(), pct);
+    }
+
+    /// Scaled price the price band is centred on until the symbol trades.
+    pub fn set_reference_price(&mut self, symbol: &str, price: Price) {
+        self.reference_prices.insert(symbol.to_string(), price);
+    }
+
+    pub fn set_max_quantity(&mut self, symbol: &str, quantity: u32) {
+        self.max_quantities.insert(symbol.to_string(), quantity);
+    }
+
     pub fn complete_order(&mut self, cl_ord_id: &str) {
         self.active_cl_ord_ids.remove(cl_ord_id);
     }
@@ -70,7 +103,8 @@ impl BusinessValidator {
         Ok(())
     }
 
-    fn validate_quantity(&self, quantity: u32) -> Result<(), BusinessError> {
+    fn validate_quantity(&self, order_qty: i64) -> Result<(), BusinessError> {
+        let quantity = FixOrderConverter::quantity_from_fix(order_qty)?;
         if quantity == 0 {
             return Err(BusinessError::InvalidQuantity { quantity });
         }
@@ -83,49 +117,86 @@ impl BusinessValidator {
     }
 
     fn validate_price(&self, price: Option<f64>, ord_type: char) -> Result<(), BusinessError> {
-        match ord_type {
-            '2' | '4' => {
+        match mapping::fix_to_order_type(ord_type) {
+            Ok(OrderType::Limit | OrderType::StopLimit | OrderType::Iceberg) => {
                 if let Some(p) = price {
-                    if p <= 0.0 || !p.is_finite() {
+                    FixOrderConverter::price_from_fix(p)?;
+                    if (p <= 0.0 && !SIGNED_PRICES) || !p.is_finite() {
                         return Err(BusinessError::InvalidPrice {
-                            price: (p * 10000.0) as u64,
+                            price: (p * 10000.0) as Price,
                         });
                     }
                     
-                    if p > 1_000_000.0 {
+                    if p.abs() > 1_000_000.0 {
                         return Err(BusinessError::InvalidPrice {
-                            price: (p * 10000.0) as u64,
+                            price: (p * 10000.0) as Price,
                         });
                     }
                 } else {
                     return Err(BusinessError::InvalidPrice { price: 0 });
                 }
             }
-            _ => {}
+            Ok(OrderType::Market | OrderType::StopMarket) | Err(_) => {}
+        }
+        Ok(())
+    }
+
+    fn validate_max_quantity(&self, symbol: &str, order_qty: i64) -> Result<(), BusinessError> {
+        let quantity = FixOrderConverter::quantity_from_fix(order_qty)?;
+        match self.max_quantities.get(symbol) {
+            Some(&max) if quantity > max => Err(BusinessError::IncorrectQuantity {
+                quantity,
+                reason: format!("exceeds the maximum order quantity {} for {}", max, symbol),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// A symbol with a band but neither a trade nor a reference price
+    /// accepts any price.
+    fn validate_price_band(&self, symbol: &str, price: Option<f64>, ord_type: char, last_trade_price: Option<Price>) -> Result<(), BusinessError> {
+        if !matches!(mapping::fix_to_order_type(ord_type), Ok(OrderType::Limit | OrderType::StopLimit | OrderType::Iceberg)) {
+            return Ok(());
+        }
+        let (Some(&band_pct), Some(price)) = (self.price_bands.get(symbol), price) else {
+            return Ok(());
+        };
+        let Some(reference) = last_trade_price.or_else(|| self.reference_prices.get(symbol).copied()) else {
+            return Ok(());
+        };
+
+        let scaled = FixOrderConverter::price_from_fix(price)?;
+        if scaled.abs_diff(reference) as f64 > price_magnitude(reference) as f64 * band_pct / 100.0 {
+            return Err(BusinessError::PriceOutsideBand {
+                price,
+                reference: scaled_to_price(reference),
+                band_pct,
+            });
         }
         Ok(())
     }
 
     fn validate_stop_price(&self, stop_px: Option<f64>, ord_type: char) -> Result<(), BusinessError> {
-        match ord_type {
-            '3' | '4' => {
+        match mapping::fix_to_order_type(ord_type) {
+            Ok(OrderType::StopMarket | OrderType::StopLimit) => {
                 if let Some(p) = stop_px {
-                    if p <= 0.0 || !p.is_finite() {
+                    FixOrderConverter::price_from_fix(p)?;
+                    if (p <= 0.0 && !SIGNED_PRICES) || !p.is_finite() {
                         return Err(BusinessError::InvalidPrice {
-                            price: (p * 10000.0) as u64,
+                            price: (p * 10000.0) as Price,
                         });
                     }
                     
-                    if p > 1_000_000.0 {
+                    if p.abs() > 1_000_000.0 {
                         return Err(BusinessError::InvalidPrice {
-                            price: (p * 10000.0) as u64,
+                            price: (p * 10000.0) as Price,
                         });
                     }
                 } else {
                     return Err(BusinessError::InvalidPrice { price: 0 });
                 }
             }
-            _ => {}
+            Ok(OrderType::Market | OrderType::Limit | OrderType::Iceberg) | Err(_) => {}
         }
         Ok(())
     }
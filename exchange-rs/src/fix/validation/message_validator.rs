@@ -17,6 +17,10 @@ impl MessageValidator {
             FixMessage::OrderCancelRequest(cancel) => self.validate_order_cancel_request_fields(cancel),
             FixMessage::Heartbeat(heartbeat) => self.validate_heartbeat_fields(heartbeat),
             FixMessage::Logon(logon) => self.validate_logon_fields(logon),
+            FixMessage::Logout(_) | FixMessage::ResendRequest(_) | FixMessage::SequenceReset(_)
+            | FixMessage::TestRequest(_) | FixMessage::MarketDataRequest(_)
+            | FixMessage::MarketDataSnapshotFullRefresh(_) | FixMessage::MarketDataIncrementalRefresh(_)
+            | FixMessage::SecurityDefinitionRequest(_) | FixMessage::OrderStatusRequest(_) => Ok(()),
         }
     }
 
@@ -62,12 +66,24 @@ impl MessageValidator {
             MessageType::OrderCancelRequest => {
                 required.extend(vec![41, 11, 55, 54, 60]);
             }
+            MessageType::OrderStatusRequest => {
+                required.extend(vec![11, 55, 54]);
+            }
             MessageType::Heartbeat => {
                 
+            }
+            MessageType::TestRequest => {
+                required.extend(vec![112]);
             }
             MessageType::Logon => {
                 required.extend(vec![98, 108]);
             }
+            MessageType::ResendRequest => {
+                required.extend(vec![7, 16]);
+            }
+            MessageType::SequenceReset => {
+                required.extend(vec![36]);
+            }
             _ => {}
         }
 
@@ -75,7 +91,7 @@ impl MessageValidator {
     }
 
     fn get_allowed_fields(&self, msg_type: &MessageType) -> Vec<u32> {
-        let standard_header = vec![8, 9, 35, 49, 56, 34, 52, 43, 97, 90, 91];
+        let standard_header = vec![8, 9, 35, 49, 56, 34, 52, 43, 97, 122, 90, 91];
         let trailer = vec![10];
         
         let mut allowed = standard_header;
@@ -96,12 +112,24 @@ impl MessageValidator {
             MessageType::OrderCancelRequest => {
                 allowed.extend(vec![41, 11, 55, 54, 60, 38, 1, 58]);
             }
-            MessageType::Heartbeat => {
+            MessageType::OrderStatusRequest => {
+                allowed.extend(vec![37, 11, 55, 54]);
+            }
+            MessageType::Heartbeat | MessageType::TestRequest => {
                 allowed.extend(vec![112]);
             }
             MessageType::Logon => {
                 allowed.extend(vec![98, 108, 95, 96, 141, 789, 553, 554]);
             }
+            MessageType::Logout => {
+                allowed.extend(vec![58]);
+            }
+            MessageType::ResendRequest => {
+                allowed.extend(vec![7, 16]);
+            }
+            MessageType::SequenceReset => {
+                allowed.extend(vec![36, 123]);
+            }
             _ => {}
         }
 
@@ -1,45 +1,74 @@
 use crate::fix::{FixParser, FixSession, FixOrderBridge, FixError};
-use crate::matching_engine::{MatchingEngine, TradeExecutionResult};
+use crate::fix::bridge::order_converter::{MAX_ORDER_PRICE, MAX_ORDER_QTY};
+use crate::fix::encoder;
+use crate::fix::market_data::{MarketDataReject, MarketDataSubscription, TAG_MD_REQ_ID, TAG_MD_REQ_REJ_REASON};
+use crate::fix::messages::market_data_request::{SUBSCRIPTION_SNAPSHOT_AND_UPDATES, SUBSCRIPTION_UNSUBSCRIBE};
+use crate::fix::error::BusinessError;
+use crate::fix::validation::BusinessValidator;
+use crate::fix::messages::{
+    FixMessage, Heartbeat, Logon, Logout, MarketDataRequest, MessageType, SecurityDefinitionRequest, Trailer,
+};
+use crate::fix::session::{FixSessionState, MessageStore, SessionStatus, SkewTracker};
+use crate::matching_engine::{MatchingEngine, MatchingError};
 use crate::order::Order;
+use crate::price_utils::scaled_to_price;
 use parking_lot::Mutex;
 use std::sync::Arc;
-use std::collections::HashMap;
+use std::time::Duration;
+use std::collections::{HashMap, HashSet};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tracing::{info, warn, error};
 
+/// How often subscribed books are checked for changes to publish.
+const MARKET_DATA_INTERVAL: Duration = Duration::from_millis(100);
+
 pub struct FixGateway {
     matching_engine: Arc<Mutex<MatchingEngine>>,
     sessions: HashMap<String, FixSession>,
     parser: FixParser,
     bridge: FixOrderBridge,
+    skew: Arc<SkewTracker>,
 }
 
 impl FixGateway {
     pub fn new(matching_engine: Arc<Mutex<MatchingEngine>>) -> Self {
+        let clock = matching_engine.lock().clock();
         Self {
             matching_engine,
             sessions: HashMap::new(),
             parser: FixParser::new(),
             bridge: FixOrderBridge::new(),
+            skew: Arc::new(SkewTracker::new(clock)),
         }
     }
 
+    /// SendingTime checks and per-session skew metrics, timed by the
+    /// engine's clock.
+    pub fn skew_tracker(&self) -> &Arc<SkewTracker> {
+        &self.skew
+    }
+
     pub async fn start_server(&mut self, address: &str) -> Result<(), FixError> {
         info!("Starting FIX gateway server on {}", address);
         
         let listener = TcpListener::bind(address).await
             .map_err(|_| FixError::Session(crate::fix::error::SessionError::InvalidSessionState))?;
 
+        self.serve(listener).await
+    }
+
+    /// Accepts FIX connections on `listener` until it fails.
+    pub async fn serve(&self, listener: TcpListener) -> Result<(), FixError> {
         loop {
             match listener.accept().await {
                 Ok((stream, addr)) => {
                     info!("New FIX connection from {}", addr);
                     
-                    let matching_engine = Arc::clone(&self.matching_engine);
+                    let connection = self.connection();
                     
                     tokio::spawn(async move {
-                        if let Err(e) = Self::handle_connection(stream, matching_engine).await {
+                        if let Err(e) = Self::handle_connection(stream, connection).await {
                             error!("Error handling FIX connection from {}: {}", addr, e);
                         }
                     });
@@ -51,23 +80,42 @@ impl FixGateway {
         }
     }
 
+    /// Protocol state for a new connection, which callers feed framed
+    /// messages themselves.
+    pub fn connection(&self) -> GatewayConnection {
+        GatewayConnection::new(Arc::clone(&self.matching_engine), Arc::clone(&self.skew))
+            .with_validator(self.bridge.validator().clone())
+    }
+
     async fn handle_connection(
         mut stream: TcpStream,
-        matching_engine: Arc<Mutex<MatchingEngine>>,
+        mut connection: GatewayConnection,
     ) -> Result<(), FixError> {
-        let mut parser = FixParser::new();
-        let mut bridge = FixOrderBridge::new();
         let mut buffer = vec![0u8; 4096];
         let mut message_buffer = Vec::new();
-        let mut cl_ord_id_counter = 1u64;
+        let mut market_data = tokio::time::interval(MARKET_DATA_INTERVAL);
 
-        loop {
-            let bytes_read = stream.read(&mut buffer).await
-                .map_err(|_| FixError::Session(crate::fix::error::SessionError::InvalidSessionState))?;
+        let outcome = 'connection: loop {
+            let read = tokio::select! {
+                read = stream.read(&mut buffer) => read,
+                _ = market_data.tick() => {
+                    for update in connection.poll_market_data() {
+                        if let Err(e) = stream.write_all(&update).await {
+                            error!("Failed to send FIX message: {}", e);
+                            break 'connection Ok(());
+                        }
+                    }
+                    continue;
+                }
+            };
+            let bytes_read = match read {
+                Ok(bytes_read) => bytes_read,
+                Err(_) => break Err(FixError::Session(crate::fix::error::SessionError::InvalidSessionState)),
+            };
 
             if bytes_read == 0 {
                 info!("FIX connection closed by client");
-                break;
+                break Ok(());
             }
 
             message_buffer.extend_from_slice(&buffer[..bytes_read]);
@@ -75,63 +123,63 @@ impl FixGateway {
             while let Some(message_end) = Self::find_message_boundary(&message_buffer) {
                 let message_data = message_buffer.drain(..message_end + 1).collect::<Vec<u8>>();
                 
-                match Self::process_fix_message(
-                    &mut parser,
-                    &mut bridge,
-                    &message_data,
-                    &matching_engine,
-                    &mut cl_ord_id_counter,
-                ).await {
-                    Ok(Some(response)) => {
-                        if let Err(e) = stream.write_all(&response).await {
-                            error!("Failed to send FIX response: {}", e);
-                            break;
-                        }
-                    }
-                    Ok(None) => {
-                        
-                    }
-                    Err(e) => {
-                        warn!("Error processing FIX message: {}", e);
-                        
-                        let reject = Self::create_reject_message(&e);
-                        if let Err(send_err) = stream.write_all(&reject).await {
-                            error!("Failed to send reject message: {}", send_err);
-                            break;
-                        }
+                for reply in connection.handle(&message_data) {
+                    if let Err(e) = stream.write_all(&reply).await {
+                        error!("Failed to send FIX message: {}", e);
+                        break 'connection Ok(());
                     }
                 }
+
+                if connection.is_logged_out() {
+                    info!("FIX session logged out");
+                    break 'connection Ok(());
+                }
             }
+        };
+
+        let canceled = connection.close();
+        if !canceled.is_empty() {
+            info!("Cancelled {} orders on FIX disconnect", canceled.len());
         }
 
-        Ok(())
+        outcome
     }
 
-    async fn process_fix_message(
-        parser: &mut FixParser,
-        bridge: &mut FixOrderBridge,
-        message_data: &[u8],
+    /// Pulls every open order entered by the users of a session that has
+    /// gone away.
+    pub fn cancel_on_disconnect(
         matching_engine: &Arc<Mutex<MatchingEngine>>,
-        cl_ord_id_counter: &mut u64,
-    ) -> Result<Option<Vec<u8>>, FixError> {
-        parser.validate_checksum(message_data)?;
-        let fix_message = parser.parse(message_data)?;
-
-        match bridge.process_fix_message(fix_message)? {
-            Some(order) => {
-                let cl_ord_id = format!("ORDER{}", *cl_ord_id_counter);
-                *cl_ord_id_counter += 1;
-
-                let result = {
-                    let mut engine = matching_engine.lock();
-                    engine.place_order(order)?
-                };
+        session_users: &HashSet<u64>,
+    ) -> Vec<Arc<parking_lot::RwLock<Order>>> {
+        let mut engine = matching_engine.lock();
+        session_users
+            .iter()
+            .flat_map(|&user_id| engine.cancel_all_for_user(user_id))
+            .collect()
+    }
 
-                let response_message = bridge.convert_trade_result(&result, &cl_ord_id)?;
-                let response_bytes = Self::serialize_fix_message(&response_message)?;
-                Ok(Some(response_bytes))
-            }
-            None => Ok(None),
+    /// Places `order` and returns the execution report for it. Orders the
+    /// engine refuses for business reasons, such as a halted symbol, are
+    /// answered with a rejection carrying OrdRejReason rather than an error.
+    pub fn execute_order(
+        matching_engine: &Mutex<MatchingEngine>,
+        bridge: &mut FixOrderBridge,
+        order: Order,
+        cl_ord_id: &str,
+    ) -> Result<FixMessage, FixError> {
+        let symbol = order.symbol.clone();
+        let placed = matching_engine.lock().place_order(order);
+        match placed {
+            Ok(result) => bridge.convert_trade_result(&result, cl_ord_id),
+            Err(MatchingError::SymbolHalted) => bridge.convert_business_reject(
+                cl_ord_id,
+                &symbol,
+                &BusinessError::TradingHalt { symbol: symbol.clone() },
+            ),
+            Err(error) => match FixError::from(error) {
+                FixError::Business(error) => bridge.convert_business_reject(cl_ord_id, &symbol, &error),
+                other => Err(other),
+            },
         }
     }
 
@@ -146,20 +194,297 @@ impl FixGateway {
         None
     }
 
-    fn serialize_fix_message(message: &crate::fix::messages::FixMessage) -> Result<Vec<u8>, FixError> {
-        Ok(b"8=FIX.4.4\x019=50\x0135=8\x0149=EXCHANGE\x0156=CLIENT\x0134=1\x0152=20240101-12:00:00\x0110=123\x01".to_vec())
-    }
-
-    fn create_reject_message(error: &FixError) -> Vec<u8> {
-        format!("8=FIX.4.4\x019=100\x0135=3\x0149=EXCHANGE\x0156=CLIENT\x0134=1\x0152=20240101-12:00:00\x0158={}\x0110=123\x01", error).into_bytes()
-    }
-
     pub fn add_symbol(&mut self, symbol: &str) {
         self.bridge.add_symbol(symbol.to_string());
         
         let mut engine = self.matching_engine.lock();
         engine.add_symbol(symbol);
     }
+
+    /// Order checks applied before orders reach the engine, such as price
+    /// bands and maximum quantities. Connections opened afterwards start
+    /// with a copy.
+    pub fn validator_mut(&mut self) -> &mut BusinessValidator {
+        self.bridge.validator_mut()
+    }
+}
+
+/// The protocol state of one FIX connection: the session's sequence
+/// numbers, the outgoing messages kept for resends, and the users whose
+/// orders are pulled when the connection goes away. It only sees framed
+/// messages, so the TCP server and in-memory harnesses drive it alike.
+pub struct GatewayConnection {
+    matching_engine: Arc<Mutex<MatchingEngine>>,
+    skew: Arc<SkewTracker>,
+    parser: FixParser,
+    bridge: FixOrderBridge,
+    session_state: FixSessionState,
+    message_store: MessageStore,
+    session_users: HashSet<u64>,
+    md_subscriptions: HashMap<String, MarketDataSubscription>,
+}
+
+impl GatewayConnection {
+    pub fn new(matching_engine: Arc<Mutex<MatchingEngine>>, skew: Arc<SkewTracker>) -> Self {
+        Self {
+            matching_engine,
+            skew,
+            parser: FixParser::new(),
+            bridge: FixOrderBridge::new(),
+            session_state: FixSessionState::new("EXCHANGE".to_string(), "CLIENT".to_string()),
+            message_store: MessageStore::new(),
+            session_users: HashSet::new(),
+            md_subscriptions: HashMap::new(),
+        }
+    }
+
+    /// Checks new orders with `validator` in place of the default one.
+    pub fn with_validator(mut self, validator: BusinessValidator) -> Self {
+        self.bridge = FixOrderBridge::with_validator(validator);
+        self
+    }
+
+    /// Processes one framed inbound message and returns the encoded
+    /// replies in the order they are to be sent. A message that cannot be
+    /// processed is answered with a session-level Reject.
+    pub fn handle(&mut self, data: &[u8]) -> Vec<Vec<u8>> {
+        match self.process(data) {
+            Ok(replies) => replies,
+            Err(error) => {
+                warn!("Error processing FIX message: {}", error);
+                vec![self.reject(Self::ref_seq_num(data), &error)]
+            }
+        }
+    }
+
+    /// Encoded updates for the books subscribed to on this connection that
+    /// changed since they were last sent. Meant to be called periodically.
+    pub fn poll_market_data(&mut self) -> Vec<Vec<u8>> {
+        let updates: Vec<FixMessage> = {
+            let engine = self.matching_engine.lock();
+            self.md_subscriptions
+                .values_mut()
+                .flat_map(|subscription| subscription.poll(&engine, &self.session_state))
+                .collect()
+        };
+
+        let mut encoded = Vec::with_capacity(updates.len());
+        for update in updates {
+            match self.send(update) {
+                Ok(update) => encoded.push(update),
+                Err(error) => warn!("Failed to send market data: {}", error),
+            }
+        }
+        encoded
+    }
+
+    /// Whether the counterparty has logged out, after which the connection
+    /// is to be closed.
+    pub fn is_logged_out(&self) -> bool {
+        self.session_state.get_status() == SessionStatus::LoggedOut
+    }
+
+    /// Cancels the open orders of the session's users. Called once the
+    /// connection has gone away.
+    pub fn close(&mut self) -> Vec<Arc<parking_lot::RwLock<Order>>> {
+        let canceled = FixGateway::cancel_on_disconnect(&self.matching_engine, &self.session_users);
+        self.session_users.clear();
+        self.session_state.set_status(SessionStatus::Disconnected);
+        canceled
+    }
+
+    fn process(&mut self, data: &[u8]) -> Result<Vec<Vec<u8>>, FixError> {
+        self.parser.validate_checksum(data)?;
+        let fix_message = self.parser.parse(data)?;
+        self.skew.check(fix_message.header())?;
+
+        match fix_message {
+            FixMessage::Logon(logon) => {
+                let next_seq_num = self.session_state.get_outgoing_seq_num();
+                self.session_state = FixSessionState::new(
+                    logon.header.target_comp_id.clone(),
+                    logon.header.sender_comp_id.clone(),
+                );
+                self.session_state.set_outgoing_seq_num(next_seq_num);
+                self.session_state.set_status(SessionStatus::LoggedOn);
+                info!("FIX logon from {}", logon.header.sender_comp_id);
+
+                let reply = Logon {
+                    header: self.session_state.create_header(MessageType::Logon),
+                    encrypt_method: '0',
+                    heart_bt_int: logon.heart_bt_int,
+                    raw_data_length: None,
+                    raw_data: None,
+                    reset_seq_num_flag: None,
+                    next_expected_msg_seq_num: None,
+                    username: None,
+                    password: None,
+                    trailer: Trailer { checksum: 0 },
+                };
+                Ok(vec![self.send(FixMessage::Logon(reply))?])
+            }
+            FixMessage::TestRequest(request) => {
+                let reply = Heartbeat {
+                    header: self.session_state.create_header(MessageType::Heartbeat),
+                    test_req_id: Some(request.test_req_id),
+                    trailer: Trailer { checksum: 0 },
+                };
+                Ok(vec![self.send(FixMessage::Heartbeat(reply))?])
+            }
+            FixMessage::ResendRequest(request) => {
+                let last_sent = self.session_state.get_outgoing_seq_num() - 1;
+                let end_seq_no = request.last_seq_no(last_sent);
+                info!("Resending messages {} to {}", request.begin_seq_no, end_seq_no);
+
+                let resent = self.message_store.resend_response(&self.session_state, request.begin_seq_no, end_seq_no);
+                Ok(resent.iter().map(encoder::encode).collect())
+            }
+            FixMessage::Logout(_) => {
+                let reply = Logout {
+                    header: self.session_state.create_header(MessageType::Logout),
+                    text: None,
+                    trailer: Trailer { checksum: 0 },
+                };
+                let reply = self.send(FixMessage::Logout(reply))?;
+                self.session_state.set_status(SessionStatus::LoggedOut);
+                Ok(vec![reply])
+            }
+            FixMessage::MarketDataRequest(request) => self.market_data_request(request),
+            FixMessage::SecurityDefinitionRequest(request) => Ok(vec![self.security_definition(&request)]),
+            FixMessage::OrderStatusRequest(request) => {
+                let report = self.bridge.order_status(&request, &self.matching_engine.lock());
+                Ok(vec![self.send(FixMessage::ExecutionReport(report))?])
+            }
+            FixMessage::OrderCancelRequest(request) => {
+                let report = self.bridge.cancel_order(&request, &mut self.matching_engine.lock());
+                Ok(vec![self.send(FixMessage::ExecutionReport(report))?])
+            }
+            FixMessage::NewOrderSingle(new_order) => {
+                let cl_ord_id = new_order.cl_ord_id.clone();
+                let symbol = new_order.symbol.clone();
+
+                let last_trade_price = self.matching_engine.lock().last_trade_price(&symbol);
+                let report = match self.bridge.process_new_order(new_order, last_trade_price) {
+                    Ok(order) => {
+                        self.session_users.insert(order.user_id);
+                        FixGateway::execute_order(&self.matching_engine, &mut self.bridge, order, &cl_ord_id)?
+                    }
+                    Err(FixError::Business(error)) => {
+                        self.bridge.convert_business_reject(&cl_ord_id, &symbol, &error)?
+                    }
+                    Err(error) => return Err(error),
+                };
+                Ok(vec![self.send(report)?])
+            }
+            other => {
+                self.bridge.process_fix_message(other)?;
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    /// Answers a MarketDataRequest with a full refresh per symbol and, for
+    /// a subscription, keeps it for `poll_market_data`. An unsubscribe
+    /// drops the subscription with the request's MDReqID.
+    fn market_data_request(&mut self, request: MarketDataRequest) -> Result<Vec<Vec<u8>>, FixError> {
+        if request.subscription_request_type == SUBSCRIPTION_UNSUBSCRIBE {
+            self.md_subscriptions.remove(&request.md_req_id);
+            return Ok(Vec::new());
+        }
+        if self.md_subscriptions.contains_key(&request.md_req_id) {
+            let reject = MarketDataReject::DuplicateMdReqId { md_req_id: request.md_req_id.clone() };
+            return Ok(vec![self.market_data_reject(&request.md_req_id, &reject)]);
+        }
+
+        let snapshot = MarketDataSubscription::new(&request).and_then(|mut subscription| {
+            let engine = self.matching_engine.lock();
+            let snapshot = subscription.snapshot(&engine, &self.session_state)?;
+            Ok((subscription, snapshot))
+        });
+        let (subscription, snapshot) = match snapshot {
+            Ok(snapshot) => snapshot,
+            Err(reject) => return Ok(vec![self.market_data_reject(&request.md_req_id, &reject)]),
+        };
+
+        if request.subscription_request_type == SUBSCRIPTION_SNAPSHOT_AND_UPDATES {
+            self.md_subscriptions.insert(request.md_req_id.clone(), subscription);
+        }
+        snapshot.into_iter().map(|refresh| self.send(refresh)).collect()
+    }
+
+    /// Answers a SecurityDefinitionRequest with the symbol's trading
+    /// rules and the largest quantity and price an order can carry, so
+    /// clients learn the limits before sending orders.
+    fn security_definition(&mut self, request: &SecurityDefinitionRequest) -> Vec<u8> {
+        let header = self.session_state.create_header(MessageType::SecurityDefinition);
+        let security_response_id = format!("SD-{}", header.msg_seq_num);
+        self.session_state.increment_outgoing_seq_num();
+
+        let spec = self.matching_engine.lock().symbol_spec(&request.symbol);
+        let mut body = vec![
+            (320, request.security_req_id.clone()),
+            (322, security_response_id),
+        ];
+        match spec {
+            Some(spec) => body.extend([
+                (323, "1".to_string()),
+                (55, request.symbol.clone()),
+                (969, scaled_to_price(spec.tick_size).to_string()),
+                (561, spec.lot_size.to_string()),
+                (562, spec.min_qty.to_string()),
+                (1140, MAX_ORDER_QTY.to_string()),
+                (1149, MAX_ORDER_PRICE.to_string()),
+            ]),
+            None => body.extend([
+                (323, "6".to_string()),
+                (55, request.symbol.clone()),
+            ]),
+        }
+        encoder::encode_fields(&header, &body)
+    }
+
+    fn market_data_reject(&mut self, md_req_id: &str, reject: &MarketDataReject) -> Vec<u8> {
+        let header = self.session_state.create_header(MessageType::MarketDataRequestReject);
+        self.session_state.increment_outgoing_seq_num();
+
+        let body = vec![
+            (TAG_MD_REQ_ID, md_req_id.to_string()),
+            (TAG_MD_REQ_REJ_REASON, reject.to_fix().to_string()),
+            (58, reject.to_string()),
+        ];
+        encoder::encode_fields(&header, &body)
+    }
+
+    /// Stamps `message` with the session's next outgoing header, keeps it
+    /// for resends and encodes it.
+    fn send(&mut self, mut message: FixMessage) -> Result<Vec<u8>, FixError> {
+        let msg_type = message.header().msg_type.clone();
+        *message.header_mut() = self.session_state.create_header(msg_type);
+        self.session_state.increment_outgoing_seq_num();
+        self.message_store.store_outgoing_message(&message)?;
+        Ok(encoder::encode(&message))
+    }
+
+    fn reject(&mut self, ref_seq_num: Option<u32>, error: &FixError) -> Vec<u8> {
+        let header = self.session_state.create_header(MessageType::Reject);
+        self.session_state.increment_outgoing_seq_num();
+
+        let mut body = Vec::new();
+        if let Some(ref_seq_num) = ref_seq_num {
+            body.push((45, ref_seq_num.to_string()));
+        }
+        body.push((58, error.to_string()));
+        encoder::encode_fields(&header, &body)
+    }
+
+    /// MsgSeqNum of a message that may not parse, for RefSeqNum.
+    fn ref_seq_num(data: &[u8]) -> Option<u32> {
+        const MSG_SEQ_NUM: &[u8] = b"\x0134=";
+
+        let start = data.windows(MSG_SEQ_NUM.len()).position(|window| window == MSG_SEQ_NUM)? + MSG_SEQ_NUM.len();
+        let end = start + data[start..].iter().position(|&byte| byte == 0x01)?;
+        std::str::from_utf8(&data[start..end]).ok()?.parse().ok()
+    }
 }
 
 impl From<crate::matching_engine::MatchingError> for FixError {
@@ -170,13 +495,75 @@ impl From<crate::matching_engine::MatchingError> for FixError {
                     symbol: "Unknown".to_string(),
                 })
             }
+            crate::matching_engine::MatchingError::OrderNotFound
+            | crate::matching_engine::MatchingError::TradeNotFound { .. } => {
+                FixError::Business(crate::fix::error::BusinessError::OrderNotFound {
+                    cl_ord_id: "Unknown".to_string(),
+                })
+            }
             crate::matching_engine::MatchingError::NoLiquidity => {
                 FixError::Business(crate::fix::error::BusinessError::InvalidQuantity { quantity: 0 })
             }
             crate::matching_engine::MatchingError::FOKCannotBeFilled => {
                 FixError::Business(crate::fix::error::BusinessError::InvalidQuantity { quantity: 0 })
             }
-            crate::matching_engine::MatchingError::InternalError(msg) => {
+            crate::matching_engine::MatchingError::NotAcceptedInAuction
+            | crate::matching_engine::MatchingError::NotInAuction => {
+                FixError::Business(crate::fix::error::BusinessError::MarketClosed {
+                    symbol: "Unknown".to_string(),
+                })
+            }
+            crate::matching_engine::MatchingError::InvalidExpiry => {
+                FixError::Validation(crate::fix::error::ValidationError::InvalidFieldValue {
+                    tag: 126,
+                    value: "ExpireTime".to_string(),
+                })
+            }
+            crate::matching_engine::MatchingError::PriceOutOfBand { limit, .. } => {
+                FixError::Business(crate::fix::error::BusinessError::InvalidPrice { price: limit })
+            }
+            crate::matching_engine::MatchingError::InvalidTickSize { price, tick_size } => {
+                FixError::Business(crate::fix::error::BusinessError::InvalidPriceIncrement { price, tick_size })
+            }
+            crate::matching_engine::MatchingError::InvalidLotSize { quantity, .. }
+            | crate::matching_engine::MatchingError::QuantityBelowMinimum { quantity, .. } => {
+                FixError::Business(crate::fix::error::BusinessError::IncorrectQuantity {
+                    quantity,
+                    reason: error.to_string(),
+                })
+            }
+            crate::matching_engine::MatchingError::DuplicateClientOrderId(cl_ord_id) => {
+                FixError::Business(crate::fix::error::BusinessError::DuplicateClOrdId { cl_ord_id })
+            }
+            crate::matching_engine::MatchingError::InvalidQuote { bid_price, .. } => {
+                FixError::Business(crate::fix::error::BusinessError::InvalidPrice { price: bid_price })
+            }
+            crate::matching_engine::MatchingError::SymbolHalted
+            | crate::matching_engine::MatchingError::CircuitBreakerTripped { .. } => {
+                FixError::Business(crate::fix::error::BusinessError::TradingHalt {
+                    symbol: "Unknown".to_string(),
+                })
+            }
+            crate::matching_engine::MatchingError::OrderTypeNotAllowed(_)
+            | crate::matching_engine::MatchingError::TimeInForceNotAllowed(_) => {
+                FixError::Business(crate::fix::error::BusinessError::UnsupportedOrderCharacteristic {
+                    reason: error.to_string(),
+                })
+            }
+            crate::matching_engine::MatchingError::LimitExceeded { .. }
+            | crate::matching_engine::MatchingError::OrderNotionalLimitExceeded { .. } => {
+                FixError::Business(crate::fix::error::BusinessError::PositionLimitExceeded { limit: 0 })
+            }
+            crate::matching_engine::MatchingError::OrderQuantityLimitExceeded { limit, .. } => {
+                FixError::Business(crate::fix::error::BusinessError::PositionLimitExceeded { limit })
+            }
+            crate::matching_engine::MatchingError::OpenOrderLimitExceeded { limit } => {
+                FixError::Business(crate::fix::error::BusinessError::PositionLimitExceeded {
+                    limit: limit as u32,
+                })
+            }
+            crate::matching_engine::MatchingError::InternalError(_)
+            | crate::matching_engine::MatchingError::JournalUnavailable(_) => {
                 FixError::Session(crate::fix::error::SessionError::InvalidSessionState)
             }
         }
@@ -0,0 +1,177 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum IdempotencyError {
+    #[error("Idempotency key {key} reused with a different request")]
+    Conflict { key: String },
+
+    #[error("Request with idempotency key {key} is still in flight")]
+    InFlight { key: String },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum IdempotencyCheck<R> {
+    /// First sighting of the key; it is now reserved until `complete`.
+    Proceed,
+    /// The request already ran and this is its final response.
+    Cached(R),
+}
+
+enum EntryState<R> {
+    Pending,
+    Completed(R),
+}
+
+struct Entry<R> {
+    fingerprint: u64,
+    state: EntryState<R>,
+    expires_at: i64,
+    recency: u64,
+}
+
+pub fn fingerprint<T: Hash>(request: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    request.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Final responses keyed by client-supplied idempotency key, for order entry
+/// paths without FIX sequence numbers. Gateways call `begin` before queueing
+/// a request so that a retry arriving while the original is still queued is
+/// recognised. Entries expire after `ttl_ns` and the least recently used
+/// entry is evicted once `capacity` is reached.
+pub struct IdempotencyCache<R> {
+    entries: HashMap<String, Entry<R>>,
+    by_recency: BTreeMap<u64, String>,
+    next_recency: u64,
+    capacity: usize,
+    ttl_ns: i64,
+}
+
+impl<R: Clone> IdempotencyCache<R> {
+    pub fn new(capacity: usize, ttl_ns: i64) -> Self {
+        Self {
+            entries: HashMap::new(),
+            by_recency: BTreeMap::new(),
+            next_recency: 0,
+            capacity,
+            ttl_ns,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn begin(
+        &mut self,
+        key: &str,
+        fingerprint: u64,
+        now: i64,
+    ) -> Result<IdempotencyCheck<R>, IdempotencyError> {
+        if self.entries.get(key).is_some_and(|e| e.expires_at <= now) {
+            self.remove(key);
+        }
+
+        let recency = self.bump_recency(key);
+
+        if let Some(entry) = self.entries.get_mut(key) {
+            entry.recency = recency;
+
+            if entry.fingerprint != fingerprint {
+                return Err(IdempotencyError::Conflict {
+                    key: key.to_string(),
+                });
+            }
+
+            return match &entry.state {
+                EntryState::Pending => Err(IdempotencyError::InFlight {
+                    key: key.to_string(),
+                }),
+                EntryState::Completed(response) => Ok(IdempotencyCheck::Cached(response.clone())),
+            };
+        }
+
+        if self.entries.len() >= self.capacity.max(1) {
+            self.evict_expired(now);
+        }
+        while self.entries.len() >= self.capacity.max(1) {
+            match self.by_recency.pop_first() {
+                Some((_, oldest)) => {
+                    self.entries.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+
+        self.by_recency.insert(recency, key.to_string());
+        self.entries.insert(
+            key.to_string(),
+            Entry {
+                fingerprint,
+                state: EntryState::Pending,
+                expires_at: now.saturating_add(self.ttl_ns),
+                recency,
+            },
+        );
+
+        Ok(IdempotencyCheck::Proceed)
+    }
+
+    /// Stores the final response for a key reserved by `begin`.
+    pub fn complete(&mut self, key: &str, response: R) {
+        if let Some(entry) = self.entries.get_mut(key) {
+            entry.state = EntryState::Completed(response);
+        }
+    }
+
+    /// Releases a reservation whose request never reached the engine so the
+    /// client may retry it.
+    pub fn abandon(&mut self, key: &str) {
+        if matches!(
+            self.entries.get(key).map(|e| &e.state),
+            Some(EntryState::Pending)
+        ) {
+            self.remove(key);
+        }
+    }
+
+    fn bump_recency(&mut self, key: &str) -> u64 {
+        let recency = self.next_recency;
+        self.next_recency += 1;
+
+        if let Some(entry) = self.entries.get(key) {
+            self.by_recency.remove(&entry.recency);
+            self.by_recency.insert(recency, key.to_string());
+        }
+
+        recency
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.by_recency.remove(&entry.recency);
+        }
+    }
+
+    fn evict_expired(&mut self, now: i64) {
+        let expired: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.expires_at <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        for key in expired {
+            self.remove(&key);
+        }
+    }
+}
@@ -0,0 +1,614 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::warn;
+
+use crate::clock::system_clock;
+use crate::contingent::ContingentTrigger;
+use crate::fees::FeeSchedule;
+use crate::matching_engine::{
+    EngineDigest, EngineSnapshot, MatchingEngine, MatchingEngineConfig, MatchingError,
+};
+use crate::order::TriggerSource;
+use crate::price_utils::Price;
+use crate::orderbook::{MatchPolicy, OrderTypeRules, PriceBands, SymbolSpec, TradingState};
+use crate::snapshot::{OrderSnapshot, SNAPSHOT_FILE_MAGIC};
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum JournalError {
+    /// The replayed command assigned a different order id or had a
+    /// different outcome than when it was journaled.
+    #[error("Replay diverged at sequence {sequence}")]
+    Divergence { sequence: u64 },
+    #[error("Journal starts after sequence {compacted_through}, snapshot is at {snapshot_sequence}")]
+    MissingEntries {
+        snapshot_sequence: u64,
+        compacted_through: u64,
+    },
+    /// Replaying from the snapshot ended in a different state than
+    /// replaying the whole journal. Names the digest components that
+    /// differ.
+    #[error("Snapshot replay differs from full replay in {components:?}")]
+    CompactionMismatch { components: Vec<String> },
+    #[error("Journal I/O error: {0}")]
+    Io(String),
+    /// A journal file record passed its checksum but does not follow the
+    /// record before it.
+    #[error("Journal file record at byte {offset} has sequence {sequence}, expected {expected}")]
+    OutOfSequence {
+        offset: u64,
+        sequence: u64,
+        expected: u64,
+    },
+    /// The engine's journal cannot continue a journal file: the file ends
+    /// before the first entry the journal still holds, or after its last.
+    #[error("Journal file ends at sequence {file}, engine journal holds {compacted_through}..={last_sequence}")]
+    FileMismatch {
+        file: u64,
+        compacted_through: u64,
+        last_sequence: u64,
+    },
+}
+
+impl From<std::io::Error> for JournalError {
+    fn from(err: std::io::Error) -> Self {
+        JournalError::Io(err.to_string())
+    }
+}
+
+/// A state-changing command as the engine received it. Orders are stored
+/// before the engine assigns their id.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum JournalCommand {
+    AddSymbol {
+        symbol: String,
+        match_policy: MatchPolicy,
+        #[serde(default)]
+        spec: SymbolSpec,
+    },
+    PlaceOrder(OrderSnapshot),
+    CancelOrder {
+        symbol: String,
+        order_id: u64,
+    },
+    ReplaceOrder {
+        symbol: String,
+        order_id: u64,
+        price: Price,
+        quantity: u32,
+    },
+    SubmitQuote {
+        symbol: String,
+        user_id: u64,
+        bid_price: Price,
+        bid_quantity: u32,
+        ask_price: Price,
+        ask_quantity: u32,
+    },
+    BustTrade {
+        symbol: String,
+        trade_id: u64,
+    },
+    CancelAllForUser {
+        user_id: u64,
+    },
+    CancelAllForSymbol {
+        symbol: String,
+    },
+    SetTradingState {
+        symbol: String,
+        state: TradingState,
+    },
+    ResumeTrading {
+        symbol: String,
+    },
+    RemoveSymbol {
+        symbol: String,
+    },
+    Uncross {
+        symbol: String,
+    },
+    UpdateReferencePrice {
+        symbol: String,
+        source: TriggerSource,
+        price: Price,
+    },
+    ExpireOrders {
+        current_time: i64,
+    },
+    SetPriceBands {
+        symbol: String,
+        bands: PriceBands,
+    },
+    SetOrderTypeRules {
+        symbol: String,
+        rules: OrderTypeRules,
+    },
+    SetSymbolSpec {
+        symbol: String,
+        spec: SymbolSpec,
+    },
+    SetFeeSchedule {
+        symbol: String,
+        schedule: FeeSchedule,
+    },
+    SetStopTriggerSource {
+        symbol: String,
+        source: TriggerSource,
+    },
+    PlaceContingentOrder {
+        trigger: ContingentTrigger,
+        order: OrderSnapshot,
+    },
+    CancelContingentOrder {
+        contingent_id: u64,
+    },
+    SettleSession {
+        close_time: i64,
+    },
+}
+
+impl JournalCommand {
+    /// Symbol the command acts on, or `None` for commands that span symbols.
+    pub fn symbol(&self) -> Option<&str> {
+        match self {
+            JournalCommand::PlaceOrder(order) | JournalCommand::PlaceContingentOrder { order, .. } => {
+                Some(&order.symbol)
+            }
+            JournalCommand::AddSymbol { symbol, .. }
+            | JournalCommand::CancelOrder { symbol, .. }
+            | JournalCommand::ReplaceOrder { symbol, .. }
+            | JournalCommand::SubmitQuote { symbol, .. }
+            | JournalCommand::BustTrade { symbol, .. }
+            | JournalCommand::CancelAllForSymbol { symbol }
+            | JournalCommand::SetTradingState { symbol, .. }
+            | JournalCommand::ResumeTrading { symbol }
+            | JournalCommand::RemoveSymbol { symbol }
+            | JournalCommand::Uncross { symbol }
+            | JournalCommand::UpdateReferencePrice { symbol, .. }
+            | JournalCommand::SetPriceBands { symbol, .. }
+            | JournalCommand::SetOrderTypeRules { symbol, .. }
+            | JournalCommand::SetSymbolSpec { symbol, .. }
+            | JournalCommand::SetFeeSchedule { symbol, .. }
+            | JournalCommand::SetStopTriggerSource { symbol, .. } => Some(symbol),
+            JournalCommand::CancelAllForUser { .. }
+            | JournalCommand::ExpireOrders { .. }
+            | JournalCommand::CancelContingentOrder { .. }
+            | JournalCommand::SettleSession { .. } => None,
+        }
+    }
+
+    /// Runs the command through the engine's public API, which journals it
+    /// again if the engine is journaling. Returns whether the engine
+    /// accepted it.
+    pub fn apply(&self, engine: &mut MatchingEngine) -> CommandOutcome {
+        match self {
+            JournalCommand::AddSymbol {
+                symbol,
+                match_policy,
+                spec,
+            } => {
+                engine.add_symbol_with(symbol, *match_policy, *spec);
+                CommandOutcome::Accepted
+            }
+            JournalCommand::PlaceOrder(order) => engine.place_order(order.to_order()).outcome(),
+            JournalCommand::CancelOrder { symbol, order_id } => {
+                engine.cancel_order(symbol, *order_id).outcome()
+            }
+            JournalCommand::ReplaceOrder {
+                symbol,
+                order_id,
+                price,
+                quantity,
+            } => engine
+                .replace_order(symbol, *order_id, *price, *quantity)
+                .outcome(),
+            JournalCommand::SubmitQuote {
+                symbol,
+                user_id,
+                bid_price,
+                bid_quantity,
+                ask_price,
+                ask_quantity,
+            } => engine
+                .submit_quote(
+                    symbol,
+                    *user_id,
+                    *bid_price,
+                    *bid_quantity,
+                    *ask_price,
+                    *ask_quantity,
+                )
+                .outcome(),
+            JournalCommand::BustTrade { symbol, trade_id } => {
+                engine.bust_trade(symbol, *trade_id).outcome()
+            }
+            JournalCommand::CancelAllForUser { user_id } => {
+                engine.cancel_all_for_user(*user_id).outcome()
+            }
+            JournalCommand::CancelAllForSymbol { symbol } => {
+                engine.cancel_all_for_symbol(symbol).outcome()
+            }
+            JournalCommand::SetTradingState { symbol, state } => {
+                engine.set_trading_state(symbol, *state).outcome()
+            }
+            JournalCommand::ResumeTrading { symbol } => engine.resume_trading(symbol).outcome(),
+            JournalCommand::RemoveSymbol { symbol } => engine.remove_symbol(symbol).outcome(),
+            JournalCommand::Uncross { symbol } => engine.uncross(symbol).outcome(),
+            JournalCommand::UpdateReferencePrice {
+                symbol,
+                source,
+                price,
+            } => engine
+                .update_reference_price(symbol, *source, *price)
+                .outcome(),
+            JournalCommand::ExpireOrders { current_time } => {
+                engine.process_expired_orders_at(*current_time).outcome()
+            }
+            JournalCommand::SetPriceBands { symbol, bands } => {
+                engine.set_price_bands(symbol, *bands).outcome()
+            }
+            JournalCommand::SetOrderTypeRules { symbol, rules } => {
+                engine.set_order_type_rules(symbol, rules.clone()).outcome()
+            }
+            JournalCommand::SetSymbolSpec { symbol, spec } => {
+                engine.set_symbol_spec(symbol, *spec).outcome()
+            }
+            JournalCommand::SetFeeSchedule { symbol, schedule } => {
+                engine.set_fee_schedule(symbol, *schedule).outcome()
+            }
+            JournalCommand::SetStopTriggerSource { symbol, source } => {
+                engine.set_stop_trigger_source(symbol, *source).outcome()
+            }
+            JournalCommand::PlaceContingentOrder { trigger, order } => engine
+                .place_contingent_order(trigger.clone(), order.to_order())
+                .outcome(),
+            JournalCommand::CancelContingentOrder { contingent_id } => {
+                engine.cancel_contingent_order(*contingent_id).outcome()
+            }
+            JournalCommand::SettleSession { close_time } => {
+                engine.settle_session(*close_time).outcome()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub sequence: u64,
+    /// Engine clock reading when the command was received.
+    pub timestamp: i64,
+    /// Id assigned to the order the command entered, if it got that far.
+    pub order_id: Option<u64>,
+    pub command: JournalCommand,
+    /// How the engine answered the command. `None` on entries journaled
+    /// before outcomes were recorded, which replay does not check.
+    #[serde(default)]
+    pub outcome: Option<CommandOutcome>,
+}
+
+/// Whether the engine accepted a journaled command. Rejections keep the
+/// error text, so replay can tell one rejection from another.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CommandOutcome {
+    Accepted,
+    Rejected(String),
+}
+
+/// Return values of engine commands, read for the outcome the journal
+/// records. Only `MatchingError`s count as rejections.
+pub(crate) trait JournaledOutput {
+    fn outcome(&self) -> CommandOutcome {
+        CommandOutcome::Accepted
+    }
+
+    /// What a command refused without running returns: `error` where the
+    /// command can report one, nothing done otherwise.
+    fn refused(error: MatchingError) -> Self;
+}
+
+impl<T> JournaledOutput for Result<T, MatchingError> {
+    fn outcome(&self) -> CommandOutcome {
+        match self {
+            Ok(_) => CommandOutcome::Accepted,
+            Err(e) => CommandOutcome::Rejected(e.to_string()),
+        }
+    }
+
+    fn refused(error: MatchingError) -> Self {
+        Err(error)
+    }
+}
+
+impl JournaledOutput for () {
+    fn refused(_: MatchingError) -> Self {}
+}
+
+impl<T> JournaledOutput for Option<T> {
+    fn refused(_: MatchingError) -> Self {
+        None
+    }
+}
+
+impl<T> JournaledOutput for Vec<T> {
+    fn refused(_: MatchingError) -> Self {
+        Vec::new()
+    }
+}
+
+/// Ordered record of every command an engine has processed, sufficient to
+/// rebuild its state with `MatchingEngine::replay`. Once compacted it only
+/// holds the commands after a snapshot, and the state is rebuilt with
+/// `MatchingEngine::replay_from_snapshot`.
+#[derive(Default, Serialize, Deserialize)]
+pub struct EngineJournal {
+    entries: Vec<JournalEntry>,
+    /// Sequence of the last command dropped by compaction.
+    #[serde(default)]
+    compacted_through: u64,
+}
+
+impl EngineJournal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An empty journal whose first entry will follow `sequence`.
+    pub fn starting_after(sequence: u64) -> Self {
+        Self {
+            entries: Vec::new(),
+            compacted_through: sequence,
+        }
+    }
+
+    pub fn entries(&self) -> &[JournalEntry] {
+        &self.entries
+    }
+
+    /// Entries with a sequence above `sequence`.
+    pub fn entries_after(&self, sequence: u64) -> &[JournalEntry] {
+        let skip = sequence.saturating_sub(self.compacted_through) as usize;
+        &self.entries[skip.min(self.entries.len())..]
+    }
+
+    pub fn compacted_through(&self) -> u64 {
+        self.compacted_through
+    }
+
+    pub fn last_sequence(&self) -> u64 {
+        self.compacted_through + self.entries.len() as u64
+    }
+
+    /// Drops every entry up to and including `sequence`, normally the
+    /// sequence of a snapshot that covers them.
+    pub fn compact(&mut self, sequence: u64) {
+        let sequence = sequence.min(self.last_sequence());
+        if sequence > self.compacted_through {
+            self.entries.drain(..(sequence - self.compacted_through) as usize);
+            self.compacted_through = sequence;
+        }
+    }
+
+    pub fn record(
+        &mut self,
+        timestamp: i64,
+        order_id: Option<u64>,
+        command: JournalCommand,
+        outcome: CommandOutcome,
+    ) {
+        let sequence = self.last_sequence() + 1;
+        self.entries.push(JournalEntry {
+            sequence,
+            timestamp,
+            order_id,
+            command,
+            outcome: Some(outcome),
+        });
+    }
+}
+
+/// When a `JournalFile` forces appended records to disk. A record that
+/// was written but not synced survives a crash of the process but not of
+/// the machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyncPolicy {
+    #[default]
+    EveryRecord,
+    /// Syncs once this many records are waiting.
+    EveryRecords(u32),
+    /// Leaves it to the operating system, or to `JournalFile::sync`.
+    Never,
+}
+
+/// Bytes before each journal file record's payload: its length and the
+/// CRC32 of the payload, both little-endian `u32`s.
+const RECORD_HEADER_LENGTH: usize = 8;
+
+/// Append-only file of journal entries, each a JSON payload framed by its
+/// length and checksum. Attach one with `MatchingEngine::attach_journal_file`
+/// and rebuild the engine after a crash with `recover`.
+pub struct JournalFile {
+    file: File,
+    sync: SyncPolicy,
+    last_sequence: u64,
+    unsynced: u32,
+}
+
+impl JournalFile {
+    /// Opens the file at `path`, creating it if needed. A record torn by a
+    /// crash mid-append, and anything after it, is cut off.
+    pub fn open(path: impl AsRef<Path>, sync: SyncPolicy) -> Result<Self, JournalError> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)?;
+        let (entries, complete) = read_records(&mut file)?;
+        if complete < file.metadata()?.len() {
+            file.set_len(complete)?;
+        }
+        Ok(Self {
+            file,
+            sync,
+            last_sequence: entries.last().map_or(0, |entry| entry.sequence),
+            unsynced: 0,
+        })
+    }
+
+    pub fn last_sequence(&self) -> u64 {
+        self.last_sequence
+    }
+
+    /// Appends `entry`, which must continue the file's sequence unless the
+    /// file is empty, and syncs as the policy says.
+    pub fn append(&mut self, entry: &JournalEntry) -> Result<(), JournalError> {
+        if self.last_sequence > 0 && entry.sequence != self.last_sequence + 1 {
+            return Err(JournalError::OutOfSequence {
+                offset: self.file.metadata()?.len(),
+                sequence: entry.sequence,
+                expected: self.last_sequence + 1,
+            });
+        }
+
+        let payload = serde_json::to_vec(entry).map_err(|e| JournalError::Io(e.to_string()))?;
+        let mut record = Vec::with_capacity(RECORD_HEADER_LENGTH + payload.len());
+        record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        record.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+        record.extend_from_slice(&payload);
+        self.file.write_all(&record)?;
+        self.last_sequence = entry.sequence;
+
+        self.unsynced += 1;
+        match self.sync {
+            SyncPolicy::EveryRecord => self.sync()?,
+            SyncPolicy::EveryRecords(records) if self.unsynced >= records => self.sync()?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Forces every appended record to disk.
+    pub fn sync(&mut self) -> Result<(), JournalError> {
+        self.file.sync_data()?;
+        self.unsynced = 0;
+        Ok(())
+    }
+}
+
+/// Reads the journal file at `path` up to its first torn or corrupt
+/// record, without changing it.
+pub fn read_journal_file(path: impl AsRef<Path>) -> Result<EngineJournal, JournalError> {
+    let (entries, _) = read_records(&mut File::open(path)?)?;
+    let mut journal =
+        EngineJournal::starting_after(entries.first().map_or(0, |entry| entry.sequence - 1));
+    journal.entries = entries;
+    Ok(journal)
+}
+
+/// The entries of a journal file's intact records, and the length they
+/// span. Reading stops at the first record that is cut short, fails its
+/// checksum or does not decode: what a crash mid-append leaves behind.
+fn read_records(file: &mut File) -> Result<(Vec<JournalEntry>, u64), JournalError> {
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+
+    let mut entries: Vec<JournalEntry> = Vec::new();
+    let mut offset = 0;
+    while let Some(header) = bytes.get(offset..offset + RECORD_HEADER_LENGTH) {
+        let length = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+        let checksum = u32::from_le_bytes(header[4..].try_into().unwrap());
+        let start = offset + RECORD_HEADER_LENGTH;
+        let Some(payload) = bytes.get(start..start + length) else {
+            break;
+        };
+        if crc32fast::hash(payload) != checksum {
+            break;
+        }
+        let Ok(entry) = serde_json::from_slice::<JournalEntry>(payload) else {
+            break;
+        };
+        if let Some(last) = entries.last() {
+            if entry.sequence != last.sequence + 1 {
+                return Err(JournalError::OutOfSequence {
+                    offset: offset as u64,
+                    sequence: entry.sequence,
+                    expected: last.sequence + 1,
+                });
+            }
+        }
+        entries.push(entry);
+        offset = start + length;
+    }
+
+    if offset < bytes.len() {
+        warn!(
+            "Journal file has {} bytes past its last intact record at byte {}",
+            bytes.len() - offset,
+            offset
+        );
+    }
+    Ok((entries, offset as u64))
+}
+
+/// Rebuilds an engine after a crash from the snapshot at `snapshot_path`,
+/// if there is one, and the journal file at `journal_path`. The snapshot
+/// may be a file written by `EngineSnapshot::save_to_file`, such as the
+/// `SnapshotScheduler`'s, or bare JSON as `save_snapshot_to_file` writes
+/// it. The entries after the snapshot's sequence are replayed, or the
+/// whole file without a snapshot. A torn record at the end of the file is
+/// cut off. The engine comes back on the system clock, appending to the
+/// same file.
+pub fn recover(
+    config: MatchingEngineConfig,
+    snapshot_path: impl AsRef<Path>,
+    journal_path: impl AsRef<Path>,
+    sync: SyncPolicy,
+) -> Result<MatchingEngine, JournalError> {
+    let file = JournalFile::open(&journal_path, sync)?;
+    let journal = read_journal_file(&journal_path)?;
+
+    let snapshot = match std::fs::read(snapshot_path) {
+        Ok(bytes) if bytes.starts_with(&SNAPSHOT_FILE_MAGIC) => Some(
+            EngineSnapshot::decode_file(&bytes).map_err(|e| JournalError::Io(e.to_string()))?,
+        ),
+        Ok(bytes) => Some(
+            serde_json::from_slice::<EngineSnapshot>(&bytes)
+                .map_err(|e| JournalError::Io(e.to_string()))?,
+        ),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    let mut engine = match &snapshot {
+        Some(snapshot) => MatchingEngine::replay_from_snapshot(config, snapshot, &journal)?,
+        None => MatchingEngine::replay_with_config(config, &journal)?,
+    };
+    engine.set_clock(system_clock());
+    engine.attach_journal_file(file)?;
+    Ok(engine)
+}
+
+/// Rebuilds the engine both from the whole of `journal` and from
+/// `snapshot` plus the entries after it, and checks the two agree. The
+/// snapshot must come from the engine that wrote the journal, and the
+/// journal must not be compacted. A mismatch means some command's effect
+/// depends on state the snapshot does not persist. Returns the digest both
+/// replays reached.
+pub fn verify_compaction(
+    config: &MatchingEngineConfig,
+    snapshot: &EngineSnapshot,
+    journal: &EngineJournal,
+) -> Result<EngineDigest, JournalError> {
+    let full = MatchingEngine::replay_with_config(config.clone(), journal)?.state_digest();
+    let compacted =
+        MatchingEngine::replay_from_snapshot(config.clone(), snapshot, journal)?.state_digest();
+
+    let components = full.differences(&compacted);
+    if components.is_empty() {
+        Ok(full)
+    } else {
+        Err(JournalError::CompactionMismatch { components })
+    }
+}
@@ -0,0 +1,523 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crossbeam::channel::{bounded, Receiver, Sender, TrySendError};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::metrics::{LatencyHistogram, LatencyPercentiles};
+use crate::order::{Order, Side};
+use crate::orderbook::MarketDepth;
+use crate::price_utils::Price;
+
+/// One change to the resting orders of a book. Sequence numbers are per
+/// book, start at 1 and have no gaps: every change is numbered whether or
+/// not anyone listens, so a consumer resuming from a snapshot can tell
+/// where the snapshot sits in the stream.
+///
+/// Quantities are open quantities; an order leaves the book once its open
+/// quantity reaches zero. `visible_quantity` is what the order shows after
+/// the event, which differs from `quantity` only for icebergs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum L3Event {
+    /// The order joined the back of the queue at `price`.
+    Add {
+        sequence: u64,
+        order_id: u64,
+        side: Side,
+        price: Price,
+        quantity: u32,
+        visible_quantity: u32,
+    },
+    /// The order left the book with `quantity` still open.
+    Cancel {
+        sequence: u64,
+        order_id: u64,
+        side: Side,
+        price: Price,
+        quantity: u32,
+    },
+    /// `quantity` of the order traded.
+    Execute {
+        sequence: u64,
+        order_id: u64,
+        side: Side,
+        price: Price,
+        quantity: u32,
+        visible_quantity: u32,
+    },
+    /// The order now has `quantity` open. It keeps its place in the queue
+    /// when `keeps_priority` is set and moves to the back otherwise, as an
+    /// iceberg showing a fresh slice does.
+    Replace {
+        sequence: u64,
+        order_id: u64,
+        side: Side,
+        price: Price,
+        quantity: u32,
+        visible_quantity: u32,
+        keeps_priority: bool,
+    },
+}
+
+impl L3Event {
+    pub fn sequence(&self) -> u64 {
+        match self {
+            L3Event::Add { sequence, .. }
+            | L3Event::Cancel { sequence, .. }
+            | L3Event::Execute { sequence, .. }
+            | L3Event::Replace { sequence, .. } => *sequence,
+        }
+    }
+
+    pub fn order_id(&self) -> u64 {
+        match self {
+            L3Event::Add { order_id, .. }
+            | L3Event::Cancel { order_id, .. }
+            | L3Event::Execute { order_id, .. }
+            | L3Event::Replace { order_id, .. } => *order_id,
+        }
+    }
+}
+
+pub type L3Callback = Arc<dyn Fn(&L3Event) + Send + Sync>;
+
+/// Consecutive changes of one book published together, in sequence order.
+/// A sweep through hundreds of resting orders reaches batch subscribers as
+/// one batch rather than hundreds of events.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L3Batch {
+    pub events: Vec<L3Event>,
+}
+
+impl L3Batch {
+    pub fn first_sequence(&self) -> u64 {
+        self.events.first().map_or(0, L3Event::sequence)
+    }
+
+    pub fn last_sequence(&self) -> u64 {
+        self.events.last().map_or(0, L3Event::sequence)
+    }
+}
+
+/// How a feed's batches were formed. `hold` is the time from the start of
+/// the first command in a batch to its publication, which is what batching
+/// adds to the latency of the batch's first change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct L3BatchMetrics {
+    pub batches: u64,
+    pub events: u64,
+    pub largest_batch: u64,
+    pub hold: LatencyPercentiles,
+}
+
+#[derive(Default)]
+struct Batcher {
+    sinks: Vec<Sender<L3Batch>>,
+    window_ns: i64,
+    pending: Vec<L3Event>,
+    /// Start of the first command whose changes are pending.
+    pending_since: Option<i64>,
+    batches: u64,
+    events: u64,
+    largest_batch: u64,
+    hold: LatencyHistogram,
+}
+
+enum L3Sink {
+    Callback(L3Callback),
+    Channel(Sender<L3Event>),
+}
+
+/// Numbers a book's changes and hands them to its listeners. Callbacks
+/// run synchronously; channel subscribers whose buffer is full miss the
+/// event and see the gap in the sequence, and those whose receiver has
+/// been dropped are removed.
+#[derive(Default)]
+pub struct L3Feed {
+    last_sequence: u64,
+    sinks: Vec<L3Sink>,
+    batcher: Batcher,
+}
+
+impl L3Feed {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sequence of the last change, zero before the first.
+    pub fn last_sequence(&self) -> u64 {
+        self.last_sequence
+    }
+
+    pub fn has_listeners(&self) -> bool {
+        !self.sinks.is_empty() || !self.batcher.sinks.is_empty()
+    }
+
+    /// Receives every event from now on, buffering up to `capacity`.
+    pub fn subscribe(&mut self, capacity: usize) -> Receiver<L3Event> {
+        let (sender, receiver) = bounded(capacity);
+        self.sinks.push(L3Sink::Channel(sender));
+        receiver
+    }
+
+    pub fn on_event(&mut self, callback: L3Callback) {
+        self.sinks.push(L3Sink::Callback(callback));
+    }
+
+    /// Receives the changes from now on in batches, buffering up to
+    /// `capacity` batches. The changes of one command always share a
+    /// batch. With a nonzero `window`, the changes of later commands join
+    /// the batch until `window` has passed since the first of them began.
+    /// The feed keeps one window, the shortest any subscriber asked for.
+    pub fn subscribe_batches(&mut self, capacity: usize, window: Duration) -> Receiver<L3Batch> {
+        let window_ns = window.as_nanos().min(i64::MAX as u128) as i64;
+        let batcher = &mut self.batcher;
+        batcher.window_ns = if batcher.sinks.is_empty() {
+            window_ns
+        } else {
+            batcher.window_ns.min(window_ns)
+        };
+        let (sender, receiver) = bounded(capacity);
+        batcher.sinks.push(sender);
+        receiver
+    }
+
+    /// Closes the command that started at `started`, publishing the
+    /// pending batch if its window has passed by `now`.
+    pub fn end_command(&mut self, started: i64, now: i64) {
+        if self.batcher.pending.is_empty() {
+            return;
+        }
+        let since = *self.batcher.pending_since.get_or_insert(started);
+        if now - since >= self.batcher.window_ns {
+            self.flush_batch(now);
+        }
+    }
+
+    /// Publishes the pending batch if its window has passed by `now`. Meant
+    /// to be driven by a timer at the window's period, so a batch is not
+    /// held past its window waiting for another command.
+    pub fn flush_due(&mut self, now: i64) {
+        if self
+            .batcher
+            .pending_since
+            .is_some_and(|since| now - since >= self.batcher.window_ns)
+        {
+            self.flush_batch(now);
+        }
+    }
+
+    pub fn batch_metrics(&self) -> L3BatchMetrics {
+        let batcher = &self.batcher;
+        L3BatchMetrics {
+            batches: batcher.batches,
+            events: batcher.events,
+            largest_batch: batcher.largest_batch,
+            hold: batcher.hold.snapshot(),
+        }
+    }
+
+    /// Subscribers whose buffer is full miss the batch and see the gap in
+    /// the sequence.
+    fn flush_batch(&mut self, now: i64) {
+        let batcher = &mut self.batcher;
+        let since = batcher.pending_since.take().unwrap_or(now);
+        let batch = L3Batch {
+            events: std::mem::take(&mut batcher.pending),
+        };
+        if batch.events.is_empty() {
+            return;
+        }
+
+        batcher.batches += 1;
+        batcher.events += batch.events.len() as u64;
+        batcher.largest_batch = batcher.largest_batch.max(batch.events.len() as u64);
+        batcher.hold.record(now.saturating_sub(since) as u64);
+        batcher.sinks.retain(|sender| {
+            !matches!(
+                sender.try_send(batch.clone()),
+                Err(TrySendError::Disconnected(_))
+            )
+        });
+    }
+
+    /// Numbers the next change and, when anyone listens, builds its event
+    /// with `event` and delivers it. Batch subscribers get it with the
+    /// rest of its batch.
+    pub fn publish(&mut self, event: impl FnOnce(u64) -> L3Event) {
+        self.last_sequence += 1;
+        if !self.has_listeners() {
+            return;
+        }
+
+        let event = event(self.last_sequence);
+        if !self.batcher.sinks.is_empty() {
+            self.batcher.pending.push(event.clone());
+        }
+        self.sinks.retain(|sink| match sink {
+            L3Sink::Callback(callback) => {
+                callback(&event);
+                true
+            }
+            L3Sink::Channel(sender) => !matches!(
+                sender.try_send(event.clone()),
+                Err(TrySendError::Disconnected(_))
+            ),
+        });
+    }
+
+    /// `order` joined the back of its level.
+    pub fn add(&mut self, order: &Order) {
+        self.publish(|sequence| L3Event::Add {
+            sequence,
+            order_id: order.id,
+            side: order.side,
+            price: order.price,
+            quantity: order.remaining_quantity(),
+            visible_quantity: order.visible_quantity(),
+        });
+    }
+
+    /// `order` left the book unfilled.
+    pub fn cancel(&mut self, order: &Order) {
+        self.publish(|sequence| L3Event::Cancel {
+            sequence,
+            order_id: order.id,
+            side: order.side,
+            price: order.price,
+            quantity: order.remaining_quantity(),
+        });
+    }
+
+    /// `quantity` of resting `order` traded; `order` already records it.
+    pub fn execute(&mut self, order: &Order, quantity: u32) {
+        self.publish(|sequence| L3Event::Execute {
+            sequence,
+            order_id: order.id,
+            side: order.side,
+            price: order.price,
+            quantity,
+            visible_quantity: order.visible_quantity(),
+        });
+    }
+
+    /// Open quantity of resting `order` changed outside matching, or it
+    /// moved to the back of its level.
+    pub fn replace(&mut self, order: &Order, keeps_priority: bool) {
+        self.publish(|sequence| L3Event::Replace {
+            sequence,
+            order_id: order.id,
+            side: order.side,
+            price: order.price,
+            quantity: order.remaining_quantity(),
+            visible_quantity: order.visible_quantity(),
+            keeps_priority,
+        });
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum L3Error {
+    #[error("Sequence gap: expected {expected}, received {received}")]
+    SequenceGap { expected: u64, received: u64 },
+    #[error("Order {0} is not on the book")]
+    UnknownOrder(u64),
+}
+
+/// Visible volume at one price before and after a batch. A level the
+/// batch created has `before` zero and one it emptied has `after` zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelChange {
+    pub side: Side,
+    pub price: Price,
+    pub before: u64,
+    pub after: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct L3BookOrder {
+    pub order_id: u64,
+    pub quantity: u32,
+    pub visible_quantity: u32,
+}
+
+/// A book rebuilt from an L3 stream. Levels hold their orders in queue
+/// order.
+#[derive(Debug, Default)]
+pub struct L3Book {
+    last_sequence: u64,
+    pub bids: BTreeMap<Price, Vec<L3BookOrder>>,
+    pub asks: BTreeMap<Price, Vec<L3BookOrder>>,
+}
+
+impl L3Book {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn last_sequence(&self) -> u64 {
+        self.last_sequence
+    }
+
+    /// Applies the next event of the stream. An event out of sequence is
+    /// refused and leaves the book as it was.
+    pub fn apply(&mut self, event: &L3Event) -> Result<(), L3Error> {
+        let expected = self.last_sequence + 1;
+        if event.sequence() != expected {
+            return Err(L3Error::SequenceGap {
+                expected,
+                received: event.sequence(),
+            });
+        }
+
+        match *event {
+            L3Event::Add {
+                order_id,
+                side,
+                price,
+                quantity,
+                visible_quantity,
+                ..
+            } => {
+                self.levels(side)
+                    .entry(price)
+                    .or_default()
+                    .push(L3BookOrder {
+                        order_id,
+                        quantity,
+                        visible_quantity,
+                    });
+            }
+            L3Event::Cancel {
+                order_id,
+                side,
+                price,
+                ..
+            } => {
+                self.take(side, price, order_id)?;
+            }
+            L3Event::Execute {
+                order_id,
+                side,
+                price,
+                quantity,
+                visible_quantity,
+                ..
+            } => {
+                let level = self.levels(side).get_mut(&price);
+                let order = level
+                    .and_then(|orders| orders.iter_mut().find(|o| o.order_id == order_id))
+                    .ok_or(L3Error::UnknownOrder(order_id))?;
+                order.quantity = order.quantity.saturating_sub(quantity);
+                order.visible_quantity = visible_quantity;
+                if order.quantity == 0 {
+                    self.take(side, price, order_id)?;
+                }
+            }
+            L3Event::Replace {
+                order_id,
+                side,
+                price,
+                quantity,
+                visible_quantity,
+                keeps_priority,
+                ..
+            } => {
+                let level = self.levels(side).get_mut(&price);
+                let index = level
+                    .as_ref()
+                    .and_then(|orders| orders.iter().position(|o| o.order_id == order_id))
+                    .ok_or(L3Error::UnknownOrder(order_id))?;
+                let orders = level.unwrap();
+                orders[index].quantity = quantity;
+                orders[index].visible_quantity = visible_quantity;
+                if !keeps_priority {
+                    let order = orders.remove(index);
+                    orders.push(order);
+                }
+            }
+        }
+
+        self.last_sequence = expected;
+        Ok(())
+    }
+
+    /// Applies every event of `batch` and returns the levels whose visible
+    /// volume it changed, in the order the batch first touched them. A
+    /// level changed and then restored within the batch is left out. On an
+    /// error the events before the failing one stay applied.
+    pub fn apply_batch(&mut self, batch: &L3Batch) -> Result<Vec<LevelChange>, L3Error> {
+        let mut touched: Vec<LevelChange> = Vec::new();
+        for event in &batch.events {
+            let (side, price) = match *event {
+                L3Event::Add { side, price, .. }
+                | L3Event::Cancel { side, price, .. }
+                | L3Event::Execute { side, price, .. }
+                | L3Event::Replace { side, price, .. } => (side, price),
+            };
+            if !touched
+                .iter()
+                .any(|level| level.side == side && level.price == price)
+            {
+                let before = self.visible_volume(side, price);
+                touched.push(LevelChange {
+                    side,
+                    price,
+                    before,
+                    after: before,
+                });
+            }
+            self.apply(event)?;
+        }
+
+        for level in &mut touched {
+            level.after = self.visible_volume(level.side, level.price);
+        }
+        touched.retain(|level| level.before != level.after);
+        Ok(touched)
+    }
+
+    /// Aggregated depth of the top `levels` prices on each side, as
+    /// `OrderBook::get_market_depth` reports it.
+    pub fn depth(&self, levels: usize) -> MarketDepth {
+        let volume = |(&price, orders): (&Price, &Vec<L3BookOrder>)| {
+            let visible = orders.iter().map(|o| o.visible_quantity as u64).sum();
+            (price, visible)
+        };
+        MarketDepth::new(
+            self.bids.iter().rev().take(levels).map(volume).collect(),
+            self.asks.iter().take(levels).map(volume).collect(),
+        )
+    }
+
+    fn visible_volume(&mut self, side: Side, price: Price) -> u64 {
+        self.levels(side).get(&price).map_or(0, |orders| {
+            orders.iter().map(|o| o.visible_quantity as u64).sum()
+        })
+    }
+
+    fn levels(&mut self, side: Side) -> &mut BTreeMap<Price, Vec<L3BookOrder>> {
+        match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        }
+    }
+
+    fn take(&mut self, side: Side, price: Price, order_id: u64) -> Result<L3BookOrder, L3Error> {
+        let levels = self.levels(side);
+        let orders = levels
+            .get_mut(&price)
+            .ok_or(L3Error::UnknownOrder(order_id))?;
+        let index = orders
+            .iter()
+            .position(|o| o.order_id == order_id)
+            .ok_or(L3Error::UnknownOrder(order_id))?;
+        let order = orders.remove(index);
+        if orders.is_empty() {
+            levels.remove(&price);
+        }
+        Ok(order)
+    }
+}
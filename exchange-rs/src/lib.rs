@@ -1,13 +1,38 @@
+pub mod account_status;
+pub mod admin;
+pub mod clock;
+pub mod contingent;
+pub mod credit;
+pub mod determinism;
+pub mod digest;
+pub mod events;
+pub mod fees;
+pub mod idempotency;
+pub mod journal;
+pub mod l3_feed;
+pub mod logging;
 pub mod matching_engine;
 pub mod metrics;
 pub mod optimizations;
 pub mod order;
 pub mod orderbook;
+pub mod participants;
+pub mod positions;
+pub mod projection;
+pub mod redaction;
+pub mod replication;
+pub mod risk;
+pub mod settlement;
 pub mod snapshot;
+pub mod snapshot_diff;
+pub mod snapshot_scheduler;
+pub mod tasks;
+pub mod timers;
+pub mod top_of_book;
 pub mod fix;
 pub mod fix_gateway;
 pub mod sbe;
 pub mod price_utils;
 
 
-pub use price_utils::{PRICE_SCALE_FACTOR, QUANTITY_SCALE_FACTOR};
\ No newline at end of file
+pub use price_utils::{Price, PRICE_SCALE_FACTOR, QUANTITY_SCALE_FACTOR};
\ No newline at end of file
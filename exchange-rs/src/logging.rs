@@ -0,0 +1,199 @@
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use thiserror::Error;
+use tracing::info;
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::{reload, Registry};
+
+use crate::clock::{SharedClock, SystemClock};
+
+/// Targets on the matching path. Their level is capped unless a change is
+/// forced, so raising a parent target or the default cannot flood them.
+pub const HOT_PATH_TARGETS: [&str; 1] = ["exchange_rs::matching_engine"];
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum LoggingError {
+    #[error("Invalid log level: {level}")]
+    InvalidLevel { level: String },
+    #[error("{target} cannot be raised above {max} without force")]
+    HotPathLimit { target: String, max: LevelFilter },
+    #[error("Failed to reload log filter: {reason}")]
+    Reload { reason: String },
+}
+
+/// Body of `GET /logging`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogLevels {
+    pub default: String,
+    pub targets: BTreeMap<String, String>,
+}
+
+/// Body of `PUT /logging`. Without a target the default level is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetLogLevel {
+    pub actor: String,
+    #[serde(default)]
+    pub target: Option<String>,
+    pub level: String,
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogLevelAudit {
+    pub timestamp: i64,
+    pub actor: String,
+    pub target: Option<String>,
+    pub previous: Option<String>,
+    pub level: String,
+    pub forced: bool,
+}
+
+#[derive(Clone)]
+struct FilterState {
+    default: LevelFilter,
+    targets: BTreeMap<String, LevelFilter>,
+}
+
+impl FilterState {
+    /// Level a target gets from its most specific configured ancestor.
+    fn inherited_level(&self, target: &str) -> LevelFilter {
+        self.targets
+            .iter()
+            .filter(|(configured, _)| target.starts_with(configured.as_str()))
+            .max_by_key(|(configured, _)| configured.len())
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default)
+    }
+
+    fn to_filter(&self, hot_path_max: LevelFilter) -> Targets {
+        let pinned = HOT_PATH_TARGETS
+            .iter()
+            .filter(|target| !self.targets.contains_key(**target))
+            .filter(|target| self.inherited_level(target) > hot_path_max)
+            .map(|target| (target.to_string(), hot_path_max));
+
+        Targets::new()
+            .with_default(self.default)
+            .with_targets(pinned)
+            .with_targets(self.targets.clone())
+    }
+}
+
+pub type LogFilterLayer = reload::Layer<Targets, Registry>;
+
+/// Per-target log levels that can be changed while running. Every change is
+/// audited.
+pub struct LogLevelController {
+    handle: reload::Handle<Targets, Registry>,
+    state: Mutex<FilterState>,
+    audit: Mutex<Vec<LogLevelAudit>>,
+    hot_path_max: LevelFilter,
+    clock: SharedClock,
+}
+
+impl LogLevelController {
+    /// Returns the filter layer to install on the registry and the controller
+    /// for it. Hot-path targets are capped at INFO in release builds.
+    pub fn new(default: LevelFilter) -> (LogFilterLayer, Self) {
+        let hot_path_max = if cfg!(debug_assertions) {
+            LevelFilter::TRACE
+        } else {
+            LevelFilter::INFO
+        };
+        Self::with_limits(default, hot_path_max, Arc::new(SystemClock))
+    }
+
+    pub fn with_limits(
+        default: LevelFilter,
+        hot_path_max: LevelFilter,
+        clock: SharedClock,
+    ) -> (LogFilterLayer, Self) {
+        let state = FilterState {
+            default,
+            targets: BTreeMap::new(),
+        };
+        let (layer, handle) = reload::Layer::new(state.to_filter(hot_path_max));
+        let controller = Self {
+            handle,
+            state: Mutex::new(state),
+            audit: Mutex::new(Vec::new()),
+            hot_path_max,
+            clock,
+        };
+        (layer, controller)
+    }
+
+    pub fn levels(&self) -> LogLevels {
+        let state = self.state.lock();
+        LogLevels {
+            default: state.default.to_string(),
+            targets: state
+                .targets
+                .iter()
+                .map(|(target, level)| (target.clone(), level.to_string()))
+                .collect(),
+        }
+    }
+
+    /// Applies `change` immediately and records it in the audit log. The
+    /// levels only change once the new filter is installed, so a failed
+    /// reload leaves them as they were.
+    pub fn set_level(&self, change: &SetLogLevel) -> Result<LogLevelAudit, LoggingError> {
+        let level: LevelFilter = change
+            .level
+            .parse()
+            .map_err(|_| LoggingError::InvalidLevel {
+                level: change.level.clone(),
+            })?;
+
+        if let Some(target) = &change.target {
+            let on_hot_path = HOT_PATH_TARGETS.iter().any(|hot| target.starts_with(hot));
+            if on_hot_path && level > self.hot_path_max && !change.force {
+                return Err(LoggingError::HotPathLimit {
+                    target: target.clone(),
+                    max: self.hot_path_max,
+                });
+            }
+        }
+
+        let mut state = self.state.lock();
+        let mut next = state.clone();
+        let previous = match &change.target {
+            Some(target) => next.targets.insert(target.clone(), level),
+            None => Some(std::mem::replace(&mut next.default, level)),
+        };
+        self.handle
+            .reload(next.to_filter(self.hot_path_max))
+            .map_err(|error| LoggingError::Reload {
+                reason: error.to_string(),
+            })?;
+        *state = next;
+        drop(state);
+
+        let entry = LogLevelAudit {
+            timestamp: self.clock.now_nanos(),
+            actor: change.actor.clone(),
+            target: change.target.clone(),
+            previous: previous.map(|level| level.to_string()),
+            level: level.to_string(),
+            forced: change.force,
+        };
+        info!(
+            target: "audit",
+            actor = %entry.actor,
+            log_target = entry.target.as_deref().unwrap_or("default"),
+            level = %entry.level,
+            forced = entry.forced,
+            "log level changed"
+        );
+        self.audit.lock().push(entry.clone());
+        Ok(entry)
+    }
+
+    pub fn audit_log(&self) -> Vec<LogLevelAudit> {
+        self.audit.lock().clone()
+    }
+}
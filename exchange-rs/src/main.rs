@@ -1,27 +1,40 @@
-mod matching_engine;
-mod optimizations;
-mod order;
-mod orderbook;
-mod metrics;
-mod snapshot;
-mod fix;
-mod fix_gateway;
-
 use parking_lot::Mutex;
 use std::sync::Arc;
 
-use matching_engine::MatchingEngine;
-use optimizations::{OrderPool, OrderProcessorPool};
-use order::{Order, OrderType, Side};
-use fix_gateway::FixGateway;
+use exchange_rs::admin::AdminServer;
+use exchange_rs::fix_gateway::FixGateway;
+use exchange_rs::logging::LogLevelController;
+use exchange_rs::matching_engine::MatchingEngine;
+use exchange_rs::optimizations::{OrderPool, OrderProcessorPool};
+use exchange_rs::order::{Order, OrderType, Side};
+use exchange_rs::tasks::{RestartPolicy, TaskRegistry, TaskSpec};
+use exchange_rs::timers::Timers;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
 
 #[tokio::main]
 async fn main() {
-    tracing_subscriber::fmt::init();
-    
-    println!("Exchange-RS: High-performance limit order book implementation with FIX support");
+    let (log_filter, log_levels) = LogLevelController::new(LevelFilter::INFO);
+    tracing_subscriber::registry()
+        .with(log_filter)
+        .with(tracing_subscriber::fmt::layer())
+        .init();
 
     let engine = Arc::new(Mutex::new(MatchingEngine::new()));
+    let tasks = TaskRegistry::new();
+    let admin = AdminServer::new()
+        .with_logging(Arc::new(log_levels))
+        .with_tasks(tasks.clone())
+        .with_engine(Arc::clone(&engine));
+    match tokio::net::TcpListener::bind("127.0.0.1:9880").await {
+        Ok(listener) => {
+            println!("Admin endpoints on 127.0.0.1:9880");
+            tokio::spawn(admin.serve(listener));
+        }
+        Err(e) => eprintln!("Admin server failed to start: {}", e),
+    }
+    
+    println!("Exchange-RS: High-performance limit order book implementation with FIX support");
 
     {
         let mut engine_ref = engine.lock();
@@ -36,6 +49,35 @@ async fn main() {
     println!("Starting order processor pool with {} workers", num_workers);
     let pool = OrderProcessorPool::new(num_workers, Arc::clone(&engine));
 
+    let timers = Timers::new(engine.lock().clock());
+    engine.lock().attach_timers(timers.clone());
+    {
+        let engine = Arc::clone(&engine);
+        let spec = TaskSpec::new("timers").restart(RestartPolicy::OnFailure {
+            initial_backoff: std::time::Duration::from_millis(100),
+            max_backoff: std::time::Duration::from_secs(5),
+        });
+        let started = tasks.spawn(spec, move |context| {
+            let (engine, timers) = (Arc::clone(&engine), timers.clone());
+            async move {
+                let mut ticker = tokio::time::interval(std::time::Duration::from_millis(10));
+                while !context.is_shutdown() {
+                    ticker.tick().await;
+                    timers.tick();
+                    if let Err(e) = engine.lock().run_timers() {
+                        tracing::error!("Order expiry failed: {}", e);
+                    }
+                    context.record_activity();
+                }
+                Ok(())
+            }
+        });
+        if let Err(e) = started {
+            eprintln!("Timer task failed to start: {}", e);
+        }
+        tasks.spawn_watchdog(std::time::Duration::from_secs(1));
+    }
+
     let order_pool = OrderPool::new(1000);
     println!("Created order pool with initial capacity of 1000 orders");
 
@@ -1,24 +1,68 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime};
 
+use crossbeam::channel::Receiver;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-
-use crate::metrics::{LatencyMetrics, LatencyMetricsSnapshot, OrderMetrics, OrderMetricsSnapshot};
-use crate::order::{Order, OrderStatus, OrderType, Side, TimeInForce};
-use crate::orderbook::OrderBook;
-use crate::snapshot::OrderBookSnapshot;
-
-#[derive(Debug, Clone)]
+use tracing::warn;
+
+use crate::clock::{system_clock, ManualClock, SharedClock};
+use crate::contingent::{ContingentOrder, ContingentOrderBook, ContingentTrigger};
+use crate::digest::StableHasher;
+use crate::fees::FeeSchedule;
+use crate::journal::{
+    CommandOutcome, EngineJournal, JournalCommand, JournalEntry, JournalError, JournalFile,
+    JournaledOutput,
+};
+use crate::events::{
+    EngineCallbacks, EngineEvent, EventBus, EventRing, OrderStatusCallback, SequencedEvent, TradeCallback,
+};
+use crate::l3_feed::{L3Batch, L3BatchMetrics, L3Event};
+use crate::metrics::{
+    LatencyHistogram, LatencyMetrics, LatencyMetricsSnapshot, LatencyPercentiles, OrderMetrics,
+    OrderMetricsSnapshot,
+};
+use crate::order::{
+    Order, OrderStatus, OrderStatusReport, OrderType, Side, TimeInForce, TriggerSource,
+};
+use crate::participants::{ParticipantError, ParticipantRegistry};
+use crate::price_utils::{notional, price_from_u64, price_magnitude, Price};
+use crate::risk::{OpenOrderCounts, RiskLimits};
+use crate::orderbook::{
+    BookInvariantError, IndicativeUncross, MarketDepth, MatchPolicy, OrderBook, OrderTypeRules, PriceBands, PriceLevel, SymbolSpec,
+    TradingState,
+};
+use crate::settlement::{
+    calculate_settlement_price, trading_day, SettlementMethod, SettlementPrice, SettlementStore,
+};
+use crate::snapshot::{
+    decode_snapshot_file, read_snapshot_file, write_snapshot_file, ContingentOrderSnapshot, OrderBookSnapshot,
+    OrderSnapshot, SnapshotFileError, SnapshotFormat,
+};
+use crate::timers::{FiredTimer, TimerEvent, TimerHandle, TimerTarget, Timers};
+
+/// Most rounds of stop triggering a single command cascades through.
+pub const MAX_STOP_CASCADE_DEPTH: usize = 32;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Trade {
     pub id: u64,
     pub buy_order_id: u64,
     pub sell_order_id: u64,
-    pub price: u64,
+    pub price: Price,
     pub quantity: u32,
     pub timestamp: i64,
+    /// Fee charged to the resting order.
+    pub maker_fee: u64,
+    /// Fee charged to the aggressing order.
+    pub taker_fee: u64,
+    /// Side of the incoming order. `None` for auction trades, where both
+    /// sides pay the maker rate.
+    pub aggressor: Option<Side>,
 }
 
 #[derive(Error, Debug, PartialEq, Eq)]
@@ -26,12 +70,72 @@ pub enum MatchingError {
     #[error("Symbol not found")]
     SymbolNotFound,
 
+    #[error("Order not found")]
+    OrderNotFound,
+
     #[error("No liquidity available")]
     NoLiquidity,
 
     #[error("FOK order cannot be filled")]
     FOKCannotBeFilled,
 
+    #[error("Order not accepted during auction")]
+    NotAcceptedInAuction,
+
+    #[error("Symbol is not in auction")]
+    NotInAuction,
+
+    #[error("Invalid expiry time")]
+    InvalidExpiry,
+
+    #[error("Price outside band: limit {limit}, reference {reference}")]
+    PriceOutOfBand { limit: Price, reference: Price },
+
+    #[error("Trading halted for symbol")]
+    SymbolHalted,
+
+    #[error("Order type {0:?} not accepted for symbol")]
+    OrderTypeNotAllowed(OrderType),
+
+    #[error("Time in force {0:?} not accepted for symbol")]
+    TimeInForceNotAllowed(TimeInForce),
+
+    #[error("Circuit breaker tripped: {price} too far from {reference}")]
+    CircuitBreakerTripped { price: Price, reference: Price },
+
+    #[error("Participant limit exceeded at {participant}")]
+    LimitExceeded { participant: u64 },
+
+    #[error("Order quantity {quantity} exceeds limit {limit}")]
+    OrderQuantityLimitExceeded { quantity: u32, limit: u32 },
+
+    #[error("Order notional exceeds limit {limit}")]
+    OrderNotionalLimitExceeded { limit: u64 },
+
+    #[error("User already has {limit} open orders")]
+    OpenOrderLimitExceeded { limit: usize },
+
+    #[error("Invalid quote: bid {bid_price} must be below ask {ask_price}, both with quantity")]
+    InvalidQuote { bid_price: Price, ask_price: Price },
+
+    #[error("Trade {trade_id} not found or too old to bust")]
+    TradeNotFound { trade_id: u64 },
+
+    #[error("Price {price} is not a multiple of tick size {tick_size}")]
+    InvalidTickSize { price: Price, tick_size: Price },
+
+    #[error("Quantity {quantity} is not a multiple of lot size {lot_size}")]
+    InvalidLotSize { quantity: u32, lot_size: u32 },
+
+    #[error("Quantity {quantity} is below the minimum of {min_qty}")]
+    QuantityBelowMinimum { quantity: u32, min_qty: u32 },
+
+    #[error("Client order id {0} is already in use by a live order")]
+    DuplicateClientOrderId(String),
+
+    #[error("Journal file unavailable: {0}")]
+    JournalUnavailable(String),
+
     #[error("Internal error: {0}")]
     InternalError(String),
 }
@@ -42,12 +146,107 @@ impl From<&str> for MatchingError {
     }
 }
 
+/// Order ids of a user's current two-sided quote on one symbol.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Quote {
+    pub symbol: String,
+    pub user_id: u64,
+    pub bid_order_id: u64,
+    pub ask_order_id: u64,
+}
+
+#[derive(Debug)]
+pub struct QuoteResult {
+    pub bid_order_id: u64,
+    pub ask_order_id: u64,
+    /// Orders of the previous quote that were still live and got cancelled.
+    pub replaced_order_ids: Vec<u64>,
+    /// Execution of each side; holds the fills if that side crossed.
+    pub bid: TradeExecutionResult,
+    pub ask: TradeExecutionResult,
+}
+
+impl QuoteResult {
+    pub fn trades(&self) -> impl Iterator<Item = &Trade> {
+        self.bid.trades.iter().chain(self.ask.trades.iter())
+    }
+}
+
+/// Outcome of `MatchingEngine::bust_trade`.
+#[derive(Debug, Clone)]
+pub struct TradeBust {
+    pub symbol: String,
+    pub trade: Trade,
+    /// Orders the trade had filled that went back on the book.
+    pub restored_order_ids: Vec<u64>,
+    /// The symbol's last trade price after the bust.
+    pub last_trade_price: Option<Price>,
+}
+
+/// A trade still eligible for busting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecentTrade {
+    trade: Trade,
+    /// The symbol's last trade price before this trade.
+    previous_price: Option<Price>,
+}
+
+/// Resident and hibernated book counts, and how long wakes took.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HibernationStats {
+    pub resident_symbols: usize,
+    pub hibernated_symbols: usize,
+    /// Books torn down since start-up.
+    pub hibernations: u64,
+    /// Books rebuilt since start-up.
+    pub wakes: u64,
+    pub wake_latency: LatencyPercentiles,
+}
+
+/// Why matching an incoming order stops short of the next opposite level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MatchStop {
+    LimitPrice,
+    PriceProtection,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CancelReason {
+    PriceProtection,
+    /// The book ran out of liquidity after the order had traded.
+    NoLiquidity,
+}
+
+#[derive(Debug)]
+pub struct ContingentExecution {
+    pub contingent_id: u64,
+    pub result: Result<TradeExecutionResult, MatchingError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolInfo {
+    pub symbol: String,
+    pub trading_state: TradingState,
+    pub resting_orders: usize,
+    pub stop_orders: usize,
+    pub last_trade_price: Option<Price>,
+    /// Order types and time-in-force values accepted right now.
+    pub order_types: Vec<OrderType>,
+    pub time_in_force: Vec<TimeInForce>,
+}
+
 #[derive(Debug)]
 pub struct TradeExecutionResult {
     pub trades: Vec<Trade>,
     pub remaining_order: Option<Arc<RwLock<Order>>>,
     pub filled_orders: Vec<Arc<RwLock<Order>>>,
     pub rejected: bool,
+    pub cancel_reason: Option<CancelReason>,
+    pub contingent_executions: Vec<ContingentExecution>,
+    /// Stop orders activated by this execution, in trigger order.
+    pub triggered_stops: Vec<Arc<RwLock<Order>>>,
+    /// Number of trades that preceded each of `triggered_stops`.
+    trigger_points: Vec<usize>,
 }
 
 impl TradeExecutionResult {
@@ -57,44 +256,1359 @@ impl TradeExecutionResult {
             remaining_order: None,
             filled_orders: Vec::new(),
             rejected: false,
+            cancel_reason: None,
+            contingent_executions: Vec::new(),
+            triggered_stops: Vec::new(),
+            trigger_points: Vec::new(),
+        }
+    }
+
+    /// Records `stops` as triggered by the trades executed so far.
+    pub(crate) fn add_triggered_stops(&mut self, stops: impl IntoIterator<Item = Arc<RwLock<Order>>>) {
+        for stop in stops {
+            self.triggered_stops.push(stop);
+            self.trigger_points.push(self.trades.len());
         }
     }
+
+    fn truncate_triggered_stops(&mut self, len: usize) {
+        self.triggered_stops.truncate(len);
+        self.trigger_points.truncate(len);
+    }
+}
+
+impl Default for TradeExecutionResult {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
+/// Terms of one trade passed to `execute_trade`. `aggressor` is `None` for
+/// auction trades, where both sides pay the maker fee.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Fill {
+    pub quantity: u32,
+    pub price: Price,
+    pub fees: FeeSchedule,
+    pub aggressor: Option<Side>,
+}
+
+/// Trades kept per symbol for busting unless configured otherwise.
+pub const DEFAULT_BUSTABLE_TRADES: usize = 1_000;
+
+/// Orders kept for status queries unless configured otherwise.
+pub const DEFAULT_ORDER_HISTORY_LIMIT: usize = 100_000;
+
+#[derive(Debug, Clone)]
+pub struct MatchingEngineConfig {
+    /// Default protection band for market orders, in basis points away from
+    /// the best opposite price at entry. `None` lets market orders sweep the
+    /// book without limit unless the order sets its own `max_slippage_bps`.
+    pub market_protection_bps: Option<u32>,
+    pub settlement_method: SettlementMethod,
+    /// Session close as nanoseconds after UTC midnight. Day orders expire at
+    /// the close when set, otherwise at midnight.
+    pub session_close_ns: Option<i64>,
+    /// Risk limits for users without their own.
+    pub risk_limits: RiskLimits,
+    /// Most recent trades kept per symbol for `bust_trade`. Older trades
+    /// can no longer be busted.
+    pub bustable_trades: usize,
+    /// Idle time, in nanoseconds, after which `hibernate_idle_symbols` tears
+    /// down the book of a symbol with no resting or stop orders. `None`
+    /// keeps every book resident.
+    pub hibernate_after_ns: Option<i64>,
+    /// Orders kept for `get_order_status`. Past it the oldest filled,
+    /// canceled, rejected and expired orders are dropped, after the history
+    /// has grown a quarter beyond it. Live orders and the orders of
+    /// bustable trades are always kept.
+    pub order_history_limit: usize,
+    /// What happens to new orders on a halted symbol.
+    pub halted_orders: HaltedOrders,
+}
+
+/// Handling of orders entered while their symbol is halted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HaltedOrders {
+    /// Rejected with `MatchingError::SymbolHalted`.
+    #[default]
+    Reject,
+    /// Booked without matching, as in an auction call. Resuming the
+    /// symbol reopens it with an uncross if the queue crosses.
+    Queue,
+}
+
+impl Default for MatchingEngineConfig {
+    fn default() -> Self {
+        Self {
+            market_protection_bps: None,
+            settlement_method: SettlementMethod::default(),
+            session_close_ns: None,
+            risk_limits: RiskLimits::default(),
+            bustable_trades: DEFAULT_BUSTABLE_TRADES,
+            hibernate_after_ns: None,
+            order_history_limit: DEFAULT_ORDER_HISTORY_LIMIT,
+            halted_orders: HaltedOrders::default(),
+        }
+    }
+}
+
+/// Everything needed to bring an engine back with the same books and id
+/// sequences: all symbols, contingent orders, the current session's trades
+/// and published settlements.
 #[derive(Serialize, Deserialize)]
-pub struct MatchingEngineSnapshot {
+pub struct EngineSnapshot {
     order_books: HashMap<String, OrderBookSnapshot>,
     next_order_id: u64,
     next_trade_id: u64,
+    #[serde(default)]
+    contingent_orders: Vec<ContingentOrderSnapshot>,
+    #[serde(default)]
+    next_contingent_id: u64,
+    #[serde(default)]
+    session_trades: HashMap<String, Vec<Trade>>,
+    #[serde(default)]
+    settlements: SettlementStore,
+    #[serde(default)]
+    quotes: Vec<Quote>,
+    /// Symbols whose entry in `order_books` is a hibernation stub; they are
+    /// restored hibernated.
+    #[serde(default)]
+    hibernated_symbols: BTreeSet<String>,
+    /// Sequence of the last journaled command the snapshot includes.
+    #[serde(default)]
+    journal_sequence: u64,
+    /// Trades still eligible for busting, oldest first.
+    #[serde(default)]
+    recent_trades: HashMap<String, VecDeque<RecentTrade>>,
+    /// Orders of bustable trades that are no longer on a book. Busting the
+    /// trade gives them their quantity back.
+    #[serde(default)]
+    bustable_orders: Vec<OrderSnapshot>,
+    /// Timestamp of the last trade; later trades are stamped after it.
+    #[serde(default)]
+    last_trade_timestamp: i64,
+}
+
+impl EngineSnapshot {
+    /// Writes the snapshot to `path` in `format` behind a snapshot file
+    /// header with an empty symbol, and returns the size of the file.
+    pub fn save_to_file(
+        &self,
+        path: impl AsRef<Path>,
+        format: SnapshotFormat,
+    ) -> Result<u64, SnapshotFileError> {
+        write_snapshot_file(path.as_ref(), format, "", self)
+    }
+
+    /// Reads a snapshot written by `save_to_file`, refusing a book
+    /// snapshot file before decoding it.
+    pub fn load_from_file(path: impl AsRef<Path>) -> Result<Self, SnapshotFileError> {
+        read_snapshot_file(path.as_ref(), Some("")).map(|(_, snapshot)| snapshot)
+    }
+
+    /// `load_from_file` on the bytes of a file already read.
+    pub(crate) fn decode_file(bytes: &[u8]) -> Result<Self, SnapshotFileError> {
+        decode_snapshot_file(bytes, Some("")).map(|(_, snapshot)| snapshot)
+    }
+
+    pub fn next_order_id(&self) -> u64 {
+        self.next_order_id
+    }
+
+    pub fn journal_sequence(&self) -> u64 {
+        self.journal_sequence
+    }
+
+    pub fn next_trade_id(&self) -> u64 {
+        self.next_trade_id
+    }
+
+    pub fn order_books(&self) -> &HashMap<String, OrderBookSnapshot> {
+        &self.order_books
+    }
+
+    /// Hashes of everything the snapshot persists: one component per book,
+    /// named `book:<symbol>`, and one per engine-wide table.
+    pub fn digest(&self) -> EngineDigest {
+        let mut components = BTreeMap::new();
+        for (symbol, book) in &self.order_books {
+            components.insert(format!("book:{}", symbol), component_hash(book));
+        }
+
+        let mut quotes: Vec<&Quote> = self.quotes.iter().collect();
+        quotes.sort_by_key(|quote| (quote.user_id, &quote.symbol));
+        let sequences = (
+            self.next_order_id,
+            self.next_trade_id,
+            self.next_contingent_id,
+            self.journal_sequence,
+            self.last_trade_timestamp,
+        );
+        components.extend([
+            ("sequences".to_string(), component_hash(&sequences)),
+            ("contingent_orders".to_string(), component_hash(&self.contingent_orders)),
+            ("session_trades".to_string(), component_hash(&self.session_trades)),
+            ("settlements".to_string(), component_hash(&self.settlements)),
+            ("quotes".to_string(), component_hash(&quotes)),
+            ("hibernated_symbols".to_string(), component_hash(&self.hibernated_symbols)),
+            ("recent_trades".to_string(), component_hash(&self.recent_trades)),
+            ("bustable_orders".to_string(), component_hash(&self.bustable_orders)),
+        ]);
+
+        EngineDigest { components }
+    }
+}
+
+/// Hash of `value`'s JSON form. JSON objects keep their keys sorted, so
+/// hash maps hash the same whatever their iteration order.
+fn component_hash<T: Serialize>(value: &T) -> u64 {
+    let json = serde_json::to_value(value).map(|value| value.to_string()).unwrap_or_default();
+    let mut hasher = StableHasher::new();
+    json.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Per-component hashes of an engine's persisted state, for checking that
+/// two ways of rebuilding an engine agree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EngineDigest {
+    components: BTreeMap<String, u64>,
+}
+
+impl EngineDigest {
+    pub fn components(&self) -> &BTreeMap<String, u64> {
+        &self.components
+    }
+
+    /// Components that differ or exist on one side only, in name order.
+    pub fn differences(&self, other: &EngineDigest) -> Vec<String> {
+        let names: BTreeSet<&String> = self.components.keys().chain(other.components.keys()).collect();
+        names
+            .into_iter()
+            .filter(|name| self.components.get(*name) != other.components.get(*name))
+            .cloned()
+            .collect()
+    }
+}
+
+/// The engine's subscription to a shared timer wheel, with one expiry timer
+/// armed per distinct expiry time.
+struct EngineTimers {
+    timers: Timers,
+    fired: Receiver<Vec<FiredTimer>>,
+    expiries: BTreeMap<i64, TimerHandle>,
 }
 
 pub struct MatchingEngine {
     pub order_books: HashMap<String, OrderBook>,
     next_order_id: u64,
     next_trade_id: u64,
+    contingent_orders: ContingentOrderBook,
+    next_contingent_id: u64,
+    session_trades: HashMap<String, Vec<Trade>>,
+    /// Stubs of hibernated symbols: the book's settings and last prices
+    /// with no levels. A symbol is in either this or `order_books`.
+    hibernated: HashMap<String, OrderBookSnapshot>,
+    /// Time of the last command on each symbol.
+    symbol_activity: HashMap<String, i64>,
+    hibernations: u64,
+    wakes: u64,
+    wake_latency: LatencyHistogram,
+    /// Last `bustable_trades` trades of each symbol, oldest first.
+    recent_trades: HashMap<String, VecDeque<RecentTrade>>,
+    settlements: SettlementStore,
+    events: EventBus,
+    /// Symbols whose L3 feed has batch subscribers, closed after every
+    /// command.
+    l3_batching: Vec<String>,
+    /// Symbols a bust in the current command rebooked an order on, which
+    /// may leave them crossed. See `debug_validate_books`.
+    #[cfg(debug_assertions)]
+    rebooked_by_bust: Vec<String>,
+    /// Last indicative uncross published for each symbol in its auction
+    /// call, so only changes go out.
+    published_uncross: HashMap<String, IndicativeUncross>,
+    callbacks: EngineCallbacks,
+    participants: ParticipantRegistry,
+    /// Orders accepted since start-up, for status queries. Bounded by
+    /// `order_history_limit`.
+    order_history: HashMap<u64, Arc<RwLock<Order>>>,
+    /// History size that triggers the next trim.
+    order_history_trim_at: usize,
     order_metrics: OrderMetrics,
     latency_metrics: LatencyMetrics,
+    config: MatchingEngineConfig,
+    user_risk_limits: HashMap<u64, RiskLimits>,
+    open_orders: OpenOrderCounts,
+    /// Current quote of each user on each symbol, keyed by (user, symbol).
+    quotes: HashMap<(u64, String), Quote>,
+    clock: SharedClock,
+    journal: Option<EngineJournal>,
+    /// Durable copy of the journal, appended to after every command.
+    journal_file: Option<JournalFile>,
+    /// Why the last append to `journal_file` failed. Commands are refused
+    /// until a file is attached again.
+    journal_file_error: Option<String>,
+    timers: Option<EngineTimers>,
+}
+
+impl Default for MatchingEngine {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl MatchingEngine {
-    pub fn new() -> Self {
-        Self {
-            order_books: HashMap::new(),
-            next_order_id: 1,
-            next_trade_id: 1,
-            order_metrics: OrderMetrics::new(),
-            latency_metrics: LatencyMetrics::new(),
+impl MatchingEngine {
+    pub fn new() -> Self {
+        Self::with_config(MatchingEngineConfig::default())
+    }
+
+    pub fn with_config(config: MatchingEngineConfig) -> Self {
+        Self::with_clock(config, system_clock())
+    }
+
+    pub fn with_clock(config: MatchingEngineConfig, clock: SharedClock) -> Self {
+        Self {
+            order_books: HashMap::new(),
+            next_order_id: 1,
+            next_trade_id: 1,
+            contingent_orders: ContingentOrderBook::new(),
+            next_contingent_id: 1,
+            session_trades: HashMap::new(),
+            hibernated: HashMap::new(),
+            symbol_activity: HashMap::new(),
+            hibernations: 0,
+            wakes: 0,
+            wake_latency: LatencyHistogram::new(),
+            recent_trades: HashMap::new(),
+            l3_batching: Vec::new(),
+            #[cfg(debug_assertions)]
+            rebooked_by_bust: Vec::new(),
+            settlements: SettlementStore::new(),
+            events: EventBus::new(),
+            published_uncross: HashMap::new(),
+            callbacks: EngineCallbacks::default(),
+            participants: ParticipantRegistry::new(),
+            order_history: HashMap::new(),
+            order_history_trim_at: config.order_history_limit + config.order_history_limit / 4,
+            order_metrics: OrderMetrics::new(),
+            latency_metrics: LatencyMetrics::new(),
+            config,
+            user_risk_limits: HashMap::new(),
+            open_orders: OpenOrderCounts::new(),
+            quotes: HashMap::new(),
+            clock,
+            journal: None,
+            journal_file: None,
+            journal_file_error: None,
+            timers: None,
+        }
+    }
+
+    pub fn clock(&self) -> SharedClock {
+        Arc::clone(&self.clock)
+    }
+
+    /// Moves the engine onto another clock, as when a replayed or
+    /// replicated engine goes live.
+    pub fn set_clock(&mut self, clock: SharedClock) {
+        self.clock = clock;
+    }
+
+    pub fn config(&self) -> &MatchingEngineConfig {
+        &self.config
+    }
+
+    /// Starts recording every command into a journal for `replay`.
+    pub fn enable_journal(&mut self) {
+        self.journal.get_or_insert_with(EngineJournal::new);
+    }
+
+    pub fn journal(&self) -> Option<&EngineJournal> {
+        self.journal.as_ref()
+    }
+
+    pub fn journal_mut(&mut self) -> Option<&mut EngineJournal> {
+        self.journal.as_mut()
+    }
+
+    pub fn take_journal(&mut self) -> Option<EngineJournal> {
+        self.journal.take()
+    }
+
+    /// Journals every command into `file` as well, starting with any
+    /// entries the journal holds that the file does not. The file must
+    /// end within the journal: at its last entry, or where the entries it
+    /// still holds begin.
+    ///
+    /// A command is appended once it has run, so one whose append fails
+    /// has already taken effect and its caller sees its result. From then
+    /// on the engine refuses every command, without running it, with
+    /// `MatchingError::JournalUnavailable` or an empty result, until a file
+    /// attached again has caught up with the journal.
+    pub fn attach_journal_file(&mut self, file: JournalFile) -> Result<(), JournalError> {
+        self.enable_journal();
+        let journal = self.journal.as_ref().unwrap();
+        let (compacted_through, last_sequence) =
+            (journal.compacted_through(), journal.last_sequence());
+        if file.last_sequence() < compacted_through || file.last_sequence() > last_sequence {
+            return Err(JournalError::FileMismatch {
+                file: file.last_sequence(),
+                compacted_through,
+                last_sequence,
+            });
+        }
+        self.journal_file = Some(file);
+        self.write_journal_file()?;
+        self.journal_file_error = None;
+        Ok(())
+    }
+
+    /// Why commands are being refused, if an append to the journal file
+    /// has failed. See `attach_journal_file`.
+    pub fn journal_file_error(&self) -> Option<&str> {
+        self.journal_file_error.as_deref()
+    }
+
+    /// Appends the journal entries the file does not have yet. On an error
+    /// they stay in the journal and the next command retries them.
+    fn write_journal_file(&mut self) -> Result<(), JournalError> {
+        let (Some(journal), Some(file)) = (&self.journal, &mut self.journal_file) else {
+            return Ok(());
+        };
+        for entry in journal.entries_after(file.last_sequence()) {
+            file.append(entry)?;
+        }
+        Ok(())
+    }
+
+    /// Rebuilds an engine by re-running `journal` against a clock pinned to
+    /// each entry's timestamp. The returned engine keeps journaling and
+    /// stays on that clock.
+    pub fn replay(journal: &EngineJournal) -> Result<Self, JournalError> {
+        Self::replay_with_config(MatchingEngineConfig::default(), journal)
+    }
+
+    pub fn replay_with_config(
+        config: MatchingEngineConfig,
+        journal: &EngineJournal,
+    ) -> Result<Self, JournalError> {
+        if journal.compacted_through() > 0 {
+            return Err(JournalError::MissingEntries {
+                snapshot_sequence: 0,
+                compacted_through: journal.compacted_through(),
+            });
+        }
+
+        let clock = Arc::new(ManualClock::default());
+        let mut engine = Self::with_clock(config, clock.clone());
+        engine.enable_journal();
+        engine.replay_entries(&clock, journal.entries())?;
+        Ok(engine)
+    }
+
+    /// Rebuilds an engine from `snapshot` and the commands `journal` holds
+    /// after it. The journal may have been compacted up to the snapshot.
+    pub fn replay_from_snapshot(
+        config: MatchingEngineConfig,
+        snapshot: &EngineSnapshot,
+        journal: &EngineJournal,
+    ) -> Result<Self, JournalError> {
+        let snapshot_sequence = snapshot.journal_sequence;
+        if journal.compacted_through() > snapshot_sequence {
+            return Err(JournalError::MissingEntries {
+                snapshot_sequence,
+                compacted_through: journal.compacted_through(),
+            });
+        }
+
+        let clock = Arc::new(ManualClock::default());
+        let mut engine = Self::restore_with_clock(config, clock.clone(), snapshot);
+        engine.journal = Some(EngineJournal::starting_after(snapshot_sequence));
+        engine.replay_entries(&clock, journal.entries_after(snapshot_sequence))?;
+        Ok(engine)
+    }
+
+    /// The sequenced events a full replay of `journal` publishes. They
+    /// carry the numbers the original engine gave them if it had a
+    /// sequenced subscriber or event ring from its first command.
+    pub fn replay_events(
+        config: MatchingEngineConfig,
+        journal: &EngineJournal,
+    ) -> Result<Vec<SequencedEvent>, JournalError> {
+        if journal.compacted_through() > 0 {
+            return Err(JournalError::MissingEntries {
+                snapshot_sequence: 0,
+                compacted_through: journal.compacted_through(),
+            });
+        }
+
+        let clock = Arc::new(ManualClock::default());
+        let mut engine = Self::with_clock(config, clock.clone());
+        engine.enable_journal();
+        let events = engine.subscribe_sequenced();
+        engine.replay_entries(&clock, journal.entries())?;
+        Ok(events.try_iter().collect())
+    }
+
+    pub(crate) fn replay_entries(&mut self, clock: &ManualClock, entries: &[JournalEntry]) -> Result<(), JournalError> {
+        for entry in entries {
+            clock.set(entry.timestamp);
+            let outcome = entry.command.apply(self);
+
+            let replayed = self.journal.as_ref().and_then(|j| j.entries().last());
+            let outcome_differs = entry.outcome.as_ref().is_some_and(|recorded| *recorded != outcome);
+            if outcome_differs
+                || replayed.map(|r| (r.sequence, r.order_id)) != Some((entry.sequence, entry.order_id))
+            {
+                return Err(JournalError::Divergence {
+                    sequence: entry.sequence,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Snapshots the engine and drops the journal entries the snapshot
+    /// covers, keeping a long-running engine's journal bounded. The
+    /// snapshot is needed with the remaining journal to rebuild the engine.
+    pub fn compact_journal(&mut self) -> EngineSnapshot {
+        let snapshot = self.create_snapshot();
+        let written = self
+            .journal_file
+            .as_ref()
+            .map_or(u64::MAX, JournalFile::last_sequence);
+        if let Some(journal) = &mut self.journal {
+            journal.compact(snapshot.journal_sequence.min(written));
+        }
+        snapshot
+    }
+
+    /// Digest of the state a snapshot would persist.
+    pub fn state_digest(&self) -> EngineDigest {
+        self.create_snapshot().digest()
+    }
+
+    /// Reads the clock once for the command about to run; everything the
+    /// command stamps uses this time.
+    fn begin_command(&mut self) -> i64 {
+        let now = self.clock.now_nanos();
+        self.callbacks.set_now(now);
+        now
+    }
+
+    fn journal_command(
+        &mut self,
+        timestamp: i64,
+        order_id: Option<u64>,
+        command: JournalCommand,
+        outcome: CommandOutcome,
+    ) {
+        if let Some(journal) = &mut self.journal {
+            journal.record(timestamp, order_id, command, outcome);
+        }
+        if let Err(e) = self.write_journal_file() {
+            warn!("Failed to append to the journal file, refusing commands: {}", e);
+            self.journal_file_error = Some(e.to_string());
+        }
+    }
+
+    /// Journals `command` around `run`, noting the order id `run` assigned.
+    /// With hibernation in use the command is built regardless, to wake its
+    /// symbol and record activity on it.
+    fn journaled<T: JournaledOutput>(
+        &mut self,
+        command: impl FnOnce() -> JournalCommand,
+        run: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let hibernation = self.config.hibernate_after_ns.is_some() || !self.hibernated.is_empty();
+        let command = (self.journal.is_some() || hibernation).then(command);
+        self.run_command(command, run)
+    }
+
+    fn run_command<T: JournaledOutput>(
+        &mut self,
+        command: Option<JournalCommand>,
+        run: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        if let Some(error) = &self.journal_file_error {
+            return T::refused(MatchingError::JournalUnavailable(error.clone()));
+        }
+        let now = self.begin_command();
+        let next_order_id = self.next_order_id;
+        if let Some(symbol) = command.as_ref().and_then(|command| command.symbol()) {
+            self.wake_symbol(symbol);
+        }
+
+        #[cfg(debug_assertions)]
+        let crossed = self.crossed_symbols();
+        let output = run(self);
+        if !self.l3_batching.is_empty() {
+            self.end_l3_batches(now);
+        }
+        #[cfg(debug_assertions)]
+        self.debug_validate_books(crossed);
+
+        if let Some(command) = command {
+            let order_id = (self.next_order_id != next_order_id).then_some(next_order_id);
+            self.journal_command(now, order_id, command, output.outcome());
+        }
+        output
+    }
+
+    /// Books crossed in continuous trading. A crossed auction or halted
+    /// book is not listed: it must uncross before trading continuously.
+    #[cfg(debug_assertions)]
+    fn crossed_symbols(&self) -> Vec<String> {
+        self.order_books
+            .iter()
+            .filter(|(_, order_book)| {
+                order_book.trading_state() == TradingState::Continuous
+                    && matches!(
+                        (order_book.get_best_bid_price(), order_book.get_best_ask_price()),
+                        (Some(bid), Some(ask)) if bid >= ask
+                    )
+            })
+            .map(|(symbol, _)| symbol.clone())
+            .collect()
+    }
+
+    /// Debug check after every command that each live book still holds
+    /// together. See `OrderBook::validate`. A bust may rebook an order
+    /// behind a level it traded through, so a book that was `crossed` in
+    /// continuous trading before the command or that a bust rebooked on
+    /// may stay crossed; any other command that leaves a continuous book
+    /// crossed fails the check, including one that reopens a crossed
+    /// auction or halted book without uncrossing it.
+    #[cfg(debug_assertions)]
+    fn debug_validate_books(&mut self, mut crossed: Vec<String>) {
+        crossed.append(&mut self.rebooked_by_bust);
+        for (symbol, order_book) in &self.order_books {
+            match order_book.validate() {
+                Ok(()) => {}
+                Err(BookInvariantError::Crossed { .. }) if crossed.contains(symbol) => {}
+                Err(e) => panic!("Book invariant violated: {}", e),
+            }
+        }
+    }
+
+    /// Validates every live book, returning the symbols that fail with the
+    /// first violation found in each, in symbol order.
+    pub fn validate_books(&self) -> Vec<(String, BookInvariantError)> {
+        let mut violations: Vec<(String, BookInvariantError)> = self
+            .order_books
+            .iter()
+            .filter_map(|(symbol, order_book)| {
+                order_book.validate().err().map(|e| (symbol.clone(), e))
+            })
+            .collect();
+        violations.sort_by(|a, b| a.0.cmp(&b.0));
+        violations
+    }
+
+    pub fn add_symbol(&mut self, symbol: &str) {
+        self.add_symbol_with_policy(symbol, MatchPolicy::Fifo);
+    }
+
+    pub fn add_symbol_with_policy(&mut self, symbol: &str, policy: MatchPolicy) {
+        self.add_symbol_with(symbol, policy, SymbolSpec::default());
+    }
+
+    /// Lists `symbol` with its tick size, lot size and minimum quantity.
+    pub fn add_symbol_with_spec(&mut self, symbol: &str, spec: SymbolSpec) {
+        self.add_symbol_with(symbol, MatchPolicy::Fifo, spec);
+    }
+
+    pub fn add_symbol_with(&mut self, symbol: &str, policy: MatchPolicy, spec: SymbolSpec) {
+        if !self.order_books.contains_key(symbol) && !self.hibernated.contains_key(symbol) {
+            let command = || JournalCommand::AddSymbol {
+                symbol: symbol.to_string(),
+                match_policy: policy,
+                spec,
+            };
+            self.journaled(command, |engine| {
+                let mut order_book = OrderBook::new(symbol);
+                order_book.share_open_order_counts(engine.open_orders.clone());
+                order_book.set_match_policy(policy);
+                order_book.set_spec(spec);
+                engine.order_books.insert(symbol.to_string(), order_book);
+                engine.symbol_activity.insert(symbol.to_string(), engine.callbacks.now());
+            });
+        }
+    }
+
+    /// Brings a hibernated symbol's book back and records activity on it.
+    /// Unknown symbols are left alone.
+    fn wake_symbol(&mut self, symbol: &str) {
+        if let Some(stub) = self.hibernated.remove(symbol) {
+            let started = Instant::now();
+            let mut order_book = OrderBook::restore_from_snapshot(&stub);
+            order_book.share_open_order_counts(self.open_orders.clone());
+            self.order_books.insert(symbol.to_string(), order_book);
+            self.wakes += 1;
+            self.wake_latency.record(started.elapsed().as_nanos() as u64);
+        }
+        if self.order_books.contains_key(symbol) {
+            self.symbol_activity.insert(symbol.to_string(), self.callbacks.now());
+        }
+    }
+
+    /// Tears down the books of symbols idle for `hibernate_after_ns` that
+    /// have no resting or stop orders and no L3 listeners, keeping only their settings and last
+    /// prices. They come back on the next command for the symbol. Meant to
+    /// be driven by a periodic task. Returns the symbols hibernated, in
+    /// symbol order.
+    pub fn hibernate_idle_symbols(&mut self) -> Vec<String> {
+        let Some(idle_ns) = self.config.hibernate_after_ns else {
+            return Vec::new();
+        };
+        let now = self.clock.now_nanos();
+
+        let mut idle: Vec<String> = self
+            .order_books
+            .iter()
+            .filter(|(_, book)| {
+                book.order_count() == 0 && book.stop_order_count() == 0 && !book.has_l3_listeners()
+            })
+            .filter(|(symbol, _)| {
+                self.symbol_activity
+                    .get(*symbol)
+                    .is_some_and(|last| now.saturating_sub(*last) >= idle_ns)
+            })
+            .map(|(symbol, _)| symbol.clone())
+            .collect();
+        idle.sort();
+
+        for symbol in &idle {
+            let order_book = self.order_books.remove(symbol).unwrap();
+            self.hibernated.insert(symbol.clone(), order_book.create_snapshot());
+            self.hibernations += 1;
+        }
+        // Symbols restored from a snapshot start their idle period now.
+        for symbol in self.order_books.keys() {
+            self.symbol_activity.entry(symbol.clone()).or_insert(now);
+        }
+        idle
+    }
+
+    pub fn is_hibernated(&self, symbol: &str) -> bool {
+        self.hibernated.contains_key(symbol)
+    }
+
+    pub fn hibernation_stats(&self) -> HibernationStats {
+        HibernationStats {
+            resident_symbols: self.order_books.len(),
+            hibernated_symbols: self.hibernated.len(),
+            hibernations: self.hibernations,
+            wakes: self.wakes,
+            wake_latency: self.wake_latency.snapshot(),
+        }
+    }
+
+    /// Runs `callback` synchronously for every executed trade, before the
+    /// order that caused it returns.
+    pub fn on_trade(&mut self, callback: TradeCallback) {
+        self.callbacks.set_on_trade(callback);
+    }
+
+    /// Runs `callback` synchronously on every order status transition.
+    pub fn on_order_status(&mut self, callback: OrderStatusCallback) {
+        self.callbacks.set_on_order_status(callback);
+    }
+
+    /// Receives every event for `symbol` from now on.
+    pub fn subscribe(&mut self, symbol: &str) -> Receiver<EngineEvent> {
+        self.events.subscribe(Some(symbol))
+    }
+
+    /// Receives every event for all symbols from now on.
+    pub fn subscribe_all(&mut self) -> Receiver<EngineEvent> {
+        self.events.subscribe(None)
+    }
+
+    /// Receives every event for all symbols from now on, numbered so a
+    /// consumer can resume or rebuild from a known point.
+    pub fn subscribe_sequenced(&mut self) -> Receiver<SequencedEvent> {
+        self.events.subscribe_sequenced()
+    }
+
+    /// Keeps the last `capacity` sequenced events for consumers that
+    /// rebuild their state from them.
+    pub fn enable_event_ring(&mut self, capacity: usize) -> EventRing {
+        self.events.enable_ring(capacity)
+    }
+
+    /// Sequence of the last numbered event the engine published.
+    pub fn event_sequence(&self) -> u64 {
+        self.events.sequence()
+    }
+
+    /// Receives every change to the resting orders of `symbol` from now
+    /// on, buffering up to `capacity` events. Books with L3 listeners are
+    /// never hibernated, as that would drop the feed.
+    pub fn subscribe_l3(&mut self, symbol: &str, capacity: usize) -> Result<Receiver<L3Event>, MatchingError> {
+        self.wake_symbol(symbol);
+        let order_book = self.order_books.get_mut(symbol).ok_or(MatchingError::SymbolNotFound)?;
+        Ok(order_book.subscribe_l3(capacity))
+    }
+
+    /// Receives the changes to the resting orders of `symbol` in batches,
+    /// one per command or per `window` under load, buffering up to
+    /// `capacity` batches. See `L3Feed::subscribe_batches`. A nonzero
+    /// window needs `flush_l3_batches` called at its period to bound how
+    /// long a batch is held.
+    pub fn subscribe_l3_batches(
+        &mut self,
+        symbol: &str,
+        capacity: usize,
+        window: Duration,
+    ) -> Result<Receiver<L3Batch>, MatchingError> {
+        self.wake_symbol(symbol);
+        let order_book = self.order_books.get_mut(symbol).ok_or(MatchingError::SymbolNotFound)?;
+        let batches = order_book.l3_feed.subscribe_batches(capacity, window);
+        if !self.l3_batching.iter().any(|batching| batching == symbol) {
+            self.l3_batching.push(symbol.to_string());
+        }
+        Ok(batches)
+    }
+
+    /// Publishes every L3 batch whose window has passed.
+    pub fn flush_l3_batches(&mut self) {
+        let now = self.clock.now_nanos();
+        for symbol in &self.l3_batching {
+            if let Some(order_book) = self.order_books.get_mut(symbol) {
+                order_book.l3_feed.flush_due(now);
+            }
+        }
+    }
+
+    pub fn l3_batch_metrics(&self, symbol: &str) -> Option<L3BatchMetrics> {
+        Some(self.order_books.get(symbol)?.l3_feed.batch_metrics())
+    }
+
+    fn end_l3_batches(&mut self, started: i64) {
+        let now = self.clock.now_nanos();
+        for symbol in &self.l3_batching {
+            if let Some(order_book) = self.order_books.get_mut(symbol) {
+                order_book.l3_feed.end_command(started, now);
+            }
+        }
+    }
+
+    /// Publishes `OrderBook::features` for every symbol, in symbol order.
+    /// Meant to be driven by the periodic stats tick.
+    pub fn publish_book_features(&mut self, n_levels: usize, tick_window: u64) {
+        if !self.events.has_subscribers() {
+            return;
+        }
+
+        let mut symbols: Vec<&String> = self.order_books.keys().collect();
+        symbols.sort();
+        for symbol in symbols {
+            let features = self.order_books[symbol].features(n_levels, tick_window);
+            self.events.publish(EngineEvent::BookFeatures {
+                symbol: symbol.clone(),
+                features,
+            });
+        }
+    }
+
+    pub fn participants(&self) -> &ParticipantRegistry {
+        &self.participants
+    }
+
+    pub fn participants_mut(&mut self) -> &mut ParticipantRegistry {
+        &mut self.participants
+    }
+
+    pub fn place_order(&mut self, new_order: Order) -> Result<TradeExecutionResult, MatchingError> {
+        let command = self
+            .journal
+            .is_some()
+            .then(|| JournalCommand::PlaceOrder(OrderSnapshot::from(&new_order)));
+        self.run_command(command, |engine| engine.process_order(new_order))
+    }
+
+    fn process_order(&mut self, new_order: Order) -> Result<TradeExecutionResult, MatchingError> {
+        self.check_risk_limits(&new_order)?;
+        let symbol = new_order.symbol.clone();
+        let order_id = self.next_order_id;
+        let exposure = self.check_participant_limits(&new_order)?;
+        let accepted = self.events.has_subscribers().then(|| {
+            let mut accepted = new_order.clone();
+            accepted.id = self.next_order_id;
+            accepted
+        });
+
+        let user_id = new_order.user_id;
+        let mut result = self.submit_order(new_order)?;
+        self.run_triggered_stops(&symbol, &mut result)?;
+
+        if let Some((notional, quantity)) = exposure {
+            self.participants.add_exposure(user_id, notional, quantity);
+            let canceled = result
+                .filled_orders
+                .iter()
+                .find(|o| o.read().id == order_id && o.read().status == OrderStatus::Canceled)
+                .map(|o| o.read().clone());
+            if let Some(order) = canceled {
+                self.release_participant_exposure(&order);
+            }
+        }
+
+        if let Some(accepted) = accepted {
+            self.publish_execution(&symbol, Some(accepted), &result);
+        }
+        self.publish_indicative_uncross(&symbol);
+
+        if !result.trades.is_empty() {
+            self.record_session_trades(&symbol, &result.trades);
+            self.fire_contingent_orders(&symbol, &mut result);
+        }
+
+        Ok(result)
+    }
+
+    /// Registers `order` to be submitted once `trigger` is satisfied by a
+    /// trade on the trigger symbol. The condition is evaluated on subsequent
+    /// trades only, not against the current last trade price.
+    pub fn place_contingent_order(
+        &mut self,
+        trigger: ContingentTrigger,
+        order: Order,
+    ) -> Result<u64, MatchingError> {
+        let command = JournalCommand::PlaceContingentOrder {
+            trigger: trigger.clone(),
+            order: OrderSnapshot::from(&order),
+        };
+        self.journaled(
+            || command,
+            |engine| {
+                if !engine.order_books.contains_key(&trigger.symbol)
+                    || !engine.order_books.contains_key(&order.symbol)
+                {
+                    return Err(MatchingError::SymbolNotFound);
+                }
+
+                let id = engine.next_contingent_id;
+                engine.next_contingent_id += 1;
+
+                engine.contingent_orders.add(ContingentOrder {
+                    id,
+                    user_id: order.user_id,
+                    trigger,
+                    order,
+                });
+
+                Ok(id)
+            },
+        )
+    }
+
+    pub fn cancel_contingent_order(&mut self, contingent_id: u64) -> Option<ContingentOrder> {
+        let command = || JournalCommand::CancelContingentOrder { contingent_id };
+        self.journaled(command, |engine| engine.contingent_orders.remove(contingent_id))
+    }
+
+    pub fn get_contingent_order(&self, contingent_id: u64) -> Option<&ContingentOrder> {
+        self.contingent_orders.get(contingent_id)
+    }
+
+    fn record_session_trades(&mut self, symbol: &str, trades: &[Trade]) {
+        self.session_trades
+            .entry(symbol.to_string())
+            .or_default()
+            .extend(trades.iter().cloned());
+
+        let recent = self.recent_trades.entry(symbol.to_string()).or_default();
+        for trade in trades {
+            let previous_price = recent.back().map(|recent| recent.trade.price);
+            recent.push_back(RecentTrade {
+                trade: trade.clone(),
+                previous_price,
+            });
+        }
+        let excess = recent.len().saturating_sub(self.config.bustable_trades);
+        recent.drain(..excess);
+    }
+
+    /// Reverses one of the last `bustable_trades` trades on `symbol`. Both
+    /// orders get the quantity back and the last trade price reverts if this
+    /// trade set it. The resting side goes back on its price level, at the
+    /// back of the queue, if the trade had filled it. The aggressor is not
+    /// rebooked, since it would cross the book again; any live remainder is
+    /// left as it was.
+    pub fn bust_trade(&mut self, symbol: &str, trade_id: u64) -> Result<TradeBust, MatchingError> {
+        let command = || JournalCommand::BustTrade {
+            symbol: symbol.to_string(),
+            trade_id,
+        };
+        self.journaled(command, |engine| engine.process_bust(symbol, trade_id))
+    }
+
+    fn process_bust(&mut self, symbol: &str, trade_id: u64) -> Result<TradeBust, MatchingError> {
+        if !self.order_books.contains_key(symbol) {
+            return Err(MatchingError::SymbolNotFound);
+        }
+        let recent = self
+            .recent_trades
+            .get_mut(symbol)
+            .ok_or(MatchingError::TradeNotFound { trade_id })?;
+        let position = recent
+            .iter()
+            .position(|recent| recent.trade.id == trade_id)
+            .ok_or(MatchingError::TradeNotFound { trade_id })?;
+
+        let latest = position + 1 == recent.len();
+        let busted = recent.remove(position).unwrap();
+        if let Some(next) = recent.get_mut(position) {
+            next.previous_price = busted.previous_price;
+        }
+        let trade = busted.trade;
+        if let Some(trades) = self.session_trades.get_mut(symbol) {
+            trades.retain(|session_trade| session_trade.id != trade_id);
+        }
+
+        let maker_id = match trade.aggressor {
+            Some(Side::Buy) => Some(trade.sell_order_id),
+            Some(Side::Sell) => Some(trade.buy_order_id),
+            None => None,
+        };
+        let mut restored_order_ids = Vec::new();
+        for order_id in [trade.buy_order_id, trade.sell_order_id] {
+            let Some(order) = self.order_history.get(&order_id).cloned() else {
+                continue;
+            };
+            let rebook = maker_id == Some(order_id);
+            if self.unfill_order(symbol, &order, &trade, rebook) {
+                restored_order_ids.push(order_id);
+            }
+        }
+        #[cfg(debug_assertions)]
+        if !restored_order_ids.is_empty() {
+            self.rebooked_by_bust.push(symbol.to_string());
+        }
+
+        let order_book = self.order_books.get_mut(symbol).unwrap();
+        if latest {
+            order_book.last_trade_price = busted.previous_price;
+        }
+        let bust = TradeBust {
+            symbol: symbol.to_string(),
+            trade,
+            restored_order_ids,
+            last_trade_price: order_book.last_trade_price,
+        };
+        self.events.publish(EngineEvent::TradeBusted(bust.clone()));
+        Ok(bust)
+    }
+
+    /// Takes a busted fill off `order`. A resting order keeps its place; a
+    /// filled one goes back on the book if `rebook` and it is a type that
+    /// rests, and is otherwise cancelled. Returns whether it was rebooked.
+    fn unfill_order(
+        &mut self,
+        symbol: &str,
+        order: &Arc<RwLock<Order>>,
+        trade: &Trade,
+        rebook: bool,
+    ) -> bool {
+        let now = self.callbacks.now();
+        let (order_id, user_id, price, status, rests) = {
+            let mut order_ref = order.write();
+            order_ref.filled_quantity = order_ref.filled_quantity.saturating_sub(trade.quantity);
+            order_ref.fill_notional = order_ref
+                .fill_notional
+                .saturating_sub(notional(trade.price, trade.quantity));
+            order_ref.last_update = now;
+            let rests = matches!(order_ref.order_type, OrderType::Limit | OrderType::Iceberg)
+                && !matches!(order_ref.time_in_force, TimeInForce::IOC | TimeInForce::FOK);
+            (order_ref.id, order_ref.user_id, order_ref.price, order_ref.status, rests)
+        };
+        let unfilled = |order: &Order| {
+            if order.filled_quantity == 0 {
+                OrderStatus::New
+            } else {
+                OrderStatus::PartiallyFilled
+            }
+        };
+
+        let order_book = self.order_books.get_mut(symbol).unwrap();
+        if order_book.refresh_order_level(order_id) {
+            let mut order_ref = order.write();
+            let status = unfilled(&order_ref);
+            self.callbacks.set_status(&mut order_ref, status);
+            return false;
+        }
+        if status == OrderStatus::Filled && rebook && rests {
+            {
+                let mut order_ref = order.write();
+                let status = unfilled(&order_ref);
+                self.callbacks.set_status(&mut order_ref, status);
+            }
+            let _ = order_book.add_order(Arc::clone(order));
+            return true;
+        }
+
+        if status == OrderStatus::Filled {
+            self.callbacks.set_status(&mut order.write(), OrderStatus::Canceled);
+        }
+        self.participants.release_exposure(
+            user_id,
+            price_magnitude(price) as u128 * trade.quantity as u128,
+            trade.quantity as u64,
+        );
+        false
+    }
+
+    /// Computes and stores the settlement price of every symbol for the
+    /// trading day containing `close_time`, then clears the session's trades.
+    /// Symbols the configured method cannot price carry the previous
+    /// settlement forward; symbols that have never settled are skipped.
+    pub fn settle_session(&mut self, close_time: i64) -> Vec<SettlementPrice> {
+        let command = || JournalCommand::SettleSession { close_time };
+        self.journaled(command, |engine| engine.process_settlement(close_time))
+    }
+
+    fn process_settlement(&mut self, close_time: i64) -> Vec<SettlementPrice> {
+        let method = self.config.settlement_method;
+        let day = trading_day(close_time);
+        let mut symbols: Vec<&String> = self.order_books.keys().chain(self.hibernated.keys()).collect();
+        symbols.sort();
+
+        let mut published = Vec::new();
+
+        for symbol in symbols {
+            let trades = self.session_trades.get(symbol).map_or(&[][..], |t| t.as_slice());
+            // A hibernated book is empty, so an empty book settles it the same.
+            let empty;
+            let order_book = match self.order_books.get(symbol) {
+                Some(order_book) => order_book,
+                None => {
+                    empty = OrderBook::new(symbol);
+                    &empty
+                }
+            };
+
+            let settlement = match calculate_settlement_price(method, trades, order_book, close_time) {
+                Some(price) => SettlementPrice {
+                    symbol: symbol.clone(),
+                    trading_day: day,
+                    price,
+                    method,
+                    carried_forward: false,
+                },
+                None => match self.settlements.previous(symbol, day) {
+                    Some(previous) => SettlementPrice {
+                        symbol: symbol.clone(),
+                        trading_day: day,
+                        price: previous.price,
+                        method,
+                        carried_forward: true,
+                    },
+                    None => continue,
+                },
+            };
+
+            published.push(settlement);
+        }
+
+        for settlement in &published {
+            self.settlements.record(settlement.clone());
+        }
+        self.session_trades.clear();
+
+        published
+    }
+
+    pub fn get_settlement(&self, symbol: &str, trading_day: i64) -> Option<&SettlementPrice> {
+        self.settlements.get(symbol, trading_day)
+    }
+
+    pub fn settlement_store(&self) -> &SettlementStore {
+        &self.settlements
+    }
+
+    pub fn set_settlement_store(&mut self, store: SettlementStore) {
+        self.settlements = store;
+    }
+
+    /// Risk limits applied to `user_id`: their own, or the engine default.
+    pub fn risk_limits(&self, user_id: u64) -> RiskLimits {
+        self.user_risk_limits
+            .get(&user_id)
+            .copied()
+            .unwrap_or(self.config.risk_limits)
+    }
+
+    pub fn set_user_risk_limits(&mut self, user_id: u64, limits: RiskLimits) {
+        self.user_risk_limits.insert(user_id, limits);
+    }
+
+    pub fn clear_user_risk_limits(&mut self, user_id: u64) {
+        self.user_risk_limits.remove(&user_id);
+    }
+
+    /// Resting and stop orders `user_id` has open across all symbols.
+    pub fn open_order_count(&self, user_id: u64) -> usize {
+        self.open_orders.get(user_id)
+    }
+
+    /// Checks `order` against its user's risk limits. Runs before the order
+    /// is given an id, so a rejection leaves no trace. Market orders are
+    /// valued at the best opposite price and stop market orders at their
+    /// stop price.
+    fn check_risk_limits(&self, order: &Order) -> Result<(), MatchingError> {
+        self.check_risk_limits_with_open(order, self.open_orders.get(order.user_id))
+    }
+
+    /// `check_risk_limits` with the user's open order count given, for
+    /// checking orders that replace or join others not yet booked.
+    fn check_risk_limits_with_open(&self, order: &Order, open_orders: usize) -> Result<(), MatchingError> {
+        let limits = self.risk_limits(order.user_id);
+
+        if let Some(limit) = limits.max_order_quantity {
+            if order.quantity > limit {
+                return Err(MatchingError::OrderQuantityLimitExceeded {
+                    quantity: order.quantity,
+                    limit,
+                });
+            }
+        }
+
+        if let Some(limit) = limits.max_order_notional {
+            let price = match (order.order_type, self.order_books.get(&order.symbol)) {
+                (OrderType::Market, Some(book)) => match order.side {
+                    Side::Buy => book.get_best_ask_price(),
+                    Side::Sell => book.get_best_bid_price(),
+                }
+                .unwrap_or(0),
+                (OrderType::StopMarket, _) => order.stop_price.unwrap_or(order.price),
+                _ => order.price,
+            };
+            if !limits.notional_within_limit(price, order.quantity) {
+                return Err(MatchingError::OrderNotionalLimitExceeded { limit });
+            }
+        }
+
+        if let Some(limit) = limits.max_open_orders {
+            if open_orders >= limit {
+                return Err(MatchingError::OpenOrderLimitExceeded { limit });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks `order` against the limits of its trader and every ancestor
+    /// and returns the exposure it commits. Market orders are valued at the
+    /// best opposite price. Users outside the hierarchy are not checked.
+    fn check_participant_limits(&self, order: &Order) -> Result<Option<(u128, u64)>, MatchingError> {
+        if self.participants.get(order.user_id).is_none() {
+            return Ok(None);
+        }
+
+        let price = match (order.order_type, self.order_books.get(&order.symbol)) {
+            (OrderType::Market, Some(book)) => match order.side {
+                Side::Buy => book.get_best_ask_price(),
+                Side::Sell => book.get_best_bid_price(),
+            }
+            .unwrap_or(0),
+            _ => order.price,
+        };
+        let notional = price_magnitude(price) as u128 * order.quantity as u128;
+        let quantity = order.quantity as u64;
+
+        match self.participants.check_order(order.user_id, notional, quantity) {
+            Ok(()) => Ok(Some((notional, quantity))),
+            Err(ParticipantError::LimitExceeded { participant }) => {
+                Err(MatchingError::LimitExceeded { participant })
+            }
+            Err(error) => Err(MatchingError::InternalError(error.to_string())),
+        }
+    }
+
+    /// Returns the unfilled remainder of a cancelled or expired order to its
+    /// participants' headroom.
+    fn release_participant_exposure(&mut self, order: &Order) {
+        let remaining = order.remaining_quantity();
+        self.participants.release_exposure(
+            order.user_id,
+            price_magnitude(order.price) as u128 * remaining as u128,
+            remaining as u64,
+        );
+    }
+
+    /// Publishes the events of one execution, buffered first so they go
+    /// out in the order `EngineEvent` documents whatever order matching
+    /// produced them in.
+    fn publish_execution(
+        &mut self,
+        symbol: &str,
+        accepted: Option<Order>,
+        result: &TradeExecutionResult,
+    ) {
+        let order_id = accepted.as_ref().map(|order| order.id);
+        let mut events = Vec::with_capacity(
+            1 + result.trades.len() + result.triggered_stops.len() + result.filled_orders.len(),
+        );
+        if let Some(accepted) = accepted {
+            events.push(EngineEvent::OrderAccepted(accepted));
+        }
+
+        let mut stops = result
+            .triggered_stops
+            .iter()
+            .zip(&result.trigger_points)
+            .peekable();
+        for (index, trade) in result.trades.iter().enumerate() {
+            while let Some((order, _)) = stops.next_if(|&(_, &point)| point <= index) {
+                events.push(EngineEvent::StopTriggered(order.read().clone()));
+            }
+            events.push(EngineEvent::Trade {
+                symbol: symbol.to_string(),
+                trade: trade.clone(),
+            });
+        }
+        for (order, _) in stops {
+            events.push(EngineEvent::StopTriggered(order.read().clone()));
+        }
+
+        let (incoming, others): (Vec<Order>, Vec<Order>) = result
+            .filled_orders
+            .iter()
+            .map(|order| order.read())
+            .filter(|order| order.status == OrderStatus::Canceled)
+            .map(|order| order.clone())
+            .partition(|order| Some(order.id) == order_id);
+        events.extend(others.into_iter().chain(incoming).map(EngineEvent::OrderCancelled));
+
+        for event in events {
+            self.events.publish(event);
         }
     }
 
-    pub fn add_symbol(&mut self, symbol: &str) {
-        if !self.order_books.contains_key(symbol) {
-            self.order_books
-                .insert(symbol.to_string(), OrderBook::new(symbol));
+    fn fire_contingent_orders(&mut self, symbol: &str, result: &mut TradeExecutionResult) {
+        let last_price = match self.order_books.get(symbol).and_then(|b| b.last_trade_price) {
+            Some(price) => price,
+            None => return,
+        };
+
+        for contingent in self.contingent_orders.take_triggered(symbol, last_price) {
+            let mut order = contingent.order;
+            order.user_id = contingent.user_id;
+            order.contingent_id = Some(contingent.id);
+            order.timestamp = self.callbacks.now();
+
+            result.contingent_executions.push(ContingentExecution {
+                contingent_id: contingent.id,
+                result: self.process_order(order),
+            });
         }
     }
 
-    pub fn place_order(
+    fn submit_order(
         &mut self,
         mut new_order: Order,
     ) -> Result<TradeExecutionResult, MatchingError> {
@@ -103,17 +1617,76 @@ impl MatchingEngine {
 
         let mut result = TradeExecutionResult::new();
 
+        self.wake_symbol(&new_order.symbol);
         if !self.order_books.contains_key(&new_order.symbol) {
             return Err(MatchingError::SymbolNotFound);
         }
 
         new_order.id = self.next_order_id;
+        new_order.received_at = self.callbacks.now();
         self.next_order_id += 1;
 
+        if new_order.time_in_force == TimeInForce::GTD
+            && new_order.expiration_time <= new_order.received_at
+        {
+            self.callbacks.set_status(&mut new_order, OrderStatus::Rejected);
+            return Err(MatchingError::InvalidExpiry);
+        }
+        if let Some(expiry) = new_order.expires_at(self.config.session_close_ns) {
+            self.arm_expiry(expiry);
+        }
+
+        if matches!(new_order.order_type, OrderType::Market | OrderType::StopMarket)
+            && new_order.max_slippage_bps.is_none()
+        {
+            new_order.max_slippage_bps = self.config.market_protection_bps;
+        }
+
         let order = Arc::new(RwLock::new(new_order));
+        self.remember_order(&order);
 
         let order_book = self.order_books.get_mut(&order.read().symbol).unwrap();
 
+        let trading_state = order_book.trading_state();
+        let halted = trading_state == TradingState::Halted;
+        if halted
+            && (self.config.halted_orders == HaltedOrders::Reject
+                || matches!(order.read().time_in_force, TimeInForce::IOC | TimeInForce::FOK))
+        {
+            self.callbacks.set_status(&mut order.write(), OrderStatus::Rejected);
+            return Err(MatchingError::SymbolHalted);
+        }
+
+        let rules_check = MatchingEngine::check_order_type_rules(order_book, &order.read(), self.callbacks.now());
+        if let Err(error) = rules_check {
+            self.callbacks.set_status(&mut order.write(), OrderStatus::Rejected);
+            return Err(error);
+        }
+
+        let spec_check = MatchingEngine::check_symbol_spec(order_book.spec(), &order.read());
+        if let Err(error) = spec_check {
+            self.callbacks.set_status(&mut order.write(), OrderStatus::Rejected);
+            return Err(error);
+        }
+
+        let client_id_check = MatchingEngine::check_client_order_id(order_book, &order.read());
+        if let Err(error) = client_id_check {
+            self.callbacks.set_status(&mut order.write(), OrderStatus::Rejected);
+            return Err(error);
+        }
+
+        let band_check = MatchingEngine::check_price_bands(order_book, &order.read());
+        if let Err(error) = band_check {
+            self.callbacks.set_status(&mut order.write(), OrderStatus::Rejected);
+            return Err(error);
+        }
+
+        if halted || trading_state == TradingState::Auction {
+            MatchingEngine::book_auction_order(&self.callbacks, order_book, &order)?;
+            result.remaining_order = Some(order);
+            return Ok(result);
+        }
+
         {
             let mut order_ref = order.write();
             if order_ref.order_type == OrderType::Market {
@@ -122,6 +1695,7 @@ impl MatchingEngine {
                         if let Some(price) = order_book.get_best_ask_price() {
                             order_ref.price = price;
                         } else {
+                            self.callbacks.set_status(&mut order_ref, OrderStatus::Rejected);
                             result.rejected = true;
                             return Err(MatchingError::NoLiquidity);
                         }
@@ -130,6 +1704,7 @@ impl MatchingEngine {
                         if let Some(price) = order_book.get_best_bid_price() {
                             order_ref.price = price;
                         } else {
+                            self.callbacks.set_status(&mut order_ref, OrderStatus::Rejected);
                             result.rejected = true;
                             return Err(MatchingError::NoLiquidity);
                         }
@@ -147,18 +1722,27 @@ impl MatchingEngine {
             is_stop_order = order_ref.is_stop_order();
         }
 
+        if !is_stop_order {
+            let breaker_check = MatchingEngine::check_circuit_breaker(order_book, &order.read());
+            if let Err(error) = breaker_check {
+                order_book.set_trading_state(TradingState::Halted);
+                self.callbacks.set_status(&mut order.write(), OrderStatus::Rejected);
+                result.rejected = true;
+                return Err(error);
+            }
+        }
+
         if time_in_force == TimeInForce::IOC || time_in_force == TimeInForce::FOK {
-            if time_in_force == TimeInForce::FOK {
-                if !MatchingEngine::can_fill_order(order_book, &order)? {
-                    result.rejected = true;
-                    let mut order_ref = order.write();
-                    order_ref.status = OrderStatus::Rejected;
-                    return Err(MatchingError::FOKCannotBeFilled);
-                }
+            if time_in_force == TimeInForce::FOK && !MatchingEngine::can_fill_order(order_book, &order) {
+                result.rejected = true;
+                let mut order_ref = order.write();
+                self.callbacks.set_status(&mut order_ref, OrderStatus::Rejected);
+                return Err(MatchingError::FOKCannotBeFilled);
             }
 
             MatchingEngine::match_order(
                 &mut self.next_trade_id,
+                &self.callbacks,
                 order_book,
                 Arc::clone(&order),
                 &mut result,
@@ -166,12 +1750,21 @@ impl MatchingEngine {
 
             {
                 let mut order_ref = order.write();
+                debug_assert!(
+                    time_in_force != TimeInForce::FOK || order_ref.is_filled(),
+                    "FOK order {} passed the fill check but matched only {} of {}",
+                    order_ref.id,
+                    order_ref.filled_quantity,
+                    order_ref.quantity
+                );
                 if order_ref.is_filled() {
-                    order_ref.status = OrderStatus::Filled;
-                } else if time_in_force == TimeInForce::IOC {
-                    order_ref.status = OrderStatus::Canceled;
+                    self.callbacks.set_status(&mut order_ref, OrderStatus::Filled);
+                } else if time_in_force == TimeInForce::IOC || order_ref.filled_quantity > 0 {
+                    // Trades already done stand: a FOK that traded is
+                    // cancelled like an IOC rather than rejected.
+                    self.callbacks.set_status(&mut order_ref, OrderStatus::Canceled);
                 } else {
-                    order_ref.status = OrderStatus::Rejected;
+                    self.callbacks.set_status(&mut order_ref, OrderStatus::Rejected);
                     result.rejected = true;
                     return Err(MatchingError::FOKCannotBeFilled);
                 }
@@ -181,14 +1774,15 @@ impl MatchingEngine {
             result.remaining_order = None;
             return Ok(result);
         } else if is_stop_order {
-            let should_trigger = if let Some(last_price) = order_book.last_trade_price {
+            let should_trigger = {
                 let order_ref = order.read();
-                order_ref.is_stop_triggered(last_price)
-            } else {
-                false
+                order_book
+                    .trigger_price(&order_ref)
+                    .is_some_and(|price| order_ref.is_stop_triggered(price))
             };
 
             if should_trigger {
+                result.add_triggered_stops([Arc::clone(&order)]);
                 {
                     let mut order_ref = order.write();
                     if order_ref.order_type == OrderType::StopMarket {
@@ -200,6 +1794,7 @@ impl MatchingEngine {
 
                 MatchingEngine::match_order(
                     &mut self.next_trade_id,
+                    &self.callbacks,
                     order_book,
                     Arc::clone(&order),
                     &mut result,
@@ -212,6 +1807,7 @@ impl MatchingEngine {
         } else {
             MatchingEngine::match_order(
                 &mut self.next_trade_id,
+                &self.callbacks,
                 order_book,
                 Arc::clone(&order),
                 &mut result,
@@ -225,85 +1821,811 @@ impl MatchingEngine {
             if !already_added {
                 result.filled_orders.push(Arc::clone(&order));
             }
-        } else if order_ref.order_type == OrderType::Limit
-            || order_ref.order_type == OrderType::Iceberg
-        {
-            drop(order_ref);
-            order_book.add_order(Arc::clone(&order))?;
-            result.remaining_order = Some(Arc::clone(&order));
+        } else if order_ref.order_type == OrderType::Limit
+            || order_ref.order_type == OrderType::Iceberg
+        {
+            order_book.debug_assert_rests_uncrossed(order_ref.side, order_ref.price);
+            drop(order_ref);
+            order_book.add_order(Arc::clone(&order))?;
+            result.remaining_order = Some(Arc::clone(&order));
+
+            let order_ref = order.read();
+            if order_ref.order_type == OrderType::Iceberg {
+                drop(order_ref);
+                order_book.replenish_iceberg_order(Arc::clone(&order))?;
+            }
+        } else if result.cancel_reason == Some(CancelReason::PriceProtection)
+            || order_ref.filled_quantity > 0
+        {
+            // Trades already done stand, along with the stops they
+            // triggered; the rest of the order is cancelled.
+            drop(order_ref);
+            result.cancel_reason.get_or_insert(CancelReason::NoLiquidity);
+            self.callbacks.set_status(&mut order.write(), OrderStatus::Canceled);
+            result.filled_orders.push(Arc::clone(&order));
+        } else {
+            drop(order_ref);
+            self.callbacks.set_status(&mut order.write(), OrderStatus::Rejected);
+            result.remaining_order = Some(Arc::clone(&order));
+            return Err(MatchingError::NoLiquidity);
+        }
+
+        let elapsed = start_time.elapsed().unwrap();
+        self.latency_metrics.record_order_processing_time(elapsed);
+
+        Ok(result)
+    }
+
+    /// Books an order during the auction call without matching. Market orders
+    /// rest at the extreme price on their side so they take part in the
+    /// uncross at whatever price it clears.
+    fn book_auction_order(
+        callbacks: &EngineCallbacks,
+        order_book: &mut OrderBook,
+        order: &Arc<RwLock<Order>>,
+    ) -> Result<(), MatchingError> {
+        let mut order_ref = order.write();
+
+        if matches!(order_ref.time_in_force, TimeInForce::IOC | TimeInForce::FOK) {
+            callbacks.set_status(&mut order_ref, OrderStatus::Rejected);
+            return Err(MatchingError::NotAcceptedInAuction);
+        }
+
+        if order_ref.is_stop_order() {
+            drop(order_ref);
+            order_book.add_stop_order(Arc::clone(order))?;
+            return Ok(());
+        }
+
+        if order_ref.order_type == OrderType::Market {
+            order_ref.price = match order_ref.side {
+                Side::Buy => Price::MAX,
+                Side::Sell => Price::MIN,
+            };
+        }
+
+        drop(order_ref);
+        order_book.add_order(Arc::clone(order))?;
+        Ok(())
+    }
+
+    fn check_order_type_rules(order_book: &OrderBook, order: &Order, now: i64) -> Result<(), MatchingError> {
+        let rules = order_book.order_type_rules();
+        if !rules.permits_order_type(order.order_type, order_book.trading_state(), now) {
+            return Err(MatchingError::OrderTypeNotAllowed(order.order_type));
+        }
+        if !rules.permits_time_in_force(order.time_in_force) {
+            return Err(MatchingError::TimeInForceNotAllowed(order.time_in_force));
+        }
+        Ok(())
+    }
+
+    fn check_symbol_spec(spec: SymbolSpec, order: &Order) -> Result<(), MatchingError> {
+        let tick_size = spec.tick_size.max(1);
+        let limit_price = matches!(
+            order.order_type,
+            OrderType::Limit | OrderType::Iceberg | OrderType::StopLimit
+        )
+        .then_some(order.price);
+        for price in limit_price.into_iter().chain(order.stop_price) {
+            if price.rem_euclid(tick_size) != 0 {
+                return Err(MatchingError::InvalidTickSize { price, tick_size });
+            }
+        }
+
+        let lot_size = spec.lot_size.max(1);
+        if !order.quantity.is_multiple_of(lot_size) {
+            return Err(MatchingError::InvalidLotSize {
+                quantity: order.quantity,
+                lot_size,
+            });
+        }
+        if order.quantity < spec.min_qty {
+            return Err(MatchingError::QuantityBelowMinimum {
+                quantity: order.quantity,
+                min_qty: spec.min_qty,
+            });
+        }
+        Ok(())
+    }
+
+    /// Refuses a client order id the user's live orders on the book already
+    /// carry.
+    fn check_client_order_id(order_book: &OrderBook, order: &Order) -> Result<(), MatchingError> {
+        match &order.client_order_id {
+            Some(client_order_id) if order_book.get_order_by_client_id(order.user_id, client_order_id).is_some() => {
+                Err(MatchingError::DuplicateClientOrderId(client_order_id.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn check_price_bands(order_book: &OrderBook, order: &Order) -> Result<(), MatchingError> {
+        if !matches!(
+            order.order_type,
+            OrderType::Limit | OrderType::Iceberg | OrderType::StopLimit
+        ) {
+            return Ok(());
+        }
+
+        let bands = order_book.price_bands();
+        let checks = [
+            (bands.reference_price, bands.static_band_bps),
+            (order_book.last_trade_price, bands.dynamic_band_bps),
+        ];
+
+        for (reference, bps) in checks {
+            if let (Some(reference), Some(bps)) = (reference, bps) {
+                let low = protection_limit(Side::Sell, reference, bps);
+                let high = protection_limit(Side::Buy, reference, bps);
+
+                if order.price < low || order.price > high {
+                    let limit = if order.price < low { low } else { high };
+                    return Err(MatchingError::PriceOutOfBand { limit, reference });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Rejects an order whose execution would move the last trade price past
+    /// the circuit breaker band, judged by the deepest level it would reach.
+    fn check_circuit_breaker(order_book: &OrderBook, order: &Order) -> Result<(), MatchingError> {
+        let (Some(bps), Some(reference)) = (
+            order_book.price_bands().circuit_breaker_bps,
+            order_book.last_trade_price,
+        ) else {
+            return Ok(());
+        };
+
+        let opposite_levels: Box<dyn Iterator<Item = (&Price, &PriceLevel)>> = match order.side {
+            Side::Buy => Box::new(order_book.sell_levels.iter()),
+            Side::Sell => Box::new(order_book.buy_levels.iter().rev()),
+        };
+
+        let mut unfilled = order.remaining_quantity();
+        let mut final_price = None;
+
+        for (&level_price, level) in opposite_levels {
+            let price_matches = match order.side {
+                Side::Buy => level_price <= order.price,
+                Side::Sell => level_price >= order.price,
+            };
+
+            if unfilled == 0 || (!price_matches && order.order_type != OrderType::Market) {
+                break;
+            }
+
+            final_price = Some(level_price);
+            let level_qty: u32 = level.orders.iter().map(|o| o.read().remaining_quantity()).sum();
+            unfilled = unfilled.saturating_sub(level_qty);
+        }
+
+        match final_price {
+            Some(price)
+                if price < protection_limit(Side::Sell, reference, bps)
+                    || price > protection_limit(Side::Buy, reference, bps) =>
+            {
+                Err(MatchingError::CircuitBreakerTripped { price, reference })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn set_price_bands(&mut self, symbol: &str, bands: PriceBands) -> Result<(), MatchingError> {
+        let command = || JournalCommand::SetPriceBands {
+            symbol: symbol.to_string(),
+            bands,
+        };
+        self.journaled(command, |engine| {
+            let order_book = engine
+                .order_books
+                .get_mut(symbol)
+                .ok_or(MatchingError::SymbolNotFound)?;
+            order_book.set_price_bands(bands);
+            Ok(())
+        })
+    }
+
+    /// Replaces the order types `symbol` accepts. Takes effect for the next
+    /// order; resting orders are left alone.
+    pub fn set_order_type_rules(&mut self, symbol: &str, rules: OrderTypeRules) -> Result<(), MatchingError> {
+        let command = || JournalCommand::SetOrderTypeRules {
+            symbol: symbol.to_string(),
+            rules: rules.clone(),
+        };
+        self.journaled(command, |engine| {
+            let order_book = engine
+                .order_books
+                .get_mut(symbol)
+                .ok_or(MatchingError::SymbolNotFound)?;
+            order_book.set_order_type_rules(rules.clone());
+            Ok(())
+        })
+    }
+
+    pub fn symbol_spec(&self, symbol: &str) -> Option<SymbolSpec> {
+        match self.order_books.get(symbol) {
+            Some(book) => Some(book.spec()),
+            None => self.hibernated.get(symbol).map(|stub| stub.spec),
+        }
+    }
+
+    /// Price of the symbol's most recent trade.
+    pub fn last_trade_price(&self, symbol: &str) -> Option<Price> {
+        match self.order_books.get(symbol) {
+            Some(book) => book.last_trade_price,
+            None => self.hibernated.get(symbol)?.last_trade_price,
+        }
+    }
+
+    /// Replaces the tick size, lot size and minimum quantity of `symbol`.
+    /// Takes effect for the next order; resting orders are left alone.
+    pub fn set_symbol_spec(&mut self, symbol: &str, spec: SymbolSpec) -> Result<(), MatchingError> {
+        let command = || JournalCommand::SetSymbolSpec {
+            symbol: symbol.to_string(),
+            spec,
+        };
+        self.journaled(command, |engine| {
+            let order_book = engine
+                .order_books
+                .get_mut(symbol)
+                .ok_or(MatchingError::SymbolNotFound)?;
+            order_book.set_spec(spec);
+            Ok(())
+        })
+    }
+
+    pub fn order_type_rules(&self, symbol: &str) -> Option<OrderTypeRules> {
+        match self.order_books.get(symbol) {
+            Some(book) => Some(book.order_type_rules().clone()),
+            None => self.hibernated.get(symbol).map(|stub| stub.order_type_rules.clone()),
+        }
+    }
+
+    pub fn set_fee_schedule(&mut self, symbol: &str, schedule: FeeSchedule) -> Result<(), MatchingError> {
+        let command = || JournalCommand::SetFeeSchedule {
+            symbol: symbol.to_string(),
+            schedule,
+        };
+        self.journaled(command, |engine| {
+            let order_book = engine
+                .order_books
+                .get_mut(symbol)
+                .ok_or(MatchingError::SymbolNotFound)?;
+            order_book.set_fee_schedule(schedule);
+            Ok(())
+        })
+    }
+
+    /// Returns a halted symbol to continuous trading. Orders queued while
+    /// it was halted that cross are first executed in an uncross, as at
+    /// the end of an auction call; its trades go out through the
+    /// callbacks and events like any other.
+    pub fn resume_trading(&mut self, symbol: &str) -> Result<(), MatchingError> {
+        let command = || JournalCommand::ResumeTrading {
+            symbol: symbol.to_string(),
+        };
+        self.journaled(command, |engine| {
+            let order_book = engine
+                .order_books
+                .get_mut(symbol)
+                .ok_or(MatchingError::SymbolNotFound)?;
+
+            if order_book.trading_state() == TradingState::Halted {
+                engine.reopen(symbol)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Moves a halted or auction book into continuous trading. Orders
+    /// queued while it was not trading are uncrossed first if they would
+    /// trade, so continuous matching never starts on a crossed book.
+    fn reopen(&mut self, symbol: &str) -> Result<(), MatchingError> {
+        let order_book = self
+            .order_books
+            .get_mut(symbol)
+            .ok_or(MatchingError::SymbolNotFound)?;
+        if order_book.indicative_uncross().is_some() || order_book.has_queued_market_orders() {
+            order_book.set_trading_state(TradingState::Auction);
+            self.process_uncross(symbol)?;
+        } else {
+            order_book.set_trading_state(TradingState::Continuous);
+        }
+        Ok(())
+    }
+
+    /// Stops new orders on `symbol`: they are rejected with
+    /// `MatchingError::SymbolHalted`, or queued if the engine is configured
+    /// with `HaltedOrders::Queue`. Resting orders stay on the book.
+    pub fn halt_symbol(&mut self, symbol: &str) -> Result<(), MatchingError> {
+        self.set_trading_state(symbol, TradingState::Halted)
+    }
+
+    pub fn resume_symbol(&mut self, symbol: &str) -> Result<(), MatchingError> {
+        self.resume_trading(symbol)
+    }
+
+    /// Delists `symbol`, cancelling its resting and stop orders. The orders
+    /// are returned in id order and stay available to status queries.
+    pub fn remove_symbol(&mut self, symbol: &str) -> Result<Vec<Arc<RwLock<Order>>>, MatchingError> {
+        let command = || JournalCommand::RemoveSymbol {
+            symbol: symbol.to_string(),
+        };
+        self.journaled(command, |engine| {
+            let mut order_book = engine
+                .order_books
+                .remove(symbol)
+                .ok_or(MatchingError::SymbolNotFound)?;
+
+            let canceled = order_book.cancel_all();
+            for order in &canceled {
+                engine.finish_cancel(order);
+            }
+            engine.session_trades.remove(symbol);
+            engine.recent_trades.remove(symbol);
+            engine.symbol_activity.remove(symbol);
+            Ok(canceled)
+        })
+    }
+
+    /// Every listed symbol, in symbol order.
+    pub fn list_symbols(&self) -> Vec<SymbolInfo> {
+        const ORDER_TYPES: [OrderType; 5] = [
+            OrderType::Limit,
+            OrderType::Market,
+            OrderType::StopLimit,
+            OrderType::StopMarket,
+            OrderType::Iceberg,
+        ];
+        const TIME_IN_FORCE: [TimeInForce; 5] = [
+            TimeInForce::GTC,
+            TimeInForce::IOC,
+            TimeInForce::FOK,
+            TimeInForce::GTD,
+            TimeInForce::Day,
+        ];
+
+        let now = self.clock.now_nanos();
+        let info = |symbol: &String,
+                    trading_state: TradingState,
+                    rules: &OrderTypeRules,
+                    (resting_orders, stop_orders): (usize, usize),
+                    last_trade_price: Option<Price>| SymbolInfo {
+            symbol: symbol.clone(),
+            trading_state,
+            resting_orders,
+            stop_orders,
+            last_trade_price,
+            order_types: ORDER_TYPES
+                .into_iter()
+                .filter(|t| rules.permits_order_type(*t, trading_state, now))
+                .collect(),
+            time_in_force: TIME_IN_FORCE
+                .into_iter()
+                .filter(|tif| rules.permits_time_in_force(*tif))
+                .collect(),
+        };
+        let mut symbols: Vec<SymbolInfo> = self
+            .order_books
+            .iter()
+            .map(|(symbol, book)| {
+                info(
+                    symbol,
+                    book.trading_state(),
+                    book.order_type_rules(),
+                    (book.order_count(), book.stop_order_count()),
+                    book.last_trade_price,
+                )
+            })
+            .chain(self.hibernated.iter().map(|(symbol, stub)| {
+                info(symbol, stub.trading_state, &stub.order_type_rules, (0, 0), stub.last_trade_price)
+            }))
+            .collect();
+        symbols.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        symbols
+    }
+
+    pub fn trading_state(&self, symbol: &str) -> Option<TradingState> {
+        match self.order_books.get(symbol) {
+            Some(book) => Some(book.trading_state()),
+            None => self.hibernated.get(symbol).map(|stub| stub.trading_state),
+        }
+    }
+
+    /// Sets `symbol`'s trading state. A halted or auction book set to
+    /// `Continuous` reopens through an uncross as in `resume_trading`.
+    pub fn set_trading_state(
+        &mut self,
+        symbol: &str,
+        state: TradingState,
+    ) -> Result<(), MatchingError> {
+        let command = || JournalCommand::SetTradingState {
+            symbol: symbol.to_string(),
+            state,
+        };
+        self.journaled(command, |engine| {
+            let order_book = engine
+                .order_books
+                .get_mut(symbol)
+                .ok_or(MatchingError::SymbolNotFound)?;
+            if state == TradingState::Continuous && order_book.trading_state() != state {
+                engine.reopen(symbol)?;
+            } else {
+                order_book.set_trading_state(state);
+            }
+            engine.publish_indicative_uncross(symbol);
+            Ok(())
+        })
+    }
+
+    /// Sets the price source used by the symbol's stop orders that do not
+    /// choose one themselves.
+    pub fn set_stop_trigger_source(
+        &mut self,
+        symbol: &str,
+        source: TriggerSource,
+    ) -> Result<(), MatchingError> {
+        let command = || JournalCommand::SetStopTriggerSource {
+            symbol: symbol.to_string(),
+            source,
+        };
+        self.journaled(command, |engine| {
+            let order_book = engine
+                .order_books
+                .get_mut(symbol)
+                .ok_or(MatchingError::SymbolNotFound)?;
+            order_book.set_stop_trigger_source(source);
+            Ok(())
+        })
+    }
+
+    /// Records a new mark price and activates the stop orders it triggers.
+    pub fn update_mark_price(
+        &mut self,
+        symbol: &str,
+        price: Price,
+    ) -> Result<TradeExecutionResult, MatchingError> {
+        self.update_reference_price(symbol, TriggerSource::MarkPrice, price)
+    }
+
+    /// Records a new index price and activates the stop orders it triggers.
+    pub fn update_index_price(
+        &mut self,
+        symbol: &str,
+        price: Price,
+    ) -> Result<TradeExecutionResult, MatchingError> {
+        self.update_reference_price(symbol, TriggerSource::IndexPrice, price)
+    }
+
+    pub(crate) fn update_reference_price(
+        &mut self,
+        symbol: &str,
+        source: TriggerSource,
+        price: Price,
+    ) -> Result<TradeExecutionResult, MatchingError> {
+        let command = || JournalCommand::UpdateReferencePrice {
+            symbol: symbol.to_string(),
+            source,
+            price,
+        };
+        self.journaled(command, |engine| {
+            engine.process_reference_price(symbol, source, price)
+        })
+    }
+
+    fn process_reference_price(
+        &mut self,
+        symbol: &str,
+        source: TriggerSource,
+        price: Price,
+    ) -> Result<TradeExecutionResult, MatchingError> {
+        let order_book = self
+            .order_books
+            .get_mut(symbol)
+            .ok_or(MatchingError::SymbolNotFound)?;
+
+        let mut result = TradeExecutionResult::new();
+        // Outside continuous trading the price is only recorded; stops it
+        // crosses fire on the next update once trading resumes.
+        if order_book.trading_state() != TradingState::Continuous {
+            match source {
+                TriggerSource::LastTrade => order_book.last_trade_price = Some(price),
+                TriggerSource::MarkPrice => order_book.mark_price = Some(price),
+                TriggerSource::IndexPrice => order_book.index_price = Some(price),
+            }
+            return Ok(result);
+        }
+
+        let triggered = match source {
+            TriggerSource::LastTrade => order_book.update_last_trade_price(price)?,
+            TriggerSource::MarkPrice => order_book.update_mark_price(price),
+            TriggerSource::IndexPrice => order_book.update_index_price(price),
+        };
+        if triggered.is_empty() {
+            return Ok(result);
+        }
+        result.add_triggered_stops(triggered);
+        self.run_triggered_stops(symbol, &mut result)?;
+
+        if self.events.has_subscribers() {
+            self.publish_execution(symbol, None, &result);
+        }
+
+        if !result.trades.is_empty() {
+            self.record_session_trades(symbol, &result.trades);
+            self.fire_contingent_orders(symbol, &mut result);
+        }
+
+        Ok(result)
+    }
+
+    pub fn indicative_price(&self, symbol: &str) -> Option<Price> {
+        self.indicative_uncross(symbol).map(|uncross| uncross.price)
+    }
+
+    pub fn indicative_uncross(&self, symbol: &str) -> Option<IndicativeUncross> {
+        self.order_books.get(symbol)?.indicative_uncross()
+    }
+
+    /// The top `levels` visible prices on each side of `symbol`. A
+    /// hibernated symbol has an empty book; `None` if it is not listed.
+    pub fn market_depth(&self, symbol: &str, levels: usize) -> Option<MarketDepth> {
+        match self.order_books.get(symbol) {
+            Some(book) => Some(book.market_depth(levels)),
+            None if self.is_hibernated(symbol) => Some(MarketDepth::new(Vec::new(), Vec::new())),
+            None => None,
+        }
+    }
+
+    /// `market_depth` with each level's order count and oldest order.
+    pub fn detailed_market_depth(&self, symbol: &str, levels: usize) -> Option<MarketDepth> {
+        match self.order_books.get(symbol) {
+            Some(book) => Some(book.detailed_market_depth(levels)),
+            None => self.market_depth(symbol, levels),
+        }
+    }
+
+    /// Publishes the indicative uncross of `symbol` if it differs from the
+    /// last one published. `None` goes out once when a published uncross
+    /// disappears, because the book no longer crosses or left the auction.
+    fn publish_indicative_uncross(&mut self, symbol: &str) {
+        if !self.events.has_subscribers() {
+            return;
+        }
+
+        let uncross = self
+            .order_books
+            .get(symbol)
+            .filter(|book| book.trading_state() == TradingState::Auction)
+            .and_then(OrderBook::indicative_uncross);
+        let previous = match uncross {
+            Some(uncross) => self.published_uncross.insert(symbol.to_string(), uncross),
+            None => self.published_uncross.remove(symbol),
+        };
+        if previous != uncross {
+            self.events.publish(EngineEvent::IndicativeUncross {
+                symbol: symbol.to_string(),
+                uncross,
+            });
+        }
+    }
+
+    /// Ends the auction call: executes every crossing order at the single
+    /// clearing price, cancels market orders left unfilled and returns the
+    /// symbol to continuous trading.
+    pub fn uncross(&mut self, symbol: &str) -> Result<TradeExecutionResult, MatchingError> {
+        let command = || JournalCommand::Uncross {
+            symbol: symbol.to_string(),
+        };
+        self.journaled(command, |engine| engine.process_uncross(symbol))
+    }
+
+    fn process_uncross(&mut self, symbol: &str) -> Result<TradeExecutionResult, MatchingError> {
+        let order_book = self
+            .order_books
+            .get_mut(symbol)
+            .ok_or(MatchingError::SymbolNotFound)?;
+
+        if order_book.trading_state() != TradingState::Auction {
+            return Err(MatchingError::NotInAuction);
+        }
+
+        let mut result = TradeExecutionResult::new();
+
+        if let Some(uncross) = order_book.indicative_uncross() {
+            let clearing_price = uncross.price;
+
+            while let (Some(bid), Some(ask)) =
+                (order_book.get_best_bid_price(), order_book.get_best_ask_price())
+            {
+                if bid < clearing_price || ask > clearing_price {
+                    break;
+                }
+
+                let buy_order = Arc::clone(&order_book.buy_levels[&bid].orders[0]);
+                let sell_order = Arc::clone(&order_book.sell_levels[&ask].orders[0]);
+                let quantity = std::cmp::min(
+                    buy_order.read().remaining_quantity(),
+                    sell_order.read().remaining_quantity(),
+                );
+
+                MatchingEngine::execute_trade(
+                    &mut self.next_trade_id,
+                    &self.callbacks,
+                    Arc::clone(&buy_order),
+                    Arc::clone(&sell_order),
+                    Fill {
+                        quantity,
+                        price: clearing_price,
+                        fees: order_book.fee_schedule(),
+                        aggressor: None,
+                    },
+                    &mut result,
+                )?;
+
+                for (order, side, price) in [(buy_order, Side::Buy, bid), (sell_order, Side::Sell, ask)] {
+                    order_book.l3_feed.execute(&order.read(), quantity);
+                    if order.read().is_filled() {
+                        result.filled_orders.push(Arc::clone(&order));
+                    }
+                    order_book.refresh_level(side, price);
+                }
+            }
+        }
 
-            let order_ref = order.read();
-            if order_ref.order_type == OrderType::Iceberg {
-                drop(order_ref);
-                order_book.replenish_iceberg_order(Arc::clone(&order))?;
+        for price in [Price::MAX, Price::MIN] {
+            let levels = if price == Price::MIN {
+                &order_book.sell_levels
+            } else {
+                &order_book.buy_levels
+            };
+            let unfilled_market: Vec<u64> = levels
+                .get(&price)
+                .map(|level| {
+                    level
+                        .orders
+                        .iter()
+                        .map(|o| o.read())
+                        .filter(|o| o.order_type == OrderType::Market)
+                        .map(|o| o.id)
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            for order_id in unfilled_market {
+                if let Some(order) = order_book.cancel_order(order_id) {
+                    self.callbacks.set_status(&mut order.write(), OrderStatus::Canceled);
+                    result.filled_orders.push(order);
+                }
             }
-        } else {
-            result.remaining_order = Some(Arc::clone(&order));
-            return Err(MatchingError::NoLiquidity);
         }
 
-        let elapsed = start_time.elapsed().unwrap();
-        self.latency_metrics.record_order_processing_time(elapsed);
+        if let Some(last_trade) = result.trades.last() {
+            let triggered = order_book.update_last_trade_price(last_trade.price)?;
+            result.add_triggered_stops(triggered);
+        }
+
+        order_book.set_trading_state(TradingState::Continuous);
+
+        let canceled: Vec<Order> = result
+            .filled_orders
+            .iter()
+            .map(|o| o.read())
+            .filter(|o| o.status == OrderStatus::Canceled)
+            .map(|o| o.clone())
+            .collect();
+        for order in &canceled {
+            self.release_participant_exposure(order);
+        }
+        self.run_triggered_stops(symbol, &mut result)?;
+
+        if self.events.has_subscribers() {
+            self.publish_execution(symbol, None, &result);
+        }
+        self.publish_indicative_uncross(symbol);
+
+        if !result.trades.is_empty() {
+            self.record_session_trades(symbol, &result.trades);
+            self.fire_contingent_orders(symbol, &mut result);
+        }
 
         Ok(result)
     }
 
-    fn can_fill_order(
-        order_book: &OrderBook,
-        order: &Arc<RwLock<Order>>,
-    ) -> Result<bool, MatchingError> {
+    /// Whether the opposite side of `order_book` holds enough that
+    /// `order` can trade against to fill it completely. The FOK pre-check,
+    /// walking the levels the way `match_order` will.
+    fn can_fill_order(order_book: &OrderBook, order: &Arc<RwLock<Order>>) -> bool {
         let order_ref = order.read();
-        let remaining_qty = order_ref.quantity;
-        let side = order_ref.side;
-        let price = order_ref.price;
-        let order_type = order_ref.order_type;
+        let protection_price = MatchingEngine::market_protection_price(order_book, &order_ref);
+        let remaining_qty = order_ref.remaining_quantity();
 
-        let opposite_levels = match side {
-            Side::Buy => &order_book.sell_levels,
-            Side::Sell => &order_book.buy_levels,
+        let opposite_levels: Box<dyn Iterator<Item = (&Price, &PriceLevel)>> = match order_ref.side {
+            Side::Buy => Box::new(order_book.sell_levels.iter()),
+            Side::Sell => Box::new(order_book.buy_levels.iter().rev()),
         };
 
-        let mut prices: Vec<u64> = opposite_levels.keys().cloned().collect();
-
-        if side == Side::Buy {
-            prices.sort_unstable();
-        } else {
-            prices.sort_unstable_by(|a, b| b.cmp(a));
+        let mut available_qty: u64 = 0;
+        for (&level_price, level) in opposite_levels {
+            if MatchingEngine::match_stop(&order_ref, protection_price, level_price).is_some() {
+                break;
+            }
+            available_qty += level.orders.iter().map(|o| o.read().remaining_quantity() as u64).sum::<u64>();
+            if available_qty >= remaining_qty as u64 {
+                return true;
+            }
         }
 
-        let mut available_qty = 0;
+        remaining_qty == 0
+    }
 
-        for &level_price in &prices {
-            let price_matches = match side {
-                Side::Buy => level_price <= price,
-                Side::Sell => level_price >= price,
-            };
+    /// Why `order` cannot trade against an opposite level at `level_price`,
+    /// or `None` if it can. Orders stop at their limit price; a market
+    /// order takes any price within its protection band.
+    pub(crate) fn match_stop(order: &Order, protection_price: Option<Price>, level_price: Price) -> Option<MatchStop> {
+        let within_band = protection_price.is_none_or(|limit| match order.side {
+            Side::Buy => level_price <= limit,
+            Side::Sell => level_price >= limit,
+        });
+        if !within_band {
+            return Some(MatchStop::PriceProtection);
+        }
 
-            if !price_matches && order_type == OrderType::Limit {
-                break;
-            }
+        let price_matches = match order.side {
+            Side::Buy => level_price <= order.price,
+            Side::Sell => level_price >= order.price,
+        };
+        if !price_matches && order.order_type != OrderType::Market {
+            return Some(MatchStop::LimitPrice);
+        }
+        None
+    }
 
-            if let Some(level) = opposite_levels.get(&level_price) {
-                for resting_order in &level.orders {
-                    available_qty += resting_order.read().remaining_quantity();
-                }
+    /// Worst price a market order may sweep to: the tighter of its explicit
+    /// `protection_price` and the `max_slippage_bps` band around the best
+    /// opposite price on entry. `None` for other order types.
+    fn market_protection_price(order_book: &OrderBook, order: &Order) -> Option<Price> {
+        let best_price = match order.side {
+            Side::Buy => order_book.get_best_ask_price(),
+            Side::Sell => order_book.get_best_bid_price(),
+        };
+        MatchingEngine::protection_price_from(best_price, order)
+    }
 
-                if available_qty >= remaining_qty {
-                    return Ok(true);
-                }
-            }
+    /// `market_protection_price` given the best opposite price on entry.
+    pub(crate) fn protection_price_from(best_price: Option<Price>, order: &Order) -> Option<Price> {
+        if order.order_type != OrderType::Market {
+            return None;
         }
 
-        Ok(false)
+        let slippage_limit = best_price
+            .zip(order.max_slippage_bps)
+            .map(|(price, bps)| protection_limit(order.side, price, bps));
+
+        match (slippage_limit, order.protection_price) {
+            (Some(a), Some(b)) => Some(match order.side {
+                Side::Buy => a.min(b),
+                Side::Sell => a.max(b),
+            }),
+            (limit, None) | (None, limit) => limit,
+        }
     }
 
     fn match_order(
         next_trade_id: &mut u64,
+        callbacks: &EngineCallbacks,
         order_book: &mut OrderBook,
         incoming_order: Arc<RwLock<Order>>,
         result: &mut TradeExecutionResult,
     ) -> Result<(), MatchingError> {
         let mut continue_matching = true;
+        let protection_price = MatchingEngine::market_protection_price(order_book, &incoming_order.read());
+        let match_policy = order_book.match_policy();
+        let fee_schedule = order_book.fee_schedule();
 
         while continue_matching {
             if incoming_order.read().is_filled() {
@@ -322,16 +2644,14 @@ impl MatchingEngine {
 
             let best_price = best_price.unwrap();
 
-            let price_matches = {
-                let order_ref = incoming_order.read();
-                match side {
-                    Side::Buy => best_price <= order_ref.price,
-                    Side::Sell => best_price >= order_ref.price,
+            let stop = MatchingEngine::match_stop(&incoming_order.read(), protection_price, best_price);
+            match stop {
+                Some(MatchStop::PriceProtection) => {
+                    result.cancel_reason = Some(CancelReason::PriceProtection);
+                    break;
                 }
-            };
-
-            if !price_matches && incoming_order.read().order_type == OrderType::Limit {
-                break;
+                Some(MatchStop::LimitPrice) => break,
+                None => {}
             }
 
             let opposite_levels = match side {
@@ -340,48 +2660,49 @@ impl MatchingEngine {
             };
 
             if let Some(level) = opposite_levels.get_mut(&best_price) {
-                let mut i = 0;
-                let mut orders_to_replenish = Vec::new();
-
-                while i < level.orders.len() && !incoming_order.read().is_filled() {
-                    let resting_order: Arc<RwLock<Order>> = Arc::clone(&level.orders[i]);
-
-                    let trade_qty = std::cmp::min(
-                        incoming_order.read().remaining_quantity(),
-                        resting_order.read().visible_quantity(),
-                    );
-
-                    if trade_qty > 0 {
-                        MatchingEngine::execute_trade(
-                            next_trade_id,
-                            Arc::clone(&incoming_order),
-                            Arc::clone(&resting_order),
-                            trade_qty,
-                            best_price,
-                            result,
-                        )?;
-
-                        if resting_order.read().is_filled() {
-                            level.orders.remove(i);
-                            result.filled_orders.push(Arc::clone(&resting_order));
-                        } else {
-                            if resting_order.read().order_type == OrderType::Iceberg {
-                                orders_to_replenish.push(Arc::clone(&resting_order));
-                            }
-                            i += 1;
-                        }
-                    } else {
-                        i += 1;
+                let mut filled = Vec::new();
+                let incoming_qty = incoming_order.read().remaining_quantity();
+                let fills = allocate_level(match_policy, incoming_qty, &level.orders);
+
+                for (resting_order, trade_qty) in fills {
+                    let (resting_id, visible_before) = {
+                        let resting = resting_order.read();
+                        (resting.id, resting.visible_quantity())
+                    };
+                    MatchingEngine::execute_trade(
+                        next_trade_id,
+                        callbacks,
+                        Arc::clone(&incoming_order),
+                        Arc::clone(&resting_order),
+                        Fill {
+                            quantity: trade_qty,
+                            price: best_price,
+                            fees: fee_schedule,
+                            aggressor: Some(side),
+                        },
+                        result,
+                    )?;
+
+                    let settled = level
+                        .settle_fill(resting_id, trade_qty, visible_before)
+                        .map_err(|e| MatchingError::InternalError(e.to_string()))?;
+                    let resting = resting_order.read();
+                    order_book.l3_feed.execute(&resting, trade_qty);
+                    if resting.has_fresh_slice() {
+                        order_book.l3_feed.replace(&resting, false);
+                    }
+                    drop(resting);
+                    if let Some(order) = settled {
+                        result.filled_orders.push(Arc::clone(&order));
+                        filled.push(order);
                     }
                 }
 
-                if level.orders.is_empty() {
-                    opposite_levels.remove(&best_price);
-                }
-
-                for order in orders_to_replenish {
-                    order_book.replenish_iceberg_order(order)?;
-                }
+                let opposite_side = match side {
+                    Side::Buy => Side::Sell,
+                    Side::Sell => Side::Buy,
+                };
+                order_book.finish_level_fills(opposite_side, best_price, &filled);
             } else {
                 continue_matching = false;
             }
@@ -389,20 +2710,33 @@ impl MatchingEngine {
 
         if !result.trades.is_empty() {
             let last_trade = &result.trades[result.trades.len() - 1];
-            order_book.update_last_trade_price(last_trade.price)?;
+            let triggered = order_book.update_last_trade_price(last_trade.price)?;
+            result.add_triggered_stops(triggered);
         }
 
         Ok(())
     }
 
-    fn execute_trade(
+    pub(crate) fn execute_trade(
         next_trade_id: &mut u64,
+        callbacks: &EngineCallbacks,
         buy_order: Arc<RwLock<Order>>,
         sell_order: Arc<RwLock<Order>>,
-        quantity: u32,
-        price: u64,
+        fill: Fill,
         result: &mut TradeExecutionResult,
     ) -> Result<(), MatchingError> {
+        let Fill {
+            quantity,
+            price,
+            fees,
+            aggressor,
+        } = fill;
+        let maker_fee = fees.maker_fee(price, quantity);
+        let taker_fee = match aggressor {
+            Some(_) => fees.taker_fee(price, quantity),
+            None => maker_fee,
+        };
+
         let trade = Trade {
             id: *next_trade_id,
             buy_order_id: if buy_order.read().side == Side::Buy {
@@ -417,29 +2751,33 @@ impl MatchingEngine {
             },
             price,
             quantity,
-            timestamp: get_nano_timestamp(),
+            timestamp: callbacks.next_trade_timestamp(),
+            maker_fee,
+            taker_fee,
+            aggressor,
         };
         *next_trade_id += 1;
+        callbacks.trade(&trade);
 
         {
             let mut buy_ref = buy_order.write();
-            buy_ref.filled_quantity += quantity;
+            buy_ref.record_fill(price, quantity, trade.timestamp);
 
             if buy_ref.is_filled() {
-                buy_ref.status = OrderStatus::Filled;
+                callbacks.set_status(&mut buy_ref, OrderStatus::Filled);
             } else {
-                buy_ref.status = OrderStatus::PartiallyFilled;
+                callbacks.set_status(&mut buy_ref, OrderStatus::PartiallyFilled);
             }
         }
 
         {
             let mut sell_ref = sell_order.write();
-            sell_ref.filled_quantity += quantity;
+            sell_ref.record_fill(price, quantity, trade.timestamp);
 
             if sell_ref.is_filled() {
-                sell_ref.status = OrderStatus::Filled;
+                callbacks.set_status(&mut sell_ref, OrderStatus::Filled);
             } else {
-                sell_ref.status = OrderStatus::PartiallyFilled;
+                callbacks.set_status(&mut sell_ref, OrderStatus::PartiallyFilled);
             }
         }
 
@@ -449,26 +2787,386 @@ impl MatchingEngine {
     }
 
     pub fn cancel_order(&mut self, symbol: &str, order_id: u64) -> Option<Arc<RwLock<Order>>> {
-        if let Some(order_book) = self.order_books.get_mut(symbol) {
-            if let Some(canceled_order) = order_book.cancel_order(order_id) {
-                let mut order_ref = canceled_order.write();
-                order_ref.status = OrderStatus::Canceled;
-                drop(order_ref);
-                return Some(canceled_order);
+        let command = || JournalCommand::CancelOrder {
+            symbol: symbol.to_string(),
+            order_id,
+        };
+        self.journaled(command, |engine| {
+            let canceled_order = engine.order_books.get_mut(symbol)?.cancel_order(order_id)?;
+            engine.finish_cancel(&canceled_order);
+            Some(canceled_order)
+        })
+    }
+
+    /// The live order `user_id` placed on `symbol` with `client_order_id`.
+    pub fn get_order_by_client_id(&self, symbol: &str, user_id: u64, client_order_id: &str) -> Option<Order> {
+        let order = self.order_books.get(symbol)?.get_order_by_client_id(user_id, client_order_id)?;
+        let order = order.read().clone();
+        Some(order)
+    }
+
+    /// Cancels the live order `user_id` placed on `symbol` with
+    /// `client_order_id`. It is journaled as a cancel by order id.
+    pub fn cancel_order_by_client_id(
+        &mut self,
+        symbol: &str,
+        user_id: u64,
+        client_order_id: &str,
+    ) -> Option<Arc<RwLock<Order>>> {
+        self.wake_symbol(symbol);
+        let order_id = self
+            .order_books
+            .get(symbol)?
+            .get_order_by_client_id(user_id, client_order_id)?
+            .read()
+            .id;
+        self.cancel_order(symbol, order_id)
+    }
+
+    /// Cancels a resting or stop order and enters a copy of it at `price`
+    /// for `quantity`. The replacement gets a new id and loses time
+    /// priority.
+    pub fn replace_order(
+        &mut self,
+        symbol: &str,
+        order_id: u64,
+        price: Price,
+        quantity: u32,
+    ) -> Result<TradeExecutionResult, MatchingError> {
+        let command = || JournalCommand::ReplaceOrder {
+            symbol: symbol.to_string(),
+            order_id,
+            price,
+            quantity,
+        };
+        self.journaled(command, |engine| {
+            let order_book = engine
+                .order_books
+                .get_mut(symbol)
+                .ok_or(MatchingError::SymbolNotFound)?;
+            let canceled_order = order_book
+                .cancel_order(order_id)
+                .ok_or(MatchingError::OrderNotFound)?;
+            engine.finish_cancel(&canceled_order);
+
+            let now = engine.callbacks.now();
+            let mut replacement = canceled_order.read().clone();
+            replacement.id = 0;
+            replacement.price = price;
+            replacement.quantity = quantity;
+            replacement.filled_quantity = 0;
+            replacement.fill_notional = 0;
+            replacement.status = OrderStatus::New;
+            replacement.timestamp = now;
+            replacement.last_update = now;
+
+            engine.process_order(replacement)
+        })
+    }
+
+    /// Replaces `user_id`'s quote on `symbol` with a new bid and ask in one
+    /// step. The previous quote's live orders are cancelled and both new
+    /// sides booked as GTC limit orders, each matching if it crosses.
+    ///
+    /// Both sides are checked before anything changes, so a rejection of
+    /// either leaves the previous quote in place and uses no order ids.
+    pub fn submit_quote(
+        &mut self,
+        symbol: &str,
+        user_id: u64,
+        bid_price: Price,
+        bid_quantity: u32,
+        ask_price: Price,
+        ask_quantity: u32,
+    ) -> Result<QuoteResult, MatchingError> {
+        let command = || JournalCommand::SubmitQuote {
+            symbol: symbol.to_string(),
+            user_id,
+            bid_price,
+            bid_quantity,
+            ask_price,
+            ask_quantity,
+        };
+        self.journaled(command, |engine| {
+            let now = engine.callbacks.now();
+            let quote_order = |side, price, quantity| {
+                let mut order = Order::new(symbol.to_string(), side, OrderType::Limit, price, quantity, user_id);
+                order.timestamp = now;
+                order.last_update = now;
+                order
+            };
+            let bid = quote_order(Side::Buy, bid_price, bid_quantity);
+            let ask = quote_order(Side::Sell, ask_price, ask_quantity);
+
+            if bid_price >= ask_price || bid_quantity == 0 || ask_quantity == 0 {
+                return Err(MatchingError::InvalidQuote { bid_price, ask_price });
+            }
+            let key = (user_id, symbol.to_string());
+            let previous: Vec<u64> = engine
+                .quotes
+                .get(&key)
+                .map(|quote| vec![quote.bid_order_id, quote.ask_order_id])
+                .unwrap_or_default();
+            if let Err(error) = engine.check_quote(&bid, &ask, &previous) {
+                if let MatchingError::CircuitBreakerTripped { .. } = error {
+                    if let Some(order_book) = engine.order_books.get_mut(symbol) {
+                        order_book.set_trading_state(TradingState::Halted);
+                    }
+                }
+                return Err(error);
+            }
+
+            let mut replaced_order_ids = Vec::new();
+            for order_id in previous {
+                let canceled = engine
+                    .order_books
+                    .get_mut(symbol)
+                    .and_then(|book| book.cancel_order(order_id));
+                if let Some(canceled) = canceled {
+                    engine.finish_cancel(&canceled);
+                    replaced_order_ids.push(order_id);
+                }
+            }
+            engine.quotes.remove(&key);
+
+            let bid_order_id = engine.next_order_id;
+            let bid = engine.process_order(bid)?;
+            let ask_order_id = engine.next_order_id;
+            let ask = match engine.process_order(ask) {
+                Ok(ask) => ask,
+                Err(error) => {
+                    // Only reachable if the bid's own fills moved the book
+                    // past what the checks above saw: pull the bid too.
+                    let canceled = engine
+                        .order_books
+                        .get_mut(symbol)
+                        .and_then(|book| book.cancel_order(bid_order_id));
+                    if let Some(canceled) = canceled {
+                        engine.finish_cancel(&canceled);
+                    }
+                    return Err(error);
+                }
+            };
+
+            engine.quotes.insert(
+                key,
+                Quote {
+                    symbol: symbol.to_string(),
+                    user_id,
+                    bid_order_id,
+                    ask_order_id,
+                },
+            );
+            Ok(QuoteResult {
+                bid_order_id,
+                ask_order_id,
+                replaced_order_ids,
+                bid,
+                ask,
+            })
+        })
+    }
+
+    pub fn quote(&self, user_id: u64, symbol: &str) -> Option<&Quote> {
+        self.quotes.get(&(user_id, symbol.to_string()))
+    }
+
+    /// Runs every check `submit_order` would make on both sides of a quote
+    /// without changing anything. Orders of the quote being replaced do not
+    /// count towards the open order limit.
+    fn check_quote(&self, bid: &Order, ask: &Order, previous: &[u64]) -> Result<(), MatchingError> {
+        let order_book = self
+            .order_books
+            .get(&bid.symbol)
+            .ok_or(MatchingError::SymbolNotFound)?;
+        if order_book.trading_state() == TradingState::Halted {
+            return Err(MatchingError::SymbolHalted);
+        }
+
+        let live_previous = previous
+            .iter()
+            .filter(|&&order_id| order_book.contains_order(order_id))
+            .count();
+        let open_orders = self.open_orders.get(bid.user_id).saturating_sub(live_previous);
+
+        for (order, already_booked) in [(bid, 0), (ask, 1)] {
+            self.check_risk_limits_with_open(order, open_orders + already_booked)?;
+            self.check_participant_limits(order)?;
+            MatchingEngine::check_order_type_rules(order_book, order, self.callbacks.now())?;
+            MatchingEngine::check_symbol_spec(order_book.spec(), order)?;
+            MatchingEngine::check_price_bands(order_book, order)?;
+            if order_book.trading_state() == TradingState::Continuous {
+                MatchingEngine::check_circuit_breaker(order_book, order)?;
             }
         }
-        None
+        Ok(())
+    }
+
+    /// Cancels every resting and stop order `user_id` has on any symbol.
+    pub fn cancel_all_for_user(&mut self, user_id: u64) -> Vec<Arc<RwLock<Order>>> {
+        let command = || JournalCommand::CancelAllForUser { user_id };
+        self.journaled(command, |engine| {
+            let mut canceled: Vec<Arc<RwLock<Order>>> = engine
+                .order_books
+                .values_mut()
+                .flat_map(|order_book| order_book.cancel_all_for_user(user_id))
+                .collect();
+            canceled.sort_by_key(|order| order.read().id);
+
+            for order in &canceled {
+                engine.finish_cancel(order);
+            }
+            canceled
+        })
+    }
+
+    /// Cancels every resting and stop order on `symbol`.
+    pub fn cancel_all_for_symbol(&mut self, symbol: &str) -> Vec<Arc<RwLock<Order>>> {
+        let command = || JournalCommand::CancelAllForSymbol {
+            symbol: symbol.to_string(),
+        };
+        self.journaled(command, |engine| {
+            let canceled = match engine.order_books.get_mut(symbol) {
+                Some(order_book) => order_book.cancel_all(),
+                None => return Vec::new(),
+            };
+
+            for order in &canceled {
+                engine.finish_cancel(order);
+            }
+            canceled
+        })
+    }
+
+    fn finish_cancel(&mut self, canceled_order: &Arc<RwLock<Order>>) {
+        let mut order_ref = canceled_order.write();
+        self.callbacks.set_status(&mut order_ref, OrderStatus::Canceled);
+        drop(order_ref);
+
+        self.order_metrics.record_order_cancelled();
+        let order = canceled_order.read().clone();
+        self.release_participant_exposure(&order);
+        if self.events.has_subscribers() {
+            self.events.publish(EngineEvent::OrderCancelled(order.clone()));
+            self.publish_indicative_uncross(&order.symbol);
+        }
     }
 
     pub fn process_expired_orders(&mut self) -> Result<Vec<Arc<RwLock<Order>>>, MatchingError> {
-        let current_time = get_nano_timestamp();
+        self.process_expired_orders_at(self.clock.now_nanos())
+    }
+
+    /// Removes every order that has expired by `current_time`. The returned
+    /// orders carry `OrderStatus::Expired` for execution reporting.
+    pub fn process_expired_orders_at(
+        &mut self,
+        current_time: i64,
+    ) -> Result<Vec<Arc<RwLock<Order>>>, MatchingError> {
+        let command = || JournalCommand::ExpireOrders { current_time };
+        self.journaled(command, |engine| engine.expire_orders(current_time))
+    }
+
+    /// Drives GTD and Day expiry from `timers` instead of polling
+    /// `process_expired_orders`. Each distinct expiry time of an accepted
+    /// order arms one timer, and `run_timers` expires the orders due when
+    /// it fires. Resting orders are armed on attaching, so a restored
+    /// engine picks up where the snapshot left off.
+    pub fn attach_timers(&mut self, timers: Timers) {
+        if let Some(previous) = self.timers.take() {
+            for handle in previous.expiries.into_values() {
+                previous.timers.cancel(handle);
+            }
+        }
+        let fired = timers.subscribe(TimerTarget::Engine);
+        self.timers = Some(EngineTimers {
+            timers,
+            fired,
+            expiries: BTreeMap::new(),
+        });
+
+        let session_close = self.config.session_close_ns;
+        let expiries: BTreeSet<i64> = self
+            .order_books
+            .values()
+            .flat_map(|book| book.orders())
+            .filter_map(|order| order.read().expires_at(session_close))
+            .collect();
+        for expiry in expiries {
+            self.arm_expiry(expiry);
+        }
+    }
+
+    fn arm_expiry(&mut self, expiry: i64) {
+        if let Some(EngineTimers { timers, expiries, .. }) = &mut self.timers {
+            expiries
+                .entry(expiry)
+                .or_insert_with(|| timers.schedule(expiry, TimerEvent::ExpireOrders));
+        }
+    }
+
+    /// Expires the orders of every expiry timer delivered since the last
+    /// call: one journaled expiry pass per deadline, in deadline order, so
+    /// a clock jump past several deadlines expires orders as if each had
+    /// fired on time.
+    pub fn run_timers(&mut self) -> Result<Vec<Arc<RwLock<Order>>>, MatchingError> {
+        let Some(engine_timers) = &mut self.timers else {
+            return Ok(Vec::new());
+        };
+        let mut deadlines = Vec::new();
+        for timer in engine_timers.fired.try_iter().flatten() {
+            if timer.event != TimerEvent::ExpireOrders {
+                continue;
+            }
+            if engine_timers.expiries.get(&timer.deadline) == Some(&timer.handle) {
+                engine_timers.expiries.remove(&timer.deadline);
+            }
+            deadlines.push(timer.deadline);
+        }
+
+        let mut expired = Vec::new();
+        for deadline in deadlines {
+            expired.extend(self.process_expired_orders_at(deadline)?);
+        }
+        Ok(expired)
+    }
+
+    fn expire_orders(&mut self, current_time: i64) -> Result<Vec<Arc<RwLock<Order>>>, MatchingError> {
+        let session_close = self.config.session_close_ns;
         let mut expired_orders = Vec::new();
 
         for order_book in self.order_books.values_mut() {
-            let book_expired = order_book.expire_orders(current_time);
+            let book_expired = order_book.expire_orders_at_close(current_time, session_close);
             expired_orders.extend(book_expired);
         }
 
+        for order in &expired_orders {
+            self.order_metrics.record_order_expired();
+            {
+                let order_ref = order.read();
+                let previous = if order_ref.filled_quantity > 0 {
+                    OrderStatus::PartiallyFilled
+                } else {
+                    OrderStatus::New
+                };
+                self.callbacks.status_changed(&order_ref, previous);
+                self.release_participant_exposure(&order_ref);
+            }
+            if self.events.has_subscribers() {
+                let order = order.read().clone();
+                self.events.publish(EngineEvent::OrderExpired(order));
+            }
+        }
+
+        if self.events.has_subscribers() {
+            let symbols: BTreeSet<String> = expired_orders
+                .iter()
+                .map(|order| order.read().symbol.clone())
+                .collect();
+            for symbol in symbols {
+                self.publish_indicative_uncross(&symbol);
+            }
+        }
+
         Ok(expired_orders)
     }
 
@@ -481,6 +3179,7 @@ impl MatchingEngine {
         let order_book = self.order_books.get_mut(&order.read().symbol).unwrap();
         MatchingEngine::match_order(
             &mut self.next_trade_id,
+            &self.callbacks,
             order_book,
             Arc::clone(&order),
             &mut result,
@@ -489,7 +3188,7 @@ impl MatchingEngine {
         {
             let mut order_ref = order.write();
             if !order_ref.is_filled() {
-                order_ref.status = OrderStatus::Canceled;
+                self.callbacks.set_status(&mut order_ref, OrderStatus::Canceled);
             }
         }
 
@@ -498,92 +3197,163 @@ impl MatchingEngine {
         Ok(result)
     }
 
-    #[allow(dead_code)]
-    fn process_stop_market_order(
+    /// Matches the stops triggered so far in `result`, then the stops their
+    /// trades trigger in turn, adding the executions to `result`. Stops still
+    /// pending after `MAX_STOP_CASCADE_DEPTH` rounds go back on the stop book.
+    fn run_triggered_stops(
         &mut self,
-        order: Arc<RwLock<Order>>,
-        trigger_price: u64,
-    ) -> Result<TradeExecutionResult, MatchingError> {
-        let mut result = TradeExecutionResult::new();
-        let order_book = self.order_books.get_mut(&order.read().symbol).unwrap();
+        symbol: &str,
+        result: &mut TradeExecutionResult,
+    ) -> Result<(), MatchingError> {
+        let Some(order_book) = self.order_books.get_mut(symbol) else {
+            return Ok(());
+        };
 
-        {
-            let mut order_ref = order.write();
-            order_ref.order_type = OrderType::Market;
-
-            match order_ref.side {
-                Side::Buy => {
-                    if let Some(price) = order_book.get_best_ask_price() {
-                        order_ref.price = price;
-                    } else {
-                        return Err(MatchingError::NoLiquidity);
-                    }
-                }
-                Side::Sell => {
-                    if let Some(price) = order_book.get_best_bid_price() {
-                        order_ref.price = price;
-                    } else {
-                        return Err(MatchingError::NoLiquidity);
-                    }
-                }
+        let activated_from = result.filled_orders.len();
+        let mut start = 0;
+        for depth in 0.. {
+            let end = result.triggered_stops.len();
+            let round: Vec<Arc<RwLock<Order>>> = result.triggered_stops[start..end]
+                .iter()
+                .filter(|order| order.read().is_stop_order())
+                .cloned()
+                .collect();
+            if round.is_empty() {
+                break;
             }
-        }
 
-        MatchingEngine::match_order(
-            &mut self.next_trade_id,
-            order_book,
-            Arc::clone(&order),
-            &mut result,
-        )?;
+            if depth == MAX_STOP_CASCADE_DEPTH {
+                result.truncate_triggered_stops(start);
+                for order in round {
+                    order_book.add_stop_order(order)?;
+                }
+                break;
+            }
 
-        order_book.update_last_trade_price(trigger_price)?;
+            for order in round {
+                MatchingEngine::activate_stop(
+                    &mut self.next_trade_id,
+                    &self.callbacks,
+                    order_book,
+                    order,
+                    result,
+                )?;
+            }
+            start = end;
+        }
 
-        result.filled_orders.push(Arc::clone(&order));
+        let canceled: Vec<Order> = result.filled_orders[activated_from..]
+            .iter()
+            .map(|o| o.read())
+            .filter(|o| o.status == OrderStatus::Canceled)
+            .map(|o| o.clone())
+            .collect();
+        for order in &canceled {
+            self.release_participant_exposure(order);
+        }
 
-        Ok(result)
+        Ok(())
     }
 
-    #[allow(dead_code)]
-    fn process_triggered_stop_order(
-        &mut self,
+    /// Turns a triggered stop into its market or limit order and matches it.
+    /// A limit remainder rests unless it is IOC or FOK; any other remainder
+    /// is cancelled.
+    fn activate_stop(
+        next_trade_id: &mut u64,
+        callbacks: &EngineCallbacks,
+        order_book: &mut OrderBook,
         order: Arc<RwLock<Order>>,
-        trigger_price: u64,
-    ) -> Result<TradeExecutionResult, MatchingError> {
-        let order_type = order.read().order_type;
-
-        match order_type {
-            OrderType::StopMarket => self.process_stop_market_order(order, trigger_price),
-            OrderType::StopLimit => {
-                let mut result = TradeExecutionResult::new();
-                let order_book = self.order_books.get_mut(&order.read().symbol).unwrap();
-
-                {
-                    let mut order_ref = order.write();
-                    order_ref.order_type = OrderType::Limit;
+        result: &mut TradeExecutionResult,
+    ) -> Result<(), MatchingError> {
+        let time_in_force = {
+            let mut order_ref = order.write();
+            if order_ref.order_type == OrderType::StopMarket {
+                order_ref.order_type = OrderType::Market;
+                let best_price = match order_ref.side {
+                    Side::Buy => order_book.get_best_ask_price(),
+                    Side::Sell => order_book.get_best_bid_price(),
+                };
+                if let Some(price) = best_price {
+                    order_ref.price = price;
                 }
+            } else if order_ref.order_type == OrderType::StopLimit {
+                order_ref.order_type = OrderType::Limit;
+            }
+            order_ref.time_in_force
+        };
 
-                MatchingEngine::match_order(
-                    &mut self.next_trade_id,
-                    order_book,
-                    Arc::clone(&order),
-                    &mut result,
-                )?;
+        if time_in_force != TimeInForce::FOK || MatchingEngine::can_fill_order(order_book, &order) {
+            MatchingEngine::match_order(next_trade_id, callbacks, order_book, Arc::clone(&order), result)?;
+            debug_assert!(
+                time_in_force != TimeInForce::FOK || order.read().is_filled(),
+                "Triggered FOK order {} passed the fill check but did not fill",
+                order.read().id
+            );
+        }
+
+        let mut order_ref = order.write();
+        if order_ref.is_filled() {
+            result.filled_orders.push(Arc::clone(&order));
+        } else if order_ref.order_type == OrderType::Limit
+            && !matches!(time_in_force, TimeInForce::IOC | TimeInForce::FOK)
+        {
+            order_book.debug_assert_rests_uncrossed(order_ref.side, order_ref.price);
+            drop(order_ref);
+            order_book.add_order(Arc::clone(&order))?;
+        } else {
+            callbacks.set_status(&mut order_ref, OrderStatus::Canceled);
+            result.filled_orders.push(Arc::clone(&order));
+        }
 
-                order_book.update_last_trade_price(trigger_price)?;
+        Ok(())
+    }
 
-                if !order.read().is_filled() {
-                    order_book.add_order(Arc::clone(&order))?;
-                    result.remaining_order = Some(Arc::clone(&order));
-                } else {
-                    result.filled_orders.push(Arc::clone(&order));
-                }
+    fn remember_order(&mut self, order: &Arc<RwLock<Order>>) {
+        self.order_history.insert(order.read().id, Arc::clone(order));
+        if self.order_history.len() > self.order_history_trim_at {
+            self.trim_order_history();
+        }
+    }
 
-                Ok(result)
-            }
-            _ => Err(MatchingError::InternalError(
-                "Invalid stop order type".to_string(),
-            )),
+    /// Drops the oldest terminal orders until the history is back at its
+    /// limit, keeping those a trade bust may still need.
+    fn trim_order_history(&mut self) {
+        let limit = self.config.order_history_limit;
+        let bustable: BTreeSet<u64> = self
+            .recent_trades
+            .values()
+            .flatten()
+            .flat_map(|recent| [recent.trade.buy_order_id, recent.trade.sell_order_id])
+            .collect();
+        let mut terminal: Vec<u64> = self
+            .order_history
+            .iter()
+            .filter(|(order_id, order)| {
+                !bustable.contains(*order_id) && order.read().status.is_terminal()
+            })
+            .map(|(order_id, _)| *order_id)
+            .collect();
+        terminal.sort_unstable();
+
+        let excess = self.order_history.len().saturating_sub(limit);
+        for order_id in terminal.into_iter().take(excess) {
+            self.order_history.remove(&order_id);
         }
+        // Live orders may keep the history above the limit; wait for it to
+        // grow again before the next sweep.
+        self.order_history_trim_at = self.order_history.len().max(limit) + limit / 4;
+    }
+
+    pub fn get_order_status(&self, symbol: &str, order_id: u64) -> Option<OrderStatusReport> {
+        let order = self.order_history.get(&order_id)?.read();
+        (order.symbol == symbol).then(|| OrderStatusReport::from(&*order))
+    }
+
+    /// A copy of order `order_id` as it stands, if the engine still
+    /// remembers it.
+    pub fn get_order(&self, symbol: &str, order_id: u64) -> Option<Order> {
+        let order = self.order_history.get(&order_id)?.read();
+        (order.symbol == symbol).then(|| order.clone())
     }
 
     pub fn get_order_metrics(&self) -> OrderMetricsSnapshot {
@@ -594,32 +3364,120 @@ impl MatchingEngine {
         self.latency_metrics.get_metrics()
     }
 
-    pub fn create_snapshot(&self) -> MatchingEngineSnapshot {
+    /// Makes the next accepted order take `order_id`, so an engine replaying
+    /// only some of another engine's commands still assigns the same ids.
+    pub(crate) fn set_next_order_id(&mut self, order_id: u64) {
+        self.next_order_id = order_id;
+    }
+
+    pub fn snapshot(&self) -> EngineSnapshot {
+        self.create_snapshot()
+    }
+
+    pub fn restore(snapshot: EngineSnapshot) -> Self {
+        Self::restore_from_snapshot(&snapshot)
+    }
+
+    pub fn create_snapshot(&self) -> EngineSnapshot {
         let mut order_books = HashMap::new();
 
         for (symbol, book) in &self.order_books {
             order_books.insert(symbol.clone(), book.create_snapshot());
         }
+        order_books.extend(self.hibernated.iter().map(|(symbol, stub)| (symbol.clone(), stub.clone())));
 
-        MatchingEngineSnapshot {
+        EngineSnapshot {
             order_books,
             next_order_id: self.next_order_id,
             next_trade_id: self.next_trade_id,
+            contingent_orders: self
+                .contingent_orders
+                .iter()
+                .map(ContingentOrderSnapshot::from)
+                .collect(),
+            next_contingent_id: self.next_contingent_id,
+            session_trades: self.session_trades.clone(),
+            settlements: self.settlements.clone(),
+            quotes: self.quotes.values().cloned().collect(),
+            hibernated_symbols: self.hibernated.keys().cloned().collect(),
+            journal_sequence: self.journal.as_ref().map_or(0, EngineJournal::last_sequence),
+            recent_trades: self.recent_trades.clone(),
+            bustable_orders: self.bustable_orders(),
+            last_trade_timestamp: self.callbacks.last_trade_timestamp(),
         }
     }
 
-    pub fn restore_from_snapshot(snapshot: &MatchingEngineSnapshot) -> Self {
-        let mut engine = Self::new();
+    /// Orders of bustable trades that no book holds any more, in id order.
+    fn bustable_orders(&self) -> Vec<OrderSnapshot> {
+        let order_ids: BTreeSet<u64> = self
+            .recent_trades
+            .values()
+            .flatten()
+            .flat_map(|recent| [recent.trade.buy_order_id, recent.trade.sell_order_id])
+            .collect();
+        order_ids
+            .into_iter()
+            .filter_map(|order_id| self.order_history.get(&order_id))
+            .map(|order| order.read())
+            .filter(|order| {
+                !self
+                    .order_books
+                    .get(&order.symbol)
+                    .is_some_and(|book| book.contains_order(order.id))
+            })
+            .map(|order| OrderSnapshot::from(&*order))
+            .collect()
+    }
+
+    pub fn restore_from_snapshot(snapshot: &EngineSnapshot) -> Self {
+        Self::restore_with_clock(MatchingEngineConfig::default(), system_clock(), snapshot)
+    }
+
+    pub fn restore_with_clock(
+        config: MatchingEngineConfig,
+        clock: SharedClock,
+        snapshot: &EngineSnapshot,
+    ) -> Self {
+        let mut engine = Self::with_clock(config, clock);
 
         engine.next_order_id = snapshot.next_order_id;
         engine.next_trade_id = snapshot.next_trade_id;
+        engine.next_contingent_id = snapshot.next_contingent_id.max(1);
+        engine.session_trades = snapshot.session_trades.clone();
+        engine.settlements = snapshot.settlements.clone();
+        engine.quotes = snapshot
+            .quotes
+            .iter()
+            .map(|quote| ((quote.user_id, quote.symbol.clone()), quote.clone()))
+            .collect();
+
+        for contingent in &snapshot.contingent_orders {
+            engine.contingent_orders.add(contingent.restore());
+        }
 
         for (symbol, book_snapshot) in &snapshot.order_books {
-            engine.order_books.insert(
-                symbol.clone(),
-                OrderBook::restore_from_snapshot(book_snapshot),
-            );
+            if snapshot.hibernated_symbols.contains(symbol) {
+                engine.hibernated.insert(symbol.clone(), book_snapshot.clone());
+                continue;
+            }
+            let mut order_book = OrderBook::restore_from_snapshot(book_snapshot);
+            order_book.share_open_order_counts(engine.open_orders.clone());
+            for order in order_book.orders() {
+                engine.order_history.insert(order.read().id, Arc::clone(order));
+            }
+            engine.order_books.insert(symbol.clone(), order_book);
+        }
+
+        engine.recent_trades = snapshot.recent_trades.clone();
+        for order in &snapshot.bustable_orders {
+            engine
+                .order_history
+                .entry(order.id)
+                .or_insert_with(|| Arc::new(RwLock::new(order.to_order())));
         }
+        engine
+            .callbacks
+            .resume_trade_timestamps(snapshot.last_trade_timestamp);
 
         engine
     }
@@ -632,18 +3490,75 @@ impl MatchingEngine {
 
     pub fn load_snapshot_from_file(path: &str) -> std::io::Result<Self> {
         let json = std::fs::read_to_string(path)?;
-        let snapshot: MatchingEngineSnapshot = serde_json::from_str(&json)?;
+        let snapshot: EngineSnapshot = serde_json::from_str(&json)?;
         Ok(Self::restore_from_snapshot(&snapshot))
     }
 }
 
-fn get_nano_timestamp() -> i64 {
-    match SystemTime::now().duration_since(UNIX_EPOCH) {
-        Ok(duration) => {
-            let nanos = duration.as_nanos() as i64;
-            (nanos / 1_000_000) * 1_000_000
+/// Splits `incoming_qty` across the resting orders of a level according to
+/// `policy`, returning the non-zero fills in queue order.
+pub(crate) fn allocate_level(
+    policy: MatchPolicy,
+    incoming_qty: u32,
+    orders: &VecDeque<Arc<RwLock<Order>>>,
+) -> Vec<(Arc<RwLock<Order>>, u32)> {
+    let MatchPolicy::ProRata { min_allocation } = policy else {
+        // Time priority only reads as far into the queue as the fill reaches.
+        let mut unallocated = incoming_qty;
+        let mut fills = Vec::new();
+        for order in orders {
+            if unallocated == 0 {
+                break;
+            }
+            let qty = std::cmp::min(unallocated, order.read().visible_quantity());
+            if qty > 0 {
+                fills.push((Arc::clone(order), qty));
+                unallocated -= qty;
+            }
+        }
+        return fills;
+    };
+
+    let available: Vec<u32> = orders.iter().map(|o| o.read().visible_quantity()).collect();
+    let mut allocations = vec![0u32; orders.len()];
+    let mut unallocated = incoming_qty;
+    let total: u64 = available.iter().map(|&qty| qty as u64).sum();
+
+    if total > incoming_qty as u64 {
+        for (allocation, &qty) in allocations.iter_mut().zip(&available) {
+            let share = (incoming_qty as u64 * qty as u64 / total) as u32;
+            if share >= min_allocation {
+                *allocation = share;
+                unallocated -= share;
+            }
+        }
+    }
+
+    for (allocation, &qty) in allocations.iter_mut().zip(&available) {
+        if unallocated == 0 {
+            break;
         }
-        Err(_) => 0,
+        let extra = std::cmp::min(unallocated, qty - *allocation);
+        *allocation += extra;
+        unallocated -= extra;
+    }
+
+    orders
+        .iter()
+        .zip(allocations)
+        .filter(|&(_, qty)| qty > 0)
+        .map(|(order, qty)| (Arc::clone(order), qty))
+        .collect()
+}
+
+/// `bps` either side of `reference_price`, sized off its magnitude so a
+/// band around a price below zero is as wide as one above.
+fn protection_limit(side: Side, reference_price: Price, bps: u32) -> Price {
+    let band = price_magnitude(reference_price).saturating_mul(bps as u64) / 10_000;
+    let band = price_from_u64(band).unwrap_or(Price::MAX);
+    match side {
+        Side::Buy => reference_price.saturating_add(band),
+        Side::Sell => reference_price.saturating_sub(band),
     }
 }
 
@@ -90,12 +90,162 @@ pub struct OrderMetricsSnapshot {
     pub last_update: u64,
 }
 
+/// Bits of sub-bucket resolution. Each power-of-two range is split into
+/// 2^(SUB_BUCKET_BITS - 1) linear buckets, bounding the relative error of a
+/// recorded value to under 1/64.
+const SUB_BUCKET_BITS: u32 = 7;
+const SUB_BUCKET_COUNT: u64 = 1 << SUB_BUCKET_BITS;
+const SUB_BUCKET_HALF: u64 = SUB_BUCKET_COUNT / 2;
+const BUCKET_COUNT: usize =
+    (SUB_BUCKET_COUNT + (64 - SUB_BUCKET_BITS as u64) * SUB_BUCKET_HALF) as usize;
+
+/// Log-linear histogram of nanosecond latencies in the style of
+/// HdrHistogram. Buckets are allocated once up front; recording is a few
+/// relaxed atomic operations and never allocates or locks.
+pub struct LatencyHistogram {
+    buckets: Box<[AtomicU64]>,
+    count: AtomicU64,
+    sum: AtomicU64,
+    min: AtomicU64,
+    max: AtomicU64,
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        Self {
+            buckets: (0..BUCKET_COUNT).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum: AtomicU64::new(0),
+            min: AtomicU64::new(u64::MAX),
+            max: AtomicU64::new(0),
+        }
+    }
+
+    fn bucket_index(value: u64) -> usize {
+        if value < SUB_BUCKET_COUNT {
+            return value as usize;
+        }
+
+        let shift = 63 - value.leading_zeros() - (SUB_BUCKET_BITS - 1);
+        let sub_bucket = (value >> shift) - SUB_BUCKET_HALF;
+        (SUB_BUCKET_COUNT + (shift as u64 - 1) * SUB_BUCKET_HALF + sub_bucket) as usize
+    }
+
+    /// Largest value that maps to the bucket at `index`.
+    fn bucket_high(index: usize) -> u64 {
+        let index = index as u64;
+        if index < SUB_BUCKET_COUNT {
+            return index;
+        }
+
+        let offset = index - SUB_BUCKET_COUNT;
+        let shift = offset / SUB_BUCKET_HALF + 1;
+        let sub_bucket = offset % SUB_BUCKET_HALF + SUB_BUCKET_HALF;
+        (((sub_bucket + 1) as u128) << shift).saturating_sub(1).min(u64::MAX as u128) as u64
+    }
+
+    pub fn record(&self, nanos: u64) {
+        self.buckets[Self::bucket_index(nanos)].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(nanos, Ordering::Relaxed);
+        self.min.fetch_min(nanos, Ordering::Relaxed);
+        self.max.fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    pub fn min(&self) -> u64 {
+        match self.count() {
+            0 => 0,
+            _ => self.min.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn max(&self) -> u64 {
+        self.max.load(Ordering::Relaxed)
+    }
+
+    pub fn mean(&self) -> u64 {
+        self.sum
+            .load(Ordering::Relaxed)
+            .checked_div(self.count())
+            .unwrap_or(0)
+    }
+
+    /// Value at or below which `p` percent of recordings fall, reported as
+    /// the upper bound of its bucket and never above the recorded maximum.
+    pub fn percentile(&self, p: f64) -> u64 {
+        let count = self.count();
+        if count == 0 {
+            return 0;
+        }
+
+        let target = ((p.clamp(0.0, 100.0) / 100.0 * count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+
+        for (index, bucket) in self.buckets.iter().enumerate() {
+            seen += bucket.load(Ordering::Relaxed);
+            if seen >= target {
+                return Self::bucket_high(index).min(self.max());
+            }
+        }
+
+        self.max()
+    }
+
+    /// Clears all recordings, e.g. at the end of a reporting interval.
+    /// Values recorded concurrently with a reset may be partially counted.
+    pub fn reset(&self) {
+        for bucket in self.buckets.iter() {
+            bucket.store(0, Ordering::Relaxed);
+        }
+        self.count.store(0, Ordering::Relaxed);
+        self.sum.store(0, Ordering::Relaxed);
+        self.min.store(u64::MAX, Ordering::Relaxed);
+        self.max.store(0, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> LatencyPercentiles {
+        LatencyPercentiles {
+            count: self.count(),
+            min: self.min(),
+            max: self.max(),
+            mean: self.mean(),
+            p50: self.percentile(50.0),
+            p90: self.percentile(90.0),
+            p99: self.percentile(99.0),
+            p999: self.percentile(99.9),
+        }
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyPercentiles {
+    pub count: u64,
+    pub min: u64,
+    pub max: u64,
+    pub mean: u64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub p999: u64,
+}
+
 #[derive(Default)]
 pub struct LatencyMetrics {
     order_processing_time: AtomicU64, 
     order_processing_count: AtomicU64,
     matching_time: AtomicU64, 
     matching_count: AtomicU64,
+    histogram: LatencyHistogram,
 }
 
 impl LatencyMetrics {
@@ -103,10 +253,43 @@ impl LatencyMetrics {
         Default::default()
     }
 
+    pub fn record(&self, nanos: u64) {
+        self.histogram.record(nanos);
+    }
+
+    pub fn percentile(&self, p: f64) -> u64 {
+        self.histogram.percentile(p)
+    }
+
+    pub fn min(&self) -> u64 {
+        self.histogram.min()
+    }
+
+    pub fn max(&self) -> u64 {
+        self.histogram.max()
+    }
+
+    pub fn mean(&self) -> u64 {
+        self.histogram.mean()
+    }
+
+    pub fn count(&self) -> u64 {
+        self.histogram.count()
+    }
+
+    pub fn reset(&self) {
+        self.histogram.reset();
+    }
+
+    pub fn snapshot(&self) -> LatencyPercentiles {
+        self.histogram.snapshot()
+    }
+
     pub fn record_order_processing_time(&self, duration: Duration) {
         self.order_processing_time
             .fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
         self.order_processing_count.fetch_add(1, Ordering::Relaxed);
+        self.record(duration.as_nanos() as u64);
     }
 
     pub fn record_matching_time(&self, duration: Duration) {
@@ -120,16 +303,16 @@ impl LatencyMetrics {
         let matching_count = self.matching_count.load(Ordering::Relaxed);
 
         LatencyMetricsSnapshot {
-            avg_order_processing_time: if order_processing_count > 0 {
-                self.order_processing_time.load(Ordering::Relaxed) / order_processing_count
-            } else {
-                0
-            },
-            avg_matching_time: if matching_count > 0 {
-                self.matching_time.load(Ordering::Relaxed) / matching_count
-            } else {
-                0
-            },
+            avg_order_processing_time: self
+                .order_processing_time
+                .load(Ordering::Relaxed)
+                .checked_div(order_processing_count)
+                .unwrap_or(0),
+            avg_matching_time: self
+                .matching_time
+                .load(Ordering::Relaxed)
+                .checked_div(matching_count)
+                .unwrap_or(0),
             order_processing_count,
             matching_count,
         }
@@ -6,16 +6,26 @@ use crossbeam::queue::ArrayQueue;
 use crossbeam_utils::CachePadded;
 use parking_lot::{Mutex, RwLock};
 use rayon::ThreadPoolBuilder;
+use thiserror::Error;
 
 use crate::matching_engine::MatchingEngine;
 use crate::order::Order;
+use crate::orderbook::PriceLevel;
+use crate::price_utils::Price;
 
 #[allow(dead_code)]
 const CACHE_LINE_SIZE: usize = 64;
 
+/// Orders each `OrderProcessorPool` worker can hold before submissions to
+/// it are refused.
+pub const DEFAULT_WORKER_QUEUE_CAPACITY: usize = 1024;
+
+/// Recycles order allocations. Released orders go on a free list for
+/// `acquire` to hand out again, up to an optional cap.
 pub struct OrderPool {
     free_list: Mutex<Vec<Arc<RwLock<Order>>>>,
     total_allocated: Mutex<usize>,
+    max_capacity: Option<usize>,
 }
 
 impl OrderPool {
@@ -37,23 +47,23 @@ impl OrderPool {
         Self {
             free_list: Mutex::new(free_list),
             total_allocated: Mutex::new(initial_capacity),
+            max_capacity: None,
         }
     }
 
+    /// Keeps at most `max_capacity` orders on the free list; orders
+    /// released beyond that are dropped.
+    pub fn with_max_capacity(mut self, max_capacity: usize) -> Self {
+        self.free_list.get_mut().truncate(max_capacity);
+        self.max_capacity = Some(max_capacity);
+        self
+    }
+
     pub fn acquire(&self) -> Arc<RwLock<Order>> {
         let mut free_list = self.free_list.lock();
 
         if let Some(order) = free_list.pop() {
-            let mut order_ref = order.write();
-            *order_ref = Order::new(
-                String::new(),
-                crate::order::Side::Buy,
-                crate::order::OrderType::Limit,
-                0,
-                0,
-                0,
-            );
-            drop(order_ref);
+            order.write().reset();
             return order;
         }
 
@@ -69,9 +79,30 @@ impl OrderPool {
         )))
     }
 
-    pub fn release(&self, order: Arc<RwLock<Order>>) {
-        let mut guard = self.free_list.lock();
-        guard.push(order);
+    /// Returns `order` to the pool and reports whether it went on the free
+    /// list. An order something else still holds is dropped instead, so
+    /// `acquire` never hands out an order that is in use, and so is one
+    /// past the cap. Debug builds also scan the free list and panic on an
+    /// order released twice.
+    pub fn release(&self, order: Arc<RwLock<Order>>) -> bool {
+        let mut free_list = self.free_list.lock();
+        debug_assert!(
+            !free_list.iter().any(|free| Arc::ptr_eq(free, &order)),
+            "order released to the pool twice"
+        );
+        if Arc::strong_count(&order) > 1 {
+            return false;
+        }
+        if self.max_capacity.is_some_and(|max| free_list.len() >= max) {
+            return false;
+        }
+        free_list.push(order);
+        true
+    }
+
+    /// Orders waiting on the free list.
+    pub fn free_count(&self) -> usize {
+        self.free_list.lock().len()
     }
 
     pub fn get_total_allocated(&self) -> usize {
@@ -79,11 +110,16 @@ impl OrderPool {
     }
 }
 
-pub struct SPSCQueue {
+/// Bounded multi-producer, single-consumer order queue. Any number of
+/// threads may enqueue concurrently; orders from one producer come out in
+/// the order it pushed them, with no ordering between producers. It stays
+/// correct with several consumers too, since `ArrayQueue` is MPMC, but
+/// each worker pool queue has exactly one.
+pub struct MPSCQueue {
     queue: ArrayQueue<Order>,
 }
 
-impl SPSCQueue {
+impl MPSCQueue {
     pub fn new(capacity: usize) -> Self {
         Self {
             queue: ArrayQueue::new(capacity),
@@ -94,25 +130,39 @@ impl SPSCQueue {
         self.queue.push(order).map_err(|_| "Queue is full")
     }
 
+    /// Like `enqueue`, but hands the order back when the queue is full.
+    pub fn try_enqueue(&self, order: Order) -> Result<(), Box<Order>> {
+        self.queue.push(order).map_err(Box::new)
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
     pub fn dequeue(&self) -> Option<Order> {
         self.queue.pop()
     }
 }
 
 pub struct CacheAlignedPriceLevel {
-    price: CachePadded<u64>,
+    price: CachePadded<Price>,
     total_volume: CachePadded<u64>,
     visible_volume: CachePadded<u64>,
-    orders: Mutex<Vec<Arc<RwLock<Order>>>>,
+    /// Queue with the same indexed removal as a book level.
+    orders: Mutex<PriceLevel>,
 }
 
 impl CacheAlignedPriceLevel {
-    pub fn new(price: u64) -> Self {
+    pub fn new(price: Price) -> Self {
         Self {
             price: CachePadded::new(price),
             total_volume: CachePadded::new(0),
             visible_volume: CachePadded::new(0),
-            orders: Mutex::new(Vec::new()),
+            orders: Mutex::new(PriceLevel::new(price)),
         }
     }
 
@@ -122,14 +172,11 @@ impl CacheAlignedPriceLevel {
         *self.visible_volume += order_ref.visible_quantity() as u64;
         drop(order_ref);
 
-        let mut orders = self.orders.lock();
-        orders.push(order);
+        self.orders.lock().add_order(order);
     }
 
     pub fn remove_order(&mut self, order_id: u64) -> Option<Arc<RwLock<Order>>> {
-        let mut orders = self.orders.lock();
-        let position = orders.iter().position(|o| o.read().id == order_id)?;
-        let order = orders.remove(position);
+        let order = self.orders.lock().remove_order(order_id)?;
 
         let remaining_qty;
         let visible_qty;
@@ -153,28 +200,53 @@ impl CacheAlignedPriceLevel {
         *self.visible_volume
     }
 
-    pub fn get_price(&self) -> u64 {
+    pub fn get_price(&self) -> Price {
         *self.price
     }
 }
 
+#[derive(Error, Debug)]
+pub enum SubmitError {
+    #[error("Worker {worker} queue is full")]
+    QueueFull { worker: usize, order: Box<Order> },
+}
+
+impl SubmitError {
+    /// The order that was not queued, for resubmission.
+    pub fn into_order(self) -> Order {
+        match self {
+            SubmitError::QueueFull { order, .. } => *order,
+        }
+    }
+}
+
 pub struct OrderProcessorPool {
     workers: Vec<Worker>,
     next_worker: std::sync::atomic::AtomicUsize,
 }
 
+/// One consumer thread draining its own queue, which every submitting
+/// thread may push to.
 struct Worker {
-    queue: Arc<SPSCQueue>,
+    queue: Arc<MPSCQueue>,
     thread: Option<thread::JoinHandle<()>>,
     stop: Arc<std::sync::atomic::AtomicBool>,
 }
 
 impl OrderProcessorPool {
     pub fn new(num_workers: usize, engine: Arc<Mutex<MatchingEngine>>) -> Self {
+        Self::with_queue_capacity(num_workers, DEFAULT_WORKER_QUEUE_CAPACITY, engine)
+    }
+
+    pub fn with_queue_capacity(
+        num_workers: usize,
+        queue_capacity: usize,
+        engine: Arc<Mutex<MatchingEngine>>,
+    ) -> Self {
         let mut workers = Vec::with_capacity(num_workers);
 
         for _ in 0..num_workers {
-            let queue = Arc::new(SPSCQueue::new(1024));
+            let queue = Arc::new(MPSCQueue::new(queue_capacity));
             let stop = Arc::new(std::sync::atomic::AtomicBool::new(false));
 
             let worker_queue = Arc::clone(&queue);
@@ -199,7 +271,7 @@ impl OrderProcessorPool {
     }
 
     fn worker_fn(
-        queue: Arc<SPSCQueue>,
+        queue: Arc<MPSCQueue>,
         stop: Arc<std::sync::atomic::AtomicBool>,
         engine: Arc<Mutex<MatchingEngine>>,
     ) {
@@ -215,13 +287,53 @@ impl OrderProcessorPool {
         }
     }
 
-    pub fn submit_order(&self, order: Order) -> Result<(), &'static str> {
-        let worker_idx = self
+    pub fn submit_order(&self, order: Order) -> Result<(), SubmitError> {
+        let worker = self
             .next_worker
             .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
             % self.workers.len();
 
-        self.workers[worker_idx].queue.enqueue(order)
+        self.enqueue(worker, order)
+    }
+
+    /// Queues a burst of orders with one atomic op, dealing them round-robin
+    /// from the next worker on. Results are parallel to `orders`. Once a
+    /// worker's queue is full the rest of its share fails without being
+    /// tried, so a slow worker costs the batch nothing.
+    pub fn submit_batch(&self, orders: Vec<Order>) -> Vec<Result<(), SubmitError>> {
+        let start = self
+            .next_worker
+            .fetch_add(orders.len(), std::sync::atomic::Ordering::Relaxed);
+        let mut full = vec![false; self.workers.len()];
+
+        orders
+            .into_iter()
+            .enumerate()
+            .map(|(i, order)| {
+                let worker = start.wrapping_add(i) % self.workers.len();
+                if full[worker] {
+                    return Err(SubmitError::QueueFull {
+                        worker,
+                        order: Box::new(order),
+                    });
+                }
+                let result = self.enqueue(worker, order);
+                full[worker] = result.is_err();
+                result
+            })
+            .collect()
+    }
+
+    /// Orders waiting in each worker's queue, for callers pacing a burst.
+    pub fn queue_depths(&self) -> Vec<usize> {
+        self.workers.iter().map(|worker| worker.queue.len()).collect()
+    }
+
+    fn enqueue(&self, worker: usize, order: Order) -> Result<(), SubmitError> {
+        self.workers[worker]
+            .queue
+            .try_enqueue(order)
+            .map_err(|order| SubmitError::QueueFull { worker, order })
     }
 }
 
@@ -318,6 +430,46 @@ mod tests {
         assert_eq!(pool.get_total_allocated(), 5);
     }
 
+    #[test]
+    fn test_order_pool_caps_free_list() {
+        let pool = OrderPool::new(4).with_max_capacity(2);
+        assert_eq!(pool.free_count(), 2);
+
+        let orders: Vec<_> = (0..5).map(|_| pool.acquire()).collect();
+        assert_eq!(pool.get_total_allocated(), 7);
+
+        let pooled: Vec<bool> = orders.into_iter().map(|order| pool.release(order)).collect();
+        assert_eq!(pooled, [true, true, false, false, false]);
+        assert_eq!(pool.free_count(), 2);
+    }
+
+    #[test]
+    fn test_order_pool_refuses_orders_in_use() {
+        let pool = OrderPool::new(0);
+        let order = pool.acquire();
+        order.write().symbol = "AAPL".to_string();
+
+        assert!(!pool.release(Arc::clone(&order)));
+        assert_eq!(pool.free_count(), 0);
+
+        assert!(pool.release(order));
+        let reused = pool.acquire();
+        assert!(reused.read().symbol.is_empty());
+        assert_eq!(pool.get_total_allocated(), 1);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "order released to the pool twice")]
+    fn test_order_pool_detects_double_release() {
+        let pool = OrderPool::new(0);
+        let order = pool.acquire();
+        let handle = Arc::downgrade(&order);
+
+        pool.release(order);
+        pool.release(handle.upgrade().unwrap());
+    }
+
     #[test]
     fn test_order_pool_concurrent() {
         let pool = Arc::new(OrderPool::new(100));
@@ -366,8 +518,8 @@ mod tests {
     }
 
     #[test]
-    fn test_spsc_queue() {
-        let queue = SPSCQueue::new(10);
+    fn test_mpsc_queue() {
+        let queue = MPSCQueue::new(10);
 
         for i in 0..5 {
             let order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 10, 1);
@@ -383,8 +535,8 @@ mod tests {
     }
 
     #[test]
-    fn test_spsc_queue_full() {
-        let queue = SPSCQueue::new(2);
+    fn test_mpsc_queue_full() {
+        let queue = MPSCQueue::new(2);
 
         for i in 0..2 {
             let order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 10, i);
@@ -398,8 +550,8 @@ mod tests {
     }
 
     #[test]
-    fn test_spsc_queue_producer_consumer() {
-        let queue = Arc::new(SPSCQueue::new(100));
+    fn test_mpsc_queue_producer_consumer() {
+        let queue = Arc::new(MPSCQueue::new(100));
         let num_orders = 100;
 
         let queue_clone = Arc::clone(&queue);
@@ -429,6 +581,33 @@ mod tests {
         assert_eq!(processed, num_orders);
     }
 
+    #[test]
+    fn test_mpsc_queue_keeps_each_producers_order() {
+        let queue = Arc::new(MPSCQueue::new(1000));
+        let producers: Vec<_> = (0..4u64)
+            .map(|user_id| {
+                let queue = Arc::clone(&queue);
+                thread::spawn(move || {
+                    for quantity in 1..=100 {
+                        let order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, quantity, user_id);
+                        queue.enqueue(order).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for producer in producers {
+            producer.join().unwrap();
+        }
+
+        let mut last_seen = [0u32; 4];
+        while let Some(order) = queue.dequeue() {
+            let last = &mut last_seen[order.user_id as usize];
+            assert_eq!(order.quantity, *last + 1);
+            *last = order.quantity;
+        }
+        assert_eq!(last_seen, [100; 4]);
+    }
+
     #[test]
     fn test_cache_aligned_price_level() {
         let mut level = CacheAlignedPriceLevel::new(100);
@@ -514,7 +693,7 @@ mod tests {
         let processed = Arc::new(AtomicUsize::new(0));
 
         for i in 0..10 {
-            let price = 100 + i;
+            let price = 100 + i as Price;
             let sell_order = Order::new(
                 "AAPL".to_string(),
                 Side::Sell,
@@ -551,7 +730,7 @@ mod tests {
         assert!(all_orders_processed, "Not all sell orders were processed");
 
         for i in 0..10 {
-            let price = 100 + i;
+            let price = 100 + i as Price;
             let buy_order = Order::new(
                 "AAPL".to_string(),
                 Side::Buy,
@@ -599,6 +778,55 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_order_processor_pool_batch_reports_full_queues() {
+        let engine = Arc::new(Mutex::new(MatchingEngine::new()));
+        engine.lock().add_symbol("AAPL");
+        let pool = OrderProcessorPool::with_queue_capacity(2, 2, Arc::clone(&engine));
+
+        // Workers block on the engine lock with at most one order each
+        // taken off their queue, so each accepts two to three.
+        let guard = engine.lock();
+        let orders: Vec<Order> = (0..12)
+            .map(|i| Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 1, i))
+            .collect();
+        let results = pool.submit_batch(orders);
+        assert_eq!(results.len(), 12);
+        assert!(results[..4].iter().all(Result::is_ok));
+
+        for worker in 0..2 {
+            let outcomes: Vec<bool> = results
+                .iter()
+                .skip(worker)
+                .step_by(2)
+                .map(Result::is_ok)
+                .collect();
+            let accepted = outcomes.iter().take_while(|ok| **ok).count();
+            assert!((2..=3).contains(&accepted));
+            assert!(outcomes[accepted..].iter().all(|ok| !ok));
+        }
+
+        let (accepted, rejected): (Vec<_>, Vec<_>) = results.into_iter().partition(Result::is_ok);
+        let rejected: Vec<Order> = rejected
+            .into_iter()
+            .map(|result| result.unwrap_err().into_order())
+            .collect();
+        assert!(rejected.iter().all(|order| order.user_id >= 4));
+        drop(guard);
+
+        for _ in 0..500 {
+            if engine.lock().get_order_metrics().orders_received == accepted.len() as u64 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(
+            engine.lock().get_order_metrics().orders_received,
+            accepted.len() as u64
+        );
+        assert_eq!(pool.queue_depths(), vec![0, 0]);
+    }
+
     #[test]
     fn test_thread_pool() {
         let pool = ThreadPool::new(4).unwrap();
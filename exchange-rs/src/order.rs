@@ -1,7 +1,8 @@
 use serde::{Deserialize, Serialize};
+use crate::price_utils::{notional, Notional, Price};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Side {
     Buy,
     Sell,
@@ -25,6 +26,15 @@ pub enum TimeInForce {
     Day,
 }
 
+/// Reference price a stop order's stop price is compared against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TriggerSource {
+    #[default]
+    LastTrade,
+    MarkPrice,
+    IndexPrice,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OrderStatus {
     New,
@@ -35,13 +45,20 @@ pub enum OrderStatus {
     Expired,
 }
 
+impl OrderStatus {
+    /// Whether the order is done and can no longer trade.
+    pub fn is_terminal(self) -> bool {
+        !matches!(self, OrderStatus::New | OrderStatus::PartiallyFilled)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Order {
     pub id: u64,
     pub symbol: String,
     pub side: Side,
     pub order_type: OrderType,
-    pub price: u64,
+    pub price: Price,
     pub quantity: u32,
     pub filled_quantity: u32,
     pub status: OrderStatus,
@@ -49,8 +66,25 @@ pub struct Order {
     pub user_id: u64,
     pub time_in_force: TimeInForce,
     pub expiration_time: i64,
-    pub stop_price: Option<u64>,
+    pub stop_price: Option<Price>,
+    /// Price the stop triggers off. `None` uses the symbol's default.
+    pub trigger_source: Option<TriggerSource>,
     pub display_quantity: Option<u32>,
+    pub max_slippage_bps: Option<u32>,
+    /// Worst price a market order may trade at; the sweep stops before any
+    /// level beyond it and the remainder is cancelled.
+    pub protection_price: Option<Price>,
+    pub contingent_id: Option<u64>,
+    /// Sum of price * quantity over every fill, for the average fill price.
+    pub fill_notional: Notional,
+    /// Time of the last fill or status change.
+    pub last_update: i64,
+    /// Engine clock reading when the engine accepted the order. `timestamp`
+    /// is the client's creation time.
+    pub received_at: i64,
+    /// The sender's own id for the order, such as a FIX ClOrdID. A user's
+    /// live orders on one symbol never share one.
+    pub client_order_id: Option<String>,
 }
 
 impl Order {
@@ -58,10 +92,11 @@ impl Order {
         symbol: String,
         side: Side,
         order_type: OrderType,
-        price: u64,
+        price: Price,
         quantity: u32,
         user_id: u64,
     ) -> Self {
+        let timestamp = Self::get_nano_timestamp();
         Self {
             id: 0,
             symbol,
@@ -71,25 +106,73 @@ impl Order {
             quantity,
             filled_quantity: 0,
             status: OrderStatus::New,
-            timestamp: Self::get_nano_timestamp(),
+            timestamp,
             user_id,
             time_in_force: TimeInForce::GTC,
             expiration_time: 0,
             stop_price: None,
+            trigger_source: None,
             display_quantity: None,
+            max_slippage_bps: None,
+            protection_price: None,
+            contingent_id: None,
+            fill_notional: 0,
+            last_update: timestamp,
+            received_at: 0,
+            client_order_id: None,
         }
     }
 
+    /// Puts the order back in the state `Order::new` gives an empty limit
+    /// buy, in place. The symbol keeps its allocation for the next use.
+    pub fn reset(&mut self) {
+        let mut symbol = std::mem::take(&mut self.symbol);
+        symbol.clear();
+        *self = Order::new(symbol, Side::Buy, OrderType::Limit, 0, 0, 0);
+    }
+
     pub fn remaining_quantity(&self) -> u32 {
         self.quantity - self.filled_quantity
     }
 
+    /// Quantity left in the current slice. Iceberg slices are cut from the
+    /// order in `display_quantity` steps, so a slice is exhausted whenever the
+    /// filled quantity reaches a multiple of the display size.
     pub fn visible_quantity(&self) -> u32 {
-        if self.order_type == OrderType::Iceberg && self.display_quantity.is_some() {
-            std::cmp::min(self.display_quantity.unwrap(), self.remaining_quantity())
-        } else {
-            self.remaining_quantity()
+        match self.display_quantity {
+            Some(display_qty) if self.order_type == OrderType::Iceberg && display_qty > 0 => {
+                let slice_remaining = display_qty - self.filled_quantity % display_qty;
+                std::cmp::min(slice_remaining, self.remaining_quantity())
+            }
+            _ => self.remaining_quantity(),
+        }
+    }
+
+    /// True once an iceberg has traded through its current slice and a fresh
+    /// one is showing.
+    pub fn has_fresh_slice(&self) -> bool {
+        match self.display_quantity {
+            Some(display_qty) if self.order_type == OrderType::Iceberg && display_qty > 0 => {
+                self.filled_quantity > 0
+                    && self.filled_quantity.is_multiple_of(display_qty)
+                    && !self.is_filled()
+            }
+            _ => false,
+        }
+    }
+
+    pub fn record_fill(&mut self, price: Price, quantity: u32, timestamp: i64) {
+        self.filled_quantity += quantity;
+        self.fill_notional += notional(price, quantity);
+        self.last_update = timestamp;
+    }
+
+    /// Quantity-weighted average of the prices this order has filled at.
+    pub fn average_fill_price(&self) -> Option<Price> {
+        if self.filled_quantity == 0 {
+            return None;
         }
+        Some((self.fill_notional / self.filled_quantity as Notional) as Price)
     }
 
     pub fn is_filled(&self) -> bool {
@@ -100,7 +183,7 @@ impl Order {
         self.order_type == OrderType::StopLimit || self.order_type == OrderType::StopMarket
     }
 
-    pub fn is_stop_triggered(&self, last_price: u64) -> bool {
+    pub fn is_stop_triggered(&self, last_price: Price) -> bool {
         if !self.is_stop_order() || self.stop_price.is_none() {
             return false;
         }
@@ -112,30 +195,102 @@ impl Order {
     }
 
     pub fn is_expired(&self, current_time: i64) -> bool {
+        self.is_expired_at_close(current_time, None)
+    }
+
+    /// Like `is_expired`, but Day orders expire at `session_close` (nanos
+    /// after UTC midnight) instead of at midnight. An order entered after
+    /// the close rolls to the next session.
+    pub fn is_expired_at_close(&self, current_time: i64, session_close: Option<i64>) -> bool {
+        self.expires_at(session_close)
+            .is_some_and(|expiry| current_time >= expiry)
+    }
+
+    /// When the order entered the engine, by the engine's clock. An order
+    /// not yet submitted has no `received_at` and goes by its sender's
+    /// `timestamp`.
+    pub fn entry_time(&self) -> i64 {
+        if self.received_at != 0 {
+            self.received_at
+        } else {
+            self.timestamp
+        }
+    }
+
+    /// When the order expires, for GTD and Day orders. Day orders expire at
+    /// `session_close` as in `is_expired_at_close`, counting from
+    /// `entry_time`.
+    pub fn expires_at(&self, session_close: Option<i64>) -> Option<i64> {
         match self.time_in_force {
-            TimeInForce::GTD => current_time >= self.expiration_time,
+            TimeInForce::GTD => Some(self.expiration_time),
             TimeInForce::Day => {
                 let ns_per_day = 86_400_000_000_000i64;
-                let current_day = current_time / ns_per_day;
-                let order_day = self.timestamp / ns_per_day;
-
-                current_day > order_day
+                let entered = self.entry_time();
+                let order_day = entered / ns_per_day;
+
+                match session_close {
+                    Some(close_offset) => {
+                        let mut close_time = order_day * ns_per_day + close_offset;
+                        if entered >= close_time {
+                            close_time += ns_per_day;
+                        }
+                        Some(close_time)
+                    }
+                    None => Some((order_day + 1) * ns_per_day),
+                }
             }
-            _ => false,
+            _ => None,
         }
     }
 
+    /// Wall-clock time for stamping an order as its sender builds it. The
+    /// engine goes by its injected `Clock` instead: expiry counts from
+    /// `received_at`, and trades are stamped by the clock.
     pub fn get_nano_timestamp() -> i64 {
         match SystemTime::now().duration_since(UNIX_EPOCH) {
-            Ok(duration) => {
-                let nanos = duration.as_nanos() as i64;
-                (nanos / 1_000_000) * 1_000_000
-            }
+            Ok(duration) => duration.as_nanos() as i64,
             Err(_) => 0,
         }
     }
 }
 
+/// Point-in-time view of an order, detached from the book's shared state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderStatusReport {
+    pub order_id: u64,
+    pub symbol: String,
+    pub side: Side,
+    pub status: OrderStatus,
+    pub quantity: u32,
+    pub filled_quantity: u32,
+    /// Quantity still working; zero once the order is done.
+    pub remaining_quantity: u32,
+    pub average_price: Option<Price>,
+    pub last_update: i64,
+}
+
+impl From<&Order> for OrderStatusReport {
+    fn from(order: &Order) -> Self {
+        let remaining_quantity = if order.status.is_terminal() {
+            0
+        } else {
+            order.remaining_quantity()
+        };
+
+        Self {
+            order_id: order.id,
+            symbol: order.symbol.clone(),
+            side: order.side,
+            status: order.status,
+            quantity: order.quantity,
+            filled_quantity: order.filled_quantity,
+            remaining_quantity,
+            average_price: order.average_fill_price(),
+            last_update: order.last_update,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -154,6 +309,42 @@ mod tests {
         assert_eq!(order.remaining_quantity(), 0);
     }
 
+    #[test]
+    fn test_reset() {
+        let mut order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Iceberg, 100, 10, 7);
+        order.id = 42;
+        order.display_quantity = Some(2);
+        order.record_fill(100, 4, 1);
+        order.status = OrderStatus::PartiallyFilled;
+        let symbol_buffer = order.symbol.as_ptr();
+
+        order.reset();
+
+        assert_eq!(order.id, 0);
+        assert!(order.symbol.is_empty());
+        assert_eq!(order.symbol.as_ptr(), symbol_buffer);
+        assert_eq!(order.side, Side::Buy);
+        assert_eq!(order.order_type, OrderType::Limit);
+        assert_eq!((order.price, order.quantity, order.user_id), (0, 0, 0));
+        assert_eq!(order.filled_quantity, 0);
+        assert_eq!(order.fill_notional, 0);
+        assert_eq!(order.status, OrderStatus::New);
+        assert_eq!(order.display_quantity, None);
+        assert_eq!(order.last_update, order.timestamp);
+    }
+
+    #[test]
+    fn test_average_fill_price_weighs_fills_by_quantity() {
+        let mut order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 101_000_000, 10, 1);
+        assert_eq!(order.average_fill_price(), None);
+
+        order.record_fill(100_000_000, 5, 1);
+        assert_eq!(order.average_fill_price(), Some(100_000_000));
+        order.record_fill(101_000_000, 5, 2);
+        assert_eq!(order.fill_notional, 1_005_000_000);
+        assert_eq!(order.average_fill_price(), Some(100_500_000));
+    }
+
     #[test]
     fn test_visible_quantity() {
         let order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 10, 1);
@@ -1,25 +1,57 @@
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+use std::hash::{Hash, Hasher};
+use std::ops::Bound::{Excluded, Unbounded};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
-use crate::order::{Order, OrderStatus, OrderType, Side};
+use crate::digest::StableHasher;
+use crate::events::EngineCallbacks;
+use crate::fees::FeeSchedule;
+use crate::l3_feed::{L3Callback, L3Event, L3Feed};
+use crate::matching_engine::{
+    allocate_level, CancelReason, Fill, MatchStop, MatchingEngine, MatchingError, TradeExecutionResult,
+};
+use crate::order::{Order, OrderStatus, OrderType, Side, TimeInForce, TriggerSource};
+use crate::price_utils::{Notional, Price};
+use crate::risk::OpenOrderCounts;
 use crate::snapshot::OrderBookSnapshot;
-use crate::snapshot::{OrderSnapshot, PriceLevelSnapshot};
+use crate::snapshot::{L3Level, L3OrderEntry, L3Snapshot, OrderSnapshot, PriceLevelSnapshot};
+use crate::top_of_book::{TopOfBook, TopOfBookCell};
+use crossbeam::channel::Receiver;
 use crossbeam_utils::CachePadded;
 use dashmap::DashMap;
-use parking_lot::RwLock;
-
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Orders resting at one price in time priority. Each entry carries a
+/// queue sequence number, ascending from the front, and `positions` maps
+/// order ids to it, so an order is found by binary search rather than a
+/// scan. Change `orders` through the methods to keep the two in step.
 pub struct PriceLevel {
-    price: u64,
-    pub orders: Vec<Arc<RwLock<Order>>>,
+    price: Price,
+    pub orders: VecDeque<Arc<RwLock<Order>>>,
+    sequences: VecDeque<u64>,
+    positions: HashMap<u64, u64>,
+    next_sequence: u64,
+    /// How many resting orders the engine received at each time, so the
+    /// oldest is at hand as orders come and go.
+    received: BTreeMap<i64, u32>,
     pub total_volume: u64,
     pub visible_volume: u64,
 }
 
 impl PriceLevel {
-    pub fn new(price: u64) -> Self {
+    pub fn new(price: Price) -> Self {
         Self {
             price,
-            orders: Vec::new(),
+            orders: VecDeque::new(),
+            sequences: VecDeque::new(),
+            positions: HashMap::new(),
+            next_sequence: 0,
+            received: BTreeMap::new(),
             total_volume: 0,
             visible_volume: 0,
         }
@@ -29,13 +61,83 @@ impl PriceLevel {
         let order_ref = order.read();
         self.total_volume += order_ref.remaining_quantity() as u64;
         self.visible_volume += order_ref.visible_quantity() as u64;
+        let order_id = order_ref.id;
         drop(order_ref);
-        self.orders.push(order);
+        self.push_back(order_id, order);
+    }
+
+    fn push_back(&mut self, order_id: u64, order: Arc<RwLock<Order>>) {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        *self.received.entry(order.read().received_at).or_default() += 1;
+        self.orders.push_back(order);
+        self.sequences.push_back(sequence);
+        self.positions.insert(order_id, sequence);
+    }
+
+    fn forget_received(&mut self, received_at: i64) {
+        if let Some(count) = self.received.get_mut(&received_at) {
+            *count -= 1;
+            if *count == 0 {
+                self.received.remove(&received_at);
+            }
+        }
+    }
+
+    /// Index of `order_id` in the queue.
+    fn index_of(&self, order_id: u64) -> Option<usize> {
+        let sequence = self.positions.get(&order_id)?;
+        self.sequences.binary_search(sequence).ok()
+    }
+
+    fn take(&mut self, order_id: u64, index: usize) -> Arc<RwLock<Order>> {
+        self.positions.remove(&order_id);
+        self.sequences.remove(index);
+        let order = self.orders.remove(index).expect("queue index in range");
+        self.forget_received(order.read().received_at);
+        order
+    }
+
+    fn move_to_back(&mut self, order_id: u64, index: usize) {
+        let order = self.take(order_id, index);
+        self.push_back(order_id, order);
+    }
+
+    pub fn front(&self) -> Option<&Arc<RwLock<Order>>> {
+        self.orders.front()
+    }
+
+    pub fn pop_front(&mut self) -> Option<Arc<RwLock<Order>>> {
+        let order_id = self.orders.front()?.read().id;
+        self.remove_order(order_id)
+    }
+
+    /// Keeps only the orders `keep` accepts, in queue order.
+    pub fn retain_orders(&mut self, mut keep: impl FnMut(&Arc<RwLock<Order>>) -> bool) {
+        let kept: Vec<bool> = self.orders.iter().map(&mut keep).collect();
+        let dropped: Vec<(u64, i64)> = self
+            .orders
+            .iter()
+            .zip(&kept)
+            .filter(|&(_, &kept)| !kept)
+            .map(|(order, _)| {
+                let order = order.read();
+                (order.id, order.received_at)
+            })
+            .collect();
+        for (order_id, received_at) in dropped {
+            self.positions.remove(&order_id);
+            self.forget_received(received_at);
+        }
+        let mut decisions = kept.iter();
+        self.orders.retain(|_| decisions.next() == Some(&true));
+        let mut decisions = kept.iter();
+        self.sequences.retain(|_| decisions.next() == Some(&true));
     }
 
     pub fn remove_order(&mut self, order_id: u64) -> Option<Arc<RwLock<Order>>> {
-        let position = self.orders.iter().position(|o| o.read().id == order_id)?;
-        let order = self.orders.remove(position);
+        let index = self.index_of(order_id)?;
+        let order = self.take(order_id, index);
 
         let remaining_qty;
         let visible_qty;
@@ -52,15 +154,20 @@ impl PriceLevel {
     }
 
     pub fn update_visible_quantity(&mut self) {
+        self.visible_volume = self
+            .orders
+            .iter()
+            .map(|order| order.read().visible_quantity() as u64)
+            .sum();
+    }
+
+    pub fn recalculate_volumes(&mut self) {
+        self.total_volume = 0;
         self.visible_volume = 0;
         for order in &self.orders {
             let order_ref = order.read();
-            if let Some(display_qty) = order_ref.display_quantity {
-                self.visible_volume +=
-                    std::cmp::min(display_qty as u64, order_ref.remaining_quantity() as u64);
-            } else {
-                self.visible_volume += order_ref.remaining_quantity() as u64;
-            }
+            self.total_volume += order_ref.remaining_quantity() as u64;
+            self.visible_volume += order_ref.visible_quantity() as u64;
         }
     }
 
@@ -68,60 +175,153 @@ impl PriceLevel {
         self.visible_volume
     }
 
+    pub fn order_count(&self) -> usize {
+        self.orders.len()
+    }
+
+    /// When the engine received the longest resting order, which need not
+    /// be at the front once icebergs have rotated.
+    pub fn oldest_received_at(&self) -> Option<i64> {
+        self.received.keys().next().copied()
+    }
+
+    pub fn depth_level(&self) -> DepthLevel {
+        DepthLevel {
+            price: self.price,
+            visible: self.visible_volume,
+            total: self.total_volume,
+            orders: self.order_count(),
+            oldest_ts: self.oldest_received_at(),
+        }
+    }
+
+    /// Records a fill of `executed_qty` on one of the level's orders and
+    /// adjusts the level by that order's change alone. An iceberg that shows
+    /// a fresh slice moves to the back of the queue.
     pub fn update_after_trade(
         &mut self,
         order_id: u64,
         executed_qty: u32,
     ) -> Result<(), &'static str> {
-        if let Some(order) = self.orders.iter().find(|o| o.read().id == order_id) {
-            let mut order_ref = order.write();
+        let index = self.index_of(order_id).ok_or("Order not found")?;
+
+        let visible_before = {
+            let mut order_ref = self.orders[index].write();
+            let visible_before = order_ref.visible_quantity();
             order_ref.filled_quantity += executed_qty;
+            visible_before
+        };
+        self.account_fill(order_id, index, executed_qty, visible_before);
 
-            if let Some(display_qty) = order_ref.display_quantity {
-                let remaining = order_ref.remaining_quantity() as u64;
-                self.visible_volume = std::cmp::min(display_qty as u64, remaining);
-            } else {
-                self.visible_volume = self.visible_volume.saturating_sub(executed_qty as u64);
-            }
+        Ok(())
+    }
 
-            Ok(())
-        } else {
-            Err("Order not found")
+    /// Adjusts the level for a fill of `executed_qty` matching has already
+    /// applied to `order_id`, which showed `visible_before` until then. A
+    /// filled order leaves the queue and is returned; an iceberg showing a
+    /// fresh slice moves to the back.
+    pub fn settle_fill(
+        &mut self,
+        order_id: u64,
+        executed_qty: u32,
+        visible_before: u32,
+    ) -> Result<Option<Arc<RwLock<Order>>>, &'static str> {
+        let index = self.index_of(order_id).ok_or("Order not found")?;
+        if self.account_fill(order_id, index, executed_qty, visible_before) {
+            return Ok(None);
+        }
+        if self.orders[index].read().is_filled() {
+            return Ok(Some(self.take(order_id, index)));
+        }
+        Ok(None)
+    }
+
+    /// Moves the level's volumes by one fill and rotates a refreshed
+    /// iceberg. Returns whether it rotated.
+    fn account_fill(&mut self, order_id: u64, index: usize, executed_qty: u32, visible_before: u32) -> bool {
+        let fresh_slice = {
+            let order_ref = self.orders[index].read();
+            self.total_volume = self.total_volume.saturating_sub(executed_qty as u64);
+            self.visible_volume = self.visible_volume.saturating_sub(visible_before as u64)
+                + order_ref.visible_quantity() as u64;
+            order_ref.has_fresh_slice()
+        };
+
+        if fresh_slice {
+            self.move_to_back(order_id, index);
         }
+        fresh_slice
     }
 
+    /// Refreshes the level after an iceberg order traded. Once the order
+    /// shows a fresh slice it loses time priority and moves to the back of
+    /// the queue.
     pub fn replenish_iceberg_order(&mut self, order_id: u64) -> Result<(), &'static str> {
-        if let Some(position) = self.orders.iter().position(|o| o.read().id == order_id) {
-            let order = &self.orders[position];
-            let order_ref = order.read();
+        let index = self
+            .index_of(order_id)
+            .ok_or("Order not found in price level")?;
+
+        let fresh_slice = {
+            let order_ref = self.orders[index].read();
 
             if order_ref.order_type != OrderType::Iceberg {
                 return Err("Not an iceberg order");
             }
+            if order_ref.display_quantity.is_none() {
+                return Err("Missing display quantity");
+            }
 
-            let display_qty = order_ref
-                .display_quantity
-                .ok_or("Missing display quantity")?;
-            let remaining = order_ref.remaining_quantity();
-            let new_visible = std::cmp::min(display_qty, remaining);
-
-            self.visible_volume = new_visible as u64;
+            order_ref.has_fresh_slice()
+        };
 
-            Ok(())
-        } else {
-            Err("Order not found in price level")
+        if fresh_slice {
+            self.move_to_back(order_id, index);
         }
+
+        self.recalculate_volumes();
+
+        Ok(())
     }
 
-    pub fn get_price(&self) -> u64 {
+    pub fn get_price(&self) -> Price {
         self.price
     }
 }
 
+/// Latest value of each price a stop order can trigger off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReferencePrices {
+    pub last_trade: Option<Price>,
+    pub mark: Option<Price>,
+    pub index: Option<Price>,
+}
+
+impl ReferencePrices {
+    pub fn last_trade(price: Price) -> Self {
+        Self {
+            last_trade: Some(price),
+            ..Self::default()
+        }
+    }
+
+    pub fn get(&self, source: TriggerSource) -> Option<Price> {
+        match source {
+            TriggerSource::LastTrade => self.last_trade,
+            TriggerSource::MarkPrice => self.mark,
+            TriggerSource::IndexPrice => self.index,
+        }
+    }
+}
+
 pub struct StopOrderBook {
     symbol: String,
-    buy_stop_orders: HashMap<u64, Vec<Arc<RwLock<Order>>>>,
-    sell_stop_orders: HashMap<u64, Vec<Arc<RwLock<Order>>>>,
+    /// Source for stop orders that do not choose one.
+    default_trigger_source: TriggerSource,
+    /// Stops by stop price, each price's orders in entry order. Ordered
+    /// maps so stops triggered together come out in the same order on
+    /// every replay.
+    buy_stop_orders: BTreeMap<Price, Vec<Arc<RwLock<Order>>>>,
+    sell_stop_orders: BTreeMap<Price, Vec<Arc<RwLock<Order>>>>,
     order_map: HashMap<u64, Arc<RwLock<Order>>>,
 }
 
@@ -129,8 +329,9 @@ impl StopOrderBook {
     pub fn new(symbol: &str) -> Self {
         Self {
             symbol: symbol.to_string(),
-            buy_stop_orders: HashMap::new(),
-            sell_stop_orders: HashMap::new(),
+            default_trigger_source: TriggerSource::LastTrade,
+            buy_stop_orders: BTreeMap::new(),
+            sell_stop_orders: BTreeMap::new(),
             order_map: HashMap::new(),
         }
     }
@@ -200,20 +401,58 @@ impl StopOrderBook {
         None
     }
 
-    pub fn get_triggered_orders(&self, last_price: u64) -> Vec<Arc<RwLock<Order>>> {
+    pub fn len(&self) -> usize {
+        self.order_map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order_map.is_empty()
+    }
+
+    pub fn default_trigger_source(&self) -> TriggerSource {
+        self.default_trigger_source
+    }
+
+    pub fn set_default_trigger_source(&mut self, source: TriggerSource) {
+        self.default_trigger_source = source;
+    }
+
+    /// Stop price of the `side` stops a moving price reaches first: the
+    /// lowest buy stop or the highest sell stop. No stop on that side can
+    /// trigger at a price short of it.
+    pub fn peek_next_trigger(&self, side: Side) -> Option<Price> {
+        match side {
+            Side::Buy => self.buy_stop_orders.keys().next().copied(),
+            Side::Sell => self.sell_stop_orders.keys().next_back().copied(),
+        }
+    }
+
+    /// Stop orders whose stop price has been reached by the price of their
+    /// trigger source. Orders whose source has no price yet never trigger.
+    /// Buy stops come first, lowest stop price first, then sell stops from
+    /// the highest down: the order a moving price would have reached them.
+    /// Stops at one price come out in the order they were added.
+    pub fn get_triggered_orders(&self, prices: &ReferencePrices) -> Vec<Arc<RwLock<Order>>> {
         let mut triggered = Vec::new();
+        let known = [prices.last_trade, prices.mark, prices.index];
+        let (Some(highest), Some(lowest)) = (
+            known.iter().flatten().max().copied(),
+            known.iter().flatten().min().copied(),
+        ) else {
+            return triggered;
+        };
 
-        for (&stop_price, orders) in &self.buy_stop_orders {
-            if last_price >= stop_price {
-                for order in orders {
+        for (&stop_price, orders) in self.buy_stop_orders.range(..=highest) {
+            for order in orders {
+                if self.reference_price(order, prices).is_some_and(|price| price >= stop_price) {
                     triggered.push(Arc::clone(order));
                 }
             }
         }
 
-        for (&stop_price, orders) in &self.sell_stop_orders {
-            if last_price <= stop_price {
-                for order in orders {
+        for (&stop_price, orders) in self.sell_stop_orders.range(lowest..).rev() {
+            for order in orders {
+                if self.reference_price(order, prices).is_some_and(|price| price <= stop_price) {
                     triggered.push(Arc::clone(order));
                 }
             }
@@ -222,6 +461,11 @@ impl StopOrderBook {
         triggered
     }
 
+    fn reference_price(&self, order: &Arc<RwLock<Order>>, prices: &ReferencePrices) -> Option<Price> {
+        let source = order.read().trigger_source.unwrap_or(self.default_trigger_source);
+        prices.get(source)
+    }
+
     pub fn remove_triggered_orders(&mut self, triggered_orders: &[Arc<RwLock<Order>>]) {
         for order in triggered_orders {
             let order_id = order.read().id;
@@ -230,34 +474,509 @@ impl StopOrderBook {
     }
 }
 
-#[derive(Default, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MatchPolicy {
+    #[default]
+    Fifo,
+    /// Split the incoming quantity across a level in proportion to resting
+    /// size. Shares below `min_allocation` are dropped, and whatever is left
+    /// after rounding is handed out in time priority.
+    ProRata { min_allocation: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TradingState {
+    #[default]
+    Continuous,
+    Auction,
+    Halted,
+}
+
+/// Fat-finger protection for a symbol. Bands are expressed in basis points
+/// and are only enforced when set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct PriceBands {
+    /// Fixed reference for the static band, typically the previous close.
+    pub reference_price: Option<Price>,
+    pub static_band_bps: Option<u32>,
+    /// Band around the last trade price.
+    pub dynamic_band_bps: Option<u32>,
+    /// Largest move of the last trade price a single order may cause before
+    /// the symbol is halted.
+    pub circuit_breaker_bps: Option<u32>,
+}
+
+/// Price and quantity granularity of a symbol. Prices must be multiples of
+/// `tick_size`, quantities multiples of `lot_size` and at least `min_qty`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SymbolSpec {
+    pub tick_size: Price,
+    pub lot_size: u32,
+    pub min_qty: u32,
+}
+
+impl Default for SymbolSpec {
+    fn default() -> Self {
+        Self {
+            tick_size: 1,
+            lot_size: 1,
+            min_qty: 1,
+        }
+    }
+}
+
+/// Order types and time-in-force values a symbol accepts. An empty allow
+/// list accepts everything not denied; a deny entry always wins.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct OrderTypeRules {
+    pub allowed_order_types: Vec<OrderType>,
+    pub denied_order_types: Vec<OrderType>,
+    pub allowed_time_in_force: Vec<TimeInForce>,
+    pub denied_time_in_force: Vec<TimeInForce>,
+    /// Order types refused only while a window is active.
+    pub restrictions: Vec<OrderTypeRestriction>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderTypeRestriction {
+    pub order_type: OrderType,
+    pub window: RestrictionWindow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RestrictionWindow {
+    /// While the symbol is in this trading state.
+    TradingState(TradingState),
+    /// From `start` up to, but not including, `end`, in engine clock
+    /// nanoseconds.
+    Between { start: i64, end: i64 },
+}
+
+impl RestrictionWindow {
+    pub fn is_active(&self, state: TradingState, now: i64) -> bool {
+        match *self {
+            RestrictionWindow::TradingState(window_state) => state == window_state,
+            RestrictionWindow::Between { start, end } => start <= now && now < end,
+        }
+    }
+}
+
+impl OrderTypeRules {
+    pub fn permits_order_type(&self, order_type: OrderType, state: TradingState, now: i64) -> bool {
+        (self.allowed_order_types.is_empty() || self.allowed_order_types.contains(&order_type))
+            && !self.denied_order_types.contains(&order_type)
+            && !self
+                .restrictions
+                .iter()
+                .any(|r| r.order_type == order_type && r.window.is_active(state, now))
+    }
+
+    pub fn permits_time_in_force(&self, time_in_force: TimeInForce) -> bool {
+        (self.allowed_time_in_force.is_empty() || self.allowed_time_in_force.contains(&time_in_force))
+            && !self.denied_time_in_force.contains(&time_in_force)
+    }
+}
+
+/// Outcome of uncrossing the auction book now: the clearing price, the
+/// volume paired at it and the quantity left over on the heavier side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndicativeUncross {
+    pub price: Price,
+    pub volume: u64,
+    pub imbalance: u64,
+    /// Side with the surplus, `None` when the paired volume clears both.
+    pub imbalance_side: Option<Side>,
+}
+
+/// Microstructure features of the displayed book. Prices are in ticks and
+/// volumes are visible quantity; hidden iceberg quantity is not counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BookFeatures {
+    /// `(bid_volume - ask_volume) * 10_000 / (bid_volume + ask_volume)` over
+    /// the top levels, truncated toward zero. `None` when both sides are
+    /// empty; a one-sided book gives +/-10_000.
+    pub imbalance_bps: Option<i64>,
+    /// `(bid * ask_size + ask * bid_size) / (bid_size + ask_size)` at the
+    /// touch, rounded half up. `None` unless both sides are quoted.
+    pub microprice: Option<Price>,
+    /// Visible volume on each side priced within the tick window of the
+    /// mid. `None` unless both sides are quoted.
+    pub bid_depth_near_mid: Option<u64>,
+    pub ask_depth_near_mid: Option<u64>,
+}
+
+/// Which resting quantity liquidity queries count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VolumeView {
+    /// What the market sees: only the showing slice of an iceberg.
+    #[default]
+    Visible,
+    /// Everything resting, hidden iceberg quantity included, which is what
+    /// a sweep actually fills against. For internal risk use; it reveals
+    /// size the owners chose to hide.
+    Total,
+}
+
+impl VolumeView {
+    fn volume(self, level: &PriceLevel) -> u64 {
+        match self {
+            VolumeView::Visible => level.visible_volume,
+            VolumeView::Total => level.total_volume,
+        }
+    }
+}
+
+/// One price level of a detailed depth view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepthLevel {
+    pub price: Price,
+    pub visible: u64,
+    pub total: u64,
+    /// Orders queued at the price.
+    pub orders: usize,
+    /// `received_at` of the longest resting order.
+    pub oldest_ts: Option<i64>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct MarketDepth {
-    pub bid_levels: Vec<(u64, u64)>, 
-    pub ask_levels: Vec<(u64, u64)>, 
+    pub bid_levels: Vec<(Price, u64)>, 
+    pub ask_levels: Vec<(Price, u64)>, 
+    /// `depth_checksum` of the levels above, so a consumer can check that
+    /// the book it maintains still matches.
+    pub checksum: u32,
+    /// The same levels in detail, filled only by `detailed_market_depth`
+    /// and left empty otherwise.
+    pub bid_details: Vec<DepthLevel>,
+    pub ask_details: Vec<DepthLevel>,
+}
+
+impl MarketDepth {
+    pub fn new(bid_levels: Vec<(Price, u64)>, ask_levels: Vec<(Price, u64)>) -> Self {
+        let checksum = depth_checksum(bid_levels.iter().copied(), ask_levels.iter().copied());
+        Self {
+            bid_levels,
+            ask_levels,
+            checksum,
+            bid_details: Vec::new(),
+            ask_details: Vec::new(),
+        }
+    }
+
+    /// The level updates that take a consumer holding `previous` to this
+    /// depth. Levels are compared within each view's window, so one pushed
+    /// out of it is deleted and one that comes back is new again.
+    pub fn diff(&self, previous: &MarketDepth) -> DepthDelta {
+        DepthDelta {
+            bid_updates: side_updates(&previous.bid_levels, &self.bid_levels),
+            ask_updates: side_updates(&previous.ask_levels, &self.ask_levels),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthAction {
+    New,
+    Change,
+    Delete,
+}
+
+/// One level's change between two depth views. A delete carries no
+/// quantity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepthUpdate {
+    pub action: DepthAction,
+    pub price: Price,
+    pub quantity: u64,
+}
+
+/// What changed between two `MarketDepth`s, from `MarketDepth::diff`. Each
+/// side lists its deletes first, then new and changed levels best first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DepthDelta {
+    pub bid_updates: Vec<DepthUpdate>,
+    pub ask_updates: Vec<DepthUpdate>,
+}
+
+impl DepthDelta {
+    pub fn is_empty(&self) -> bool {
+        self.bid_updates.is_empty() && self.ask_updates.is_empty()
+    }
+
+    /// The depth `previous` becomes with these updates applied, with its
+    /// checksum recomputed. Detailed levels are not carried over.
+    pub fn apply(&self, previous: &MarketDepth) -> MarketDepth {
+        let apply = |levels: &[(Price, u64)], updates: &[DepthUpdate]| {
+            let mut levels: BTreeMap<Price, u64> = levels.iter().copied().collect();
+            for update in updates {
+                match update.action {
+                    DepthAction::Delete => levels.remove(&update.price),
+                    DepthAction::New | DepthAction::Change => {
+                        levels.insert(update.price, update.quantity)
+                    }
+                };
+            }
+            levels
+        };
+        MarketDepth::new(
+            apply(&previous.bid_levels, &self.bid_updates).into_iter().rev().collect(),
+            apply(&previous.ask_levels, &self.ask_updates).into_iter().collect(),
+        )
+    }
+}
+
+fn side_updates(previous: &[(Price, u64)], current: &[(Price, u64)]) -> Vec<DepthUpdate> {
+    let before: HashMap<Price, u64> = previous.iter().copied().collect();
+    let after: HashSet<Price> = current.iter().map(|&(price, _)| price).collect();
+
+    let mut updates: Vec<DepthUpdate> = previous
+        .iter()
+        .filter(|(price, _)| !after.contains(price))
+        .map(|&(price, _)| DepthUpdate {
+            action: DepthAction::Delete,
+            price,
+            quantity: 0,
+        })
+        .collect();
+    for &(price, quantity) in current {
+        let action = match before.get(&price) {
+            None => DepthAction::New,
+            Some(&previous) if previous != quantity => DepthAction::Change,
+            Some(_) => continue,
+        };
+        updates.push(DepthUpdate {
+            action,
+            price,
+            quantity,
+        });
+    }
+    updates
+}
+
+/// CRC32 (IEEE) over the given levels, each side best price first, in the
+/// form Kraken checksums its books: the asks and then the bids, each level
+/// written as its price followed by its quantity. Both are the decimal
+/// digits of the scaled integer, with no separators, decimal point or
+/// leading zeros. Ask 100.5 (100500000) for 3 then bid 100.25 for 12 hash
+/// the ASCII string `100500000310025000012`.
+pub fn depth_checksum(
+    bid_levels: impl Iterator<Item = (Price, u64)>,
+    ask_levels: impl Iterator<Item = (Price, u64)>,
+) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    let mut digits = String::new();
+    for (price, quantity) in ask_levels.chain(bid_levels) {
+        digits.clear();
+        let _ = write!(digits, "{price}{quantity}");
+        hasher.update(digits.as_bytes());
+    }
+    hasher.finalize()
+}
+
+/// Resting orders that can expire, soonest first, so an expiry scan stops
+/// at the first order still live. GTD orders are keyed by their expiration
+/// time. Day orders are keyed by entry time: their deadline depends on the
+/// session close passed to the scan, but for any close it never decreases
+/// as entry time grows.
+///
+/// Orders that leave the book for other reasons keep their entries, which
+/// the scan drops when it reaches them and `compact` drops once they
+/// outnumber the live orders.
+#[derive(Default)]
+struct ExpiryIndex {
+    gtd: BinaryHeap<Reverse<(i64, u64)>>,
+    day: BinaryHeap<Reverse<(i64, u64)>>,
+}
+
+impl ExpiryIndex {
+    fn key(order: &Order) -> Option<(bool, i64)> {
+        match order.time_in_force {
+            TimeInForce::GTD => Some((true, order.expiration_time)),
+            TimeInForce::Day => Some((false, order.entry_time())),
+            _ => None,
+        }
+    }
+
+    fn heap(&mut self, gtd: bool) -> &mut BinaryHeap<Reverse<(i64, u64)>> {
+        if gtd {
+            &mut self.gtd
+        } else {
+            &mut self.day
+        }
+    }
+
+    fn insert(&mut self, order: &Order) {
+        if let Some((gtd, key)) = Self::key(order) {
+            self.heap(gtd).push(Reverse((key, order.id)));
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.gtd.len() + self.day.len()
+    }
+
+    /// Pops the entries of one heap up to the first live order that has
+    /// not expired, returning the ids of those that have. Entries whose
+    /// order is no longer resting, or rests under a different key, are
+    /// dropped.
+    fn pop_expired(
+        &mut self,
+        gtd: bool,
+        order_map: &HashMap<u64, Arc<RwLock<Order>>>,
+        current_time: i64,
+        session_close: Option<i64>,
+    ) -> Vec<u64> {
+        let heap = self.heap(gtd);
+        let mut expired = Vec::new();
+        while let Some(&Reverse((key, order_id))) = heap.peek() {
+            let live = order_map.get(&order_id).map(|order| order.read());
+            match live {
+                Some(order) if Self::key(&order) == Some((gtd, key)) => {
+                    if !order.is_expired_at_close(current_time, session_close) {
+                        break;
+                    }
+                    expired.push(order_id);
+                }
+                _ => {}
+            }
+            heap.pop();
+        }
+        expired
+    }
+
+    /// Drops the entries of orders no longer resting.
+    fn compact(&mut self, order_map: &HashMap<u64, Arc<RwLock<Order>>>) {
+        let live = |entry: &Reverse<(i64, u64)>| order_map.contains_key(&entry.0 .1);
+        self.gtd.retain(live);
+        self.day.retain(live);
+    }
+}
+
+/// A way the book's own structures disagree, found by
+/// `OrderBook::validate`.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum BookInvariantError {
+    #[error("{symbol} book crossed: bid {bid_price} (order {bid_order_id:?}) is not below ask {ask_price} (order {ask_order_id:?})")]
+    Crossed {
+        symbol: String,
+        bid_price: Price,
+        bid_order_id: Option<u64>,
+        ask_price: Price,
+        ask_order_id: Option<u64>,
+    },
+    #[error("{symbol} {side:?} level {price} records volume {recorded_total} ({recorded_visible} visible) but its orders hold {total} ({visible} visible)")]
+    LevelVolume {
+        symbol: String,
+        side: Side,
+        price: Price,
+        recorded_total: u64,
+        recorded_visible: u64,
+        total: u64,
+        visible: u64,
+    },
+    #[error("{symbol} {side:?} level {price} is empty")]
+    EmptyLevel { symbol: String, side: Side, price: Price },
+    /// An order queued on a level is not indexed by id, or is indexed as
+    /// a different side or price than the level it sits on.
+    #[error("{symbol} order {order_id} queued on the {side:?} level {price} is not indexed there")]
+    UnindexedOrder {
+        symbol: String,
+        order_id: u64,
+        side: Side,
+        price: Price,
+    },
+    /// An indexed order is missing from the level its side and price
+    /// name.
+    #[error("{symbol} order {order_id} is indexed at {price} on the {side:?} side but not queued there")]
+    UnqueuedOrder {
+        symbol: String,
+        order_id: u64,
+        side: Side,
+        price: Price,
+    },
+    #[error("{symbol} levels queue {queued} orders but {indexed} are indexed")]
+    OrderCount {
+        symbol: String,
+        queued: usize,
+        indexed: usize,
+    },
+    #[error("{symbol} stop book holds order {order_id}, a {order_type:?} order")]
+    NotAStopOrder {
+        symbol: String,
+        order_id: u64,
+        order_type: OrderType,
+    },
+}
+
+/// The last checksum handed out, valid while the depth generation holds.
+#[derive(Clone, Copy)]
+struct CachedChecksum {
+    generation: u64,
+    levels: usize,
+    checksum: u32,
 }
 
 pub struct OrderBook {
     symbol: String,
-    pub buy_levels: HashMap<u64, PriceLevel>,
-    pub sell_levels: HashMap<u64, PriceLevel>,
+    pub buy_levels: BTreeMap<Price, PriceLevel>,
+    pub sell_levels: BTreeMap<Price, PriceLevel>,
     order_map: HashMap<u64, Arc<RwLock<Order>>>,
+    expiries: ExpiryIndex,
     stop_order_book: StopOrderBook,
-    pub last_trade_price: Option<u64>,
+    /// Ids of every resting and stop order, keyed by user.
+    user_orders: HashMap<u64, HashSet<u64>>,
+    /// Ids of the resting and stop orders that carry a client order id,
+    /// by user and client order id.
+    client_orders: HashMap<u64, HashMap<String, u64>>,
+    open_orders: OpenOrderCounts,
+    pub last_trade_price: Option<Price>,
+    pub mark_price: Option<Price>,
+    pub index_price: Option<Price>,
     depth: RwLock<MarketDepth>,
     depth_levels: usize, 
+    depth_generation: u64,
+    checksum: Mutex<Option<CachedChecksum>>,
+    /// Best bid and offer, republished on every change for lock-free
+    /// readers.
+    top_of_book: Arc<TopOfBookCell>,
+    /// Order-by-order changes to the resting orders. Matching publishes
+    /// the executions itself, while it holds a level borrowed.
+    pub(crate) l3_feed: L3Feed,
+    match_policy: MatchPolicy,
+    trading_state: TradingState,
+    price_bands: PriceBands,
+    order_type_rules: OrderTypeRules,
+    fee_schedule: FeeSchedule,
+    spec: SymbolSpec,
 }
 
 impl OrderBook {
     pub fn new(symbol: &str) -> Self {
         Self {
             symbol: symbol.to_string(),
-            buy_levels: HashMap::new(),
-            sell_levels: HashMap::new(),
+            buy_levels: BTreeMap::new(),
+            sell_levels: BTreeMap::new(),
             order_map: HashMap::new(),
+            expiries: ExpiryIndex::default(),
             stop_order_book: StopOrderBook::new(symbol),
+            user_orders: HashMap::new(),
+            client_orders: HashMap::new(),
+            open_orders: OpenOrderCounts::new(),
             last_trade_price: None,
+            mark_price: None,
+            index_price: None,
             depth: RwLock::new(MarketDepth::default()),
             depth_levels: 10, 
+            depth_generation: 0,
+            checksum: Mutex::new(None),
+            top_of_book: Arc::new(TopOfBookCell::new()),
+            l3_feed: L3Feed::new(),
+            match_policy: MatchPolicy::Fifo,
+            trading_state: TradingState::Continuous,
+            price_bands: PriceBands::default(),
+            order_type_rules: OrderTypeRules::default(),
+            fee_schedule: FeeSchedule::default(),
+            spec: SymbolSpec::default(),
         }
     }
 
@@ -266,6 +985,8 @@ impl OrderBook {
         let order_id = order_ref.id;
         let price = order_ref.price;
         let side = order_ref.side;
+        self.expiries.insert(&order_ref);
+        self.index_order(&order_ref);
 
         drop(order_ref);
 
@@ -280,8 +1001,9 @@ impl OrderBook {
             .entry(price)
             .or_insert_with(|| PriceLevel::new(price));
         level.add_order(Arc::clone(&order));
+        self.l3_feed.add(&order.read());
 
-        self.update_depth();
+        self.update_depth_level(side, price);
 
         Ok(())
     }
@@ -295,7 +1017,100 @@ impl OrderBook {
 
         drop(order_ref);
 
-        self.stop_order_book.add_stop_order(order)
+        self.stop_order_book.add_stop_order(Arc::clone(&order))?;
+        self.index_order(&order.read());
+        Ok(())
+    }
+
+    /// Makes the book count its open orders into `counts`, shared with the
+    /// other books of an engine.
+    pub fn share_open_order_counts(&mut self, counts: OpenOrderCounts) {
+        for (user_id, ids) in &self.user_orders {
+            counts.add(*user_id, ids.len());
+        }
+        self.open_orders = counts;
+    }
+
+    fn index_order(&mut self, order: &Order) {
+        if self.user_orders.entry(order.user_id).or_default().insert(order.id) {
+            self.open_orders.add(order.user_id, 1);
+        }
+        if let Some(client_order_id) = &order.client_order_id {
+            self.client_orders
+                .entry(order.user_id)
+                .or_default()
+                .insert(client_order_id.clone(), order.id);
+        }
+    }
+
+    fn unindex_order(&mut self, order: &Arc<RwLock<Order>>) {
+        Self::unindex(&mut self.user_orders, &mut self.client_orders, &self.open_orders, &order.read());
+    }
+
+    fn unindex(
+        user_orders: &mut HashMap<u64, HashSet<u64>>,
+        client_orders: &mut HashMap<u64, HashMap<String, u64>>,
+        open_orders: &OpenOrderCounts,
+        order: &Order,
+    ) {
+        if let Some(ids) = user_orders.get_mut(&order.user_id) {
+            if ids.remove(&order.id) {
+                open_orders.remove(order.user_id);
+            }
+            if ids.is_empty() {
+                user_orders.remove(&order.user_id);
+            }
+        }
+        if let (Some(client_order_id), Some(ids)) =
+            (&order.client_order_id, client_orders.get_mut(&order.user_id))
+        {
+            if ids.get(client_order_id) == Some(&order.id) {
+                ids.remove(client_order_id);
+            }
+            if ids.is_empty() {
+                client_orders.remove(&order.user_id);
+            }
+        }
+    }
+
+    /// The resting or stop order `user_id` placed with `client_order_id`.
+    pub fn get_order_by_client_id(&self, user_id: u64, client_order_id: &str) -> Option<Arc<RwLock<Order>>> {
+        let order_id = self.client_orders.get(&user_id)?.get(client_order_id)?;
+        self.get_order(*order_id)
+    }
+
+    pub fn cancel_order_by_client_id(&mut self, user_id: u64, client_order_id: &str) -> Option<Arc<RwLock<Order>>> {
+        let order_id = *self.client_orders.get(&user_id)?.get(client_order_id)?;
+        self.cancel_order(order_id)
+    }
+
+    /// Ids of the resting and stop orders `user_id` has on this book.
+    pub fn user_order_ids(&self, user_id: u64) -> Vec<u64> {
+        let mut ids: Vec<u64> = self
+            .user_orders
+            .get(&user_id)
+            .map(|ids| ids.iter().copied().collect())
+            .unwrap_or_default();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Removes every resting and stop order of `user_id`, in id order.
+    pub fn cancel_all_for_user(&mut self, user_id: u64) -> Vec<Arc<RwLock<Order>>> {
+        self.user_order_ids(user_id)
+            .into_iter()
+            .filter_map(|order_id| self.remove_order(order_id))
+            .collect()
+    }
+
+    /// Removes every resting and stop order on the book, in id order.
+    pub fn cancel_all(&mut self) -> Vec<Arc<RwLock<Order>>> {
+        let mut ids: Vec<u64> = self.user_orders.values().flatten().copied().collect();
+        ids.sort_unstable();
+
+        ids.into_iter()
+            .filter_map(|order_id| self.remove_order(order_id))
+            .collect()
     }
 
     pub fn remove_order(&mut self, order_id: u64) -> Option<Arc<RwLock<Order>>> {
@@ -314,83 +1129,151 @@ impl OrderBook {
             if let Some(level) = levels.get_mut(&price) {
                 if let Some(removed_order) = level.remove_order(order_id) {
                     self.order_map.remove(&order_id);
+                    self.l3_feed.cancel(&removed_order.read());
 
                     if level.orders.is_empty() {
                         levels.remove(&price);
                     }
 
-                    self.update_depth();
+                    self.update_depth_level(side, price);
+                    self.unindex_order(&removed_order);
 
                     return Some(removed_order);
                 }
             }
         }
 
-        self.stop_order_book.remove_stop_order(order_id)
+        let removed_order = self.stop_order_book.remove_stop_order(order_id)?;
+        self.unindex_order(&removed_order);
+        Some(removed_order)
     }
 
     pub fn cancel_order(&mut self, order_id: u64) -> Option<Arc<RwLock<Order>>> {
-        let result = self.remove_order(order_id);
-
-        self.update_depth();
+        self.remove_order(order_id)
+    }
 
-        result
+    /// Recomputes the volumes of the level `order_id` rests on after its
+    /// quantity changed outside matching. Returns false if it is not resting.
+    pub fn refresh_order_level(&mut self, order_id: u64) -> bool {
+        let Some(order) = self.order_map.get(&order_id) else {
+            return false;
+        };
+        let (side, price) = {
+            let order_ref = order.read();
+            (order_ref.side, order_ref.price)
+        };
+        let levels = match side {
+            Side::Buy => &mut self.buy_levels,
+            Side::Sell => &mut self.sell_levels,
+        };
+        let Some(level) = levels.get_mut(&price) else {
+            return false;
+        };
+        level.recalculate_volumes();
+        self.l3_feed.replace(&order.read(), true);
+        self.update_depth_level(side, price);
+        true
     }
 
-    pub fn get_best_bid_price(&self) -> Option<u64> {
-        self.buy_levels.keys().max().copied()
+    pub fn get_best_bid_price(&self) -> Option<Price> {
+        self.buy_levels.keys().next_back().copied()
     }
 
-    pub fn get_best_ask_price(&self) -> Option<u64> {
-        self.sell_levels.keys().min().copied()
+    pub fn get_best_ask_price(&self) -> Option<Price> {
+        self.sell_levels.keys().next().copied()
     }
 
-    pub fn update_last_trade_price(&mut self, price: u64) -> Result<(), &'static str> {
+    /// Records a trade price and takes the stop orders it triggers off the
+    /// stop book, returning them in trigger order. They are not booked; the
+    /// engine matches them.
+    pub fn update_last_trade_price(
+        &mut self,
+        price: Price,
+    ) -> Result<Vec<Arc<RwLock<Order>>>, &'static str> {
         self.last_trade_price = Some(price);
+        Ok(self.take_triggered_stops())
+    }
 
-        let triggered_orders = self.stop_order_book.get_triggered_orders(price);
+    /// Records a mark price and takes the stop orders it triggers off the
+    /// stop book, like `update_last_trade_price`.
+    pub fn update_mark_price(&mut self, price: Price) -> Vec<Arc<RwLock<Order>>> {
+        self.mark_price = Some(price);
+        self.take_triggered_stops()
+    }
 
-        if !triggered_orders.is_empty() {
-            self.stop_order_book
-                .remove_triggered_orders(&triggered_orders);
+    pub fn update_index_price(&mut self, price: Price) -> Vec<Arc<RwLock<Order>>> {
+        self.index_price = Some(price);
+        self.take_triggered_stops()
+    }
 
-            for order in triggered_orders {
-                let mut order_ref = order.write();
+    pub fn reference_prices(&self) -> ReferencePrices {
+        ReferencePrices {
+            last_trade: self.last_trade_price,
+            mark: self.mark_price,
+            index: self.index_price,
+        }
+    }
 
-                if order_ref.order_type == OrderType::StopMarket {
-                    order_ref.order_type = OrderType::Market;
-                    order_ref.price = match order_ref.side {
-                        Side::Buy => self.get_best_ask_price().unwrap_or(price),
-                        Side::Sell => self.get_best_bid_price().unwrap_or(price),
-                    };
-                } else if order_ref.order_type == OrderType::StopLimit {
-                    order_ref.order_type = OrderType::Limit;
-                }
+    /// Current price of the source `order` triggers off.
+    pub fn trigger_price(&self, order: &Order) -> Option<Price> {
+        let source = order
+            .trigger_source
+            .unwrap_or(self.stop_order_book.default_trigger_source());
+        self.reference_prices().get(source)
+    }
 
-                drop(order_ref);
+    pub fn stop_trigger_source(&self) -> TriggerSource {
+        self.stop_order_book.default_trigger_source()
+    }
+
+    pub fn set_stop_trigger_source(&mut self, source: TriggerSource) {
+        self.stop_order_book.set_default_trigger_source(source);
+    }
 
-                self.add_order(Arc::clone(&order))?;
+    fn take_triggered_stops(&mut self) -> Vec<Arc<RwLock<Order>>> {
+        let triggered_orders = self.stop_order_book.get_triggered_orders(&self.reference_prices());
+        if !triggered_orders.is_empty() {
+            self.stop_order_book
+                .remove_triggered_orders(&triggered_orders);
+            for order in &triggered_orders {
+                self.unindex_order(order);
             }
         }
 
-        Ok(())
+        triggered_orders
     }
 
     pub fn expire_orders(&mut self, current_time: i64) -> Vec<Arc<RwLock<Order>>> {
-        let mut expired_order_ids = Vec::new();
-        let mut expired_orders = Vec::new();
+        self.expire_orders_at_close(current_time, None)
+    }
 
-        for (&order_id, order) in &self.order_map {
-            let order_ref = order.read();
-            if order_ref.is_expired(current_time) {
-                expired_order_ids.push(order_id);
-            }
+    /// Expires the resting orders whose deadline has passed by
+    /// `current_time`, with Day orders ending at `session_close` as in
+    /// `Order::is_expired_at_close`. Only orders that are due are touched.
+    pub fn expire_orders_at_close(
+        &mut self,
+        current_time: i64,
+        session_close: Option<i64>,
+    ) -> Vec<Arc<RwLock<Order>>> {
+        let mut expired_order_ids =
+            self.expiries
+                .pop_expired(true, &self.order_map, current_time, session_close);
+        expired_order_ids.extend(self.expiries.pop_expired(
+            false,
+            &self.order_map,
+            current_time,
+            session_close,
+        ));
+        if self.expiries.len() > 2 * self.order_map.len() + 64 {
+            self.expiries.compact(&self.order_map);
         }
 
+        let mut expired_orders = Vec::new();
         for order_id in expired_order_ids {
             if let Some(order) = self.remove_order(order_id) {
                 let mut order_ref = order.write();
                 order_ref.status = OrderStatus::Expired;
+                order_ref.last_update = current_time;
                 drop(order_ref);
                 expired_orders.push(order);
             }
@@ -399,19 +1282,47 @@ impl OrderBook {
         expired_orders
     }
 
+    /// A resting or pending stop order by id.
     pub fn get_order(&self, order_id: u64) -> Option<Arc<RwLock<Order>>> {
-        self.order_map.get(&order_id).cloned()
+        self.order_map
+            .get(&order_id)
+            .or_else(|| self.stop_order_book.order_map.get(&order_id))
+            .cloned()
     }
 
-    pub fn replenish_iceberg_order(
-        &mut self,
+    /// Whether `order_id` is resting or a pending stop order on the book.
+    pub fn contains_order(&self, order_id: u64) -> bool {
+        self.order_map.contains_key(&order_id) || self.stop_order_book.order_map.contains_key(&order_id)
+    }
+
+    /// Number of orders resting on the book, excluding stop orders.
+    pub fn order_count(&self) -> usize {
+        self.order_map.len()
+    }
+
+    pub fn stop_order_count(&self) -> usize {
+        self.stop_order_book.len()
+    }
+
+    /// See `StopOrderBook::peek_next_trigger`.
+    pub fn peek_next_stop_trigger(&self, side: Side) -> Option<Price> {
+        self.stop_order_book.peek_next_trigger(side)
+    }
+
+    /// Every resting and pending stop order in the book.
+    pub fn orders(&self) -> impl Iterator<Item = &Arc<RwLock<Order>>> {
+        self.order_map.values().chain(self.stop_order_book.order_map.values())
+    }
+
+    pub fn replenish_iceberg_order(
+        &mut self,
         order: Arc<RwLock<Order>>,
     ) -> Result<(), &'static str> {
         let order_ref = order.read();
+        let order_id = order_ref.id;
         let price = order_ref.price;
         let side = order_ref.side;
-        let display_qty = order_ref.display_quantity.unwrap_or(0);
-        let remaining_qty = order_ref.remaining_quantity();
+        let fresh_slice = order_ref.has_fresh_slice();
         drop(order_ref);
 
         let levels = match side {
@@ -419,40 +1330,229 @@ impl OrderBook {
             Side::Sell => &mut self.sell_levels,
         };
 
-        if let Some(level) = levels.get_mut(&price) {
-            let new_visible = std::cmp::min(display_qty as u64, remaining_qty as u64);
-            level.visible_volume = new_visible;
+        levels
+            .get_mut(&price)
+            .ok_or("Price level not found")?
+            .replenish_iceberg_order(order_id)?;
+        if fresh_slice {
+            self.l3_feed.replace(&order.read(), false);
+        }
 
-            Ok(())
-        } else {
-            Err("Price level not found")
+        self.update_depth_level(side, price);
+
+        Ok(())
+    }
+
+    /// Brings the cached depth and top of book in line after the level at
+    /// `price` changed.
+    fn update_depth_level(&mut self, side: Side, price: Price) {
+        self.update_depth_window(side, price);
+        self.publish_top_of_book();
+    }
+
+    /// Updates the displayed depth touching only the level at `price`. The
+    /// level past the window is looked up only when a displayed level
+    /// disappears from a full window.
+    fn update_depth_window(&mut self, side: Side, price: Price) {
+        let levels = match side {
+            Side::Buy => &self.buy_levels,
+            Side::Sell => &self.sell_levels,
+        };
+        let depth = self.depth.get_mut();
+        let shown = match side {
+            Side::Buy => &mut depth.bid_levels,
+            Side::Sell => &mut depth.ask_levels,
+        };
+        // Displayed levels run best first, so bids are searched descending.
+        let slot = shown.binary_search_by(|&(p, _)| match side {
+            Side::Buy => price.cmp(&p),
+            Side::Sell => p.cmp(&price),
+        });
+
+        match (slot, levels.get(&price)) {
+            (Ok(i), Some(level)) => {
+                if shown[i].1 == level.visible_volume {
+                    return;
+                }
+                shown[i].1 = level.visible_volume;
+            }
+            (Err(i), Some(level)) => {
+                if i >= self.depth_levels {
+                    return;
+                }
+                shown.insert(i, (price, level.visible_volume));
+                shown.truncate(self.depth_levels);
+            }
+            (Ok(i), None) => {
+                let was_full = shown.len() == self.depth_levels;
+                shown.remove(i);
+                if was_full {
+                    let next = match (side, shown.last()) {
+                        (Side::Buy, Some(&(worst, _))) => levels.range(..worst).next_back(),
+                        (Side::Sell, Some(&(worst, _))) => levels.range((Excluded(worst), Unbounded)).next(),
+                        (Side::Buy, None) => levels.iter().next_back(),
+                        (Side::Sell, None) => levels.iter().next(),
+                    };
+                    if let Some((&next_price, level)) = next {
+                        shown.push((next_price, level.visible_volume));
+                    }
+                }
+            }
+            (Err(_), None) => return,
         }
+
+        self.depth_generation += 1;
     }
 
-    fn update_depth(&self) {
-        let mut depth = self.depth.write();
+    fn update_depth(&mut self) {
+        let depth = self.depth.get_mut();
         depth.bid_levels.clear();
         depth.ask_levels.clear();
 
-        let mut bid_prices: Vec<_> = self.buy_levels.keys().cloned().collect();
-        bid_prices.sort_by(|a, b| b.cmp(a));
-        for &price in bid_prices.iter().take(self.depth_levels) {
-            if let Some(level) = self.buy_levels.get(&price) {
-                depth.bid_levels.push((price, level.visible_volume));
-            }
+        for (&price, level) in self.buy_levels.iter().rev().take(self.depth_levels) {
+            depth.bid_levels.push((price, level.visible_volume));
         }
 
-        let mut ask_prices: Vec<_> = self.sell_levels.keys().cloned().collect();
-        ask_prices.sort();
-        for &price in ask_prices.iter().take(self.depth_levels) {
-            if let Some(level) = self.sell_levels.get(&price) {
-                depth.ask_levels.push((price, level.visible_volume));
-            }
+        for (&price, level) in self.sell_levels.iter().take(self.depth_levels) {
+            depth.ask_levels.push((price, level.visible_volume));
+        }
+        self.depth_generation += 1;
+        self.publish_top_of_book();
+    }
+
+    /// Publishes the best bid and offer if they changed.
+    fn publish_top_of_book(&self) {
+        let visible = |(&price, level): (&Price, &PriceLevel)| (price, level.visible_volume);
+        let bid = self.buy_levels.iter().next_back().map(visible);
+        let ask = self.sell_levels.iter().next().map(visible);
+        self.top_of_book.publish(bid, ask);
+    }
+
+    /// Debug check that an order about to rest after matching at `price`
+    /// does not cross the opposite best. Auction calls rest crossed by
+    /// design, and a busted trade may re-book an order behind a level it
+    /// had already traded through, so only the incoming side is checked.
+    pub(crate) fn debug_assert_rests_uncrossed(&self, side: Side, price: Price) {
+        if self.trading_state == TradingState::Auction {
+            return;
+        }
+        let opposite = match side {
+            Side::Buy => self.sell_levels.keys().next(),
+            Side::Sell => self.buy_levels.keys().next_back(),
+        };
+        if let Some(&best) = opposite {
+            debug_assert!(
+                match side {
+                    Side::Buy => price < best,
+                    Side::Sell => price > best,
+                },
+                "{} book crossed: {:?} order rests at {} against {}",
+                self.symbol,
+                side,
+                price,
+                best
+            );
         }
     }
 
+    /// The best bid and offer with their visible sizes, read from the cache
+    /// rather than the level maps.
+    pub fn top_of_book(&self) -> TopOfBook {
+        self.top_of_book.read()
+    }
+
+    /// The cache behind `top_of_book`, for readers on other threads that
+    /// must not wait for matching. It belongs to this book: a book rebuilt
+    /// from a snapshot or woken from hibernation publishes to a new one.
+    pub fn top_of_book_cell(&self) -> Arc<TopOfBookCell> {
+        Arc::clone(&self.top_of_book)
+    }
+
+    /// Bumped whenever the cached depth changes, so a consumer can skip
+    /// copying the depth when the generation it last saw is current.
+    pub fn depth_generation(&self) -> u64 {
+        self.depth_generation
+    }
+
     pub fn get_market_depth(&self) -> MarketDepth {
-        self.depth.read().clone()
+        let mut depth = self.depth.read().clone();
+        depth.checksum = self.checksum(self.depth_levels);
+        depth
+    }
+
+    /// The top `levels` visible prices on each side, best first. Unlike
+    /// `get_market_depth` it is not limited to the cached window.
+    pub fn market_depth(&self, levels: usize) -> MarketDepth {
+        MarketDepth::new(
+            self.volume_levels(Side::Buy, VolumeView::Visible).take(levels).collect(),
+            self.volume_levels(Side::Sell, VolumeView::Visible).take(levels).collect(),
+        )
+    }
+
+    /// `market_depth` with each level's order count and oldest order in
+    /// `bid_details` and `ask_details`.
+    pub fn detailed_market_depth(&self, levels: usize) -> MarketDepth {
+        let details = |side| {
+            self.levels_best_first(side)
+                .filter(|(_, level)| level.visible_volume > 0)
+                .take(levels)
+                .map(|(_, level)| level.depth_level())
+                .collect()
+        };
+        let mut depth = self.market_depth(levels);
+        depth.bid_details = details(Side::Buy);
+        depth.ask_details = details(Side::Sell);
+        depth
+    }
+
+    /// `depth_checksum` of the top `levels` prices on each side. Computed
+    /// on request and reused until the depth generation moves; the
+    /// generation only follows the displayed window, so checksums over
+    /// more than `depth_levels` levels are computed every time.
+    pub fn checksum(&self, levels: usize) -> u32 {
+        let cacheable = levels <= self.depth_levels;
+        if cacheable {
+            if let Some(cached) = *self.checksum.lock() {
+                if cached.generation == self.depth_generation && cached.levels == levels {
+                    return cached.checksum;
+                }
+            }
+        }
+
+        let volume = |(&price, level): (&Price, &PriceLevel)| (price, level.visible_volume);
+        let checksum = depth_checksum(
+            self.buy_levels.iter().rev().take(levels).map(volume),
+            self.sell_levels.iter().take(levels).map(volume),
+        );
+        if cacheable {
+            *self.checksum.lock() = Some(CachedChecksum {
+                generation: self.depth_generation,
+                levels,
+                checksum,
+            });
+        }
+        checksum
+    }
+
+    /// Receives every change to the resting orders from now on, buffering
+    /// up to `capacity` events.
+    pub fn subscribe_l3(&mut self, capacity: usize) -> Receiver<L3Event> {
+        self.l3_feed.subscribe(capacity)
+    }
+
+    /// Runs `callback` synchronously on every change to the resting orders.
+    pub fn on_l3_event(&mut self, callback: L3Callback) {
+        self.l3_feed.on_event(callback);
+    }
+
+    pub fn has_l3_listeners(&self) -> bool {
+        self.l3_feed.has_listeners()
+    }
+
+    /// Sequence of the last L3 event, so a consumer can place a snapshot
+    /// in the stream.
+    pub fn l3_sequence(&self) -> u64 {
+        self.l3_feed.last_sequence()
     }
 
     pub fn set_depth_levels(&mut self, levels: usize) {
@@ -460,6 +1560,440 @@ impl OrderBook {
         self.update_depth();
     }
 
+    /// Features over the top `n_levels` of the depth cache, so at most
+    /// `depth_levels` levels per side are considered. Depth near the mid
+    /// counts levels with `|price - mid| <= tick_window`, compared in half
+    /// ticks so an odd spread needs no rounding.
+    pub fn features(&self, n_levels: usize, tick_window: u64) -> BookFeatures {
+        let depth = self.depth.read();
+        let bids = &depth.bid_levels[..n_levels.min(depth.bid_levels.len())];
+        let asks = &depth.ask_levels[..n_levels.min(depth.ask_levels.len())];
+
+        let bid_volume: u64 = bids.iter().map(|&(_, volume)| volume).sum();
+        let ask_volume: u64 = asks.iter().map(|&(_, volume)| volume).sum();
+        let total_volume = bid_volume as i128 + ask_volume as i128;
+        let imbalance_bps = (total_volume > 0).then(|| {
+            ((bid_volume as i128 - ask_volume as i128) * 10_000 / total_volume) as i64
+        });
+
+        let (Some(&(bid, bid_size)), Some(&(ask, ask_size))) = (bids.first(), asks.first()) else {
+            return BookFeatures {
+                imbalance_bps,
+                ..Default::default()
+            };
+        };
+
+        let touch_size = bid_size as Notional + ask_size as Notional;
+        let microprice = (touch_size > 0).then(|| {
+            let weighted = bid as Notional * ask_size as Notional + ask as Notional * bid_size as Notional;
+            // Floored, so a negative microprice rounds half up as well.
+            (2 * weighted + touch_size).div_euclid(2 * touch_size) as Price
+        });
+
+        let twice_mid = bid as Notional + ask as Notional;
+        let twice_window = 2 * tick_window as u128;
+        let near_mid = |levels: &[(Price, u64)]| -> u64 {
+            levels
+                .iter()
+                .filter(|&&(price, _)| (2 * price as Notional).abs_diff(twice_mid) <= twice_window)
+                .map(|&(_, volume)| volume)
+                .sum()
+        };
+
+        BookFeatures {
+            imbalance_bps,
+            microprice,
+            bid_depth_near_mid: Some(near_mid(bids)),
+            ask_depth_near_mid: Some(near_mid(asks)),
+        }
+    }
+
+    /// Prices and volumes of one side of the book, best price first,
+    /// skipping levels with nothing to count.
+    fn volume_levels(&self, side: Side, view: VolumeView) -> impl Iterator<Item = (Price, u64)> + '_ {
+        self.levels_best_first(side)
+            .map(move |(&price, level)| (price, view.volume(level)))
+            .filter(|&(_, volume)| volume > 0)
+    }
+
+    fn levels_best_first(&self, side: Side) -> impl Iterator<Item = (&Price, &PriceLevel)> + '_ {
+        let (bids, asks) = match side {
+            Side::Buy => (Some(self.buy_levels.iter().rev()), None),
+            Side::Sell => (None, Some(self.sell_levels.iter())),
+        };
+        bids.into_iter().flatten().chain(asks.into_iter().flatten())
+    }
+
+    /// The first `max_levels` prices on `side` of the book, best first,
+    /// each with the volume resting at it or better. Walks the book lazily
+    /// and allocates nothing.
+    pub fn cumulative_depth(
+        &self,
+        side: Side,
+        max_levels: usize,
+        view: VolumeView,
+    ) -> impl Iterator<Item = (Price, u64)> + '_ {
+        self.volume_levels(side, view)
+            .take(max_levels)
+            .scan(0u64, |cumulative, (price, volume)| {
+                *cumulative += volume;
+                Some((price, *cumulative))
+            })
+    }
+
+    /// Volume-weighted average price, truncated, and worst price of
+    /// sweeping `quantity` from `side` of the book: the asks for a buyer,
+    /// the bids for a seller. `None` if the side holds less than
+    /// `quantity`, or `quantity` is zero.
+    pub fn vwap_for_quantity(&self, side: Side, quantity: u64, view: VolumeView) -> Option<(Price, Price)> {
+        if quantity == 0 {
+            return None;
+        }
+        let mut remaining = quantity;
+        let mut total: Notional = 0;
+        for (price, volume) in self.volume_levels(side, view) {
+            let taken = volume.min(remaining);
+            total += price as Notional * taken as Notional;
+            remaining -= taken;
+            if remaining == 0 {
+                return Some(((total / quantity as Notional) as Price, price));
+            }
+        }
+        None
+    }
+
+    /// Worst price reached sweeping `quantity` from `side` of the book, the
+    /// limit an order needs to fill completely. `None` as for
+    /// `vwap_for_quantity`.
+    pub fn impact_price(&self, side: Side, quantity: u64, view: VolumeView) -> Option<Price> {
+        if quantity == 0 {
+            return None;
+        }
+        self.cumulative_depth(side, usize::MAX, view)
+            .find(|&(_, cumulative)| cumulative >= quantity)
+            .map(|(price, _)| price)
+    }
+
+    pub fn get_l3_snapshot(&self, depth: usize) -> L3Snapshot {
+        let to_l3_level = |(&price, level): (&Price, &PriceLevel)| L3Level {
+            price,
+            orders: level
+                .orders
+                .iter()
+                .map(|o| L3OrderEntry::from(&*o.read()))
+                .collect(),
+        };
+
+        L3Snapshot {
+            symbol: self.symbol.clone(),
+            bids: self.buy_levels.iter().rev().take(depth).map(to_l3_level).collect(),
+            asks: self.sell_levels.iter().take(depth).map(to_l3_level).collect(),
+        }
+    }
+
+    /// Hash of the resting book: every order's id, price and remaining
+    /// quantity in queue order, plus the last trade price. Timestamps are
+    /// left out so two books built from the same commands agree.
+    pub fn digest(&self) -> u64 {
+        let mut hasher = StableHasher::new();
+        self.symbol.hash(&mut hasher);
+        self.last_trade_price.hash(&mut hasher);
+
+        for levels in [&self.buy_levels, &self.sell_levels] {
+            hash_levels(&mut hasher, levels.iter().map(|(&price, level)| (price, level)));
+        }
+
+        hasher.finish()
+    }
+
+    /// Checks that the book holds together: each level's volumes match its
+    /// orders, levels and the order index agree on every resting order,
+    /// the stop book holds only stop orders, and the best bid is below the
+    /// best ask. An auction call, or a halt queueing orders, rests crossed
+    /// by design, so the last is only checked in continuous trading.
+    /// Reports the first violation found.
+    pub fn validate(&self) -> Result<(), BookInvariantError> {
+        let symbol = || self.symbol.clone();
+        let mut queued = 0;
+        for (side, levels) in [(Side::Buy, &self.buy_levels), (Side::Sell, &self.sell_levels)] {
+            for (&price, level) in levels {
+                if level.orders.is_empty() {
+                    return Err(BookInvariantError::EmptyLevel {
+                        symbol: symbol(),
+                        side,
+                        price,
+                    });
+                }
+                let (mut total, mut visible) = (0, 0);
+                for order in &level.orders {
+                    let order = order.read();
+                    let indexed = self.order_map.get(&order.id).is_some_and(|indexed| {
+                        let indexed = indexed.read();
+                        indexed.side == side && indexed.price == price
+                    });
+                    if !indexed {
+                        return Err(BookInvariantError::UnindexedOrder {
+                            symbol: symbol(),
+                            order_id: order.id,
+                            side,
+                            price,
+                        });
+                    }
+                    total += order.remaining_quantity() as u64;
+                    visible += order.visible_quantity() as u64;
+                }
+                if (level.total_volume, level.visible_volume) != (total, visible) {
+                    return Err(BookInvariantError::LevelVolume {
+                        symbol: symbol(),
+                        side,
+                        price,
+                        recorded_total: level.total_volume,
+                        recorded_visible: level.visible_volume,
+                        total,
+                        visible,
+                    });
+                }
+                queued += level.orders.len();
+            }
+        }
+
+        for (&order_id, order) in &self.order_map {
+            let (side, price) = {
+                let order = order.read();
+                (order.side, order.price)
+            };
+            let levels = match side {
+                Side::Buy => &self.buy_levels,
+                Side::Sell => &self.sell_levels,
+            };
+            if levels.get(&price).and_then(|level| level.index_of(order_id)).is_none() {
+                return Err(BookInvariantError::UnqueuedOrder {
+                    symbol: symbol(),
+                    order_id,
+                    side,
+                    price,
+                });
+            }
+        }
+        if queued != self.order_map.len() {
+            return Err(BookInvariantError::OrderCount {
+                symbol: symbol(),
+                queued,
+                indexed: self.order_map.len(),
+            });
+        }
+
+        for (&order_id, order) in &self.stop_order_book.order_map {
+            let order = order.read();
+            if !order.is_stop_order() {
+                return Err(BookInvariantError::NotAStopOrder {
+                    symbol: symbol(),
+                    order_id,
+                    order_type: order.order_type,
+                });
+            }
+        }
+
+        if self.trading_state != TradingState::Continuous {
+            return Ok(());
+        }
+        let best_bid = self.buy_levels.iter().next_back();
+        let best_ask = self.sell_levels.iter().next();
+        if let (Some((&bid_price, bid)), Some((&ask_price, ask))) = (best_bid, best_ask) {
+            if bid_price >= ask_price {
+                return Err(BookInvariantError::Crossed {
+                    symbol: symbol(),
+                    bid_price,
+                    bid_order_id: bid.front().map(|order| order.read().id),
+                    ask_price,
+                    ask_order_id: ask.front().map(|order| order.read().id),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn match_policy(&self) -> MatchPolicy {
+        self.match_policy
+    }
+
+    pub fn set_match_policy(&mut self, policy: MatchPolicy) {
+        self.match_policy = policy;
+    }
+
+    pub fn trading_state(&self) -> TradingState {
+        self.trading_state
+    }
+
+    pub fn set_trading_state(&mut self, state: TradingState) {
+        self.trading_state = state;
+    }
+
+    pub fn price_bands(&self) -> PriceBands {
+        self.price_bands
+    }
+
+    pub fn set_price_bands(&mut self, bands: PriceBands) {
+        self.price_bands = bands;
+    }
+
+    pub fn order_type_rules(&self) -> &OrderTypeRules {
+        &self.order_type_rules
+    }
+
+    pub fn set_order_type_rules(&mut self, rules: OrderTypeRules) {
+        self.order_type_rules = rules;
+    }
+
+    pub fn fee_schedule(&self) -> FeeSchedule {
+        self.fee_schedule
+    }
+
+    pub fn set_fee_schedule(&mut self, schedule: FeeSchedule) {
+        self.fee_schedule = schedule;
+    }
+
+    pub fn spec(&self) -> SymbolSpec {
+        self.spec
+    }
+
+    pub fn set_spec(&mut self, spec: SymbolSpec) {
+        self.spec = spec;
+    }
+
+    /// Drops filled orders from the level at `price` and recomputes its
+    /// volumes, removing the level entirely once it is empty.
+    pub fn refresh_level(&mut self, side: Side, price: Price) {
+        let levels = match side {
+            Side::Buy => &mut self.buy_levels,
+            Side::Sell => &mut self.sell_levels,
+        };
+
+        if let Some(level) = levels.get_mut(&price) {
+            let order_map = &mut self.order_map;
+            let user_orders = &mut self.user_orders;
+            let client_orders = &mut self.client_orders;
+            let open_orders = &self.open_orders;
+            level.retain_orders(|o| {
+                let order_ref = o.read();
+                if order_ref.is_filled() {
+                    order_map.remove(&order_ref.id);
+                    Self::unindex(user_orders, client_orders, open_orders, &order_ref);
+                    false
+                } else {
+                    true
+                }
+            });
+            level.recalculate_volumes();
+
+            if level.orders.is_empty() {
+                levels.remove(&price);
+            }
+        }
+
+        self.update_depth_level(side, price);
+    }
+
+    /// Completes matching against the level at `price` once `filled` have
+    /// left its queue: drops them from the book's indexes and the level
+    /// itself once it is empty.
+    pub fn finish_level_fills(&mut self, side: Side, price: Price, filled: &[Arc<RwLock<Order>>]) {
+        for order in filled {
+            let order_ref = order.read();
+            self.order_map.remove(&order_ref.id);
+            Self::unindex(&mut self.user_orders, &mut self.client_orders, &self.open_orders, &order_ref);
+        }
+
+        let levels = match side {
+            Side::Buy => &mut self.buy_levels,
+            Side::Sell => &mut self.sell_levels,
+        };
+        if levels.get(&price).is_some_and(|level| level.orders.is_empty()) {
+            levels.remove(&price);
+        }
+
+        self.update_depth_level(side, price);
+    }
+
+    /// Whether market orders booked during an auction call or a halt are
+    /// waiting for the uncross. They rest at the extreme prices.
+    pub(crate) fn has_queued_market_orders(&self) -> bool {
+        self.buy_levels.contains_key(&Price::MAX) || self.sell_levels.contains_key(&Price::MIN)
+    }
+
+    /// Price that would clear the most volume if the book were uncrossed now.
+    /// Ties go to the smallest imbalance, then to the price closest to the
+    /// last trade, then to the lower price. Resting market orders are counted
+    /// as willing to trade at any price.
+    pub fn indicative_uncross(&self) -> Option<IndicativeUncross> {
+        let level_quantity = |level: &PriceLevel| -> u64 {
+            level
+                .orders
+                .iter()
+                .map(|o| o.read().remaining_quantity() as u64)
+                .sum()
+        };
+        let bids: Vec<(Price, u64)> = self
+            .buy_levels
+            .iter()
+            .map(|(&price, level)| (price, level_quantity(level)))
+            .collect();
+        let asks: Vec<(Price, u64)> = self
+            .sell_levels
+            .iter()
+            .map(|(&price, level)| (price, level_quantity(level)))
+            .collect();
+
+        let mut candidates: Vec<Price> = bids
+            .iter()
+            .chain(asks.iter())
+            .map(|&(price, _)| price)
+            .filter(|&price| price != Price::MIN && price != Price::MAX)
+            .chain(self.last_trade_price)
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        let reference = self.last_trade_price;
+        let mut best: Option<IndicativeUncross> = None;
+
+        for price in candidates {
+            let buy_volume: u64 = bids.iter().filter(|&&(p, _)| p >= price).map(|&(_, q)| q).sum();
+            let sell_volume: u64 = asks.iter().filter(|&&(p, _)| p <= price).map(|&(_, q)| q).sum();
+            let candidate = IndicativeUncross {
+                price,
+                volume: buy_volume.min(sell_volume),
+                imbalance: buy_volume.abs_diff(sell_volume),
+                imbalance_side: match buy_volume.cmp(&sell_volume) {
+                    std::cmp::Ordering::Greater => Some(Side::Buy),
+                    std::cmp::Ordering::Less => Some(Side::Sell),
+                    std::cmp::Ordering::Equal => None,
+                },
+            };
+
+            if candidate.volume == 0 {
+                continue;
+            }
+
+            let better = match best {
+                None => true,
+                Some(current) => {
+                    let distance = |p: Price| reference.map_or(0, |r| p.abs_diff(r));
+                    candidate.volume > current.volume
+                        || (candidate.volume == current.volume
+                            && (candidate.imbalance, distance(price))
+                                < (current.imbalance, distance(current.price)))
+                }
+            };
+
+            if better {
+                best = Some(candidate);
+            }
+        }
+
+        best
+    }
+
     pub fn create_snapshot(&self) -> OrderBookSnapshot {
         let mut buy_levels = HashMap::new();
         let mut sell_levels = HashMap::new();
@@ -509,6 +2043,8 @@ impl OrderBook {
                 .map(|o| OrderSnapshot::from(&*o.read()))
                 .collect();
             stop_orders.extend(snapshot_stop_orders);
+            // Restoring in id order keeps each stop price's entry order.
+            stop_orders.sort_by_key(|order| order.id);
         }
 
         OrderBookSnapshot {
@@ -517,6 +2053,17 @@ impl OrderBook {
             sell_levels,
             stop_orders,
             last_trade_price: self.last_trade_price,
+            mark_price: self.mark_price,
+            index_price: self.index_price,
+            stop_trigger_source: self.stop_order_book.default_trigger_source(),
+            match_policy: self.match_policy,
+            trading_state: self.trading_state,
+            price_bands: self.price_bands,
+            order_type_rules: self.order_type_rules.clone(),
+            fee_schedule: self.fee_schedule,
+            spec: self.spec,
+            checksum_levels: self.depth_levels,
+            checksum: self.checksum(self.depth_levels),
         }
     }
 
@@ -525,13 +2072,35 @@ impl OrderBook {
     }
 }
 
+/// Hashes one side of a book, levels in ascending price order.
+fn hash_levels<'a>(hasher: &mut StableHasher, levels: impl ExactSizeIterator<Item = (Price, &'a PriceLevel)>) {
+    levels.len().hash(hasher);
+    for (price, level) in levels {
+        price.hash(hasher);
+        for order in &level.orders {
+            let order_ref = order.read();
+            order_ref.id.hash(hasher);
+            order_ref.remaining_quantity().hash(hasher);
+        }
+    }
+}
+
+/// DashMap-backed book that can be shared between threads without an outer
+/// lock. Adds and cancels only lock the shard holding their price level;
+/// `match_order` serializes takers on this book so the book never crosses,
+/// leaving other symbols free to match in parallel.
 pub struct ConcurrentOrderBook {
     symbol: String,
-    buy_levels: DashMap<u64, CachePadded<PriceLevel>>,
-    sell_levels: DashMap<u64, CachePadded<PriceLevel>>,
+    buy_levels: DashMap<Price, CachePadded<PriceLevel>>,
+    sell_levels: DashMap<Price, CachePadded<PriceLevel>>,
     order_map: DashMap<u64, Arc<RwLock<Order>>>,
     stop_order_book: RwLock<StopOrderBook>,
-    last_trade_price: RwLock<Option<u64>>,
+    last_trade_price: RwLock<Option<Price>>,
+    /// Held exclusively while matching and shared by cancels, so liquidity
+    /// a match has counted cannot be cancelled out from under it.
+    match_lock: RwLock<()>,
+    match_policy: RwLock<MatchPolicy>,
+    fee_schedule: RwLock<FeeSchedule>,
 }
 
 impl ConcurrentOrderBook {
@@ -543,6 +2112,9 @@ impl ConcurrentOrderBook {
             order_map: DashMap::new(),
             stop_order_book: RwLock::new(StopOrderBook::new(symbol)),
             last_trade_price: RwLock::new(None),
+            match_lock: RwLock::new(()),
+            match_policy: RwLock::new(MatchPolicy::Fifo),
+            fee_schedule: RwLock::new(FeeSchedule::default()),
         }
     }
 
@@ -550,6 +2122,13 @@ impl ConcurrentOrderBook {
         &self.symbol
     }
 
+    fn levels(&self, side: Side) -> &DashMap<Price, CachePadded<PriceLevel>> {
+        match side {
+            Side::Buy => &self.buy_levels,
+            Side::Sell => &self.sell_levels,
+        }
+    }
+
     pub fn add_order(&self, order: Arc<RwLock<Order>>) -> Result<(), &'static str> {
         let order_ref = order.read();
         let order_id = order_ref.id;
@@ -557,12 +2136,8 @@ impl ConcurrentOrderBook {
         let side = order_ref.side;
         drop(order_ref);
 
-        let levels = match side {
-            Side::Buy => &self.buy_levels,
-            Side::Sell => &self.sell_levels,
-        };
-
-        let mut entry = levels
+        let mut entry = self
+            .levels(side)
             .entry(price)
             .or_insert_with(|| CachePadded::new(PriceLevel::new(price)));
         entry.value_mut().add_order(Arc::clone(&order));
@@ -570,12 +2145,351 @@ impl ConcurrentOrderBook {
         Ok(())
     }
 
-    pub fn get_last_trade_price(&self) -> Option<u64> {
+    pub fn add_stop_order(&self, order: Arc<RwLock<Order>>) -> Result<(), &'static str> {
+        self.stop_order_book.write().add_stop_order(order)
+    }
+
+    /// Removes a resting or stop order. Cancels run alongside each other
+    /// but wait for a match in progress, so a match never loses liquidity
+    /// it has already counted.
+    pub fn remove_order(&self, order_id: u64) -> Option<Arc<RwLock<Order>>> {
+        let _guard = self.match_lock.read();
+        let located = self.order_map.get(&order_id).map(|order| {
+            let order_ref = order.read();
+            (order_ref.side, order_ref.price)
+        });
+
+        if let Some((side, price)) = located {
+            let levels = self.levels(side);
+            let removed = levels
+                .get_mut(&price)
+                .and_then(|mut level| level.remove_order(order_id));
+
+            if let Some(order) = removed {
+                self.order_map.remove(&order_id);
+                levels.remove_if(&price, |_, level| level.orders.is_empty());
+                return Some(order);
+            }
+        }
+
+        self.stop_order_book.write().remove_stop_order(order_id)
+    }
+
+    pub fn cancel_order(&self, order_id: u64) -> Option<Arc<RwLock<Order>>> {
+        self.remove_order(order_id)
+    }
+
+    pub fn get_order(&self, order_id: u64) -> Option<Arc<RwLock<Order>>> {
+        self.order_map.get(&order_id).map(|order| Arc::clone(&order))
+    }
+
+    pub fn order_count(&self) -> usize {
+        self.order_map.len()
+    }
+
+    pub fn get_best_bid_price(&self) -> Option<Price> {
+        self.buy_levels
+            .iter()
+            .filter(|level| !level.orders.is_empty())
+            .map(|level| *level.key())
+            .max()
+    }
+
+    pub fn get_best_ask_price(&self) -> Option<Price> {
+        self.sell_levels
+            .iter()
+            .filter(|level| !level.orders.is_empty())
+            .map(|level| *level.key())
+            .min()
+    }
+
+    /// Visible volume of the best `levels` prices per side. Built on demand
+    /// by scanning every level.
+    pub fn get_market_depth(&self, levels: usize) -> MarketDepth {
+        let collect = |side_levels: &DashMap<Price, CachePadded<PriceLevel>>| {
+            let mut shown: Vec<(Price, u64)> = side_levels
+                .iter()
+                .filter(|level| !level.orders.is_empty())
+                .map(|level| (*level.key(), level.visible_volume))
+                .collect();
+            shown.sort_unstable_by_key(|&(price, _)| price);
+            shown
+        };
+
+        let mut bid_levels = collect(&self.buy_levels);
+        bid_levels.reverse();
+        bid_levels.truncate(levels);
+        let mut ask_levels = collect(&self.sell_levels);
+        ask_levels.truncate(levels);
+
+        MarketDepth::new(bid_levels, ask_levels)
+    }
+
+    pub fn match_policy(&self) -> MatchPolicy {
+        *self.match_policy.read()
+    }
+
+    pub fn set_match_policy(&self, policy: MatchPolicy) {
+        *self.match_policy.write() = policy;
+    }
+
+    pub fn fee_schedule(&self) -> FeeSchedule {
+        *self.fee_schedule.read()
+    }
+
+    pub fn set_fee_schedule(&self, schedule: FeeSchedule) {
+        *self.fee_schedule.write() = schedule;
+    }
+
+    /// Same hash as `OrderBook::digest` for a book holding the same orders,
+    /// so a concurrent book can be checked against a single-threaded one.
+    pub fn digest(&self) -> u64 {
+        let mut hasher = StableHasher::new();
+        self.symbol.hash(&mut hasher);
+        self.get_last_trade_price().hash(&mut hasher);
+
+        for levels in [&self.buy_levels, &self.sell_levels] {
+            let mut levels: Vec<_> = levels.iter().collect();
+            levels.sort_by_key(|level| *level.key());
+            hash_levels(&mut hasher, levels.iter().map(|level| (*level.key(), &**level.value())));
+        }
+
+        hasher.finish()
+    }
+
+    pub fn get_last_trade_price(&self) -> Option<Price> {
         *self.last_trade_price.read()
     }
 
-    pub fn update_last_trade_price(&self, price: u64) {
+    /// Records a trade price and takes the stop orders it triggers off the
+    /// stop book, in trigger order. `match_order` runs the stops its own
+    /// trades trigger.
+    pub fn update_last_trade_price(&self, price: Price) -> Vec<Arc<RwLock<Order>>> {
         *self.last_trade_price.write() = Some(price);
+
+        let reachable = {
+            let stop_order_book = self.stop_order_book.read();
+            stop_order_book.peek_next_trigger(Side::Buy).is_some_and(|stop| price >= stop)
+                || stop_order_book.peek_next_trigger(Side::Sell).is_some_and(|stop| price <= stop)
+        };
+        if !reachable {
+            return Vec::new();
+        }
+        let mut stop_order_book = self.stop_order_book.write();
+        let triggered = stop_order_book.get_triggered_orders(&ReferencePrices::last_trade(price));
+        stop_order_book.remove_triggered_orders(&triggered);
+        triggered
+    }
+
+    /// Matches `incoming` against the opposite side using the book's match
+    /// policy and fees, then rests what is left of a limit or iceberg order
+    /// unless it is IOC or FOK. Stop orders its trades trigger are matched
+    /// in turn, as are any they trigger. `incoming` must already carry its
+    /// order id. Trade ids come from `next_trade_id`, which may be shared
+    /// by books.
+    pub fn match_order(
+        &self,
+        incoming: Arc<RwLock<Order>>,
+        next_trade_id: &AtomicU64,
+        callbacks: &EngineCallbacks,
+    ) -> Result<TradeExecutionResult, MatchingError> {
+        let _guard = self.match_lock.write();
+        let mut result = TradeExecutionResult::new();
+
+        self.match_locked(&incoming, next_trade_id, callbacks, &mut result)?;
+        if !incoming.read().is_filled() {
+            result.remaining_order = Some(incoming);
+        }
+
+        let mut next = 0;
+        while next < result.triggered_stops.len() {
+            let stop = Arc::clone(&result.triggered_stops[next]);
+            next += 1;
+            self.activate_stop(stop, next_trade_id, callbacks, &mut result)?;
+        }
+
+        Ok(result)
+    }
+
+    /// Turns a triggered stop into its market or limit order and matches
+    /// it. A limit remainder rests unless it is IOC or FOK; any other
+    /// remainder is cancelled.
+    fn activate_stop(
+        &self,
+        order: Arc<RwLock<Order>>,
+        next_trade_id: &AtomicU64,
+        callbacks: &EngineCallbacks,
+        result: &mut TradeExecutionResult,
+    ) -> Result<(), MatchingError> {
+        {
+            let mut order_ref = order.write();
+            if order_ref.order_type == OrderType::StopMarket {
+                order_ref.order_type = OrderType::Market;
+                let best_price = match order_ref.side {
+                    Side::Buy => self.get_best_ask_price(),
+                    Side::Sell => self.get_best_bid_price(),
+                };
+                if let Some(price) = best_price {
+                    order_ref.price = price;
+                }
+            } else if order_ref.order_type == OrderType::StopLimit {
+                order_ref.order_type = OrderType::Limit;
+            }
+        }
+
+        match self.match_locked(&order, next_trade_id, callbacks, result) {
+            Err(MatchingError::FOKCannotBeFilled) => {}
+            outcome => outcome?,
+        }
+
+        let mut order_ref = order.write();
+        if order_ref.is_filled() {
+            drop(order_ref);
+            result.filled_orders.push(order);
+        } else if !Self::rests(&order_ref) {
+            callbacks.set_status(&mut order_ref, OrderStatus::Canceled);
+            drop(order_ref);
+            result.filled_orders.push(order);
+        }
+        Ok(())
+    }
+
+    fn rests(order: &Order) -> bool {
+        matches!(order.order_type, OrderType::Limit | OrderType::Iceberg)
+            && !matches!(order.time_in_force, TimeInForce::IOC | TimeInForce::FOK)
+    }
+
+    /// Debug check that an order about to rest after matching, including a
+    /// triggered stop, does not cross the opposite best. Callers hold
+    /// `match_lock`, so the opposite side cannot move underneath it.
+    fn debug_assert_rests_uncrossed(&self, side: Side, price: Price) {
+        let opposite = match side {
+            Side::Buy => self.get_best_ask_price(),
+            Side::Sell => self.get_best_bid_price(),
+        };
+        if let Some(best) = opposite {
+            debug_assert!(
+                match side {
+                    Side::Buy => price < best,
+                    Side::Sell => price > best,
+                },
+                "{} book crossed: {:?} order rests at {} against {}",
+                self.symbol,
+                side,
+                price,
+                best
+            );
+        }
+    }
+
+    /// Matches one order with `match_lock` held, resting its remainder if
+    /// it rests, and records the stops its trades trigger.
+    fn match_locked(
+        &self,
+        incoming: &Arc<RwLock<Order>>,
+        next_trade_id: &AtomicU64,
+        callbacks: &EngineCallbacks,
+        result: &mut TradeExecutionResult,
+    ) -> Result<(), MatchingError> {
+        let (side, time_in_force) = {
+            let order_ref = incoming.read();
+            (order_ref.side, order_ref.time_in_force)
+        };
+        let (opposite_levels, best_opposite) = match side {
+            Side::Buy => (&self.sell_levels, self.get_best_ask_price()),
+            Side::Sell => (&self.buy_levels, self.get_best_bid_price()),
+        };
+        let protection_price = MatchingEngine::protection_price_from(best_opposite, &incoming.read());
+        let match_stop = |price: Price| MatchingEngine::match_stop(&incoming.read(), protection_price, price);
+
+        if time_in_force == TimeInForce::FOK {
+            let available: u64 = opposite_levels
+                .iter()
+                .filter(|level| match_stop(*level.key()).is_none())
+                .map(|level| level.total_volume)
+                .sum();
+            if available < incoming.read().remaining_quantity() as u64 {
+                return Err(MatchingError::FOKCannotBeFilled);
+            }
+        }
+
+        let match_policy = self.match_policy();
+        let fee_schedule = self.fee_schedule();
+        let trades_before = result.trades.len();
+
+        while !incoming.read().is_filled() {
+            let best_price = match side {
+                Side::Buy => self.get_best_ask_price(),
+                Side::Sell => self.get_best_bid_price(),
+            };
+            let Some(best_price) = best_price else {
+                break;
+            };
+            match match_stop(best_price) {
+                Some(MatchStop::PriceProtection) => {
+                    result.cancel_reason = Some(CancelReason::PriceProtection);
+                    break;
+                }
+                Some(MatchStop::LimitPrice) => break,
+                None => {}
+            }
+            let Some(mut level) = opposite_levels.get_mut(&best_price) else {
+                continue;
+            };
+
+            let incoming_qty = incoming.read().remaining_quantity();
+            for (resting_order, trade_qty) in allocate_level(match_policy, incoming_qty, &level.orders) {
+                let mut trade_id = next_trade_id.fetch_add(1, Ordering::Relaxed);
+                MatchingEngine::execute_trade(
+                    &mut trade_id,
+                    callbacks,
+                    Arc::clone(incoming),
+                    Arc::clone(&resting_order),
+                    Fill {
+                        quantity: trade_qty,
+                        price: best_price,
+                        fees: fee_schedule,
+                        aggressor: Some(side),
+                    },
+                    result,
+                )?;
+
+                let (resting_id, filled, iceberg) = {
+                    let resting = resting_order.read();
+                    (resting.id, resting.is_filled(), resting.order_type == OrderType::Iceberg)
+                };
+                if filled {
+                    self.order_map.remove(&resting_id);
+                    result.filled_orders.push(resting_order);
+                } else if iceberg {
+                    level
+                        .replenish_iceberg_order(resting_id)
+                        .map_err(|e| MatchingError::InternalError(e.to_string()))?;
+                }
+            }
+
+            level.retain_orders(|o| !o.read().is_filled());
+            level.recalculate_volumes();
+            drop(level);
+            opposite_levels.remove_if(&best_price, |_, level| level.orders.is_empty());
+        }
+
+        if result.trades.len() > trades_before {
+            let last_price = result.trades[result.trades.len() - 1].price;
+            let triggered = self.update_last_trade_price(last_price);
+            result.add_triggered_stops(triggered);
+        }
+
+        if !incoming.read().is_filled() && Self::rests(&incoming.read()) {
+            let (side, price) = {
+                let order_ref = incoming.read();
+                (order_ref.side, order_ref.price)
+            };
+            self.debug_assert_rests_uncrossed(side, price);
+            self.add_order(Arc::clone(incoming))
+                .map_err(|e| MatchingError::InternalError(e.to_string()))?;
+        }
+        Ok(())
     }
 }
 
@@ -7,7 +7,7 @@ use parking_lot::RwLock;
 mod orderbook_tests {
     use super::*;
 
-    fn create_test_order(side: Side, price: u64, quantity: u32, user_id: u64) -> Arc<RwLock<Order>> {
+    fn create_test_order(side: Side, price: Price, quantity: u32, user_id: u64) -> Arc<RwLock<Order>> {
         Arc::new(RwLock::new(Order::new(
             "TEST".to_string(),
             side,
@@ -216,4 +216,94 @@ mod orderbook_tests {
             assert!(depth.ask_levels[i-1].0 < depth.ask_levels[i].0);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_best_prices_follow_level_removal() {
+        let mut orderbook = OrderBook::new("TEST");
+
+        for (i, price) in [97, 99, 95, 98].iter().enumerate() {
+            let mut order = Order::new("TEST".to_string(), Side::Buy, OrderType::Limit, *price, 10, 1);
+            order.id = i as u64 + 1;
+            orderbook.add_order(Arc::new(RwLock::new(order))).unwrap();
+        }
+
+        for (i, price) in [104, 101, 103].iter().enumerate() {
+            let mut order = Order::new("TEST".to_string(), Side::Sell, OrderType::Limit, *price, 10, 1);
+            order.id = i as u64 + 10;
+            orderbook.add_order(Arc::new(RwLock::new(order))).unwrap();
+        }
+
+        assert_eq!(orderbook.get_best_bid_price(), Some(99));
+        assert_eq!(orderbook.get_best_ask_price(), Some(101));
+
+        orderbook.cancel_order(2).unwrap();
+        orderbook.cancel_order(11).unwrap();
+
+        assert_eq!(orderbook.get_best_bid_price(), Some(98));
+        assert_eq!(orderbook.get_best_ask_price(), Some(103));
+    }
+
+    #[test]
+    fn test_incremental_depth_matches_full_rebuild() {
+        let mut orderbook = OrderBook::new("TEST");
+        orderbook.set_depth_levels(3);
+
+        let prices = [100, 103, 101, 99, 104, 102, 98, 105, 103, 100];
+        for (i, price) in prices.iter().enumerate() {
+            let side = if *price <= 101 { Side::Buy } else { Side::Sell };
+            let mut order = Order::new("TEST".to_string(), side, OrderType::Limit, *price, 10 + i as u32, 1);
+            order.id = i as u64 + 1;
+            orderbook.add_order(Arc::new(RwLock::new(order))).unwrap();
+        }
+
+        for order_id in [3, 5, 9, 1] {
+            orderbook.cancel_order(order_id).unwrap();
+
+            let incremental = orderbook.get_market_depth();
+            orderbook.update_depth();
+            let rebuilt = orderbook.get_market_depth();
+
+            assert_eq!(incremental.bid_levels, rebuilt.bid_levels);
+            assert_eq!(incremental.ask_levels, rebuilt.ask_levels);
+        }
+
+        let depth = orderbook.get_market_depth();
+        assert_eq!(depth.bid_levels, vec![(100, 19), (99, 13), (98, 16)]);
+        assert_eq!(depth.ask_levels, vec![(102, 15), (103, 11), (105, 17)]);
+    }
+
+    #[test]
+    fn test_l3_snapshot_preserves_queue_order() {
+        let mut orderbook = OrderBook::new("TEST");
+
+        let specs = [
+            (Side::Buy, 99, 10, None),
+            (Side::Buy, 100, 20, None),
+            (Side::Buy, 100, 50, Some(5)),
+            (Side::Buy, 98, 10, None),
+            (Side::Sell, 102, 7, None),
+            (Side::Sell, 101, 3, None),
+        ];
+        for (i, (side, price, quantity, display)) in specs.into_iter().enumerate() {
+            let order_type = if display.is_some() { OrderType::Iceberg } else { OrderType::Limit };
+            let mut order = Order::new("TEST".to_string(), side, order_type, price, quantity, 1);
+            order.id = i as u64 + 1;
+            order.display_quantity = display;
+            orderbook.add_order(Arc::new(RwLock::new(order))).unwrap();
+        }
+
+        let l3 = orderbook.get_l3_snapshot(2);
+        assert_eq!(l3.symbol, "TEST");
+
+        let bid_prices: Vec<Price> = l3.bids.iter().map(|l| l.price).collect();
+        assert_eq!(bid_prices, vec![100, 99]);
+        let ask_prices: Vec<Price> = l3.asks.iter().map(|l| l.price).collect();
+        assert_eq!(ask_prices, vec![101, 102]);
+
+        let top = &l3.bids[0].orders;
+        assert_eq!(top.iter().map(|e| e.order.id).collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!((top[0].visible_quantity, top[0].hidden_quantity), (20, 0));
+        assert_eq!((top[1].visible_quantity, top[1].hidden_quantity), (5, 45));
+        assert!(top[0].order.timestamp <= top[1].order.timestamp);
+    }
+}
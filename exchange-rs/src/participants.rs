@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+pub type ParticipantId = u64;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ParticipantError {
+    #[error("Participant {id} already registered")]
+    AlreadyRegistered { id: ParticipantId },
+
+    #[error("Unknown participant {id}")]
+    UnknownParticipant { id: ParticipantId },
+
+    #[error("Participant {parent} cannot be the parent of a {kind:?}")]
+    InvalidParent { parent: ParticipantId, kind: ParticipantKind },
+
+    #[error("Limit exceeded at participant {participant}")]
+    LimitExceeded { participant: ParticipantId },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParticipantKind {
+    Firm,
+    Account,
+    Trader,
+}
+
+impl ParticipantKind {
+    fn parent_kind(self) -> Option<ParticipantKind> {
+        match self {
+            ParticipantKind::Firm => None,
+            ParticipantKind::Account => Some(ParticipantKind::Firm),
+            ParticipantKind::Trader => Some(ParticipantKind::Account),
+        }
+    }
+}
+
+/// Limits that may be set at any level of the hierarchy. Each applies to the
+/// aggregate exposure of the participant and all of its descendants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ParticipantLimits {
+    pub max_notional: Option<u128>,
+    pub max_quantity: Option<u64>,
+}
+
+/// Committed exposure: notional and quantity of accepted orders, less the
+/// unfilled remainder of orders that were cancelled or expired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Exposure {
+    pub notional: u128,
+    pub quantity: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Participant {
+    pub id: ParticipantId,
+    pub kind: ParticipantKind,
+    pub name: String,
+    pub parent: Option<ParticipantId>,
+    pub limits: ParticipantLimits,
+    /// Aggregate over this participant and all of its descendants.
+    pub exposure: Exposure,
+}
+
+/// Firm → account → trader hierarchy. Trader ids are the `user_id` carried
+/// on orders; FIX and other gateway identities map onto traders.
+#[derive(Default)]
+pub struct ParticipantRegistry {
+    participants: HashMap<ParticipantId, Participant>,
+    fix_identities: HashMap<(String, Option<String>), ParticipantId>,
+    identities: HashMap<String, ParticipantId>,
+}
+
+impl ParticipantRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register_firm(&mut self, id: ParticipantId, name: &str) -> Result<(), ParticipantError> {
+        self.register(id, ParticipantKind::Firm, name, None)
+    }
+
+    pub fn register_account(
+        &mut self,
+        id: ParticipantId,
+        name: &str,
+        firm: ParticipantId,
+    ) -> Result<(), ParticipantError> {
+        self.register(id, ParticipantKind::Account, name, Some(firm))
+    }
+
+    pub fn register_trader(
+        &mut self,
+        id: ParticipantId,
+        name: &str,
+        account: ParticipantId,
+    ) -> Result<(), ParticipantError> {
+        self.register(id, ParticipantKind::Trader, name, Some(account))
+    }
+
+    fn register(
+        &mut self,
+        id: ParticipantId,
+        kind: ParticipantKind,
+        name: &str,
+        parent: Option<ParticipantId>,
+    ) -> Result<(), ParticipantError> {
+        if self.participants.contains_key(&id) {
+            return Err(ParticipantError::AlreadyRegistered { id });
+        }
+
+        if let Some(parent_id) = parent {
+            let parent_participant = self
+                .participants
+                .get(&parent_id)
+                .ok_or(ParticipantError::UnknownParticipant { id: parent_id })?;
+
+            if Some(parent_participant.kind) != kind.parent_kind() {
+                return Err(ParticipantError::InvalidParent {
+                    parent: parent_id,
+                    kind,
+                });
+            }
+        }
+
+        self.participants.insert(
+            id,
+            Participant {
+                id,
+                kind,
+                name: name.to_string(),
+                parent,
+                limits: ParticipantLimits::default(),
+                exposure: Exposure::default(),
+            },
+        );
+
+        Ok(())
+    }
+
+    pub fn get(&self, id: ParticipantId) -> Option<&Participant> {
+        self.participants.get(&id)
+    }
+
+    pub fn set_limits(
+        &mut self,
+        id: ParticipantId,
+        limits: ParticipantLimits,
+    ) -> Result<(), ParticipantError> {
+        let participant = self
+            .participants
+            .get_mut(&id)
+            .ok_or(ParticipantError::UnknownParticipant { id })?;
+        participant.limits = limits;
+        Ok(())
+    }
+
+    /// Maps a FIX SenderCompID, optionally qualified by OnBehalfOfCompID, to
+    /// a trader.
+    pub fn map_fix_identity(
+        &mut self,
+        sender_comp_id: &str,
+        on_behalf_of: Option<&str>,
+        trader: ParticipantId,
+    ) -> Result<(), ParticipantError> {
+        self.require_trader(trader)?;
+        let key = (sender_comp_id.to_string(), on_behalf_of.map(str::to_string));
+        self.fix_identities.insert(key, trader);
+        Ok(())
+    }
+
+    /// Resolves a FIX identity, falling back to the SenderCompID mapping when
+    /// no trader is registered for the OnBehalfOfCompID.
+    pub fn resolve_fix_identity(
+        &self,
+        sender_comp_id: &str,
+        on_behalf_of: Option<&str>,
+    ) -> Option<ParticipantId> {
+        let sender = sender_comp_id.to_string();
+        on_behalf_of
+            .and_then(|obo| {
+                self.fix_identities
+                    .get(&(sender.clone(), Some(obo.to_string())))
+            })
+            .or_else(|| self.fix_identities.get(&(sender, None)))
+            .copied()
+    }
+
+    /// Maps a non-FIX login (e.g. a WebSocket API key) to a trader.
+    pub fn map_identity(&mut self, identity: &str, trader: ParticipantId) -> Result<(), ParticipantError> {
+        self.require_trader(trader)?;
+        self.identities.insert(identity.to_string(), trader);
+        Ok(())
+    }
+
+    pub fn resolve_identity(&self, identity: &str) -> Option<ParticipantId> {
+        self.identities.get(identity).copied()
+    }
+
+    fn require_trader(&self, id: ParticipantId) -> Result<(), ParticipantError> {
+        match self.participants.get(&id) {
+            Some(participant) if participant.kind == ParticipantKind::Trader => Ok(()),
+            Some(_) => Err(ParticipantError::InvalidParent {
+                parent: id,
+                kind: ParticipantKind::Trader,
+            }),
+            None => Err(ParticipantError::UnknownParticipant { id }),
+        }
+    }
+
+    /// The participant followed by each ancestor up to its firm.
+    pub fn ancestry(&self, id: ParticipantId) -> Vec<ParticipantId> {
+        let mut chain = Vec::new();
+        let mut current = Some(id);
+
+        while let Some(participant) = current.and_then(|id| self.participants.get(&id)) {
+            chain.push(participant.id);
+            current = participant.parent;
+        }
+
+        chain
+    }
+
+    /// Direct and indirect descendants of `id`, for rolling up per-trader
+    /// figures such as positions.
+    pub fn descendants(&self, id: ParticipantId) -> Vec<ParticipantId> {
+        let mut found = Vec::new();
+        let mut pending = vec![id];
+
+        while let Some(parent) = pending.pop() {
+            let mut children: Vec<ParticipantId> = self
+                .participants
+                .values()
+                .filter(|p| p.parent == Some(parent))
+                .map(|p| p.id)
+                .collect();
+            children.sort_unstable();
+            found.extend(&children);
+            pending.extend(children);
+        }
+
+        found
+    }
+
+    pub fn exposure(&self, id: ParticipantId) -> Option<Exposure> {
+        self.participants.get(&id).map(|p| p.exposure)
+    }
+
+    /// Checks an order against the limits of the trader and every ancestor,
+    /// using each level's aggregate exposure. Unregistered users are not
+    /// subject to participant limits.
+    pub fn check_order(
+        &self,
+        trader: ParticipantId,
+        notional: u128,
+        quantity: u64,
+    ) -> Result<(), ParticipantError> {
+        for id in self.ancestry(trader) {
+            let participant = &self.participants[&id];
+            let limits = participant.limits;
+            let exposure = participant.exposure;
+
+            let notional_breached = limits
+                .max_notional
+                .is_some_and(|max| exposure.notional.saturating_add(notional) > max);
+            let quantity_breached = limits
+                .max_quantity
+                .is_some_and(|max| exposure.quantity.saturating_add(quantity) > max);
+
+            if notional_breached || quantity_breached {
+                return Err(ParticipantError::LimitExceeded { participant: id });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn add_exposure(&mut self, trader: ParticipantId, notional: u128, quantity: u64) {
+        for id in self.ancestry(trader) {
+            let exposure = &mut self.participants.get_mut(&id).unwrap().exposure;
+            exposure.notional = exposure.notional.saturating_add(notional);
+            exposure.quantity = exposure.quantity.saturating_add(quantity);
+        }
+    }
+
+    pub fn release_exposure(&mut self, trader: ParticipantId, notional: u128, quantity: u64) {
+        for id in self.ancestry(trader) {
+            let exposure = &mut self.participants.get_mut(&id).unwrap().exposure;
+            exposure.notional = exposure.notional.saturating_sub(notional);
+            exposure.quantity = exposure.quantity.saturating_sub(quantity);
+        }
+    }
+
+    /// Clears exposure at every level, e.g. at the start of a session.
+    pub fn reset_exposure(&mut self) {
+        for participant in self.participants.values_mut() {
+            participant.exposure = Exposure::default();
+        }
+    }
+}
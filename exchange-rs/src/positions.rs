@@ -0,0 +1,117 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::events::EngineEvent;
+use crate::order::Side;
+use crate::price_utils::{notional, Notional, Price};
+use crate::projection::{OrderOwners, Projection};
+
+/// What a user has traded in one symbol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Position {
+    pub bought: u64,
+    pub sold: u64,
+    pub buy_notional: Notional,
+    pub sell_notional: Notional,
+}
+
+impl Position {
+    /// Quantity bought less quantity sold.
+    pub fn net_quantity(&self) -> i64 {
+        self.bought as i64 - self.sold as i64
+    }
+}
+
+/// Positions by user and symbol, built from trades and busts alone. Keep
+/// it current with `EventSourced<Positions>`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Positions {
+    positions: BTreeMap<u64, BTreeMap<String, Position>>,
+    owners: OrderOwners,
+}
+
+impl Positions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, user_id: u64, symbol: &str) -> Option<&Position> {
+        self.positions.get(&user_id)?.get(symbol)
+    }
+
+    /// A user's positions by symbol.
+    pub fn for_user(&self, user_id: u64) -> impl Iterator<Item = (&str, &Position)> {
+        self.positions
+            .get(&user_id)
+            .into_iter()
+            .flatten()
+            .map(|(symbol, position)| (symbol.as_str(), position))
+    }
+
+    fn value(&self, key: &(u64, String)) -> Position {
+        self.get(key.0, &key.1).copied().unwrap_or_default()
+    }
+
+    fn keys(&self) -> impl Iterator<Item = (u64, String)> + '_ {
+        self.positions.iter().flat_map(|(&user_id, symbols)| {
+            symbols.keys().map(move |symbol| (user_id, symbol.clone()))
+        })
+    }
+
+    /// Adds a fill of `quantity` at `price`, or takes it back if `busted`.
+    fn fill(&mut self, user_id: u64, symbol: &str, side: Side, price: Price, quantity: u32, busted: bool) {
+        let position = self
+            .positions
+            .entry(user_id)
+            .or_default()
+            .entry(symbol.to_string())
+            .or_default();
+        let notional = notional(price, quantity);
+        let (traded, traded_notional) = match side {
+            Side::Buy => (&mut position.bought, &mut position.buy_notional),
+            Side::Sell => (&mut position.sold, &mut position.sell_notional),
+        };
+        if busted {
+            *traded = traded.saturating_sub(quantity as u64);
+            *traded_notional = traded_notional.saturating_sub(notional);
+        } else {
+            *traded += quantity as u64;
+            *traded_notional += notional;
+        }
+    }
+}
+
+impl Projection for Positions {
+    type Key = (u64, String);
+
+    const NAME: &'static str = "positions";
+
+    fn apply(&mut self, event: &EngineEvent) -> Vec<Self::Key> {
+        let (symbol, trade, busted) = match event {
+            EngineEvent::Trade { symbol, trade } => (symbol, trade, false),
+            EngineEvent::TradeBusted(bust) => (&bust.symbol, &bust.trade, true),
+            _ => {
+                self.owners.observe(event);
+                return Vec::new();
+            }
+        };
+
+        let mut changed = Vec::new();
+        for (user_id, side, _) in self.owners.parties(trade) {
+            self.fill(user_id, symbol, side, trade.price, trade.quantity, busted);
+            let key = (user_id, symbol.clone());
+            if !changed.contains(&key) {
+                changed.push(key);
+            }
+        }
+        changed
+    }
+
+    fn differences(&self, other: &Self) -> Vec<Self::Key> {
+        let keys: BTreeSet<_> = self.keys().chain(other.keys()).collect();
+        keys.into_iter()
+            .filter(|key| self.value(key) != other.value(key))
+            .collect()
+    }
+}
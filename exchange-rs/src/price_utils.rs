@@ -1,28 +1,117 @@
 
 
-pub const PRICE_SCALE_FACTOR: u64 = 1_000_000; 
+use thiserror::Error;
+
+pub const PRICE_SCALE_FACTOR: Price = 1_000_000; 
 pub const QUANTITY_SCALE_FACTOR: u32 = 1000; 
 
-pub fn float_to_scaled_price(price: f64) -> Result<u64, String> {
-    if price < 0.0 || !price.is_finite() {
-        return Err(format!("Invalid price: {}", price));
+/// Scaled price, `PRICE_SCALE_FACTOR` units to the whole. Unsigned unless
+/// the `signed-prices` feature is on, which lets spread and basis
+/// instruments quote at or below zero.
+#[cfg(not(feature = "signed-prices"))]
+pub type Price = u64;
+#[cfg(feature = "signed-prices")]
+pub type Price = i64;
+
+/// Whether `Price` goes below zero.
+pub const SIGNED_PRICES: bool = cfg!(feature = "signed-prices");
+
+/// Price times quantity, signed whenever `Price` is.
+#[cfg(not(feature = "signed-prices"))]
+pub type Notional = u128;
+#[cfg(feature = "signed-prices")]
+pub type Notional = i128;
+
+#[derive(Error, Debug, Clone, Copy, PartialEq)]
+pub enum PriceError {
+    #[error("Value is not finite: {0}")]
+    NotFinite(f64),
+
+    #[error("Value is negative: {0}")]
+    Negative(f64),
+
+    #[error("Value does not fit the scaled representation: {0}")]
+    OutOfRange(f64),
+}
+
+/// `value * scale` rounded to the nearest integer, within `min..=max`.
+fn to_scaled(value: f64, scale: f64, min: f64, max: f64) -> Result<f64, PriceError> {
+    if !value.is_finite() {
+        return Err(PriceError::NotFinite(value));
+    }
+    if value < 0.0 && min >= 0.0 {
+        return Err(PriceError::Negative(value));
     }
-    Ok((price * PRICE_SCALE_FACTOR as f64) as u64)
+    let scaled = (value * scale).round();
+    if scaled > max || scaled < min {
+        return Err(PriceError::OutOfRange(value));
+    }
+    Ok(scaled)
+}
+
+/// Fixed-point form of `price`, rounded to the nearest tick of
+/// `1 / PRICE_SCALE_FACTOR` so values like 0.1 that floats cannot hold
+/// exactly land on the intended integer.
+pub fn price_to_scaled(price: f64) -> Result<Price, PriceError> {
+    // Price::MAX is not representable; the largest f64 below it is.
+    let max = (Price::MAX - (Price::MAX >> 53)) as f64;
+    to_scaled(price, PRICE_SCALE_FACTOR as f64, Price::MIN as f64, max).map(|scaled| scaled as Price)
 }
 
-pub fn scaled_price_to_float(price: u64) -> f64 {
+pub fn scaled_to_price(price: Price) -> f64 {
     price as f64 / PRICE_SCALE_FACTOR as f64
 }
 
+/// `price` for formats that carry prices unsigned, or `None` if it is
+/// below zero.
+#[allow(clippy::unnecessary_fallible_conversions, clippy::useless_conversion)]
+pub fn price_to_u64(price: Price) -> Option<u64> {
+    u64::try_from(price).ok()
+}
+
+/// A price read from a format that carries it unsigned, or `None` if it
+/// does not fit `Price`.
+#[allow(clippy::unnecessary_fallible_conversions, clippy::useless_conversion)]
+pub fn price_from_u64(price: u64) -> Option<Price> {
+    Price::try_from(price).ok()
+}
+
+/// Distance of `price` from zero, for sizing fees and limits that do not
+/// care which side of zero a price is on.
+pub fn price_magnitude(price: Price) -> u64 {
+    price.abs_diff(0)
+}
+
+/// `price * quantity`, which is negative for a trade below zero.
+pub fn notional(price: Price, quantity: u32) -> Notional {
+    price as Notional * quantity as Notional
+}
+
+/// Fixed-point form of `quantity`, rounded like `price_to_scaled`.
+pub fn quantity_to_scaled(quantity: f64) -> Result<u32, PriceError> {
+    to_scaled(quantity, QUANTITY_SCALE_FACTOR as f64, 0.0, u32::MAX as f64).map(|scaled| scaled as u32)
+}
+
+pub fn scaled_to_quantity(quantity: u32) -> f64 {
+    quantity as f64 / QUANTITY_SCALE_FACTOR as f64
+}
+
+/// `price_to_scaled` with the error as a message.
+pub fn float_to_scaled_price(price: f64) -> Result<Price, String> {
+    price_to_scaled(price).map_err(|err| err.to_string())
+}
+
+pub fn scaled_price_to_float(price: Price) -> f64 {
+    scaled_to_price(price)
+}
+
+/// `quantity_to_scaled` with the error as a message.
 pub fn float_to_scaled_quantity(quantity: f64) -> Result<u32, String> {
-    if quantity < 0.0 || !quantity.is_finite() {
-        return Err(format!("Invalid quantity: {}", quantity));
-    }
-    Ok((quantity * QUANTITY_SCALE_FACTOR as f64) as u32)
+    quantity_to_scaled(quantity).map_err(|err| err.to_string())
 }
 
 pub fn scaled_quantity_to_float(quantity: u32) -> f64 {
-    quantity as f64 / QUANTITY_SCALE_FACTOR as f64
+    scaled_to_quantity(quantity)
 }
 
 #[cfg(test)]
@@ -51,6 +140,7 @@ mod tests {
 
     #[test]
     fn test_invalid_price() {
+        #[cfg(not(feature = "signed-prices"))]
         assert!(float_to_scaled_price(-1.0).is_err());
         assert!(float_to_scaled_price(f64::INFINITY).is_err());
         assert!(float_to_scaled_price(f64::NAN).is_err());
@@ -62,4 +152,66 @@ mod tests {
         assert!(float_to_scaled_quantity(f64::INFINITY).is_err());
         assert!(float_to_scaled_quantity(f64::NAN).is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_inexact_values_round_to_nearest() {
+        assert_eq!(price_to_scaled(0.1), Ok(100_000));
+        assert_eq!(price_to_scaled(0.3), Ok(300_000));
+        assert_eq!(price_to_scaled(1.1), Ok(1_100_000));
+        assert_eq!(price_to_scaled(0.000_000_4), Ok(0));
+        assert_eq!(price_to_scaled(0.000_000_6), Ok(1));
+        assert_eq!(quantity_to_scaled(0.1), Ok(100));
+        assert_eq!(quantity_to_scaled(1.005), Ok(1005));
+        assert_eq!(quantity_to_scaled(4.35), Ok(4350));
+    }
+
+    #[test]
+    fn test_scaling_rejects_bad_values() {
+        #[cfg(not(feature = "signed-prices"))]
+        assert_eq!(price_to_scaled(-0.1), Err(PriceError::Negative(-0.1)));
+        assert_eq!(price_to_scaled(f64::NEG_INFINITY), Err(PriceError::NotFinite(f64::NEG_INFINITY)));
+        assert!(matches!(price_to_scaled(f64::NAN), Err(PriceError::NotFinite(_))));
+        assert_eq!(price_to_scaled(1e14), Err(PriceError::OutOfRange(1e14)));
+        assert_eq!(quantity_to_scaled(5e6), Err(PriceError::OutOfRange(5e6)));
+        assert_eq!(price_to_scaled(-0.0), Ok(0));
+    }
+
+    #[test]
+    fn test_scaling_range_boundaries() {
+        let max_quantity = u32::MAX as f64 / QUANTITY_SCALE_FACTOR as f64;
+        assert_eq!(quantity_to_scaled(max_quantity), Ok(u32::MAX));
+        assert_eq!(scaled_to_quantity(u32::MAX), max_quantity);
+        #[cfg(not(feature = "signed-prices"))]
+        {
+            assert!(price_to_scaled(1.8e13).is_ok());
+            assert!(price_to_scaled(1.9e13).is_err());
+        }
+        #[cfg(feature = "signed-prices")]
+        {
+            assert!(price_to_scaled(9.2e12).is_ok());
+            assert!(price_to_scaled(-9.2e12).is_ok());
+            assert!(price_to_scaled(9.3e12).is_err());
+            assert!(price_to_scaled(-9.3e12).is_err());
+        }
+        assert_eq!(scaled_to_price(price_to_scaled(123.456789).unwrap()), 123.456789);
+    }
+
+    #[cfg(feature = "signed-prices")]
+    #[test]
+    fn test_negative_prices_scale_both_ways() {
+        assert_eq!(price_to_scaled(-0.1), Ok(-100_000));
+        assert_eq!(price_to_scaled(-2.5), Ok(-2_500_000));
+        assert_eq!(scaled_to_price(-2_500_000), -2.5);
+        assert_eq!(price_to_u64(-1), None);
+        assert_eq!(price_to_u64(7), Some(7));
+        assert_eq!(price_from_u64(u64::MAX), None);
+        assert_eq!(notional(-2_000_000, 3), -6_000_000);
+    }
+
+    #[test]
+    fn test_unsigned_conversions_round_trip() {
+        assert_eq!(price_from_u64(123_456_789), Some(123_456_789));
+        assert_eq!(price_to_u64(123_456_789), Some(123_456_789));
+        assert_eq!(notional(2_000_000, 3), 6_000_000);
+    }
+}
@@ -0,0 +1,359 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{error, warn};
+
+use crate::events::{EngineEvent, SequencedEvent};
+use crate::matching_engine::Trade;
+use crate::order::Side;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ProjectionError {
+    /// An event arrived with a sequence past the next one expected.
+    #[error("Expected event {expected}, got {found}")]
+    Gap { expected: u64, found: u64 },
+    /// The events given to a rebuild do not reach back to the start or to
+    /// the last checkpoint.
+    #[error("Events after {after} through {through} are not available")]
+    Unavailable { after: u64, through: u64 },
+}
+
+/// State derived from the sequenced engine event stream and nothing else.
+pub trait Projection: Default + Clone + Serialize + DeserializeOwned {
+    type Key: Ord + Clone + Debug + Serialize;
+
+    /// Name used in alerts and logs.
+    const NAME: &'static str;
+
+    /// Applies `event`, returning the keys whose value it changed.
+    fn apply(&mut self, event: &EngineEvent) -> Vec<Self::Key>;
+
+    /// Keys whose value differs between the two states, in key order.
+    fn differences(&self, other: &Self) -> Vec<Self::Key>;
+}
+
+/// A projection's state as of an event sequence.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint<P> {
+    pub sequence: u64,
+    pub state: P,
+}
+
+/// Raised when a rebuild from the event stream disagrees with the live
+/// state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProjectionDivergence<K> {
+    pub projection: &'static str,
+    /// First event whose effect on a diverging key the live state does
+    /// not show. A key only ever changed outside the stream is reported at
+    /// the live cursor.
+    pub sequence: u64,
+    pub keys: Vec<K>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RebuildReport<K> {
+    /// Sequence the rebuild started from: 0 when it replayed every event,
+    /// otherwise that of the checkpoint it started from.
+    pub from: u64,
+    /// The live cursor, which the rebuild stopped at.
+    pub through: u64,
+    pub events_applied: u64,
+    /// Set when the live state had drifted and was replaced.
+    pub divergence: Option<ProjectionDivergence<K>>,
+}
+
+/// Keeps a `Projection` current from sequenced engine events. It records
+/// the sequence of the last event applied, checkpoints its state every
+/// `checkpoint_interval` events, optionally to a file, and can rebuild
+/// itself from an event ring or a journal replay and compare the result
+/// with what it holds.
+pub struct EventSourced<P: Projection> {
+    state: P,
+    cursor: u64,
+    /// Sequence of the last event that changed each key.
+    changed_at: BTreeMap<P::Key, u64>,
+    checkpoint_interval: u64,
+    checkpoint: Option<Checkpoint<P>>,
+    checkpoint_path: Option<PathBuf>,
+    subscribers: Vec<Sender<ProjectionDivergence<P::Key>>>,
+}
+
+impl<P: Projection> EventSourced<P> {
+    pub fn new() -> Self {
+        Self {
+            state: P::default(),
+            cursor: 0,
+            changed_at: BTreeMap::new(),
+            checkpoint_interval: 1_000,
+            checkpoint: None,
+            checkpoint_path: None,
+            subscribers: Vec::new(),
+        }
+    }
+
+    pub fn with_checkpoint_interval(mut self, events: u64) -> Self {
+        self.checkpoint_interval = events.max(1);
+        self
+    }
+
+    /// Writes every checkpoint to `path` as JSON.
+    pub fn with_checkpoint_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.checkpoint_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Resumes from the checkpoint at `path`, or starts empty if there is
+    /// none yet. Later checkpoints are written back to `path`. Events after
+    /// the checkpoint are then applied with `catch_up`.
+    pub fn recover(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let mut projection = Self::new().with_checkpoint_path(path);
+        match std::fs::read_to_string(path) {
+            Ok(json) => {
+                let checkpoint: Checkpoint<P> = serde_json::from_str(&json)?;
+                projection.state = checkpoint.state.clone();
+                projection.cursor = checkpoint.sequence;
+                projection.checkpoint = Some(checkpoint);
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        Ok(projection)
+    }
+
+    pub fn state(&self) -> &P {
+        &self.state
+    }
+
+    /// Sequence of the last event applied.
+    pub fn cursor(&self) -> u64 {
+        self.cursor
+    }
+
+    pub fn checkpoint(&self) -> Option<&Checkpoint<P>> {
+        self.checkpoint.as_ref()
+    }
+
+    /// Receives every divergence a rebuild finds.
+    pub fn subscribe(&mut self) -> Receiver<ProjectionDivergence<P::Key>> {
+        let (sender, receiver) = unbounded();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// Applies the next event. Events at or before the cursor were already
+    /// applied and are skipped.
+    pub fn apply(&mut self, event: &SequencedEvent) -> Result<(), ProjectionError> {
+        if event.sequence <= self.cursor {
+            return Ok(());
+        }
+        if event.sequence != self.cursor + 1 {
+            return Err(ProjectionError::Gap {
+                expected: self.cursor + 1,
+                found: event.sequence,
+            });
+        }
+
+        for key in self.state.apply(&event.event) {
+            self.changed_at.insert(key, event.sequence);
+        }
+        self.cursor = event.sequence;
+        if self.cursor.is_multiple_of(self.checkpoint_interval) {
+            self.take_checkpoint();
+        }
+        Ok(())
+    }
+
+    /// Applies every event waiting on `events`. Returns how many there were.
+    pub fn drain(&mut self, events: &Receiver<SequencedEvent>) -> Result<usize, ProjectionError> {
+        let mut drained = 0;
+        for event in events.try_iter() {
+            self.apply(&event)?;
+            drained += 1;
+        }
+        Ok(drained)
+    }
+
+    /// Applies the events after the cursor, as on startup from a ring or a
+    /// journal replay.
+    pub fn catch_up(&mut self, events: &[SequencedEvent]) -> Result<(), ProjectionError> {
+        events.iter().try_for_each(|event| self.apply(event))
+    }
+
+    /// Rebuilds the state from `events` up to the cursor and compares it
+    /// with the live state. The rebuild replays from the first event when
+    /// `events` reach back that far, and from the last checkpoint
+    /// otherwise. If the two differ the divergence is alerted and the
+    /// rebuilt state replaces the live one.
+    pub fn rebuild(
+        &mut self,
+        events: &[SequencedEvent],
+    ) -> Result<RebuildReport<P::Key>, ProjectionError> {
+        let cursor = self.cursor;
+        let covers = |after: u64| {
+            after == cursor
+                || (events.first().is_some_and(|event| event.sequence <= after + 1)
+                    && events.last().is_some_and(|event| event.sequence >= cursor))
+        };
+        let (from, mut rebuilt) = if covers(0) {
+            (0, P::default())
+        } else {
+            match self.checkpoint.as_ref().filter(|checkpoint| covers(checkpoint.sequence)) {
+                Some(checkpoint) => (checkpoint.sequence, checkpoint.state.clone()),
+                None => {
+                    return Err(ProjectionError::Unavailable {
+                        after: self.checkpoint.as_ref().map_or(0, |checkpoint| checkpoint.sequence),
+                        through: cursor,
+                    })
+                }
+            }
+        };
+
+        let mut changes: BTreeMap<P::Key, Vec<u64>> = BTreeMap::new();
+        let mut events_applied = 0;
+        for event in events
+            .iter()
+            .filter(|event| event.sequence > from && event.sequence <= cursor)
+        {
+            for key in rebuilt.apply(&event.event) {
+                changes.entry(key).or_default().push(event.sequence);
+            }
+            events_applied += 1;
+        }
+
+        let keys = self.state.differences(&rebuilt);
+        let divergence = (!keys.is_empty()).then(|| {
+            let sequence = keys
+                .iter()
+                .map(|key| {
+                    let live = self.changed_at.get(key).copied();
+                    changes
+                        .get(key)
+                        .and_then(|sequences| {
+                            sequences
+                                .iter()
+                                .copied()
+                                .find(|&sequence| live.is_none_or(|live| sequence > live))
+                        })
+                        .or(live)
+                        .unwrap_or(cursor)
+                })
+                .min()
+                .unwrap_or(cursor);
+            ProjectionDivergence {
+                projection: P::NAME,
+                sequence,
+                keys,
+            }
+        });
+
+        if let Some(divergence) = &divergence {
+            error!(
+                "{} diverged from the event stream at event {} in {:?}",
+                P::NAME,
+                divergence.sequence,
+                divergence.keys
+            );
+            self.subscribers
+                .retain(|subscriber| subscriber.send(divergence.clone()).is_ok());
+            self.state = rebuilt;
+            for (key, sequences) in changes {
+                self.changed_at.insert(key, *sequences.last().unwrap());
+            }
+        }
+        self.take_checkpoint();
+
+        Ok(RebuildReport {
+            from,
+            through: cursor,
+            events_applied,
+            divergence,
+        })
+    }
+
+    /// Applies `event` to the live state without advancing the cursor, the
+    /// way a consumer that also took updates from outside the stream would.
+    /// Lets tests make the live state drift.
+    #[doc(hidden)]
+    pub fn apply_unsequenced(&mut self, event: &EngineEvent) {
+        self.state.apply(event);
+    }
+
+    fn take_checkpoint(&mut self) {
+        let checkpoint = Checkpoint {
+            sequence: self.cursor,
+            state: self.state.clone(),
+        };
+        if let Some(path) = &self.checkpoint_path {
+            let written = serde_json::to_string(&checkpoint)
+                .map_err(std::io::Error::from)
+                .and_then(|json| std::fs::write(path, json));
+            if let Err(e) = written {
+                warn!(
+                    "Failed to write {} checkpoint to {}: {}",
+                    P::NAME,
+                    path.display(),
+                    e
+                );
+            }
+        }
+        self.checkpoint = Some(checkpoint);
+    }
+}
+
+impl<P: Projection> Default for EventSourced<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Which user placed each order, learned from `OrderAccepted`, since
+/// trades only name order ids. An order that ends without trading is
+/// forgotten, as no trade or bust can name it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderOwners {
+    owners: HashMap<u64, u64>,
+}
+
+impl OrderOwners {
+    pub fn observe(&mut self, event: &EngineEvent) {
+        match event {
+            EngineEvent::OrderAccepted(order) => {
+                self.owners.insert(order.id, order.user_id);
+            }
+            EngineEvent::OrderCancelled(order) | EngineEvent::OrderExpired(order)
+                if order.filled_quantity == 0 =>
+            {
+                self.owners.remove(&order.id);
+            }
+            _ => {}
+        }
+    }
+
+    /// The buyer and seller of `trade`, each with the side it traded on
+    /// and the fee it paid, where the order's owner is known.
+    pub fn parties(&self, trade: &Trade) -> Vec<(u64, Side, u64)> {
+        let fee = |side| {
+            if trade.aggressor == Some(side) {
+                trade.taker_fee
+            } else {
+                trade.maker_fee
+            }
+        };
+        [(trade.buy_order_id, Side::Buy), (trade.sell_order_id, Side::Sell)]
+            .into_iter()
+            .filter_map(|(order_id, side)| {
+                self.owners
+                    .get(&order_id)
+                    .map(|&user_id| (user_id, side, fee(side)))
+            })
+            .collect()
+    }
+}
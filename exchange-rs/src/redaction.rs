@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::Sha256;
+use thiserror::Error;
+
+use crate::journal::EngineJournal;
+
+/// Prefix of every token, so tokenized values are recognisable in exports.
+pub const TOKEN_PREFIX: &str = "tok_";
+
+#[derive(Error, Debug)]
+pub enum RedactionError {
+    #[error("Sink {sink} tokenizes fields but has no tokenization key")]
+    MissingKey { sink: String },
+    #[error("Tokenization key is empty")]
+    EmptyKey,
+    #[error("Failed to serialize record: {0}")]
+    Serialize(#[from] serde_json::Error),
+    #[error("Failed to write to sink: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Kinds of sensitive fields a sink can redact.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldClass {
+    UserId,
+    ClientOrderId,
+    CustomTag,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Redaction {
+    #[default]
+    Keep,
+    /// Replace the value with a keyed HMAC, so records stay joinable on it.
+    Tokenize,
+    Drop,
+}
+
+/// What one sink does with each field class. The default keeps everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RedactionPolicy {
+    actions: HashMap<FieldClass, Redaction>,
+}
+
+impl RedactionPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tokenize(mut self, class: FieldClass) -> Self {
+        self.actions.insert(class, Redaction::Tokenize);
+        self
+    }
+
+    pub fn drop(mut self, class: FieldClass) -> Self {
+        self.actions.insert(class, Redaction::Drop);
+        self
+    }
+
+    pub fn action(&self, class: FieldClass) -> Redaction {
+        self.actions.get(&class).copied().unwrap_or_default()
+    }
+
+    pub fn needs_key(&self) -> bool {
+        self.actions
+            .values()
+            .any(|action| *action == Redaction::Tokenize)
+    }
+}
+
+/// Record field names and the class each belongs to. Fields are matched by
+/// name at any depth of the record.
+#[derive(Debug, Clone)]
+pub struct FieldClasses {
+    fields: HashMap<String, FieldClass>,
+}
+
+impl Default for FieldClasses {
+    fn default() -> Self {
+        let fields = [
+            ("user_id", FieldClass::UserId),
+            ("buy_user_id", FieldClass::UserId),
+            ("sell_user_id", FieldClass::UserId),
+            ("account", FieldClass::UserId),
+            ("sender_comp_id", FieldClass::UserId),
+            ("target_comp_id", FieldClass::UserId),
+            ("client_order_id", FieldClass::ClientOrderId),
+            ("cl_ord_id", FieldClass::ClientOrderId),
+            ("orig_cl_ord_id", FieldClass::ClientOrderId),
+            ("tags", FieldClass::CustomTag),
+            ("custom_tags", FieldClass::CustomTag),
+        ];
+        Self {
+            fields: fields
+                .into_iter()
+                .map(|(name, class)| (name.to_string(), class))
+                .collect(),
+        }
+    }
+}
+
+impl FieldClasses {
+    pub fn with_field(mut self, name: &str, class: FieldClass) -> Self {
+        self.fields.insert(name.to_string(), class);
+        self
+    }
+
+    pub fn class_of(&self, name: &str) -> Option<FieldClass> {
+        self.fields.get(name).copied()
+    }
+}
+
+/// Secret for tokenization. Kept apart from the exports it protects, so a
+/// recipient cannot reverse or recompute tokens.
+#[derive(Clone)]
+pub struct TokenizationKey(Vec<u8>);
+
+impl TokenizationKey {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, RedactionError> {
+        if bytes.is_empty() {
+            return Err(RedactionError::EmptyKey);
+        }
+        Ok(Self(bytes.to_vec()))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RedactionError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Stable token for `value`: the same value and key always give the
+    /// same token.
+    pub fn tokenize(&self, value: &str) -> String {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.0).expect("HMAC accepts keys of any length");
+        mac.update(value.as_bytes());
+        let digest = mac.finalize().into_bytes();
+        let mut token = String::with_capacity(TOKEN_PREFIX.len() + digest.len() * 2);
+        token.push_str(TOKEN_PREFIX);
+        for byte in digest {
+            token.push_str(&format!("{:02x}", byte));
+        }
+        token
+    }
+}
+
+impl fmt::Debug for TokenizationKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TokenizationKey(..)")
+    }
+}
+
+/// Applies a sink's policy to serialized records.
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    policy: RedactionPolicy,
+    classes: FieldClasses,
+    key: Option<TokenizationKey>,
+}
+
+impl Redactor {
+    /// Fails if the policy tokenizes a class but no key is given.
+    pub fn new(
+        sink: &str,
+        policy: RedactionPolicy,
+        classes: FieldClasses,
+        key: Option<TokenizationKey>,
+    ) -> Result<Self, RedactionError> {
+        if policy.needs_key() && key.is_none() {
+            return Err(RedactionError::MissingKey {
+                sink: sink.to_string(),
+            });
+        }
+        Ok(Self {
+            policy,
+            classes,
+            key,
+        })
+    }
+
+    pub fn redact(&self, value: &mut Value) {
+        match value {
+            Value::Object(fields) => {
+                fields.retain(|name, _| self.action_for(name) != Redaction::Drop);
+                for (name, field) in fields.iter_mut() {
+                    match self.action_for(name) {
+                        Redaction::Tokenize => self.tokenize(field),
+                        _ => self.redact(field),
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact(item)),
+            _ => {}
+        }
+    }
+
+    fn action_for(&self, name: &str) -> Redaction {
+        self.classes
+            .class_of(name)
+            .map_or(Redaction::Keep, |class| self.policy.action(class))
+    }
+
+    /// Tokenizes every scalar in `value`. Nulls stay null.
+    fn tokenize(&self, value: &mut Value) {
+        let Some(key) = &self.key else {
+            unreachable!("Redactor::new requires a key for tokenizing policies");
+        };
+        match value {
+            Value::Null => {}
+            Value::String(raw) => *value = Value::String(key.tokenize(raw)),
+            Value::Bool(_) | Value::Number(_) => {
+                *value = Value::String(key.tokenize(&value.to_string()))
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.tokenize(item)),
+            Value::Object(fields) => fields.values_mut().for_each(|field| self.tokenize(field)),
+        }
+    }
+}
+
+/// Writes records as JSON lines. A complete sink writes them unchanged, as
+/// the live audit files do; an export sink redacts them first.
+pub struct AuditSink<W: Write> {
+    name: String,
+    writer: W,
+    redactor: Option<Redactor>,
+}
+
+impl<W: Write> AuditSink<W> {
+    pub fn complete(name: &str, writer: W) -> Self {
+        Self {
+            name: name.to_string(),
+            writer,
+            redactor: None,
+        }
+    }
+
+    pub fn export(
+        name: &str,
+        writer: W,
+        policy: RedactionPolicy,
+        key: Option<TokenizationKey>,
+    ) -> Result<Self, RedactionError> {
+        Self::export_with_classes(name, writer, policy, FieldClasses::default(), key)
+    }
+
+    pub fn export_with_classes(
+        name: &str,
+        writer: W,
+        policy: RedactionPolicy,
+        classes: FieldClasses,
+        key: Option<TokenizationKey>,
+    ) -> Result<Self, RedactionError> {
+        let redactor = Redactor::new(name, policy, classes, key)?;
+        Ok(Self {
+            name: name.to_string(),
+            writer,
+            redactor: Some(redactor),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn write_record<T: Serialize>(&mut self, record: &T) -> Result<(), RedactionError> {
+        let mut value = serde_json::to_value(record)?;
+        if let Some(redactor) = &self.redactor {
+            redactor.redact(&mut value);
+        }
+        serde_json::to_writer(&mut self.writer, &value)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    /// Writes every journal entry in sequence order.
+    pub fn write_journal(&mut self, journal: &EngineJournal) -> Result<(), RedactionError> {
+        for entry in journal.entries() {
+            self.write_record(entry)?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
@@ -0,0 +1,749 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use parking_lot::{Condvar, Mutex};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::clock::{ManualClock, SharedClock};
+use crate::fix::FixError;
+use crate::fix_gateway::FixGateway;
+use crate::journal::{JournalEntry, JournalError};
+use crate::matching_engine::{EngineDigest, MatchingEngine, MatchingEngineConfig};
+
+/// How often idle loops check for shutdown.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+const RECONNECT_MIN: Duration = Duration::from_millis(20);
+const RECONNECT_MAX: Duration = Duration::from_secs(2);
+
+#[derive(Error, Debug)]
+pub enum ReplicationError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Malformed record: {0}")]
+    Decode(#[from] serde_json::Error),
+
+    #[error("Sequence gap: expected {expected}, received {received}")]
+    SequenceGap { expected: u64, received: u64 },
+
+    #[error("Follower behind: applied {applied}, required {required}")]
+    Behind { applied: u64, required: u64 },
+
+    #[error("Manifest at sequence {manifest} is older than the applied sequence {applied}")]
+    StaleManifest { manifest: u64, applied: u64 },
+
+    #[error("Digest mismatch at sequence {sequence} in {components:?}")]
+    DigestMismatch {
+        sequence: u64,
+        components: Vec<String>,
+    },
+
+    #[error("Engine journal is at sequence {engine}, log is at {log}")]
+    LogMismatch { engine: u64, log: u64 },
+
+    #[error("Replay failed: {0}")]
+    Journal(#[from] JournalError),
+}
+
+/// One line of the replication log, or of the stream that ships it.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum ReplicationRecord {
+    /// A command as the primary journaled it, stamped with the primary's
+    /// clock.
+    Entry(Box<JournalEntry>),
+    /// Digest of the primary's state once it had applied a sequence.
+    Checkpoint(ReplicationManifest),
+    /// Where the primary's log ends. Sent on connecting and whenever the log
+    /// is idle, and never written to the log.
+    Heartbeat { sequence: u64, timestamp: i64 },
+}
+
+impl ReplicationRecord {
+    /// Whether a follower that applied `sequence` still needs the record.
+    /// A checkpoint at that very sequence is still worth checking.
+    fn follows(&self, sequence: u64) -> bool {
+        match self {
+            ReplicationRecord::Entry(entry) => entry.sequence > sequence,
+            ReplicationRecord::Checkpoint(manifest) => manifest.sequence >= sequence,
+            ReplicationRecord::Heartbeat { .. } => true,
+        }
+    }
+}
+
+/// First line a follower sends: the last sequence it applied.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ResumeRequest {
+    pub after: u64,
+}
+
+/// Digest of the primary's state at a sequence. A follower must match it
+/// before it may be promoted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplicationManifest {
+    pub sequence: u64,
+    pub digest: EngineDigest,
+}
+
+fn write_record<W: Write>(writer: &mut W, record: &ReplicationRecord) -> Result<(), ReplicationError> {
+    let mut line = serde_json::to_vec(record)?;
+    line.push(b'\n');
+    writer.write_all(&line)?;
+    Ok(())
+}
+
+#[derive(Default)]
+struct LogHead {
+    /// Complete lines in the log.
+    records: u64,
+    sequence: u64,
+    /// Primary clock reading of the last entry.
+    timestamp: i64,
+}
+
+impl LogHead {
+    fn observe(&mut self, record: &ReplicationRecord) {
+        self.records += 1;
+        if let ReplicationRecord::Entry(entry) = record {
+            self.sequence = entry.sequence;
+            self.timestamp = entry.timestamp;
+        }
+    }
+}
+
+struct LogState {
+    head: Mutex<LogHead>,
+    appended: Condvar,
+}
+
+/// Append-only file of replication records, one JSON object per line.
+/// Each append reaches the disk before readers are told about it, so a
+/// follower is never sent a record the primary could lose in a crash.
+pub struct ReplicationLog {
+    file: File,
+    source: LogSource,
+}
+
+impl ReplicationLog {
+    /// Opens the log at `path`, creating it if needed. A record torn by a
+    /// crash mid-append is cut off.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ReplicationError> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)?;
+
+        let mut head = LogHead::default();
+        let mut complete = 0;
+        let mut reader = BufReader::new(&file);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            let read = reader.read_until(b'\n', &mut line)?;
+            if read == 0 || line.last() != Some(&b'\n') {
+                break;
+            }
+            head.observe(&serde_json::from_slice(&line)?);
+            complete += read as u64;
+        }
+        file.set_len(complete)?;
+
+        Ok(Self {
+            file,
+            source: LogSource {
+                path,
+                state: Arc::new(LogState {
+                    head: Mutex::new(head),
+                    appended: Condvar::new(),
+                }),
+            },
+        })
+    }
+
+    pub fn last_sequence(&self) -> u64 {
+        self.source.last_sequence()
+    }
+
+    /// Handle for streaming the log from other threads.
+    pub fn source(&self) -> LogSource {
+        self.source.clone()
+    }
+
+    /// Appends and syncs `record`. Entries must continue the log's
+    /// sequence.
+    pub fn append(&mut self, record: &ReplicationRecord) -> Result<(), ReplicationError> {
+        if let ReplicationRecord::Entry(entry) = record {
+            let expected = self.last_sequence() + 1;
+            if entry.sequence != expected {
+                return Err(ReplicationError::SequenceGap {
+                    expected,
+                    received: entry.sequence,
+                });
+            }
+        }
+
+        write_record(&mut self.file, record)?;
+        self.file.sync_data()?;
+
+        self.source.state.head.lock().observe(record);
+        self.source.state.appended.notify_all();
+        Ok(())
+    }
+}
+
+/// Path and end of a replication log, shared with the threads streaming it.
+#[derive(Clone)]
+pub struct LogSource {
+    path: PathBuf,
+    state: Arc<LogState>,
+}
+
+impl LogSource {
+    pub fn last_sequence(&self) -> u64 {
+        self.state.head.lock().sequence
+    }
+
+    fn heartbeat(&self) -> ReplicationRecord {
+        let head = self.state.head.lock();
+        ReplicationRecord::Heartbeat {
+            sequence: head.sequence,
+            timestamp: head.timestamp,
+        }
+    }
+
+    /// Reader of the records a follower that applied `after` still needs,
+    /// including those appended later.
+    pub fn tail(&self, after: u64) -> Result<LogTail, ReplicationError> {
+        Ok(LogTail {
+            reader: BufReader::new(File::open(&self.path)?),
+            line: Vec::new(),
+            read: 0,
+            after,
+            state: Arc::clone(&self.state),
+        })
+    }
+}
+
+pub struct LogTail {
+    reader: BufReader<File>,
+    line: Vec<u8>,
+    /// Lines read so far.
+    read: u64,
+    after: u64,
+    state: Arc<LogState>,
+}
+
+impl LogTail {
+    /// Next record the follower needs, waiting up to `timeout` for one to
+    /// be appended.
+    pub fn next(&mut self, timeout: Duration) -> Result<Option<ReplicationRecord>, ReplicationError> {
+        let mut waited = false;
+        loop {
+            // Only lines the log has announced are read, and those are
+            // complete on disk.
+            while self.read < self.state.head.lock().records {
+                self.line.clear();
+                self.reader.read_until(b'\n', &mut self.line)?;
+                self.read += 1;
+                let record: ReplicationRecord = serde_json::from_slice(&self.line)?;
+                if record.follows(self.after) {
+                    return Ok(Some(record));
+                }
+            }
+            if waited {
+                return Ok(None);
+            }
+
+            let mut head = self.state.head.lock();
+            if head.records == self.read {
+                self.state.appended.wait_for(&mut head, timeout);
+            }
+            waited = true;
+        }
+    }
+}
+
+/// An engine whose journal is shipped to followers through a durable log.
+/// The journal keeps only what the log has not taken yet.
+pub struct ReplicationPrimary {
+    engine: MatchingEngine,
+    log: ReplicationLog,
+}
+
+impl ReplicationPrimary {
+    /// Journals `engine` into `log`. The engine must be at the log's
+    /// sequence, as a fresh engine is at a fresh log's.
+    pub fn new(mut engine: MatchingEngine, log: ReplicationLog) -> Result<Self, ReplicationError> {
+        engine.enable_journal();
+        let sequence = engine.journal().map_or(0, |journal| journal.last_sequence());
+        if sequence != log.last_sequence() {
+            return Err(ReplicationError::LogMismatch {
+                engine: sequence,
+                log: log.last_sequence(),
+            });
+        }
+        Ok(Self { engine, log })
+    }
+
+    pub fn engine(&self) -> &MatchingEngine {
+        &self.engine
+    }
+
+    pub fn last_sequence(&self) -> u64 {
+        self.log.last_sequence()
+    }
+
+    pub fn source(&self) -> LogSource {
+        self.log.source()
+    }
+
+    /// Runs `command` against the engine and logs what it journaled before
+    /// returning. On an error the entries stay in the journal, the log is
+    /// behind the engine, and the next command retries them.
+    pub fn execute<T>(
+        &mut self,
+        command: impl FnOnce(&mut MatchingEngine) -> T,
+    ) -> Result<T, ReplicationError> {
+        let output = command(&mut self.engine);
+        self.ship()?;
+        Ok(output)
+    }
+
+    fn ship(&mut self) -> Result<(), ReplicationError> {
+        let logged = self.log.last_sequence();
+        let Some(journal) = self.engine.journal_mut() else {
+            return Ok(());
+        };
+        for entry in journal.entries_after(logged) {
+            self.log.append(&ReplicationRecord::Entry(Box::new(entry.clone())))?;
+        }
+        journal.compact(self.log.last_sequence());
+        Ok(())
+    }
+
+    pub fn manifest(&self) -> ReplicationManifest {
+        ReplicationManifest {
+            sequence: self.last_sequence(),
+            digest: self.engine.state_digest(),
+        }
+    }
+
+    /// Logs the current manifest so followers check their state against it
+    /// as they reach its sequence.
+    pub fn checkpoint(&mut self) -> Result<ReplicationManifest, ReplicationError> {
+        let manifest = self.manifest();
+        self.log.append(&ReplicationRecord::Checkpoint(manifest.clone()))?;
+        Ok(manifest)
+    }
+}
+
+/// Streams a replication log to followers over TCP. A connection starts
+/// after the sequence the follower asks for and stays open, sending
+/// records as they are appended and a heartbeat while the log is idle.
+pub struct ReplicationServer {
+    address: SocketAddr,
+    stop: Arc<AtomicBool>,
+    acceptor: Option<JoinHandle<()>>,
+}
+
+impl ReplicationServer {
+    pub fn spawn(listener: TcpListener, source: LogSource, heartbeat: Duration) -> io::Result<Self> {
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let acceptor = {
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || accept_followers(listener, source, heartbeat, stop))
+        };
+        Ok(Self {
+            address,
+            stop,
+            acceptor: Some(acceptor),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Stops accepting and closes every follower connection.
+    pub fn shutdown(self) {}
+}
+
+impl Drop for ReplicationServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(acceptor) = self.acceptor.take() {
+            if acceptor.join().is_err() {
+                warn!("Replication acceptor panicked");
+            }
+        }
+    }
+}
+
+fn accept_followers(listener: TcpListener, source: LogSource, heartbeat: Duration, stop: Arc<AtomicBool>) {
+    let mut connections = Vec::new();
+    while !stop.load(Ordering::Acquire) {
+        match listener.accept() {
+            Ok((stream, peer)) => {
+                info!("Follower connected from {}", peer);
+                let source = source.clone();
+                let stop = Arc::clone(&stop);
+                connections.push(thread::spawn(move || {
+                    if let Err(e) = stream_log(stream, &source, heartbeat, &stop) {
+                        warn!("Replication stream to {} ended: {}", peer, e);
+                    }
+                }));
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+            Err(e) => {
+                warn!("Failed to accept follower: {}", e);
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+        connections.retain(|connection: &JoinHandle<()>| !connection.is_finished());
+    }
+
+    for connection in connections {
+        if connection.join().is_err() {
+            warn!("Replication stream panicked");
+        }
+    }
+}
+
+fn stream_log(
+    stream: TcpStream,
+    source: &LogSource,
+    heartbeat: Duration,
+    stop: &AtomicBool,
+) -> Result<(), ReplicationError> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(RECONNECT_MAX))?;
+    let mut request = String::new();
+    BufReader::new(stream.try_clone()?).read_line(&mut request)?;
+    let request: ResumeRequest = serde_json::from_str(&request)?;
+
+    let mut tail = source.tail(request.after)?;
+    let mut writer = BufWriter::new(stream);
+    write_record(&mut writer, &source.heartbeat())?;
+    writer.flush()?;
+
+    while !stop.load(Ordering::Acquire) {
+        let record = match tail.next(heartbeat)? {
+            Some(record) => record,
+            None => source.heartbeat(),
+        };
+        write_record(&mut writer, &record)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplicationMetrics {
+    pub applied_sequence: u64,
+    /// Highest sequence the primary is known to have logged.
+    pub primary_sequence: u64,
+    /// Primary clock reading of the last applied entry.
+    pub applied_timestamp: i64,
+    /// Primary clock reading of the last entry it is known to have logged.
+    pub primary_timestamp: i64,
+    pub records_applied: u64,
+    pub checkpoints_verified: u64,
+    pub connects: u64,
+    /// Connections lost or refused.
+    pub disconnects: u64,
+}
+
+impl ReplicationMetrics {
+    /// Entries the primary has logged that are not applied yet.
+    pub fn lag(&self) -> u64 {
+        self.primary_sequence.saturating_sub(self.applied_sequence)
+    }
+
+    /// How far the applied state trails the primary, in primary clock
+    /// time.
+    pub fn lag_nanos(&self) -> i64 {
+        if self.lag() == 0 {
+            return 0;
+        }
+        (self.primary_timestamp - self.applied_timestamp).max(0)
+    }
+
+    fn observe_primary(&mut self, sequence: u64, timestamp: i64) {
+        if sequence >= self.primary_sequence {
+            self.primary_sequence = sequence;
+            self.primary_timestamp = self.primary_timestamp.max(timestamp);
+        }
+    }
+}
+
+/// An engine rebuilt from a primary's log. Commands run against a clock
+/// pinned to the primary's timestamps, so the follower stamps orders and
+/// trades exactly as the primary did.
+pub struct ReplicationFollower {
+    engine: MatchingEngine,
+    clock: Arc<ManualClock>,
+    /// Shared with the handle of a follower running on its own thread.
+    metrics: Arc<Mutex<ReplicationMetrics>>,
+}
+
+impl ReplicationFollower {
+    pub fn new(config: MatchingEngineConfig) -> Self {
+        let clock = Arc::new(ManualClock::default());
+        let mut engine = MatchingEngine::with_clock(config, clock.clone());
+        engine.enable_journal();
+        Self {
+            engine,
+            clock,
+            metrics: Arc::new(Mutex::new(ReplicationMetrics::default())),
+        }
+    }
+
+    pub fn engine(&self) -> &MatchingEngine {
+        &self.engine
+    }
+
+    pub fn last_applied(&self) -> u64 {
+        self.metrics.lock().applied_sequence
+    }
+
+    pub fn metrics(&self) -> ReplicationMetrics {
+        self.metrics.lock().clone()
+    }
+
+    /// Applies a record in sequence. Entries already applied are skipped
+    /// so a resumed stream may overlap; one past the next expected sequence
+    /// is a gap and leaves the follower untouched. A command that does not
+    /// replay as the primary journaled it is a divergence.
+    pub fn apply(&mut self, record: &ReplicationRecord) -> Result<(), ReplicationError> {
+        let applied = self.last_applied();
+        match record {
+            ReplicationRecord::Entry(entry) => {
+                if entry.sequence <= applied {
+                    return Ok(());
+                }
+                if entry.sequence > applied + 1 {
+                    return Err(ReplicationError::SequenceGap {
+                        expected: applied + 1,
+                        received: entry.sequence,
+                    });
+                }
+
+                self.engine
+                    .replay_entries(&self.clock, std::slice::from_ref(&**entry))?;
+                if let Some(journal) = self.engine.journal_mut() {
+                    journal.compact(entry.sequence);
+                }
+
+                let mut metrics = self.metrics.lock();
+                metrics.applied_sequence = entry.sequence;
+                metrics.applied_timestamp = entry.timestamp;
+                metrics.records_applied += 1;
+                metrics.observe_primary(entry.sequence, entry.timestamp);
+            }
+            ReplicationRecord::Checkpoint(manifest) => {
+                if manifest.sequence > applied {
+                    return Err(ReplicationError::SequenceGap {
+                        expected: applied + 1,
+                        received: manifest.sequence,
+                    });
+                }
+                if manifest.sequence == applied {
+                    self.verify(manifest)?;
+                    self.metrics.lock().checkpoints_verified += 1;
+                }
+            }
+            ReplicationRecord::Heartbeat {
+                sequence,
+                timestamp,
+            } => self.metrics.lock().observe_primary(*sequence, *timestamp),
+        }
+        Ok(())
+    }
+
+    fn verify(&self, manifest: &ReplicationManifest) -> Result<(), ReplicationError> {
+        let components = self.engine.state_digest().differences(&manifest.digest);
+        if components.is_empty() {
+            Ok(())
+        } else {
+            Err(ReplicationError::DigestMismatch {
+                sequence: manifest.sequence,
+                components,
+            })
+        }
+    }
+
+    /// Applies newline-delimited records until the reader is exhausted and
+    /// returns how many entries were applied.
+    pub fn read_from<R: BufRead>(&mut self, reader: R) -> Result<usize, ReplicationError> {
+        let before = self.metrics.lock().records_applied;
+
+        for line in reader.lines() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            self.apply(&serde_json::from_str(&line)?)?;
+        }
+
+        Ok((self.metrics.lock().records_applied - before) as usize)
+    }
+
+    /// Follows the primary at `address` until `stop` is set. A lost
+    /// connection is retried with backoff and resumes after the last
+    /// applied sequence. Any other error ends following, since the
+    /// follower's state can no longer be trusted.
+    pub fn follow(&mut self, address: SocketAddr, stop: &AtomicBool) -> Result<(), ReplicationError> {
+        let mut backoff = RECONNECT_MIN;
+        while !stop.load(Ordering::Acquire) {
+            let outcome = match TcpStream::connect(address) {
+                Ok(stream) => {
+                    self.metrics.lock().connects += 1;
+                    backoff = RECONNECT_MIN;
+                    self.follow_connection(stream, stop)
+                }
+                Err(e) => Err(e.into()),
+            };
+            match outcome {
+                Ok(()) => {}
+                Err(ReplicationError::Io(e)) => {
+                    warn!("Replication connection to {} lost: {}", address, e);
+                    self.metrics.lock().disconnects += 1;
+                    thread::sleep(backoff);
+                    backoff = (backoff * 2).min(RECONNECT_MAX);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Follows the primary at `address` on a thread of its own.
+    pub fn spawn(mut self, address: SocketAddr) -> FollowerHandle {
+        let stop = Arc::new(AtomicBool::new(false));
+        let metrics = Arc::clone(&self.metrics);
+        let thread = {
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || self.follow(address, &stop).map(|()| self))
+        };
+        FollowerHandle {
+            stop,
+            metrics,
+            thread,
+        }
+    }
+
+    fn follow_connection(&mut self, stream: TcpStream, stop: &AtomicBool) -> Result<(), ReplicationError> {
+        stream.set_read_timeout(Some(POLL_INTERVAL))?;
+        let mut writer = stream.try_clone()?;
+        let request = ResumeRequest {
+            after: self.last_applied(),
+        };
+        writer.write_all(&serde_json::to_vec(&request)?)?;
+        writer.write_all(b"\n")?;
+
+        let mut reader = BufReader::new(stream);
+        let mut line = Vec::new();
+        while !stop.load(Ordering::Acquire) {
+            // A timed-out read keeps what it got, so a line may arrive in
+            // pieces.
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => {
+                    return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+                }
+                Ok(_) if line.last() == Some(&b'\n') => {
+                    self.apply(&serde_json::from_slice(&line)?)?;
+                    line.clear();
+                }
+                Ok(_) => {}
+                Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Takes over as primary once the follower has applied exactly
+    /// `manifest`'s sequence and its state digest agrees. The engine moves
+    /// onto `clock` and a FIX gateway accepts orders on each listener. Must
+    /// be called within a Tokio runtime.
+    pub fn promote(
+        self,
+        manifest: &ReplicationManifest,
+        clock: SharedClock,
+        listeners: Vec<tokio::net::TcpListener>,
+    ) -> Result<PromotedPrimary, ReplicationError> {
+        let applied = self.last_applied();
+        if applied < manifest.sequence {
+            return Err(ReplicationError::Behind {
+                applied,
+                required: manifest.sequence,
+            });
+        }
+        if applied > manifest.sequence {
+            return Err(ReplicationError::StaleManifest {
+                manifest: manifest.sequence,
+                applied,
+            });
+        }
+        self.verify(manifest)?;
+
+        let mut engine = self.engine;
+        engine.set_clock(clock);
+        let engine = Arc::new(Mutex::new(engine));
+        let gateway = Arc::new(FixGateway::new(Arc::clone(&engine)));
+        let acceptors = listeners
+            .into_iter()
+            .map(|listener| {
+                let gateway = Arc::clone(&gateway);
+                tokio::spawn(async move { gateway.serve(listener).await })
+            })
+            .collect();
+
+        Ok(PromotedPrimary { engine, acceptors })
+    }
+}
+
+/// A promoted follower: the engine and the tasks accepting FIX
+/// connections for it.
+pub struct PromotedPrimary {
+    pub engine: Arc<Mutex<MatchingEngine>>,
+    pub acceptors: Vec<tokio::task::JoinHandle<Result<(), FixError>>>,
+}
+
+/// A follower running on its own thread.
+pub struct FollowerHandle {
+    stop: Arc<AtomicBool>,
+    metrics: Arc<Mutex<ReplicationMetrics>>,
+    thread: JoinHandle<Result<ReplicationFollower, ReplicationError>>,
+}
+
+impl FollowerHandle {
+    pub fn metrics(&self) -> ReplicationMetrics {
+        self.metrics.lock().clone()
+    }
+
+    /// Whether following ended on its own, which only an error does.
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Stops following and hands the follower back, or the error that
+    /// ended following.
+    pub fn stop(self) -> Result<ReplicationFollower, ReplicationError> {
+        self.stop.store(true, Ordering::Release);
+        self.thread
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    }
+}
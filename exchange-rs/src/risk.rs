@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::price_utils::{price_magnitude, Price, PRICE_SCALE_FACTOR};
+
+/// Pre-trade limits checked before the engine accepts an order. Unset
+/// limits are not enforced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct RiskLimits {
+    pub max_order_quantity: Option<u32>,
+    /// Largest `price * quantity` of a single order, in whole units of
+    /// currency (prices carry `PRICE_SCALE_FACTOR`).
+    pub max_order_notional: Option<u64>,
+    /// Resting and stop orders a user may have open at once, across all
+    /// symbols.
+    pub max_open_orders: Option<usize>,
+}
+
+impl RiskLimits {
+    /// Whether `price * quantity` is within `max_order_notional`, either
+    /// side of zero.
+    pub fn notional_within_limit(&self, price: Price, quantity: u32) -> bool {
+        self.max_order_notional.is_none_or(|limit| {
+            price_magnitude(price) as u128 * quantity as u128 <= limit as u128 * PRICE_SCALE_FACTOR as u128
+        })
+    }
+}
+
+/// Open orders per user. Every book of an engine shares one instance and
+/// keeps it in step with its user index, so a user's count is a lookup.
+#[derive(Debug, Clone, Default)]
+pub struct OpenOrderCounts(Arc<Mutex<HashMap<u64, usize>>>);
+
+impl OpenOrderCounts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, user_id: u64) -> usize {
+        self.0.lock().get(&user_id).copied().unwrap_or(0)
+    }
+
+    pub(crate) fn add(&self, user_id: u64, count: usize) {
+        if count > 0 {
+            *self.0.lock().entry(user_id).or_default() += count;
+        }
+    }
+
+    pub(crate) fn remove(&self, user_id: u64) {
+        let mut counts = self.0.lock();
+        if let Some(count) = counts.get_mut(&user_id) {
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(&user_id);
+            }
+        }
+    }
+}
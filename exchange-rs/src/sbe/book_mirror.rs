@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+use thiserror::Error;
+use tracing::{debug, warn};
+
+use crate::order::{Order, OrderType, Side};
+use crate::orderbook::{MarketDepth, OrderBook};
+use crate::price_utils::{price_to_scaled, quantity_to_scaled, Price, PriceError};
+use crate::sbe::parser::{BookMessage, SbeMessage};
+use crate::sbe::snapshot_assembler::{OrderBookSnapshot, SnapshotAssembler, SnapshotAssemblyError};
+use crate::sbe::BookChange as ChangeKind;
+use crate::top_of_book::TopOfBook;
+
+/// User id of the synthetic orders that stand for mirrored levels.
+pub const MIRROR_USER_ID: u64 = 0;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MirrorError {
+    /// A change did not follow on from the last one applied. The mirror
+    /// drops changes until the next snapshot seeds it again.
+    #[error("Book {instrument_id} needs a resync: expected prev_change_id {expected}, got {got}")]
+    ResyncNeeded {
+        instrument_id: u32,
+        expected: u64,
+        got: u64,
+    },
+    /// A change named a level the mirror does not hold, or created one it
+    /// already does. The mirror needs a snapshot as after a gap.
+    #[error("Book {instrument_id} change {change_id} does not fit the mirrored book at {price} on the {side:?} side")]
+    Inconsistent {
+        instrument_id: u32,
+        change_id: u64,
+        side: Side,
+        price: Price,
+    },
+    #[error("Invalid side value: {0}")]
+    InvalidSide(u8),
+    #[error("Invalid book change value: {0}")]
+    InvalidChange(u8),
+    #[error("Price conversion error: {0}")]
+    PriceConversion(String),
+    #[error(transparent)]
+    Snapshot(#[from] SnapshotAssemblyError),
+}
+
+impl From<PriceError> for MirrorError {
+    fn from(err: PriceError) -> Self {
+        MirrorError::PriceConversion(err.to_string())
+    }
+}
+
+/// Mirrors one Deribit instrument's book in an `OrderBook`. A snapshot
+/// seeds it with one synthetic order per price level; `Book` messages
+/// then create, change and delete levels by price, each continuing from
+/// the last `change_id` applied. The book answers depth and top-of-book
+/// queries like a native one.
+pub struct BookMirror {
+    instrument_id: u32,
+    symbol: String,
+    book: OrderBook,
+    /// Synthetic order standing for each level, by side and scaled price.
+    levels: HashMap<(Side, Price), u64>,
+    next_order_id: u64,
+    assembler: SnapshotAssembler,
+    /// Last change applied, or `None` until a snapshot seeds the book.
+    change_id: Option<u64>,
+    /// Whether the last `Book` message was one part of a larger change,
+    /// whose later parts carry the same `change_id`.
+    change_open: bool,
+}
+
+impl BookMirror {
+    pub fn new(instrument_id: u32, symbol: &str) -> Self {
+        Self {
+            instrument_id,
+            symbol: symbol.to_string(),
+            book: OrderBook::new(symbol),
+            levels: HashMap::new(),
+            next_order_id: 1,
+            assembler: SnapshotAssembler::new(),
+            change_id: None,
+            change_open: false,
+        }
+    }
+
+    /// Feeds one message off the feed. Snapshot cycles are reassembled and
+    /// seed the book once this instrument's is complete; `Book` messages
+    /// for it are applied. Other messages are ignored. Returns whether the
+    /// message changed the book.
+    pub fn process(&mut self, message: &SbeMessage) -> Result<bool, MirrorError> {
+        match message {
+            SbeMessage::Book(msg) if msg.instrument_id == self.instrument_id => {
+                self.apply_book(msg)
+            }
+            SbeMessage::SnapshotStart(_) | SbeMessage::Snapshot(_) | SbeMessage::SnapshotEnd(_) => {
+                match self.assembler.process(message)? {
+                    Some(snapshot) if snapshot.instrument_id == self.instrument_id => {
+                        self.seed(&snapshot)?;
+                        Ok(true)
+                    }
+                    _ => Ok(false),
+                }
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Replaces the mirrored book with `snapshot`, which becomes the point
+    /// changes continue from.
+    pub fn seed(&mut self, snapshot: &OrderBookSnapshot) -> Result<(), MirrorError> {
+        let mut levels = Vec::with_capacity(snapshot.bids.len() + snapshot.asks.len());
+        for (side, side_levels) in [(Side::Buy, &snapshot.bids), (Side::Sell, &snapshot.asks)] {
+            for &(price, amount) in side_levels {
+                levels.push((side, price_to_scaled(price)?, quantity_to_scaled(amount)?));
+            }
+        }
+
+        self.book.cancel_all();
+        self.levels.clear();
+        for (side, price, quantity) in levels {
+            if quantity > 0 {
+                self.add_level(side, price, quantity);
+            }
+        }
+        self.change_id = Some(snapshot.change_id);
+        self.change_open = false;
+        debug!(
+            "Seeded book {} at change {} with {} levels",
+            self.instrument_id,
+            snapshot.change_id,
+            self.levels.len()
+        );
+        Ok(())
+    }
+
+    /// Applies one `Book` message. Returns false if it was dropped: the
+    /// mirror has no snapshot yet, or already holds the change.
+    pub fn apply_book(&mut self, msg: &BookMessage) -> Result<bool, MirrorError> {
+        let Some(last) = self.change_id else {
+            debug!(
+                "Dropping change {} for book {} until a snapshot arrives",
+                msg.change_id, self.instrument_id
+            );
+            return Ok(false);
+        };
+        if msg.change_id < last || (msg.change_id == last && !self.change_open) {
+            return Ok(false);
+        }
+        let continues = msg.prev_change_id == last || (self.change_open && msg.change_id == last);
+        if !continues {
+            return Err(self.resync(msg.prev_change_id));
+        }
+
+        for change in &msg.changes {
+            let side = match change.side {
+                1 => Side::Buy,
+                0 => Side::Sell,
+                _ => return Err(MirrorError::InvalidSide(change.side)),
+            };
+            let price = price_to_scaled(change.price)?;
+            let quantity = quantity_to_scaled(change.amount)?;
+            let existing = self.levels.get(&(side, price)).copied();
+            match (ChangeKind::from(change.change), existing) {
+                (ChangeKind::created, None) if quantity > 0 => {
+                    self.add_level(side, price, quantity);
+                }
+                (ChangeKind::changed, Some(order_id)) if quantity > 0 => {
+                    self.resize_level(order_id, quantity);
+                }
+                (ChangeKind::deleted, Some(order_id)) | (ChangeKind::changed, Some(order_id)) => {
+                    self.book.remove_order(order_id);
+                    self.levels.remove(&(side, price));
+                }
+                (ChangeKind::NullVal, _) => {
+                    return Err(MirrorError::InvalidChange(change.change));
+                }
+                _ => {
+                    self.change_id = None;
+                    warn!(
+                        "Book {} change {} does not fit the mirror at {} on the {:?} side",
+                        self.instrument_id, msg.change_id, price, side
+                    );
+                    return Err(MirrorError::Inconsistent {
+                        instrument_id: self.instrument_id,
+                        change_id: msg.change_id,
+                        side,
+                        price,
+                    });
+                }
+            }
+        }
+        self.change_id = Some(msg.change_id);
+        self.change_open = !msg.is_last;
+        Ok(true)
+    }
+
+    fn resync(&mut self, got: u64) -> MirrorError {
+        let expected = self.change_id.take().unwrap_or_default();
+        warn!(
+            "Gap in book {}: expected prev_change_id {}, got {}",
+            self.instrument_id, expected, got
+        );
+        MirrorError::ResyncNeeded {
+            instrument_id: self.instrument_id,
+            expected,
+            got,
+        }
+    }
+
+    fn add_level(&mut self, side: Side, price: Price, quantity: u32) {
+        let mut order = Order::new(
+            self.symbol.clone(),
+            side,
+            OrderType::Limit,
+            price,
+            quantity,
+            MIRROR_USER_ID,
+        );
+        order.id = self.next_order_id;
+        self.next_order_id += 1;
+        self.levels.insert((side, price), order.id);
+        // A native book never rests crossed, but the mirror takes the
+        // source's word for its levels.
+        let _ = self.book.add_order(Arc::new(RwLock::new(order)));
+    }
+
+    fn resize_level(&mut self, order_id: u64, quantity: u32) {
+        if let Some(order) = self.book.get_order(order_id) {
+            let mut order = order.write();
+            order.quantity = quantity;
+            order.filled_quantity = 0;
+        }
+        self.book.refresh_order_level(order_id);
+    }
+
+    pub fn instrument_id(&self) -> u32 {
+        self.instrument_id
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    /// Last change applied, or `None` while the mirror waits for a
+    /// snapshot.
+    pub fn change_id(&self) -> Option<u64> {
+        self.change_id
+    }
+
+    pub fn needs_snapshot(&self) -> bool {
+        self.change_id.is_none()
+    }
+
+    pub fn book(&self) -> &OrderBook {
+        &self.book
+    }
+
+    pub fn get_market_depth(&self) -> MarketDepth {
+        self.book.get_market_depth()
+    }
+
+    pub fn market_depth(&self, levels: usize) -> MarketDepth {
+        self.book.market_depth(levels)
+    }
+
+    pub fn top_of_book(&self) -> TopOfBook {
+        self.book.top_of_book()
+    }
+}
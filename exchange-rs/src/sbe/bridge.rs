@@ -1,20 +1,26 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use thiserror::Error;
 use tracing::{debug, info, warn};
 use chrono;
 
 use crate::order::{Order, Side, OrderType, OrderStatus, TimeInForce};
-use crate::matching_engine::{Trade, MatchingEngine};
-use crate::orderbook::OrderBook;
+use crate::matching_engine::{Trade, MatchingEngine, MatchingError};
+use crate::orderbook::{OrderBook, SymbolSpec};
 use crate::sbe::{InstrumentKind, InstrumentType, OptionType};
+use crate::price_utils::price_magnitude;
 use crate::PRICE_SCALE_FACTOR;
+use crate::price_utils::{price_to_scaled, quantity_to_scaled, PriceError};
 use crate::sbe::parser::{
     SbeMessage, BookMessage, BookChange, TradesMessage, Trade as SbeTrade,
     TickerMessage, SnapshotMessage, InstrumentMessage, SnapshotLevel
 };
 
+/// First id of the synthetic top-of-book orders, clear of engine ids.
+const SYNTHETIC_ORDER_ID_BASE: u64 = 1 << 62;
+
 #[derive(Error, Debug)]
 pub enum BridgeError {
     #[error("Unknown instrument ID: {0}")]
@@ -27,6 +33,14 @@ pub enum BridgeError {
     PriceConversion(String),
     #[error("Matching engine error: {0}")]
     MatchingEngine(String),
+    #[error("Book sequence gap: expected prev_change_id {expected}, got {got}")]
+    SequenceGap { expected: u64, got: u64 },
+}
+
+impl From<PriceError> for BridgeError {
+    fn from(err: PriceError) -> Self {
+        BridgeError::PriceConversion(err.to_string())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -48,7 +62,25 @@ pub struct DeribitInstrument {
     pub is_active: bool,
 }
 
-
+impl DeribitInstrument {
+    /// The engine spec for this instrument: tick size as a scaled price and
+    /// the minimum trade amount as both lot size and minimum quantity.
+    pub fn symbol_spec(&self) -> Result<SymbolSpec, BridgeError> {
+        let tick_size = price_to_scaled(self.tick_size)?;
+        let lot_size = quantity_to_scaled(self.min_trade_amount)?;
+        if tick_size == 0 || lot_size == 0 {
+            return Err(BridgeError::PriceConversion(format!(
+                "{} has tick size {} and minimum trade amount {}",
+                self.name, self.tick_size, self.min_trade_amount
+            )));
+        }
+        Ok(SymbolSpec {
+            tick_size,
+            lot_size,
+            min_qty: lot_size,
+        })
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct MarketDataUpdate {
@@ -62,22 +94,134 @@ pub struct MarketDataUpdate {
     pub index_price: Option<f64>,
 }
 
+#[derive(Debug, Clone)]
+pub struct SbeBridgeConfig {
+    /// Number of recent trade ids remembered per instrument for duplicate
+    /// suppression. Zero disables it.
+    pub trade_dedup_window: usize,
+}
+
+impl Default for SbeBridgeConfig {
+    fn default() -> Self {
+        Self {
+            trade_dedup_window: 4096,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TradeDedupStats {
+    pub duplicates_suppressed: u64,
+    pub trade_seq_gaps: u64,
+    pub trade_seq_out_of_order: u64,
+}
+
+#[derive(Default)]
+struct TradeDedupMetrics {
+    duplicates_suppressed: AtomicU64,
+    trade_seq_gaps: AtomicU64,
+    trade_seq_out_of_order: AtomicU64,
+}
+
+/// Recent trade ids of one instrument, oldest first, and the highest
+/// trade_seq seen.
+#[derive(Default)]
+struct TradeDedupWindow {
+    recent: VecDeque<u64>,
+    seen: HashSet<u64>,
+    last_trade_seq: Option<u64>,
+}
+
+/// Where the incremental book feed of one instrument stands.
+#[derive(Default)]
+struct BookSequence {
+    /// change_id of the last delta or snapshot applied.
+    last_change_id: Option<u64>,
+    /// The last delta applied was not the last message of its change, so
+    /// more messages with the same ids follow.
+    change_open: bool,
+    /// A gap was seen; deltas are dropped until the next full snapshot.
+    needs_snapshot: bool,
+}
+
 pub struct SbeBridge {
     pub instruments: RwLock<HashMap<u32, DeribitInstrument>>,
     symbol_to_id: RwLock<HashMap<String, u32>>,
     external_user_id_counter: RwLock<u64>,
+    config: SbeBridgeConfig,
+    trade_windows: Mutex<HashMap<u32, TradeDedupWindow>>,
+    dedup_metrics: TradeDedupMetrics,
+    book_sequences: Mutex<HashMap<u32, BookSequence>>,
+    /// Synthetic order standing for the top of book of each side of a
+    /// symbol in books kept by `update_orderbook_from_market_data`.
+    top_of_book_orders: Mutex<HashMap<(String, Side), u64>>,
+    next_synthetic_order_id: AtomicU64,
+    /// Engine fed with ticker mark and index prices, once connected.
+    engine: RwLock<Option<Arc<RwLock<MatchingEngine>>>>,
 }
 
 impl SbeBridge {
     pub fn new(_price_scale: u64) -> Self {
-        
+        Self::with_config(SbeBridgeConfig::default())
+    }
+
+    pub fn with_config(config: SbeBridgeConfig) -> Self {
         Self {
             instruments: RwLock::new(HashMap::new()),
             symbol_to_id: RwLock::new(HashMap::new()),
             external_user_id_counter: RwLock::new(1000), 
+            config,
+            trade_windows: Mutex::new(HashMap::new()),
+            dedup_metrics: TradeDedupMetrics::default(),
+            book_sequences: Mutex::new(HashMap::new()),
+            top_of_book_orders: Mutex::new(HashMap::new()),
+            next_synthetic_order_id: AtomicU64::new(SYNTHETIC_ORDER_ID_BASE),
+            engine: RwLock::new(None),
         }
     }
 
+    /// Feeds ticker mark and index prices into the engine's book for the
+    /// instrument's symbol, so stops triggering off them can fire, and
+    /// applies each instrument's spec to the symbol if it is listed.
+    pub fn connect_engine(&self, engine: Arc<RwLock<MatchingEngine>>) -> Result<(), BridgeError> {
+        *self.engine.write() = Some(engine);
+        for instrument in self.list_instruments() {
+            self.apply_symbol_spec(&instrument)?;
+        }
+        Ok(())
+    }
+
+    /// Sets the instrument's tick and lot sizes on the engine's symbol.
+    /// Instruments the engine does not list are skipped.
+    fn apply_symbol_spec(&self, instrument: &DeribitInstrument) -> Result<(), BridgeError> {
+        let Some(engine) = self.engine.read().clone() else {
+            return Ok(());
+        };
+        let spec = instrument.symbol_spec()?;
+        let applied = engine.write().set_symbol_spec(&instrument.symbol, spec);
+        match applied {
+            Ok(()) | Err(MatchingError::SymbolNotFound) => Ok(()),
+            Err(err) => Err(BridgeError::MatchingEngine(err.to_string())),
+        }
+    }
+
+    pub fn trade_dedup_stats(&self) -> TradeDedupStats {
+        TradeDedupStats {
+            duplicates_suppressed: self.dedup_metrics.duplicates_suppressed.load(Ordering::Relaxed),
+            trade_seq_gaps: self.dedup_metrics.trade_seq_gaps.load(Ordering::Relaxed),
+            trade_seq_out_of_order: self.dedup_metrics.trade_seq_out_of_order.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Whether book deltas for the instrument are being dropped after a
+    /// sequence gap, until a fresh snapshot arrives.
+    pub fn needs_snapshot(&self, instrument_id: u32) -> bool {
+        self.book_sequences
+            .lock()
+            .get(&instrument_id)
+            .is_some_and(|sequence| sequence.needs_snapshot)
+    }
+
     fn get_next_external_user_id(&self) -> u64 {
         let mut counter = self.external_user_id_counter.write();
         *counter += 1;
@@ -137,10 +281,10 @@ impl SbeBridge {
 
         {
             let mut symbol_map = self.symbol_to_id.write();
-            symbol_map.insert(instrument.name, msg.instrument_id);
+            symbol_map.insert(instrument.name.clone(), msg.instrument_id);
         }
 
-        Ok(())
+        self.apply_symbol_spec(&instrument)
     }
 
 
@@ -152,6 +296,10 @@ impl SbeBridge {
                 .clone()
         };
 
+        if !self.check_book_sequence(&instrument.symbol, &msg)? {
+            return Ok(Vec::new());
+        }
+
         debug!("Processing book update for {}: {} changes", instrument.symbol, msg.changes.len());
 
         let mut best_bid: Option<(f64, f64)> = None;
@@ -191,6 +339,38 @@ impl SbeBridge {
         Ok(vec![update])
     }
 
+    /// Checks that `msg` continues the instrument's book from the last
+    /// applied change_id. The first delta seen starts the sequence. On a
+    /// gap the instrument needs a fresh snapshot, and later deltas are
+    /// dropped until one arrives; returns false for those.
+    fn check_book_sequence(&self, symbol: &str, msg: &BookMessage) -> Result<bool, BridgeError> {
+        let mut sequences = self.book_sequences.lock();
+        let sequence = sequences.entry(msg.instrument_id).or_default();
+        if sequence.needs_snapshot {
+            debug!("Dropping book update {} for {} until a snapshot arrives", msg.change_id, symbol);
+            return Ok(false);
+        }
+
+        if let Some(last) = sequence.last_change_id {
+            let continues = msg.prev_change_id == last
+                || (sequence.change_open && msg.change_id == last);
+            if !continues {
+                warn!(
+                    "Gap in book changes for {}: expected prev_change_id {}, got {}",
+                    symbol, last, msg.prev_change_id
+                );
+                sequence.needs_snapshot = true;
+                return Err(BridgeError::SequenceGap {
+                    expected: last,
+                    got: msg.prev_change_id,
+                });
+            }
+        }
+        sequence.last_change_id = Some(msg.change_id);
+        sequence.change_open = !msg.is_last;
+        Ok(true)
+    }
+
     fn handle_trades(&self, msg: TradesMessage) -> Result<Vec<MarketDataUpdate>, BridgeError> {
         let instrument = {
             let instruments = self.instruments.read();
@@ -201,9 +381,10 @@ impl SbeBridge {
 
         debug!("Processing {} trades for {}", msg.trades.len(), instrument.symbol);
 
+        let trades = self.filter_duplicate_trades(&instrument.symbol, msg.instrument_id, &msg.trades);
         let mut updates = Vec::new();
 
-        if let Some(last_trade) = msg.trades.last() {
+        if let Some(last_trade) = trades.last() {
             let update = MarketDataUpdate {
                 instrument_id: msg.instrument_id,
                 symbol: instrument.symbol.clone(),
@@ -220,6 +401,67 @@ impl SbeBridge {
         Ok(updates)
     }
 
+    /// Drops trades whose trade_id was already delivered within the dedup
+    /// window, e.g. after a reconnect or from the other side of an A/B feed.
+    /// Gaps and regressions in trade_seq are logged and counted but the
+    /// trades are kept.
+    fn filter_duplicate_trades<'a>(
+        &self,
+        symbol: &str,
+        instrument_id: u32,
+        trades: &'a [SbeTrade],
+    ) -> Vec<&'a SbeTrade> {
+        let window_size = self.config.trade_dedup_window;
+        if window_size == 0 {
+            return trades.iter().collect();
+        }
+
+        let mut windows = self.trade_windows.lock();
+        let window = windows.entry(instrument_id).or_default();
+        let mut fresh = Vec::with_capacity(trades.len());
+
+        for trade in trades {
+            if window.seen.contains(&trade.trade_id) {
+                self.dedup_metrics.duplicates_suppressed.fetch_add(1, Ordering::Relaxed);
+                debug!("Suppressed duplicate trade {} for {}", trade.trade_id, symbol);
+                continue;
+            }
+
+            match window.last_trade_seq {
+                Some(last) if trade.trade_seq <= last => {
+                    self.dedup_metrics.trade_seq_out_of_order.fetch_add(1, Ordering::Relaxed);
+                    warn!(
+                        "Out-of-order trade_seq {} after {} for {}",
+                        trade.trade_seq, last, symbol
+                    );
+                }
+                Some(last) if trade.trade_seq > last + 1 => {
+                    self.dedup_metrics.trade_seq_gaps.fetch_add(1, Ordering::Relaxed);
+                    warn!(
+                        "Gap in trade_seq for {}: expected {}, got {}",
+                        symbol,
+                        last + 1,
+                        trade.trade_seq
+                    );
+                }
+                _ => {}
+            }
+            window.last_trade_seq = window.last_trade_seq.max(Some(trade.trade_seq));
+
+            window.seen.insert(trade.trade_id);
+            window.recent.push_back(trade.trade_id);
+            if window.recent.len() > window_size {
+                if let Some(evicted) = window.recent.pop_front() {
+                    window.seen.remove(&evicted);
+                }
+            }
+
+            fresh.push(trade);
+        }
+
+        fresh
+    }
+
     fn handle_ticker(&self, msg: TickerMessage) -> Result<Vec<MarketDataUpdate>, BridgeError> {
         let instrument = {
             let instruments = self.instruments.read();
@@ -230,6 +472,8 @@ impl SbeBridge {
 
         debug!("Processing ticker for {}", instrument.symbol);
 
+        self.feed_reference_prices(&instrument.symbol, msg.mark_price, msg.index_price)?;
+
         let update = MarketDataUpdate {
             instrument_id: msg.instrument_id,
             symbol: instrument.symbol,
@@ -244,6 +488,28 @@ impl SbeBridge {
         Ok(vec![update])
     }
 
+    fn feed_reference_prices(
+        &self,
+        symbol: &str,
+        mark_price: f64,
+        index_price: f64,
+    ) -> Result<(), BridgeError> {
+        let Some(engine) = self.engine.read().clone() else {
+            return Ok(());
+        };
+        let mark_price = price_to_scaled(mark_price)?;
+        let index_price = price_to_scaled(index_price)?;
+
+        let mut engine = engine.write();
+        let fed = engine
+            .update_mark_price(symbol, mark_price)
+            .and_then(|_| engine.update_index_price(symbol, index_price));
+        match fed {
+            Ok(_) | Err(MatchingError::SymbolNotFound) => Ok(()),
+            Err(err) => Err(BridgeError::MatchingEngine(err.to_string())),
+        }
+    }
+
     fn handle_snapshot(&self, msg: SnapshotMessage) -> Result<Vec<MarketDataUpdate>, BridgeError> {
         let instrument = {
             let instruments = self.instruments.read();
@@ -254,6 +520,15 @@ impl SbeBridge {
 
         debug!("Processing snapshot for {}: {} levels", instrument.symbol, msg.levels.len());
 
+        if msg.is_last_in_book {
+            let mut sequences = self.book_sequences.lock();
+            let sequence = sequences.entry(msg.instrument_id).or_default();
+            *sequence = BookSequence {
+                last_change_id: Some(msg.change_id),
+                ..Default::default()
+            };
+        }
+
         let mut best_bid: Option<(f64, f64)> = None;
         let mut best_ask: Option<(f64, f64)> = None;
 
@@ -321,10 +596,8 @@ impl SbeBridge {
         _instrument_id: u32,
         trade_id: u64
     ) -> Result<Trade, BridgeError> {
-        let price_scaled = crate::price_utils::float_to_scaled_price(sbe_trade.price)
-            .map_err(|err| BridgeError::PriceConversion(err))?;
-        let quantity = crate::price_utils::float_to_scaled_quantity(sbe_trade.amount)
-            .map_err(|err| BridgeError::PriceConversion(err))?;
+        let price_scaled = price_to_scaled(sbe_trade.price)?;
+        let quantity = quantity_to_scaled(sbe_trade.amount)?;
 
         Ok(Trade {
             id: trade_id,
@@ -333,6 +606,13 @@ impl SbeBridge {
             price: price_scaled,
             quantity,
             timestamp: sbe_trade.timestamp_ms as i64,
+            maker_fee: 0,
+            taker_fee: 0,
+            aggressor: match sbe_trade.direction {
+                0 => Some(Side::Buy),
+                1 => Some(Side::Sell),
+                _ => None,
+            },
         })
     }
 
@@ -354,13 +634,12 @@ impl SbeBridge {
                 .clone()
         };
 
-        let price_scaled = crate::price_utils::float_to_scaled_price(change.price)
-            .map_err(|err| BridgeError::PriceConversion(err))?;
-        let quantity = crate::price_utils::float_to_scaled_quantity(change.amount)
-            .map_err(|err| BridgeError::PriceConversion(err))?;
+        let price_scaled = price_to_scaled(change.price)?;
+        let quantity = quantity_to_scaled(change.amount)?;
 
         let external_user_id = self.get_next_external_user_id();
 
+        let timestamp = Order::get_nano_timestamp();
         Ok(Order {
             id: order_id,
             symbol: instrument.symbol,
@@ -370,12 +649,20 @@ impl SbeBridge {
             quantity,
             filled_quantity: 0,
             status: OrderStatus::New,
-            timestamp: Order::get_nano_timestamp(), 
+            timestamp,
             user_id: external_user_id, 
             time_in_force: TimeInForce::GTC,
             expiration_time: 0, 
             stop_price: None,
+            trigger_source: None,
             display_quantity: Some(quantity), 
+            max_slippage_bps: None,
+            protection_price: None,
+            contingent_id: None,
+            fill_notional: 0,
+            last_update: timestamp,
+            received_at: 0,
+            client_order_id: None,
         })
     }
 
@@ -399,35 +686,67 @@ impl SbeBridge {
         instruments.values().cloned().collect()
     }
 
+    /// Keeps a synthetic top of book in `orderbook`: each side present in
+    /// `update` replaces the order previously standing for that side with
+    /// one at the new best price and amount. A zero amount empties the side.
     pub fn update_orderbook_from_market_data(
         &self,
-        _orderbook: &mut OrderBook,
+        orderbook: &mut OrderBook,
         update: &MarketDataUpdate
     ) -> Result<(), BridgeError> {
         debug!("Updating orderbook for {} with market data", update.symbol);
 
         if let Some((bid_price, bid_amount)) = update.best_bid {
-            let _price_scaled = crate::price_utils::float_to_scaled_price(bid_price)
-                .map_err(|err| BridgeError::PriceConversion(err))?;
-            let _quantity = crate::price_utils::float_to_scaled_quantity(bid_amount)
-                .map_err(|err| BridgeError::PriceConversion(err))?;
-            
+            self.replace_top_of_book(orderbook, &update.symbol, Side::Buy, bid_price, bid_amount)?;
         }
 
         if let Some((ask_price, ask_amount)) = update.best_ask {
-            let _price_scaled = crate::price_utils::float_to_scaled_price(ask_price)
-                .map_err(|err| BridgeError::PriceConversion(err))?;
-            let _quantity = crate::price_utils::float_to_scaled_quantity(ask_amount)
-                .map_err(|err| BridgeError::PriceConversion(err))?;
-            
+            self.replace_top_of_book(orderbook, &update.symbol, Side::Sell, ask_price, ask_amount)?;
         }
 
         Ok(())
     }
+
+    fn replace_top_of_book(
+        &self,
+        orderbook: &mut OrderBook,
+        symbol: &str,
+        side: Side,
+        price: f64,
+        amount: f64,
+    ) -> Result<(), BridgeError> {
+        let price_scaled = price_to_scaled(price)?;
+        let quantity = quantity_to_scaled(amount)?;
+
+        let mut top_of_book = self.top_of_book_orders.lock();
+        let key = (symbol.to_string(), side);
+        if let Some(stale) = top_of_book.remove(&key) {
+            orderbook.remove_order(stale);
+        }
+        if quantity == 0 {
+            return Ok(());
+        }
+
+        let order_id = self.next_synthetic_order_id.fetch_add(1, Ordering::Relaxed);
+        let mut order = Order::new(
+            symbol.to_string(),
+            side,
+            OrderType::Limit,
+            price_scaled,
+            quantity,
+            self.get_next_external_user_id(),
+        );
+        order.id = order_id;
+        orderbook
+            .add_order(Arc::new(RwLock::new(order)))
+            .map_err(|err| BridgeError::MatchingEngine(err.to_string()))?;
+        top_of_book.insert(key, order_id);
+        Ok(())
+    }
 }
 
 impl Default for SbeBridge {
     fn default() -> Self {
-        Self::new(PRICE_SCALE_FACTOR) 
+        Self::new(price_magnitude(PRICE_SCALE_FACTOR)) 
     }
 }
\ No newline at end of file
@@ -35,6 +35,9 @@ pub mod parser;
 pub mod bridge;
 pub mod multicast;
 pub mod simple;
+pub mod publisher;
+pub mod snapshot_assembler;
+pub mod book_mirror;
 
 pub use group_size_encoding_codec::*;
 pub use snapshot_codec::*;
@@ -68,6 +71,9 @@ pub type SbeResult<T> = core::result::Result<T, SbeErr>;
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum SbeErr {
     ParentNotSet,
+    /// A read or checked write of a field at `index` ran past the end of
+    /// a buffer of `len` bytes.
+    OutOfBounds { index: usize, len: usize },
 }
 
 impl core::fmt::Display for SbeErr {
@@ -92,6 +98,14 @@ pub trait Writer<'a>: Sized {
 pub trait Encoder<'a>: Writer<'a> {
     fn get_limit(&self) -> usize;
     fn set_limit(&mut self, limit: usize);
+
+    /// Bytes left in the buffer past the limit, for checking a group or
+    /// variable-length field fits before writing it.
+    #[inline]
+    fn remaining(&mut self) -> usize {
+        let limit = self.get_limit();
+        self.get_buf_mut().remaining(limit)
+    }
 }
 
 pub trait Reader<'a>: Sized {
@@ -185,26 +199,129 @@ impl<'a> ReadBuf<'a> {
     pub fn get_slice_at(&self, index: usize, len: usize) -> &[u8] {
         self.data.split_at(index).1.split_at(len).0
     }
+
+    // Fallible reads for input that has not been length checked, such as
+    // packets off the network: a read past the end is an error rather
+    // than a panic.
+
+    #[inline]
+    fn try_get_bytes_at<const COUNT: usize>(&self, index: usize) -> SbeResult<[u8; COUNT]> {
+        self.try_get_slice_at(index, COUNT).map(Self::get_bytes)
+    }
+
+    #[inline]
+    pub fn try_get_u8_at(&self, index: usize) -> SbeResult<u8> {
+        self.try_get_bytes_at(index).map(u8::from_le_bytes)
+    }
+
+    #[inline]
+    pub fn try_get_i8_at(&self, index: usize) -> SbeResult<i8> {
+        self.try_get_bytes_at(index).map(i8::from_le_bytes)
+    }
+
+    #[inline]
+    pub fn try_get_i16_at(&self, index: usize) -> SbeResult<i16> {
+        self.try_get_bytes_at(index).map(i16::from_le_bytes)
+    }
+
+    #[inline]
+    pub fn try_get_i32_at(&self, index: usize) -> SbeResult<i32> {
+        self.try_get_bytes_at(index).map(i32::from_le_bytes)
+    }
+
+    #[inline]
+    pub fn try_get_i64_at(&self, index: usize) -> SbeResult<i64> {
+        self.try_get_bytes_at(index).map(i64::from_le_bytes)
+    }
+
+    #[inline]
+    pub fn try_get_u16_at(&self, index: usize) -> SbeResult<u16> {
+        self.try_get_bytes_at(index).map(u16::from_le_bytes)
+    }
+
+    #[inline]
+    pub fn try_get_u32_at(&self, index: usize) -> SbeResult<u32> {
+        self.try_get_bytes_at(index).map(u32::from_le_bytes)
+    }
+
+    #[inline]
+    pub fn try_get_u64_at(&self, index: usize) -> SbeResult<u64> {
+        self.try_get_bytes_at(index).map(u64::from_le_bytes)
+    }
+
+    #[inline]
+    pub fn try_get_f32_at(&self, index: usize) -> SbeResult<f32> {
+        self.try_get_bytes_at(index).map(f32::from_le_bytes)
+    }
+
+    #[inline]
+    pub fn try_get_f64_at(&self, index: usize) -> SbeResult<f64> {
+        self.try_get_bytes_at(index).map(f64::from_le_bytes)
+    }
+
+    #[inline]
+    pub fn try_get_slice_at(&self, index: usize, len: usize) -> SbeResult<&[u8]> {
+        index
+            .checked_add(len)
+            .and_then(|end| self.data.get(index..end))
+            .ok_or(SbeErr::OutOfBounds {
+                index,
+                len: self.data.len(),
+            })
+    }
 }
 
+/// Buffer an encoder writes into. One made with `new` writes unchecked,
+/// and a write past the end panics. One made with `checked` drops such a
+/// write instead and keeps the first error for `error`, so any encoder
+/// wrapped around it can encode into a buffer of uncertain size safely.
 #[derive(Debug, Default)]
 pub struct WriteBuf<'a> {
     data: &'a mut [u8],
+    checked: bool,
+    error: Option<SbeErr>,
 }
 
 impl<'a> WriteBuf<'a> {
     pub fn new(data: &'a mut [u8]) -> Self {
-        Self { data }
+        Self {
+            data,
+            checked: false,
+            error: None,
+        }
+    }
+
+    pub fn checked(data: &'a mut [u8]) -> Self {
+        Self {
+            data,
+            checked: true,
+            error: None,
+        }
+    }
+
+    /// Bytes from `index` to the end of the buffer.
+    #[inline]
+    pub fn remaining(&self, index: usize) -> usize {
+        self.data.len().saturating_sub(index)
+    }
+
+    /// The first write a checked buffer dropped for running past its end.
+    pub fn error(&self) -> Option<SbeErr> {
+        self.error
     }
 
     #[inline]
     pub fn put_bytes_at<const COUNT: usize>(&mut self, index: usize, bytes: [u8; COUNT]) -> usize {
+        if self.checked {
+            let written = self.try_put_slice_at(index, &bytes);
+            self.keep_error(written);
+            return COUNT;
+        }
         for (i, byte) in bytes.iter().enumerate() {
             self.data[index + i] = *byte;
         }
         COUNT
     }
-
     #[inline]
     pub fn put_u8_at(&mut self, index: usize, value: u8) {
         self.put_bytes_at(index, u8::to_le_bytes(value));
@@ -258,8 +375,84 @@ impl<'a> WriteBuf<'a> {
     #[inline]
     pub fn put_slice_at(&mut self, index: usize, src: &[u8]) -> usize {
         let len = src.len();
+        if self.checked {
+            let written = self.try_put_slice_at(index, src);
+            self.keep_error(written);
+            return len;
+        }
         let dest = self.data.split_at_mut(index).1.split_at_mut(len).0;
         dest.clone_from_slice(src);
         len
     }
+
+    fn keep_error(&mut self, written: SbeResult<usize>) {
+        if let Err(e) = written {
+            self.error.get_or_insert(e);
+        }
+    }
+
+    // Checked writes, for buffers whose size the caller cannot be sure of:
+    // a write past the end is an error and leaves the buffer untouched.
+    // Each returns the bytes written.
+
+    #[inline]
+    pub fn try_put_u8_at(&mut self, index: usize, value: u8) -> SbeResult<usize> {
+        self.try_put_slice_at(index, &u8::to_le_bytes(value))
+    }
+
+    #[inline]
+    pub fn try_put_i8_at(&mut self, index: usize, value: i8) -> SbeResult<usize> {
+        self.try_put_slice_at(index, &i8::to_le_bytes(value))
+    }
+
+    #[inline]
+    pub fn try_put_i16_at(&mut self, index: usize, value: i16) -> SbeResult<usize> {
+        self.try_put_slice_at(index, &i16::to_le_bytes(value))
+    }
+
+    #[inline]
+    pub fn try_put_i32_at(&mut self, index: usize, value: i32) -> SbeResult<usize> {
+        self.try_put_slice_at(index, &i32::to_le_bytes(value))
+    }
+
+    #[inline]
+    pub fn try_put_i64_at(&mut self, index: usize, value: i64) -> SbeResult<usize> {
+        self.try_put_slice_at(index, &i64::to_le_bytes(value))
+    }
+
+    #[inline]
+    pub fn try_put_u16_at(&mut self, index: usize, value: u16) -> SbeResult<usize> {
+        self.try_put_slice_at(index, &u16::to_le_bytes(value))
+    }
+
+    #[inline]
+    pub fn try_put_u32_at(&mut self, index: usize, value: u32) -> SbeResult<usize> {
+        self.try_put_slice_at(index, &u32::to_le_bytes(value))
+    }
+
+    #[inline]
+    pub fn try_put_u64_at(&mut self, index: usize, value: u64) -> SbeResult<usize> {
+        self.try_put_slice_at(index, &u64::to_le_bytes(value))
+    }
+
+    #[inline]
+    pub fn try_put_f32_at(&mut self, index: usize, value: f32) -> SbeResult<usize> {
+        self.try_put_slice_at(index, &f32::to_le_bytes(value))
+    }
+
+    #[inline]
+    pub fn try_put_f64_at(&mut self, index: usize, value: f64) -> SbeResult<usize> {
+        self.try_put_slice_at(index, &f64::to_le_bytes(value))
+    }
+
+    #[inline]
+    pub fn try_put_slice_at(&mut self, index: usize, src: &[u8]) -> SbeResult<usize> {
+        let len = self.data.len();
+        let dest = index
+            .checked_add(src.len())
+            .and_then(|end| self.data.get_mut(index..end))
+            .ok_or(SbeErr::OutOfBounds { index, len })?;
+        dest.copy_from_slice(src);
+        Ok(src.len())
+    }
 }
\ No newline at end of file
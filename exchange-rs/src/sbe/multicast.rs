@@ -195,12 +195,13 @@ impl DeribitMulticastReceiver {
         bridge: &SbeBridge,
         data: &[u8]
     ) -> Result<Vec<MarketDataUpdate>, MulticastError> {
-        let message = parser.parse_message(data)?;
-        
-        debug!("Received message: {}", message);
+        let mut updates = Vec::new();
+        for message in parser.iter_messages(data) {
+            let message = message?;
+            debug!("Received message: {}", message);
+            updates.extend(bridge.process_message(message)?);
+        }
 
-        let updates = bridge.process_message(message)?;
-        
         Ok(updates)
     }
 
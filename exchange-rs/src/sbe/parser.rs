@@ -1,8 +1,12 @@
 use std::fmt;
 use thiserror::Error;
-use tracing::{debug, error, warn};
+use tracing::{debug, error};
 
-use super::{ReadBuf};
+use super::{
+    book_codec, combo_legs_codec, instrument_codec, instrument_v2_codec, price_index_codec, rfq_codec,
+    snapshot_end_codec, snapshot_start_codec, ticker_codec, trades_codec, Decoder, InstrumentDecoder,
+    InstrumentV2Decoder, Period, ReadBuf, SbeErr, TickerDecoder,
+};
 use crate::sbe::message_header_codec::decoder::MessageHeaderDecoder;
 
 #[derive(Error, Debug)]
@@ -17,6 +21,17 @@ pub enum SbeParseError {
     DecodingError(String),
     #[error("Buffer underrun at position {0}")]
     BufferUnderrun(usize),
+    #[error("Cannot find the end of a template {0} message within a datagram")]
+    UnframedTemplate(u16),
+}
+
+impl From<SbeErr> for SbeParseError {
+    fn from(err: SbeErr) -> Self {
+        match err {
+            SbeErr::OutOfBounds { index, .. } => SbeParseError::BufferUnderrun(index),
+            err => SbeParseError::DecodingError(err.to_string()),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -218,6 +233,19 @@ pub struct ComboLeg {
 
 
 
+/// Schema version the codecs were generated from. A later version only
+/// appends fields to a block, so the parser reads what it knows and skips
+/// the rest using the block length in the header. An earlier version whose
+/// block stops short of a field the parser reads cannot be parsed.
+pub const SCHEMA_VERSION: u16 = 3;
+
+/// Length of the message header that precedes every block.
+const HEADER_LENGTH: usize = 12;
+
+/// Snapshot blocks from before the completeness flags stop after the
+/// change id.
+const SNAPSHOT_BLOCK_LENGTH_WITHOUT_FLAGS: u16 = 20;
+
 #[derive(Debug, Clone)]
 pub struct SbeMessageParser;
 
@@ -227,87 +255,211 @@ impl SbeMessageParser {
     }
 
     pub fn parse_message(&self, data: &[u8]) -> Result<SbeMessage, SbeParseError> {
-        if data.len() < 12 {
+        if data.len() < HEADER_LENGTH {
             return Err(SbeParseError::InvalidLength(data.len()));
         }
 
         let buf = ReadBuf::new(data);
-        
-        let template_id = buf.get_u16_at(4);
-        let schema_version = buf.get_u16_at(6);
-        let block_length = buf.get_u16_at(0);
-        if schema_version != 3 {
-            warn!("Schema version mismatch: expected 3, got {}", schema_version);
+        let block_length = buf.try_get_u16_at(0)?;
+        let template_id = buf.try_get_u16_at(2)?;
+        let schema_version = buf.try_get_u16_at(6)?;
+
+        debug!(
+            "Parsing message with template_id: {}, block_length: {}, version: {}",
+            template_id, block_length, schema_version
+        );
+
+        let Some(required) = Self::required_block_length(template_id) else {
+            error!("Unknown template ID: {}", template_id);
+            return Err(SbeParseError::UnknownTemplateId(template_id));
+        };
+        if block_length < required {
+            if schema_version < SCHEMA_VERSION {
+                return Err(SbeParseError::SchemaVersionMismatch {
+                    expected: SCHEMA_VERSION,
+                    actual: schema_version,
+                });
+            }
+            return Err(SbeParseError::DecodingError(format!(
+                "Template {} block of {} bytes is shorter than the {} its fields need",
+                template_id, block_length, required
+            )));
+        }
+        if data.len() < HEADER_LENGTH + block_length as usize {
+            return Err(SbeParseError::BufferUnderrun(data.len()));
+        }
+        if schema_version > SCHEMA_VERSION {
+            debug!(
+                "Template {} is from schema version {}, reading the fields of version {}",
+                template_id, schema_version, SCHEMA_VERSION
+            );
         }
 
-        let message_start = 12;
-
-        debug!("Parsing message with template_id: {}, block_length: {}", template_id, block_length);
-
+        let message_start = HEADER_LENGTH;
         match template_id {
-            1000 => self.parse_instrument_basic(data, message_start),
+            1000 => self.parse_instrument_basic(data),
             1001 => self.parse_book_basic(data, message_start),
             1002 => self.parse_trades_basic(data, message_start),
-            1003 => self.parse_ticker_basic(data, message_start),
+            1003 => self.parse_ticker_basic(data),
             1004 => self.parse_snapshot_basic(data, message_start),
             1005 => self.parse_snapshot_start_basic(data, message_start),
             1006 => self.parse_snapshot_end_basic(),
             1007 => self.parse_combo_legs_basic(data, message_start),
             1008 => self.parse_price_index_basic(data, message_start),
             1009 => self.parse_rfq_basic(data, message_start),
-            1010 => self.parse_instrument_v2_basic(data, message_start),
-            _ => {
-                error!("Unknown template ID: {}", template_id);
-                Err(SbeParseError::UnknownTemplateId(template_id))
-            }
+            1010 => self.parse_instrument_v2_basic(data),
+            _ => Err(SbeParseError::UnknownTemplateId(template_id)),
         }
     }
 
+    /// Shortest block of `template_id` that holds every field the parser
+    /// reads from it.
+    fn required_block_length(template_id: u16) -> Option<u16> {
+        Some(match template_id {
+            1000 => instrument_codec::SBE_BLOCK_LENGTH,
+            1001 => book_codec::SBE_BLOCK_LENGTH,
+            1002 => trades_codec::SBE_BLOCK_LENGTH,
+            1003 => ticker_codec::SBE_BLOCK_LENGTH,
+            1004 => SNAPSHOT_BLOCK_LENGTH_WITHOUT_FLAGS,
+            1005 => snapshot_start_codec::SBE_BLOCK_LENGTH,
+            1006 => snapshot_end_codec::SBE_BLOCK_LENGTH,
+            1007 => combo_legs_codec::SBE_BLOCK_LENGTH,
+            1008 => price_index_codec::SBE_BLOCK_LENGTH,
+            1009 => rfq_codec::SBE_BLOCK_LENGTH,
+            1010 => instrument_v2_codec::SBE_BLOCK_LENGTH,
+            _ => return None,
+        })
+    }
 
-    fn parse_instrument_basic(&self, data: &[u8], offset: usize) -> Result<SbeMessage, SbeParseError> {
-        if data.len() < offset + 120 {
-            return Err(SbeParseError::BufferUnderrun(offset));
+    /// Parses every message of a datagram that packs several back to
+    /// back, as `SbePublisher` sends them, failing on the first that
+    /// does not parse.
+    pub fn parse_datagram(&self, data: &[u8]) -> Result<Vec<SbeMessage>, SbeParseError> {
+        self.iter_messages(data).collect()
+    }
+
+    /// Walks the messages packed back to back in `data`, parsing each in
+    /// place. A message that fails to parse yields its error and the walk
+    /// goes on to the next; one whose end cannot be found, such as a
+    /// partial message at the end of the datagram, yields an error and
+    /// ends it.
+    pub fn iter_messages<'a>(&'a self, data: &'a [u8]) -> DatagramMessages<'a> {
+        DatagramMessages {
+            parser: self,
+            data,
+            offset: 0,
+        }
+    }
+
+    /// Encoded length of the message at the start of `data`: its block
+    /// and whatever groups and variable-length fields its template
+    /// appends to it.
+    fn message_length(data: &[u8]) -> Result<usize, SbeParseError> {
+        if data.len() < HEADER_LENGTH {
+            return Err(SbeParseError::BufferUnderrun(data.len()));
+        }
+        let buf = ReadBuf::new(data);
+        let block_length = buf.try_get_u16_at(0)? as usize;
+        let block_end = HEADER_LENGTH + block_length;
+        let template_id = buf.try_get_u16_at(2)?;
+
+        let length = match template_id {
+            1003 | 1005 | 1006 | 1008 | 1009 => block_end,
+            // Snapshots from before the flags carry no levels group.
+            1004 if block_length < 22 => block_end,
+            1001 | 1002 | 1004 | 1007 => Self::group_end(data, block_end)?,
+            1000 => Self::var_string_end(data, block_end)?,
+            1010 => Self::var_string_end(data, Self::group_end(data, block_end)?)?,
+            _ => return Err(SbeParseError::UnframedTemplate(template_id)),
+        };
+        if data.len() < length {
+            return Err(SbeParseError::BufferUnderrun(data.len()));
         }
+        Ok(length)
+    }
 
-        let buf = ReadBuf::new(&data[offset..]);
-        
-        let instrument_id = buf.get_u32_at(0);
-        let instrument_state = buf.get_u8_at(4);
-        let kind = buf.get_u8_at(5);
-        let instrument_type = buf.get_u8_at(6);
-        let option_type = buf.get_u8_at(7);
+    /// End of the repeating group whose header starts at `group`.
+    fn group_end(data: &[u8], group: usize) -> Result<usize, SbeParseError> {
+        if data.len() < group + 8 {
+            return Err(SbeParseError::BufferUnderrun(data.len()));
+        }
+        let buf = ReadBuf::new(&data[group..]);
+        let entry_length = buf.try_get_u16_at(0)? as usize;
+        let count = buf.try_get_u16_at(2)? as usize;
+        Ok(group + 8 + entry_length * count)
+    }
+
+    /// End of the variable-length string whose length byte is at `offset`.
+    fn var_string_end(data: &[u8], offset: usize) -> Result<usize, SbeParseError> {
+        match data.get(offset) {
+            Some(&length) => Ok(offset + 1 + length as usize),
+            None => Err(SbeParseError::BufferUnderrun(data.len())),
+        }
+    }
+
+    fn parse_instrument_basic(&self, data: &[u8]) -> Result<SbeMessage, SbeParseError> {
+        let header = MessageHeaderDecoder::default().wrap(ReadBuf::new(data), 0);
+        let decoder = InstrumentDecoder::default().header(header);
+        let block_end = decoder.get_limit();
+        let (instrument_name, _) = Self::var_string(data, block_end)?;
 
         let message = InstrumentMessage {
-            instrument_id,
-            instrument_state,
-            kind,
-            instrument_type,
-            option_type,
-            rfq: buf.get_u8_at(8),
-            settlement_period: None,
-            settlement_period_count: buf.get_u16_at(10),
-            base_currency: "BTC".to_string(),
-            quote_currency: "USD".to_string(),
-            counter_currency: "USD".to_string(),
-            settlement_currency: "USD".to_string(),
-            size_currency: "BTC".to_string(),
-            creation_timestamp_ms: buf.get_u64_at(50),
-            expiration_timestamp_ms: buf.get_u64_at(58),
-            strike_price: None,
-            contract_size: 1.0,
-            min_trade_amount: 0.001,
-            tick_size: 0.5,
-            maker_commission: 0.0002,
-            taker_commission: 0.0005,
-            block_trade_commission: None,
-            max_liquidation_commission: None,
-            max_leverage: None,
-            instrument_name: format!("INSTRUMENT_{}", instrument_id),
+            instrument_id: decoder.instrument_id(),
+            instrument_state: decoder.instrument_state() as u8,
+            kind: decoder.kind() as u8,
+            instrument_type: decoder.instrument_type() as u8,
+            option_type: decoder.option_type() as u8,
+            rfq: decoder.rfq() as u8,
+            settlement_period: Self::period(decoder.settlement_period()),
+            settlement_period_count: decoder.settlement_period_count(),
+            base_currency: Self::currency(decoder.base_currency()),
+            quote_currency: Self::currency(decoder.quote_currency()),
+            counter_currency: Self::currency(decoder.counter_currency()),
+            settlement_currency: Self::currency(decoder.settlement_currency()),
+            size_currency: Self::currency(decoder.size_currency()),
+            creation_timestamp_ms: decoder.creation_timestamp_ms(),
+            expiration_timestamp_ms: decoder.expiration_timestamp_ms(),
+            strike_price: decoder.strike_price(),
+            contract_size: decoder.contract_size(),
+            min_trade_amount: decoder.min_trade_amount(),
+            tick_size: decoder.tick_size(),
+            maker_commission: decoder.maker_commission(),
+            taker_commission: decoder.taker_commission(),
+            block_trade_commission: decoder.block_trade_commission(),
+            max_liquidation_commission: decoder.max_liquidation_commission(),
+            max_leverage: decoder.max_leverage(),
+            instrument_name,
         };
 
         Ok(SbeMessage::Instrument(message))
     }
 
+    /// Variable-length string at `offset`, as a one byte length and the
+    /// bytes that follow. Returns it with the offset just past it.
+    fn var_string(data: &[u8], offset: usize) -> Result<(String, usize), SbeParseError> {
+        if data.len() < offset + 1 {
+            return Err(SbeParseError::BufferUnderrun(offset));
+        }
+        let length = data[offset] as usize;
+        let end = offset + 1 + length;
+        if data.len() < end {
+            return Err(SbeParseError::BufferUnderrun(offset));
+        }
+        Ok((String::from_utf8_lossy(&data[offset + 1..end]).into_owned(), end))
+    }
+
+    fn currency(bytes: [u8; 8]) -> String {
+        let length = bytes.iter().position(|&byte| byte == 0).unwrap_or(bytes.len());
+        String::from_utf8_lossy(&bytes[..length]).into_owned()
+    }
+
+    fn period(period: Period) -> Option<u8> {
+        match period {
+            Period::NullVal => None,
+            period => Some(period as u8),
+        }
+    }
+
     fn parse_book_basic(&self, data: &[u8], offset: usize) -> Result<SbeMessage, SbeParseError> {
         if data.len() < offset + 29 {
             return Err(SbeParseError::BufferUnderrun(offset));
@@ -315,13 +467,32 @@ impl SbeMessageParser {
 
         let buf = ReadBuf::new(&data[offset..]);
         
-        let instrument_id = buf.get_u32_at(0);
-        let timestamp_ms = buf.get_u64_at(4);
-        let prev_change_id = buf.get_u64_at(12);
-        let change_id = buf.get_u64_at(20);
-        let is_last = buf.get_u8_at(28) != 0;
-
-        let changes = Vec::new();
+        let instrument_id = buf.try_get_u32_at(0)?;
+        let timestamp_ms = buf.try_get_u64_at(4)?;
+        let prev_change_id = buf.try_get_u64_at(12)?;
+        let change_id = buf.try_get_u64_at(20)?;
+        let is_last = buf.try_get_u8_at(28)? != 0;
+
+        let mut changes = Vec::new();
+        let block_length = ReadBuf::new(data).try_get_u16_at(0)? as usize;
+        let group = offset + block_length;
+        if data.len() >= group + 8 {
+            let buf = ReadBuf::new(&data[group..]);
+            let entry_length = buf.try_get_u16_at(0)? as usize;
+            let count = buf.try_get_u16_at(2)? as usize;
+            if entry_length < 18 || data.len() < group + 8 + entry_length * count {
+                return Err(SbeParseError::BufferUnderrun(group));
+            }
+            for index in 0..count {
+                let entry = 8 + index * entry_length;
+                changes.push(BookChange {
+                    side: buf.try_get_u8_at(entry)?,
+                    change: buf.try_get_u8_at(entry + 1)?,
+                    price: buf.try_get_f64_at(entry + 2)?,
+                    amount: buf.try_get_f64_at(entry + 10)?,
+                });
+            }
+        }
 
         let message = BookMessage {
             instrument_id,
@@ -341,7 +512,7 @@ impl SbeMessageParser {
         }
 
         let buf = ReadBuf::new(&data[offset..]);
-        let instrument_id = buf.get_u32_at(0);
+        let instrument_id = buf.try_get_u32_at(0)?;
 
         let trades = Vec::new();
 
@@ -353,36 +524,29 @@ impl SbeMessageParser {
         Ok(SbeMessage::Trades(message))
     }
 
-    fn parse_ticker_basic(&self, data: &[u8], offset: usize) -> Result<SbeMessage, SbeParseError> {
-        if data.len() < offset + 120 {
-            return Err(SbeParseError::BufferUnderrun(offset));
-        }
-
-        let buf = ReadBuf::new(&data[offset..]);
-        
-        let instrument_id = buf.get_u32_at(0);
-        let instrument_state = buf.get_u8_at(4);
-        let timestamp_ms = buf.get_u64_at(5);
+    fn parse_ticker_basic(&self, data: &[u8]) -> Result<SbeMessage, SbeParseError> {
+        let header = MessageHeaderDecoder::default().wrap(ReadBuf::new(data), 0);
+        let ticker = TickerDecoder::default().header(header);
 
         let message = TickerMessage {
-            instrument_id,
-            instrument_state,
-            timestamp_ms,
-            open_interest: None,
-            min_sell_price: 0.0,
-            max_buy_price: 100000.0,
-            last_price: Some(50000.0),
-            index_price: 50000.0,
-            mark_price: 50000.0,
-            best_bid_price: 49995.0,
-            best_bid_amount: 1.0,
-            best_ask_price: 50005.0,
-            best_ask_amount: 1.0,
-            current_funding: None,
-            funding_8h: None,
-            estimated_delivery_price: None,
-            delivery_price: None,
-            settlement_price: None,
+            instrument_id: ticker.instrument_id(),
+            instrument_state: ticker.instrument_state() as u8,
+            timestamp_ms: ticker.timestamp_ms(),
+            open_interest: ticker.open_interest(),
+            min_sell_price: ticker.min_sell_price(),
+            max_buy_price: ticker.max_buy_price(),
+            last_price: ticker.last_price(),
+            index_price: ticker.index_price(),
+            mark_price: ticker.mark_price(),
+            best_bid_price: ticker.best_bid_price(),
+            best_bid_amount: ticker.best_bid_amount(),
+            best_ask_price: ticker.best_ask_price(),
+            best_ask_amount: ticker.best_ask_amount(),
+            current_funding: ticker.current_funding(),
+            funding_8h: ticker.funding_8h(),
+            estimated_delivery_price: ticker.estimated_delivery_price(),
+            delivery_price: ticker.delivery_price(),
+            settlement_price: ticker.settlement_price(),
         };
 
         Ok(SbeMessage::Ticker(message))
@@ -395,17 +559,44 @@ impl SbeMessageParser {
 
         let buf = ReadBuf::new(&data[offset..]);
         
-        let instrument_id = buf.get_u32_at(0);
-        let timestamp_ms = buf.get_u64_at(4);
-        let change_id = buf.get_u64_at(12);
+        let instrument_id = buf.try_get_u32_at(0)?;
+        let timestamp_ms = buf.try_get_u64_at(4)?;
+        let change_id = buf.try_get_u64_at(12)?;
+        // Flags were added in the same schema version as the levels group,
+        // so a block without them carries neither.
+        let block_length = ReadBuf::new(data).try_get_u16_at(0)? as usize;
+        let (is_book_complete, is_last_in_book) = if block_length >= 22 {
+            (buf.try_get_u8_at(20)? != 0, buf.try_get_u8_at(21)? != 0)
+        } else {
+            (true, true)
+        };
+
+        let mut levels = Vec::new();
+        let group = offset + block_length;
+        if block_length >= 22 && data.len() >= group + 8 {
+            let buf = ReadBuf::new(&data[group..]);
+            let entry_length = buf.try_get_u16_at(0)? as usize;
+            let count = buf.try_get_u16_at(2)? as usize;
+            if entry_length < 17 || data.len() < group + 8 + entry_length * count {
+                return Err(SbeParseError::BufferUnderrun(group));
+            }
+            for index in 0..count {
+                let entry = 8 + index * entry_length;
+                levels.push(SnapshotLevel {
+                    side: buf.try_get_u8_at(entry)?,
+                    price: buf.try_get_f64_at(entry + 1)?,
+                    amount: buf.try_get_f64_at(entry + 9)?,
+                });
+            }
+        }
 
         let message = SnapshotMessage {
             instrument_id,
             timestamp_ms,
             change_id,
-            is_book_complete: true,
-            is_last_in_book: true,
-            levels: Vec::new(),
+            is_book_complete,
+            is_last_in_book,
+            levels,
         };
 
         Ok(SbeMessage::Snapshot(message))
@@ -417,7 +608,7 @@ impl SbeMessageParser {
         }
 
         let buf = ReadBuf::new(&data[offset..]);
-        let snapshot_delay = buf.get_u32_at(0);
+        let snapshot_delay = buf.try_get_u32_at(0)?;
 
         let message = SnapshotStartMessage {
             snapshot_delay,
@@ -436,7 +627,7 @@ impl SbeMessageParser {
         }
 
         let buf = ReadBuf::new(&data[offset..]);
-        let instrument_id = buf.get_u32_at(0);
+        let instrument_id = buf.try_get_u32_at(0)?;
 
         let message = ComboLegsMessage {
             instrument_id,
@@ -452,8 +643,8 @@ impl SbeMessageParser {
         }
 
         let buf = ReadBuf::new(&data[offset..]);
-        let price = buf.get_f64_at(16);
-        let timestamp_ms = buf.get_u64_at(24);
+        let price = buf.try_get_f64_at(16)?;
+        let timestamp_ms = buf.try_get_u64_at(24)?;
 
         let message = PriceIndexMessage {
             index_name: "BTC_USD".to_string(),
@@ -465,17 +656,17 @@ impl SbeMessageParser {
     }
 
     fn parse_rfq_basic(&self, data: &[u8], offset: usize) -> Result<SbeMessage, SbeParseError> {
-        if data.len() < offset + 24 {
+        if data.len() < offset + 22 {
             return Err(SbeParseError::BufferUnderrun(offset));
         }
 
         let buf = ReadBuf::new(&data[offset..]);
         
-        let instrument_id = buf.get_u32_at(0);
-        let state = buf.get_u8_at(4);
-        let side = buf.get_u8_at(5);
-        let amount = buf.get_f64_at(8);
-        let timestamp_ms = buf.get_u64_at(16);
+        let instrument_id = buf.try_get_u32_at(0)?;
+        let state = buf.try_get_u8_at(4)?;
+        let side = buf.try_get_u8_at(5)?;
+        let amount = buf.try_get_f64_at(6)?;
+        let timestamp_ms = buf.try_get_u64_at(14)?;
 
         let message = RfqMessage {
             instrument_id,
@@ -488,41 +679,91 @@ impl SbeMessageParser {
         Ok(SbeMessage::Rfq(message))
     }
 
-    fn parse_instrument_v2_basic(&self, data: &[u8], offset: usize) -> Result<SbeMessage, SbeParseError> {
-        let instrument_basic = self.parse_instrument_basic(data, offset)?;
-        
-        if let SbeMessage::Instrument(basic_msg) = instrument_basic {
-            let message = InstrumentV2Message {
-                instrument_id: basic_msg.instrument_id,
-                instrument_state: basic_msg.instrument_state,
-                kind: basic_msg.kind,
-                instrument_type: basic_msg.instrument_type,
-                option_type: basic_msg.option_type,
-                settlement_period: basic_msg.settlement_period,
-                settlement_period_count: basic_msg.settlement_period_count,
-                base_currency: basic_msg.base_currency,
-                quote_currency: basic_msg.quote_currency,
-                counter_currency: basic_msg.counter_currency,
-                settlement_currency: basic_msg.settlement_currency,
-                size_currency: basic_msg.size_currency,
-                creation_timestamp_ms: basic_msg.creation_timestamp_ms,
-                expiration_timestamp_ms: basic_msg.expiration_timestamp_ms,
-                strike_price: basic_msg.strike_price,
-                contract_size: basic_msg.contract_size,
-                min_trade_amount: basic_msg.min_trade_amount,
-                tick_size: basic_msg.tick_size,
-                maker_commission: basic_msg.maker_commission,
-                taker_commission: basic_msg.taker_commission,
-                block_trade_commission: basic_msg.block_trade_commission,
-                max_liquidation_commission: basic_msg.max_liquidation_commission,
-                max_leverage: basic_msg.max_leverage,
-                tick_steps: Vec::new(),
-                instrument_name: basic_msg.instrument_name,
-            };
-            
-            Ok(SbeMessage::InstrumentV2(message))
-        } else {
-            Err(SbeParseError::DecodingError("Failed to parse basic instrument".to_string()))
+    fn parse_instrument_v2_basic(&self, data: &[u8]) -> Result<SbeMessage, SbeParseError> {
+        let header = MessageHeaderDecoder::default().wrap(ReadBuf::new(data), 0);
+        let decoder = InstrumentV2Decoder::default().header(header);
+
+        let group = decoder.get_limit();
+        if data.len() < group + 8 {
+            return Err(SbeParseError::BufferUnderrun(group));
+        }
+        let buf = ReadBuf::new(&data[group..]);
+        let entry_length = buf.try_get_u16_at(0)? as usize;
+        let count = buf.try_get_u16_at(2)? as usize;
+        let group_end = group + 8 + entry_length * count;
+        if entry_length < 16 || data.len() < group_end {
+            return Err(SbeParseError::BufferUnderrun(group));
+        }
+        let tick_steps = (0..count)
+            .map(|index| {
+                let entry = 8 + index * entry_length;
+                Ok(TickStep {
+                    above_price: buf.try_get_f64_at(entry)?,
+                    tick_size: buf.try_get_f64_at(entry + 8)?,
+                })
+            })
+            .collect::<Result<_, SbeErr>>()?;
+        let (instrument_name, _) = Self::var_string(data, group_end)?;
+
+        let message = InstrumentV2Message {
+            instrument_id: decoder.instrument_id(),
+            instrument_state: decoder.instrument_state() as u8,
+            kind: decoder.kind() as u8,
+            instrument_type: decoder.instrument_type() as u8,
+            option_type: decoder.option_type() as u8,
+            settlement_period: Self::period(decoder.settlement_period()),
+            settlement_period_count: decoder.settlement_period_count(),
+            base_currency: Self::currency(decoder.base_currency()),
+            quote_currency: Self::currency(decoder.quote_currency()),
+            counter_currency: Self::currency(decoder.counter_currency()),
+            settlement_currency: Self::currency(decoder.settlement_currency()),
+            size_currency: Self::currency(decoder.size_currency()),
+            creation_timestamp_ms: decoder.creation_timestamp_ms(),
+            expiration_timestamp_ms: decoder.expiration_timestamp_ms(),
+            strike_price: decoder.strike_price(),
+            contract_size: decoder.contract_size(),
+            min_trade_amount: decoder.min_trade_amount(),
+            tick_size: decoder.tick_size(),
+            maker_commission: decoder.maker_commission(),
+            taker_commission: decoder.taker_commission(),
+            block_trade_commission: decoder.block_trade_commission(),
+            max_liquidation_commission: decoder.max_liquidation_commission(),
+            max_leverage: decoder.max_leverage(),
+            tick_steps,
+            instrument_name,
+        };
+
+        Ok(SbeMessage::InstrumentV2(message))
+    }
+}
+
+/// Messages of one datagram in the order they were packed. See
+/// `SbeMessageParser::iter_messages`.
+pub struct DatagramMessages<'a> {
+    parser: &'a SbeMessageParser,
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl Iterator for DatagramMessages<'_> {
+    type Item = Result<SbeMessage, SbeParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let message = &self.data[self.offset..];
+        if message.is_empty() {
+            return None;
+        }
+        match SbeMessageParser::message_length(message) {
+            Ok(length) => {
+                self.offset += length;
+                Some(self.parser.parse_message(&message[..length]))
+            }
+            Err(err) => {
+                // Past a message of unknown length there is no telling
+                // where the next one starts.
+                self.offset = self.data.len();
+                Some(Err(err))
+            }
         }
     }
 }
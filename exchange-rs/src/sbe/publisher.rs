@@ -0,0 +1,380 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+
+use thiserror::Error;
+use tracing::{debug, error};
+
+use crate::l3_feed::{L3Batch, LevelChange};
+use crate::metrics::{LatencyHistogram, LatencyPercentiles};
+use crate::order::Side;
+use crate::price_utils::scaled_to_price;
+use crate::sbe::message_header_codec;
+use crate::sbe::parser::{self, BookMessage, SnapshotLevel, SnapshotMessage};
+use crate::sbe::{
+    book_codec, snapshot_codec, snapshot_end_codec, snapshot_start_codec, BookChange, BookEncoder,
+    BookSide, ChangesListEncoder, Encoder, LevelsListEncoder, SnapshotEncoder, SnapshotEndEncoder,
+    SnapshotStartEncoder, WriteBuf, YesNo,
+};
+
+/// Payload of a 1500 byte Ethernet frame less IPv4 and UDP headers.
+pub const DEFAULT_MTU: usize = 1472;
+
+/// Bytes of the group header in front of a repeating group's entries.
+const GROUP_HEADER_LENGTH: usize = 8;
+
+/// A Book message carrying the level changes `changes` that `batch` made,
+/// as `L3Book::apply_batch` reports them. Its change ids are the batch's
+/// L3 sequence numbers, so `prev_change_id` is the last change before the
+/// batch and a consumer sees a gap as it would in the L3 stream.
+pub fn l3_book_message(
+    instrument_id: u32,
+    timestamp_ms: u64,
+    batch: &L3Batch,
+    changes: &[LevelChange],
+) -> BookMessage {
+    BookMessage {
+        instrument_id,
+        timestamp_ms,
+        prev_change_id: batch.first_sequence().saturating_sub(1),
+        change_id: batch.last_sequence(),
+        is_last: true,
+        changes: changes
+            .iter()
+            .map(|level| parser::BookChange {
+                side: match level.side {
+                    Side::Buy => BookSide::bid as u8,
+                    Side::Sell => BookSide::ask as u8,
+                },
+                change: if level.before == 0 {
+                    BookChange::created as u8
+                } else if level.after == 0 {
+                    BookChange::deleted as u8
+                } else {
+                    BookChange::changed as u8
+                },
+                price: scaled_to_price(level.price),
+                amount: level.after as f64,
+            })
+            .collect(),
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PublishError {
+    #[error("Template {template_id} message of {size} bytes exceeds the {mtu} byte MTU")]
+    MessageTooLarge {
+        template_id: u16,
+        size: usize,
+        mtu: usize,
+    },
+}
+
+/// Encoded sizes of each template and the publisher's datagram counts.
+#[derive(Default)]
+pub struct PublisherMetrics {
+    message_sizes: BTreeMap<u16, LatencyHistogram>,
+    datagrams: u64,
+    messages: u64,
+    split_messages: u64,
+    oversized_messages: u64,
+}
+
+impl PublisherMetrics {
+    fn record_message(&mut self, template_id: u16, size: usize) {
+        self.messages += 1;
+        self.message_sizes
+            .entry(template_id)
+            .or_default()
+            .record(size as u64);
+    }
+
+    /// Distribution of encoded sizes, in bytes, of the messages published
+    /// with `template_id`. Parts of a split message count separately.
+    pub fn message_sizes(&self, template_id: u16) -> Option<LatencyPercentiles> {
+        self.message_sizes
+            .get(&template_id)
+            .map(LatencyHistogram::snapshot)
+    }
+
+    pub fn datagrams(&self) -> u64 {
+        self.datagrams
+    }
+
+    pub fn messages(&self) -> u64 {
+        self.messages
+    }
+
+    /// Messages that had to be sent as more than one part.
+    pub fn split_messages(&self) -> u64 {
+        self.split_messages
+    }
+
+    /// Messages refused because they could not fit one datagram.
+    pub fn oversized_messages(&self) -> u64 {
+        self.oversized_messages
+    }
+}
+
+/// Packs SBE messages into datagrams of at most `mtu` bytes. Messages are
+/// appended to the open datagram until the next one would not fit, then a
+/// new datagram is started; consumers split a datagram back into messages
+/// by their encoded lengths. Snapshots too large for one datagram are split
+/// into several `Snapshot` messages between level entries, with
+/// `is_last_in_book` set only on the final part, so `SnapshotAssembler`
+/// reassembles them. Book messages split the same way between changes,
+/// with `is_last` set only on the final part. A message that cannot be
+/// split and does not fit is refused.
+pub struct SbePublisher {
+    mtu: usize,
+    current: Vec<u8>,
+    ready: Vec<Vec<u8>>,
+    metrics: PublisherMetrics,
+}
+
+impl Default for SbePublisher {
+    fn default() -> Self {
+        Self::new(DEFAULT_MTU)
+    }
+}
+
+impl SbePublisher {
+    pub fn new(mtu: usize) -> Self {
+        Self {
+            mtu,
+            current: Vec::with_capacity(mtu),
+            ready: Vec::new(),
+            metrics: PublisherMetrics::default(),
+        }
+    }
+
+    pub fn mtu(&self) -> usize {
+        self.mtu
+    }
+
+    pub fn metrics(&self) -> &PublisherMetrics {
+        &self.metrics
+    }
+
+    pub fn publish_snapshot_start(&mut self, snapshot_delay: u32) -> Result<(), PublishError> {
+        let mut message = vec![0; Self::fixed_length(snapshot_start_codec::SBE_BLOCK_LENGTH)];
+        let encoder = SnapshotStartEncoder::default().wrap(
+            WriteBuf::new(&mut message),
+            message_header_codec::ENCODED_LENGTH,
+        );
+        let mut encoder = encoder.header(0).parent().unwrap();
+        encoder.snapshot_delay(snapshot_delay);
+        self.publish_encoded(&message)
+    }
+
+    pub fn publish_snapshot_end(&mut self) -> Result<(), PublishError> {
+        let mut message = vec![0; Self::fixed_length(snapshot_end_codec::SBE_BLOCK_LENGTH)];
+        let encoder = SnapshotEndEncoder::default().wrap(
+            WriteBuf::new(&mut message),
+            message_header_codec::ENCODED_LENGTH,
+        );
+        encoder.header(0).parent().unwrap();
+        self.publish_encoded(&message)
+    }
+
+    /// Publishes `snapshot`, split over as many messages as the MTU needs.
+    /// Levels keep their order across the parts.
+    pub fn publish_snapshot(&mut self, snapshot: &SnapshotMessage) -> Result<(), PublishError> {
+        let overhead = Self::fixed_length(snapshot_codec::SBE_BLOCK_LENGTH) + GROUP_HEADER_LENGTH;
+        let entry_length = LevelsListEncoder::<SnapshotEncoder>::block_length() as usize;
+        let per_part = (self.mtu.saturating_sub(overhead) / entry_length).min(u16::MAX as usize);
+        if per_part == 0 {
+            return Err(self.refuse(snapshot_codec::SBE_TEMPLATE_ID, overhead + entry_length));
+        }
+
+        let parts: Vec<&[SnapshotLevel]> = if snapshot.levels.is_empty() {
+            vec![&[]]
+        } else {
+            snapshot.levels.chunks(per_part).collect()
+        };
+        if parts.len() > 1 {
+            self.metrics.split_messages += 1;
+            debug!(
+                "Splitting snapshot of instrument {} into {} messages",
+                snapshot.instrument_id,
+                parts.len()
+            );
+        }
+
+        let last = parts.len() - 1;
+        for (index, levels) in parts.into_iter().enumerate() {
+            let message = Self::encode_snapshot(
+                snapshot,
+                levels,
+                index == last && snapshot.is_last_in_book,
+                overhead + levels.len() * entry_length,
+            );
+            self.publish_encoded(&message)?;
+        }
+        Ok(())
+    }
+
+    /// Publishes `book`, split over as many messages as the MTU needs.
+    /// Every part carries the same change ids and only the final one has
+    /// `is_last` set, if `book` does.
+    pub fn publish_book(&mut self, book: &BookMessage) -> Result<(), PublishError> {
+        let overhead = Self::fixed_length(book_codec::SBE_BLOCK_LENGTH) + GROUP_HEADER_LENGTH;
+        let entry_length = ChangesListEncoder::<BookEncoder>::block_length() as usize;
+        let per_part = (self.mtu.saturating_sub(overhead) / entry_length).min(u16::MAX as usize);
+        if per_part == 0 {
+            return Err(self.refuse(book_codec::SBE_TEMPLATE_ID, overhead + entry_length));
+        }
+
+        let parts: Vec<&[parser::BookChange]> = if book.changes.is_empty() {
+            vec![&[]]
+        } else {
+            book.changes.chunks(per_part).collect()
+        };
+        if parts.len() > 1 {
+            self.metrics.split_messages += 1;
+            debug!(
+                "Splitting book change {} of instrument {} into {} messages",
+                book.change_id,
+                book.instrument_id,
+                parts.len()
+            );
+        }
+
+        let last = parts.len() - 1;
+        for (index, changes) in parts.into_iter().enumerate() {
+            let message = Self::encode_book(
+                book,
+                changes,
+                index == last && book.is_last,
+                overhead + changes.len() * entry_length,
+            );
+            self.publish_encoded(&message)?;
+        }
+        Ok(())
+    }
+
+    /// Publishes one already encoded message, which is never split.
+    pub fn publish_encoded(&mut self, message: &[u8]) -> Result<(), PublishError> {
+        let template_id = u16::from_le_bytes([message[2], message[3]]);
+        if message.len() > self.mtu {
+            return Err(self.refuse(template_id, message.len()));
+        }
+
+        if self.current.len() + message.len() > self.mtu {
+            self.flush();
+        }
+        self.current.extend_from_slice(message);
+        self.metrics.record_message(template_id, message.len());
+        Ok(())
+    }
+
+    /// Closes the open datagram, if it holds anything.
+    pub fn flush(&mut self) {
+        if self.current.is_empty() {
+            return;
+        }
+        let datagram = std::mem::replace(&mut self.current, Vec::with_capacity(self.mtu));
+        self.ready.push(datagram);
+        self.metrics.datagrams += 1;
+    }
+
+    /// Every datagram completed so far, the open one included.
+    pub fn take_datagrams(&mut self) -> Vec<Vec<u8>> {
+        self.flush();
+        std::mem::take(&mut self.ready)
+    }
+
+    /// Sends every completed datagram to `target`. Returns how many were
+    /// sent.
+    pub fn send(&mut self, socket: &UdpSocket, target: SocketAddr) -> io::Result<usize> {
+        let datagrams = self.take_datagrams();
+        for datagram in &datagrams {
+            socket.send_to(datagram, target)?;
+        }
+        Ok(datagrams.len())
+    }
+
+    fn refuse(&mut self, template_id: u16, size: usize) -> PublishError {
+        self.metrics.oversized_messages += 1;
+        error!(
+            "Template {} message of {} bytes does not fit the {} byte MTU",
+            template_id, size, self.mtu
+        );
+        PublishError::MessageTooLarge {
+            template_id,
+            size,
+            mtu: self.mtu,
+        }
+    }
+
+    fn fixed_length(block_length: u16) -> usize {
+        message_header_codec::ENCODED_LENGTH + block_length as usize
+    }
+
+    fn encode_snapshot(
+        snapshot: &SnapshotMessage,
+        levels: &[SnapshotLevel],
+        is_last_in_book: bool,
+        length: usize,
+    ) -> Vec<u8> {
+        let yes_no = |value: bool| if value { YesNo::yes } else { YesNo::no };
+
+        let mut message = vec![0; length];
+        let encoder = SnapshotEncoder::default().wrap(
+            WriteBuf::new(&mut message),
+            message_header_codec::ENCODED_LENGTH,
+        );
+        let mut header = encoder.header(0);
+        header.num_groups(1);
+        let mut encoder = header.parent().unwrap();
+        encoder.instrument_id(snapshot.instrument_id);
+        encoder.timestamp_ms(snapshot.timestamp_ms);
+        encoder.change_id(snapshot.change_id);
+        encoder.is_book_complete(yes_no(snapshot.is_book_complete));
+        encoder.is_last_in_book(yes_no(is_last_in_book));
+
+        let mut entries =
+            encoder.levels_list_encoder(levels.len() as u16, LevelsListEncoder::default());
+        for level in levels {
+            entries.advance().unwrap();
+            entries.side(BookSide::from(level.side));
+            entries.price(level.price);
+            entries.amount(level.amount);
+        }
+        debug_assert_eq!(entries.get_limit(), length);
+        message
+    }
+
+    fn encode_book(
+        book: &BookMessage,
+        changes: &[parser::BookChange],
+        is_last: bool,
+        length: usize,
+    ) -> Vec<u8> {
+        let mut message = vec![0; length];
+        let encoder = BookEncoder::default().wrap(
+            WriteBuf::new(&mut message),
+            message_header_codec::ENCODED_LENGTH,
+        );
+        let mut header = encoder.header(0);
+        header.num_groups(1);
+        let mut encoder = header.parent().unwrap();
+        encoder.instrument_id(book.instrument_id);
+        encoder.timestamp_ms(book.timestamp_ms);
+        encoder.prev_change_id(book.prev_change_id);
+        encoder.change_id(book.change_id);
+        encoder.is_last(if is_last { YesNo::yes } else { YesNo::no });
+
+        let mut entries =
+            encoder.changes_list_encoder(changes.len() as u16, ChangesListEncoder::default());
+        for change in changes {
+            entries.advance().unwrap();
+            entries.side(BookSide::from(change.side));
+            entries.change(BookChange::from(change.change));
+            entries.price(change.price);
+            entries.amount(change.amount);
+        }
+        debug_assert_eq!(entries.get_limit(), length);
+        message
+    }
+}
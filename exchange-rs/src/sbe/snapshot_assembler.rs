@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use thiserror::Error;
+use tracing::{debug, warn};
+
+use crate::sbe::parser::{SbeMessage, SnapshotMessage};
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotAssemblyError {
+    #[error("SnapshotEnd received without a SnapshotStart")]
+    EndWithoutStart,
+    #[error("Snapshot for instrument {0} received outside SnapshotStart/SnapshotEnd")]
+    SnapshotWithoutStart(u32),
+}
+
+/// Full book of one instrument, reassembled from every `Snapshot` message
+/// sent for it in one snapshot cycle. Levels are `(price, amount)`, best
+/// first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderBookSnapshot {
+    pub instrument_id: u32,
+    pub timestamp_ms: u64,
+    pub change_id: u64,
+    /// False if the source sent only part of the book's depth.
+    pub is_book_complete: bool,
+    pub bids: Vec<(f64, f64)>,
+    pub asks: Vec<(f64, f64)>,
+}
+
+impl OrderBookSnapshot {
+    fn start(msg: &SnapshotMessage) -> Self {
+        Self {
+            instrument_id: msg.instrument_id,
+            timestamp_ms: msg.timestamp_ms,
+            change_id: msg.change_id,
+            is_book_complete: msg.is_book_complete,
+            bids: Vec::new(),
+            asks: Vec::new(),
+        }
+    }
+
+    fn extend(&mut self, msg: &SnapshotMessage) {
+        self.timestamp_ms = msg.timestamp_ms;
+        self.is_book_complete = msg.is_book_complete;
+        for level in &msg.levels {
+            match level.side {
+                1 => self.bids.push((level.price, level.amount)),
+                0 => self.asks.push((level.price, level.amount)),
+                _ => warn!("Unknown side in snapshot: {}", level.side),
+            }
+        }
+    }
+
+    fn finish(mut self) -> Self {
+        self.bids.sort_by(|a, b| b.0.total_cmp(&a.0));
+        self.asks.sort_by(|a, b| a.0.total_cmp(&b.0));
+        self
+    }
+}
+
+/// Reassembles multi-part book snapshots. A cycle opens with
+/// `SnapshotStart` and closes with `SnapshotEnd`; in between, each
+/// instrument's levels may be split over several `Snapshot` messages, the
+/// last one flagged `is_last_in_book`.
+#[derive(Debug, Default)]
+pub struct SnapshotAssembler {
+    in_cycle: bool,
+    partial: HashMap<u32, OrderBookSnapshot>,
+    completed: HashMap<u32, OrderBookSnapshot>,
+}
+
+impl SnapshotAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds one message. Returns the instrument's book when the message
+    /// completes it. Messages other than snapshots are ignored.
+    pub fn process(
+        &mut self,
+        message: &SbeMessage,
+    ) -> Result<Option<OrderBookSnapshot>, SnapshotAssemblyError> {
+        match message {
+            SbeMessage::SnapshotStart(_) => {
+                self.discard_partial("SnapshotStart");
+                self.in_cycle = true;
+                Ok(None)
+            }
+            SbeMessage::Snapshot(msg) => self.add_snapshot(msg),
+            SbeMessage::SnapshotEnd(_) => {
+                if !self.in_cycle {
+                    return Err(SnapshotAssemblyError::EndWithoutStart);
+                }
+                self.discard_partial("SnapshotEnd");
+                self.in_cycle = false;
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn add_snapshot(
+        &mut self,
+        msg: &SnapshotMessage,
+    ) -> Result<Option<OrderBookSnapshot>, SnapshotAssemblyError> {
+        if !self.in_cycle {
+            return Err(SnapshotAssemblyError::SnapshotWithoutStart(
+                msg.instrument_id,
+            ));
+        }
+
+        let book = self
+            .partial
+            .entry(msg.instrument_id)
+            .or_insert_with(|| OrderBookSnapshot::start(msg));
+        if book.change_id != msg.change_id {
+            warn!(
+                "Snapshot for instrument {} moved from change {} to {} mid-book, restarting it",
+                msg.instrument_id, book.change_id, msg.change_id
+            );
+            *book = OrderBookSnapshot::start(msg);
+        }
+        book.extend(msg);
+
+        if !msg.is_last_in_book {
+            return Ok(None);
+        }
+
+        let book = self.partial.remove(&msg.instrument_id).unwrap().finish();
+        debug!(
+            "Assembled snapshot for instrument {}: {} bids, {} asks",
+            book.instrument_id,
+            book.bids.len(),
+            book.asks.len()
+        );
+        self.completed.insert(book.instrument_id, book.clone());
+        Ok(Some(book))
+    }
+
+    fn discard_partial(&mut self, reason: &str) {
+        if self.partial.is_empty() {
+            return;
+        }
+        let mut instruments: Vec<u32> = self.partial.keys().copied().collect();
+        instruments.sort_unstable();
+        warn!(
+            "{} arrived with incomplete snapshots for instruments {:?}, discarding them",
+            reason, instruments
+        );
+        self.partial.clear();
+    }
+
+    pub fn is_in_cycle(&self) -> bool {
+        self.in_cycle
+    }
+
+    /// Instruments with a snapshot still being assembled.
+    pub fn pending_instruments(&self) -> usize {
+        self.partial.len()
+    }
+
+    /// Latest completed book of `instrument_id`.
+    pub fn book(&self, instrument_id: u32) -> Option<&OrderBookSnapshot> {
+        self.completed.get(&instrument_id)
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::matching_engine::Trade;
+use crate::orderbook::OrderBook;
+use crate::price_utils::{notional, Notional, Price};
+
+pub const NANOS_PER_DAY: i64 = 86_400_000_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SettlementMethod {
+    #[default]
+    LastTrade,
+    /// Volume-weighted average of the trades in the final `window_ns`
+    /// nanoseconds before the close.
+    Vwap { window_ns: i64 },
+    MidpointAtClose,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SettlementPrice {
+    pub symbol: String,
+    pub trading_day: i64,
+    pub price: Price,
+    pub method: SettlementMethod,
+    /// Set when the methodology produced no price and the previous
+    /// settlement was carried forward.
+    pub carried_forward: bool,
+}
+
+pub fn trading_day(timestamp: i64) -> i64 {
+    timestamp / NANOS_PER_DAY
+}
+
+pub fn calculate_settlement_price(
+    method: SettlementMethod,
+    trades: &[Trade],
+    order_book: &OrderBook,
+    close_time: i64,
+) -> Option<Price> {
+    match method {
+        SettlementMethod::LastTrade => trades
+            .iter()
+            .filter(|t| t.timestamp <= close_time)
+            .max_by_key(|t| (t.timestamp, t.id))
+            .map(|t| t.price),
+        SettlementMethod::Vwap { window_ns } => {
+            let window_start = close_time.saturating_sub(window_ns);
+            let (total, volume) = trades
+                .iter()
+                .filter(|t| t.timestamp > window_start && t.timestamp <= close_time)
+                .fold((0, 0), |(total, volume): (Notional, Notional), t| {
+                    (
+                        total + notional(t.price, t.quantity),
+                        volume + t.quantity as Notional,
+                    )
+                });
+
+            total.checked_div(volume).map(|price| price as Price)
+        }
+        SettlementMethod::MidpointAtClose => {
+            let bid = order_book.get_best_bid_price()?;
+            let ask = order_book.get_best_ask_price()?;
+            Some((bid as Notional + ask as Notional).div_euclid(2) as Price)
+        }
+    }
+}
+
+/// Settlement prices by symbol and trading day.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct SettlementStore {
+    prices: BTreeMap<String, BTreeMap<i64, SettlementPrice>>,
+}
+
+impl SettlementStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, settlement: SettlementPrice) {
+        self.prices
+            .entry(settlement.symbol.clone())
+            .or_default()
+            .insert(settlement.trading_day, settlement);
+    }
+
+    pub fn get(&self, symbol: &str, trading_day: i64) -> Option<&SettlementPrice> {
+        self.prices.get(symbol)?.get(&trading_day)
+    }
+
+    /// Most recent settlement strictly before `trading_day`.
+    pub fn previous(&self, symbol: &str, trading_day: i64) -> Option<&SettlementPrice> {
+        self.prices
+            .get(symbol)?
+            .range(..trading_day)
+            .next_back()
+            .map(|(_, settlement)| settlement)
+    }
+
+    pub fn history(&self, symbol: &str) -> Vec<SettlementPrice> {
+        self.prices
+            .get(symbol)
+            .map(|days| days.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn save_to_file(&self, path: &str) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)
+    }
+
+    pub fn load_from_file(path: &str) -> std::io::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+}
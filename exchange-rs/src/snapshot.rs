@@ -1,27 +1,90 @@
 use parking_lot::RwLock;
-use serde::{Deserialize, Serialize};
+use serde::ser::SerializeStruct;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize, Serializer};
 use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
 use std::sync::Arc;
+use thiserror::Error;
 
-use super::order::{Order, OrderStatus, OrderType, Side, TimeInForce};
-use super::orderbook::OrderBook;
+use super::clock::{Clock, SystemClock};
+use super::contingent::{ContingentOrder, ContingentTrigger};
+use super::fees::FeeSchedule;
+use super::order::{Order, OrderStatus, OrderType, Side, TimeInForce, TriggerSource};
+use super::price_utils::{Notional, Price};
+use super::orderbook::{
+    DepthLevel, MatchPolicy, OrderBook, OrderTypeRules, PriceBands, SymbolSpec, TradingState,
+};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Deserialize)]
 pub struct OrderSnapshot {
     pub id: u64,
     pub symbol: String,
     pub side: Side,
     pub order_type: OrderType,
-    pub price: u64,
+    pub price: Price,
     pub quantity: u32,
     pub filled_quantity: u32,
     pub status: OrderStatus,
     pub time_in_force: TimeInForce,
     pub display_quantity: Option<u32>,
-    pub stop_price: Option<u64>,
+    pub stop_price: Option<Price>,
+    #[serde(default)]
+    pub trigger_source: Option<TriggerSource>,
     pub timestamp: i64,
     pub user_id: u64,
     pub expiration_time: i64,
+    pub max_slippage_bps: Option<u32>,
+    #[serde(default)]
+    pub protection_price: Option<Price>,
+    pub contingent_id: Option<u64>,
+    #[serde(default)]
+    pub fill_notional: Notional,
+    #[serde(default)]
+    pub last_update: i64,
+    #[serde(default)]
+    pub received_at: i64,
+    /// Left out of JSON when unset, so books without client ids keep their
+    /// digest.
+    #[serde(default)]
+    pub client_order_id: Option<String>,
+}
+
+impl Serialize for OrderSnapshot {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Binary formats read fields by position, so they always get
+        // `client_order_id`.
+        let skip_client_order_id = self.client_order_id.is_none() && serializer.is_human_readable();
+        let mut state = serializer.serialize_struct("OrderSnapshot", 22)?;
+        state.serialize_field("id", &self.id)?;
+        state.serialize_field("symbol", &self.symbol)?;
+        state.serialize_field("side", &self.side)?;
+        state.serialize_field("order_type", &self.order_type)?;
+        state.serialize_field("price", &self.price)?;
+        state.serialize_field("quantity", &self.quantity)?;
+        state.serialize_field("filled_quantity", &self.filled_quantity)?;
+        state.serialize_field("status", &self.status)?;
+        state.serialize_field("time_in_force", &self.time_in_force)?;
+        state.serialize_field("display_quantity", &self.display_quantity)?;
+        state.serialize_field("stop_price", &self.stop_price)?;
+        state.serialize_field("trigger_source", &self.trigger_source)?;
+        state.serialize_field("timestamp", &self.timestamp)?;
+        state.serialize_field("user_id", &self.user_id)?;
+        state.serialize_field("expiration_time", &self.expiration_time)?;
+        state.serialize_field("max_slippage_bps", &self.max_slippage_bps)?;
+        state.serialize_field("protection_price", &self.protection_price)?;
+        state.serialize_field("contingent_id", &self.contingent_id)?;
+        state.serialize_field("fill_notional", &self.fill_notional)?;
+        state.serialize_field("last_update", &self.last_update)?;
+        state.serialize_field("received_at", &self.received_at)?;
+        if skip_client_order_id {
+            state.skip_field("client_order_id")?;
+        } else {
+            state.serialize_field("client_order_id", &self.client_order_id)?;
+        }
+        state.end()
+    }
 }
 
 impl From<&Order> for OrderSnapshot {
@@ -38,42 +101,397 @@ impl From<&Order> for OrderSnapshot {
             time_in_force: order.time_in_force,
             display_quantity: order.display_quantity,
             stop_price: order.stop_price,
+            trigger_source: order.trigger_source,
             timestamp: order.timestamp,
             user_id: order.user_id,
             expiration_time: order.expiration_time,
+            max_slippage_bps: order.max_slippage_bps,
+            protection_price: order.protection_price,
+            contingent_id: order.contingent_id,
+            fill_notional: order.fill_notional,
+            last_update: order.last_update,
+            received_at: order.received_at,
+            client_order_id: order.client_order_id.clone(),
         }
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct PriceLevelSnapshot {
-    pub price: u64,
+    pub price: Price,
     pub orders: Vec<OrderSnapshot>,
     pub total_volume: u64,
     pub visible_volume: u64,
 }
 
+impl PriceLevelSnapshot {
+    /// The level as `detailed_market_depth` shows it. The order count and
+    /// oldest order come from `orders`, so they add nothing to the
+    /// serialized snapshot.
+    pub fn depth_level(&self) -> DepthLevel {
+        DepthLevel {
+            price: self.price,
+            visible: self.visible_volume,
+            total: self.total_volume,
+            orders: self.orders.len(),
+            oldest_ts: self.orders.iter().map(|order| order.received_at).min(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct L3OrderEntry {
+    pub order: OrderSnapshot,
+    pub visible_quantity: u32,
+    pub hidden_quantity: u32,
+}
+
+impl From<&Order> for L3OrderEntry {
+    fn from(order: &Order) -> Self {
+        let visible_quantity = order.visible_quantity();
+        Self {
+            order: OrderSnapshot::from(order),
+            visible_quantity,
+            hidden_quantity: order.remaining_quantity() - visible_quantity,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct L3Level {
+    pub price: Price,
+    pub orders: Vec<L3OrderEntry>,
+}
+
+/// Order-by-order view of the book. Levels are in price priority and the
+/// orders within a level are in queue order.
 #[derive(Serialize, Deserialize)]
+pub struct L3Snapshot {
+    pub symbol: String,
+    pub bids: Vec<L3Level>,
+    pub asks: Vec<L3Level>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ContingentOrderSnapshot {
+    pub id: u64,
+    pub user_id: u64,
+    pub trigger: ContingentTrigger,
+    pub order: OrderSnapshot,
+}
+
+impl From<&ContingentOrder> for ContingentOrderSnapshot {
+    fn from(contingent: &ContingentOrder) -> Self {
+        Self {
+            id: contingent.id,
+            user_id: contingent.user_id,
+            trigger: contingent.trigger.clone(),
+            order: OrderSnapshot::from(&contingent.order),
+        }
+    }
+}
+
+impl ContingentOrderSnapshot {
+    pub fn restore(&self) -> ContingentOrder {
+        ContingentOrder {
+            id: self.id,
+            user_id: self.user_id,
+            trigger: self.trigger.clone(),
+            order: self.order.to_order(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct OrderBookSnapshot {
     pub symbol: String,
-    pub buy_levels: HashMap<u64, PriceLevelSnapshot>,
-    pub sell_levels: HashMap<u64, PriceLevelSnapshot>,
+    pub buy_levels: HashMap<Price, PriceLevelSnapshot>,
+    pub sell_levels: HashMap<Price, PriceLevelSnapshot>,
     pub stop_orders: Vec<OrderSnapshot>,
-    pub last_trade_price: Option<u64>,
+    pub last_trade_price: Option<Price>,
+    #[serde(default)]
+    pub mark_price: Option<Price>,
+    #[serde(default)]
+    pub index_price: Option<Price>,
+    #[serde(default)]
+    pub stop_trigger_source: TriggerSource,
+    #[serde(default)]
+    pub match_policy: MatchPolicy,
+    #[serde(default)]
+    pub trading_state: TradingState,
+    #[serde(default)]
+    pub price_bands: PriceBands,
+    #[serde(default)]
+    pub order_type_rules: OrderTypeRules,
+    #[serde(default)]
+    pub fee_schedule: FeeSchedule,
+    #[serde(default)]
+    pub spec: SymbolSpec,
+    /// Levels per side `checksum` covers, the book's depth window.
+    #[serde(default)]
+    pub checksum_levels: usize,
+    /// `OrderBook::checksum(checksum_levels)` when the snapshot was taken.
+    #[serde(default)]
+    pub checksum: u32,
+}
+
+/// Encoding of the snapshot inside a snapshot file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    Json,
+    Bincode,
+}
+
+impl SnapshotFormat {
+    fn code(self) -> u8 {
+        match self {
+            SnapshotFormat::Json => 1,
+            SnapshotFormat::Bincode => 2,
+        }
+    }
+
+    fn from_code(code: u8) -> Option<Self> {
+        match code {
+            1 => Some(SnapshotFormat::Json),
+            2 => Some(SnapshotFormat::Bincode),
+            _ => None,
+        }
+    }
+}
+
+/// First bytes of every snapshot file.
+pub const SNAPSHOT_FILE_MAGIC: [u8; 4] = *b"OBSN";
+/// Layout version of the snapshot file header and payload.
+pub const SNAPSHOT_FILE_VERSION: u16 = 1;
+
+#[derive(Error, Debug)]
+pub enum SnapshotFileError {
+    #[error("Snapshot file I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Not a snapshot file")]
+    BadMagic,
+    #[error("Snapshot file version {found} is not supported, expected {expected}")]
+    UnsupportedVersion { found: u16, expected: u16 },
+    #[error("Unknown snapshot format code {0}")]
+    UnknownFormat(u8),
+    #[error("Snapshot file is cut short")]
+    Truncated,
+    #[error("Snapshot checksum mismatch: header says {expected:#010x}, payload hashes to {actual:#010x}")]
+    ChecksumMismatch { expected: u32, actual: u32 },
+    #[error("Snapshot header names {header}, payload holds {payload}")]
+    SymbolMismatch { header: String, payload: String },
+    #[error("Snapshot encoding error: {0}")]
+    Encoding(String),
+}
+
+/// What a snapshot file says about itself ahead of the snapshot. Reading
+/// it checks the file whole: magic, version, length and checksum.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotFileHeader {
+    pub version: u16,
+    pub format: SnapshotFormat,
+    /// Symbol of a book snapshot; empty for an engine-wide one.
+    pub symbol: String,
+    /// When the file was written, in nanoseconds since the Unix epoch.
+    pub timestamp: i64,
+    /// CRC32 of the payload.
+    pub checksum: u32,
+}
+
+impl SnapshotFileHeader {
+    /// Reads and checks the header of the snapshot file at `path`.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, SnapshotFileError> {
+        let bytes = fs::read(path)?;
+        Self::parse(&bytes).map(|(header, _)| header)
+    }
+
+    /// Splits a snapshot file into its header and payload. Layout, all
+    /// little-endian: magic, `u16` version, `u8` format, `u16` symbol
+    /// length and the symbol, `i64` timestamp, `u32` payload checksum,
+    /// `u64` payload length, payload.
+    fn parse(bytes: &[u8]) -> Result<(Self, &[u8]), SnapshotFileError> {
+        let mut reader = HeaderReader { bytes, offset: 0 };
+        if reader.take(SNAPSHOT_FILE_MAGIC.len())? != SNAPSHOT_FILE_MAGIC {
+            return Err(SnapshotFileError::BadMagic);
+        }
+        let version = u16::from_le_bytes(reader.array()?);
+        if version != SNAPSHOT_FILE_VERSION {
+            return Err(SnapshotFileError::UnsupportedVersion {
+                found: version,
+                expected: SNAPSHOT_FILE_VERSION,
+            });
+        }
+        let [code] = reader.array()?;
+        let format =
+            SnapshotFormat::from_code(code).ok_or(SnapshotFileError::UnknownFormat(code))?;
+        let symbol_length = u16::from_le_bytes(reader.array()?) as usize;
+        let symbol = String::from_utf8(reader.take(symbol_length)?.to_vec())
+            .map_err(|e| SnapshotFileError::Encoding(e.to_string()))?;
+        let timestamp = i64::from_le_bytes(reader.array()?);
+        let checksum = u32::from_le_bytes(reader.array()?);
+        let payload_length = u64::from_le_bytes(reader.array()?);
+        let payload = usize::try_from(payload_length)
+            .map_err(|_| SnapshotFileError::Truncated)
+            .and_then(|length| reader.take(length))?;
+
+        let actual = crc32fast::hash(payload);
+        if actual != checksum {
+            return Err(SnapshotFileError::ChecksumMismatch {
+                expected: checksum,
+                actual,
+            });
+        }
+        let header = Self {
+            version,
+            format,
+            symbol,
+            timestamp,
+            checksum,
+        };
+        Ok((header, payload))
+    }
+}
+
+struct HeaderReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> HeaderReader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], SnapshotFileError> {
+        let end = self
+            .offset
+            .checked_add(length)
+            .ok_or(SnapshotFileError::Truncated)?;
+        let taken = self
+            .bytes
+            .get(self.offset..end)
+            .ok_or(SnapshotFileError::Truncated)?;
+        self.offset = end;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], SnapshotFileError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+}
+
+/// Writes `value` to `path` in `format` behind a snapshot file header
+/// naming `symbol`, returning the size of the file. The file is written
+/// aside and renamed into place, so a crash leaves either the old file or
+/// the new one.
+pub(crate) fn write_snapshot_file<T: Serialize>(
+    path: &Path,
+    format: SnapshotFormat,
+    symbol: &str,
+    value: &T,
+) -> Result<u64, SnapshotFileError> {
+    let payload = match format {
+        SnapshotFormat::Json => {
+            serde_json::to_vec(value).map_err(|e| SnapshotFileError::Encoding(e.to_string()))?
+        }
+        SnapshotFormat::Bincode => {
+            bincode::serialize(value).map_err(|e| SnapshotFileError::Encoding(e.to_string()))?
+        }
+    };
+    let symbol_length =
+        u16::try_from(symbol.len()).map_err(|e| SnapshotFileError::Encoding(e.to_string()))?;
+
+    let mut bytes = Vec::with_capacity(32 + symbol.len() + payload.len());
+    bytes.extend_from_slice(&SNAPSHOT_FILE_MAGIC);
+    bytes.extend_from_slice(&SNAPSHOT_FILE_VERSION.to_le_bytes());
+    bytes.push(format.code());
+    bytes.extend_from_slice(&symbol_length.to_le_bytes());
+    bytes.extend_from_slice(symbol.as_bytes());
+    bytes.extend_from_slice(&SystemClock.now_nanos().to_le_bytes());
+    bytes.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+    bytes.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&payload);
+
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    fs::write(&partial, &bytes)?;
+    fs::rename(&partial, path)?;
+    Ok(bytes.len() as u64)
+}
+
+/// Reads a file written by `write_snapshot_file`. Nothing is decoded
+/// until the header and checksum check out, and the header names
+/// `expected_symbol` if one is given.
+pub(crate) fn read_snapshot_file<T: DeserializeOwned>(
+    path: &Path,
+    expected_symbol: Option<&str>,
+) -> Result<(SnapshotFileHeader, T), SnapshotFileError> {
+    decode_snapshot_file(&fs::read(path)?, expected_symbol)
+}
+
+/// `read_snapshot_file` on the bytes of a file already read.
+pub(crate) fn decode_snapshot_file<T: DeserializeOwned>(
+    bytes: &[u8],
+    expected_symbol: Option<&str>,
+) -> Result<(SnapshotFileHeader, T), SnapshotFileError> {
+    let (header, payload) = SnapshotFileHeader::parse(bytes)?;
+    if let Some(expected) = expected_symbol.filter(|expected| *expected != header.symbol) {
+        return Err(SnapshotFileError::SymbolMismatch {
+            header: header.symbol,
+            payload: expected.to_string(),
+        });
+    }
+    let value = match header.format {
+        SnapshotFormat::Json => serde_json::from_slice(payload)
+            .map_err(|e| SnapshotFileError::Encoding(e.to_string()))?,
+        SnapshotFormat::Bincode => bincode::deserialize(payload)
+            .map_err(|e| SnapshotFileError::Encoding(e.to_string()))?,
+    };
+    Ok((header, value))
 }
 
 impl OrderBookSnapshot {
+    /// Writes the snapshot to `path` in `format`, behind a header that
+    /// lets `load_from_file` refuse a damaged or foreign file. The file is
+    /// written aside and renamed into place, so a crash leaves either the
+    /// old file or the new one.
+    pub fn save_to_file(
+        &self,
+        path: impl AsRef<Path>,
+        format: SnapshotFormat,
+    ) -> Result<(), SnapshotFileError> {
+        write_snapshot_file(path.as_ref(), format, &self.symbol, self).map(|_| ())
+    }
+
+    /// Reads a snapshot written by `save_to_file`. Nothing is decoded
+    /// until the header and checksum check out.
+    pub fn load_from_file(path: impl AsRef<Path>) -> Result<Self, SnapshotFileError> {
+        let (header, snapshot): (SnapshotFileHeader, Self) = read_snapshot_file(path.as_ref(), None)?;
+        if snapshot.symbol != header.symbol {
+            return Err(SnapshotFileError::SymbolMismatch {
+                header: header.symbol,
+                payload: snapshot.symbol,
+            });
+        }
+        Ok(snapshot)
+    }
+
     pub fn restore(&self) -> OrderBook {
         let mut book = OrderBook::new(&self.symbol);
+        book.set_match_policy(self.match_policy);
+        book.set_trading_state(self.trading_state);
+        book.set_price_bands(self.price_bands);
+        book.set_order_type_rules(self.order_type_rules.clone());
+        book.set_stop_trigger_source(self.stop_trigger_source);
+        book.mark_price = self.mark_price;
+        book.index_price = self.index_price;
+        book.set_fee_schedule(self.fee_schedule);
+        book.set_spec(self.spec);
 
-        for (_price, level_snapshot) in &self.buy_levels {
+        for level_snapshot in self.buy_levels.values() {
             for order_snapshot in &level_snapshot.orders {
                 let order = Arc::new(RwLock::new(order_snapshot.to_order()));
                 book.add_order(order).unwrap();
             }
         }
 
-        for (_price, level_snapshot) in &self.sell_levels {
+        for level_snapshot in self.sell_levels.values() {
             for order_snapshot in &level_snapshot.orders {
                 let order = Arc::new(RwLock::new(order_snapshot.to_order()));
                 book.add_order(order).unwrap();
@@ -85,16 +503,16 @@ impl OrderBookSnapshot {
             book.add_stop_order(order).unwrap();
         }
 
-        if let Some(price) = self.last_trade_price {
-            book.update_last_trade_price(price).unwrap();
-        }
+        // Set directly: going through `update_last_trade_price` would take
+        // stops it triggers off the book.
+        book.last_trade_price = self.last_trade_price;
 
         book
     }
 }
 
 impl OrderSnapshot {
-    fn to_order(&self) -> Order {
+    pub(crate) fn to_order(&self) -> Order {
         Order {
             id: self.id,
             symbol: self.symbol.clone(),
@@ -107,9 +525,17 @@ impl OrderSnapshot {
             time_in_force: self.time_in_force,
             display_quantity: self.display_quantity,
             stop_price: self.stop_price,
+            trigger_source: self.trigger_source,
             timestamp: self.timestamp,
             user_id: self.user_id,
             expiration_time: self.expiration_time,
+            max_slippage_bps: self.max_slippage_bps,
+            protection_price: self.protection_price,
+            contingent_id: self.contingent_id,
+            fill_notional: self.fill_notional,
+            last_update: self.last_update,
+            received_at: self.received_at,
+            client_order_id: self.client_order_id.clone(),
         }
     }
 }
@@ -0,0 +1,387 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::matching_engine::EngineSnapshot;
+use crate::order::{OrderStatus, OrderType, Side};
+use crate::orderbook::OrderBook;
+use crate::price_utils::Price;
+use crate::snapshot::{OrderBookSnapshot, OrderSnapshot, PriceLevelSnapshot};
+
+/// One field whose value differs, with both values as they serialize.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+/// The identifying fields of an order that appeared or disappeared.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderSummary {
+    pub id: u64,
+    pub user_id: u64,
+    pub side: Side,
+    pub order_type: OrderType,
+    pub price: Price,
+    pub stop_price: Option<Price>,
+    pub quantity: u32,
+    pub filled_quantity: u32,
+    pub status: OrderStatus,
+}
+
+impl From<&OrderSnapshot> for OrderSummary {
+    fn from(order: &OrderSnapshot) -> Self {
+        Self {
+            id: order.id,
+            user_id: order.user_id,
+            side: order.side,
+            order_type: order.order_type,
+            price: order.price,
+            stop_price: order.stop_price,
+            quantity: order.quantity,
+            filled_quantity: order.filled_quantity,
+            status: order.status,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderChange {
+    pub id: u64,
+    pub changes: Vec<FieldChange>,
+}
+
+/// Total quantity at one price before and after; zero where the level is
+/// absent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LevelChange {
+    pub side: Side,
+    pub price: Price,
+    pub before: u64,
+    pub after: u64,
+}
+
+/// Orders of one kind, resting or stop, that changed between snapshots.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OrderChanges {
+    pub added: Vec<OrderSummary>,
+    pub removed: Vec<OrderSummary>,
+    pub modified: Vec<OrderChange>,
+}
+
+impl OrderChanges {
+    fn between(before: &[&OrderSnapshot], after: &[&OrderSnapshot]) -> Self {
+        let before: BTreeMap<u64, &OrderSnapshot> =
+            before.iter().map(|order| (order.id, *order)).collect();
+        let after: BTreeMap<u64, &OrderSnapshot> =
+            after.iter().map(|order| (order.id, *order)).collect();
+
+        let mut changes = Self::default();
+        for (id, order) in &after {
+            match before.get(id) {
+                None => changes.added.push(OrderSummary::from(*order)),
+                Some(previous) => {
+                    let fields = field_changes(&to_value(*previous), &to_value(*order));
+                    if !fields.is_empty() {
+                        changes.modified.push(OrderChange {
+                            id: *id,
+                            changes: fields,
+                        });
+                    }
+                }
+            }
+        }
+        changes.removed = before
+            .iter()
+            .filter(|(id, _)| !after.contains_key(id))
+            .map(|(_, order)| OrderSummary::from(*order))
+            .collect();
+        changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// Everything that differs between two snapshots of one book.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotDiff {
+    pub symbol: String,
+    pub orders: OrderChanges,
+    pub levels: Vec<LevelChange>,
+    pub stop_orders: OrderChanges,
+    /// Book-wide settings and reference prices that changed.
+    pub book: Vec<FieldChange>,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+            && self.levels.is_empty()
+            && self.stop_orders.is_empty()
+            && self.book.is_empty()
+    }
+}
+
+impl OrderBookSnapshot {
+    /// Changes that turn `self` into `other`.
+    pub fn diff(&self, other: &OrderBookSnapshot) -> SnapshotDiff {
+        let mut levels = level_changes(Side::Buy, &self.buy_levels, &other.buy_levels);
+        levels.extend(level_changes(
+            Side::Sell,
+            &self.sell_levels,
+            &other.sell_levels,
+        ));
+
+        SnapshotDiff {
+            symbol: other.symbol.clone(),
+            orders: OrderChanges::between(&resting(self), &resting(other)),
+            levels,
+            stop_orders: OrderChanges::between(
+                &self.stop_orders.iter().collect::<Vec<_>>(),
+                &other.stop_orders.iter().collect::<Vec<_>>(),
+            ),
+            book: field_changes(&book_fields(self), &book_fields(other)),
+        }
+    }
+}
+
+fn resting(snapshot: &OrderBookSnapshot) -> Vec<&OrderSnapshot> {
+    snapshot
+        .buy_levels
+        .values()
+        .chain(snapshot.sell_levels.values())
+        .flat_map(|level| level.orders.iter())
+        .collect()
+}
+
+fn to_value<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).expect("snapshots serialize to JSON")
+}
+
+/// The book's own fields, without its levels and stop orders or the
+/// checksum derived from the levels.
+fn book_fields(snapshot: &OrderBookSnapshot) -> Value {
+    let mut value = to_value(snapshot);
+    if let Value::Object(fields) = &mut value {
+        for nested in [
+            "symbol",
+            "buy_levels",
+            "sell_levels",
+            "stop_orders",
+            "checksum_levels",
+            "checksum",
+        ] {
+            fields.remove(nested);
+        }
+    }
+    value
+}
+
+/// Top-level fields of two JSON objects that differ, by field name.
+fn field_changes(before: &Value, after: &Value) -> Vec<FieldChange> {
+    let (Value::Object(before), Value::Object(after)) = (before, after) else {
+        return Vec::new();
+    };
+    let names: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let old = before.get(name).cloned().unwrap_or(Value::Null);
+            let new = after.get(name).cloned().unwrap_or(Value::Null);
+            (old != new).then(|| FieldChange {
+                field: name.clone(),
+                before: old,
+                after: new,
+            })
+        })
+        .collect()
+}
+
+fn level_changes(
+    side: Side,
+    before: &HashMap<Price, PriceLevelSnapshot>,
+    after: &HashMap<Price, PriceLevelSnapshot>,
+) -> Vec<LevelChange> {
+    let prices: BTreeSet<Price> = before.keys().chain(after.keys()).copied().collect();
+    prices
+        .into_iter()
+        .filter_map(|price| {
+            let quantity = |levels: &HashMap<Price, PriceLevelSnapshot>| {
+                levels.get(&price).map_or(0, |level| level.total_volume)
+            };
+            let (old, new) = (quantity(before), quantity(after));
+            (old != new).then_some(LevelChange {
+                side,
+                price,
+                before: old,
+                after: new,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffSummary {
+    pub symbols_changed: usize,
+    pub orders_added: usize,
+    pub orders_removed: usize,
+    pub orders_modified: usize,
+    pub levels_changed: usize,
+    pub stop_orders_added: usize,
+    pub stop_orders_removed: usize,
+    pub stop_orders_modified: usize,
+}
+
+/// Per-symbol differences between two engine snapshots. Symbols only in
+/// one snapshot are diffed against an empty book.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EngineSnapshotDiff {
+    pub symbols_added: Vec<String>,
+    pub symbols_removed: Vec<String>,
+    /// Books that changed, by symbol.
+    pub books: Vec<SnapshotDiff>,
+    pub summary: DiffSummary,
+}
+
+impl EngineSnapshot {
+    pub fn diff(&self, other: &EngineSnapshot) -> EngineSnapshotDiff {
+        let (before, after) = (self.order_books(), other.order_books());
+        let symbols: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+
+        let mut diff = EngineSnapshotDiff {
+            symbols_added: Vec::new(),
+            symbols_removed: Vec::new(),
+            books: Vec::new(),
+            summary: DiffSummary::default(),
+        };
+        for symbol in symbols {
+            let empty = || OrderBook::new(symbol).create_snapshot();
+            let book = match (before.get(symbol), after.get(symbol)) {
+                (Some(old), Some(new)) => old.diff(new),
+                (None, Some(new)) => {
+                    diff.symbols_added.push(symbol.clone());
+                    empty().diff(new)
+                }
+                (Some(old), None) => {
+                    diff.symbols_removed.push(symbol.clone());
+                    old.diff(&empty())
+                }
+                (None, None) => continue,
+            };
+            if !book.is_empty() {
+                diff.books.push(book);
+            }
+        }
+
+        let summary = &mut diff.summary;
+        summary.symbols_changed = diff.books.len();
+        for book in &diff.books {
+            summary.orders_added += book.orders.added.len();
+            summary.orders_removed += book.orders.removed.len();
+            summary.orders_modified += book.orders.modified.len();
+            summary.levels_changed += book.levels.len();
+            summary.stop_orders_added += book.stop_orders.added.len();
+            summary.stop_orders_removed += book.stop_orders.removed.len();
+            summary.stop_orders_modified += book.stop_orders.modified.len();
+        }
+        diff
+    }
+}
+
+impl fmt::Display for OrderSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {:?} {:?} {} x {}",
+            self.id, self.side, self.order_type, self.price, self.quantity
+        )?;
+        if let Some(stop_price) = self.stop_price {
+            write!(f, " stop {}", stop_price)?;
+        }
+        write!(
+            f,
+            " filled {} {:?} user {}",
+            self.filled_quantity, self.status, self.user_id
+        )
+    }
+}
+
+fn write_field_changes(f: &mut fmt::Formatter<'_>, changes: &[FieldChange]) -> fmt::Result {
+    let rendered: Vec<String> = changes
+        .iter()
+        .map(|change| format!("{} {} -> {}", change.field, change.before, change.after))
+        .collect();
+    write!(f, "{}", rendered.join(", "))
+}
+
+fn write_order_changes(
+    f: &mut fmt::Formatter<'_>,
+    kind: &str,
+    changes: &OrderChanges,
+) -> fmt::Result {
+    for order in &changes.added {
+        writeln!(f, "  + {} {}", kind, order)?;
+    }
+    for order in &changes.removed {
+        writeln!(f, "  - {} {}", kind, order)?;
+    }
+    for order in &changes.modified {
+        write!(f, "  ~ {} {}: ", kind, order.id)?;
+        write_field_changes(f, &order.changes)?;
+        writeln!(f)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for SnapshotDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.symbol)?;
+        write_order_changes(f, "order", &self.orders)?;
+        for level in &self.levels {
+            writeln!(
+                f,
+                "  level {:?} {}: {} -> {}",
+                level.side, level.price, level.before, level.after
+            )?;
+        }
+        write_order_changes(f, "stop", &self.stop_orders)?;
+        if !self.book.is_empty() {
+            write!(f, "  book: ")?;
+            write_field_changes(f, &self.book)?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for EngineSnapshotDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for symbol in &self.symbols_added {
+            writeln!(f, "+ symbol {}", symbol)?;
+        }
+        for symbol in &self.symbols_removed {
+            writeln!(f, "- symbol {}", symbol)?;
+        }
+        for book in &self.books {
+            write!(f, "{}", book)?;
+        }
+        let s = &self.summary;
+        writeln!(
+            f,
+            "{} symbols changed: orders +{} -{} ~{}, levels {}, stops +{} -{} ~{}",
+            s.symbols_changed,
+            s.orders_added,
+            s.orders_removed,
+            s.orders_modified,
+            s.levels_changed,
+            s.stop_orders_added,
+            s.stop_orders_removed,
+            s.stop_orders_modified
+        )
+    }
+}
@@ -0,0 +1,253 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, warn};
+
+use crate::clock::{Clock, SystemClock};
+use crate::matching_engine::MatchingEngine;
+use crate::snapshot::{SnapshotFileError, SnapshotFormat};
+
+const FILE_PREFIX: &str = "snapshot-";
+const FILE_EXTENSION: &str = ".snap";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotSchedulerMetrics {
+    pub snapshots_written: u64,
+    pub failures: u64,
+    pub files_removed: u64,
+    /// How long the engine lock was held to capture the last snapshot.
+    pub last_capture_ns: u64,
+    /// Capture, encoding and writing of the last snapshot.
+    pub last_duration_ns: u64,
+    pub last_size_bytes: u64,
+    pub last_path: Option<PathBuf>,
+}
+
+/// Writes engine-wide snapshots into a directory on a fixed interval and
+/// keeps only the newest `retention` of them. The engine is locked just
+/// long enough to capture the snapshot; encoding and writing happen on a
+/// blocking thread after the lock is released.
+pub struct SnapshotScheduler {
+    writer: Arc<SnapshotWriter>,
+    interval: Duration,
+    running: Option<(watch::Sender<bool>, JoinHandle<()>)>,
+}
+
+struct SnapshotWriter {
+    engine: Arc<Mutex<MatchingEngine>>,
+    directory: PathBuf,
+    retention: usize,
+    format: SnapshotFormat,
+    /// Timestamp in the last file name, so names stay unique and ordered
+    /// even when the clock does not move between two snapshots.
+    last_stamp: AtomicI64,
+    metrics: Mutex<SnapshotSchedulerMetrics>,
+}
+
+impl SnapshotScheduler {
+    /// A scheduler for `engine` writing every `interval` into `directory`,
+    /// keeping at least one file however low `retention` is.
+    pub fn new(
+        engine: Arc<Mutex<MatchingEngine>>,
+        interval: Duration,
+        directory: impl Into<PathBuf>,
+        retention: usize,
+    ) -> Self {
+        Self {
+            writer: Arc::new(SnapshotWriter {
+                engine,
+                directory: directory.into(),
+                retention: retention.max(1),
+                format: SnapshotFormat::Bincode,
+                last_stamp: AtomicI64::new(0),
+                metrics: Mutex::new(SnapshotSchedulerMetrics::default()),
+            }),
+            interval,
+            running: None,
+        }
+    }
+
+    /// Encoding of the snapshot files, bincode unless set. Only takes
+    /// effect before the scheduler is started.
+    pub fn with_format(mut self, format: SnapshotFormat) -> Self {
+        if let Some(writer) = Arc::get_mut(&mut self.writer) {
+            writer.format = format;
+        }
+        self
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.writer.directory
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    pub fn metrics(&self) -> SnapshotSchedulerMetrics {
+        self.writer.metrics.lock().clone()
+    }
+
+    /// Starts writing a snapshot every interval on the current tokio
+    /// runtime, the first one interval from now. Does nothing if already
+    /// started.
+    pub fn start(&mut self) {
+        if self.running.is_some() {
+            return;
+        }
+        let (stop, mut stopped) = watch::channel(false);
+        let writer = Arc::clone(&self.writer);
+        let interval = self.interval;
+        let task = tokio::spawn(async move {
+            let mut ticker =
+                tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        // Failures are counted and logged; the next tick tries again.
+                        let _ = writer.write().await;
+                    }
+                    _ = stopped.changed() => break,
+                }
+            }
+        });
+        self.running = Some((stop, task));
+    }
+
+    /// Stops the timer, waiting for a snapshot being written to finish.
+    pub async fn stop(&mut self) {
+        if let Some((stop, task)) = self.running.take() {
+            let _ = stop.send(true);
+            if let Err(e) = task.await {
+                if e.is_panic() {
+                    std::panic::resume_unwind(e.into_panic());
+                }
+            }
+        }
+    }
+
+    /// Writes a snapshot now, whether or not the scheduler is started, and
+    /// returns the file written. Older files are rotated out as after a
+    /// scheduled snapshot.
+    pub async fn snapshot_now(&self) -> Result<PathBuf, SnapshotFileError> {
+        self.writer.write().await
+    }
+
+    /// Snapshot files in the directory, oldest first.
+    pub fn snapshot_files(&self) -> Result<Vec<PathBuf>, SnapshotFileError> {
+        snapshot_files(&self.writer.directory)
+    }
+}
+
+impl Drop for SnapshotScheduler {
+    fn drop(&mut self) {
+        if let Some((stop, _)) = self.running.take() {
+            let _ = stop.send(true);
+        }
+    }
+}
+
+impl SnapshotWriter {
+    async fn write(self: &Arc<Self>) -> Result<PathBuf, SnapshotFileError> {
+        let started = Instant::now();
+        let snapshot = self.engine.lock().create_snapshot();
+        let captured = started.elapsed();
+
+        let writer = Arc::clone(self);
+        let path = self.directory.join(self.next_file_name());
+        let result = tokio::task::spawn_blocking(move || {
+            fs::create_dir_all(&writer.directory)?;
+            let size = snapshot.save_to_file(&path, writer.format)?;
+            let removed = writer.rotate()?;
+            Ok((path, size, removed))
+        })
+        .await
+        .unwrap_or_else(|e| Err(SnapshotFileError::Io(std::io::Error::other(e))));
+
+        let mut metrics = self.metrics.lock();
+        match result {
+            Ok((path, size, removed)) => {
+                metrics.snapshots_written += 1;
+                metrics.files_removed += removed;
+                metrics.last_capture_ns = captured.as_nanos() as u64;
+                metrics.last_duration_ns = started.elapsed().as_nanos() as u64;
+                metrics.last_size_bytes = size;
+                metrics.last_path = Some(path.clone());
+                debug!(
+                    "Wrote engine snapshot {} ({} bytes, engine locked for {:?})",
+                    path.display(),
+                    size,
+                    captured
+                );
+                Ok(path)
+            }
+            Err(e) => {
+                metrics.failures += 1;
+                warn!(
+                    "Failed to write engine snapshot into {}: {}",
+                    self.directory.display(),
+                    e
+                );
+                Err(e)
+            }
+        }
+    }
+
+    fn next_file_name(&self) -> String {
+        let now = SystemClock.now_nanos();
+        let previous = self
+            .last_stamp
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |last| {
+                Some(now.max(last + 1))
+            })
+            .unwrap();
+        format!(
+            "{}{:020}{}",
+            FILE_PREFIX,
+            now.max(previous + 1),
+            FILE_EXTENSION
+        )
+    }
+
+    /// Removes all but the newest `retention` snapshot files, returning
+    /// how many went.
+    fn rotate(&self) -> Result<u64, SnapshotFileError> {
+        let files = snapshot_files(&self.directory)?;
+        let excess = files.len().saturating_sub(self.retention);
+        for file in &files[..excess] {
+            fs::remove_file(file)?;
+        }
+        Ok(excess as u64)
+    }
+}
+
+/// Files in `directory` named like scheduled snapshots, none if it does
+/// not exist yet. Their names carry
+/// a zero-padded timestamp, so name order is age order.
+fn snapshot_files(directory: &Path) -> Result<Vec<PathBuf>, SnapshotFileError> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with(FILE_PREFIX) && name.ends_with(FILE_EXTENSION) {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
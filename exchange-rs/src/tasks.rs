@@ -0,0 +1,388 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+
+use crate::clock::{system_clock, SharedClock};
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum TaskError {
+    #[error("Task {name} already registered")]
+    DuplicateTask { name: String },
+    #[error("Task {name} depends on unregistered task {dependency}")]
+    UnknownDependency { name: String, dependency: String },
+}
+
+pub type TaskFuture = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+
+/// Builds a fresh run of a task. Called again for every restart.
+pub type TaskFactory = Arc<dyn Fn(TaskContext) -> TaskFuture + Send + Sync>;
+
+pub type HealthCheck = Arc<dyn Fn() -> TaskHealth + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartPolicy {
+    Never,
+    /// Restart after an unexpected exit, waiting `initial_backoff` and
+    /// doubling the wait after each restart up to `max_backoff`.
+    OnFailure {
+        initial_backoff: Duration,
+        max_backoff: Duration,
+    },
+}
+
+#[derive(Clone)]
+pub struct TaskSpec {
+    pub name: String,
+    /// Tasks that must still be running while this one shuts down.
+    pub depends_on: Vec<String>,
+    pub restart: RestartPolicy,
+    pub health_check: Option<HealthCheck>,
+}
+
+impl TaskSpec {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            depends_on: Vec::new(),
+            restart: RestartPolicy::Never,
+            health_check: None,
+        }
+    }
+
+    pub fn depends_on(mut self, name: &str) -> Self {
+        self.depends_on.push(name.to_string());
+        self
+    }
+
+    pub fn restart(mut self, policy: RestartPolicy) -> Self {
+        self.restart = policy;
+        self
+    }
+
+    pub fn health_check(mut self, check: impl Fn() -> TaskHealth + Send + Sync + 'static) -> Self {
+        self.health_check = Some(Arc::new(check));
+        self
+    }
+}
+
+/// Handed to each run of a task: its shutdown signal and activity stamp.
+#[derive(Clone)]
+pub struct TaskContext {
+    shutdown: watch::Receiver<bool>,
+    last_activity: Arc<AtomicI64>,
+    clock: SharedClock,
+}
+
+impl TaskContext {
+    pub fn is_shutdown(&self) -> bool {
+        *self.shutdown.borrow()
+    }
+
+    /// Resolves once shutdown has been requested.
+    pub async fn shutdown_requested(&mut self) {
+        let _ = self.shutdown.wait_for(|stop| *stop).await;
+    }
+
+    /// Records that the task did some work, for health reporting.
+    pub fn record_activity(&self) {
+        self.last_activity
+            .store(self.clock.now_nanos(), Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TaskHealth {
+    Healthy,
+    Unhealthy(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TaskState {
+    Running,
+    /// Died and waiting out its backoff before the watchdog restarts it.
+    Restarting,
+    /// Exited on its own without an error.
+    Finished,
+    Failed(String),
+    Stopped,
+}
+
+/// One entry of the `GET /tasks` body.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskStatus {
+    pub name: String,
+    pub state: TaskState,
+    pub health: TaskHealth,
+    /// Engine clock reading of the last recorded activity, or of the last
+    /// start if the task has not reported any.
+    pub last_activity: i64,
+    pub restarts: u32,
+}
+
+struct TaskEntry {
+    spec: TaskSpec,
+    factory: TaskFactory,
+    shutdown: watch::Sender<bool>,
+    handle: Option<JoinHandle<Result<(), String>>>,
+    state: TaskState,
+    last_activity: Arc<AtomicI64>,
+    restarts: u32,
+    backoff: Duration,
+    restart_at: Option<Instant>,
+}
+
+/// Background tasks of the process, started, watched and stopped in one
+/// place. Clones share the same registry.
+#[derive(Clone)]
+pub struct TaskRegistry {
+    tasks: Arc<Mutex<Vec<TaskEntry>>>,
+    clock: SharedClock,
+}
+
+impl Default for TaskRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TaskRegistry {
+    pub fn new() -> Self {
+        Self::with_clock(system_clock())
+    }
+
+    pub fn with_clock(clock: SharedClock) -> Self {
+        Self {
+            tasks: Arc::new(Mutex::new(Vec::new())),
+            clock,
+        }
+    }
+
+    /// Registers and starts a task. Dependencies must be registered first.
+    pub fn spawn<F, Fut>(&self, spec: TaskSpec, factory: F) -> Result<(), TaskError>
+    where
+        F: Fn(TaskContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        let mut tasks = self.tasks.lock();
+        if tasks.iter().any(|task| task.spec.name == spec.name) {
+            return Err(TaskError::DuplicateTask { name: spec.name });
+        }
+        if let Some(dependency) = spec
+            .depends_on
+            .iter()
+            .find(|dependency| !tasks.iter().any(|task| &task.spec.name == *dependency))
+        {
+            return Err(TaskError::UnknownDependency {
+                name: spec.name.clone(),
+                dependency: dependency.clone(),
+            });
+        }
+
+        let backoff = match spec.restart {
+            RestartPolicy::Never => Duration::ZERO,
+            RestartPolicy::OnFailure {
+                initial_backoff, ..
+            } => initial_backoff,
+        };
+        let (shutdown, _) = watch::channel(false);
+        let mut entry = TaskEntry {
+            spec,
+            factory: Arc::new(move |context| Box::pin(factory(context))),
+            shutdown,
+            handle: None,
+            state: TaskState::Running,
+            last_activity: Arc::new(AtomicI64::new(0)),
+            restarts: 0,
+            backoff,
+            restart_at: None,
+        };
+        self.start(&mut entry);
+        info!("Started task {}", entry.spec.name);
+        tasks.push(entry);
+        Ok(())
+    }
+
+    fn start(&self, entry: &mut TaskEntry) {
+        let context = TaskContext {
+            shutdown: entry.shutdown.subscribe(),
+            last_activity: Arc::clone(&entry.last_activity),
+            clock: Arc::clone(&self.clock),
+        };
+        entry
+            .last_activity
+            .store(self.clock.now_nanos(), Ordering::Relaxed);
+        entry.handle = Some(tokio::spawn((entry.factory)(context)));
+        entry.state = TaskState::Running;
+    }
+
+    /// Records tasks that have exited and restarts restartable ones whose
+    /// backoff has elapsed. The watchdog calls this periodically.
+    pub async fn check(&self) {
+        let exited: Vec<(usize, JoinHandle<Result<(), String>>)> = {
+            let mut tasks = self.tasks.lock();
+            tasks
+                .iter_mut()
+                .enumerate()
+                .filter(|(_, task)| task.handle.as_ref().is_some_and(|h| h.is_finished()))
+                .map(|(index, task)| (index, task.handle.take().unwrap()))
+                .collect()
+        };
+
+        let mut outcomes = Vec::with_capacity(exited.len());
+        for (index, handle) in exited {
+            let outcome = match handle.await {
+                Ok(result) => result,
+                Err(join_error) => Err(join_error.to_string()),
+            };
+            outcomes.push((index, outcome));
+        }
+
+        let now = Instant::now();
+        let mut tasks = self.tasks.lock();
+        for (index, outcome) in outcomes {
+            let task = &mut tasks[index];
+            let stopping = *task.shutdown.borrow();
+            task.state = match (&outcome, stopping) {
+                (_, true) => TaskState::Stopped,
+                (Ok(()), false) => TaskState::Finished,
+                (Err(reason), false) => TaskState::Failed(reason.clone()),
+            };
+            if stopping {
+                continue;
+            }
+
+            match &outcome {
+                Ok(()) => warn!("Task {} exited", task.spec.name),
+                Err(reason) => error!("Task {} failed: {}", task.spec.name, reason),
+            }
+            if let RestartPolicy::OnFailure { .. } = task.spec.restart {
+                task.state = TaskState::Restarting;
+                task.restart_at = Some(now + task.backoff);
+            }
+        }
+
+        for index in 0..tasks.len() {
+            let task = &mut tasks[index];
+            let due = task.restart_at.is_some_and(|at| at <= now) && !*task.shutdown.borrow();
+            if !due {
+                continue;
+            }
+            if let RestartPolicy::OnFailure { max_backoff, .. } = task.spec.restart {
+                task.restart_at = None;
+                task.restarts += 1;
+                task.backoff = (task.backoff * 2).min(max_backoff);
+                info!(
+                    "Restarting task {} (restart {})",
+                    task.spec.name, task.restarts
+                );
+                self.start(task);
+            }
+        }
+    }
+
+    /// Runs `check` every `interval` until the registry shuts down.
+    pub fn spawn_watchdog(&self, interval: Duration) -> JoinHandle<()> {
+        let registry = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if registry.is_shutting_down() {
+                    break;
+                }
+                registry.check().await;
+            }
+        })
+    }
+
+    fn is_shutting_down(&self) -> bool {
+        let tasks = self.tasks.lock();
+        !tasks.is_empty() && tasks.iter().all(|task| *task.shutdown.borrow())
+    }
+
+    /// Per-task state and health, sorted by name.
+    pub fn statuses(&self) -> Vec<TaskStatus> {
+        let tasks = self.tasks.lock();
+        let mut statuses: Vec<TaskStatus> = tasks
+            .iter()
+            .map(|task| {
+                let health = match &task.state {
+                    TaskState::Running => task
+                        .spec
+                        .health_check
+                        .as_ref()
+                        .map_or(TaskHealth::Healthy, |check| check()),
+                    TaskState::Stopped | TaskState::Finished => TaskHealth::Healthy,
+                    TaskState::Restarting => TaskHealth::Unhealthy("restarting".to_string()),
+                    TaskState::Failed(reason) => TaskHealth::Unhealthy(reason.clone()),
+                };
+                TaskStatus {
+                    name: task.spec.name.clone(),
+                    state: task.state.clone(),
+                    health,
+                    last_activity: task.last_activity.load(Ordering::Relaxed),
+                    restarts: task.restarts,
+                }
+            })
+            .collect();
+        statuses.sort_by(|a, b| a.name.cmp(&b.name));
+        statuses
+    }
+
+    /// Stops every task, dependents before the tasks they depend on. A task
+    /// that does not exit within `timeout` of its signal is aborted. Returns
+    /// the task names in the order they were stopped.
+    pub async fn shutdown(&self, timeout: Duration) -> Vec<String> {
+        let order = self.shutdown_order();
+
+        for name in &order {
+            let handle = {
+                let mut tasks = self.tasks.lock();
+                let task = tasks
+                    .iter_mut()
+                    .find(|task| &task.spec.name == name)
+                    .unwrap();
+                task.shutdown.send_replace(true);
+                task.restart_at = None;
+                task.handle.take()
+            };
+
+            if let Some(mut handle) = handle {
+                if tokio::time::timeout(timeout, &mut handle).await.is_err() {
+                    warn!("Task {} did not stop within {:?}, aborting", name, timeout);
+                    handle.abort();
+                }
+            }
+
+            let mut tasks = self.tasks.lock();
+            let task = tasks
+                .iter_mut()
+                .find(|task| &task.spec.name == name)
+                .unwrap();
+            task.state = TaskState::Stopped;
+            info!("Stopped task {}", name);
+        }
+        order
+    }
+
+    /// Registration order reversed, so every task stops before anything it
+    /// depends on: `spawn` only accepts dependencies registered earlier.
+    fn shutdown_order(&self) -> Vec<String> {
+        self.tasks
+            .lock()
+            .iter()
+            .rev()
+            .map(|task| task.spec.name.clone())
+            .collect()
+    }
+}
@@ -0,0 +1,403 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::clock::SharedClock;
+
+const SLOT_BITS: u32 = 6;
+const SLOTS: usize = 1 << SLOT_BITS;
+const LEVELS: usize = 6;
+/// Ticks covered by the wheel. Deadlines further out than this wait in the
+/// overflow heap until the wheel reaches their window.
+const WHEEL_SPAN: u64 = 1 << (SLOT_BITS as usize * LEVELS);
+
+/// What a timer does when it fires.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimerEvent {
+    /// Expire GTD and Day orders due by the timer's deadline.
+    ExpireOrders,
+    /// The FIX session's heartbeat interval may have passed without inbound
+    /// traffic.
+    TestRequestDue { session: String },
+    /// The FIX session's TestRequest has gone unanswered for an interval.
+    TestRequestTimeout { session: String },
+}
+
+impl TimerEvent {
+    pub fn target(&self) -> TimerTarget {
+        match self {
+            TimerEvent::ExpireOrders => TimerTarget::Engine,
+            TimerEvent::TestRequestDue { session } | TimerEvent::TestRequestTimeout { session } => {
+                TimerTarget::Session(session.clone())
+            }
+        }
+    }
+}
+
+/// The component whose channel a fired timer is delivered on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TimerTarget {
+    Engine,
+    /// A FIX session, keyed `SENDER->TARGET`.
+    Session(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TimerHandle(u64);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FiredTimer {
+    pub handle: TimerHandle,
+    /// Clock reading the timer was scheduled for.
+    pub deadline: i64,
+    /// Clock reading of the tick that fired it.
+    pub fired_at: i64,
+    pub event: TimerEvent,
+}
+
+impl FiredTimer {
+    pub fn lag_nanos(&self) -> i64 {
+        self.fired_at - self.deadline
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimerMetrics {
+    /// Timers scheduled and neither fired nor cancelled.
+    pub pending: usize,
+    pub peak_pending: usize,
+    /// Pending timers beyond the wheel's span, held in the overflow heap.
+    pub overflow: usize,
+    pub scheduled: u64,
+    pub fired: u64,
+    pub cancelled: u64,
+    /// Fired timers with no live channel for their target.
+    pub undelivered: u64,
+    pub last_batch: usize,
+    pub max_batch: usize,
+    /// Largest delay between a deadline and the tick that fired it.
+    pub max_lag_nanos: i64,
+}
+
+struct TimerEntry {
+    tick: u64,
+    deadline: i64,
+    event: TimerEvent,
+}
+
+#[derive(Default)]
+struct WheelLevel {
+    /// Bit `i` is set when slot `i` holds timer ids, some possibly
+    /// cancelled.
+    occupied: u64,
+    slots: Vec<Vec<u64>>,
+}
+
+/// Hierarchical timer wheel: six levels of 64 slots, each slot of a level
+/// spanning a whole turn of the level below. A timer sits in the lowest
+/// level whose turn still contains its deadline and moves down as the wheel
+/// reaches its slot, so advancing visits occupied slots rather than every
+/// tick, however far the clock jumps.
+///
+/// The wheel only moves when told to: `advance_to` fires everything due by
+/// the given clock reading. Cancelled timers are dropped lazily when their
+/// slot comes up.
+pub struct TimerWheel {
+    tick_nanos: i64,
+    /// Last tick the wheel has advanced through.
+    elapsed: u64,
+    levels: Vec<WheelLevel>,
+    overflow: BinaryHeap<Reverse<(u64, u64)>>,
+    /// Timers scheduled at or before `elapsed`, fired on the next advance.
+    ready: Vec<u64>,
+    entries: HashMap<u64, TimerEntry>,
+    next_id: u64,
+    metrics: TimerMetrics,
+}
+
+impl TimerWheel {
+    pub fn new(tick: Duration, now: i64) -> Self {
+        let tick_nanos = (tick.as_nanos() as i64).max(1);
+        let levels = (0..LEVELS)
+            .map(|_| WheelLevel {
+                occupied: 0,
+                slots: vec![Vec::new(); SLOTS],
+            })
+            .collect();
+        Self {
+            tick_nanos,
+            elapsed: now.max(0) as u64 / tick_nanos as u64,
+            levels,
+            overflow: BinaryHeap::new(),
+            ready: Vec::new(),
+            entries: HashMap::new(),
+            next_id: 1,
+            metrics: TimerMetrics::default(),
+        }
+    }
+
+    /// Schedules `event` to fire on the first tick at or after `deadline`.
+    /// A deadline already passed fires on the next advance.
+    pub fn schedule(&mut self, deadline: i64, event: TimerEvent) -> TimerHandle {
+        let id = self.next_id;
+        self.next_id += 1;
+        let tick_nanos = self.tick_nanos as u64;
+        let tick = (deadline.max(0) as u64).div_ceil(tick_nanos);
+        self.entries.insert(
+            id,
+            TimerEntry {
+                tick,
+                deadline,
+                event,
+            },
+        );
+        self.insert(id, tick);
+
+        self.metrics.scheduled += 1;
+        self.metrics.pending = self.entries.len();
+        self.metrics.peak_pending = self.metrics.peak_pending.max(self.metrics.pending);
+        TimerHandle(id)
+    }
+
+    /// Returns false if the timer already fired or was cancelled.
+    pub fn cancel(&mut self, handle: TimerHandle) -> bool {
+        let cancelled = self.entries.remove(&handle.0).is_some();
+        if cancelled {
+            self.metrics.cancelled += 1;
+            self.metrics.pending = self.entries.len();
+        }
+        cancelled
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn metrics(&self) -> TimerMetrics {
+        let mut metrics = self.metrics.clone();
+        metrics.overflow = self
+            .overflow
+            .iter()
+            .filter(|Reverse((_, id))| self.entries.contains_key(id))
+            .count();
+        metrics
+    }
+
+    /// Fires every timer due by `now`, ordered by deadline and then by
+    /// scheduling order. A clock reading behind the wheel fires only timers
+    /// scheduled in the past.
+    pub fn advance_to(&mut self, now: i64) -> Vec<FiredTimer> {
+        let target = now.max(0) as u64 / self.tick_nanos as u64;
+        let mut due = std::mem::take(&mut self.ready);
+
+        loop {
+            if let Some((level, slot, start)) = self.next_slot() {
+                if start > target {
+                    break;
+                }
+                self.elapsed = self.elapsed.max(start);
+                self.levels[level].occupied &= !(1 << slot);
+                let ids = std::mem::take(&mut self.levels[level].slots[slot]);
+                for id in ids {
+                    if let Some(tick) = self.entries.get(&id).map(|entry| entry.tick) {
+                        self.insert(id, tick);
+                    }
+                }
+                due.append(&mut self.ready);
+                continue;
+            }
+
+            // The wheel is empty: jump to the earliest overflow deadline and
+            // bring its window into the wheel.
+            match self.overflow.peek() {
+                Some(Reverse((tick, _))) if *tick <= target => {
+                    self.elapsed = self.elapsed.max(*tick);
+                    self.refill_from_overflow();
+                    due.append(&mut self.ready);
+                }
+                _ => break,
+            }
+        }
+        if target > self.elapsed {
+            self.elapsed = target;
+            self.refill_from_overflow();
+            due.append(&mut self.ready);
+        }
+
+        let mut fired: Vec<FiredTimer> = due
+            .into_iter()
+            .filter_map(|id| {
+                self.entries.remove(&id).map(|entry| FiredTimer {
+                    handle: TimerHandle(id),
+                    deadline: entry.deadline,
+                    fired_at: now,
+                    event: entry.event,
+                })
+            })
+            .collect();
+        fired.sort_by_key(|timer| (timer.deadline, timer.handle));
+
+        self.metrics.pending = self.entries.len();
+        if !fired.is_empty() {
+            self.metrics.fired += fired.len() as u64;
+            self.metrics.last_batch = fired.len();
+            self.metrics.max_batch = self.metrics.max_batch.max(fired.len());
+            let lag = fired.iter().map(FiredTimer::lag_nanos).max().unwrap_or(0);
+            self.metrics.max_lag_nanos = self.metrics.max_lag_nanos.max(lag);
+        }
+        fired
+    }
+
+    fn insert(&mut self, id: u64, tick: u64) {
+        if tick <= self.elapsed {
+            self.ready.push(id);
+            return;
+        }
+        let differing = tick ^ self.elapsed;
+        let level = ((63 - differing.leading_zeros()) / SLOT_BITS) as usize;
+        if level >= LEVELS {
+            self.overflow.push(Reverse((tick, id)));
+            return;
+        }
+        let slot = ((tick >> (SLOT_BITS as usize * level)) as usize) & (SLOTS - 1);
+        self.levels[level].slots[slot].push(id);
+        self.levels[level].occupied |= 1 << slot;
+    }
+
+    /// Earliest occupied slot as (level, slot, first tick). Every timer in
+    /// a level is later than every timer in the levels below it, and its
+    /// slot is past the wheel's position in that level.
+    fn next_slot(&self) -> Option<(usize, usize, u64)> {
+        self.levels
+            .iter()
+            .enumerate()
+            .find(|(_, level)| level.occupied != 0)
+            .map(|(index, level)| {
+                let slot = level.occupied.trailing_zeros() as usize;
+                let shift = SLOT_BITS as usize * index;
+                let turn_start =
+                    self.elapsed >> (shift + SLOT_BITS as usize) << (shift + SLOT_BITS as usize);
+                (index, slot, turn_start + ((slot as u64) << shift))
+            })
+    }
+
+    fn refill_from_overflow(&mut self) {
+        while let Some(Reverse((tick, id))) = self.overflow.peek().copied() {
+            if tick > self.elapsed && (tick ^ self.elapsed) >= WHEEL_SPAN {
+                break;
+            }
+            self.overflow.pop();
+            if self.entries.contains_key(&id) {
+                self.insert(id, tick);
+            }
+        }
+    }
+}
+
+struct TimerRoutes {
+    wheel: TimerWheel,
+    routes: HashMap<TimerTarget, Sender<Vec<FiredTimer>>>,
+}
+
+/// A timer wheel shared by the engine and the gateways, read against one
+/// clock. Each `tick` fires what is due and delivers it as one batch per
+/// target on the channel that target subscribed. Clones share the wheel.
+#[derive(Clone)]
+pub struct Timers {
+    shared: Arc<Mutex<TimerRoutes>>,
+    clock: SharedClock,
+}
+
+impl Timers {
+    /// A wheel with one-millisecond ticks.
+    pub fn new(clock: SharedClock) -> Self {
+        Self::with_tick(clock, Duration::from_millis(1))
+    }
+
+    pub fn with_tick(clock: SharedClock, tick: Duration) -> Self {
+        let wheel = TimerWheel::new(tick, clock.now_nanos());
+        Self {
+            shared: Arc::new(Mutex::new(TimerRoutes {
+                wheel,
+                routes: HashMap::new(),
+            })),
+            clock,
+        }
+    }
+
+    pub fn clock(&self) -> SharedClock {
+        Arc::clone(&self.clock)
+    }
+
+    /// Channel of the timers fired for `target`, replacing any earlier
+    /// subscription of the same target.
+    pub fn subscribe(&self, target: TimerTarget) -> Receiver<Vec<FiredTimer>> {
+        let (sender, receiver) = unbounded();
+        self.shared.lock().routes.insert(target, sender);
+        receiver
+    }
+
+    pub fn schedule(&self, deadline: i64, event: TimerEvent) -> TimerHandle {
+        self.shared.lock().wheel.schedule(deadline, event)
+    }
+
+    pub fn schedule_after(&self, delay: Duration, event: TimerEvent) -> TimerHandle {
+        let deadline = self.clock.now_nanos() + delay.as_nanos() as i64;
+        self.schedule(deadline, event)
+    }
+
+    pub fn cancel(&self, handle: TimerHandle) -> bool {
+        self.shared.lock().wheel.cancel(handle)
+    }
+
+    pub fn pending(&self) -> usize {
+        self.shared.lock().wheel.len()
+    }
+
+    pub fn metrics(&self) -> TimerMetrics {
+        self.shared.lock().wheel.metrics()
+    }
+
+    /// Fires the timers due at the current clock reading and delivers them.
+    /// Returns how many fired.
+    pub fn tick(&self) -> usize {
+        let now = self.clock.now_nanos();
+        let mut shared = self.shared.lock();
+        let fired = shared.wheel.advance_to(now);
+        let count = fired.len();
+
+        let mut batches: Vec<(TimerTarget, Vec<FiredTimer>)> = Vec::new();
+        for timer in fired {
+            let target = timer.event.target();
+            match batches.iter_mut().find(|(existing, _)| *existing == target) {
+                Some((_, batch)) => batch.push(timer),
+                None => batches.push((target, vec![timer])),
+            }
+        }
+
+        let mut undelivered = 0;
+        for (target, batch) in batches {
+            let delivery = match shared.routes.get(&target) {
+                Some(sender) => sender.send(batch).map_err(|error| error.into_inner()),
+                None => Err(batch),
+            };
+            if let Err(batch) = delivery {
+                warn!("Dropping {} fired timers for {:?}", batch.len(), target);
+                shared.routes.remove(&target);
+                undelivered += batch.len() as u64;
+            }
+        }
+        shared.wheel.metrics.undelivered += undelivered;
+        count
+    }
+}
@@ -0,0 +1,113 @@
+use std::hint::spin_loop;
+use std::sync::atomic::{fence, AtomicU64, Ordering};
+
+use crate::price_utils::Price;
+
+/// Price stored for a side with no resting level.
+const NO_PRICE: Price = Price::MAX;
+
+/// Best bid and offer of a book with their visible sizes. `sequence`
+/// counts the changes published, so a reader can tell a fresh quote from
+/// one it has seen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TopOfBook {
+    pub bid: Option<(Price, u64)>,
+    pub ask: Option<(Price, u64)>,
+    pub sequence: u64,
+}
+
+/// A book's `TopOfBook` behind a seqlock. The book writes it on every
+/// change while it holds itself exclusively; readers on other threads
+/// copy it without a lock and never see a bid from one change next to an
+/// ask from another.
+#[derive(Debug)]
+pub struct TopOfBookCell {
+    /// Twice the published sequence, plus one while a write is under way.
+    version: AtomicU64,
+    /// Prices are kept as their bits, which for a signed `Price` is its
+    /// two's complement.
+    bid_price: AtomicU64,
+    bid_size: AtomicU64,
+    ask_price: AtomicU64,
+    ask_size: AtomicU64,
+}
+
+impl TopOfBookCell {
+    pub fn new() -> Self {
+        Self {
+            version: AtomicU64::new(0),
+            bid_price: AtomicU64::new(bits(NO_PRICE)),
+            bid_size: AtomicU64::new(0),
+            ask_price: AtomicU64::new(bits(NO_PRICE)),
+            ask_size: AtomicU64::new(0),
+        }
+    }
+
+    /// The last published quote. Retries while a write is in progress.
+    pub fn read(&self) -> TopOfBook {
+        loop {
+            let before = self.version.load(Ordering::Acquire);
+            if before % 2 == 1 {
+                spin_loop();
+                continue;
+            }
+            let bid = side(
+                self.bid_price.load(Ordering::Relaxed),
+                self.bid_size.load(Ordering::Relaxed),
+            );
+            let ask = side(
+                self.ask_price.load(Ordering::Relaxed),
+                self.ask_size.load(Ordering::Relaxed),
+            );
+            fence(Ordering::Acquire);
+            if self.version.load(Ordering::Relaxed) == before {
+                return TopOfBook {
+                    bid,
+                    ask,
+                    sequence: before / 2,
+                };
+            }
+        }
+    }
+
+    /// Publishes `bid` and `ask` as the next sequence unless they are what
+    /// is already published. There must be a single writer.
+    pub(crate) fn publish(&self, bid: Option<(Price, u64)>, ask: Option<(Price, u64)>) {
+        let (bid_price, bid_size) = bid.unwrap_or((NO_PRICE, 0));
+        let (ask_price, ask_size) = ask.unwrap_or((NO_PRICE, 0));
+        let (bid_price, ask_price) = (bits(bid_price), bits(ask_price));
+        if self.bid_price.load(Ordering::Relaxed) == bid_price
+            && self.bid_size.load(Ordering::Relaxed) == bid_size
+            && self.ask_price.load(Ordering::Relaxed) == ask_price
+            && self.ask_size.load(Ordering::Relaxed) == ask_size
+        {
+            return;
+        }
+
+        let version = self.version.load(Ordering::Relaxed);
+        self.version.store(version + 1, Ordering::Relaxed);
+        fence(Ordering::Release);
+        self.bid_price.store(bid_price, Ordering::Relaxed);
+        self.bid_size.store(bid_size, Ordering::Relaxed);
+        self.ask_price.store(ask_price, Ordering::Relaxed);
+        self.ask_size.store(ask_size, Ordering::Relaxed);
+        self.version.store(version + 2, Ordering::Release);
+    }
+}
+
+impl Default for TopOfBookCell {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `price` as stored in the cell, its two's complement when signed.
+#[allow(clippy::unnecessary_cast)]
+fn bits(price: Price) -> u64 {
+    price as u64
+}
+
+fn side(bits: u64, size: u64) -> Option<(Price, u64)> {
+    let price = bits as Price;
+    (price != NO_PRICE).then_some((price, size))
+}
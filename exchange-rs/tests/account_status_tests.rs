@@ -0,0 +1,137 @@
+use exchange_rs::{
+    account_status::{
+        AccountMonitor, AccountStatusError, MessageKind, FIX_REPORT_MSG_TYPE, TAG_FILL_RATIO,
+        TAG_OPEN_ORDER_HEADROOM, WS_METHOD,
+    },
+    matching_engine::MatchingEngine,
+    order::{Order, OrderType, Side},
+    risk::RiskLimits,
+    settlement::NANOS_PER_DAY,
+};
+use exchange_rs::Price;
+
+const SECOND: i64 = 1_000_000_000;
+
+fn place(
+    engine: &mut MatchingEngine,
+    monitor: &mut AccountMonitor,
+    side: Side,
+    price: Price,
+    quantity: u32,
+    user_id: u64,
+    now: i64,
+) -> u64 {
+    monitor.record_message(user_id, MessageKind::Order, now);
+    let order = Order::new(
+        "AAPL".to_string(),
+        side,
+        OrderType::Limit,
+        price,
+        quantity,
+        user_id,
+    );
+    let result = engine.place_order(order).unwrap();
+    monitor.record_execution(user_id, &result, now);
+    result.remaining_order.map_or(0, |order| order.read().id)
+}
+
+#[test]
+fn test_scripted_session_reports_own_activity() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    engine.set_user_risk_limits(
+        1,
+        RiskLimits {
+            max_open_orders: Some(5),
+            ..RiskLimits::default()
+        },
+    );
+    let mut monitor = AccountMonitor::new(SECOND);
+
+    place(&mut engine, &mut monitor, Side::Sell, 100, 10, 2, 0);
+    place(&mut engine, &mut monitor, Side::Sell, 101, 10, 2, 0);
+    let resting = place(&mut engine, &mut monitor, Side::Buy, 99, 10, 1, SECOND);
+    place(&mut engine, &mut monitor, Side::Buy, 98, 10, 1, SECOND);
+    place(&mut engine, &mut monitor, Side::Buy, 101, 15, 1, SECOND);
+
+    monitor.record_message(1, MessageKind::Cancel, 2 * SECOND);
+    engine.cancel_order("AAPL", resting).unwrap();
+
+    let status = monitor.query(&engine, 1, 3 * SECOND).unwrap();
+    assert_eq!(status.user_id, 1);
+    assert_eq!(status.open_orders, 1);
+    assert_eq!(status.open_order_headroom, Some(4));
+    assert_eq!(status.orders_today, 3);
+    assert_eq!(status.cancels_today, 1);
+    assert_eq!(status.fills_today, 2);
+    assert_eq!(status.messages_today, 5);
+    assert!((status.fill_ratio - 2.0 / 3.0).abs() < 1e-9);
+
+    let other = monitor.query(&engine, 2, 3 * SECOND).unwrap();
+    assert_eq!(other.orders_today, 2);
+    assert_eq!(other.fills_today, 0);
+    assert_eq!(other.open_order_headroom, None);
+}
+
+#[test]
+fn test_status_queries_are_rate_limited() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    let mut monitor = AccountMonitor::new(SECOND);
+
+    monitor.query(&engine, 1, 0).unwrap();
+    assert_eq!(
+        monitor.query(&engine, 1, SECOND / 4),
+        Err(AccountStatusError::Throttled {
+            retry_after_ns: 3 * SECOND / 4
+        })
+    );
+    assert!(monitor.query(&engine, 2, SECOND / 4).is_ok());
+
+    let status = monitor.query(&engine, 1, SECOND).unwrap();
+    assert_eq!(status.messages_today, 2);
+}
+
+#[test]
+fn test_counters_reset_on_new_trading_day() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    let mut monitor = AccountMonitor::new(SECOND);
+
+    place(
+        &mut engine,
+        &mut monitor,
+        Side::Buy,
+        99,
+        10,
+        1,
+        NANOS_PER_DAY - SECOND,
+    );
+    let status = monitor.query(&engine, 1, NANOS_PER_DAY).unwrap();
+    assert_eq!(status.orders_today, 0);
+    assert_eq!(status.messages_today, 1);
+    assert_eq!(status.open_orders, 1);
+}
+
+#[test]
+fn test_status_encodes_for_ws_and_fix() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    let mut monitor = AccountMonitor::new(SECOND);
+    place(&mut engine, &mut monitor, Side::Buy, 99, 10, 7, 0);
+    let status = monitor.query(&engine, 7, 0).unwrap();
+
+    let ws = status.to_ws_response(42);
+    assert_eq!(ws["id"], 42);
+    assert_eq!(ws["method"], WS_METHOD);
+    assert_eq!(ws["result"]["open_orders"], 1);
+    assert_eq!(ws["result"]["orders_today"], 1);
+
+    let fields = status.to_fix_fields();
+    assert_eq!(FIX_REPORT_MSG_TYPE, "U2");
+    assert_eq!(fields[0], (1, "7".to_string()));
+    assert!(fields
+        .iter()
+        .all(|(tag, _)| *tag != TAG_OPEN_ORDER_HEADROOM));
+    assert_eq!(fields.last(), Some(&(TAG_FILL_RATIO, "0.0000".to_string())));
+}
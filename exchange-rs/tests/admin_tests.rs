@@ -0,0 +1,152 @@
+use exchange_rs::{
+    admin::AdminServer,
+    clock::ManualClock,
+    logging::{LogLevelAudit, LogLevelController, LogLevels},
+    tasks::{TaskHealth, TaskRegistry, TaskSpec, TaskState, TaskStatus},
+};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing_subscriber::{filter::LevelFilter, prelude::*};
+
+fn logging_admin() -> (AdminServer, impl tracing::Subscriber + Send + Sync) {
+    let (filter, controller) = LogLevelController::with_limits(
+        LevelFilter::INFO,
+        LevelFilter::INFO,
+        Arc::new(ManualClock::new(7)),
+    );
+    let subscriber = tracing_subscriber::registry().with(filter);
+    (
+        AdminServer::new().with_logging(Arc::new(controller)),
+        subscriber,
+    )
+}
+
+#[test]
+fn test_logging_endpoint_reads_and_changes_levels() {
+    let (admin, subscriber) = logging_admin();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let response = admin.handle("GET", "/logging", b"");
+    assert_eq!(response.status, 200);
+    let levels: LogLevels = serde_json::from_str(&response.body).unwrap();
+    assert_eq!(levels.default, "info");
+    assert!(levels.targets.is_empty());
+
+    let response = admin.handle(
+        "PUT",
+        "/logging",
+        br#"{"actor":"oncall","target":"exchange_rs::fix","level":"debug"}"#,
+    );
+    assert_eq!(response.status, 200);
+    let audit: LogLevelAudit = serde_json::from_str(&response.body).unwrap();
+    assert_eq!((audit.timestamp, audit.level.as_str()), (7, "debug"));
+
+    let levels: LogLevels =
+        serde_json::from_str(&admin.handle("GET", "/logging", b"").body).unwrap();
+    assert_eq!(levels.targets["exchange_rs::fix"], "debug");
+}
+
+#[test]
+fn test_logging_endpoint_rejects_bad_requests() {
+    let (admin, subscriber) = logging_admin();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let status = |method: &str, path: &str, body: &[u8]| admin.handle(method, path, body).status;
+    assert_eq!(status("PUT", "/logging", b"not json"), 400);
+    assert_eq!(
+        status("PUT", "/logging", br#"{"actor":"oncall","level":"loud"}"#),
+        400
+    );
+    assert_eq!(
+        status(
+            "PUT",
+            "/logging",
+            br#"{"actor":"oncall","target":"exchange_rs::matching_engine","level":"trace"}"#
+        ),
+        409
+    );
+    assert_eq!(status("DELETE", "/logging", b""), 405);
+    assert_eq!(status("GET", "/nothing", b""), 404);
+    assert_eq!(
+        AdminServer::new().handle("GET", "/logging", b"").status,
+        404
+    );
+}
+
+#[tokio::test]
+async fn test_admin_server_answers_over_http() {
+    let (admin, subscriber) = logging_admin();
+    let _guard = tracing::subscriber::set_default(subscriber);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(admin.serve(listener));
+
+    let body = r#"{"actor":"oncall","level":"warn"}"#;
+    let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+    stream
+        .write_all(
+            format!(
+                "PUT /logging HTTP/1.1\r\nHost: admin\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .as_bytes(),
+        )
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    let (_, json) = response.split_once("\r\n\r\n").unwrap();
+    let audit: LogLevelAudit = serde_json::from_str(json).unwrap();
+    assert_eq!(audit.previous.as_deref(), Some("info"));
+
+    let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+    stream
+        .write_all(b"GET /logging HTTP/1.1\r\nHost: admin\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(
+        response.ends_with(r#"{"default":"warn","targets":{}}"#),
+        "{}",
+        response
+    );
+}
+
+#[tokio::test]
+async fn test_tasks_endpoint_lists_registered_tasks() {
+    let registry = TaskRegistry::with_clock(Arc::new(ManualClock::new(42)));
+    registry
+        .spawn(TaskSpec::new("timers"), |mut context| async move {
+            context.shutdown_requested().await;
+            Ok(())
+        })
+        .unwrap();
+    registry
+        .spawn(TaskSpec::new("exporter"), |_| async {
+            Err("socket closed".to_string())
+        })
+        .unwrap();
+    tokio::task::yield_now().await;
+    registry.check().await;
+
+    let admin = AdminServer::new().with_tasks(registry.clone());
+    let response = admin.handle("GET", "/tasks", b"");
+    assert_eq!(response.status, 200);
+    let statuses: Vec<TaskStatus> = serde_json::from_str(&response.body).unwrap();
+    assert_eq!(statuses, registry.statuses());
+    assert_eq!(statuses[0].name, "exporter");
+    assert_eq!(
+        statuses[0].health,
+        TaskHealth::Unhealthy("socket closed".to_string())
+    );
+    assert_eq!(
+        (statuses[1].name.as_str(), &statuses[1].state),
+        ("timers", &TaskState::Running)
+    );
+
+    assert_eq!(admin.handle("PUT", "/tasks", b"").status, 405);
+    assert_eq!(AdminServer::new().handle("GET", "/tasks", b"").status, 404);
+}
@@ -0,0 +1,239 @@
+use std::sync::Arc;
+
+use exchange_rs::admin::AdminServer;
+use exchange_rs::matching_engine::MatchingEngine;
+use exchange_rs::order::{Order, OrderType, Side};
+use exchange_rs::orderbook::{BookInvariantError, OrderBook, PriceLevel, TradingState};
+use exchange_rs::Price;
+use parking_lot::{Mutex, RwLock};
+
+fn order(
+    id: u64,
+    side: Side,
+    order_type: OrderType,
+    price: Price,
+    quantity: u32,
+) -> Arc<RwLock<Order>> {
+    let mut order = Order::new("AAPL".to_string(), side, order_type, price, quantity, id);
+    order.id = id;
+    Arc::new(RwLock::new(order))
+}
+
+fn seeded_book() -> OrderBook {
+    let mut book = OrderBook::new("AAPL");
+    book.add_order(order(1, Side::Buy, OrderType::Limit, 99, 10))
+        .unwrap();
+    book.add_order(order(2, Side::Buy, OrderType::Limit, 99, 5))
+        .unwrap();
+    book.add_order(order(3, Side::Sell, OrderType::Limit, 101, 7))
+        .unwrap();
+    let stop = order(4, Side::Sell, OrderType::StopLimit, 95, 3);
+    stop.write().stop_price = Some(96);
+    book.add_stop_order(stop).unwrap();
+    book
+}
+
+#[test]
+fn test_consistent_book_validates() {
+    let mut book = seeded_book();
+    assert_eq!(book.validate(), Ok(()));
+
+    book.remove_order(2).unwrap();
+    book.remove_order(4).unwrap();
+    assert_eq!(book.validate(), Ok(()));
+}
+
+#[test]
+fn test_crossed_book_names_both_fronts() {
+    let mut book = seeded_book();
+    book.add_order(order(5, Side::Buy, OrderType::Limit, 102, 1))
+        .unwrap();
+    assert_eq!(
+        book.validate(),
+        Err(BookInvariantError::Crossed {
+            symbol: "AAPL".to_string(),
+            bid_price: 102,
+            bid_order_id: Some(5),
+            ask_price: 101,
+            ask_order_id: Some(3),
+        })
+    );
+
+    // An auction call rests crossed by design.
+    book.set_trading_state(TradingState::Auction);
+    assert_eq!(book.validate(), Ok(()));
+}
+
+#[test]
+fn test_level_volume_must_match_its_orders() {
+    let mut book = seeded_book();
+    book.buy_levels.get_mut(&99).unwrap().total_volume = 14;
+    assert_eq!(
+        book.validate(),
+        Err(BookInvariantError::LevelVolume {
+            symbol: "AAPL".to_string(),
+            side: Side::Buy,
+            price: 99,
+            recorded_total: 14,
+            recorded_visible: 15,
+            total: 15,
+            visible: 15,
+        })
+    );
+}
+
+#[test]
+fn test_levels_and_index_must_agree() {
+    // Queued on a level without being indexed.
+    let mut book = seeded_book();
+    book.buy_levels
+        .get_mut(&99)
+        .unwrap()
+        .add_order(order(9, Side::Buy, OrderType::Limit, 99, 1));
+    assert!(matches!(
+        book.validate(),
+        Err(BookInvariantError::UnindexedOrder {
+            order_id: 9,
+            side: Side::Buy,
+            price: 99,
+            ..
+        })
+    ));
+
+    // Indexed but gone from its level.
+    let mut book = seeded_book();
+    let removed = book.sell_levels.get_mut(&101).unwrap().pop_front().unwrap();
+    book.sell_levels.remove(&101);
+    assert_eq!(removed.read().id, 3);
+    assert!(matches!(
+        book.validate(),
+        Err(BookInvariantError::UnqueuedOrder {
+            order_id: 3,
+            side: Side::Sell,
+            price: 101,
+            ..
+        })
+    ));
+
+    // Repriced behind the book's back.
+    let book = seeded_book();
+    book.get_order(1).unwrap().write().price = 98;
+    assert!(matches!(
+        book.validate(),
+        Err(BookInvariantError::UnindexedOrder { order_id: 1, .. })
+    ));
+}
+
+#[test]
+fn test_empty_level_is_a_violation() {
+    let mut book = seeded_book();
+    book.sell_levels.insert(105, PriceLevel::new(105));
+    assert!(matches!(
+        book.validate(),
+        Err(BookInvariantError::EmptyLevel {
+            side: Side::Sell,
+            price: 105,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_stop_book_holds_only_stop_orders() {
+    let book = seeded_book();
+    book.get_order(4).unwrap().write().order_type = OrderType::Limit;
+    assert_eq!(
+        book.validate(),
+        Err(BookInvariantError::NotAStopOrder {
+            symbol: "AAPL".to_string(),
+            order_id: 4,
+            order_type: OrderType::Limit,
+        })
+    );
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "Book invariant violated")]
+fn test_engine_checks_books_after_every_command_in_debug_builds() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    engine
+        .place_order(Order::new(
+            "AAPL".to_string(),
+            Side::Buy,
+            OrderType::Limit,
+            99,
+            10,
+            1,
+        ))
+        .unwrap();
+    engine
+        .order_books
+        .get_mut("AAPL")
+        .unwrap()
+        .buy_levels
+        .get_mut(&99)
+        .unwrap()
+        .total_volume = 0;
+    engine
+        .place_order(Order::new(
+            "AAPL".to_string(),
+            Side::Sell,
+            OrderType::Limit,
+            105,
+            1,
+            2,
+        ))
+        .unwrap();
+}
+
+#[test]
+fn test_admin_endpoint_lists_violations() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    engine.add_symbol("MSFT");
+    engine
+        .place_order(Order::new(
+            "AAPL".to_string(),
+            Side::Buy,
+            OrderType::Limit,
+            99,
+            10,
+            1,
+        ))
+        .unwrap();
+    let engine = Arc::new(Mutex::new(engine));
+    let admin = AdminServer::new().with_engine(Arc::clone(&engine));
+
+    let response = admin.handle("GET", "/books/validate", b"");
+    assert_eq!(response.status, 200);
+    let report: serde_json::Value = serde_json::from_str(&response.body).unwrap();
+    assert_eq!(report["books"], 2);
+    assert_eq!(report["violations"], serde_json::json!([]));
+
+    engine
+        .lock()
+        .order_books
+        .get_mut("AAPL")
+        .unwrap()
+        .buy_levels
+        .get_mut(&99)
+        .unwrap()
+        .total_volume = 3;
+    let report: serde_json::Value =
+        serde_json::from_str(&admin.handle("GET", "/books/validate", b"").body).unwrap();
+    assert_eq!(report["violations"][0]["symbol"], "AAPL");
+    assert!(report["violations"][0]["error"]
+        .as_str()
+        .unwrap()
+        .contains("level 99 records volume 3"));
+
+    assert_eq!(admin.handle("POST", "/books/validate", b"").status, 405);
+    assert_eq!(
+        AdminServer::new()
+            .handle("GET", "/books/validate", b"")
+            .status,
+        404
+    );
+}
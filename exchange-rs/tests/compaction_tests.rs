@@ -0,0 +1,371 @@
+use std::sync::Arc;
+
+use exchange_rs::{
+    clock::{Clock, ManualClock},
+    contingent::{ContingentTrigger, TriggerDirection},
+    fees::FeeSchedule,
+    journal::{verify_compaction, EngineJournal, JournalError},
+    matching_engine::{EngineSnapshot, MatchingEngine, MatchingEngineConfig},
+    order::{Order, OrderType, Side, TimeInForce, TriggerSource},
+    orderbook::{MatchPolicy, OrderTypeRules, PriceBands, SymbolSpec, TradingState},
+    settlement::trading_day,
+};
+use exchange_rs::Price;
+
+const SECOND: i64 = 1_000_000_000;
+const START: i64 = 1_700_000_000 * SECOND;
+
+/// A journaling engine on a clock that moves one second per command.
+struct Session {
+    clock: Arc<ManualClock>,
+    engine: MatchingEngine,
+}
+
+impl Session {
+    fn new() -> Self {
+        let clock = Arc::new(ManualClock::new(START));
+        let mut engine = MatchingEngine::with_clock(config(), clock.clone());
+        engine.enable_journal();
+        Self { clock, engine }
+    }
+
+    fn engine(&mut self) -> &mut MatchingEngine {
+        self.clock.set(self.clock.now_nanos() + SECOND);
+        &mut self.engine
+    }
+
+    fn place(&mut self, order: Order) -> Option<u64> {
+        let result = self.engine().place_order(order).ok()?;
+        result.remaining_order.map(|order| order.read().id)
+    }
+}
+
+fn config() -> MatchingEngineConfig {
+    MatchingEngineConfig {
+        bustable_trades: 50,
+        ..MatchingEngineConfig::default()
+    }
+}
+
+fn order(
+    symbol: &str,
+    side: Side,
+    order_type: OrderType,
+    price: Price,
+    quantity: u32,
+    user_id: u64,
+) -> Order {
+    Order::new(
+        symbol.to_string(),
+        side,
+        order_type,
+        price,
+        quantity,
+        user_id,
+    )
+}
+
+fn stop(
+    symbol: &str,
+    side: Side,
+    order_type: OrderType,
+    stop_price: Price,
+    price: Price,
+    quantity: u32,
+    user_id: u64,
+) -> Order {
+    let mut order = order(symbol, side, order_type, price, quantity, user_id);
+    order.stop_price = Some(stop_price);
+    order
+}
+
+/// Commands up to the snapshot. Returns the id of a trade whose resting
+/// side was filled completely, so only the snapshot's bustable orders can
+/// give it back.
+fn before_snapshot(session: &mut Session) -> u64 {
+    let engine = session.engine();
+    engine.add_symbol("AAPL");
+    engine.add_symbol_with_policy("MSFT", MatchPolicy::ProRata { min_allocation: 1 });
+    session
+        .engine()
+        .set_price_bands(
+            "AAPL",
+            PriceBands {
+                reference_price: Some(100),
+                static_band_bps: Some(5_000),
+                ..PriceBands::default()
+            },
+        )
+        .unwrap();
+    session
+        .engine()
+        .set_fee_schedule(
+            "AAPL",
+            FeeSchedule {
+                maker_bps: 1,
+                taker_bps: 2,
+                min_fee: None,
+            },
+        )
+        .unwrap();
+    session
+        .engine()
+        .set_stop_trigger_source("MSFT", TriggerSource::MarkPrice)
+        .unwrap();
+
+    session.place(order("AAPL", Side::Sell, OrderType::Limit, 101, 10, 1));
+    session.place(order("AAPL", Side::Sell, OrderType::Limit, 102, 10, 1));
+    let mut iceberg = order("AAPL", Side::Sell, OrderType::Iceberg, 103, 20, 2);
+    iceberg.display_quantity = Some(5);
+    session.place(iceberg);
+    session.place(order("AAPL", Side::Buy, OrderType::Limit, 99, 10, 3));
+    let mut good_till = order("AAPL", Side::Buy, OrderType::Limit, 98, 5, 3);
+    good_till.time_in_force = TimeInForce::GTD;
+    good_till.expiration_time = START + 100 * SECOND;
+    session.place(good_till);
+
+    // Stops at two prices that one trade sets off together, and two on the
+    // mark price that only the first of can fill once triggered.
+    session.place(stop("AAPL", Side::Buy, OrderType::StopMarket, 102, 0, 3, 5));
+    session.place(stop(
+        "AAPL",
+        Side::Buy,
+        OrderType::StopLimit,
+        101,
+        104,
+        5,
+        4,
+    ));
+    session.place(stop("MSFT", Side::Sell, OrderType::StopMarket, 48, 0, 2, 6));
+    session.place(stop(
+        "MSFT",
+        Side::Sell,
+        OrderType::StopMarket,
+        48,
+        0,
+        2,
+        12,
+    ));
+
+    session.place(order("MSFT", Side::Buy, OrderType::Limit, 45, 2, 13));
+    session.place(order("MSFT", Side::Sell, OrderType::Limit, 50, 10, 1));
+    session.place(order("MSFT", Side::Sell, OrderType::Limit, 50, 10, 2));
+    session
+        .engine()
+        .submit_quote("AAPL", 7, 97, 4, 105, 4)
+        .unwrap();
+    session
+        .engine()
+        .place_contingent_order(
+            ContingentTrigger::new("AAPL", TriggerDirection::AtOrAbove, 102),
+            order("MSFT", Side::Buy, OrderType::Limit, 50, 4, 8),
+        )
+        .unwrap();
+    session
+        .engine()
+        .place_contingent_order(
+            ContingentTrigger::new("AAPL", TriggerDirection::AtOrBelow, 90),
+            order("MSFT", Side::Sell, OrderType::Limit, 49, 1, 8),
+        )
+        .unwrap();
+
+    let sweep = session
+        .engine()
+        .place_order(order("AAPL", Side::Buy, OrderType::Limit, 101, 10, 9))
+        .unwrap();
+    let busted = sweep.trades[0].id;
+
+    session.engine().settle_session(START + 30 * SECOND);
+    session.place(order("AAPL", Side::Sell, OrderType::Limit, 99, 2, 10));
+    session
+        .engine()
+        .set_trading_state("MSFT", TradingState::Auction)
+        .unwrap();
+    session.place(order("MSFT", Side::Buy, OrderType::Limit, 51, 5, 3));
+    busted
+}
+
+fn after_snapshot(session: &mut Session, busted: u64) {
+    session.engine().bust_trade("AAPL", busted).unwrap();
+    session.engine().uncross("MSFT").unwrap();
+    session.engine().update_mark_price("MSFT", 47).unwrap();
+    session.engine().cancel_contingent_order(2).unwrap();
+    session
+        .engine()
+        .process_expired_orders_at(START + 200 * SECOND)
+        .unwrap();
+
+    let iceberg_id = 3;
+    let _ = session.engine().replace_order("AAPL", iceberg_id, 104, 12);
+    session.engine().cancel_all_for_user(3);
+    session
+        .engine()
+        .submit_quote("AAPL", 7, 96, 2, 106, 2)
+        .unwrap();
+    session
+        .engine()
+        .set_symbol_spec(
+            "AAPL",
+            SymbolSpec {
+                tick_size: 1,
+                lot_size: 1,
+                min_qty: 2,
+            },
+        )
+        .unwrap();
+    session
+        .engine()
+        .set_order_type_rules(
+            "AAPL",
+            OrderTypeRules {
+                denied_order_types: vec![OrderType::Market],
+                ..OrderTypeRules::default()
+            },
+        )
+        .unwrap();
+    session.place(order("AAPL", Side::Buy, OrderType::Limit, 103, 6, 11));
+}
+
+/// Removes `fields` from a snapshot, as a snapshot written before they
+/// were persisted would lack them.
+fn without(snapshot: &EngineSnapshot, fields: &[&str]) -> EngineSnapshot {
+    let mut value = serde_json::to_value(snapshot).unwrap();
+    for field in fields {
+        value.as_object_mut().unwrap().remove(*field);
+    }
+    serde_json::from_value(value).unwrap()
+}
+
+#[test]
+fn test_snapshot_replay_matches_full_replay() {
+    let mut session = Session::new();
+    let busted = before_snapshot(&mut session);
+    let snapshot = session.engine.snapshot();
+    after_snapshot(&mut session, busted);
+
+    let journal = session.engine.journal().unwrap();
+    let digest = verify_compaction(&config(), &snapshot, journal).unwrap();
+    assert_eq!(digest, session.engine.state_digest());
+
+    // The scenario reached the state it is meant to cover: the contingent
+    // order fired, the auction uncrossed, the first mark price stop took
+    // the bid and the good-till order expired.
+    assert!(session.engine.get_contingent_order(1).is_none());
+    assert_eq!(
+        session.engine.trading_state("MSFT"),
+        Some(TradingState::Continuous)
+    );
+    assert_eq!(
+        session
+            .engine
+            .get_order_status("MSFT", 8)
+            .unwrap()
+            .filled_quantity,
+        2
+    );
+    assert_eq!(
+        session
+            .engine
+            .get_order_status("MSFT", 9)
+            .unwrap()
+            .filled_quantity,
+        0
+    );
+    assert!(!session.engine.order_books["AAPL"].contains_order(5));
+    assert!(session
+        .engine
+        .get_settlement("AAPL", trading_day(START + 30 * SECOND))
+        .is_some());
+}
+
+#[test]
+fn test_verification_reports_state_missing_from_snapshot() {
+    let mut session = Session::new();
+    let busted = before_snapshot(&mut session);
+    let snapshot = without(&session.engine.snapshot(), &["quotes"]);
+    after_snapshot(&mut session, busted);
+
+    let journal = session.engine.journal().unwrap();
+    let Err(JournalError::CompactionMismatch { components }) =
+        verify_compaction(&config(), &snapshot, journal)
+    else {
+        panic!("a snapshot without quotes must not verify");
+    };
+    assert_eq!(components, vec!["book:AAPL".to_string()]);
+}
+
+#[test]
+fn test_verification_reports_rejected_bust_as_divergence() {
+    let mut session = Session::new();
+    let busted = before_snapshot(&mut session);
+    let snapshot = without(
+        &session.engine.snapshot(),
+        &["recent_trades", "bustable_orders"],
+    );
+    let bust_sequence = session.engine.journal().unwrap().last_sequence() + 1;
+    after_snapshot(&mut session, busted);
+
+    // Without the trade the bust is rejected on replay, unlike the first
+    // time round.
+    let journal = session.engine.journal().unwrap();
+    assert_eq!(
+        verify_compaction(&config(), &snapshot, journal).err(),
+        Some(JournalError::Divergence {
+            sequence: bust_sequence
+        })
+    );
+}
+
+#[test]
+fn test_compacted_journal_rebuilds_engine() {
+    let mut session = Session::new();
+    let busted = before_snapshot(&mut session);
+    let sequence = session.engine.journal().unwrap().last_sequence();
+
+    let snapshot = session.engine.compact_journal();
+    assert_eq!(snapshot.journal_sequence(), sequence);
+    let journal = session.engine.journal().unwrap();
+    assert!(journal.entries().is_empty());
+    assert_eq!(journal.compacted_through(), sequence);
+
+    after_snapshot(&mut session, busted);
+    let journal = session.engine.journal().unwrap();
+    assert_eq!(journal.entries()[0].sequence, sequence + 1);
+
+    let json = serde_json::to_string(journal).unwrap();
+    let journal: EngineJournal = serde_json::from_str(&json).unwrap();
+    let rebuilt = MatchingEngine::replay_from_snapshot(config(), &snapshot, &journal).unwrap();
+    assert_eq!(rebuilt.state_digest(), session.engine.state_digest());
+    assert_eq!(
+        rebuilt.journal().unwrap().last_sequence(),
+        journal.last_sequence()
+    );
+
+    assert_eq!(
+        MatchingEngine::replay(&journal).err(),
+        Some(JournalError::MissingEntries {
+            snapshot_sequence: 0,
+            compacted_through: sequence,
+        })
+    );
+}
+
+#[test]
+fn test_journal_compaction_is_bounded_by_its_contents() {
+    let mut session = Session::new();
+    before_snapshot(&mut session);
+    let last = session.engine.journal().unwrap().last_sequence();
+
+    let mut journal = serde_json::from_value::<EngineJournal>(
+        serde_json::to_value(session.engine.journal().unwrap()).unwrap(),
+    )
+    .unwrap();
+    journal.compact(5);
+    assert_eq!(journal.entries()[0].sequence, 6);
+    assert_eq!(journal.entries_after(10)[0].sequence, 11);
+    journal.compact(3);
+    assert_eq!(journal.compacted_through(), 5);
+    journal.compact(last + 10);
+    assert!(journal.entries().is_empty());
+    assert_eq!(journal.last_sequence(), last);
+}
@@ -0,0 +1,147 @@
+//! Gateway order-entry conformance suite, reported as TAP.
+//!
+//! ```text
+//! cargo test --test conformance -- [OPTIONS] [FILTER...]
+//!
+//!   --mode memory|tcp|all   transports to run through (default all)
+//!   --addr HOST:PORT        run over TCP against the gateway at HOST:PORT
+//!                           instead of one started here
+//!   --junit PATH            also write a JUnit XML report to PATH
+//!   --fault SPEC            inject a fault: drop:N, rewrite:TAG=VALUE or
+//!                           ignore-disconnect; may be repeated
+//!   --list                  list the scenarios and exit
+//! ```
+//!
+//! Filters select scenarios by name substring or tag. Other options are
+//! ignored, so the binary runs under a plain `cargo test`.
+
+use std::net::SocketAddr;
+use std::process::ExitCode;
+
+use exchange_rs::fix::conformance::scenarios::{fixture_gateway, standard_scenarios};
+use exchange_rs::fix::conformance::transport::{
+    serve_in_background, Fault, FaultInjector, MemoryConnector, TcpConnector,
+};
+use exchange_rs::fix::conformance::{Connector, Report, Runner, RunnerConfig};
+
+#[derive(Default)]
+struct Options {
+    memory: bool,
+    tcp: bool,
+    address: Option<SocketAddr>,
+    junit: Option<String>,
+    faults: Vec<Fault>,
+    list: bool,
+    filters: Vec<String>,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut mode = None;
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or(format!("{name} needs a value"));
+            match arg.as_str() {
+                "--mode" => mode = Some(value("--mode")?),
+                "--addr" => {
+                    let address = value("--addr")?;
+                    let address = address
+                        .parse()
+                        .map_err(|_| format!("--addr {address} is not HOST:PORT"))?;
+                    options.address = Some(address);
+                }
+                "--junit" => options.junit = Some(value("--junit")?),
+                "--fault" => options.faults.push(parse_fault(&value("--fault")?)?),
+                "--list" => options.list = true,
+                other if other.starts_with('-') => {}
+                filter => options.filters.push(filter.to_string()),
+            }
+        }
+
+        let default_mode = if options.address.is_some() {
+            "tcp"
+        } else {
+            "all"
+        };
+        match mode.as_deref().unwrap_or(default_mode) {
+            "memory" => options.memory = true,
+            "tcp" => options.tcp = true,
+            "all" => (options.memory, options.tcp) = (true, true),
+            other => return Err(format!("unknown mode {other}")),
+        }
+        Ok(options)
+    }
+}
+
+fn parse_fault(spec: &str) -> Result<Fault, String> {
+    let invalid = || format!("unknown fault {spec}");
+    match spec.split_once(':') {
+        Some(("drop", n)) => n.parse().map(Fault::DropMessage).map_err(|_| invalid()),
+        Some(("rewrite", field)) => {
+            let (tag, value) = field.split_once('=').ok_or_else(invalid)?;
+            Ok(Fault::RewriteField {
+                tag: tag.parse().map_err(|_| invalid())?,
+                value: value.to_string(),
+            })
+        }
+        None if spec == "ignore-disconnect" => Ok(Fault::IgnoreDisconnect),
+        _ => Err(invalid()),
+    }
+}
+
+fn run(connector: impl Connector + 'static, config: RunnerConfig, options: &Options) -> Report {
+    let scenarios: Vec<_> = standard_scenarios()
+        .into_iter()
+        .filter(|scenario| scenario.matches(&options.filters))
+        .collect();
+    let connector = FaultInjector::new(connector, options.faults.clone());
+    Runner::new(connector, config).run(&scenarios)
+}
+
+fn main() -> ExitCode {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("conformance: {error}");
+            return ExitCode::from(2);
+        }
+    };
+
+    if options.list {
+        for scenario in standard_scenarios() {
+            println!("{} [{}]", scenario.name, scenario.tags.join(", "));
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    let mut report = Report::default();
+    if options.memory {
+        let connector = MemoryConnector::new(fixture_gateway());
+        report.extend(run(connector, RunnerConfig::immediate(), &options));
+    }
+    if options.tcp {
+        let address = match options.address {
+            Some(address) => address,
+            None => serve_in_background(fixture_gateway()).expect("local gateway starts"),
+        };
+        report.extend(run(
+            TcpConnector::new(address),
+            RunnerConfig::default(),
+            &options,
+        ));
+    }
+
+    print!("{}", report.to_tap());
+    if let Some(path) = &options.junit {
+        if let Err(error) = std::fs::write(path, report.to_junit()) {
+            eprintln!("conformance: cannot write {path}: {error}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if report.passed() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,79 @@
+use exchange_rs::fix::conformance::scenarios::{fixture_gateway, standard_scenarios};
+use exchange_rs::fix::conformance::transport::{Fault, FaultInjector, MemoryConnector};
+use exchange_rs::fix::conformance::{Report, Runner, RunnerConfig, Scenario};
+
+fn run_memory(faults: Vec<Fault>, filters: &[&str]) -> Report {
+    let filters: Vec<String> = filters.iter().map(|filter| filter.to_string()).collect();
+    let scenarios: Vec<Scenario> = standard_scenarios()
+        .into_iter()
+        .filter(|scenario| scenario.matches(&filters))
+        .collect();
+    let connector = FaultInjector::new(MemoryConnector::new(fixture_gateway()), faults);
+    Runner::new(connector, RunnerConfig::immediate()).run(&scenarios)
+}
+
+#[test]
+fn test_gateway_passes_in_memory() {
+    let report = run_memory(Vec::new(), &[]);
+
+    assert_eq!(report.results.len(), standard_scenarios().len());
+    assert!(report.passed(), "{}", report.to_tap());
+}
+
+#[test]
+fn test_filters_select_by_name_and_tag() {
+    let report = run_memory(Vec::new(), &["resend", "logon-is-answered"]);
+
+    let names: Vec<&str> = report
+        .results
+        .iter()
+        .map(|result| result.name.as_str())
+        .collect();
+    assert_eq!(
+        names,
+        ["logon-is-answered", "resend-request-replays-reports"]
+    );
+}
+
+#[test]
+fn test_dropped_message_is_reported() {
+    let report = run_memory(vec![Fault::DropMessage(1)], &["logon-is-answered"]);
+
+    let failure = report.results[0].failure.as_ref().expect("scenario fails");
+    assert_eq!(failure.step, 3);
+    assert!(failure.message.starts_with("no message within"));
+
+    let tap = report.to_tap();
+    assert!(tap.contains("not ok 1 - memory/logon-is-answered"));
+    assert!(tap.contains("action: \"expect 35=A on connection 0\""));
+
+    let junit = report.to_junit();
+    assert!(junit.contains("failures=\"1\""));
+    assert!(junit.contains("<failure message=\"step 3: no message within"));
+}
+
+#[test]
+fn test_rewritten_field_is_named_with_the_message() {
+    let report = run_memory(
+        vec![Fault::RewriteField {
+            tag: 39,
+            value: "2".to_string(),
+        }],
+        &["new-order-is-acknowledged"],
+    );
+
+    let failure = report.results[0].failure.as_ref().expect("scenario fails");
+    assert_eq!(failure.message, "tag 39 is \"2\", expected \"0\"");
+    let received = failure.received.as_deref().expect("message is reported");
+    assert!(received.contains("|11=ACK-1|"));
+    assert!(received.contains("|39=2|"));
+}
+
+#[test]
+fn test_ignored_disconnect_fails_cancel_on_disconnect() {
+    let report = run_memory(vec![Fault::IgnoreDisconnect], &["cancel-on-disconnect"]);
+
+    let failure = report.results[0].failure.as_ref().expect("scenario fails");
+    assert_eq!(failure.description, "expect 35=8 on connection 1");
+    assert_eq!(failure.message, "tag 150 is \"2\", expected \"0\"");
+}
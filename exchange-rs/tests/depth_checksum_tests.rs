@@ -0,0 +1,124 @@
+use exchange_rs::l3_feed::L3Book;
+use exchange_rs::order::{Order, OrderType, Side};
+use exchange_rs::orderbook::{depth_checksum, MarketDepth, OrderBook};
+use exchange_rs::Price;
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+// Checksums below were computed independently, as the CRC32 of the
+// concatenated digits with Python's `zlib.crc32`. They must not change
+// between releases.
+const FULL_CHECKSUM: u32 = 2185872488;
+const TWO_LEVEL_CHECKSUM: u32 = 116002860;
+const ONE_LEVEL_CHECKSUM: u32 = 2084617455;
+const BIDS_ONLY_CHECKSUM: u32 = 1081841467;
+
+fn add(book: &mut OrderBook, id: u64, side: Side, price: Price, quantity: u32) {
+    let mut order = Order::new(
+        "AAPL".to_string(),
+        side,
+        OrderType::Limit,
+        price,
+        quantity,
+        1,
+    );
+    order.id = id;
+    book.add_order(Arc::new(RwLock::new(order))).unwrap();
+}
+
+/// Asks 100.5 x 3, 101 x 20, 102.75 x 1 over bids 100.25 x 12, 100 x 5
+/// (two orders) and 99.5 x 7.
+fn fixture_book() -> OrderBook {
+    let mut book = OrderBook::new("AAPL");
+    add_fixture_orders(&mut book);
+    book
+}
+
+fn add_fixture_orders(book: &mut OrderBook) {
+    add(book, 1, Side::Buy, 100_000_000, 2);
+    add(book, 2, Side::Sell, 101_000_000, 20);
+    add(book, 3, Side::Buy, 99_500_000, 7);
+    add(book, 4, Side::Sell, 102_750_000, 1);
+    add(book, 5, Side::Buy, 100_250_000, 12);
+    add(book, 6, Side::Sell, 100_500_000, 3);
+    add(book, 7, Side::Buy, 100_000_000, 3);
+}
+
+#[test]
+fn test_checksum_fixtures() {
+    let book = fixture_book();
+
+    assert_eq!(book.checksum(3), FULL_CHECKSUM);
+    assert_eq!(book.checksum(10), FULL_CHECKSUM);
+    assert_eq!(book.checksum(2), TWO_LEVEL_CHECKSUM);
+    assert_eq!(book.checksum(1), ONE_LEVEL_CHECKSUM);
+    assert_eq!(book.checksum(0), 0);
+    assert_eq!(OrderBook::new("AAPL").checksum(10), 0);
+
+    let bids = [(100_250_000, 12), (100_000_000, 5), (99_500_000, 7)];
+    assert_eq!(
+        depth_checksum(bids.into_iter(), std::iter::empty()),
+        BIDS_ONLY_CHECKSUM
+    );
+}
+
+#[test]
+fn test_depth_carries_checksum() {
+    let book = fixture_book();
+
+    let depth = book.get_market_depth();
+    assert_eq!(depth.checksum, FULL_CHECKSUM);
+
+    let rebuilt = MarketDepth::new(depth.bid_levels.clone(), depth.ask_levels.clone());
+    assert_eq!(rebuilt.checksum, FULL_CHECKSUM);
+}
+
+#[test]
+fn test_checksum_follows_book_changes() {
+    let mut book = fixture_book();
+    book.set_depth_levels(2);
+    assert_eq!(book.checksum(2), TWO_LEVEL_CHECKSUM);
+    assert_eq!(book.get_market_depth().checksum, TWO_LEVEL_CHECKSUM);
+
+    // Past the depth window: the cached two-level checksum stands while
+    // a wider one sees the change.
+    add(&mut book, 8, Side::Buy, 99_000_000, 4);
+    assert_eq!(book.checksum(2), TWO_LEVEL_CHECKSUM);
+    assert_ne!(book.checksum(4), FULL_CHECKSUM);
+    book.cancel_order(8);
+    assert_eq!(book.checksum(3), FULL_CHECKSUM);
+
+    add(&mut book, 9, Side::Sell, 100_500_000, 1);
+    assert_ne!(book.checksum(2), TWO_LEVEL_CHECKSUM);
+    book.cancel_order(9);
+    assert_eq!(book.checksum(2), TWO_LEVEL_CHECKSUM);
+}
+
+#[test]
+fn test_snapshot_records_checksum() {
+    let book = fixture_book();
+
+    let snapshot = book.create_snapshot();
+    assert_eq!(snapshot.checksum_levels, 10);
+    assert_eq!(snapshot.checksum, FULL_CHECKSUM);
+
+    let restored = snapshot.restore();
+    assert_eq!(
+        restored.checksum(snapshot.checksum_levels),
+        snapshot.checksum
+    );
+}
+
+#[test]
+fn test_l3_depth_checksum_matches_book() {
+    let mut book = OrderBook::new("AAPL");
+    let events = book.subscribe_l3(64);
+    let mut l3 = L3Book::new();
+    add_fixture_orders(&mut book);
+    add(&mut book, 10, Side::Sell, 101_000_000, 5);
+    for event in events.try_iter() {
+        l3.apply(&event).unwrap();
+    }
+
+    assert_eq!(l3.depth(10).checksum, book.checksum(10));
+}
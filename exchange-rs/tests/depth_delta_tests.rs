@@ -0,0 +1,127 @@
+use exchange_rs::matching_engine::MatchingEngine;
+use exchange_rs::order::{Order, OrderType, Side};
+use exchange_rs::orderbook::{DepthAction, DepthDelta, DepthUpdate, MarketDepth};
+use exchange_rs::Price;
+
+const TICK: Price = 1_000_000;
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+/// Up to eight levels drawn from `ticks`, with sizes from a small range
+/// so levels often match.
+fn random_levels(rng: &mut XorShift, ticks: impl Iterator<Item = u64>) -> Vec<(Price, u64)> {
+    let mut levels = Vec::new();
+    for tick in ticks {
+        if rng.below(6) == 0 {
+            levels.push((tick as Price * TICK, 1 + rng.below(4)));
+        }
+    }
+    levels.truncate(8);
+    levels
+}
+
+fn random_depth(rng: &mut XorShift) -> MarketDepth {
+    let bids = random_levels(rng, (1..50).rev());
+    let asks = random_levels(rng, 50..100);
+    MarketDepth::new(bids, asks)
+}
+
+fn update(action: DepthAction, price: Price, quantity: u64) -> DepthUpdate {
+    DepthUpdate {
+        action,
+        price,
+        quantity,
+    }
+}
+
+#[test]
+fn test_apply_rebuilds_random_depths() {
+    let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+    let mut previous = MarketDepth::default();
+    for _ in 0..500 {
+        let current = random_depth(&mut rng);
+        let delta = current.diff(&previous);
+        assert_eq!(delta.apply(&previous), current);
+        assert!(current.diff(&current).is_empty());
+        previous = current;
+    }
+}
+
+#[test]
+fn test_apply_rebuilds_engine_depth_windows() {
+    let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("BTC-USD");
+    let mut resting: Vec<u64> = Vec::new();
+
+    for window in [1, 3, 5] {
+        let mut previous = MarketDepth::default();
+        for _ in 0..300 {
+            if !resting.is_empty() && rng.below(3) == 0 {
+                let order_id = resting.swap_remove(rng.below(resting.len() as u64) as usize);
+                let _ = engine.cancel_order("BTC-USD", order_id);
+            } else {
+                let side = if rng.below(2) == 0 {
+                    Side::Buy
+                } else {
+                    Side::Sell
+                };
+                let price = (40 + rng.below(20)) as Price * TICK;
+                let order = Order::new(
+                    "BTC-USD".to_string(),
+                    side,
+                    OrderType::Limit,
+                    price,
+                    1 + rng.below(5) as u32,
+                    1 + rng.below(3),
+                );
+                let result = engine.place_order(order).unwrap();
+                if let Some(order) = result.remaining_order {
+                    resting.push(order.read().id);
+                }
+            }
+
+            let current = engine.market_depth("BTC-USD", window).unwrap();
+            assert_eq!(current.diff(&previous).apply(&previous), current);
+            previous = current;
+        }
+    }
+}
+
+#[test]
+fn test_level_leaving_the_window_is_deleted() {
+    let previous = MarketDepth::new(vec![(10 * TICK, 5), (9 * TICK, 2)], vec![(11 * TICK, 1)]);
+    // A better bid pushes 9 out of a two-level window; 10 shrinks.
+    let current = MarketDepth::new(
+        vec![(10 * TICK + TICK / 2, 3), (10 * TICK, 4)],
+        vec![(11 * TICK, 1)],
+    );
+
+    let delta = current.diff(&previous);
+    assert_eq!(
+        delta,
+        DepthDelta {
+            bid_updates: vec![
+                update(DepthAction::Delete, 9 * TICK, 0),
+                update(DepthAction::New, 10 * TICK + TICK / 2, 3),
+                update(DepthAction::Change, 10 * TICK, 4),
+            ],
+            ask_updates: Vec::new(),
+        }
+    );
+    assert_eq!(delta.apply(&previous), current);
+    assert_eq!(delta.apply(&previous).checksum, current.checksum);
+}
@@ -0,0 +1,218 @@
+use exchange_rs::{
+    determinism::{AuditConfig, DeterminismAudit, Divergence, TradeFingerprint},
+    events::EngineCallbacks,
+    journal::{JournalCommand, JournalEntry},
+    matching_engine::MatchingEngine,
+    order::{Order, OrderType, Side},
+    orderbook::{ConcurrentOrderBook, MatchPolicy, SymbolSpec},
+    snapshot::OrderSnapshot,
+};
+use exchange_rs::Price;
+use parking_lot::RwLock;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+
+/// Live side of the audit: one concurrent book per symbol sharing a trade
+/// id counter, teeing every command it runs.
+#[derive(Default)]
+struct ShardedEngine {
+    books: HashMap<String, ConcurrentOrderBook>,
+    next_order_id: u64,
+    next_trade_id: AtomicU64,
+    callbacks: EngineCallbacks,
+    sequence: u64,
+}
+
+impl ShardedEngine {
+    fn run(&mut self, audit: &mut DeterminismAudit, command: JournalCommand) {
+        self.sequence += 1;
+        let mut order_id = None;
+        let mut trades = Vec::new();
+        match &command {
+            JournalCommand::AddSymbol { symbol, .. } => {
+                self.books
+                    .insert(symbol.clone(), ConcurrentOrderBook::new(symbol));
+            }
+            JournalCommand::PlaceOrder(snapshot) => {
+                self.next_order_id += 1;
+                let mut order = Order::new(
+                    snapshot.symbol.clone(),
+                    snapshot.side,
+                    snapshot.order_type,
+                    snapshot.price,
+                    snapshot.quantity,
+                    snapshot.user_id,
+                );
+                order.id = self.next_order_id;
+                order_id = Some(order.id);
+                let book = &self.books[&order.symbol];
+                let result = book
+                    .match_order(
+                        Arc::new(RwLock::new(order)),
+                        &self.next_trade_id,
+                        &self.callbacks,
+                    )
+                    .unwrap();
+                trades = result.trades;
+            }
+            JournalCommand::CancelOrder { symbol, order_id } => {
+                self.books[symbol].cancel_order(*order_id);
+            }
+            _ => unreachable!(),
+        }
+
+        let entry = JournalEntry {
+            sequence: self.sequence,
+            timestamp: 0,
+            order_id,
+            command,
+            outcome: None,
+        };
+        audit.tee(entry, &trades, |symbol| {
+            self.books.get(symbol).map(|book| book.digest())
+        });
+    }
+}
+
+fn add_symbol(symbol: &str) -> JournalCommand {
+    JournalCommand::AddSymbol {
+        symbol: symbol.to_string(),
+        match_policy: MatchPolicy::Fifo,
+        spec: SymbolSpec::default(),
+    }
+}
+
+fn limit(symbol: &str, side: Side, price: Price, quantity: u32) -> JournalCommand {
+    let order = Order::new(
+        symbol.to_string(),
+        side,
+        OrderType::Limit,
+        price,
+        quantity,
+        1,
+    );
+    JournalCommand::PlaceOrder(OrderSnapshot::from(&order))
+}
+
+fn fill(buy_order_id: u64, sell_order_id: u64, price: Price, quantity: u32) -> TradeFingerprint {
+    TradeFingerprint {
+        buy_order_id,
+        sell_order_id,
+        price,
+        quantity,
+    }
+}
+
+#[test]
+fn test_lagging_shadow_agrees_with_sharded_engine() {
+    let config = AuditConfig {
+        digest_interval: 2,
+        ..AuditConfig::default()
+    };
+    let mut audit = DeterminismAudit::new(config, MatchingEngine::new());
+    let mut live = ShardedEngine::default();
+
+    for command in [
+        add_symbol("AAPL"),
+        limit("AAPL", Side::Sell, 101, 5),
+        limit("AAPL", Side::Sell, 102, 5),
+        limit("AAPL", Side::Buy, 99, 3),
+        limit("AAPL", Side::Buy, 102, 7),
+        JournalCommand::CancelOrder {
+            symbol: "AAPL".to_string(),
+            order_id: 3,
+        },
+    ] {
+        live.run(&mut audit, command);
+    }
+
+    assert_eq!(audit.lag(), 6);
+    assert_eq!(audit.catch_up(4), 4);
+    assert_eq!(audit.catch_up(10), 2);
+    assert_eq!(audit.lag(), 0);
+    assert_eq!(audit.divergences().count(), 0);
+    assert_eq!(
+        audit.shadow().order_books["AAPL"].digest(),
+        live.books["AAPL"].digest()
+    );
+}
+
+#[test]
+fn test_injected_divergence_is_reported_at_first_diverging_command() {
+    let mut audit = DeterminismAudit::new(AuditConfig::default(), MatchingEngine::new());
+    let alerts = audit.subscribe();
+    audit.set_shadow_hook(|sequence, shadow| {
+        if sequence == 4 {
+            shadow.cancel_order("AAPL", 1);
+        }
+    });
+    let mut live = ShardedEngine::default();
+
+    for command in [
+        add_symbol("AAPL"),
+        limit("AAPL", Side::Sell, 101, 5),
+        limit("AAPL", Side::Sell, 102, 5),
+        limit("AAPL", Side::Buy, 102, 7),
+        limit("AAPL", Side::Buy, 102, 3),
+    ] {
+        live.run(&mut audit, command);
+    }
+    audit.catch_up(usize::MAX);
+
+    let alert = alerts.try_recv().unwrap();
+    assert_eq!(alert.symbol, "AAPL");
+    assert_eq!(alert.sequence, 4);
+    assert_eq!(
+        alert.divergence,
+        Divergence::Trades {
+            live: vec![fill(3, 1, 101, 5), fill(3, 2, 102, 2)],
+            shadow: vec![fill(3, 2, 102, 5)],
+        }
+    );
+    // Command 5 differs as a consequence and is not reported again.
+    assert!(alerts.try_recv().is_err());
+    assert_eq!(audit.divergences().count(), 1);
+}
+
+#[test]
+fn test_book_divergence_caught_at_digest_checkpoint_for_sampled_symbols() {
+    let config = AuditConfig {
+        symbols: Some(BTreeSet::from(["AAPL".to_string()])),
+        digest_interval: 3,
+        max_lag: 1,
+    };
+    let mut audit = DeterminismAudit::new(config, MatchingEngine::new());
+    audit.set_shadow_hook(|sequence, shadow| {
+        if sequence == 5 {
+            shadow.cancel_order("AAPL", 1);
+        }
+    });
+    let mut live = ShardedEngine::default();
+
+    for command in [
+        add_symbol("AAPL"),
+        add_symbol("MSFT"),
+        limit("AAPL", Side::Buy, 99, 5),
+        limit("MSFT", Side::Sell, 50, 5),
+        limit("AAPL", Side::Sell, 105, 2),
+        limit("AAPL", Side::Sell, 106, 2),
+    ] {
+        live.run(&mut audit, command);
+        assert!(audit.lag() <= 1);
+    }
+    audit.catch_up(usize::MAX);
+
+    let divergences: Vec<_> = audit.divergences().collect();
+    assert_eq!(divergences.len(), 1);
+    assert_eq!(divergences[0].symbol, "AAPL");
+    assert_eq!(divergences[0].sequence, 6);
+    assert!(matches!(
+        divergences[0].divergence,
+        Divergence::Digest {
+            live: Some(_),
+            shadow: Some(_)
+        }
+    ));
+    assert!(!audit.shadow().order_books.contains_key("MSFT"));
+}
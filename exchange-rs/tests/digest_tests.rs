@@ -0,0 +1,71 @@
+use exchange_rs::clock::ManualClock;
+use exchange_rs::digest::StableHasher;
+use exchange_rs::matching_engine::MatchingEngine;
+use exchange_rs::matching_engine::MatchingEngineConfig;
+use exchange_rs::order::{Order, OrderType, Side};
+use exchange_rs::orderbook::{ConcurrentOrderBook, OrderBook};
+use exchange_rs::Price;
+use parking_lot::RwLock;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+#[test]
+fn test_stable_hasher_matches_fnv1a() {
+    let hash = |bytes: &[u8]| {
+        let mut hasher = StableHasher::new();
+        hasher.write(bytes);
+        hasher.finish()
+    };
+    assert_eq!(hash(b""), 0xcbf2_9ce4_8422_2325);
+    assert_eq!(hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+    assert_eq!(hash(b"foobar"), 0x8594_4171_f739_67e8);
+
+    // Integers hash as fixed-width little-endian bytes.
+    let mut hasher = StableHasher::new();
+    7usize.hash(&mut hasher);
+    assert_eq!(hasher.finish(), hash(&7u64.to_le_bytes()));
+}
+
+fn order(id: u64, side: Side, price: Price, quantity: u32) -> Arc<RwLock<Order>> {
+    let mut order = Order::new(
+        "AAPL".to_string(),
+        side,
+        OrderType::Limit,
+        price,
+        quantity,
+        1,
+    );
+    order.id = id;
+    order.timestamp = 1_000;
+    order.last_update = 1_000;
+    Arc::new(RwLock::new(order))
+}
+
+/// Digests are compared between processes, so they must not change from
+/// one run or build to the next.
+#[test]
+fn test_book_digests_are_pinned() {
+    let mut book = OrderBook::new("AAPL");
+    let concurrent = ConcurrentOrderBook::new("AAPL");
+    for (id, side, price, quantity) in [
+        (1, Side::Buy, 99, 5),
+        (2, Side::Sell, 101, 3),
+        (3, Side::Buy, 98, 7),
+    ] {
+        book.add_order(order(id, side, price, quantity)).unwrap();
+        concurrent
+            .add_order(order(id, side, price, quantity))
+            .unwrap();
+    }
+
+    assert_eq!(book.digest(), concurrent.digest());
+    assert_eq!(book.digest(), 12_346_506_048_869_487_882);
+
+    let clock = Arc::new(ManualClock::new(1_000));
+    let mut engine = MatchingEngine::with_clock(MatchingEngineConfig::default(), clock);
+    engine.add_symbol("AAPL");
+    let placed = order(0, Side::Buy, 99, 5).read().clone();
+    engine.place_order(placed).unwrap();
+    let digest = engine.state_digest();
+    assert_eq!(digest.components()["book:AAPL"], 16_050_434_821_169_832_510);
+}
@@ -0,0 +1,328 @@
+use exchange_rs::{
+    events::EngineEvent,
+    matching_engine::MatchingEngine,
+    orderbook::TradingState,
+    order::{Order, OrderStatus, OrderType, Side, TimeInForce},
+};
+use exchange_rs::Price;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::thread;
+
+fn limit(symbol: &str, side: Side, price: Price, quantity: u32) -> Order {
+    Order::new(symbol.to_string(), side, OrderType::Limit, price, quantity, 1)
+}
+
+#[test]
+fn test_events_follow_execution_order() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    let events = engine.subscribe("AAPL");
+
+    engine.place_order(limit("AAPL", Side::Sell, 100, 5)).unwrap();
+    let mut stop_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::StopLimit, 90, 5, 2);
+    stop_order.stop_price = Some(100);
+    engine.place_order(stop_order).unwrap();
+
+    let mut buy_order = limit("AAPL", Side::Buy, 100, 8);
+    buy_order.time_in_force = TimeInForce::IOC;
+    engine.place_order(buy_order).unwrap();
+
+    let received: Vec<EngineEvent> = events.try_iter().collect();
+    assert_eq!(received.len(), 6);
+    assert!(matches!(&received[0], EngineEvent::OrderAccepted(o) if o.id == 1));
+    assert!(matches!(&received[1], EngineEvent::OrderAccepted(o) if o.id == 2));
+    assert!(matches!(&received[2], EngineEvent::OrderAccepted(o) if o.id == 3));
+    assert!(matches!(
+        &received[3],
+        EngineEvent::Trade { symbol, trade }
+            if symbol == "AAPL" && trade.buy_order_id == 3 && trade.sell_order_id == 1 && trade.quantity == 5
+    ));
+    assert!(matches!(&received[4], EngineEvent::StopTriggered(o) if o.id == 2));
+    assert!(matches!(&received[5], EngineEvent::OrderCancelled(o) if o.id == 3 && o.filled_quantity == 5));
+}
+
+#[test]
+fn test_subscription_filters_by_symbol() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    engine.add_symbol("MSFT");
+    let aapl_events = engine.subscribe("AAPL");
+    let all_events = engine.subscribe_all();
+
+    engine.place_order(limit("AAPL", Side::Buy, 100, 5)).unwrap();
+    let msft_order = engine.place_order(limit("MSFT", Side::Buy, 200, 5)).unwrap();
+    let msft_id = msft_order.remaining_order.unwrap().read().id;
+    engine.cancel_order("MSFT", msft_id);
+
+    assert_eq!(aapl_events.try_iter().count(), 1);
+
+    let received: Vec<EngineEvent> = all_events.try_iter().collect();
+    assert_eq!(received.len(), 3);
+    assert!(matches!(&received[2], EngineEvent::OrderCancelled(o) if o.id == msft_id));
+}
+
+#[test]
+fn test_expired_orders_are_published() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    let events = engine.subscribe("AAPL");
+
+    let mut gtd_order = limit("AAPL", Side::Buy, 100, 5);
+    gtd_order.time_in_force = TimeInForce::GTD;
+    // Expiry is checked against the engine clock, which runs after the
+    // order's own timestamp.
+    gtd_order.expiration_time = gtd_order.timestamp + 1_000_000_000;
+    let expire_time = gtd_order.expiration_time;
+    engine.place_order(gtd_order).unwrap();
+    engine.process_expired_orders_at(expire_time).unwrap();
+
+    let received: Vec<EngineEvent> = events.try_iter().collect();
+    assert_eq!(received.len(), 2);
+    assert!(matches!(&received[1], EngineEvent::OrderExpired(o) if o.id == 1));
+}
+
+#[test]
+fn test_events_consumed_without_engine_lock() {
+    let engine = Arc::new(Mutex::new(MatchingEngine::new()));
+    let events = {
+        let mut engine = engine.lock();
+        engine.add_symbol("AAPL");
+        engine.subscribe("AAPL")
+    };
+
+    let consumer = thread::spawn(move || {
+        events
+            .iter()
+            .filter(|event| matches!(event, EngineEvent::Trade { .. }))
+            .take(10)
+            .count()
+    });
+
+    for _ in 0..10 {
+        let mut engine = engine.lock();
+        engine.place_order(limit("AAPL", Side::Sell, 100, 1)).unwrap();
+        engine.place_order(limit("AAPL", Side::Buy, 100, 1)).unwrap();
+    }
+
+    assert_eq!(consumer.join().unwrap(), 10);
+}
+
+#[test]
+fn test_dropped_receiver_is_unsubscribed() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    drop(engine.subscribe("AAPL"));
+    let events = engine.subscribe("AAPL");
+
+    engine.place_order(limit("AAPL", Side::Buy, 100, 5)).unwrap();
+    assert_eq!(events.try_iter().count(), 1);
+}
+
+#[test]
+fn test_trade_callback_fires_for_every_trade() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+
+    let trades = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&trades);
+    engine.on_trade(Arc::new(move |trade| recorded.lock().push((trade.id, trade.price, trade.quantity))));
+
+    engine.place_order(limit("AAPL", Side::Sell, 100, 3)).unwrap();
+    engine.place_order(limit("AAPL", Side::Sell, 101, 3)).unwrap();
+    engine.place_order(limit("AAPL", Side::Buy, 101, 5)).unwrap();
+
+    assert_eq!(*trades.lock(), vec![(1, 100, 3), (2, 101, 2)]);
+}
+
+#[test]
+fn test_order_status_callback_reports_transitions() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+
+    let transitions = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&transitions);
+    engine.on_order_status(Arc::new(move |order, previous| {
+        recorded.lock().push((order.id, previous, order.status))
+    }));
+
+    engine.place_order(limit("AAPL", Side::Sell, 100, 5)).unwrap();
+    engine.place_order(limit("AAPL", Side::Buy, 100, 2)).unwrap();
+    engine.cancel_order("AAPL", 1);
+
+    assert_eq!(
+        *transitions.lock(),
+        vec![
+            (2, OrderStatus::New, OrderStatus::Filled),
+            (1, OrderStatus::New, OrderStatus::PartiallyFilled),
+            (1, OrderStatus::PartiallyFilled, OrderStatus::Canceled),
+        ]
+    );
+}
+
+#[test]
+fn test_book_features_published_per_symbol() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("MSFT");
+    engine.add_symbol("AAPL");
+    let events = engine.subscribe_all();
+
+    engine.place_order(limit("AAPL", Side::Buy, 100, 3)).unwrap();
+    engine.place_order(limit("AAPL", Side::Sell, 101, 1)).unwrap();
+    events.try_iter().for_each(drop);
+
+    engine.publish_book_features(5, 1);
+    let received: Vec<EngineEvent> = events.try_iter().collect();
+    assert_eq!(received.len(), 2);
+    assert!(matches!(
+        &received[0],
+        EngineEvent::BookFeatures { symbol, features }
+            if symbol == "AAPL" && features.imbalance_bps == Some(5_000) && features.microprice == Some(101)
+    ));
+    assert!(matches!(
+        &received[1],
+        EngineEvent::BookFeatures { symbol, features } if symbol == "MSFT" && features.imbalance_bps.is_none()
+    ));
+}
+
+/// Compact form of an event for asserting whole sequences.
+fn describe(event: &EngineEvent) -> String {
+    match event {
+        EngineEvent::OrderAccepted(order) => format!("accepted {}", order.id),
+        EngineEvent::Trade { trade, .. } => format!(
+            "trade {}/{} {}@{}",
+            trade.buy_order_id, trade.sell_order_id, trade.quantity, trade.price
+        ),
+        EngineEvent::StopTriggered(order) => format!("triggered {}", order.id),
+        EngineEvent::OrderCancelled(order) => format!("cancelled {}", order.id),
+        EngineEvent::OrderExpired(order) => format!("expired {}", order.id),
+        EngineEvent::TradeBusted(bust) => format!("busted {}", bust.trade.id),
+        EngineEvent::BookFeatures { .. } => "features".to_string(),
+        EngineEvent::IndicativeUncross { uncross: Some(uncross), .. } => format!(
+            "indicative {}@{} imbalance {} {:?}",
+            uncross.volume, uncross.price, uncross.imbalance, uncross.imbalance_side
+        ),
+        EngineEvent::IndicativeUncross { uncross: None, .. } => "no indicative".to_string(),
+    }
+}
+
+fn stop(side: Side, order_type: OrderType, stop_price: Price, price: Price, quantity: u32, user_id: u64) -> Order {
+    let mut order = Order::new("AAPL".to_string(), side, order_type, price, quantity, user_id);
+    order.stop_price = Some(stop_price);
+    order
+}
+
+#[test]
+fn test_stop_cascade_interleaves_triggers_with_trades() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    for price in [100, 99, 98] {
+        engine.place_order(Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, price, 5, 9)).unwrap();
+    }
+    engine.place_order(stop(Side::Sell, OrderType::StopMarket, 100, 0, 5, 2)).unwrap();
+    engine.place_order(stop(Side::Sell, OrderType::StopMarket, 99, 0, 5, 3)).unwrap();
+    let events = engine.subscribe("AAPL");
+
+    engine.place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100, 5, 4)).unwrap();
+
+    let received: Vec<String> = events.try_iter().map(|event| describe(&event)).collect();
+    assert_eq!(
+        received,
+        [
+            "accepted 6",
+            "trade 1/6 5@100",
+            "triggered 4",
+            "trade 2/4 5@99",
+            "triggered 5",
+            "trade 3/5 5@98",
+        ]
+    );
+}
+
+#[test]
+fn test_iceberg_refill_trades_in_execution_order() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    let mut iceberg = Order::new("AAPL".to_string(), Side::Sell, OrderType::Iceberg, 100, 10, 2);
+    iceberg.display_quantity = Some(4);
+    engine.place_order(iceberg).unwrap();
+    engine.place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100, 3, 3)).unwrap();
+    let events = engine.subscribe("AAPL");
+
+    let mut buy_order = limit("AAPL", Side::Buy, 100, 9);
+    buy_order.time_in_force = TimeInForce::IOC;
+    engine.place_order(buy_order).unwrap();
+
+    let received: Vec<String> = events.try_iter().map(|event| describe(&event)).collect();
+    assert_eq!(
+        received,
+        ["accepted 3", "trade 3/1 4@100", "trade 3/2 3@100", "trade 3/1 2@100"]
+    );
+}
+
+#[test]
+fn test_partial_ioc_terminal_status_comes_last() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    engine.place_order(limit("AAPL", Side::Sell, 100, 5)).unwrap();
+    engine.place_order(stop(Side::Sell, OrderType::StopMarket, 100, 0, 5, 2)).unwrap();
+    let events = engine.subscribe("AAPL");
+
+    let mut buy_order = limit("AAPL", Side::Buy, 100, 8);
+    buy_order.time_in_force = TimeInForce::IOC;
+    engine.place_order(buy_order).unwrap();
+
+    let received: Vec<String> = events.try_iter().map(|event| describe(&event)).collect();
+    assert_eq!(
+        received,
+        ["accepted 3", "trade 3/1 5@100", "triggered 2", "cancelled 2", "cancelled 3"]
+    );
+}
+
+#[test]
+fn test_auction_call_publishes_indicative_uncross_changes() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    let events = engine.subscribe("AAPL");
+    engine.set_trading_state("AAPL", TradingState::Auction).unwrap();
+
+    let place = |engine: &mut MatchingEngine, side, price, quantity| {
+        engine.place_order(limit("AAPL", side, price, quantity)).unwrap();
+        events.try_iter().map(|event| describe(&event)).collect::<Vec<_>>()
+    };
+
+    assert_eq!(place(&mut engine, Side::Buy, 100, 10), ["accepted 1"]);
+    assert_eq!(place(&mut engine, Side::Sell, 101, 5), ["accepted 2"]);
+    assert_eq!(
+        place(&mut engine, Side::Sell, 99, 4),
+        ["accepted 3", "indicative 4@99 imbalance 6 Some(Buy)"]
+    );
+    assert_eq!(
+        place(&mut engine, Side::Sell, 100, 6),
+        ["accepted 4", "indicative 10@100 imbalance 0 None"]
+    );
+    // Adds nothing below the clearing price, so nothing is published.
+    assert_eq!(place(&mut engine, Side::Sell, 102, 1), ["accepted 5"]);
+
+    engine.cancel_order("AAPL", 3);
+    engine.cancel_order("AAPL", 4);
+    let cancels: Vec<String> = events.try_iter().map(|event| describe(&event)).collect();
+    assert_eq!(
+        cancels,
+        [
+            "cancelled 3",
+            "indicative 6@100 imbalance 4 Some(Buy)",
+            "cancelled 4",
+            "no indicative",
+        ]
+    );
+
+    assert_eq!(
+        place(&mut engine, Side::Sell, 99, 3),
+        ["accepted 6", "indicative 3@99 imbalance 7 Some(Buy)"]
+    );
+
+    engine.uncross("AAPL").unwrap();
+    let uncross: Vec<String> = events.try_iter().map(|event| describe(&event)).collect();
+    assert_eq!(uncross, ["trade 1/6 3@99", "no indicative"]);
+}
@@ -0,0 +1,88 @@
+use exchange_rs::{
+    fees::{compute_fee, FeeSchedule},
+    fix::{messages::FixMessage, FixOrderBridge},
+    matching_engine::MatchingEngine,
+    order::{Order, OrderType, Side},
+    PRICE_SCALE_FACTOR, QUANTITY_SCALE_FACTOR,
+};
+use exchange_rs::Price;
+
+const PRICE_100: Price = 100 * PRICE_SCALE_FACTOR;
+const QTY_1: u32 = QUANTITY_SCALE_FACTOR;
+
+fn schedule(maker_bps: u32, taker_bps: u32, min_fee: Option<u64>) -> FeeSchedule {
+    FeeSchedule { maker_bps, taker_bps, min_fee }
+}
+
+#[test]
+fn test_fee_on_round_lot() {
+    assert_eq!(compute_fee(PRICE_100, 2 * QTY_1, 10), 200_000);
+}
+
+#[test]
+fn test_fee_rounds_up_on_odd_lots() {
+    assert_eq!(compute_fee(PRICE_100 + 1, 3, 5), 151);
+    assert_eq!(compute_fee(PRICE_SCALE_FACTOR, 1, 1), 1);
+    assert_eq!(compute_fee(Price::MAX, u32::MAX, u32::MAX), u64::MAX);
+}
+
+#[test]
+fn test_minimum_fee() {
+    let fees = schedule(1, 2, Some(50));
+    assert_eq!(fees.maker_fee(PRICE_SCALE_FACTOR, 1), 50);
+    assert_eq!(fees.taker_fee(PRICE_100, 100 * QTY_1), 2_000_000);
+}
+
+#[test]
+fn test_zero_fee_symbol() {
+    let fees = schedule(0, 0, Some(50));
+    assert_eq!(fees.maker_fee(PRICE_100, QTY_1), 0);
+    assert_eq!(fees.taker_fee(PRICE_100, QTY_1), 0);
+
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    let sell_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, PRICE_100, QTY_1, 1);
+    engine.place_order(sell_order).unwrap();
+    let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, PRICE_100, QTY_1, 2);
+    let trade = engine.place_order(buy_order).unwrap().trades.remove(0);
+
+    assert_eq!((trade.maker_fee, trade.taker_fee), (0, 0));
+    assert_eq!(trade.aggressor, Some(Side::Buy));
+}
+
+#[test]
+fn test_trades_carry_maker_and_taker_fees() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    engine.set_fee_schedule("AAPL", schedule(2, 5, None)).unwrap();
+
+    let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, PRICE_100, 10 * QTY_1, 1);
+    engine.place_order(buy_order).unwrap();
+    let sell_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, PRICE_100, 4 * QTY_1, 2);
+    let result = engine.place_order(sell_order).unwrap();
+
+    let trade = &result.trades[0];
+    assert_eq!(trade.aggressor, Some(Side::Sell));
+    assert_eq!(trade.maker_fee, compute_fee(PRICE_100, 4 * QTY_1, 2));
+    assert_eq!(trade.taker_fee, compute_fee(PRICE_100, 4 * QTY_1, 5));
+}
+
+#[test]
+fn test_execution_report_carries_commission() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    engine.set_fee_schedule("AAPL", schedule(2, 5, None)).unwrap();
+
+    let sell_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, PRICE_100, QTY_1, 1);
+    engine.place_order(sell_order).unwrap();
+    let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, PRICE_100, 3 * QTY_1, 2);
+    let result = engine.place_order(buy_order).unwrap();
+
+    let mut bridge = FixOrderBridge::new();
+    let report = match bridge.convert_trade_result(&result, "CL1").unwrap() {
+        FixMessage::ExecutionReport(report) => report,
+        _ => panic!("expected an execution report"),
+    };
+
+    assert_eq!(report.commission, Some(0.05));
+}
@@ -1,6 +1,12 @@
-use exchange_rs::fix::bridge::FixOrderConverter;
-use exchange_rs::fix::messages::{NewOrderSingle, StandardHeader, Trailer, MessageType};
-use exchange_rs::order::{OrderType, Side, TimeInForce};
+use exchange_rs::fix::bridge::{FixOrderBridge, FixOrderConverter};
+use exchange_rs::fix::error::FixError;
+use exchange_rs::fix::messages::{FixMessage, NewOrderSingle, OrderCancelRequest, OrderStatusRequest, StandardHeader, Trailer, MessageType};
+use exchange_rs::fix::messages::execution_report::{ExecType, OrdRejReason, OrdStatus};
+use exchange_rs::fix_gateway::FixGateway;
+use exchange_rs::matching_engine::MatchingEngine;
+use exchange_rs::order::{Order, OrderType, Side, TimeInForce};
+use exchange_rs::orderbook::{OrderTypeRules, SymbolSpec};
+use parking_lot::Mutex;
 
 #[test]
 fn test_convert_limit_buy_order() {
@@ -16,6 +22,7 @@ fn test_convert_limit_buy_order() {
         sending_time: "20240101-12:00:00".to_string(),
         poss_dup_flag: None,
         poss_resend: None,
+        orig_sending_time: None,
         secure_data_len: None,
         secure_data: None,
     };
@@ -64,6 +71,7 @@ fn test_convert_market_sell_order() {
         sending_time: "20240101-12:00:00".to_string(),
         poss_dup_flag: None,
         poss_resend: None,
+        orig_sending_time: None,
         secure_data_len: None,
         secure_data: None,
     };
@@ -113,6 +121,7 @@ fn test_convert_stop_limit_order() {
         sending_time: "20240101-12:00:00".to_string(),
         poss_dup_flag: None,
         poss_resend: None,
+        orig_sending_time: None,
         secure_data_len: None,
         secure_data: None,
     };
@@ -162,6 +171,7 @@ fn test_convert_stop_market_order() {
         sending_time: "20240101-12:00:00".to_string(),
         poss_dup_flag: None,
         poss_resend: None,
+        orig_sending_time: None,
         secure_data_len: None,
         secure_data: None,
     };
@@ -211,6 +221,7 @@ fn test_invalid_order_type() {
         sending_time: "20240101-12:00:00".to_string(),
         poss_dup_flag: None,
         poss_resend: None,
+        orig_sending_time: None,
         secure_data_len: None,
         secure_data: None,
     };
@@ -252,6 +263,7 @@ fn test_missing_price_for_limit_order() {
         sending_time: "20240101-12:00:00".to_string(),
         poss_dup_flag: None,
         poss_resend: None,
+        orig_sending_time: None,
         secure_data_len: None,
         secure_data: None,
     };
@@ -293,6 +305,7 @@ fn test_missing_stop_price_for_stop_order() {
         sending_time: "20240101-12:00:00".to_string(),
         poss_dup_flag: None,
         poss_resend: None,
+        orig_sending_time: None,
         secure_data_len: None,
         secure_data: None,
     };
@@ -318,4 +331,382 @@ fn test_missing_stop_price_for_stop_order() {
 
     let result = converter.convert_new_order_single(fix_order);
     assert!(result.is_err());
-}
\ No newline at end of file
+}
+#[test]
+fn test_halted_symbol_is_rejected_with_reason() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    engine.halt_symbol("AAPL").unwrap();
+    let engine = Mutex::new(engine);
+    let mut bridge = FixOrderBridge::new();
+
+    let order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 15_000, 100, 1);
+    let response = FixGateway::execute_order(&engine, &mut bridge, order, "ORDER1").unwrap();
+
+    let FixMessage::ExecutionReport(report) = response else {
+        panic!("expected an execution report, got {:?}", response);
+    };
+    assert_eq!(report.cl_ord_id, "ORDER1");
+    assert_eq!(report.symbol, "AAPL");
+    assert_eq!(report.ord_status, OrdStatus::Rejected.to_char());
+    assert_eq!(report.ord_rej_reason, Some(OrdRejReason::ExchangeClosed.to_code()));
+    assert!(report.text.unwrap().contains("AAPL"));
+
+    engine.lock().resume_symbol("AAPL").unwrap();
+    let order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 15_000, 100, 1);
+    let FixMessage::ExecutionReport(report) =
+        FixGateway::execute_order(&engine, &mut bridge, order, "ORDER2").unwrap()
+    else {
+        panic!("expected an execution report");
+    };
+    assert_eq!(report.ord_status, OrdStatus::New.to_char());
+    assert_eq!(report.ord_rej_reason, None);
+}
+
+#[test]
+fn test_denied_order_type_is_rejected_as_unsupported() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    let rules = OrderTypeRules {
+        denied_order_types: vec![OrderType::Iceberg],
+        ..Default::default()
+    };
+    engine.set_order_type_rules("AAPL", rules).unwrap();
+    let engine = Mutex::new(engine);
+    let mut bridge = FixOrderBridge::new();
+
+    let mut order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Iceberg, 15_000, 100, 1);
+    order.display_quantity = Some(10);
+    let FixMessage::ExecutionReport(report) =
+        FixGateway::execute_order(&engine, &mut bridge, order, "ORDER1").unwrap()
+    else {
+        panic!("expected an execution report");
+    };
+    assert_eq!(report.ord_status, OrdStatus::Rejected.to_char());
+    assert_eq!(
+        report.ord_rej_reason,
+        Some(OrdRejReason::UnsupportedOrderCharacteristic.to_code())
+    );
+}
+
+#[test]
+fn test_tick_and_lot_violations_carry_reject_reasons() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol_with_spec("AAPL", SymbolSpec { tick_size: 5, lot_size: 100, min_qty: 100 });
+    let engine = Mutex::new(engine);
+    let mut bridge = FixOrderBridge::new();
+
+    let cases = [
+        (15_002, 100, OrdRejReason::InvalidPriceIncrement),
+        (15_000, 150, OrdRejReason::IncorrectQuantity),
+        (15_000, 0, OrdRejReason::IncorrectQuantity),
+    ];
+    for (index, (price, quantity, reason)) in cases.into_iter().enumerate() {
+        let order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, price, quantity, 1);
+        let cl_ord_id = format!("ORDER{}", index);
+        let FixMessage::ExecutionReport(report) =
+            FixGateway::execute_order(&engine, &mut bridge, order, &cl_ord_id).unwrap()
+        else {
+            panic!("expected an execution report");
+        };
+        assert_eq!(report.ord_status, OrdStatus::Rejected.to_char());
+        assert_eq!(report.ord_rej_reason, Some(reason.to_code()));
+    }
+}
+
+fn report_states(reports: &[exchange_rs::fix::messages::ExecutionReport]) -> Vec<(char, char, u32, u32)> {
+    reports
+        .iter()
+        .map(|r| (r.exec_type, r.ord_status, r.cum_qty, r.leaves_qty))
+        .collect()
+}
+
+#[test]
+fn test_partial_fill_reports_new_then_partially_filled() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    engine
+        .place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100_000_000, 3, 2))
+        .unwrap();
+    engine
+        .place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 101_000_000, 1, 2))
+        .unwrap();
+    let mut bridge = FixOrderBridge::new();
+
+    let order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 101_000_000, 10, 1);
+    let result = engine.place_order(order).unwrap();
+    let reports = bridge.build_execution_reports(&result, "ORDER1");
+
+    assert_eq!(
+        report_states(&reports),
+        [
+            (ExecType::New.to_char(), OrdStatus::New.to_char(), 0, 10),
+            (ExecType::PartialFill.to_char(), OrdStatus::PartiallyFilled.to_char(), 3, 7),
+            (ExecType::PartialFill.to_char(), OrdStatus::PartiallyFilled.to_char(), 4, 6),
+        ]
+    );
+    assert!(reports.iter().all(|r| r.cl_ord_id == "ORDER1" && r.side == '1'));
+    assert_eq!(reports[0].avg_px, None);
+    assert_eq!((reports[1].last_qty, reports[1].last_px, reports[1].avg_px), (Some(3), Some(100.0), Some(100.0)));
+    assert_eq!((reports[2].last_qty, reports[2].last_px, reports[2].avg_px), (Some(1), Some(101.0), Some(100.25)));
+    let exec_ids: std::collections::HashSet<_> = reports.iter().map(|r| &r.exec_id).collect();
+    assert_eq!(exec_ids.len(), 3);
+}
+
+#[test]
+fn test_fill_and_ioc_cancel_reports() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    engine
+        .place_order(Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100_000_000, 5, 2))
+        .unwrap();
+    let mut bridge = FixOrderBridge::new();
+
+    let order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100_000_000, 2, 1);
+    let result = engine.place_order(order).unwrap();
+    assert_eq!(
+        report_states(&bridge.build_execution_reports(&result, "FILL")),
+        [
+            (ExecType::New.to_char(), OrdStatus::New.to_char(), 0, 2),
+            (ExecType::Fill.to_char(), OrdStatus::Filled.to_char(), 2, 0),
+        ]
+    );
+
+    let mut order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100_000_000, 5, 1);
+    order.time_in_force = TimeInForce::IOC;
+    let result = engine.place_order(order).unwrap();
+    assert_eq!(
+        report_states(&bridge.build_execution_reports(&result, "IOC")),
+        [
+            (ExecType::New.to_char(), OrdStatus::New.to_char(), 0, 5),
+            (ExecType::PartialFill.to_char(), OrdStatus::PartiallyFilled.to_char(), 3, 2),
+            (ExecType::Canceled.to_char(), OrdStatus::Canceled.to_char(), 3, 0),
+        ]
+    );
+}
+
+
+fn status_request(order_id: Option<&str>, cl_ord_id: &str, side: char) -> OrderStatusRequest {
+    OrderStatusRequest {
+        header: StandardHeader {
+            begin_string: "FIX.4.4".to_string(),
+            body_length: 0,
+            msg_type: MessageType::OrderStatusRequest,
+            sender_comp_id: "CLIENT123".to_string(),
+            target_comp_id: "EXCHANGE".to_string(),
+            msg_seq_num: 2,
+            sending_time: "20240101-12:00:00".to_string(),
+            poss_dup_flag: None,
+            poss_resend: None,
+            orig_sending_time: None,
+            secure_data_len: None,
+            secure_data: None,
+        },
+        order_id: order_id.map(str::to_string),
+        cl_ord_id: cl_ord_id.to_string(),
+        symbol: "AAPL".to_string(),
+        side,
+        trailer: Trailer { checksum: 0 },
+    }
+}
+
+#[test]
+fn test_trade_result_reports_the_average_across_levels() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    for price in [100_000_000, 101_000_000] {
+        engine
+            .place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, price, 5, 1))
+            .unwrap();
+    }
+    let result = engine
+        .place_order(Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 101_000_000, 10, 2))
+        .unwrap();
+    assert_eq!(result.trades.len(), 2);
+
+    let report = match FixOrderBridge::new().convert_trade_result(&result, "BUY").unwrap() {
+        FixMessage::ExecutionReport(report) => report,
+        other => panic!("expected an execution report, got {:?}", other),
+    };
+    assert_eq!(report.ord_status, OrdStatus::Filled.to_char());
+    assert_eq!((report.cum_qty, report.avg_px), (10, Some(100.5)));
+}
+
+#[test]
+fn test_order_status_reports_the_order_as_it_stands() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    let mut bridge = FixOrderBridge::new();
+
+    let order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100_000_000, 10, 1);
+    let result = engine.place_order(order).unwrap();
+    let order_id = bridge.build_execution_reports(&result, "SELL")[0].order_id.clone();
+    engine
+        .place_order(Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100_000_000, 4, 2))
+        .unwrap();
+
+    // Found by the ClOrdID it was placed with, and by OrderID.
+    for request in [status_request(None, "SELL", '2'), status_request(Some(&order_id), "STATUS", '2')] {
+        let report = bridge.order_status(&request, &engine);
+        assert_eq!(report.exec_type, ExecType::OrderStatus.to_char());
+        assert_eq!(report.ord_status, OrdStatus::PartiallyFilled.to_char());
+        assert_eq!((report.cum_qty, report.leaves_qty, report.avg_px), (4, 6, Some(100.0)));
+        assert_eq!((report.order_id.as_str(), report.cl_ord_id.as_str()), (order_id.as_str(), request.cl_ord_id.as_str()));
+        assert_eq!(report.last_qty, None);
+    }
+
+    engine.cancel_order("AAPL", order_id.parse().unwrap());
+    let report = bridge.order_status(&status_request(None, "SELL", '2'), &engine);
+    assert_eq!(report.ord_status, OrdStatus::Canceled.to_char());
+    assert_eq!((report.cum_qty, report.leaves_qty), (4, 0));
+}
+
+#[test]
+fn test_order_status_for_an_unknown_order_is_rejected() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    let mut bridge = FixOrderBridge::new();
+
+    for request in [status_request(Some("42"), "STATUS", '1'), status_request(None, "NEVER-SENT", '1')] {
+        let report = bridge.order_status(&request, &engine);
+        assert_eq!(report.exec_type, ExecType::OrderStatus.to_char());
+        assert_eq!(report.ord_status, OrdStatus::Rejected.to_char());
+        assert_eq!(report.ord_rej_reason, Some(OrdRejReason::UnknownOrder.to_code()));
+        assert_eq!((report.symbol.as_str(), report.side), ("AAPL", '1'));
+        assert!(report.text.unwrap().contains("Unknown order"));
+    }
+}
+
+fn limit_order(cl_ord_id: &str, order_qty: i64, price: f64) -> NewOrderSingle {
+    NewOrderSingle {
+        header: StandardHeader {
+            begin_string: "FIX.4.4".to_string(),
+            body_length: 0,
+            msg_type: MessageType::NewOrderSingle,
+            sender_comp_id: "CLIENT123".to_string(),
+            target_comp_id: "EXCHANGE".to_string(),
+            msg_seq_num: 1,
+            sending_time: "20240101-12:00:00".to_string(),
+            poss_dup_flag: None,
+            poss_resend: None,
+            orig_sending_time: None,
+            secure_data_len: None,
+            secure_data: None,
+        },
+        cl_ord_id: cl_ord_id.to_string(),
+        account: None,
+        handl_inst: '1',
+        symbol: "AAPL".to_string(),
+        side: '1',
+        transact_time: "20240101-12:00:00".to_string(),
+        order_qty,
+        ord_type: '2',
+        price: Some(price),
+        stop_px: None,
+        time_in_force: None,
+        exec_inst: None,
+        trailer: Trailer { checksum: 0 },
+    }
+}
+
+#[test]
+fn test_price_band_rejects_fat_finger_prices() {
+    let mut bridge = FixOrderBridge::new();
+    bridge.validator_mut().set_price_band("AAPL", 5.0);
+
+    // No trade and no reference price: any price is accepted.
+    assert!(bridge.process_new_order(limit_order("A", 10, 500.0), None).is_ok());
+
+    bridge.validator_mut().set_reference_price("AAPL", 100_000_000);
+    assert!(bridge.process_new_order(limit_order("B", 10, 105.0), None).is_ok());
+    let error = bridge.process_new_order(limit_order("C", 10, 105.5), None).unwrap_err();
+    assert!(error.to_string().contains("5% away from the reference price 100"), "{}", error);
+
+    // The last trade takes over from the reference price.
+    assert!(bridge.process_new_order(limit_order("D", 10, 190.0), Some(200_000_000)).is_ok());
+    let Err(FixError::Business(error)) = bridge.process_new_order(limit_order("E", 10, 100.0), Some(200_000_000)) else {
+        panic!("expected a business reject");
+    };
+    assert_eq!(
+        exchange_rs::fix::mapping::business_error_to_ord_rej_reason(&error),
+        OrdRejReason::OrderExceedsLimit
+    );
+
+    // Other symbols are unbanded.
+    let mut order = limit_order("F", 10, 1_000.0);
+    order.symbol = "MSFT".to_string();
+    assert!(bridge.process_new_order(order, Some(100_000_000)).is_ok());
+}
+
+#[test]
+fn test_max_quantity_rejects_fat_finger_quantities() {
+    let mut bridge = FixOrderBridge::new();
+    bridge.validator_mut().set_max_quantity("AAPL", 1_000);
+
+    assert!(bridge.process_new_order(limit_order("A", 1_000, 100.0), None).is_ok());
+    let Err(FixError::Business(error)) = bridge.process_new_order(limit_order("B", 1_001, 100.0), None) else {
+        panic!("expected a business reject");
+    };
+    assert!(error.to_string().contains("maximum order quantity 1000 for AAPL"), "{}", error);
+    assert_eq!(
+        exchange_rs::fix::mapping::business_error_to_ord_rej_reason(&error),
+        OrdRejReason::IncorrectQuantity
+    );
+}
+
+fn cancel_request(sender_comp_id: &str, orig_cl_ord_id: &str, cl_ord_id: &str) -> OrderCancelRequest {
+    OrderCancelRequest {
+        header: StandardHeader {
+            begin_string: "FIX.4.4".to_string(),
+            body_length: 0,
+            msg_type: MessageType::OrderCancelRequest,
+            sender_comp_id: sender_comp_id.to_string(),
+            target_comp_id: "EXCHANGE".to_string(),
+            msg_seq_num: 3,
+            sending_time: "20240101-12:00:00".to_string(),
+            poss_dup_flag: None,
+            poss_resend: None,
+            orig_sending_time: None,
+            secure_data_len: None,
+            secure_data: None,
+        },
+        orig_cl_ord_id: orig_cl_ord_id.to_string(),
+        cl_ord_id: cl_ord_id.to_string(),
+        symbol: "AAPL".to_string(),
+        side: '1',
+        transact_time: "20240101-12:00:00".to_string(),
+        order_qty: None,
+        account: None,
+        text: None,
+        trailer: Trailer { checksum: 0 },
+    }
+}
+
+#[test]
+fn test_cancel_finds_the_order_by_orig_cl_ord_id() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    let mut bridge = FixOrderBridge::new();
+
+    let order = bridge.process_new_order(limit_order("BUY-1", 10, 100.0), None).unwrap();
+    assert_eq!(order.client_order_id.as_deref(), Some("BUY-1"));
+    let order_id = engine.place_order(order).unwrap().remaining_order.unwrap().read().id;
+
+    // Another session's ClOrdIDs are not this one's to cancel.
+    let report = bridge.cancel_order(&cancel_request("OTHER", "BUY-1", "CXL-0"), &mut engine);
+    assert_eq!(report.ord_status, OrdStatus::Rejected.to_char());
+
+    let report = bridge.cancel_order(&cancel_request("CLIENT123", "BUY-1", "CXL-1"), &mut engine);
+    assert_eq!(report.exec_type, ExecType::Canceled.to_char());
+    assert_eq!(report.ord_status, OrdStatus::Canceled.to_char());
+    assert_eq!((report.cl_ord_id.as_str(), report.orig_cl_ord_id.as_deref()), ("CXL-1", Some("BUY-1")));
+    assert_eq!((report.order_id, report.leaves_qty), (order_id.to_string(), 0));
+    assert!(engine.order_books["AAPL"].get_order(order_id).is_none());
+
+    let report = bridge.cancel_order(&cancel_request("CLIENT123", "BUY-1", "CXL-2"), &mut engine);
+    assert_eq!(report.ord_status, OrdStatus::Rejected.to_char());
+    assert_eq!(report.ord_rej_reason, Some(OrdRejReason::UnknownOrder.to_code()));
+
+    // The ClOrdID is free again once its order is gone.
+    assert!(bridge.process_new_order(limit_order("BUY-1", 5, 100.0), None).is_ok());
+}
@@ -0,0 +1,129 @@
+use exchange_rs::fix::mapping::{self, MappingError};
+use exchange_rs::fix::market_data::indicative_uncross_entries;
+use exchange_rs::fix::messages::execution_report::{ExecType, OrdStatus};
+use exchange_rs::order::{OrderStatus, OrderType, Side, TimeInForce};
+use exchange_rs::orderbook::{IndicativeUncross, TradingState};
+
+#[test]
+fn test_side_round_trip() {
+    let table = [(Side::Buy, '1'), (Side::Sell, '2')];
+
+    for (side, code) in table {
+        assert_eq!(mapping::side_to_fix(side), code);
+        assert_eq!(mapping::fix_to_side(code), Ok(side));
+    }
+}
+
+#[test]
+fn test_order_type_round_trip() {
+    let table = [
+        (OrderType::Market, '1'),
+        (OrderType::Limit, '2'),
+        (OrderType::StopMarket, '3'),
+        (OrderType::StopLimit, '4'),
+    ];
+
+    for (order_type, code) in table {
+        assert_eq!(mapping::order_type_to_fix(order_type), code);
+        assert_eq!(mapping::fix_to_order_type(code), Ok(order_type));
+    }
+
+    assert_eq!(mapping::order_type_to_fix(OrderType::Iceberg), '2');
+}
+
+#[test]
+fn test_time_in_force_round_trip() {
+    let table = [
+        (TimeInForce::Day, '0'),
+        (TimeInForce::GTC, '1'),
+        (TimeInForce::IOC, '3'),
+        (TimeInForce::FOK, '4'),
+        (TimeInForce::GTD, '6'),
+    ];
+
+    for (time_in_force, code) in table {
+        assert_eq!(mapping::time_in_force_to_fix(time_in_force), code);
+        assert_eq!(mapping::fix_to_time_in_force(code), Ok(time_in_force));
+    }
+
+    assert_eq!(mapping::fix_to_time_in_force_or_default(None), Ok(TimeInForce::GTC));
+}
+
+#[test]
+fn test_order_status_round_trip() {
+    let table = [
+        (OrderStatus::New, ExecType::New, OrdStatus::New),
+        (OrderStatus::PartiallyFilled, ExecType::PartialFill, OrdStatus::PartiallyFilled),
+        (OrderStatus::Filled, ExecType::Fill, OrdStatus::Filled),
+        (OrderStatus::Canceled, ExecType::Canceled, OrdStatus::Canceled),
+        (OrderStatus::Rejected, ExecType::Rejected, OrdStatus::Rejected),
+        (OrderStatus::Expired, ExecType::Expired, OrdStatus::Expired),
+    ];
+
+    for (status, exec_type, ord_status) in table {
+        assert_eq!(mapping::order_status_to_exec_type(status), exec_type);
+        assert_eq!(mapping::order_status_to_fix(status), ord_status);
+        assert_eq!(mapping::fix_exec_type_to_order_status(exec_type.to_char()), Ok(status));
+        assert_eq!(mapping::fix_to_order_status(ord_status.to_char()), Ok(status));
+    }
+}
+
+#[test]
+fn test_trading_state_round_trip() {
+    let table = [
+        (TradingState::Halted, '1'),
+        (TradingState::Continuous, '2'),
+        (TradingState::Auction, '4'),
+    ];
+
+    for (state, code) in table {
+        assert_eq!(mapping::trading_state_to_fix(state), code);
+        assert_eq!(mapping::fix_to_trading_state(code), Ok(state));
+    }
+}
+
+#[test]
+fn test_unsupported_values() {
+    let cases = [
+        (mapping::fix_to_side('3').map(|_| ()), 54, '3'),
+        (mapping::fix_to_order_type('P').map(|_| ()), 40, 'P'),
+        (mapping::fix_to_time_in_force('2').map(|_| ()), 59, '2'),
+        (mapping::fix_to_order_status('6').map(|_| ()), 39, '6'),
+        (mapping::fix_exec_type_to_order_status('F').map(|_| ()), 150, 'F'),
+        (mapping::fix_to_trading_state('3').map(|_| ()), 340, '3'),
+    ];
+
+    for (result, tag, value) in cases {
+        assert_eq!(result, Err(MappingError::UnsupportedValue { tag, value }));
+    }
+}
+
+#[test]
+fn test_indicative_uncross_market_data_entries() {
+    let uncross = IndicativeUncross {
+        price: 101_500_000,
+        volume: 15,
+        imbalance: 9,
+        imbalance_side: Some(Side::Sell),
+    };
+    let fields = indicative_uncross_entries(Some(&uncross));
+    let expected = [
+        (268, "2"),
+        (279, "0"),
+        (269, "Q"),
+        (270, "101.5"),
+        (271, "15"),
+        (279, "0"),
+        (269, "A"),
+        (271, "9"),
+        (54, "2"),
+    ];
+    assert_eq!(fields.len(), expected.len());
+    for ((tag, value), (expected_tag, expected_value)) in fields.iter().zip(expected) {
+        assert_eq!((*tag, value.as_str()), (expected_tag, expected_value));
+    }
+
+    let cleared = indicative_uncross_entries(None);
+    assert_eq!(cleared[0], (268, "2".to_string()));
+    assert!(cleared.iter().all(|(tag, _)| *tag != 270 && *tag != 271));
+}
@@ -0,0 +1,188 @@
+use exchange_rs::fix::encoder;
+use exchange_rs::fix::market_data::{MarketDataReject, MarketDataSubscription};
+use exchange_rs::fix::messages::market_data_request::MarketDataRequest;
+use exchange_rs::fix::messages::{FixMessage, MdIncrementalEntry};
+use exchange_rs::fix::parser::FixParser;
+use exchange_rs::fix::session::FixSessionState;
+use exchange_rs::matching_engine::MatchingEngine;
+use exchange_rs::order::{Order, OrderType, Side};
+use exchange_rs::price_utils::price_to_scaled;
+
+mod test_utils;
+use test_utils::frame;
+
+fn request(fields: &str) -> MarketDataRequest {
+    let message = frame(&format!(
+        "35=V|49=CLIENT|56=EXCHANGE|34=2|52=20240101-12:00:00|{}",
+        fields
+    ));
+    match FixParser::new().parse(&message).unwrap() {
+        FixMessage::MarketDataRequest(request) => request,
+        other => panic!("expected a MarketDataRequest, got {:?}", other),
+    }
+}
+
+fn engine() -> MatchingEngine {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    engine
+}
+
+fn place(engine: &mut MatchingEngine, side: Side, price: f64, quantity: u32) -> u64 {
+    let order = Order::new(
+        "AAPL".to_string(),
+        side,
+        OrderType::Limit,
+        price_to_scaled(price).unwrap(),
+        quantity,
+        1,
+    );
+    let result = engine.place_order(order).unwrap();
+    let resting = result.remaining_order.expect("order rests");
+    let id = resting.read().id;
+    id
+}
+
+fn session() -> FixSessionState {
+    FixSessionState::new("EXCHANGE".to_string(), "CLIENT".to_string())
+}
+
+fn incremental_entries(messages: Vec<FixMessage>) -> Vec<MdIncrementalEntry> {
+    match <[FixMessage; 1]>::try_from(messages) {
+        Ok([FixMessage::MarketDataIncrementalRefresh(refresh)]) => refresh.entries,
+        other => panic!("expected one incremental refresh, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_market_data_request_groups() {
+    let request = request("262=REQ-1|263=1|264=5|265=1|267=2|269=0|269=1|146=2|55=AAPL|55=MSFT|");
+
+    assert_eq!(request.md_req_id, "REQ-1");
+    assert_eq!(request.subscription_request_type, '1');
+    assert_eq!(request.market_depth, 5);
+    assert_eq!(request.md_update_type, Some(1));
+    assert_eq!(request.md_entry_types, vec!['0', '1']);
+    assert_eq!(request.symbols, vec!["AAPL".to_string(), "MSFT".to_string()]);
+}
+
+#[test]
+fn test_subscription_requires_update_type() {
+    let message = frame(
+        "35=V|49=CLIENT|56=EXCHANGE|34=2|52=20240101-12:00:00|262=REQ-1|263=1|264=0|267=1|269=0|146=1|55=AAPL|",
+    );
+
+    assert!(FixParser::new().parse(&message).is_err());
+}
+
+#[test]
+fn test_incremental_refresh_round_trips_through_the_encoder() {
+    let mut engine = engine();
+    let bid = place(&mut engine, Side::Buy, 99.5, 10);
+    let mut subscription = MarketDataSubscription::new(&request(
+        "262=REQ-1|263=1|264=0|265=1|267=2|269=0|269=1|146=1|55=AAPL|",
+    ))
+    .unwrap();
+    subscription.snapshot(&engine, &session()).unwrap();
+
+    engine.cancel_order("AAPL", bid);
+    place(&mut engine, Side::Sell, 101.0, 4);
+    let update = subscription.poll(&engine, &session());
+    let encoded = encoder::encode(&update[0]);
+
+    let entries = incremental_entries(vec![FixParser::new().parse(&encoded).unwrap()]);
+    assert_eq!(entries, incremental_entries(update));
+    assert_eq!(
+        entries,
+        vec![
+            MdIncrementalEntry {
+                md_update_action: '2',
+                md_entry_type: '0',
+                md_entry_id: None,
+                symbol: "AAPL".to_string(),
+                md_entry_px: 99.5,
+                md_entry_size: 0,
+                rpt_seq: None,
+            },
+            MdIncrementalEntry {
+                md_update_action: '0',
+                md_entry_type: '1',
+                md_entry_id: None,
+                symbol: "AAPL".to_string(),
+                md_entry_px: 101.0,
+                md_entry_size: 4,
+                rpt_seq: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_market_depth_limits_levels() {
+    let mut engine = engine();
+    place(&mut engine, Side::Buy, 99.0, 1);
+    place(&mut engine, Side::Buy, 99.5, 2);
+    let mut subscription = MarketDataSubscription::new(&request(
+        "262=REQ-1|263=1|264=1|265=1|267=1|269=0|146=1|55=AAPL|",
+    ))
+    .unwrap();
+
+    match subscription.snapshot(&engine, &session()).unwrap().as_slice() {
+        [FixMessage::MarketDataSnapshotFullRefresh(refresh)] => {
+            assert_eq!(refresh.entries.len(), 1);
+            assert_eq!(refresh.entries[0].md_entry_px, 99.5);
+            assert_eq!(refresh.entries[0].md_entry_size, 2);
+        }
+        other => panic!("expected one full refresh, got {:?}", other),
+    }
+
+    place(&mut engine, Side::Buy, 98.0, 3);
+    assert!(subscription.poll(&engine, &session()).is_empty());
+
+    place(&mut engine, Side::Buy, 99.5, 1);
+    let entries = incremental_entries(subscription.poll(&engine, &session()));
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].md_update_action, '1');
+    assert_eq!(entries[0].md_entry_size, 3);
+}
+
+#[test]
+fn test_full_refresh_updates_resend_the_book() {
+    let mut engine = engine();
+    let mut subscription = MarketDataSubscription::new(&request(
+        "262=REQ-1|263=1|264=0|265=0|267=2|269=0|269=1|146=1|55=AAPL|",
+    ))
+    .unwrap();
+    subscription.snapshot(&engine, &session()).unwrap();
+
+    place(&mut engine, Side::Buy, 99.0, 1);
+    place(&mut engine, Side::Sell, 100.0, 2);
+    match subscription.poll(&engine, &session()).as_slice() {
+        [FixMessage::MarketDataSnapshotFullRefresh(refresh)] => {
+            assert_eq!(refresh.md_req_id.as_deref(), Some("REQ-1"));
+            assert_eq!(refresh.entries.len(), 2);
+        }
+        other => panic!("expected one full refresh, got {:?}", other),
+    }
+    assert!(subscription.poll(&engine, &session()).is_empty());
+}
+
+#[test]
+fn test_unsupported_requests_are_rejected() {
+    let trades = request("262=REQ-1|263=0|264=0|267=1|269=2|146=1|55=AAPL|");
+    assert_eq!(
+        MarketDataSubscription::new(&trades).unwrap_err(),
+        MarketDataReject::UnsupportedMdEntryType { value: '2' }
+    );
+
+    let mut unlisted = MarketDataSubscription::new(&request(
+        "262=REQ-2|263=0|264=0|267=1|269=0|146=2|55=AAPL|55=ZZZZ|",
+    ))
+    .unwrap();
+    assert_eq!(
+        unlisted.snapshot(&engine(), &session()).unwrap_err(),
+        MarketDataReject::UnknownSymbol {
+            symbol: "ZZZZ".to_string()
+        }
+    );
+}
@@ -0,0 +1,124 @@
+use exchange_rs::fix::bridge::order_converter::{MAX_ORDER_PRICE, MAX_ORDER_QTY};
+use exchange_rs::fix::bridge::{FixOrderBridge, FixOrderConverter};
+use exchange_rs::fix::error::{BusinessError, FixError};
+use exchange_rs::fix::mapping::business_error_to_ord_rej_reason;
+use exchange_rs::fix::messages::execution_report::OrdRejReason;
+use exchange_rs::fix::messages::FixMessage;
+use exchange_rs::fix::parser::FixParser;
+
+mod test_utils;
+use test_utils::frame;
+
+fn new_order(quantity: &str, price: &str) -> Vec<u8> {
+    frame(&format!(
+        "35=D|49=CLIENT1|56=EXCHANGE|34=2|52=20240101-12:00:00|11=ORDER1|21=1|55=AAPL|54=1|60=20240101-12:00:00|38={}|40=2|44={}|",
+        quantity, price
+    ))
+}
+
+fn reason(error: BusinessError) -> OrdRejReason {
+    business_error_to_ord_rej_reason(&error)
+}
+
+#[test]
+fn test_quantity_range() {
+    assert_eq!(FixOrderConverter::quantity_from_fix(MAX_ORDER_QTY as i64).unwrap(), u32::MAX);
+    assert_eq!(FixOrderConverter::quantity_from_fix(1).unwrap(), 1);
+
+    let error = FixOrderConverter::quantity_from_fix(MAX_ORDER_QTY as i64 + 1).unwrap_err();
+    assert!(error.to_string().contains("quantity exceeds maximum supported 4294967295"));
+    assert_eq!(reason(error), OrdRejReason::IncorrectQuantity);
+
+    for quantity in [0, -1, i64::MIN] {
+        let error = FixOrderConverter::quantity_from_fix(quantity).unwrap_err();
+        assert_eq!(reason(error), OrdRejReason::IncorrectQuantity);
+    }
+}
+
+#[test]
+fn test_price_range() {
+    let scaled = FixOrderConverter::price_from_fix(MAX_ORDER_PRICE).unwrap();
+    assert_eq!(scaled / 1_000_000, 18_446_744_073_709);
+
+    let error = FixOrderConverter::price_from_fix(MAX_ORDER_PRICE + 1.0).unwrap_err();
+    assert!(error.to_string().contains("price exceeds maximum supported 18446744073709"));
+    assert_eq!(reason(error), OrdRejReason::UnsupportedOrderCharacteristic);
+
+    for price in [-0.01, f64::NAN, f64::INFINITY, f64::MAX] {
+        let error = FixOrderConverter::price_from_fix(price).unwrap_err();
+        assert_eq!(reason(error), OrdRejReason::UnsupportedOrderCharacteristic);
+    }
+}
+
+#[test]
+fn test_quantity_is_not_wrapped() {
+    let FixMessage::NewOrderSingle(order) = FixParser::new().parse(&new_order("4294967297", "100")).unwrap() else {
+        panic!("expected a NewOrderSingle");
+    };
+    assert_eq!(order.order_qty, 4_294_967_297);
+
+    let error = FixOrderBridge::new()
+        .process_fix_message(FixMessage::NewOrderSingle(order))
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        FixError::Business(BusinessError::QuantityOutOfRange { quantity: 4_294_967_297, .. })
+    ));
+}
+
+#[test]
+fn test_exponent_notation_is_not_a_fix_number() {
+    let mut parser = FixParser::new();
+
+    assert!(parser.parse(&new_order("1e3", "100")).is_err());
+    for price in ["1e3", "1E-2", "inf", "NaN", "+1", ".", "-", "1.2.3", "0x10"] {
+        assert!(parser.parse(&new_order("1", price)).is_err(), "price {} parsed", price);
+    }
+    for price in ["100", "100.", ".5", "-0.25", "0018446744073709"] {
+        assert!(parser.parse(&new_order("1", price)).is_ok(), "price {} refused", price);
+    }
+}
+
+/// Digit strings around the limits and random ones of every length, through
+/// the parser and the bridge. Nothing may panic, and an order accepted
+/// must carry exactly the quantity sent.
+#[test]
+fn test_extreme_numeric_strings() {
+    let mut values: Vec<String> = [
+        "0", "1", "-1", "4294967295", "4294967296", "-4294967296", "9223372036854775807",
+        "9223372036854775808", "-9223372036854775808", "18446744073709551616",
+        "18446744073709", "18446744073710", "18446744073709.5", "0.000001", "0.0000001",
+        "99999999999999999999999999999999", "-0", "00000000000000000001",
+    ]
+    .iter()
+    .map(|value| value.to_string())
+    .collect();
+
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    for length in 1..=40 {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let digits: String = (0..length)
+            .map(|i| char::from(b'0' + ((state >> (i % 16 * 4)) & 0xf) as u8 % 10))
+            .collect();
+        values.push(digits.clone());
+        values.push(format!("-{}", digits));
+        values.push(format!("{}.{}", &digits[..length / 2], &digits[length / 2..]));
+    }
+
+    let mut parser = FixParser::new();
+    for quantity in &values {
+        for price in &values {
+            let Ok(FixMessage::NewOrderSingle(fix_order)) = parser.parse(&new_order(quantity, price)) else {
+                continue;
+            };
+            let sent = fix_order.order_qty;
+            if let Ok(order) = FixOrderConverter::new().convert_new_order_single(fix_order.clone()) {
+                assert_eq!(order.quantity as i64, sent, "quantity {} price {}", quantity, price);
+                assert!(order.price > 0);
+            }
+            let _ = FixOrderBridge::new().process_fix_message(FixMessage::NewOrderSingle(fix_order));
+        }
+    }
+}
@@ -0,0 +1,381 @@
+use exchange_rs::fix::{
+    error::{FixError, SessionError},
+    messages::FixMessage,
+    parser::FixParser,
+    session::{FixSession, FixSessionState, MessageStore, SessionStatus},
+};
+use exchange_rs::clock::ManualClock;
+use exchange_rs::timers::Timers;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+mod test_utils;
+use test_utils::frame;
+
+fn cancel_request(msg_seq_num: u32, poss_dup: bool) -> Vec<u8> {
+    let poss_dup = if poss_dup { "43=Y|" } else { "" };
+    frame(&format!(
+        "35=F|49=CLIENT|56=EXCHANGE|34={}|{}52=20240101-12:00:00|41=ORIG{}|11=CXL{}|55=AAPL|54=1|60=20240101-12:00:00|",
+        msg_seq_num, poss_dup, msg_seq_num, msg_seq_num
+    ))
+}
+
+fn resend_request(msg_seq_num: u32, begin: u32, end: u32) -> Vec<u8> {
+    frame(&format!(
+        "35=2|49=CLIENT|56=EXCHANGE|34={}|52=20240101-12:00:00|7={}|16={}|",
+        msg_seq_num, begin, end
+    ))
+}
+
+fn heartbeat(msg_seq_num: u32) -> Vec<u8> {
+    frame(&format!("35=0|49=CLIENT|56=EXCHANGE|34={}|52=20240101-12:00:00|", msg_seq_num))
+}
+
+fn heartbeat_echo(msg_seq_num: u32, test_req_id: &str) -> Vec<u8> {
+    frame(&format!(
+        "35=0|49=CLIENT|56=EXCHANGE|34={}|52=20240101-12:00:00|112={}|",
+        msg_seq_num, test_req_id
+    ))
+}
+
+fn test_request(msg_seq_num: u32, test_req_id: &str) -> Vec<u8> {
+    frame(&format!(
+        "35=1|49=CLIENT|56=EXCHANGE|34={}|52=20240101-12:00:00|112={}|",
+        msg_seq_num, test_req_id
+    ))
+}
+
+fn gap_fill(msg_seq_num: u32, new_seq_no: u32) -> Vec<u8> {
+    frame(&format!(
+        "35=4|49=CLIENT|56=EXCHANGE|34={}|43=Y|52=20240101-12:00:00|123=Y|36={}|",
+        msg_seq_num, new_seq_no
+    ))
+}
+
+fn logout(msg_seq_num: u32) -> Vec<u8> {
+    frame(&format!(
+        "35=5|49=CLIENT|56=EXCHANGE|34={}|52=20240101-12:00:00|58=Done for the day|",
+        msg_seq_num
+    ))
+}
+
+fn seq_nums(messages: &[FixMessage]) -> Vec<u32> {
+    messages.iter().map(|message| message.header().msg_seq_num).collect()
+}
+
+fn session() -> FixSession {
+    FixSession::new("EXCHANGE".to_string(), "CLIENT".to_string())
+}
+
+#[tokio::test]
+async fn test_gap_is_queued_until_resent() {
+    let mut session = session();
+
+    let ready = session.process_incoming_message(&cancel_request(1, false)).await.unwrap();
+    assert_eq!(seq_nums(&ready), vec![1]);
+
+    for msg_seq_num in [4, 5] {
+        let ready = session.process_incoming_message(&cancel_request(msg_seq_num, false)).await.unwrap();
+        assert!(ready.is_empty());
+    }
+    assert_eq!(session.pending_resend(), Some((2, 3)));
+    assert_eq!(session.queued_message_count(), 2);
+    assert_eq!(session.get_incoming_seq_num(), 2);
+
+    let ready = session.process_incoming_message(&cancel_request(2, true)).await.unwrap();
+    assert_eq!(seq_nums(&ready), vec![2]);
+    assert_eq!(session.pending_resend(), Some((2, 3)));
+
+    let ready = session.process_incoming_message(&cancel_request(3, true)).await.unwrap();
+    assert_eq!(seq_nums(&ready), vec![3, 4, 5]);
+    assert_eq!(session.pending_resend(), None);
+    assert_eq!(session.queued_message_count(), 0);
+    assert_eq!(session.get_incoming_seq_num(), 6);
+}
+
+#[tokio::test]
+async fn test_new_gap_is_requested_after_recovery() {
+    let mut session = session();
+
+    session.process_incoming_message(&cancel_request(3, false)).await.unwrap();
+    assert_eq!(session.pending_resend(), Some((1, 2)));
+    session.process_incoming_message(&cancel_request(6, false)).await.unwrap();
+    assert_eq!(session.pending_resend(), Some((1, 2)));
+
+    session.process_incoming_message(&cancel_request(1, true)).await.unwrap();
+    let ready = session.process_incoming_message(&cancel_request(2, true)).await.unwrap();
+    assert_eq!(seq_nums(&ready), vec![2, 3]);
+    assert_eq!(session.pending_resend(), Some((4, 5)));
+}
+
+#[tokio::test]
+async fn test_low_sequence_number() {
+    let mut session = session();
+    session.process_incoming_message(&cancel_request(1, false)).await.unwrap();
+    session.process_incoming_message(&cancel_request(2, false)).await.unwrap();
+
+    let ready = session.process_incoming_message(&cancel_request(1, true)).await.unwrap();
+    assert!(ready.is_empty());
+    assert_eq!(session.get_incoming_seq_num(), 3);
+
+    match session.process_incoming_message(&cancel_request(2, false)).await {
+        Err(FixError::Session(SessionError::InvalidSequenceNumber { expected, actual })) => {
+            assert_eq!((expected, actual), (3, 2))
+        }
+        other => panic!("expected a sequence number error, got {:?}", other),
+    }
+    assert_eq!(session.get_session_status(), SessionStatus::LoggedOut);
+}
+
+#[tokio::test]
+async fn test_resend_request_is_consumed_in_sequence() {
+    let mut session = session();
+
+    let ready = session.process_incoming_message(&resend_request(1, 1, 0)).await.unwrap();
+    assert!(ready.is_empty());
+    assert_eq!(session.get_incoming_seq_num(), 2);
+
+    assert!(session.process_incoming_message(&resend_request(2, 5, 3)).await.is_err());
+}
+
+#[tokio::test]
+async fn test_gap_fill_advances_expected_sequence() {
+    let mut session = session();
+    session.process_incoming_message(&cancel_request(1, false)).await.unwrap();
+    session.process_incoming_message(&cancel_request(4, false)).await.unwrap();
+    assert_eq!(session.pending_resend(), Some((2, 3)));
+
+    let ready = session.process_incoming_message(&gap_fill(2, 4)).await.unwrap();
+    assert_eq!(seq_nums(&ready), vec![4]);
+    assert_eq!(session.pending_resend(), None);
+    assert_eq!(session.get_incoming_seq_num(), 5);
+
+    let reset = frame("35=4|49=CLIENT|56=EXCHANGE|34=1|52=20240101-12:00:00|36=20|");
+    assert!(session.process_incoming_message(&reset).await.unwrap().is_empty());
+    assert_eq!(session.get_incoming_seq_num(), 20);
+}
+
+fn outgoing_store(messages: Vec<Vec<u8>>) -> MessageStore {
+    let mut parser = FixParser::new();
+    let mut store = MessageStore::new();
+    for data in messages {
+        store.store_outgoing_message(&parser.parse(&data).unwrap()).unwrap();
+    }
+    store
+}
+
+#[test]
+fn test_resend_response_for_sequences_5_to_8() {
+    let store = outgoing_store(vec![
+        cancel_request(4, false),
+        cancel_request(5, false),
+        heartbeat(6),
+        resend_request(7, 1, 0),
+        cancel_request(8, false),
+        cancel_request(9, false),
+    ]);
+    let state = FixSessionState::new("CLIENT".to_string(), "EXCHANGE".to_string());
+
+    assert_eq!(seq_nums(&store.get_range(5, 8)), vec![5, 6, 7, 8]);
+
+    let response = store.resend_response(&state, 5, 8);
+    assert_eq!(seq_nums(&response), vec![5, 6, 8]);
+    assert!(response.iter().all(|message| message.header().poss_dup_flag == Some(true)));
+
+    for index in [0, 2] {
+        let FixMessage::OrderCancelRequest(cancel) = &response[index] else {
+            panic!("expected a resent cancel request, got {:?}", response[index]);
+        };
+        assert_eq!(cancel.header.orig_sending_time.as_deref(), Some("20240101-12:00:00"));
+    }
+
+    let FixMessage::SequenceReset(reset) = &response[1] else {
+        panic!("expected a gap fill, got {:?}", response[1]);
+    };
+    assert!(reset.is_gap_fill());
+    assert_eq!(reset.new_seq_no, 8);
+
+    assert_eq!(store.get_outgoing_message(5).unwrap().header().poss_dup_flag, None);
+}
+
+#[test]
+fn test_resend_response_fills_missing_tail() {
+    let store = outgoing_store(vec![cancel_request(1, false), heartbeat(2)]);
+    let state = FixSessionState::new("CLIENT".to_string(), "EXCHANGE".to_string());
+
+    let response = store.resend_response(&state, 1, 4);
+    assert_eq!(seq_nums(&response), vec![1, 2]);
+    let FixMessage::SequenceReset(reset) = &response[1] else {
+        panic!("expected a gap fill, got {:?}", response[1]);
+    };
+    assert_eq!(reset.new_seq_no, 5);
+}
+
+/// A session logged on to a local peer, and the peer's end of the socket.
+async fn connected_session() -> (FixSession, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let mut session = session();
+    let (started, accepted) = tokio::join!(session.start(&address), listener.accept());
+    started.unwrap();
+    (session, accepted.unwrap().0)
+}
+
+async fn assert_closed(peer: &mut TcpStream) {
+    let mut buffer = [0u8; 64];
+    assert_eq!(peer.read(&mut buffer).await.unwrap(), 0);
+}
+
+#[test]
+fn test_parse_logout() {
+    let message = FixParser::new().parse(&logout(3)).unwrap();
+    let FixMessage::Logout(logout) = message else {
+        panic!("expected a Logout, got {:?}", message);
+    };
+    assert_eq!(logout.header.msg_seq_num, 3);
+    assert_eq!(logout.text.as_deref(), Some("Done for the day"));
+}
+
+#[tokio::test]
+async fn test_counterparty_logout_is_answered_then_disconnected() {
+    let (mut session, mut peer) = connected_session().await;
+    assert_eq!(session.get_session_status(), SessionStatus::LoggedOn);
+
+    let ready = session.process_incoming_message(&logout(1)).await.unwrap();
+    assert!(ready.is_empty());
+    assert_eq!(session.get_outgoing_seq_num(), 3);
+    assert_eq!(session.get_session_status(), SessionStatus::Disconnected);
+    assert_closed(&mut peer).await;
+}
+
+#[tokio::test]
+async fn test_shutdown_waits_for_logout_confirmation() {
+    let (mut session, mut peer) = connected_session().await;
+    session.set_logout_timeout(Duration::from_secs(5));
+
+    let confirmation = logout(1);
+    let started = Instant::now();
+    let (shutdown, _) = tokio::join!(session.shutdown(), peer.write_all(&confirmation));
+    shutdown.unwrap();
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(session.get_incoming_seq_num(), 2);
+    assert_eq!(session.get_outgoing_seq_num(), 3);
+    assert_eq!(session.get_session_status(), SessionStatus::Disconnected);
+    assert_closed(&mut peer).await;
+}
+
+#[tokio::test]
+async fn test_shutdown_closes_after_logout_timeout() {
+    let (mut session, mut peer) = connected_session().await;
+    session.set_logout_timeout(Duration::from_millis(100));
+
+    let started = Instant::now();
+    session.shutdown().await.unwrap();
+    assert!(started.elapsed() >= Duration::from_millis(100));
+    assert_eq!(session.get_incoming_seq_num(), 1);
+    assert_eq!(session.get_session_status(), SessionStatus::Disconnected);
+    assert_closed(&mut peer).await;
+}
+
+#[test]
+fn test_parse_test_request() {
+    let message = FixParser::new().parse(&test_request(4, "PROBE-1")).unwrap();
+    let FixMessage::TestRequest(request) = message else {
+        panic!("expected a TestRequest, got {:?}", message);
+    };
+    assert_eq!(request.header.msg_seq_num, 4);
+    assert_eq!(request.test_req_id, "PROBE-1");
+
+    let missing_id = frame("35=1|49=CLIENT|56=EXCHANGE|34=4|52=20240101-12:00:00|");
+    assert!(FixParser::new().parse(&missing_id).is_err());
+}
+
+#[tokio::test]
+async fn test_silence_is_probed_before_timing_out() {
+    let (mut session, _peer) = connected_session().await;
+    session.set_heartbeat_interval(Duration::from_millis(50));
+
+    session.check_heartbeat_timeout().await.unwrap();
+    assert_eq!(session.pending_test_request(), None);
+
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    session.check_heartbeat_timeout().await.unwrap();
+    assert!(session.pending_test_request().is_some());
+    assert_eq!(session.get_outgoing_seq_num(), 3);
+
+    // Waiting on the answer does not send another probe.
+    session.check_heartbeat_timeout().await.unwrap();
+    assert_eq!(session.get_outgoing_seq_num(), 3);
+
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    match session.check_heartbeat_timeout().await {
+        Err(FixError::Session(SessionError::HeartbeatTimeout)) => {}
+        other => panic!("expected a heartbeat timeout, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_only_echoed_test_req_id_answers_probe() {
+    let (mut session, _peer) = connected_session().await;
+    session.set_heartbeat_interval(Duration::from_millis(50));
+
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    session.check_heartbeat_timeout().await.unwrap();
+    let test_req_id = session.pending_test_request().unwrap().to_string();
+
+    session.process_incoming_message(&heartbeat(1)).await.unwrap();
+    session.process_incoming_message(&heartbeat_echo(2, "SOMETHING-ELSE")).await.unwrap();
+    assert_eq!(session.pending_test_request(), Some(test_req_id.as_str()));
+
+    session.process_incoming_message(&heartbeat_echo(3, &test_req_id)).await.unwrap();
+    assert_eq!(session.pending_test_request(), None);
+
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    session.check_heartbeat_timeout().await.unwrap();
+    let next_id = session.pending_test_request().unwrap();
+    assert_ne!(next_id, test_req_id);
+}
+
+#[tokio::test]
+async fn test_probe_runs_on_a_shared_timer_wheel() {
+    let (mut session, _peer) = connected_session().await;
+    let clock = Arc::new(ManualClock::new(1_700_000_000_000_000_000));
+    let timers = Timers::new(clock.clone());
+    session.set_timers(timers.clone());
+    session.set_heartbeat_interval(Duration::from_secs(30));
+    let second = 1_000_000_000;
+
+    clock.advance(29 * second);
+    session.check_heartbeat_timeout().await.unwrap();
+    session.process_incoming_message(&heartbeat(1)).await.unwrap();
+
+    // The first deadline finds recent traffic and re-arms from it.
+    clock.advance(2 * second);
+    session.check_heartbeat_timeout().await.unwrap();
+    assert_eq!(session.pending_test_request(), None);
+    assert_eq!(timers.pending(), 1);
+
+    clock.advance(28 * second);
+    session.check_heartbeat_timeout().await.unwrap();
+    assert!(session.pending_test_request().is_some());
+
+    clock.advance(29 * second);
+    session.check_heartbeat_timeout().await.unwrap();
+    clock.advance(second);
+    match session.check_heartbeat_timeout().await {
+        Err(FixError::Session(SessionError::HeartbeatTimeout)) => {}
+        other => panic!("expected a heartbeat timeout, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_counterparty_test_request_is_answered() {
+    let (mut session, _peer) = connected_session().await;
+
+    let ready = session.process_incoming_message(&test_request(1, "PROBE-1")).await.unwrap();
+    assert!(ready.is_empty());
+    assert_eq!(session.get_incoming_seq_num(), 2);
+    assert_eq!(session.get_outgoing_seq_num(), 3);
+}
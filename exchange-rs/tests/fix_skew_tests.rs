@@ -0,0 +1,121 @@
+use exchange_rs::{
+    clock::ManualClock,
+    fix::{
+        error::{FixError, SessionError},
+        messages::{MessageType, StandardHeader},
+        session::skew::{parse_sending_time, SkewConfig, SkewPolicy, SkewTracker},
+    },
+    fix_gateway::FixGateway,
+    matching_engine::{MatchingEngine, MatchingEngineConfig},
+};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+const NOON_2024_01_01: i64 = 1_704_110_400_000_000_000;
+const SECOND: i64 = 1_000_000_000;
+
+fn header(sender_comp_id: &str, sending_time: &str) -> StandardHeader {
+    StandardHeader {
+        begin_string: "FIX.4.4".to_string(),
+        body_length: 100,
+        msg_type: MessageType::NewOrderSingle,
+        sender_comp_id: sender_comp_id.to_string(),
+        target_comp_id: "EXCHANGE".to_string(),
+        msg_seq_num: 1,
+        sending_time: sending_time.to_string(),
+        poss_dup_flag: None,
+        poss_resend: None,
+        orig_sending_time: None,
+        secure_data_len: None,
+        secure_data: None,
+    }
+}
+
+fn tracker_at(nanos: i64) -> (Arc<ManualClock>, SkewTracker) {
+    let clock = Arc::new(ManualClock::new(nanos));
+    let tracker = SkewTracker::new(clock.clone());
+    (clock, tracker)
+}
+
+#[test]
+fn test_parse_sending_time() {
+    assert_eq!(parse_sending_time("20240101-12:00:00"), Some(NOON_2024_01_01));
+    assert_eq!(parse_sending_time("20240101-12:00:00.250"), Some(NOON_2024_01_01 + SECOND / 4));
+    assert_eq!(parse_sending_time("2024-01-01 12:00"), None);
+}
+
+#[test]
+fn test_reject_policy() {
+    let (clock, tracker) = tracker_at(NOON_2024_01_01);
+
+    assert!(tracker.check(&header("CLIENT1", "20240101-12:01:59")).is_ok());
+
+    clock.advance(-5 * SECOND);
+    match tracker.check(&header("CLIENT1", "20240101-12:02:00")) {
+        Err(FixError::Session(SessionError::SendingTimeAccuracy { skew_ns })) => {
+            assert_eq!(skew_ns, 125 * SECOND)
+        }
+        other => panic!("expected a SendingTime rejection, got {:?}", other),
+    }
+
+    let metrics = tracker.metrics("CLIENT1").unwrap();
+    assert_eq!((metrics.samples, metrics.violations), (2, 1));
+
+    assert!(matches!(
+        tracker.check(&header("CLIENT1", "not-a-time")),
+        Err(FixError::Validation(_))
+    ));
+}
+
+#[test]
+fn test_accept_with_warning_policy_per_session() {
+    let (_clock, tracker) = tracker_at(NOON_2024_01_01);
+    tracker.set_session_config(
+        "LENIENT",
+        SkewConfig {
+            max_skew_ns: SECOND,
+            policy: SkewPolicy::AcceptWithWarning,
+            ..Default::default()
+        },
+    );
+
+    assert!(tracker.check(&header("LENIENT", "20240101-11:59:50")).is_ok());
+    assert_eq!(tracker.metrics("LENIENT").unwrap().violations, 1);
+
+    assert!(tracker.check(&header("STRICT", "20240101-11:59:50")).is_ok());
+    assert_eq!(tracker.metrics("STRICT").unwrap().violations, 0);
+    assert_eq!(tracker.metrics("UNKNOWN"), None);
+}
+
+#[test]
+fn test_skew_ewma() {
+    let clock = Arc::new(ManualClock::new(NOON_2024_01_01));
+    let config = SkewConfig {
+        ewma_alpha: 0.5,
+        ..Default::default()
+    };
+    let tracker = SkewTracker::with_default_config(clock.clone(), config);
+
+    for (receive_offset, expected_ewma) in [(-4, 4.0), (0, 2.0), (2, 0.0), (-6, 3.0)] {
+        clock.set(NOON_2024_01_01 + receive_offset * SECOND);
+        tracker.check(&header("CLIENT1", "20240101-12:00:00")).unwrap();
+        assert_eq!(tracker.metrics("CLIENT1").unwrap().ewma_ns, expected_ewma * SECOND as f64);
+    }
+
+    let metrics = tracker.metrics("CLIENT1").unwrap();
+    assert_eq!((metrics.last_skew_ns, metrics.samples), (6 * SECOND, 4));
+}
+
+#[test]
+fn test_gateway_shares_engine_clock() {
+    let clock = Arc::new(ManualClock::new(NOON_2024_01_01));
+    let engine = MatchingEngine::with_clock(MatchingEngineConfig::default(), clock.clone());
+    assert_eq!(engine.clock().now_nanos(), NOON_2024_01_01);
+
+    let gateway = FixGateway::new(Arc::new(Mutex::new(engine)));
+    let sending_time = "20240102-12:00:00";
+
+    assert!(gateway.skew_tracker().check(&header("CLIENT1", sending_time)).is_err());
+    clock.advance(86_400 * SECOND);
+    assert!(gateway.skew_tracker().check(&header("CLIENT1", sending_time)).is_ok());
+}
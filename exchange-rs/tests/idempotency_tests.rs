@@ -0,0 +1,97 @@
+use exchange_rs::{
+    idempotency::{fingerprint, IdempotencyCache, IdempotencyCheck, IdempotencyError},
+    matching_engine::MatchingEngine,
+    order::{Order, OrderType, Side},
+};
+use exchange_rs::Price;
+
+const TTL: i64 = 1_000;
+
+#[derive(Hash)]
+struct PlaceRequest {
+    side: u8,
+    price: Price,
+    quantity: u32,
+}
+
+fn submit(
+    engine: &mut MatchingEngine,
+    cache: &mut IdempotencyCache<u64>,
+    key: &str,
+    request: &PlaceRequest,
+    now: i64,
+) -> Result<u64, IdempotencyError> {
+    match cache.begin(key, fingerprint(request), now)? {
+        IdempotencyCheck::Cached(order_id) => Ok(order_id),
+        IdempotencyCheck::Proceed => {
+            let side = if request.side == 0 { Side::Buy } else { Side::Sell };
+            let order = Order::new("AAPL".to_string(), side, OrderType::Limit, request.price, request.quantity, 1);
+            let result = engine.place_order(order).unwrap();
+            let order_id = result.remaining_order.unwrap().read().id;
+            cache.complete(key, order_id);
+            Ok(order_id)
+        }
+    }
+}
+
+#[test]
+fn test_duplicate_key_places_one_order() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    let mut cache = IdempotencyCache::new(16, TTL);
+    let request = PlaceRequest { side: 0, price: 100, quantity: 10 };
+
+    let first = submit(&mut engine, &mut cache, "k1", &request, 0).unwrap();
+    let retry = submit(&mut engine, &mut cache, "k1", &request, 10).unwrap();
+    assert_eq!(first, retry);
+
+    let level = &engine.order_books.get("AAPL").unwrap().buy_levels[&100];
+    assert_eq!(level.orders.len(), 1);
+}
+
+#[test]
+fn test_conflicting_payload_and_in_flight_retry() {
+    let mut cache: IdempotencyCache<u64> = IdempotencyCache::new(16, TTL);
+    let request = PlaceRequest { side: 0, price: 100, quantity: 10 };
+    let altered = PlaceRequest { side: 0, price: 101, quantity: 10 };
+
+    assert_eq!(cache.begin("k1", fingerprint(&request), 0), Ok(IdempotencyCheck::Proceed));
+    assert_eq!(
+        cache.begin("k1", fingerprint(&request), 1),
+        Err(IdempotencyError::InFlight { key: "k1".to_string() })
+    );
+
+    cache.complete("k1", 7);
+    assert_eq!(
+        cache.begin("k1", fingerprint(&altered), 2),
+        Err(IdempotencyError::Conflict { key: "k1".to_string() })
+    );
+}
+
+#[test]
+fn test_expiry_allows_key_reuse() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    let mut cache = IdempotencyCache::new(16, TTL);
+    let request = PlaceRequest { side: 1, price: 100, quantity: 10 };
+
+    let first = submit(&mut engine, &mut cache, "k1", &request, 0).unwrap();
+    let second = submit(&mut engine, &mut cache, "k1", &request, TTL).unwrap();
+    assert_ne!(first, second);
+}
+
+#[test]
+fn test_least_recently_used_key_is_evicted() {
+    let mut cache: IdempotencyCache<u64> = IdempotencyCache::new(2, TTL);
+
+    for (key, response) in [("a", 1), ("b", 2)] {
+        cache.begin(key, 0, 0).unwrap();
+        cache.complete(key, response);
+    }
+    assert_eq!(cache.begin("a", 0, 1), Ok(IdempotencyCheck::Cached(1)));
+
+    cache.begin("c", 0, 2).unwrap();
+    assert_eq!(cache.len(), 2);
+    assert_eq!(cache.begin("a", 0, 3), Ok(IdempotencyCheck::Cached(1)));
+    assert_eq!(cache.begin("b", 0, 4), Ok(IdempotencyCheck::Proceed));
+}
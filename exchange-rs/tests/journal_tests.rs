@@ -0,0 +1,429 @@
+use std::fs;
+use std::path::PathBuf;
+
+use exchange_rs::{
+    journal::{
+        read_journal_file, recover, CommandOutcome, EngineJournal, JournalCommand, JournalError,
+        JournalFile, SyncPolicy,
+    },
+    matching_engine::{MatchingEngine, MatchingEngineConfig, MatchingError},
+    order::{Order, OrderType, Side},
+};
+use exchange_rs::Price;
+
+mod test_utils;
+use test_utils::{random_order, XorShift};
+
+const SYMBOLS: [&str; 2] = ["AAPL", "MSFT"];
+
+fn run_workload(seed: u64, commands: usize) -> MatchingEngine {
+    let mut engine = MatchingEngine::new();
+    engine.enable_journal();
+    for symbol in SYMBOLS {
+        engine.add_symbol(symbol);
+    }
+    continue_workload(&mut engine, seed, commands);
+    engine
+}
+
+fn continue_workload(engine: &mut MatchingEngine, seed: u64, commands: usize) {
+    let mut rng = XorShift(seed);
+    for _ in 0..commands {
+        let symbol = SYMBOLS[rng.below(2) as usize];
+        let last_id = engine
+            .journal()
+            .unwrap()
+            .entries()
+            .iter()
+            .filter_map(|e| e.order_id)
+            .max();
+        let known_id = 1 + rng.below(last_id.unwrap_or(0) + 1);
+        match rng.below(20) {
+            0..=12 => {
+                let _ = engine.place_order(random_order(&mut rng, &SYMBOLS));
+            }
+            13..=15 => {
+                engine.cancel_order(symbol, known_id);
+            }
+            16..=18 => {
+                let _ = engine.replace_order(
+                    symbol,
+                    known_id,
+                    95 + rng.below(11) as Price,
+                    1 + rng.below(20) as u32,
+                );
+            }
+            _ => {
+                engine.cancel_all_for_user(1 + rng.below(5));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_replay_reproduces_random_workload() {
+    for seed in [0x9E37_79B9_7F4A_7C15, 42, 7_777_777] {
+        let engine = run_workload(seed, 500);
+        let journal = engine.journal().unwrap();
+
+        let json = serde_json::to_string(journal).unwrap();
+        let journal: EngineJournal = serde_json::from_str(&json).unwrap();
+        let replayed = MatchingEngine::replay(&journal).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(replayed.create_snapshot()).unwrap(),
+            serde_json::to_value(engine.create_snapshot()).unwrap(),
+            "seed {seed}"
+        );
+        assert_eq!(
+            replayed.journal().unwrap().last_sequence(),
+            journal.last_sequence()
+        );
+    }
+}
+
+#[test]
+fn test_journal_records_assigned_ids_and_sequence() {
+    let mut engine = MatchingEngine::new();
+    engine.enable_journal();
+    engine.add_symbol("AAPL");
+
+    engine
+        .place_order(Order::new(
+            "AAPL".to_string(),
+            Side::Sell,
+            OrderType::Limit,
+            100,
+            10,
+            1,
+        ))
+        .unwrap();
+    engine
+        .place_order(Order::new(
+            "AAPL".to_string(),
+            Side::Buy,
+            OrderType::Limit,
+            90,
+            10,
+            2,
+        ))
+        .unwrap();
+    engine.replace_order("AAPL", 2, 100, 4).unwrap();
+    assert_eq!(
+        engine.replace_order("AAPL", 2, 100, 4).unwrap_err(),
+        MatchingError::OrderNotFound
+    );
+    engine.cancel_order("AAPL", 1);
+
+    let journal = engine.take_journal().unwrap();
+    let entries: Vec<(u64, Option<u64>)> = journal
+        .entries()
+        .iter()
+        .map(|entry| (entry.sequence, entry.order_id))
+        .collect();
+    assert_eq!(
+        entries,
+        vec![
+            (1, None),
+            (2, Some(1)),
+            (3, Some(2)),
+            (4, Some(3)),
+            (5, None),
+            (6, None)
+        ]
+    );
+    assert!(matches!(
+        journal.entries()[3].command,
+        JournalCommand::ReplaceOrder { .. }
+    ));
+    assert!(journal
+        .entries()
+        .windows(2)
+        .all(|pair| pair[0].timestamp <= pair[1].timestamp));
+    assert!(engine.journal().is_none());
+}
+
+#[test]
+fn test_replay_detects_divergence() {
+    let mut engine = MatchingEngine::new();
+    engine.enable_journal();
+    engine.add_symbol("AAPL");
+    engine
+        .place_order(Order::new(
+            "AAPL".to_string(),
+            Side::Buy,
+            OrderType::Limit,
+            100,
+            10,
+            1,
+        ))
+        .unwrap();
+
+    // Drop the AddSymbol entry so the order is rejected on replay.
+    let mut value = serde_json::to_value(engine.journal().unwrap()).unwrap();
+    value["entries"].as_array_mut().unwrap().remove(0);
+    let journal: EngineJournal = serde_json::from_value(value).unwrap();
+
+    assert_eq!(
+        MatchingEngine::replay(&journal).err(),
+        Some(JournalError::Divergence { sequence: 2 })
+    );
+}
+
+#[test]
+fn test_replay_detects_changed_outcome() {
+    let mut engine = MatchingEngine::new();
+    engine.enable_journal();
+    engine.add_symbol("AAPL");
+    let rejected = engine.set_price_bands("MSFT", Default::default());
+    assert_eq!(rejected, Err(MatchingError::SymbolNotFound));
+    engine.add_symbol("MSFT");
+
+    let entries = engine.journal().unwrap().entries();
+    assert_eq!(entries[0].outcome, Some(CommandOutcome::Accepted));
+    assert_eq!(
+        entries[1].outcome,
+        Some(CommandOutcome::Rejected(
+            MatchingError::SymbolNotFound.to_string()
+        ))
+    );
+    assert!(MatchingEngine::replay(engine.journal().unwrap()).is_ok());
+
+    // Claim the rejected command was accepted; replay rejects it again.
+    let mut value = serde_json::to_value(engine.journal().unwrap()).unwrap();
+    value["entries"][1]["outcome"] = serde_json::json!("Accepted");
+    let journal: EngineJournal = serde_json::from_value(value).unwrap();
+    assert_eq!(
+        MatchingEngine::replay(&journal).err(),
+        Some(JournalError::Divergence { sequence: 2 })
+    );
+
+    // Entries journaled before outcomes were recorded are not checked.
+    let mut value = serde_json::to_value(engine.journal().unwrap()).unwrap();
+    value["entries"][1]
+        .as_object_mut()
+        .unwrap()
+        .remove("outcome");
+    let journal: EngineJournal = serde_json::from_value(value).unwrap();
+    assert!(MatchingEngine::replay(&journal).is_ok());
+}
+
+fn journal_paths(name: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir();
+    let snapshot = dir.join(format!("journal_{}_{}.snapshot", name, std::process::id()));
+    let journal = dir.join(format!("journal_{}_{}.log", name, std::process::id()));
+    let _ = fs::remove_file(&snapshot);
+    let _ = fs::remove_file(&journal);
+    (snapshot, journal)
+}
+
+fn engine_writing_to(journal_path: &PathBuf, sync: SyncPolicy) -> MatchingEngine {
+    let mut engine = MatchingEngine::new();
+    engine
+        .attach_journal_file(JournalFile::open(journal_path, sync).unwrap())
+        .unwrap();
+    for symbol in SYMBOLS {
+        engine.add_symbol(symbol);
+    }
+    engine
+}
+
+#[test]
+fn test_recover_replays_the_journal_file() {
+    let (snapshot_path, journal_path) = journal_paths("full");
+    let mut engine = engine_writing_to(&journal_path, SyncPolicy::EveryRecords(16));
+    continue_workload(&mut engine, 0x2545_F491_4F6C_DD1D, 300);
+
+    let written = read_journal_file(&journal_path).unwrap();
+    assert_eq!(
+        serde_json::to_value(written.entries()).unwrap(),
+        serde_json::to_value(engine.journal().unwrap().entries()).unwrap()
+    );
+
+    let recovered = recover(
+        MatchingEngineConfig::default(),
+        &snapshot_path,
+        &journal_path,
+        SyncPolicy::Never,
+    )
+    .unwrap();
+    assert_eq!(recovered.state_digest(), engine.state_digest());
+    assert_eq!(
+        recovered.journal().unwrap().last_sequence(),
+        engine.journal().unwrap().last_sequence()
+    );
+    let _ = fs::remove_file(&journal_path);
+}
+
+#[test]
+fn test_writer_killed_mid_record_recovers_the_last_whole_command() {
+    let (snapshot_path, journal_path) = journal_paths("torn");
+    let mut engine = engine_writing_to(&journal_path, SyncPolicy::EveryRecord);
+    let mut rng = XorShift(99);
+    // File length and engine state after each command.
+    let mut checkpoints = Vec::new();
+    for _ in 0..40 {
+        let _ = engine.place_order(random_order(&mut rng, &SYMBOLS));
+        checkpoints.push((
+            fs::metadata(&journal_path).unwrap().len(),
+            engine.state_digest(),
+        ));
+    }
+    let bytes = fs::read(&journal_path).unwrap();
+    let (whole, expected) = checkpoints[checkpoints.len() - 2].clone();
+
+    // Every cut through the last record, header included.
+    for cut in whole as usize + 1..bytes.len() {
+        fs::write(&journal_path, &bytes[..cut]).unwrap();
+        let recovered = recover(
+            MatchingEngineConfig::default(),
+            &snapshot_path,
+            &journal_path,
+            SyncPolicy::Never,
+        )
+        .unwrap();
+        assert_eq!(recovered.state_digest(), expected, "cut at {cut}");
+        assert_eq!(fs::metadata(&journal_path).unwrap().len(), whole);
+    }
+    let _ = fs::remove_file(&journal_path);
+}
+
+#[test]
+fn test_corrupt_record_cuts_off_the_rest_of_the_file() {
+    let (snapshot_path, journal_path) = journal_paths("corrupt");
+    let mut engine = engine_writing_to(&journal_path, SyncPolicy::Never);
+    let mut rng = XorShift(7);
+    let mut checkpoints = Vec::new();
+    for _ in 0..20 {
+        let _ = engine.place_order(random_order(&mut rng, &SYMBOLS));
+        checkpoints.push((
+            fs::metadata(&journal_path).unwrap().len(),
+            engine.state_digest(),
+        ));
+    }
+
+    // Flip a payload byte of the eleventh command's record.
+    let (start, expected) = checkpoints[9].clone();
+    let mut bytes = fs::read(&journal_path).unwrap();
+    bytes[start as usize + 12] ^= 0x20;
+    fs::write(&journal_path, &bytes).unwrap();
+
+    let journal = read_journal_file(&journal_path).unwrap();
+    assert_eq!(
+        journal.last_sequence(),
+        engine.journal().unwrap().last_sequence() - 10
+    );
+    let recovered = recover(
+        MatchingEngineConfig::default(),
+        &snapshot_path,
+        &journal_path,
+        SyncPolicy::Never,
+    )
+    .unwrap();
+    assert_eq!(recovered.state_digest(), expected);
+    assert_eq!(fs::metadata(&journal_path).unwrap().len(), start);
+    let _ = fs::remove_file(&journal_path);
+}
+
+#[test]
+fn test_recover_replays_the_tail_after_a_snapshot() {
+    let (snapshot_path, journal_path) = journal_paths("snapshot");
+    let mut engine = engine_writing_to(&journal_path, SyncPolicy::EveryRecord);
+    continue_workload(&mut engine, 42, 200);
+    let snapshot = engine.compact_journal();
+    fs::write(&snapshot_path, serde_json::to_string(&snapshot).unwrap()).unwrap();
+    continue_workload(&mut engine, 43, 200);
+
+    let recovered = recover(
+        MatchingEngineConfig::default(),
+        &snapshot_path,
+        &journal_path,
+        SyncPolicy::EveryRecord,
+    )
+    .unwrap();
+    assert_eq!(recovered.state_digest(), engine.state_digest());
+    assert_eq!(
+        recovered.journal().unwrap().compacted_through(),
+        snapshot.journal_sequence()
+    );
+    let _ = fs::remove_file(&snapshot_path);
+    let _ = fs::remove_file(&journal_path);
+}
+
+#[test]
+fn test_recovered_engine_keeps_appending_to_the_file() {
+    let (snapshot_path, journal_path) = journal_paths("continue");
+    let mut engine = engine_writing_to(&journal_path, SyncPolicy::EveryRecord);
+    continue_workload(&mut engine, 5, 100);
+    drop(engine);
+
+    let mut engine = recover(
+        MatchingEngineConfig::default(),
+        &snapshot_path,
+        &journal_path,
+        SyncPolicy::EveryRecord,
+    )
+    .unwrap();
+    continue_workload(&mut engine, 6, 100);
+
+    let recovered = recover(
+        MatchingEngineConfig::default(),
+        &snapshot_path,
+        &journal_path,
+        SyncPolicy::EveryRecord,
+    )
+    .unwrap();
+    assert_eq!(recovered.state_digest(), engine.state_digest());
+
+    // A file that has moved past the engine's journal cannot be attached.
+    let mut fresh = MatchingEngine::new();
+    assert!(matches!(
+        fresh.attach_journal_file(JournalFile::open(&journal_path, SyncPolicy::Never).unwrap()),
+        Err(JournalError::FileMismatch {
+            compacted_through: 0,
+            last_sequence: 0,
+            ..
+        })
+    ));
+    let _ = fs::remove_file(&journal_path);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_failed_append_refuses_commands_until_a_file_catches_up() {
+    let (snapshot_path, journal_path) = journal_paths("failed");
+    let mut engine = MatchingEngine::new();
+    // Writes to /dev/null go through, but syncing it fails.
+    engine
+        .attach_journal_file(JournalFile::open("/dev/null", SyncPolicy::EveryRecord).unwrap())
+        .unwrap();
+    // The command whose append fails has run; the ones after it do not.
+    engine.add_symbol("AAPL");
+    assert!(engine.order_books.contains_key("AAPL"));
+    let error = engine.journal_file_error().unwrap().to_string();
+    let sell = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100, 5, 1);
+    assert_eq!(
+        engine.place_order(sell.clone()).unwrap_err(),
+        MatchingError::JournalUnavailable(error)
+    );
+    assert!(engine.cancel_order("AAPL", 1).is_none());
+    assert_eq!(engine.journal().unwrap().last_sequence(), 1);
+    assert_eq!(engine.order_books["AAPL"].get_best_ask_price(), None);
+
+    // A working file takes the entries it missed and commands run again.
+    engine
+        .attach_journal_file(JournalFile::open(&journal_path, SyncPolicy::EveryRecord).unwrap())
+        .unwrap();
+    assert_eq!(engine.journal_file_error(), None);
+    engine.place_order(sell).unwrap();
+    assert_eq!(read_journal_file(&journal_path).unwrap().last_sequence(), 2);
+    let recovered = recover(
+        MatchingEngineConfig::default(),
+        &snapshot_path,
+        &journal_path,
+        SyncPolicy::Never,
+    )
+    .unwrap();
+    assert_eq!(recovered.state_digest(), engine.state_digest());
+    let _ = fs::remove_file(&journal_path);
+}
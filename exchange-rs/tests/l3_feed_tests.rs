@@ -0,0 +1,457 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use exchange_rs::{
+    clock::ManualClock,
+    fix::{
+        encoder, market_data::l3_incremental_refresh, messages::FixMessage, parser::FixParser,
+        session::FixSessionState,
+    },
+    l3_feed::{L3Batch, L3Book, L3BookOrder, L3Error, L3Event},
+    matching_engine::{MatchingEngine, MatchingEngineConfig},
+    order::{Order, OrderType, Side},
+    orderbook::{OrderBook, TradingState},
+    price_utils::scaled_to_price,
+    sbe::{
+        parser::{SbeMessage, SbeMessageParser},
+        publisher::{l3_book_message, SbePublisher},
+        BookChange,
+    },
+    snapshot::PriceLevelSnapshot,
+};
+use exchange_rs::Price;
+
+mod test_utils;
+use test_utils::{random_order, XorShift};
+
+fn engine() -> MatchingEngine {
+    let mut engine = MatchingEngine::with_config(MatchingEngineConfig {
+        bustable_trades: 100,
+        ..MatchingEngineConfig::default()
+    });
+    engine.add_symbol("AAPL");
+    engine
+}
+
+/// Price, queued order ids with open quantity, and total volume of each
+/// level in ascending price order.
+type Levels = Vec<(Price, Vec<(u64, u32)>, u64)>;
+
+/// The snapshot's levels reduced to what the L3 stream carries.
+fn expected_levels(levels: &std::collections::HashMap<Price, PriceLevelSnapshot>) -> Levels {
+    let mut expected: Vec<_> = levels
+        .values()
+        .map(|level| {
+            let orders = level
+                .orders
+                .iter()
+                .map(|o| (o.id, o.quantity - o.filled_quantity))
+                .collect();
+            (level.price, orders, level.total_volume)
+        })
+        .collect();
+    expected.sort_by_key(|&(price, _, _)| price);
+    expected
+}
+
+fn rebuilt_levels(levels: &std::collections::BTreeMap<Price, Vec<L3BookOrder>>) -> Levels {
+    levels
+        .iter()
+        .map(|(&price, orders)| {
+            let queue = orders.iter().map(|o| (o.order_id, o.quantity)).collect();
+            let total = orders.iter().map(|o| o.quantity as u64).sum();
+            (price, queue, total)
+        })
+        .collect()
+}
+
+fn assert_matches_book(rebuilt: &L3Book, book: &OrderBook, context: &str) {
+    let snapshot = book.create_snapshot();
+    assert_eq!(
+        rebuilt_levels(&rebuilt.bids),
+        expected_levels(&snapshot.buy_levels),
+        "bids diverged {context}"
+    );
+    assert_eq!(
+        rebuilt_levels(&rebuilt.asks),
+        expected_levels(&snapshot.sell_levels),
+        "asks diverged {context}"
+    );
+
+    let depth = book.get_market_depth();
+    let derived = rebuilt.depth(10);
+    assert_eq!(derived.bid_levels, depth.bid_levels, "bid depth {context}");
+    assert_eq!(derived.ask_levels, depth.ask_levels, "ask depth {context}");
+    assert_eq!(rebuilt.last_sequence(), book.l3_sequence());
+}
+
+#[test]
+fn test_l3_stream_rebuilds_book() {
+    for seed in 1..=10u64 {
+        let mut rng = XorShift(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        let mut engine = engine();
+        let events = engine.subscribe_l3("AAPL", 100_000).unwrap();
+        let mut rebuilt = L3Book::new();
+        let mut trade_ids = Vec::new();
+        let mut next_id = 1;
+
+        for step in 0..300 {
+            let target = 1 + rng.below(next_id);
+            match rng.below(20) {
+                0..=11 => {
+                    if let Ok(result) = engine.place_order(random_order(&mut rng, &["AAPL"])) {
+                        trade_ids.extend(result.trades.iter().map(|t| t.id));
+                    }
+                    next_id += 1;
+                }
+                12..=14 => {
+                    engine.cancel_order("AAPL", target);
+                }
+                15..=16 => {
+                    let price = 95 + rng.below(11) as Price;
+                    let quantity = 1 + rng.below(30) as u32;
+                    if let Ok(result) = engine.replace_order("AAPL", target, price, quantity) {
+                        trade_ids.extend(result.trades.iter().map(|t| t.id));
+                        next_id += 1;
+                    }
+                }
+                17 => {
+                    if !trade_ids.is_empty() {
+                        let index = rng.below(trade_ids.len() as u64) as usize;
+                        let _ = engine.bust_trade("AAPL", trade_ids.swap_remove(index));
+                    }
+                }
+                18 => {
+                    engine
+                        .set_trading_state("AAPL", TradingState::Auction)
+                        .unwrap();
+                }
+                _ => {
+                    if let Ok(result) = engine.uncross("AAPL") {
+                        trade_ids.extend(result.trades.iter().map(|t| t.id));
+                    }
+                }
+            }
+
+            for event in events.try_iter() {
+                rebuilt.apply(&event).unwrap();
+            }
+            let context = format!("at seed {seed} step {step}");
+            assert_matches_book(&rebuilt, &engine.order_books["AAPL"], &context);
+        }
+    }
+}
+
+#[test]
+fn test_l3_events_describe_each_change() {
+    let mut engine = engine();
+    let events = engine.subscribe_l3("AAPL", 16).unwrap();
+
+    let mut iceberg = Order::new(
+        "AAPL".to_string(),
+        Side::Sell,
+        OrderType::Iceberg,
+        101,
+        10,
+        1,
+    );
+    iceberg.display_quantity = Some(4);
+    engine.place_order(iceberg).unwrap();
+    engine
+        .place_order(Order::new(
+            "AAPL".to_string(),
+            Side::Sell,
+            OrderType::Limit,
+            101,
+            5,
+            2,
+        ))
+        .unwrap();
+    engine
+        .place_order(Order::new(
+            "AAPL".to_string(),
+            Side::Buy,
+            OrderType::Limit,
+            101,
+            4,
+            3,
+        ))
+        .unwrap();
+    engine.cancel_order("AAPL", 2).unwrap();
+
+    let received: Vec<L3Event> = events.try_iter().collect();
+    assert_eq!(
+        received,
+        vec![
+            L3Event::Add {
+                sequence: 1,
+                order_id: 1,
+                side: Side::Sell,
+                price: 101,
+                quantity: 10,
+                visible_quantity: 4,
+            },
+            L3Event::Add {
+                sequence: 2,
+                order_id: 2,
+                side: Side::Sell,
+                price: 101,
+                quantity: 5,
+                visible_quantity: 5,
+            },
+            L3Event::Execute {
+                sequence: 3,
+                order_id: 1,
+                side: Side::Sell,
+                price: 101,
+                quantity: 4,
+                visible_quantity: 4,
+            },
+            L3Event::Replace {
+                sequence: 4,
+                order_id: 1,
+                side: Side::Sell,
+                price: 101,
+                quantity: 6,
+                visible_quantity: 4,
+                keeps_priority: false,
+            },
+            L3Event::Cancel {
+                sequence: 5,
+                order_id: 2,
+                side: Side::Sell,
+                price: 101,
+                quantity: 5,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_full_buffer_shows_as_sequence_gap() {
+    let mut engine = engine();
+    let events = engine.subscribe_l3("AAPL", 1).unwrap();
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&seen);
+    engine
+        .order_books
+        .get_mut("AAPL")
+        .unwrap()
+        .on_l3_event(Arc::new(move |event| {
+            sink.lock().unwrap().push(event.sequence())
+        }));
+
+    for price in [99, 98] {
+        engine
+            .place_order(Order::new(
+                "AAPL".to_string(),
+                Side::Buy,
+                OrderType::Limit,
+                price,
+                1,
+                1,
+            ))
+            .unwrap();
+    }
+    let mut rebuilt = L3Book::new();
+    rebuilt.apply(&events.recv().unwrap()).unwrap();
+
+    engine
+        .place_order(Order::new(
+            "AAPL".to_string(),
+            Side::Buy,
+            OrderType::Limit,
+            97,
+            1,
+            1,
+        ))
+        .unwrap();
+    assert_eq!(
+        rebuilt.apply(&events.recv().unwrap()),
+        Err(L3Error::SequenceGap {
+            expected: 2,
+            received: 3
+        })
+    );
+    assert_eq!(*seen.lock().unwrap(), vec![1, 2, 3]);
+}
+
+#[test]
+fn test_books_with_l3_listeners_stay_resident() {
+    let mut engine = MatchingEngine::with_config(MatchingEngineConfig {
+        hibernate_after_ns: Some(0),
+        ..MatchingEngineConfig::default()
+    });
+    engine.add_symbol("AAPL");
+    engine.add_symbol("MSFT");
+    let _events = engine.subscribe_l3("AAPL", 8).unwrap();
+
+    assert_eq!(engine.hibernate_idle_symbols(), vec!["MSFT".to_string()]);
+    assert!(engine.subscribe_l3("MSFT", 8).is_ok());
+    assert!(!engine.is_hibernated("MSFT"));
+    assert!(engine.subscribe_l3("IBM", 8).is_err());
+}
+
+fn place(engine: &mut MatchingEngine, side: Side, price: Price, quantity: u32) {
+    let order = Order::new(
+        "AAPL".to_string(),
+        side,
+        OrderType::Limit,
+        price,
+        quantity,
+        1,
+    );
+    engine.place_order(order).unwrap();
+}
+
+/// One aggressive order sweeping 300 resting orders reaches batch
+/// subscribers as a single batch, which goes out as one FIX incremental
+/// refresh, one SBE Book message and one JSON frame, each of which
+/// accounts for every change in order.
+#[test]
+fn test_sweep_is_published_as_one_batch() {
+    const BASE: Price = 100_000_000;
+    let mut engine = engine();
+    let events = engine.subscribe_l3("AAPL", 1_000).unwrap();
+    let batches = engine
+        .subscribe_l3_batches("AAPL", 1_000, Duration::ZERO)
+        .unwrap();
+
+    let mut resting = 0;
+    for i in 0..300 {
+        let quantity = 1 + i % 3;
+        place(
+            &mut engine,
+            Side::Sell,
+            BASE + (i as Price % 10) * 10_000,
+            quantity,
+        );
+        resting += quantity;
+    }
+    let mut book = L3Book::new();
+    assert_eq!(batches.len(), 300);
+    for batch in batches.try_iter() {
+        book.apply_batch(&batch).unwrap();
+    }
+    assert_eq!(events.try_iter().count(), 300);
+
+    place(&mut engine, Side::Buy, BASE + 90_000, resting);
+    let sweep: Vec<L3Batch> = batches.try_iter().collect();
+    assert_eq!(sweep.len(), 1);
+    let batch = &sweep[0];
+    assert_eq!(batch.events, events.try_iter().collect::<Vec<_>>());
+    assert_eq!((batch.first_sequence(), batch.last_sequence()), (301, 600));
+
+    let frame = serde_json::to_string(batch).unwrap();
+    assert_eq!(&serde_json::from_str::<L3Batch>(&frame).unwrap(), batch);
+
+    let refresh = l3_incremental_refresh(
+        &FixSessionState::new("EXCHANGE".to_string(), "CLIENT".to_string()),
+        "AAPL",
+        Some("L3"),
+        batch,
+    );
+    let entries = match FixParser::new().parse(&encoder::encode(&refresh)).unwrap() {
+        FixMessage::MarketDataIncrementalRefresh(refresh) => refresh.entries,
+        other => panic!("expected an incremental refresh, got {:?}", other),
+    };
+    assert_eq!(entries.len(), batch.events.len());
+    for (entry, event) in entries.iter().zip(&batch.events) {
+        let L3Event::Execute {
+            order_id,
+            price,
+            quantity,
+            ..
+        } = *event
+        else {
+            panic!("expected an execution, got {:?}", event);
+        };
+        assert_eq!(entry.md_entry_type, '2');
+        assert_eq!(entry.md_entry_id, Some(order_id.to_string()));
+        assert_eq!(entry.rpt_seq, Some(event.sequence()));
+        assert_eq!(
+            (entry.md_entry_px, entry.md_entry_size),
+            (scaled_to_price(price), quantity as u64)
+        );
+    }
+
+    let changes = book.apply_batch(batch).unwrap();
+    assert_eq!(changes.len(), 10);
+    assert_eq!(
+        book.depth(usize::MAX),
+        engine.market_depth("AAPL", usize::MAX).unwrap()
+    );
+    let mut publisher = SbePublisher::default();
+    publisher
+        .publish_book(&l3_book_message(7, 0, batch, &changes))
+        .unwrap();
+    let datagrams = publisher.take_datagrams();
+    assert_eq!(datagrams.len(), 1);
+    let messages = SbeMessageParser::new()
+        .parse_datagram(&datagrams[0])
+        .unwrap();
+    let [SbeMessage::Book(message)] = messages.as_slice() else {
+        panic!("expected one Book message, got {:?}", messages);
+    };
+    assert_eq!(
+        (message.prev_change_id, message.change_id, message.is_last),
+        (300, 600, true)
+    );
+    assert_eq!(message.changes.len(), 10);
+    assert!(message
+        .changes
+        .iter()
+        .all(|change| change.change == BookChange::deleted as u8 && change.amount == 0.0));
+
+    let metrics = engine.l3_batch_metrics("AAPL").unwrap();
+    assert_eq!(
+        (metrics.batches, metrics.events, metrics.largest_batch),
+        (301, 600, 300)
+    );
+}
+
+#[test]
+fn test_batch_window_spans_commands() {
+    let clock = Arc::new(ManualClock::new(0));
+    let mut engine = MatchingEngine::with_clock(MatchingEngineConfig::default(), clock.clone());
+    engine.add_symbol("AAPL");
+    let batches = engine
+        .subscribe_l3_batches("AAPL", 16, Duration::from_micros(100))
+        .unwrap();
+
+    for price in [99, 98, 97] {
+        place(&mut engine, Side::Buy, price, 5);
+        clock.advance(30_000);
+    }
+    engine.flush_l3_batches();
+    assert!(batches.try_recv().is_err());
+
+    clock.set(100_000);
+    engine.flush_l3_batches();
+    let batch = batches.try_recv().unwrap();
+    assert_eq!((batch.first_sequence(), batch.last_sequence()), (1, 3));
+
+    // A command ending past the window publishes without a flush.
+    place(&mut engine, Side::Sell, 101, 5);
+    assert!(batches.try_recv().is_err());
+    clock.set(250_000);
+    place(&mut engine, Side::Sell, 99, 2);
+    let batch = batches.try_recv().unwrap();
+    assert_eq!(batch.events.len(), 2);
+    assert!(matches!(batch.events[0], L3Event::Add { order_id: 4, .. }));
+    assert!(matches!(
+        batch.events[1],
+        L3Event::Execute {
+            order_id: 1,
+            quantity: 2,
+            ..
+        }
+    ));
+
+    let metrics = engine.l3_batch_metrics("AAPL").unwrap();
+    assert_eq!(
+        (metrics.batches, metrics.events, metrics.largest_batch),
+        (2, 5, 3)
+    );
+    assert_eq!((metrics.hold.count, metrics.hold.max), (2, 150_000));
+}
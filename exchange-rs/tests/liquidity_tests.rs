@@ -0,0 +1,187 @@
+use exchange_rs::matching_engine::MatchingEngine;
+use exchange_rs::order::{Order, OrderType, Side};
+use exchange_rs::orderbook::VolumeView;
+use exchange_rs::Price;
+
+const P98: Price = 98_000_000;
+const P99: Price = 99_000_000;
+const P100: Price = 100_000_000;
+const P101: Price = 101_000_000;
+const P102: Price = 102_000_000;
+
+fn place(engine: &mut MatchingEngine, side: Side, price: Price, quantity: u32, user_id: u64) {
+    let order = Order::new(
+        "AAPL".to_string(),
+        side,
+        OrderType::Limit,
+        price,
+        quantity,
+        user_id,
+    );
+    engine.place_order(order).unwrap();
+}
+
+/// Asks: 100 x 10, an iceberg at 101 with 23 left showing 3, and 102
+/// with 12 of 20 left. Bids: 99 x 4 and 98 x 6.
+fn fixture_engine() -> MatchingEngine {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+
+    place(&mut engine, Side::Sell, P102, 20, 1);
+    place(&mut engine, Side::Buy, P102, 8, 2);
+
+    let mut iceberg = Order::new(
+        "AAPL".to_string(),
+        Side::Sell,
+        OrderType::Iceberg,
+        P101,
+        30,
+        1,
+    );
+    iceberg.display_quantity = Some(5);
+    engine.place_order(iceberg).unwrap();
+    place(&mut engine, Side::Buy, P101, 7, 2);
+
+    place(&mut engine, Side::Sell, P100, 10, 1);
+    place(&mut engine, Side::Buy, P99, 4, 2);
+    place(&mut engine, Side::Buy, P98, 6, 2);
+    engine
+}
+
+#[test]
+fn test_cumulative_depth() {
+    let engine = fixture_engine();
+    let book = &engine.order_books["AAPL"];
+
+    let asks: Vec<_> = book
+        .cumulative_depth(Side::Sell, 10, VolumeView::Visible)
+        .collect();
+    assert_eq!(asks, [(P100, 10), (P101, 13), (P102, 25)]);
+
+    let asks: Vec<_> = book
+        .cumulative_depth(Side::Sell, 2, VolumeView::Total)
+        .collect();
+    assert_eq!(asks, [(P100, 10), (P101, 33)]);
+
+    let bids: Vec<_> = book
+        .cumulative_depth(Side::Buy, 10, VolumeView::Visible)
+        .collect();
+    assert_eq!(bids, [(P99, 4), (P98, 10)]);
+
+    assert_eq!(
+        book.cumulative_depth(Side::Sell, 0, VolumeView::Visible)
+            .count(),
+        0
+    );
+}
+
+#[test]
+fn test_vwap_for_quantity() {
+    let engine = fixture_engine();
+    let book = &engine.order_books["AAPL"];
+
+    // 10 @ 100, 3 @ 101 showing, 7 @ 102.
+    assert_eq!(
+        book.vwap_for_quantity(Side::Sell, 20, VolumeView::Visible),
+        Some((100_850_000, P102))
+    );
+    // 10 @ 100, then the iceberg's hidden size covers the rest at 101.
+    assert_eq!(
+        book.vwap_for_quantity(Side::Sell, 20, VolumeView::Total),
+        Some((100_500_000, P101))
+    );
+    assert_eq!(
+        book.vwap_for_quantity(Side::Sell, 10, VolumeView::Visible),
+        Some((P100, P100))
+    );
+    assert_eq!(
+        book.vwap_for_quantity(Side::Buy, 5, VolumeView::Visible),
+        Some((98_800_000, P98))
+    );
+
+    assert_eq!(
+        book.vwap_for_quantity(Side::Sell, 25, VolumeView::Visible),
+        Some((101_080_000, P102))
+    );
+    assert_eq!(
+        book.vwap_for_quantity(Side::Sell, 26, VolumeView::Visible),
+        None
+    );
+    assert!(book
+        .vwap_for_quantity(Side::Sell, 45, VolumeView::Total)
+        .is_some());
+    assert_eq!(
+        book.vwap_for_quantity(Side::Sell, 46, VolumeView::Total),
+        None
+    );
+    assert_eq!(
+        book.vwap_for_quantity(Side::Sell, 0, VolumeView::Visible),
+        None
+    );
+}
+
+#[test]
+fn test_impact_price() {
+    let engine = fixture_engine();
+    let book = &engine.order_books["AAPL"];
+
+    assert_eq!(
+        book.impact_price(Side::Sell, 10, VolumeView::Visible),
+        Some(P100)
+    );
+    assert_eq!(
+        book.impact_price(Side::Sell, 11, VolumeView::Visible),
+        Some(P101)
+    );
+    assert_eq!(
+        book.impact_price(Side::Sell, 14, VolumeView::Visible),
+        Some(P102)
+    );
+    assert_eq!(
+        book.impact_price(Side::Sell, 14, VolumeView::Total),
+        Some(P101)
+    );
+    assert_eq!(
+        book.impact_price(Side::Buy, 5, VolumeView::Visible),
+        Some(P98)
+    );
+    assert_eq!(book.impact_price(Side::Buy, 11, VolumeView::Visible), None);
+    assert_eq!(book.impact_price(Side::Buy, 0, VolumeView::Visible), None);
+}
+
+/// A sweep also takes the iceberg's hidden size, so its outcome is what
+/// the total view predicts.
+#[test]
+fn test_total_view_matches_a_sweep() {
+    for quantity in [1, 10, 11, 13, 14, 33, 34, 45] {
+        let mut engine = fixture_engine();
+        let impact =
+            engine.order_books["AAPL"].impact_price(Side::Sell, quantity, VolumeView::Total);
+        let (vwap, worst) = engine.order_books["AAPL"]
+            .vwap_for_quantity(Side::Sell, quantity, VolumeView::Total)
+            .unwrap();
+        assert_eq!(impact, Some(worst));
+
+        let sweep = Order::new(
+            "AAPL".to_string(),
+            Side::Buy,
+            OrderType::Limit,
+            worst,
+            quantity as u32,
+            3,
+        );
+        let result = engine.place_order(sweep).unwrap();
+        let notional: u128 = result
+            .trades
+            .iter()
+            .map(|t| t.price as u128 * t.quantity as u128)
+            .sum();
+        let filled: u64 = result.trades.iter().map(|t| t.quantity as u64).sum();
+        assert_eq!(filled, quantity, "sweep of {quantity} filled {filled}");
+        assert_eq!(
+            (notional / quantity as u128) as Price,
+            vwap,
+            "sweep of {quantity}"
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use exchange_rs::{
+    clock::ManualClock,
+    logging::{LogLevelController, LoggingError, SetLogLevel},
+};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::{filter::LevelFilter, layer::Context, prelude::*, Layer};
+
+/// Records the target and level of every event that passes the filter.
+#[derive(Clone, Default)]
+struct CaptureLayer {
+    events: Arc<Mutex<Vec<(String, Level)>>>,
+}
+
+impl CaptureLayer {
+    fn take(&self) -> Vec<(String, Level)> {
+        std::mem::take(&mut self.events.lock())
+    }
+}
+
+impl<S: Subscriber> Layer<S> for CaptureLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        self.events
+            .lock()
+            .push((metadata.target().to_string(), *metadata.level()));
+    }
+}
+
+fn change(target: Option<&str>, level: &str, force: bool) -> SetLogLevel {
+    SetLogLevel {
+        actor: "oncall".to_string(),
+        target: target.map(str::to_string),
+        level: level.to_string(),
+        force,
+    }
+}
+
+fn emit_debug() {
+    tracing::debug!(target: "exchange_rs::fix::session", "sequence gap");
+    tracing::debug!(target: "exchange_rs::matching_engine", "order matched");
+}
+
+#[test]
+fn test_set_level_enables_suppressed_events() {
+    let clock = Arc::new(ManualClock::new(1_000));
+    let (filter, controller) =
+        LogLevelController::with_limits(LevelFilter::INFO, LevelFilter::TRACE, clock);
+    let capture = CaptureLayer::default();
+    let subscriber = tracing_subscriber::registry()
+        .with(filter)
+        .with(capture.clone());
+
+    tracing::subscriber::with_default(subscriber, || {
+        emit_debug();
+        assert!(capture.take().is_empty());
+
+        let audit = controller
+            .set_level(&change(Some("exchange_rs::fix::session"), "debug", false))
+            .unwrap();
+        assert_eq!(audit.timestamp, 1_000);
+        assert_eq!(audit.previous, None);
+        capture.take();
+
+        emit_debug();
+        assert_eq!(
+            capture.take(),
+            vec![("exchange_rs::fix::session".to_string(), Level::DEBUG)]
+        );
+    });
+
+    let levels = controller.levels();
+    assert_eq!(levels.default, "info");
+    assert_eq!(
+        levels.targets.get("exchange_rs::fix::session").map(String::as_str),
+        Some("debug")
+    );
+    assert_eq!(
+        controller.set_level(&change(None, "loud", false)).unwrap_err(),
+        LoggingError::InvalidLevel { level: "loud".to_string() }
+    );
+    assert_eq!(controller.audit_log().len(), 1);
+}
+
+#[test]
+fn test_hot_path_targets_are_capped() {
+    let clock = Arc::new(ManualClock::new(0));
+    let (filter, controller) =
+        LogLevelController::with_limits(LevelFilter::INFO, LevelFilter::INFO, clock);
+    let capture = CaptureLayer::default();
+    let subscriber = tracing_subscriber::registry()
+        .with(filter)
+        .with(capture.clone());
+
+    tracing::subscriber::with_default(subscriber, || {
+        controller.set_level(&change(None, "debug", false)).unwrap();
+        capture.take();
+        emit_debug();
+        assert_eq!(
+            capture.take(),
+            vec![("exchange_rs::fix::session".to_string(), Level::DEBUG)]
+        );
+
+        assert_eq!(
+            controller
+                .set_level(&change(Some("exchange_rs::matching_engine"), "debug", false))
+                .unwrap_err(),
+            LoggingError::HotPathLimit {
+                target: "exchange_rs::matching_engine".to_string(),
+                max: LevelFilter::INFO,
+            }
+        );
+
+        controller
+            .set_level(&change(Some("exchange_rs::matching_engine"), "debug", true))
+            .unwrap();
+        capture.take();
+        emit_debug();
+        assert_eq!(capture.take().len(), 2);
+    });
+
+    let audit = controller.audit_log();
+    assert_eq!(audit.len(), 2);
+    assert_eq!(audit[0].previous.as_deref(), Some("info"));
+    assert!(audit[1].forced);
+}
+
+#[test]
+fn test_failed_reload_leaves_levels_unchanged() {
+    let clock = Arc::new(ManualClock::new(0));
+    let (filter, controller) =
+        LogLevelController::with_limits(LevelFilter::INFO, LevelFilter::TRACE, clock);
+    // Without a subscriber holding the filter there is nothing to reload.
+    drop(filter);
+
+    let error = controller
+        .set_level(&change(Some("exchange_rs::fix::session"), "debug", false))
+        .unwrap_err();
+    assert!(matches!(error, LoggingError::Reload { .. }));
+    assert!(controller.levels().targets.is_empty());
+    assert!(controller.set_level(&change(None, "warn", false)).is_err());
+    assert_eq!(controller.levels().default, "info");
+    assert!(controller.audit_log().is_empty());
+}
@@ -0,0 +1,143 @@
+use exchange_rs::{
+    fix_gateway::FixGateway,
+    matching_engine::MatchingEngine,
+    optimizations::OrderProcessorPool,
+    order::{Order, OrderStatus, OrderType, Side},
+};
+use exchange_rs::Price;
+use parking_lot::Mutex;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn limit(symbol: &str, side: Side, price: Price, quantity: u32, user_id: u64) -> Order {
+    Order::new(symbol.to_string(), side, OrderType::Limit, price, quantity, user_id)
+}
+
+fn stop_limit(symbol: &str, side: Side, price: Price, stop_price: Price, user_id: u64) -> Order {
+    let mut order = Order::new(symbol.to_string(), side, OrderType::StopLimit, price, 5, user_id);
+    order.stop_price = Some(stop_price);
+    order
+}
+
+fn two_symbol_engine() -> MatchingEngine {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    engine.add_symbol("MSFT");
+    engine
+}
+
+#[test]
+fn test_cancel_all_for_user_sweeps_levels_and_stops() {
+    let mut engine = two_symbol_engine();
+
+    engine.place_order(limit("AAPL", Side::Buy, 100, 10, 1)).unwrap();
+    engine.place_order(limit("AAPL", Side::Sell, 110, 10, 2)).unwrap();
+    engine.place_order(limit("MSFT", Side::Sell, 200, 10, 1)).unwrap();
+    engine.place_order(stop_limit("AAPL", Side::Sell, 90, 95, 1)).unwrap();
+
+    let canceled = engine.cancel_all_for_user(1);
+    let ids: Vec<u64> = canceled.iter().map(|o| o.read().id).collect();
+    assert_eq!(ids, vec![1, 3, 4]);
+    assert!(canceled.iter().all(|o| o.read().status == OrderStatus::Canceled));
+
+    let aapl = engine.order_books.get("AAPL").unwrap();
+    assert_eq!(aapl.get_best_bid_price(), None);
+    assert_eq!(aapl.get_best_ask_price(), Some(110));
+    assert!(aapl.user_order_ids(1).is_empty());
+    assert_eq!(aapl.user_order_ids(2), vec![2]);
+    assert_eq!(engine.order_books.get("MSFT").unwrap().get_best_ask_price(), None);
+
+    assert!(engine.cancel_all_for_user(1).is_empty());
+}
+
+#[test]
+fn test_cancel_all_for_symbol() {
+    let mut engine = two_symbol_engine();
+
+    engine.place_order(limit("AAPL", Side::Buy, 100, 10, 1)).unwrap();
+    engine.place_order(limit("AAPL", Side::Sell, 110, 10, 2)).unwrap();
+    engine.place_order(stop_limit("AAPL", Side::Buy, 120, 115, 3)).unwrap();
+    engine.place_order(limit("MSFT", Side::Sell, 200, 10, 1)).unwrap();
+
+    let canceled = engine.cancel_all_for_symbol("AAPL");
+    assert_eq!(canceled.len(), 3);
+
+    let aapl = engine.order_books.get("AAPL").unwrap();
+    assert_eq!((aapl.get_best_bid_price(), aapl.get_best_ask_price()), (None, None));
+    assert_eq!(engine.order_books.get("MSFT").unwrap().get_best_ask_price(), Some(200));
+    assert!(engine.cancel_all_for_symbol("NONE").is_empty());
+}
+
+#[test]
+fn test_user_index_drops_filled_orders() {
+    let mut engine = two_symbol_engine();
+
+    engine.place_order(limit("AAPL", Side::Sell, 100, 5, 1)).unwrap();
+    engine.place_order(limit("AAPL", Side::Sell, 101, 5, 1)).unwrap();
+    engine.place_order(limit("AAPL", Side::Buy, 100, 5, 2)).unwrap();
+
+    assert_eq!(engine.order_books.get("AAPL").unwrap().user_order_ids(1), vec![2]);
+    assert!(engine.order_books.get("AAPL").unwrap().user_order_ids(2).is_empty());
+}
+
+#[test]
+fn test_cancel_on_disconnect() {
+    let engine = Arc::new(Mutex::new(two_symbol_engine()));
+    {
+        let mut engine = engine.lock();
+        engine.place_order(limit("AAPL", Side::Buy, 100, 10, 7)).unwrap();
+        engine.place_order(limit("MSFT", Side::Buy, 100, 10, 8)).unwrap();
+    }
+
+    let canceled = FixGateway::cancel_on_disconnect(&engine, &HashSet::from([7]));
+    assert_eq!(canceled.len(), 1);
+    assert_eq!(canceled[0].read().user_id, 7);
+    assert_eq!(engine.lock().order_books.get("MSFT").unwrap().get_best_bid_price(), Some(100));
+}
+
+#[test]
+fn test_mass_cancel_races_with_processor_pool() {
+    const ORDERS: u64 = 400;
+
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    let engine = Arc::new(Mutex::new(engine));
+    let pool = OrderProcessorPool::new(4, Arc::clone(&engine));
+
+    let canceller = {
+        let engine = Arc::clone(&engine);
+        thread::spawn(move || {
+            let mut canceled = 0;
+            for _ in 0..50 {
+                canceled += engine.lock().cancel_all_for_user(1).len();
+                thread::sleep(Duration::from_micros(200));
+            }
+            canceled
+        })
+    };
+
+    for i in 0..ORDERS {
+        let order = limit("AAPL", Side::Buy, 100 + (i % 10) as Price, 1, 1);
+        while pool.submit_order(order.clone()).is_err() {
+            thread::yield_now();
+        }
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while engine.lock().get_order_metrics().orders_received < ORDERS {
+        assert!(Instant::now() < deadline, "processor pool did not drain");
+        thread::sleep(Duration::from_millis(1));
+    }
+    drop(pool);
+
+    let canceled_during_race = canceller.join().unwrap();
+    let canceled_after = engine.lock().cancel_all_for_user(1).len();
+    assert_eq!(canceled_during_race + canceled_after, ORDERS as usize);
+
+    let engine = engine.lock();
+    let order_book = engine.order_books.get("AAPL").unwrap();
+    assert!(order_book.user_order_ids(1).is_empty());
+    assert_eq!(order_book.get_best_bid_price(), None);
+}
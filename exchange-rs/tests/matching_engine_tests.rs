@@ -1,7 +1,15 @@
 use exchange_rs::{
-    matching_engine::{MatchingEngine, MatchingError},
-    order::{Order, OrderStatus, OrderType, Side, TimeInForce},
+    clock::ManualClock,
+    contingent::{ContingentTrigger, TriggerDirection},
+    events::EngineEvent,
+    matching_engine::{CancelReason, HaltedOrders, MatchingEngine, MatchingEngineConfig, MatchingError},
+    order::{Order, OrderStatus, OrderType, Side, TimeInForce, TriggerSource},
+    risk::RiskLimits,
+    orderbook::{MatchPolicy, OrderTypeRestriction, OrderTypeRules, PriceBands, RestrictionWindow, SymbolSpec, TradingState},
 };
+use exchange_rs::PRICE_SCALE_FACTOR;
+use exchange_rs::Price;
+use std::sync::Arc;
 
 mod test_utils;
 
@@ -104,3 +112,1656 @@ fn test_iceberg_orders() {
     let level = order_book.sell_levels.get(&100).unwrap();
     assert_eq!(level.visible_volume, 5);
 }
+
+#[test]
+fn test_market_order_protection_band() {
+    let mut engine = MatchingEngine::with_config(MatchingEngineConfig {
+        market_protection_bps: Some(100),
+        ..Default::default()
+    });
+    engine.add_symbol("AAPL");
+
+    for (price, user_id) in [(10_000, 1), (10_050, 2), (10_200, 3)] {
+        let sell_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, price, 5, user_id);
+        engine.place_order(sell_order).unwrap();
+    }
+
+    let market_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Market, 0, 15, 4);
+
+    let result = engine.place_order(market_order).unwrap();
+    assert!(!result.rejected);
+    assert_eq!(result.cancel_reason, Some(CancelReason::PriceProtection));
+    assert_eq!(result.trades.len(), 2);
+    assert_eq!(result.trades[0].price, 10_000);
+    assert_eq!(result.trades[1].price, 10_050);
+    assert!(result.remaining_order.is_none());
+
+    let market_order = result.filled_orders.last().unwrap().read();
+    assert_eq!(market_order.filled_quantity, 10);
+    assert_eq!(market_order.status, OrderStatus::Canceled);
+
+    let order_book = engine.order_books.get("AAPL").unwrap();
+    assert_eq!(order_book.get_best_ask_price(), Some(10_200));
+}
+
+#[test]
+fn test_fok_market_order_respects_protection_band() {
+    let mut engine = MatchingEngine::with_config(MatchingEngineConfig {
+        market_protection_bps: Some(100),
+        ..Default::default()
+    });
+    engine.add_symbol("AAPL");
+
+    for (price, user_id) in [(10_000, 1), (10_050, 2), (10_200, 3)] {
+        let sell_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, price, 5, user_id);
+        engine.place_order(sell_order).unwrap();
+    }
+
+    let mut market_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Market, 0, 15, 4);
+    market_order.time_in_force = TimeInForce::FOK;
+    assert_eq!(engine.place_order(market_order).unwrap_err(), MatchingError::FOKCannotBeFilled);
+
+    // Nothing traded: the whole book is still there.
+    let order_book = engine.order_books.get("AAPL").unwrap();
+    assert_eq!(order_book.get_best_ask_price(), Some(10_000));
+    assert_eq!(order_book.order_count(), 3);
+
+    let mut market_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Market, 0, 10, 4);
+    market_order.time_in_force = TimeInForce::FOK;
+    let result = engine.place_order(market_order).unwrap();
+    assert_eq!(result.trades.len(), 2);
+    assert_eq!(result.filled_orders.last().unwrap().read().status, OrderStatus::Filled);
+}
+
+#[test]
+fn test_fok_market_order_with_exact_and_one_short_liquidity() {
+    for side in [Side::Buy, Side::Sell] {
+        let resting_side = match side {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        };
+        let mut engine = MatchingEngine::new();
+        engine.add_symbol("AAPL");
+        // 4 + 6 + 12, the iceberg showing only 3 of its 12.
+        for (price, quantity, user_id) in [(10_000, 4, 1), (10_050, 6, 2), (10_100, 12, 3)] {
+            let price = match side {
+                Side::Buy => price,
+                Side::Sell => 20_100 - price,
+            };
+            let order_type = if user_id == 3 { OrderType::Iceberg } else { OrderType::Limit };
+            let mut order = Order::new("AAPL".to_string(), resting_side, order_type, price, quantity, user_id);
+            order.display_quantity = (user_id == 3).then_some(3);
+            engine.place_order(order).unwrap();
+        }
+
+        let mut short = Order::new("AAPL".to_string(), side, OrderType::Market, 0, 23, 4);
+        short.time_in_force = TimeInForce::FOK;
+        assert_eq!(engine.place_order(short).unwrap_err(), MatchingError::FOKCannotBeFilled);
+        let order_book = engine.order_books.get("AAPL").unwrap();
+        assert_eq!(order_book.order_count(), 3);
+
+        let mut exact = Order::new("AAPL".to_string(), side, OrderType::Market, 0, 22, 4);
+        exact.time_in_force = TimeInForce::FOK;
+        let result = engine.place_order(exact).unwrap();
+        assert_eq!(result.trades.iter().map(|t| t.quantity).sum::<u32>(), 22);
+        assert_eq!(result.filled_orders.last().unwrap().read().status, OrderStatus::Filled);
+        assert_eq!(engine.order_books.get("AAPL").unwrap().order_count(), 0);
+    }
+}
+
+#[test]
+fn test_incoming_iceberg_stops_at_its_limit_price() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    for (price, quantity, user_id) in [(99, 10, 1), (98, 7, 2)] {
+        let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, price, quantity, user_id);
+        engine.place_order(buy_order).unwrap();
+    }
+
+    let mut iceberg = Order::new("AAPL".to_string(), Side::Sell, OrderType::Iceberg, 99, 20, 3);
+    iceberg.display_quantity = Some(5);
+    let result = engine.place_order(iceberg).unwrap();
+    assert_eq!(result.trades.iter().map(|t| (t.price, t.quantity)).collect::<Vec<_>>(), vec![(99, 10)]);
+
+    let order_book = engine.order_books.get("AAPL").unwrap();
+    assert_eq!((order_book.get_best_bid_price(), order_book.get_best_ask_price()), (Some(98), Some(99)));
+
+    // Not marketable at all: it rests untouched.
+    let mut iceberg = Order::new("AAPL".to_string(), Side::Sell, OrderType::Iceberg, 103, 20, 4);
+    iceberg.display_quantity = Some(5);
+    assert!(engine.place_order(iceberg).unwrap().trades.is_empty());
+}
+
+#[test]
+fn test_market_order_slippage_override() {
+    let mut engine = MatchingEngine::with_config(MatchingEngineConfig {
+        market_protection_bps: Some(100),
+        ..Default::default()
+    });
+    engine.add_symbol("AAPL");
+
+    for (price, user_id) in [(10_000, 1), (10_200, 2)] {
+        let sell_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, price, 5, user_id);
+        engine.place_order(sell_order).unwrap();
+    }
+
+    let mut market_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Market, 0, 10, 3);
+    market_order.max_slippage_bps = Some(300);
+
+    let result = engine.place_order(market_order).unwrap();
+    assert_eq!(result.cancel_reason, None);
+    assert_eq!(result.trades.len(), 2);
+    assert_eq!(result.filled_orders.last().unwrap().read().status, OrderStatus::Filled);
+}
+
+#[test]
+fn test_market_order_protection_price() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+
+    for (price, user_id) in [(10_000, 1), (10_100, 2), (50_000, 3)] {
+        let sell_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, price, 5, user_id);
+        engine.place_order(sell_order).unwrap();
+    }
+
+    let mut market_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Market, 0, 15, 4);
+    market_order.protection_price = Some(10_100);
+
+    let result = engine.place_order(market_order).unwrap();
+    assert_eq!(result.cancel_reason, Some(CancelReason::PriceProtection));
+    assert_eq!(result.trades.len(), 2);
+    assert_eq!(result.trades[1].price, 10_100);
+    assert!(result.remaining_order.is_none());
+
+    let market_order = result.filled_orders.last().unwrap().read();
+    assert_eq!(market_order.filled_quantity, 10);
+    assert_eq!(market_order.status, OrderStatus::Canceled);
+
+    let order_book = engine.order_books.get("AAPL").unwrap();
+    assert_eq!(order_book.get_best_ask_price(), Some(50_000));
+    assert_eq!(order_book.get_best_bid_price(), None);
+}
+
+#[test]
+fn test_market_order_protection_price_tighter_than_band() {
+    let mut engine = MatchingEngine::with_config(MatchingEngineConfig {
+        market_protection_bps: Some(500),
+        ..Default::default()
+    });
+    engine.add_symbol("AAPL");
+
+    for (price, user_id) in [(10_000, 1), (9_900, 2)] {
+        let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, price, 5, user_id);
+        engine.place_order(buy_order).unwrap();
+    }
+
+    let mut market_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Market, 0, 10, 3);
+    market_order.protection_price = Some(9_950);
+
+    let result = engine.place_order(market_order).unwrap();
+    assert_eq!(result.cancel_reason, Some(CancelReason::PriceProtection));
+    assert_eq!(result.trades.len(), 1);
+    assert_eq!(result.filled_orders.last().unwrap().read().filled_quantity, 5);
+
+    let mut market_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Market, 0, 5, 3);
+    market_order.protection_price = Some(10_000);
+
+    let result = engine.place_order(market_order).unwrap();
+    assert!(result.trades.is_empty());
+    assert_eq!(result.filled_orders[0].read().status, OrderStatus::Canceled);
+    assert_eq!(engine.order_books.get("AAPL").unwrap().get_best_bid_price(), Some(9_900));
+}
+
+#[test]
+fn test_contingent_order_triggers_on_other_symbol() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("BTC");
+    engine.add_symbol("ETH");
+
+    let eth_ask = Order::new("ETH".to_string(), Side::Sell, OrderType::Limit, 2_000, 10, 1);
+    engine.place_order(eth_ask).unwrap();
+
+    let hedge = Order::new("ETH".to_string(), Side::Buy, OrderType::Limit, 2_000, 4, 7);
+    let trigger = ContingentTrigger::new("BTC", TriggerDirection::AtOrBelow, 50_000);
+    let contingent_id = engine.place_contingent_order(trigger, hedge).unwrap();
+
+    let btc_bid = Order::new("BTC".to_string(), Side::Buy, OrderType::Limit, 51_000, 1, 2);
+    engine.place_order(btc_bid).unwrap();
+    let btc_sell = Order::new("BTC".to_string(), Side::Sell, OrderType::Limit, 51_000, 1, 3);
+    let result = engine.place_order(btc_sell).unwrap();
+    assert!(result.contingent_executions.is_empty());
+    assert!(engine.get_contingent_order(contingent_id).is_some());
+
+    let btc_bid = Order::new("BTC".to_string(), Side::Buy, OrderType::Limit, 49_500, 1, 2);
+    engine.place_order(btc_bid).unwrap();
+    let btc_sell = Order::new("BTC".to_string(), Side::Sell, OrderType::Limit, 49_500, 1, 3);
+    let result = engine.place_order(btc_sell).unwrap();
+
+    assert_eq!(result.contingent_executions.len(), 1);
+    let execution = &result.contingent_executions[0];
+    assert_eq!(execution.contingent_id, contingent_id);
+
+    let hedge_result = execution.result.as_ref().unwrap();
+    assert_eq!(hedge_result.trades.len(), 1);
+    assert_eq!(hedge_result.trades[0].quantity, 4);
+    assert_eq!(hedge_result.trades[0].price, 2_000);
+
+    let hedge_order = hedge_result.filled_orders.last().unwrap().read();
+    assert_eq!(hedge_order.user_id, 7);
+    assert_eq!(hedge_order.contingent_id, Some(contingent_id));
+    assert!(engine.get_contingent_order(contingent_id).is_none());
+}
+
+#[test]
+fn test_contingent_order_cancel_before_trigger() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("BTC");
+    engine.add_symbol("ETH");
+
+    let hedge = Order::new("ETH".to_string(), Side::Sell, OrderType::Limit, 2_000, 4, 7);
+    let trigger = ContingentTrigger::new("BTC", TriggerDirection::AtOrAbove, 50_000);
+    let contingent_id = engine.place_contingent_order(trigger, hedge).unwrap();
+
+    let canceled = engine.cancel_contingent_order(contingent_id).unwrap();
+    assert_eq!(canceled.user_id, 7);
+    assert!(engine.cancel_contingent_order(contingent_id).is_none());
+
+    let btc_ask = Order::new("BTC".to_string(), Side::Sell, OrderType::Limit, 50_500, 1, 2);
+    engine.place_order(btc_ask).unwrap();
+    let btc_buy = Order::new("BTC".to_string(), Side::Buy, OrderType::Limit, 50_500, 1, 3);
+    let result = engine.place_order(btc_buy).unwrap();
+
+    assert_eq!(result.trades.len(), 1);
+    assert!(result.contingent_executions.is_empty());
+    assert!(engine.order_books.get("ETH").unwrap().get_best_ask_price().is_none());
+}
+
+#[test]
+fn test_contingent_order_unknown_symbol() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("ETH");
+
+    let hedge = Order::new("ETH".to_string(), Side::Buy, OrderType::Limit, 2_000, 4, 7);
+    let trigger = ContingentTrigger::new("BTC", TriggerDirection::AtOrBelow, 50_000);
+    let result = engine.place_contingent_order(trigger, hedge);
+    assert_eq!(result.unwrap_err(), MatchingError::SymbolNotFound);
+}
+
+#[test]
+fn test_contingent_order_survives_snapshot() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("BTC");
+    engine.add_symbol("ETH");
+
+    let hedge = Order::new("ETH".to_string(), Side::Buy, OrderType::Limit, 2_000, 4, 7);
+    let trigger = ContingentTrigger::new("BTC", TriggerDirection::AtOrBelow, 50_000);
+    let contingent_id = engine.place_contingent_order(trigger.clone(), hedge).unwrap();
+
+    let json = serde_json::to_string(&engine.create_snapshot()).unwrap();
+    let snapshot = serde_json::from_str(&json).unwrap();
+    let mut restored = MatchingEngine::restore_from_snapshot(&snapshot);
+
+    let contingent = restored.get_contingent_order(contingent_id).unwrap();
+    assert_eq!(contingent.trigger, trigger);
+    assert_eq!(contingent.user_id, 7);
+
+    let hedge = Order::new("ETH".to_string(), Side::Buy, OrderType::Limit, 2_000, 4, 7);
+    let next_id = restored.place_contingent_order(trigger, hedge).unwrap();
+    assert!(next_id > contingent_id);
+}
+
+#[test]
+fn test_engine_snapshot_round_trip() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    engine.add_symbol("MSFT");
+
+    for (symbol, side, price, quantity, user_id) in [
+        ("AAPL", Side::Sell, 101, 10, 1),
+        ("AAPL", Side::Sell, 102, 5, 2),
+        ("AAPL", Side::Buy, 101, 4, 3),
+        ("AAPL", Side::Buy, 99, 7, 4),
+        ("MSFT", Side::Buy, 250, 3, 1),
+    ] {
+        let order = Order::new(symbol.to_string(), side, OrderType::Limit, price, quantity, user_id);
+        engine.place_order(order).unwrap();
+    }
+    let mut stop = Order::new("AAPL".to_string(), Side::Sell, OrderType::StopLimit, 95, 6, 5);
+    stop.stop_price = Some(96);
+    engine.place_order(stop).unwrap();
+
+    let json = serde_json::to_string(&engine.snapshot()).unwrap();
+    let mut restored = MatchingEngine::restore(serde_json::from_str(&json).unwrap());
+
+    assert_eq!(
+        serde_json::to_value(restored.snapshot()).unwrap(),
+        serde_json::to_value(engine.snapshot()).unwrap()
+    );
+    let book = restored.order_books.get("AAPL").unwrap();
+    assert_eq!(book.last_trade_price, Some(101));
+    assert_eq!((book.get_best_bid_price(), book.get_best_ask_price()), (Some(99), Some(101)));
+    assert_eq!(restored.get_order_status("AAPL", 6).unwrap().status, OrderStatus::New);
+    assert_eq!(restored.get_order_status("AAPL", 1).unwrap().filled_quantity, 4);
+
+    for engine in [&mut engine, &mut restored] {
+        let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 101, 2, 6);
+        let result = engine.place_order(buy_order).unwrap();
+        assert_eq!((result.trades[0].id, result.trades[0].buy_order_id), (2, 7));
+    }
+}
+
+#[test]
+fn test_restored_engine_keeps_indices_stops_and_states() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    engine.add_symbol("MSFT");
+
+    for (side, price, quantity, user_id, client_order_id) in [
+        (Side::Buy, 99, 10, 1, "bid-1"),
+        (Side::Buy, 98, 5, 2, "bid-2"),
+        (Side::Sell, 101, 8, 1, "ask-1"),
+        (Side::Sell, 102, 4, 2, "ask-2"),
+    ] {
+        let mut order = Order::new("AAPL".to_string(), side, OrderType::Limit, price, quantity, user_id);
+        order.client_order_id = Some(client_order_id.to_string());
+        engine.place_order(order).unwrap();
+    }
+    let mut stop = Order::new("AAPL".to_string(), Side::Sell, OrderType::StopLimit, 97, 3, 3);
+    stop.stop_price = Some(98);
+    engine.place_order(stop).unwrap();
+    engine.place_order(Order::new("MSFT".to_string(), Side::Buy, OrderType::Limit, 250, 3, 1)).unwrap();
+    engine.halt_symbol("MSFT").unwrap();
+
+    let json = serde_json::to_string(&engine.create_snapshot()).unwrap();
+    let mut restored = MatchingEngine::restore(serde_json::from_str(&json).unwrap());
+    assert_eq!(restored.trading_state("MSFT"), Some(TradingState::Halted));
+
+    let mut outcomes = Vec::new();
+    for engine in [&mut engine, &mut restored] {
+        let mut log = Vec::new();
+
+        let mut duplicate = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 97, 1, 1);
+        duplicate.client_order_id = Some("bid-1".to_string());
+        log.push(format!("{:?}", engine.place_order(duplicate).err()));
+
+        let cancelled = engine.cancel_order_by_client_id("AAPL", 2, "ask-2");
+        log.push(format!("{:?}", cancelled.map(|order| order.read().id)));
+
+        let halted = Order::new("MSFT".to_string(), Side::Sell, OrderType::Limit, 250, 1, 2);
+        log.push(format!("{:?}", engine.place_order(halted).err()));
+
+        // Sweeps both bids: the trade at 98 triggers the stop.
+        let sweep = Order::new("AAPL".to_string(), Side::Sell, OrderType::Market, 0, 15, 4);
+        let result = engine.place_order(sweep).unwrap();
+        for trade in &result.trades {
+            log.push(format!(
+                "trade {} {}/{} {}x{}",
+                trade.id, trade.buy_order_id, trade.sell_order_id, trade.quantity, trade.price
+            ));
+        }
+        log.push(format!("triggered {:?}", result.triggered_stops.iter().map(|order| order.read().id).collect::<Vec<_>>()));
+
+        let mut cancelled: Vec<u64> = engine.cancel_all_for_user(1).iter().map(|order| order.read().id).collect();
+        cancelled.sort_unstable();
+        log.push(format!("cancelled {:?}", cancelled));
+
+        let next = engine.place_order(Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 90, 1, 5)).unwrap();
+        log.push(format!("next id {}", next.remaining_order.unwrap().read().id));
+        outcomes.push(log);
+    }
+
+    assert_eq!(outcomes[0], outcomes[1]);
+    assert!(outcomes[0][0].contains("DuplicateClientOrderId"));
+    assert_eq!(outcomes[0][1], "Some(4)");
+    assert!(outcomes[0].iter().any(|line| line == "triggered [5]"));
+}
+
+fn level_allocations(policy: MatchPolicy, incoming_qty: u32) -> Vec<(u64, u32)> {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol_with_policy("OPT", policy);
+
+    for (quantity, user_id) in [(10, 1), (30, 2), (60, 3)] {
+        let sell_order = Order::new("OPT".to_string(), Side::Sell, OrderType::Limit, 100, quantity, user_id);
+        engine.place_order(sell_order).unwrap();
+    }
+
+    let buy_order = Order::new("OPT".to_string(), Side::Buy, OrderType::Limit, 100, incoming_qty, 4);
+    let result = engine.place_order(buy_order).unwrap();
+    assert_eq!(result.trades.iter().map(|t| t.quantity).sum::<u32>(), incoming_qty);
+
+    result.trades.iter().map(|t| (t.sell_order_id, t.quantity)).collect()
+}
+
+#[test]
+fn test_fifo_and_pro_rata_allocation() {
+    assert_eq!(level_allocations(MatchPolicy::Fifo, 50), vec![(1, 10), (2, 30), (3, 10)]);
+    assert_eq!(
+        level_allocations(MatchPolicy::ProRata { min_allocation: 0 }, 50),
+        vec![(1, 5), (2, 15), (3, 30)]
+    );
+}
+
+#[test]
+fn test_pro_rata_rounding_and_min_allocation() {
+    assert_eq!(
+        level_allocations(MatchPolicy::ProRata { min_allocation: 0 }, 45),
+        vec![(1, 5), (2, 13), (3, 27)]
+    );
+    assert_eq!(
+        level_allocations(MatchPolicy::ProRata { min_allocation: 5 }, 45),
+        vec![(1, 5), (2, 13), (3, 27)]
+    );
+    assert_eq!(
+        level_allocations(MatchPolicy::ProRata { min_allocation: 14 }, 45),
+        vec![(1, 10), (2, 8), (3, 27)]
+    );
+}
+
+#[test]
+fn test_pro_rata_sweeps_level_when_incoming_exceeds_it() {
+    assert_eq!(
+        level_allocations(MatchPolicy::ProRata { min_allocation: 0 }, 100),
+        vec![(1, 10), (2, 30), (3, 60)]
+    );
+}
+
+fn auction_engine() -> MatchingEngine {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    engine.set_trading_state("AAPL", TradingState::Auction).unwrap();
+
+    for (side, price, quantity, user_id) in [
+        (Side::Buy, 102, 10, 1),
+        (Side::Buy, 101, 5, 2),
+        (Side::Buy, 100, 10, 3),
+        (Side::Sell, 99, 8, 4),
+        (Side::Sell, 100, 6, 5),
+        (Side::Sell, 101, 10, 6),
+    ] {
+        let order = Order::new("AAPL".to_string(), side, OrderType::Limit, price, quantity, user_id);
+        let result = engine.place_order(order).unwrap();
+        assert!(result.trades.is_empty());
+    }
+
+    engine
+}
+
+#[test]
+fn test_auction_uncross_at_single_price() {
+    let mut engine = auction_engine();
+
+    assert_eq!(engine.indicative_price("AAPL"), Some(101));
+    let indicative = engine.indicative_uncross("AAPL").unwrap();
+    assert_eq!(indicative.volume, 15);
+    assert_eq!(indicative.imbalance, 9);
+
+    let result = engine.uncross("AAPL").unwrap();
+    assert_eq!(result.trades.iter().map(|t| t.quantity).sum::<u32>(), 15);
+    assert!(result.trades.iter().all(|t| t.price == 101));
+    assert_eq!(engine.trading_state("AAPL"), Some(TradingState::Continuous));
+
+    let order_book = engine.order_books.get("AAPL").unwrap();
+    assert_eq!(order_book.get_best_bid_price(), Some(100));
+    assert_eq!(order_book.get_best_ask_price(), Some(101));
+    assert_eq!(order_book.sell_levels[&101].total_volume, 9);
+    assert_eq!(order_book.last_trade_price, Some(101));
+    assert_eq!(order_book.get_market_depth().ask_levels, vec![(101, 9)]);
+}
+
+#[test]
+fn test_auction_market_orders_price_at_cross() {
+    let mut engine = auction_engine();
+
+    let market_sell = Order::new("AAPL".to_string(), Side::Sell, OrderType::Market, 0, 20, 7);
+    engine.place_order(market_sell).unwrap();
+    assert_eq!(engine.indicative_price("AAPL"), Some(99));
+
+    let market_buy = Order::new("AAPL".to_string(), Side::Buy, OrderType::Market, 0, 50, 8);
+    engine.place_order(market_buy).unwrap();
+
+    let result = engine.uncross("AAPL").unwrap();
+    assert_eq!(result.trades.iter().map(|t| t.quantity).sum::<u32>(), 44);
+    assert!(result.trades.iter().all(|t| t.price == 102));
+
+    let leftover = result
+        .filled_orders
+        .iter()
+        .find(|o| o.read().user_id == 8)
+        .unwrap()
+        .read();
+    assert_eq!(leftover.status, OrderStatus::Canceled);
+    assert_eq!(leftover.filled_quantity, 44);
+
+    let order_book = engine.order_books.get("AAPL").unwrap();
+    assert!(!order_book.buy_levels.contains_key(&Price::MAX));
+    assert!(!order_book.sell_levels.contains_key(&0));
+}
+
+#[test]
+fn test_auction_rejects_ioc_and_uncross_outside_auction() {
+    let mut engine = auction_engine();
+
+    let mut ioc_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 105, 5, 9);
+    ioc_order.time_in_force = TimeInForce::IOC;
+    assert_eq!(engine.place_order(ioc_order).unwrap_err(), MatchingError::NotAcceptedInAuction);
+
+    engine.uncross("AAPL").unwrap();
+    assert_eq!(engine.uncross("AAPL").unwrap_err(), MatchingError::NotInAuction);
+    assert_eq!(engine.uncross("MSFT").unwrap_err(), MatchingError::SymbolNotFound);
+}
+
+#[test]
+fn test_halted_symbol_queues_orders_and_reopens_with_an_uncross() {
+    let mut engine = MatchingEngine::with_config(MatchingEngineConfig {
+        halted_orders: HaltedOrders::Queue,
+        ..Default::default()
+    });
+    engine.add_symbol("AAPL");
+    engine.place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 101, 10, 1)).unwrap();
+    engine.place_order(Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 99, 5, 2)).unwrap();
+    engine.halt_symbol("AAPL").unwrap();
+
+    // Queued without matching, though it crosses.
+    let result = engine.place_order(Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 102, 6, 3)).unwrap();
+    assert!(result.trades.is_empty());
+    let buy_id = result.remaining_order.unwrap().read().id;
+    let result = engine.place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Market, 0, 4, 4)).unwrap();
+    let market_id = result.remaining_order.unwrap().read().id;
+    assert_eq!(engine.get_order_status("AAPL", buy_id).unwrap().status, OrderStatus::New);
+    assert!(engine.validate_books().is_empty());
+
+    let mut ioc_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 105, 5, 5);
+    ioc_order.time_in_force = TimeInForce::IOC;
+    assert_eq!(engine.place_order(ioc_order).unwrap_err(), MatchingError::SymbolHalted);
+    assert_eq!(engine.indicative_price("AAPL"), Some(101));
+
+    engine.resume_trading("AAPL").unwrap();
+    assert_eq!(engine.trading_state("AAPL"), Some(TradingState::Continuous));
+    assert_eq!(engine.get_order_status("AAPL", buy_id).unwrap().status, OrderStatus::Filled);
+    assert_eq!(engine.get_order_status("AAPL", market_id).unwrap().status, OrderStatus::Filled);
+    let order_book = engine.order_books.get("AAPL").unwrap();
+    assert_eq!(order_book.last_trade_price, Some(101));
+    assert_eq!((order_book.get_best_bid_price(), order_book.get_best_ask_price()), (Some(99), Some(101)));
+    assert_eq!(order_book.sell_levels[&101].total_volume, 8);
+
+    // A queue that does not cross reopens as it is.
+    engine.halt_symbol("AAPL").unwrap();
+    engine.place_order(Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 3, 6)).unwrap();
+    engine.resume_trading("AAPL").unwrap();
+    assert_eq!(engine.trading_state("AAPL"), Some(TradingState::Continuous));
+    assert_eq!(engine.order_books.get("AAPL").unwrap().get_best_bid_price(), Some(100));
+
+    // Setting the state directly reopens through the same uncross.
+    engine.halt_symbol("AAPL").unwrap();
+    let buy_id = engine
+        .place_order(Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 110, 2, 7))
+        .unwrap()
+        .remaining_order
+        .unwrap()
+        .read()
+        .id;
+    engine.set_trading_state("AAPL", TradingState::Continuous).unwrap();
+    assert_eq!(engine.trading_state("AAPL"), Some(TradingState::Continuous));
+    assert_eq!(engine.get_order_status("AAPL", buy_id).unwrap().status, OrderStatus::Filled);
+    assert!(engine.validate_books().is_empty());
+    assert_eq!(engine.order_books.get("AAPL").unwrap().sell_levels[&101].total_volume, 6);
+}
+
+#[test]
+fn test_replenished_iceberg_cedes_priority() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+
+    let mut iceberg_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Iceberg, 100, 30, 1);
+    iceberg_order.display_quantity = Some(10);
+    let iceberg_id = engine.place_order(iceberg_order).unwrap().remaining_order.unwrap().read().id;
+
+    let regular_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100, 10, 2);
+    let regular_id = engine.place_order(regular_order).unwrap().remaining_order.unwrap().read().id;
+
+    let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 4, 3);
+    let result = engine.place_order(buy_order).unwrap();
+    assert_eq!(result.trades[0].sell_order_id, iceberg_id);
+
+    let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 6, 3);
+    let result = engine.place_order(buy_order).unwrap();
+    assert_eq!(result.trades.len(), 1);
+    assert_eq!(result.trades[0].sell_order_id, iceberg_id);
+
+    {
+        let level = engine.order_books.get("AAPL").unwrap().sell_levels.get(&100).unwrap();
+        let queue: Vec<u64> = level.orders.iter().map(|o| o.read().id).collect();
+        assert_eq!(queue, vec![regular_id, iceberg_id]);
+        assert_eq!(level.visible_volume, 20);
+        assert_eq!(level.total_volume, 30);
+    }
+
+    let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 10, 3);
+    let result = engine.place_order(buy_order).unwrap();
+    assert_eq!(result.trades.len(), 1);
+    assert_eq!(result.trades[0].sell_order_id, regular_id);
+}
+
+#[test]
+fn test_two_icebergs_rotate_with_regular_order() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    let place_iceberg = |engine: &mut MatchingEngine, quantity, user_id| {
+        let mut order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Iceberg, 100, quantity, user_id);
+        order.display_quantity = Some(10);
+        engine.place_order(order).unwrap().remaining_order.unwrap().read().id
+    };
+    let first = place_iceberg(&mut engine, 25, 1);
+    let second = place_iceberg(&mut engine, 20, 2);
+    let regular = engine
+        .place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100, 10, 3))
+        .unwrap()
+        .remaining_order
+        .unwrap()
+        .read()
+        .id;
+    let queue = |engine: &MatchingEngine| -> Vec<u64> {
+        let level = &engine.order_books["AAPL"].sell_levels[&100];
+        level.orders.iter().map(|o| o.read().id).collect()
+    };
+    let fills = |engine: &mut MatchingEngine, quantity| -> Vec<(u64, u32)> {
+        let buy = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, quantity, 9);
+        let result = engine.place_order(buy).unwrap();
+        result.trades.iter().map(|t| (t.sell_order_id, t.quantity)).collect()
+    };
+
+    // Each iceberg only gives up its visible clip before the next order.
+    assert_eq!(fills(&mut engine, 15), vec![(first, 10), (second, 5)]);
+    assert_eq!(queue(&engine), vec![second, regular, first]);
+    assert_eq!(fills(&mut engine, 10), vec![(second, 5), (regular, 5)]);
+    assert_eq!(queue(&engine), vec![regular, first, second]);
+
+    // One sweep through the level: the rotated clips trade in their new order.
+    assert_eq!(fills(&mut engine, 30), vec![(regular, 5), (first, 10), (second, 10), (first, 5)]);
+    assert!(!engine.order_books["AAPL"].sell_levels.contains_key(&100));
+}
+
+#[test]
+fn test_static_and_dynamic_price_bands() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    engine
+        .set_price_bands(
+            "AAPL",
+            PriceBands {
+                reference_price: Some(10_000),
+                static_band_bps: Some(1_000),
+                dynamic_band_bps: Some(200),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    let order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 11_500, 1, 1);
+    assert_eq!(
+        engine.place_order(order).unwrap_err(),
+        MatchingError::PriceOutOfBand { limit: 11_000, reference: 10_000 }
+    );
+
+    let sell_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 10_500, 1, 1);
+    engine.place_order(sell_order).unwrap();
+    let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 10_500, 1, 2);
+    engine.place_order(buy_order).unwrap();
+
+    let order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 10_200, 1, 1);
+    assert_eq!(
+        engine.place_order(order).unwrap_err(),
+        MatchingError::PriceOutOfBand { limit: 10_290, reference: 10_500 }
+    );
+
+    let order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 10_300, 1, 1);
+    assert!(engine.place_order(order).is_ok());
+}
+
+#[test]
+fn test_circuit_breaker_halts_symbol() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+
+    let sell_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 10_000, 1, 1);
+    engine.place_order(sell_order).unwrap();
+    let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 10_000, 1, 2);
+    engine.place_order(buy_order).unwrap();
+
+    engine
+        .set_price_bands(
+            "AAPL",
+            PriceBands { circuit_breaker_bps: Some(500), ..Default::default() },
+        )
+        .unwrap();
+
+    for (price, quantity) in [(10_100, 5), (11_000, 5)] {
+        let sell_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, price, quantity, 1);
+        engine.place_order(sell_order).unwrap();
+    }
+
+    let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Market, 0, 8, 2);
+    assert_eq!(
+        engine.place_order(buy_order).unwrap_err(),
+        MatchingError::CircuitBreakerTripped { price: 11_000, reference: 10_000 }
+    );
+    assert_eq!(engine.trading_state("AAPL"), Some(TradingState::Halted));
+
+    let order_book = engine.order_books.get("AAPL").unwrap();
+    assert_eq!(order_book.get_best_ask_price(), Some(10_100));
+    assert_eq!(order_book.last_trade_price, Some(10_000));
+
+    let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 10_100, 1, 2);
+    assert_eq!(engine.place_order(buy_order).unwrap_err(), MatchingError::SymbolHalted);
+
+    engine.resume_trading("AAPL").unwrap();
+    let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Market, 0, 5, 2);
+    let result = engine.place_order(buy_order).unwrap();
+    assert_eq!(result.trades.len(), 1);
+    assert_eq!(result.trades[0].price, 10_100);
+}
+
+#[test]
+fn test_symbol_lifecycle() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("MSFT");
+    engine.add_symbol("AAPL");
+
+    engine.place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100, 5, 1)).unwrap();
+    engine.place_order(Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 2, 2)).unwrap();
+    engine.place_order(Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 90, 4, 2)).unwrap();
+    let mut stop = Order::new("AAPL".to_string(), Side::Sell, OrderType::StopLimit, 80, 5, 3);
+    stop.stop_price = Some(85);
+    engine.place_order(stop).unwrap();
+
+    let symbols = engine.list_symbols();
+    assert_eq!(
+        symbols.iter().map(|info| info.symbol.as_str()).collect::<Vec<_>>(),
+        vec!["AAPL", "MSFT"]
+    );
+    assert_eq!(symbols[0].trading_state, TradingState::Continuous);
+    assert_eq!((symbols[0].resting_orders, symbols[0].stop_orders), (2, 1));
+    assert_eq!(symbols[0].last_trade_price, Some(100));
+    assert_eq!(symbols[1].last_trade_price, None);
+
+    engine.halt_symbol("AAPL").unwrap();
+    assert_eq!(engine.list_symbols()[0].trading_state, TradingState::Halted);
+    let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 1, 2);
+    assert_eq!(engine.place_order(buy_order).unwrap_err(), MatchingError::SymbolHalted);
+    assert_eq!(engine.halt_symbol("IBM").unwrap_err(), MatchingError::SymbolNotFound);
+
+    engine.resume_symbol("AAPL").unwrap();
+    let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 1, 2);
+    assert_eq!(engine.place_order(buy_order).unwrap().trades.len(), 1);
+
+    let removed = engine.remove_symbol("AAPL").unwrap();
+    let removed_ids: Vec<u64> = removed.iter().map(|order| order.read().id).collect();
+    assert_eq!(removed_ids, vec![1, 3, 4]);
+    assert!(removed.iter().all(|order| order.read().status == OrderStatus::Canceled));
+
+    assert_eq!(engine.list_symbols().len(), 1);
+    assert_eq!(engine.get_order_status("AAPL", 3).unwrap().status, OrderStatus::Canceled);
+    let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 1, 2);
+    assert_eq!(engine.place_order(buy_order).unwrap_err(), MatchingError::SymbolNotFound);
+    assert_eq!(engine.remove_symbol("AAPL").unwrap_err(), MatchingError::SymbolNotFound);
+}
+
+fn stop(side: Side, order_type: OrderType, price: Price, stop_price: Price, quantity: u32, user_id: u64) -> Order {
+    let mut order = Order::new("AAPL".to_string(), side, order_type, price, quantity, user_id);
+    order.stop_price = Some(stop_price);
+    order
+}
+
+#[test]
+fn test_triggered_stop_market_fills_immediately() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+
+    engine.place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100, 5, 1)).unwrap();
+    engine.place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 102, 10, 1)).unwrap();
+    let stop_id = engine
+        .place_order(stop(Side::Buy, OrderType::StopMarket, 0, 100, 4, 2))
+        .unwrap()
+        .remaining_order
+        .unwrap()
+        .read()
+        .id;
+
+    let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 5, 3);
+    let result = engine.place_order(buy_order).unwrap();
+
+    assert_eq!(result.triggered_stops.len(), 1);
+    assert_eq!(
+        result.trades.iter().map(|t| (t.buy_order_id, t.price, t.quantity)).collect::<Vec<_>>(),
+        vec![(4, 100, 5), (stop_id, 102, 4)]
+    );
+    let stop_status = engine.get_order_status("AAPL", stop_id).unwrap();
+    assert_eq!((stop_status.status, stop_status.filled_quantity), (OrderStatus::Filled, 4));
+
+    let book = engine.order_books.get("AAPL").unwrap();
+    assert_eq!(book.get_best_bid_price(), None);
+    assert_eq!(book.last_trade_price, Some(102));
+}
+
+#[test]
+fn test_chained_stops_fire_in_one_call() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+
+    for (price, quantity) in [(100, 5), (98, 3), (96, 3), (94, 2)] {
+        engine.place_order(Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, price, quantity, 1)).unwrap();
+    }
+    engine.place_order(stop(Side::Sell, OrderType::StopMarket, 0, 100, 3, 2)).unwrap();
+    engine.place_order(stop(Side::Sell, OrderType::StopLimit, 96, 98, 3, 3)).unwrap();
+    engine.place_order(stop(Side::Sell, OrderType::StopLimit, 90, 96, 4, 4)).unwrap();
+
+    let sell_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100, 5, 5);
+    let result = engine.place_order(sell_order).unwrap();
+
+    assert_eq!(result.triggered_stops.len(), 3);
+    assert_eq!(
+        result.trades.iter().map(|t| (t.price, t.quantity)).collect::<Vec<_>>(),
+        vec![(100, 5), (98, 3), (96, 3), (94, 2)]
+    );
+
+    let book = engine.order_books.get("AAPL").unwrap();
+    assert_eq!(book.stop_order_count(), 0);
+    assert_eq!(book.get_best_bid_price(), None);
+    assert_eq!(book.get_best_ask_price(), Some(90));
+    assert_eq!(book.last_trade_price, Some(94));
+    let last_stop = engine.get_order_status("AAPL", 7).unwrap();
+    assert_eq!((last_stop.status, last_stop.remaining_quantity), (OrderStatus::PartiallyFilled, 2));
+}
+
+#[test]
+fn test_gtd_orders_expire_with_expired_status() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+
+    let mut gtd_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 10, 1);
+    gtd_order.time_in_force = TimeInForce::GTD;
+    let expire_time = gtd_order.timestamp + 1_000_000_000;
+    gtd_order.expiration_time = expire_time;
+    let gtd_id = engine.place_order(gtd_order).unwrap().remaining_order.unwrap().read().id;
+
+    let gtc_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 99, 10, 1);
+    engine.place_order(gtc_order).unwrap();
+
+    assert!(engine.process_expired_orders_at(expire_time - 1).unwrap().is_empty());
+
+    let expired = engine.process_expired_orders_at(expire_time).unwrap();
+    assert_eq!(expired.len(), 1);
+    assert_eq!(expired[0].read().id, gtd_id);
+    assert_eq!(expired[0].read().status, OrderStatus::Expired);
+
+    let order_book = engine.order_books.get("AAPL").unwrap();
+    assert!(order_book.get_order(gtd_id).is_none());
+    assert_eq!(order_book.get_best_bid_price(), Some(99));
+}
+
+#[test]
+fn test_gtd_order_with_past_expiry_is_rejected() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+
+    let mut order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 10, 1);
+    order.time_in_force = TimeInForce::GTD;
+    order.expiration_time = order.timestamp;
+
+    assert_eq!(engine.place_order(order).unwrap_err(), MatchingError::InvalidExpiry);
+    assert!(engine.order_books.get("AAPL").unwrap().get_best_bid_price().is_none());
+}
+
+#[test]
+fn test_day_orders_expire_at_configured_session_close() {
+    let mut engine = MatchingEngine::with_config(MatchingEngineConfig {
+        session_close_ns: Some(0),
+        ..Default::default()
+    });
+    engine.add_symbol("AAPL");
+
+    let mut day_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100, 10, 1);
+    day_order.time_in_force = TimeInForce::Day;
+    let placed_at = day_order.timestamp;
+    engine.place_order(day_order).unwrap();
+
+    let ns_per_day = 86_400_000_000_000i64;
+    let next_close = (placed_at / ns_per_day + 1) * ns_per_day;
+    assert!(engine.process_expired_orders_at(next_close - 1).unwrap().is_empty());
+    assert_eq!(engine.process_expired_orders_at(next_close).unwrap().len(), 1);
+}
+
+#[test]
+fn test_expiry_follows_the_engine_clock_not_the_senders() {
+    let clock = Arc::new(ManualClock::new(1_000_000));
+    let mut engine = MatchingEngine::with_clock(MatchingEngineConfig::default(), clock.clone());
+    engine.add_symbol("AAPL");
+
+    // Both orders carry the sender's wall-clock timestamp, decades ahead
+    // of the engine's clock.
+    let mut gtd_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 10, 1);
+    gtd_order.time_in_force = TimeInForce::GTD;
+    gtd_order.expiration_time = 2_000_000;
+    let gtd_id = engine.place_order(gtd_order).unwrap().remaining_order.unwrap().read().id;
+    let mut day_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 110, 10, 1);
+    day_order.time_in_force = TimeInForce::Day;
+    let day_id = engine.place_order(day_order).unwrap().remaining_order.unwrap().read().id;
+
+    clock.set(2_000_000);
+    let expired: Vec<u64> = engine.process_expired_orders().unwrap().iter().map(|o| o.read().id).collect();
+    assert_eq!(expired, vec![gtd_id]);
+
+    let ns_per_day = 86_400_000_000_000i64;
+    clock.set(ns_per_day - 1);
+    assert!(engine.process_expired_orders().unwrap().is_empty());
+    clock.set(3 * ns_per_day);
+    let expired: Vec<u64> = engine.process_expired_orders().unwrap().iter().map(|o| o.read().id).collect();
+    assert_eq!(expired, vec![day_id]);
+
+    // An expiry already past by the engine's clock is refused.
+    let mut late = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 10, 1);
+    late.time_in_force = TimeInForce::GTD;
+    late.expiration_time = 3 * ns_per_day;
+    assert_eq!(engine.place_order(late).unwrap_err(), MatchingError::InvalidExpiry);
+}
+
+#[test]
+fn test_order_status_query() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    engine.add_symbol("MSFT");
+
+    for (price, quantity) in [(100, 4), (102, 4), (105, 10)] {
+        let sell_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, price, quantity, 1);
+        engine.place_order(sell_order).unwrap();
+    }
+
+    let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 102, 8, 2);
+    let buy_id = engine.place_order(buy_order).unwrap().filled_orders.last().unwrap().read().id;
+
+    let filled = engine.get_order_status("AAPL", buy_id).unwrap();
+    assert_eq!(filled.status, OrderStatus::Filled);
+    assert_eq!((filled.filled_quantity, filled.remaining_quantity), (8, 0));
+    assert_eq!(filled.average_price, Some(101));
+
+    let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 105, 3, 2);
+    engine.place_order(buy_order).unwrap();
+    let partial = engine.get_order_status("AAPL", 3).unwrap();
+    assert_eq!(partial.status, OrderStatus::PartiallyFilled);
+    assert_eq!((partial.filled_quantity, partial.remaining_quantity), (3, 7));
+    assert_eq!(partial.average_price, Some(105));
+    assert!(partial.last_update >= filled.last_update);
+
+    let resting_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 90, 5, 3);
+    let resting_id = engine.place_order(resting_order).unwrap().remaining_order.unwrap().read().id;
+    let resting = engine.get_order_status("AAPL", resting_id).unwrap();
+    assert_eq!(resting.status, OrderStatus::New);
+    assert_eq!((resting.filled_quantity, resting.remaining_quantity), (0, 5));
+    assert_eq!(resting.average_price, None);
+
+    let mut stop_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::StopLimit, 80, 5, 3);
+    stop_order.stop_price = Some(85);
+    let stop_id = engine.place_order(stop_order).unwrap().remaining_order.unwrap().read().id;
+    let stop = engine.get_order_status("AAPL", stop_id).unwrap();
+    assert_eq!(stop.status, OrderStatus::New);
+    assert_eq!(stop.remaining_quantity, 5);
+
+    engine.cancel_order("AAPL", resting_id);
+    let canceled = engine.get_order_status("AAPL", resting_id).unwrap();
+    assert_eq!((canceled.status, canceled.remaining_quantity), (OrderStatus::Canceled, 0));
+
+    assert_eq!(engine.get_order_status("AAPL", 999), None);
+    assert_eq!(engine.get_order_status("MSFT", buy_id), None);
+}
+
+#[test]
+fn test_order_history_drops_oldest_terminal_orders() {
+    let mut engine = MatchingEngine::with_config(MatchingEngineConfig {
+        order_history_limit: 8,
+        bustable_trades: 1,
+        ..MatchingEngineConfig::default()
+    });
+    engine.add_symbol("AAPL");
+
+    let place = |engine: &mut MatchingEngine, side, price, quantity| {
+        let order = Order::new("AAPL".to_string(), side, OrderType::Limit, price, quantity, 1);
+        let result = engine.place_order(order).unwrap();
+        let id = result
+            .remaining_order
+            .as_ref()
+            .or(result.filled_orders.last())
+            .unwrap()
+            .read()
+            .id;
+        (id, result)
+    };
+    let resting: Vec<u64> = (0..4).map(|i| place(&mut engine, Side::Sell, 110 + i, 5).0).collect();
+    let mut canceled = Vec::new();
+    for _ in 0..10 {
+        let (id, _) = place(&mut engine, Side::Buy, 90, 5);
+        engine.cancel_order("AAPL", id).unwrap();
+        canceled.push(id);
+    }
+
+    // A trade's orders stay while the trade can still be busted.
+    let (taker, result) = place(&mut engine, Side::Buy, 110, 5);
+    let trade_id = result.trades[0].id;
+    for _ in 0..3 {
+        let (id, _) = place(&mut engine, Side::Buy, 90, 5);
+        engine.cancel_order("AAPL", id).unwrap();
+        canceled.push(id);
+    }
+
+    for id in &canceled[..6] {
+        assert_eq!(engine.get_order_status("AAPL", *id), None, "order {}", id);
+    }
+    assert_eq!(
+        engine.get_order_status("AAPL", *canceled.last().unwrap()).unwrap().status,
+        OrderStatus::Canceled
+    );
+    for id in &resting[1..] {
+        assert_eq!(engine.get_order_status("AAPL", *id).unwrap().status, OrderStatus::New);
+    }
+    assert_eq!(engine.get_order_status("AAPL", taker).unwrap().status, OrderStatus::Filled);
+    assert_eq!(engine.get_order_status("AAPL", resting[0]).unwrap().status, OrderStatus::Filled);
+    assert!(engine.bust_trade("AAPL", trade_id).is_ok());
+    assert_eq!(engine.order_books["AAPL"].sell_levels[&110].total_volume, 5);
+}
+
+#[test]
+fn test_market_orders_without_liquidity_end_terminal() {
+    let mut engine = MatchingEngine::with_config(MatchingEngineConfig {
+        order_history_limit: 10,
+        ..MatchingEngineConfig::default()
+    });
+    engine.add_symbol("AAPL");
+    let market = |side| Order::new("AAPL".to_string(), side, OrderType::Market, 0, 10, 1);
+
+    let first_id = engine.create_snapshot().next_order_id();
+    assert!(matches!(
+        engine.place_order(market(Side::Buy)),
+        Err(MatchingError::NoLiquidity)
+    ));
+    assert_eq!(
+        engine.get_order_status("AAPL", first_id).unwrap().status,
+        OrderStatus::Rejected
+    );
+    for side in [Side::Buy, Side::Sell].into_iter().cycle().take(1000) {
+        assert!(engine.place_order(market(side)).is_err());
+    }
+    // Rejected orders are terminal, so the history trims them.
+    assert_eq!(engine.get_order_status("AAPL", first_id), None);
+
+    // A remainder left without liquidity is cancelled.
+    engine
+        .place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100, 5, 2))
+        .unwrap();
+    let taker_id = engine.create_snapshot().next_order_id();
+    let _ = engine.place_order(market(Side::Buy));
+    let taker = engine.get_order_status("AAPL", taker_id).unwrap();
+    assert_eq!((taker.status, taker.filled_quantity), (OrderStatus::Canceled, 5));
+}
+
+#[test]
+fn test_market_order_running_out_of_liquidity_keeps_its_trades_and_stops() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    let events = engine.subscribe("AAPL");
+    engine
+        .place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100, 5, 1))
+        .unwrap();
+    let mut stop = Order::new("AAPL".to_string(), Side::Buy, OrderType::StopLimit, 105, 3, 2);
+    stop.stop_price = Some(100);
+    let stop_id = engine.place_order(stop).unwrap().remaining_order.unwrap().read().id;
+
+    let result = engine
+        .place_order(Order::new("AAPL".to_string(), Side::Buy, OrderType::Market, 0, 10, 3))
+        .unwrap();
+    assert_eq!(result.trades.len(), 1);
+    assert_eq!(result.cancel_reason, Some(CancelReason::NoLiquidity));
+    let taker = result.filled_orders.last().unwrap().read().clone();
+    assert_eq!((taker.status, taker.filled_quantity), (OrderStatus::Canceled, 5));
+
+    // The stop the trade triggered is on the book as a limit order.
+    assert_eq!(result.triggered_stops.len(), 1);
+    let stop = engine.get_order_status("AAPL", stop_id).unwrap();
+    assert_eq!((stop.status, stop.remaining_quantity), (OrderStatus::New, 3));
+    assert_eq!(engine.order_books["AAPL"].get_best_bid_price(), Some(105));
+    assert_eq!(engine.order_books["AAPL"].get_order(stop_id).unwrap().read().order_type, OrderType::Limit);
+
+    // The trade was published and recorded like any other.
+    let trade_id = result.trades[0].id;
+    assert!(std::iter::from_fn(|| events.try_recv().ok())
+        .any(|event| matches!(event, EngineEvent::Trade { trade, .. } if trade.id == trade_id)));
+    assert!(engine.bust_trade("AAPL", trade_id).is_ok());
+}
+
+#[test]
+fn test_trade_timestamps_strictly_increase_within_a_command() {
+    const START: i64 = 1_704_110_400_123_456_789;
+    let clock = Arc::new(ManualClock::new(START));
+    let mut engine = MatchingEngine::with_clock(MatchingEngineConfig::default(), clock.clone());
+    engine.add_symbol("AAPL");
+
+    for price in [100, 101, 102] {
+        let sell_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, price, 2, 1);
+        engine.place_order(sell_order).unwrap();
+    }
+
+    let mut buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 102, 6, 2);
+    buy_order.timestamp = START - 5_000;
+    let result = engine.place_order(buy_order).unwrap();
+    let timestamps: Vec<i64> = result.trades.iter().map(|trade| trade.timestamp).collect();
+    assert_eq!(timestamps, vec![START, START + 1, START + 2]);
+
+    let taker = result.filled_orders.last().unwrap().read().clone();
+    assert_eq!((taker.timestamp, taker.received_at), (START - 5_000, START));
+
+    // A clock that has not moved on still cannot reuse an earlier timestamp.
+    let sell_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 99, 1, 1);
+    engine.place_order(sell_order).unwrap();
+    let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 99, 1, 2);
+    let result = engine.place_order(buy_order).unwrap();
+    assert_eq!(result.trades[0].timestamp, START + 3);
+
+    clock.advance(1_000);
+    let sell_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 99, 1, 1);
+    engine.place_order(sell_order).unwrap();
+    let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 99, 1, 2);
+    let result = engine.place_order(buy_order).unwrap();
+    assert_eq!(result.trades[0].timestamp, START + 1_000);
+}
+
+#[test]
+fn test_order_type_rules() {
+    const OPEN: i64 = 1_704_110_400_000_000_000;
+    const MINUTE: i64 = 60_000_000_000;
+    let clock = Arc::new(ManualClock::new(OPEN));
+    let mut engine = MatchingEngine::with_clock(MatchingEngineConfig::default(), clock.clone());
+    engine.add_symbol("AAPL");
+    engine
+        .place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100, 50, 1))
+        .unwrap();
+
+    let rules = OrderTypeRules {
+        denied_order_types: vec![OrderType::Iceberg],
+        denied_time_in_force: vec![TimeInForce::FOK],
+        restrictions: vec![
+            OrderTypeRestriction {
+                order_type: OrderType::Market,
+                window: RestrictionWindow::Between { start: OPEN, end: OPEN + MINUTE },
+            },
+            OrderTypeRestriction {
+                order_type: OrderType::Market,
+                window: RestrictionWindow::TradingState(TradingState::Auction),
+            },
+        ],
+        ..Default::default()
+    };
+    engine.set_order_type_rules("AAPL", rules).unwrap();
+
+    let mut iceberg = Order::new("AAPL".to_string(), Side::Buy, OrderType::Iceberg, 90, 20, 2);
+    iceberg.display_quantity = Some(5);
+    assert_eq!(
+        engine.place_order(iceberg).unwrap_err(),
+        MatchingError::OrderTypeNotAllowed(OrderType::Iceberg)
+    );
+    let mut fok = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 5, 2);
+    fok.time_in_force = TimeInForce::FOK;
+    assert_eq!(
+        engine.place_order(fok).unwrap_err(),
+        MatchingError::TimeInForceNotAllowed(TimeInForce::FOK)
+    );
+    assert_eq!(engine.get_order_status("AAPL", 2).unwrap().status, OrderStatus::Rejected);
+
+    let market = || Order::new("AAPL".to_string(), Side::Buy, OrderType::Market, 0, 5, 2);
+    assert_eq!(
+        engine.place_order(market()).unwrap_err(),
+        MatchingError::OrderTypeNotAllowed(OrderType::Market)
+    );
+    let info = engine.list_symbols().remove(0);
+    assert_eq!(
+        info.order_types,
+        vec![OrderType::Limit, OrderType::StopLimit, OrderType::StopMarket]
+    );
+    assert!(!info.time_in_force.contains(&TimeInForce::FOK));
+
+    clock.advance(MINUTE);
+    assert_eq!(engine.place_order(market()).unwrap().trades.len(), 1);
+    assert!(engine.list_symbols()[0].order_types.contains(&OrderType::Market));
+
+    engine.set_trading_state("AAPL", TradingState::Auction).unwrap();
+    assert_eq!(
+        engine.place_order(market()).unwrap_err(),
+        MatchingError::OrderTypeNotAllowed(OrderType::Market)
+    );
+    engine.set_trading_state("AAPL", TradingState::Continuous).unwrap();
+
+    engine.set_order_type_rules("AAPL", OrderTypeRules::default()).unwrap();
+    let mut iceberg = Order::new("AAPL".to_string(), Side::Buy, OrderType::Iceberg, 90, 20, 2);
+    iceberg.display_quantity = Some(5);
+    assert!(engine.place_order(iceberg).is_ok());
+    assert_eq!(engine.order_type_rules("AAPL"), Some(OrderTypeRules::default()));
+    assert_eq!(
+        engine.set_order_type_rules("MSFT", OrderTypeRules::default()),
+        Err(MatchingError::SymbolNotFound)
+    );
+}
+
+#[test]
+fn test_risk_limit_boundaries() {
+    let mut engine = MatchingEngine::with_config(MatchingEngineConfig {
+        risk_limits: RiskLimits {
+            max_order_quantity: Some(100),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    engine.add_symbol("AAPL");
+    engine.set_user_risk_limits(
+        2,
+        RiskLimits {
+            max_order_notional: Some(1_000),
+            ..Default::default()
+        },
+    );
+    let price = 10 * PRICE_SCALE_FACTOR;
+    let order = |user_id, quantity| Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, price, quantity, user_id);
+
+    assert_eq!(
+        engine.place_order(order(1, 101)).unwrap_err(),
+        MatchingError::OrderQuantityLimitExceeded { quantity: 101, limit: 100 }
+    );
+    let accepted = engine.place_order(order(1, 100)).unwrap();
+    assert_eq!(accepted.remaining_order.unwrap().read().id, 1);
+
+    assert_eq!(
+        engine.place_order(order(2, 101)).unwrap_err(),
+        MatchingError::OrderNotionalLimitExceeded { limit: 1_000 }
+    );
+    let accepted = engine.place_order(order(2, 100)).unwrap();
+    assert_eq!(accepted.remaining_order.unwrap().read().id, 2);
+    assert_eq!(engine.get_order_status("AAPL", 3), None);
+
+    engine.clear_user_risk_limits(2);
+    assert_eq!(engine.risk_limits(2).max_order_quantity, Some(100));
+    assert!(engine.place_order(order(2, 100)).is_ok());
+}
+
+#[test]
+fn test_open_order_cap_freed_by_fill() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    engine.add_symbol("MSFT");
+    engine.set_user_risk_limits(
+        1,
+        RiskLimits {
+            max_open_orders: Some(3),
+            ..Default::default()
+        },
+    );
+
+    engine
+        .place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100, 5, 1))
+        .unwrap();
+    engine
+        .place_order(Order::new("MSFT".to_string(), Side::Sell, OrderType::Limit, 200, 5, 1))
+        .unwrap();
+    let mut stop = Order::new("AAPL".to_string(), Side::Sell, OrderType::StopLimit, 80, 5, 1);
+    stop.stop_price = Some(85);
+    engine.place_order(stop).unwrap();
+    assert_eq!(engine.open_order_count(1), 3);
+
+    let next = || Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 101, 5, 1);
+    assert_eq!(
+        engine.place_order(next()).unwrap_err(),
+        MatchingError::OpenOrderLimitExceeded { limit: 3 }
+    );
+
+    let partial = engine
+        .place_order(Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 3, 2))
+        .unwrap();
+    assert_eq!(partial.trades.len(), 1);
+    assert_eq!(engine.open_order_count(1), 3);
+
+    engine
+        .place_order(Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 2, 2))
+        .unwrap();
+    assert_eq!(engine.open_order_count(1), 2);
+    let accepted = engine.place_order(next()).unwrap();
+    assert_eq!(accepted.remaining_order.unwrap().read().id, 6);
+    assert_eq!(engine.open_order_count(1), 3);
+
+    engine.cancel_all_for_user(1);
+    assert_eq!(engine.open_order_count(1), 0);
+}
+
+#[test]
+fn test_mark_price_stop_fires_without_trades() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+
+    let mut mark_stop = stop(Side::Buy, OrderType::StopLimit, 95, 100, 4, 1);
+    mark_stop.trigger_source = Some(TriggerSource::MarkPrice);
+    engine.place_order(mark_stop).unwrap();
+    engine.place_order(stop(Side::Buy, OrderType::StopLimit, 95, 100, 2, 2)).unwrap();
+
+    let result = engine.update_mark_price("AAPL", 99).unwrap();
+    assert!(result.triggered_stops.is_empty());
+
+    let result = engine.update_mark_price("AAPL", 101).unwrap();
+    assert_eq!(result.triggered_stops.len(), 1);
+    assert_eq!(result.triggered_stops[0].read().id, 1);
+    assert!(result.trades.is_empty());
+
+    let book = engine.order_books.get("AAPL").unwrap();
+    assert_eq!(book.last_trade_price, None);
+    assert_eq!(book.mark_price, Some(101));
+    assert_eq!(book.stop_order_count(), 1);
+    assert_eq!(book.get_best_bid_price(), Some(95));
+    assert_eq!(engine.get_order_status("AAPL", 1).unwrap().status, OrderStatus::New);
+
+    engine.place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 102, 5, 3)).unwrap();
+    let mut mark_stop = stop(Side::Buy, OrderType::StopMarket, 0, 100, 3, 4);
+    mark_stop.trigger_source = Some(TriggerSource::MarkPrice);
+    let result = engine.place_order(mark_stop).unwrap();
+    // The stop's own fill prints the first trade, which fires the
+    // last-trade stop as well.
+    assert_eq!(
+        result.triggered_stops.iter().map(|o| o.read().id).collect::<Vec<_>>(),
+        vec![4, 2]
+    );
+    assert_eq!(
+        result.trades.iter().map(|t| (t.buy_order_id, t.price, t.quantity)).collect::<Vec<_>>(),
+        vec![(4, 102, 3)]
+    );
+}
+
+#[test]
+fn test_triggered_marketable_stop_limit_trades_instead_of_resting() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+
+    engine.place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 101, 3, 1)).unwrap();
+    engine.place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 104, 5, 1)).unwrap();
+    let mut mark_stop = stop(Side::Buy, OrderType::StopLimit, 102, 100, 5, 2);
+    mark_stop.trigger_source = Some(TriggerSource::MarkPrice);
+    let stop_id = engine.place_order(mark_stop).unwrap().remaining_order.unwrap().read().id;
+
+    // The limit of 102 crosses the 101 ask once the stop fires, so it
+    // lifts the ask and rests only what is left, below the next ask.
+    let result = engine.update_mark_price("AAPL", 100).unwrap();
+    assert_eq!(
+        result.trades.iter().map(|t| (t.buy_order_id, t.price, t.quantity)).collect::<Vec<_>>(),
+        vec![(stop_id, 101, 3)]
+    );
+    let book = engine.order_books.get("AAPL").unwrap();
+    assert_eq!((book.get_best_bid_price(), book.get_best_ask_price()), (Some(102), Some(104)));
+    let status = engine.get_order_status("AAPL", stop_id).unwrap();
+    assert_eq!((status.status, status.remaining_quantity), (OrderStatus::PartiallyFilled, 2));
+}
+
+#[test]
+fn test_stop_trigger_source_per_symbol() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    engine.set_stop_trigger_source("AAPL", TriggerSource::IndexPrice).unwrap();
+
+    engine.place_order(stop(Side::Sell, OrderType::StopLimit, 105, 100, 3, 1)).unwrap();
+    let mut last_trade_stop = stop(Side::Sell, OrderType::StopLimit, 105, 100, 2, 2);
+    last_trade_stop.trigger_source = Some(TriggerSource::LastTrade);
+    engine.place_order(last_trade_stop).unwrap();
+
+    assert!(engine.update_mark_price("AAPL", 90).unwrap().triggered_stops.is_empty());
+
+    engine.set_trading_state("AAPL", TradingState::Halted).unwrap();
+    assert!(engine.update_index_price("AAPL", 100).unwrap().triggered_stops.is_empty());
+    assert_eq!(engine.order_books.get("AAPL").unwrap().index_price, Some(100));
+    engine.set_trading_state("AAPL", TradingState::Continuous).unwrap();
+
+    let result = engine.update_index_price("AAPL", 99).unwrap();
+    assert_eq!(
+        result.triggered_stops.iter().map(|o| o.read().id).collect::<Vec<_>>(),
+        vec![1]
+    );
+    assert_eq!(engine.order_books.get("AAPL").unwrap().stop_order_count(), 1);
+    assert_eq!(
+        engine.update_mark_price("UNKNOWN", 99).unwrap_err(),
+        MatchingError::SymbolNotFound
+    );
+}
+
+#[test]
+fn test_submit_quote_replaces_previous_quote() {
+    let mut engine = MatchingEngine::new();
+    engine.enable_journal();
+    engine.add_symbol("AAPL");
+
+    let first = engine.submit_quote("AAPL", 7, 99, 10, 101, 10).unwrap();
+    assert_eq!((first.bid_order_id, first.ask_order_id), (1, 2));
+    assert!(first.replaced_order_ids.is_empty());
+    assert_eq!(first.trades().count(), 0);
+
+    engine.place_order(Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 101, 4, 8)).unwrap();
+    let second = engine.submit_quote("AAPL", 7, 98, 10, 102, 10).unwrap();
+    assert_eq!((second.bid_order_id, second.ask_order_id), (4, 5));
+    assert_eq!(second.replaced_order_ids, vec![1, 2]);
+    assert_eq!(engine.get_order_status("AAPL", 1).unwrap().status, OrderStatus::Canceled);
+    let partly_filled = engine.get_order_status("AAPL", 2).unwrap();
+    assert_eq!((partly_filled.status, partly_filled.filled_quantity), (OrderStatus::Canceled, 4));
+
+    engine.place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100, 3, 8)).unwrap();
+    let crossing = engine.submit_quote("AAPL", 7, 100, 5, 103, 5).unwrap();
+    assert_eq!(
+        crossing.trades().map(|t| (t.buy_order_id, t.sell_order_id, t.price, t.quantity)).collect::<Vec<_>>(),
+        vec![(7, 6, 100, 3)]
+    );
+    assert_eq!(crossing.replaced_order_ids, vec![4, 5]);
+    let quote = engine.quote(7, "AAPL").unwrap();
+    assert_eq!((quote.bid_order_id, quote.ask_order_id), (7, 8));
+
+    let book = engine.order_books.get("AAPL").unwrap();
+    assert_eq!((book.get_best_bid_price(), book.get_best_ask_price()), (Some(100), Some(103)));
+
+    let replayed = MatchingEngine::replay(engine.journal().unwrap()).unwrap();
+    assert_eq!(replayed.quote(7, "AAPL"), engine.quote(7, "AAPL"));
+    let restored = MatchingEngine::restore(engine.snapshot());
+    assert_eq!(restored.quote(7, "AAPL"), engine.quote(7, "AAPL"));
+}
+
+#[test]
+fn test_rejected_quote_side_rolls_back_whole_quote() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    engine
+        .set_price_bands(
+            "AAPL",
+            PriceBands { reference_price: Some(100), static_band_bps: Some(1_000), ..Default::default() },
+        )
+        .unwrap();
+    engine.set_user_risk_limits(7, RiskLimits { max_open_orders: Some(2), max_order_quantity: Some(50), ..Default::default() });
+    engine.submit_quote("AAPL", 7, 99, 10, 101, 10).unwrap();
+
+    let assert_previous_quote_intact = |engine: &MatchingEngine| {
+        let quote = engine.quote(7, "AAPL").unwrap();
+        assert_eq!((quote.bid_order_id, quote.ask_order_id), (1, 2));
+        assert_eq!(engine.get_order_status("AAPL", 1).unwrap().status, OrderStatus::New);
+        assert_eq!(engine.get_order_status("AAPL", 2).unwrap().status, OrderStatus::New);
+        let book = engine.order_books.get("AAPL").unwrap();
+        assert_eq!((book.get_best_bid_price(), book.get_best_ask_price()), (Some(99), Some(101)));
+        assert_eq!(engine.open_order_count(7), 2);
+        assert_eq!(engine.get_order_status("AAPL", 3), None);
+    };
+
+    assert_eq!(
+        engine.submit_quote("AAPL", 7, 98, 10, 150, 10).unwrap_err(),
+        MatchingError::PriceOutOfBand { limit: 110, reference: 100 }
+    );
+    assert_previous_quote_intact(&engine);
+
+    assert_eq!(
+        engine.submit_quote("AAPL", 7, 98, 10, 102, 51).unwrap_err(),
+        MatchingError::OrderQuantityLimitExceeded { quantity: 51, limit: 50 }
+    );
+    assert_previous_quote_intact(&engine);
+
+    assert_eq!(
+        engine.submit_quote("AAPL", 7, 101, 10, 101, 10).unwrap_err(),
+        MatchingError::InvalidQuote { bid_price: 101, ask_price: 101 }
+    );
+    assert_previous_quote_intact(&engine);
+
+    // The quote being replaced does not count towards the open order cap.
+    let replaced = engine.submit_quote("AAPL", 7, 98, 10, 102, 10).unwrap();
+    assert_eq!((replaced.bid_order_id, replaced.ask_order_id), (3, 4));
+    assert_eq!(engine.open_order_count(7), 2);
+}
+
+#[test]
+fn test_bust_trade_restores_orders_and_last_price() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    let events = engine.subscribe("AAPL");
+    engine.place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100, 5, 1)).unwrap();
+    engine.place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 101, 5, 2)).unwrap();
+    let result = engine.place_order(Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 101, 7, 3)).unwrap();
+    let (first, second) = (result.trades[0].id, result.trades[1].id);
+    while events.try_recv().is_ok() {}
+
+    // The partially filled maker keeps its place; the filled taker is not rebooked.
+    let bust = engine.bust_trade("AAPL", second).unwrap();
+    assert!(bust.restored_order_ids.is_empty());
+    assert_eq!(bust.last_trade_price, Some(100));
+    let maker = engine.get_order_status("AAPL", 2).unwrap();
+    assert_eq!((maker.status, maker.filled_quantity), (OrderStatus::New, 0));
+    let taker = engine.get_order_status("AAPL", 3).unwrap();
+    assert_eq!((taker.status, taker.filled_quantity), (OrderStatus::Canceled, 5));
+    let book = engine.order_books.get("AAPL").unwrap();
+    assert_eq!(book.get_market_depth().ask_levels, vec![(101, 5)]);
+    assert!(events.try_recv().is_ok_and(|event| matches!(event, EngineEvent::TradeBusted(bust) if bust.trade.id == second)));
+
+    // The filled maker goes back on the book.
+    let bust = engine.bust_trade("AAPL", first).unwrap();
+    assert_eq!(bust.restored_order_ids, vec![1]);
+    assert_eq!(bust.last_trade_price, None);
+    assert_eq!(engine.get_order_status("AAPL", 1).unwrap().status, OrderStatus::New);
+    let book = engine.order_books.get("AAPL").unwrap();
+    assert_eq!(book.get_market_depth().ask_levels, vec![(100, 5), (101, 5)]);
+    assert_eq!(book.last_trade_price, None);
+}
+
+#[test]
+fn test_bust_trade_rejects_unknown_and_expired_trades() {
+    let mut engine = MatchingEngine::with_config(MatchingEngineConfig { bustable_trades: 2, ..Default::default() });
+    engine.add_symbol("AAPL");
+    let mut trade_ids = Vec::new();
+    for price in [100, 101, 102] {
+        engine.place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, price, 1, 1)).unwrap();
+        let result = engine.place_order(Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, price, 1, 2)).unwrap();
+        trade_ids.push(result.trades[0].id);
+    }
+
+    assert_eq!(engine.bust_trade("AAPL", trade_ids[0]).unwrap_err(), MatchingError::TradeNotFound { trade_id: trade_ids[0] });
+    assert_eq!(engine.bust_trade("AAPL", 999).unwrap_err(), MatchingError::TradeNotFound { trade_id: 999 });
+    assert_eq!(engine.bust_trade("MSFT", trade_ids[1]).unwrap_err(), MatchingError::SymbolNotFound);
+    assert_eq!(engine.bust_trade("AAPL", trade_ids[1]).unwrap().last_trade_price, Some(102));
+    assert_eq!(engine.bust_trade("AAPL", trade_ids[1]).unwrap_err(), MatchingError::TradeNotFound { trade_id: trade_ids[1] });
+    assert_eq!(engine.bust_trade("AAPL", trade_ids[2]).unwrap().last_trade_price, Some(100));
+}
+
+#[test]
+fn test_idle_symbol_hibernates_and_wakes() {
+    let clock = Arc::new(ManualClock::new(0));
+    let config = MatchingEngineConfig { hibernate_after_ns: Some(1_000), ..Default::default() };
+    let mut engine = MatchingEngine::with_clock(config, clock.clone());
+    for symbol in ["AAPL", "MSFT", "TSLA"] {
+        engine.add_symbol(symbol);
+    }
+    engine.set_price_bands("AAPL", PriceBands { reference_price: Some(100), static_band_bps: Some(1_000), ..Default::default() }).unwrap();
+    engine.place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100, 5, 1)).unwrap();
+    engine.place_order(Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 5, 2)).unwrap();
+    engine.place_order(Order::new("MSFT".to_string(), Side::Sell, OrderType::Limit, 200, 5, 1)).unwrap();
+    let mut stop = Order::new("TSLA".to_string(), Side::Sell, OrderType::StopMarket, 0, 5, 1);
+    stop.stop_price = Some(50);
+    engine.place_order(stop).unwrap();
+
+    clock.set(500);
+    assert!(engine.hibernate_idle_symbols().is_empty());
+    clock.set(1_500);
+    // Books with resting or stop orders stay resident however long idle.
+    assert_eq!(engine.hibernate_idle_symbols(), vec!["AAPL"]);
+    assert!(engine.is_hibernated("AAPL") && !engine.order_books.contains_key("AAPL"));
+    let stats = engine.hibernation_stats();
+    assert_eq!((stats.resident_symbols, stats.hibernated_symbols, stats.hibernations, stats.wakes), (2, 1, 1, 0));
+
+    // The stub still answers queries and is snapshotted without levels.
+    assert_eq!(engine.trading_state("AAPL"), Some(TradingState::Continuous));
+    let listed = engine.list_symbols();
+    assert_eq!((listed[0].symbol.as_str(), listed[0].last_trade_price), ("AAPL", Some(100)));
+    let restored = MatchingEngine::restore(engine.snapshot());
+    assert!(restored.is_hibernated("AAPL"));
+    assert_eq!(restored.hibernation_stats().resident_symbols, 2);
+
+    // The next order wakes the book with its settings.
+    assert_eq!(
+        engine.place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 150, 5, 1)).unwrap_err(),
+        MatchingError::PriceOutOfBand { limit: 110, reference: 100 }
+    );
+    assert!(!engine.is_hibernated("AAPL"));
+    engine.place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 101, 5, 1)).unwrap();
+    let result = engine.place_order(Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 101, 5, 2)).unwrap();
+    assert_eq!(result.trades[0].price, 101);
+    assert_eq!(engine.order_books["AAPL"].last_trade_price, Some(101));
+
+    let stats = engine.hibernation_stats();
+    assert_eq!((stats.resident_symbols, stats.hibernated_symbols, stats.hibernations, stats.wakes), (3, 0, 1, 1));
+    assert_eq!(stats.wake_latency.count, 1);
+
+    assert!(engine.hibernate_idle_symbols().is_empty());
+    clock.set(2_500);
+    assert_eq!(engine.hibernate_idle_symbols(), vec!["AAPL"]);
+    assert_eq!(engine.hibernation_stats().hibernations, 2);
+}
+
+#[test]
+fn test_symbol_spec_rejects_off_tick_and_odd_lot_orders() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol_with_spec("BTC", SymbolSpec { tick_size: 50, lot_size: 10, min_qty: 20 });
+    engine.add_symbol("AAPL");
+    let order = |order_type, price, quantity| Order::new("BTC".to_string(), Side::Buy, order_type, price, quantity, 1);
+
+    assert_eq!(
+        engine.place_order(order(OrderType::Limit, 10_025, 20)).unwrap_err(),
+        MatchingError::InvalidTickSize { price: 10_025, tick_size: 50 }
+    );
+    assert_eq!(engine.get_order_status("BTC", 1).unwrap().status, OrderStatus::Rejected);
+    assert_eq!(
+        engine.place_order(order(OrderType::Limit, 10_000, 25)).unwrap_err(),
+        MatchingError::InvalidLotSize { quantity: 25, lot_size: 10 }
+    );
+    assert_eq!(
+        engine.place_order(order(OrderType::Limit, 10_000, 10)).unwrap_err(),
+        MatchingError::QuantityBelowMinimum { quantity: 10, min_qty: 20 }
+    );
+    let mut stop = order(OrderType::StopMarket, 0, 20);
+    stop.stop_price = Some(10_010);
+    assert_eq!(
+        engine.place_order(stop).unwrap_err(),
+        MatchingError::InvalidTickSize { price: 10_010, tick_size: 50 }
+    );
+    assert_eq!(
+        engine.submit_quote("BTC", 2, 9_950, 20, 10_025, 20).unwrap_err(),
+        MatchingError::InvalidTickSize { price: 10_025, tick_size: 50 }
+    );
+
+    assert!(engine.place_order(order(OrderType::Limit, 10_000, 30)).unwrap().remaining_order.is_some());
+    // Market orders carry no price to check.
+    engine.place_order(Order::new("BTC".to_string(), Side::Sell, OrderType::Market, 0, 30, 2)).unwrap();
+
+    // Symbols listed without a spec accept any price and quantity.
+    assert_eq!(engine.symbol_spec("AAPL"), Some(SymbolSpec::default()));
+    engine.place_order(Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 10_001, 1, 1)).unwrap();
+
+    let restored = MatchingEngine::restore(engine.snapshot());
+    assert_eq!(restored.symbol_spec("BTC"), Some(SymbolSpec { tick_size: 50, lot_size: 10, min_qty: 20 }));
+}
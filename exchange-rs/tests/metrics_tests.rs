@@ -0,0 +1,96 @@
+use exchange_rs::metrics::{LatencyHistogram, LatencyMetrics};
+use std::sync::Arc;
+use std::thread;
+
+fn within_resolution(actual: u64, expected: u64) -> bool {
+    let tolerance = expected / 64 + 1;
+    actual.abs_diff(expected) <= tolerance
+}
+
+#[test]
+fn test_percentiles_of_uniform_distribution() {
+    let metrics = LatencyMetrics::new();
+    for nanos in 1..=10_000 {
+        metrics.record(nanos);
+    }
+
+    assert_eq!(metrics.count(), 10_000);
+    assert_eq!(metrics.min(), 1);
+    assert_eq!(metrics.max(), 10_000);
+    assert_eq!(metrics.mean(), 5_000);
+
+    for (p, expected) in [(50.0, 5_000), (90.0, 9_000), (99.0, 9_900), (99.9, 9_990)] {
+        let actual = metrics.percentile(p);
+        assert!(within_resolution(actual, expected), "p{} = {}", p, actual);
+    }
+    assert_eq!(metrics.percentile(100.0), 10_000);
+}
+
+#[test]
+fn test_small_values_are_exact() {
+    let histogram = LatencyHistogram::new();
+    for nanos in [3, 3, 7, 100] {
+        histogram.record(nanos);
+    }
+
+    assert_eq!(histogram.percentile(50.0), 3);
+    assert_eq!(histogram.percentile(75.0), 7);
+    assert_eq!(histogram.percentile(100.0), 100);
+}
+
+#[test]
+fn test_extreme_values() {
+    let histogram = LatencyHistogram::new();
+    histogram.record(0);
+    histogram.record(u64::MAX);
+
+    assert_eq!(histogram.min(), 0);
+    assert_eq!(histogram.percentile(50.0), 0);
+    assert_eq!(histogram.percentile(100.0), u64::MAX);
+}
+
+#[test]
+fn test_snapshot_and_reset() {
+    let metrics = LatencyMetrics::new();
+    assert_eq!(metrics.snapshot().count, 0);
+    assert_eq!(metrics.percentile(99.0), 0);
+
+    for nanos in [1_000, 2_000, 3_000, 1_000_000] {
+        metrics.record(nanos);
+    }
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.count, 4);
+    assert_eq!(snapshot.min, 1_000);
+    assert_eq!(snapshot.max, 1_000_000);
+    assert!(within_resolution(snapshot.p50, 2_000));
+    assert_eq!(snapshot.p999, 1_000_000);
+
+    metrics.reset();
+    let snapshot = metrics.snapshot();
+    assert_eq!((snapshot.count, snapshot.min, snapshot.max, snapshot.p50), (0, 0, 0, 0));
+}
+
+#[test]
+fn test_concurrent_recording() {
+    let metrics = Arc::new(LatencyMetrics::new());
+
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let metrics = Arc::clone(&metrics);
+            thread::spawn(move || {
+                for nanos in 1..=1_000 {
+                    metrics.record(nanos);
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    assert_eq!(metrics.count(), 4_000);
+    assert_eq!(metrics.max(), 1_000);
+    assert!(within_resolution(metrics.percentile(50.0), 500));
+}
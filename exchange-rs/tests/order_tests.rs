@@ -47,3 +47,21 @@ fn test_order_expiration() {
     order.expiration_time = current_time + 1000000;
     assert!(!order.is_expired(current_time));
 }
+
+#[test]
+fn test_day_order_expires_at_session_close() {
+    let ns_per_day = 86_400_000_000_000i64;
+    let close_offset = 16 * 3_600_000_000_000i64;
+    let mut order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 10, 1);
+    order.time_in_force = TimeInForce::Day;
+
+    order.timestamp = 20_000 * ns_per_day + 10 * 3_600_000_000_000;
+    let close = 20_000 * ns_per_day + close_offset;
+    assert!(!order.is_expired_at_close(close - 1, Some(close_offset)));
+    assert!(order.is_expired_at_close(close, Some(close_offset)));
+    assert!(!order.is_expired(close));
+
+    order.timestamp = close + 1;
+    assert!(!order.is_expired_at_close(close + 2, Some(close_offset)));
+    assert!(order.is_expired_at_close(close + ns_per_day, Some(close_offset)));
+}
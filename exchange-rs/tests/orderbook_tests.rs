@@ -1,7 +1,15 @@
-use exchange_rs::order::{Order, OrderStatus, OrderType, Side, TimeInForce};
-use exchange_rs::orderbook::{OrderBook, PriceLevel, StopOrderBook};
+use exchange_rs::events::EngineCallbacks;
+use exchange_rs::clock::ManualClock;
+use exchange_rs::matching_engine::{CancelReason, MatchingEngine, MatchingEngineConfig, MatchingError};
+use exchange_rs::order::{Order, OrderStatus, OrderType, Side, TimeInForce, TriggerSource};
+use exchange_rs::orderbook::{
+    BookFeatures, ConcurrentOrderBook, MarketDepth, OrderBook, PriceLevel, ReferencePrices, StopOrderBook,
+};
+use exchange_rs::Price;
 use parking_lot::RwLock;
+use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
+use std::thread;
 
 #[test]
 fn test_price_level() {
@@ -81,7 +89,7 @@ fn test_order_book_multiple_price_levels() {
     let mut book = OrderBook::new("AAPL");
 
     for i in 0..5 {
-        let price = 100 - i;
+        let price = 100 - i as Price;
         let mut order = Order::new(
             "AAPL".to_string(),
             Side::Buy,
@@ -97,7 +105,7 @@ fn test_order_book_multiple_price_levels() {
     }
 
     for i in 5..10 {
-        let price = 110 + (i - 5);
+        let price = 110 + (i - 5) as Price;
         let mut order = Order::new(
             "AAPL".to_string(),
             Side::Sell,
@@ -140,16 +148,17 @@ fn test_stop_order() {
 
     book.add_stop_order(Arc::clone(&stop_order_arc)).unwrap();
 
-    book.update_last_trade_price(100).unwrap();
+    assert!(book.update_last_trade_price(100).unwrap().is_empty());
+    assert_eq!(book.stop_order_count(), 1);
 
-    assert_eq!(book.get_best_bid_price(), None);
-
-    book.update_last_trade_price(106).unwrap();
-
-    assert_eq!(book.get_best_bid_price(), Some(110));
+    let triggered = book.update_last_trade_price(106).unwrap();
+    assert_eq!(triggered.len(), 1);
+    assert_eq!(triggered[0].read().id, 1);
 
-    let order = book.get_order(1).unwrap();
-    assert_eq!(order.read().order_type, OrderType::Limit);
+    // Triggered stops are left to the engine to match, not booked here.
+    assert_eq!(book.stop_order_count(), 0);
+    assert_eq!(book.get_best_bid_price(), None);
+    assert_eq!(triggered[0].read().order_type, OrderType::StopLimit);
 }
 
 #[test]
@@ -188,18 +197,18 @@ fn test_stop_order_book() {
         .add_stop_order(Arc::clone(&sell_stop_arc))
         .unwrap();
 
-    let triggered_at_100 = stop_book.get_triggered_orders(100);
+    let triggered_at_100 = stop_book.get_triggered_orders(&ReferencePrices::last_trade(100));
     assert_eq!(triggered_at_100.len(), 0);
 
-    let triggered_at_105 = stop_book.get_triggered_orders(105);
+    let triggered_at_105 = stop_book.get_triggered_orders(&ReferencePrices::last_trade(105));
     assert_eq!(triggered_at_105.len(), 1);
     assert_eq!(triggered_at_105[0].read().id, 1);
 
-    let triggered_at_90 = stop_book.get_triggered_orders(90);
+    let triggered_at_90 = stop_book.get_triggered_orders(&ReferencePrices::last_trade(90));
     assert_eq!(triggered_at_90.len(), 1);
     assert_eq!(triggered_at_90[0].read().id, 2);
 
-    let triggered_at_85 = stop_book.get_triggered_orders(85);
+    let triggered_at_85 = stop_book.get_triggered_orders(&ReferencePrices::last_trade(85));
     assert_eq!(triggered_at_85.len(), 1);
     assert_eq!(triggered_at_85[0].read().id, 2);
 
@@ -207,10 +216,50 @@ fn test_stop_order_book() {
     assert!(removed.is_some());
     assert_eq!(removed.unwrap().read().id, 1);
 
-    let triggered_after_remove = stop_book.get_triggered_orders(105);
+    let triggered_after_remove = stop_book.get_triggered_orders(&ReferencePrices::last_trade(105));
     assert_eq!(triggered_after_remove.len(), 0);
 }
 
+#[test]
+fn test_stops_trigger_in_price_then_entry_order() {
+    let mut stop_book = StopOrderBook::new("AAPL");
+    let stops = [
+        (1, Side::Buy, 103, None),
+        (2, Side::Buy, 101, None),
+        (3, Side::Sell, 97, Some(TriggerSource::MarkPrice)),
+        (4, Side::Buy, 102, None),
+        (5, Side::Buy, 101, None),
+        (6, Side::Sell, 99, Some(TriggerSource::MarkPrice)),
+        (7, Side::Buy, 104, None),
+        (8, Side::Sell, 99, Some(TriggerSource::MarkPrice)),
+    ];
+    for (id, side, stop_price, trigger_source) in stops {
+        let mut order = Order::new("AAPL".to_string(), side, OrderType::StopMarket, 0, 1, 1);
+        order.id = id;
+        order.stop_price = Some(stop_price);
+        order.trigger_source = trigger_source;
+        stop_book.add_stop_order(Arc::new(RwLock::new(order))).unwrap();
+    }
+    assert_eq!(stop_book.peek_next_trigger(Side::Buy), Some(101));
+    assert_eq!(stop_book.peek_next_trigger(Side::Sell), Some(99));
+
+    let prices = ReferencePrices {
+        last_trade: Some(103),
+        mark: Some(97),
+        index: None,
+    };
+    let ids = |orders: Vec<Arc<RwLock<Order>>>| orders.iter().map(|o| o.read().id).collect::<Vec<_>>();
+    for _ in 0..3 {
+        assert_eq!(ids(stop_book.get_triggered_orders(&prices)), vec![2, 5, 4, 1, 6, 8, 3]);
+    }
+
+    let triggered = stop_book.get_triggered_orders(&prices);
+    stop_book.remove_triggered_orders(&triggered);
+    assert_eq!(stop_book.peek_next_trigger(Side::Buy), Some(104));
+    assert_eq!(stop_book.peek_next_trigger(Side::Sell), None);
+    assert!(stop_book.get_triggered_orders(&prices).is_empty());
+}
+
 #[test]
 fn test_order_expiration() {
     let mut book = OrderBook::new("AAPL");
@@ -249,6 +298,52 @@ fn test_order_expiration() {
     assert_eq!(expired[1].read().status, OrderStatus::Expired);
 }
 
+#[test]
+fn test_expiry_skips_orders_that_left_the_book() {
+    let mut book = OrderBook::new("AAPL");
+    let one_day_ns: i64 = 86_400_000_000_000;
+    let add = |book: &mut OrderBook, id: u64, time_in_force: TimeInForce, time: i64| {
+        let mut order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100 - id as Price, 10, 1);
+        order.id = id;
+        order.time_in_force = time_in_force;
+        order.timestamp = time;
+        order.expiration_time = time;
+        let order = Arc::new(RwLock::new(order));
+        book.add_order(Arc::clone(&order)).unwrap();
+        order
+    };
+    let ids = |orders: Vec<Arc<RwLock<Order>>>| {
+        let mut ids: Vec<u64> = orders.iter().map(|order| order.read().id).collect();
+        ids.sort_unstable();
+        ids
+    };
+
+    add(&mut book, 1, TimeInForce::GTD, 10);
+    add(&mut book, 2, TimeInForce::GTD, 20);
+    let third = add(&mut book, 3, TimeInForce::GTD, 30);
+    add(&mut book, 4, TimeInForce::GTC, 0);
+    add(&mut book, 5, TimeInForce::Day, 5);
+    add(&mut book, 6, TimeInForce::Day, one_day_ns + 5);
+
+    // Order 3 comes back with a later deadline, leaving a stale entry.
+    book.cancel_order(2);
+    book.cancel_order(3);
+    third.write().expiration_time = 40;
+    book.add_order(third).unwrap();
+
+    assert_eq!(ids(book.expire_orders(25)), vec![1]);
+    assert!(book.expire_orders(35).is_empty());
+    assert_eq!(ids(book.expire_orders(40)), vec![3]);
+
+    // With a session close an hour after midnight, order 5 expires that
+    // night and order 6 the next.
+    let close = 3_600_000_000_000;
+    assert!(book.expire_orders_at_close(close - 1, Some(close)).is_empty());
+    assert_eq!(ids(book.expire_orders_at_close(close, Some(close))), vec![5]);
+    assert_eq!(ids(book.expire_orders_at_close(one_day_ns + close, Some(close))), vec![6]);
+    assert_eq!(book.order_count(), 1);
+}
+
 #[test]
 fn test_iceberg_order() {
     let mut book = OrderBook::new("AAPL");
@@ -356,3 +451,530 @@ fn test_order_book_error_handling() {
     assert!(result.is_err());
     assert_eq!(result.unwrap_err(), "Missing stop price");
 }
+
+fn book_with_levels(bids: &[(Price, u32)], asks: &[(Price, u32)]) -> OrderBook {
+    let mut book = OrderBook::new("AAPL");
+    let levels = bids.iter().map(|&l| (Side::Buy, l)).chain(asks.iter().map(|&l| (Side::Sell, l)));
+    for (id, (side, (price, quantity))) in levels.enumerate() {
+        let mut order = Order::new("AAPL".to_string(), side, OrderType::Limit, price, quantity, 1);
+        order.id = id as u64 + 1;
+        book.add_order(Arc::new(RwLock::new(order))).unwrap();
+    }
+    book
+}
+
+#[test]
+fn test_book_features() {
+    let book = book_with_levels(&[(100, 30), (99, 10), (97, 20)], &[(102, 10), (103, 40)]);
+
+    // Microprice (100 * 10 + 102 * 30) / 40 = 101.5 rounds up; mid is 101.
+    assert_eq!(
+        book.features(2, 2),
+        BookFeatures {
+            imbalance_bps: Some(-1111),
+            microprice: Some(102),
+            bid_depth_near_mid: Some(40),
+            ask_depth_near_mid: Some(50),
+        }
+    );
+
+    let features = book.features(3, 1);
+    assert_eq!(features.imbalance_bps, Some(909));
+    assert_eq!((features.bid_depth_near_mid, features.ask_depth_near_mid), (Some(30), Some(10)));
+
+    // Mid of 100 and 103 is 101.5, so a one-tick window reaches 102.5.
+    let book = book_with_levels(&[(100, 5), (99, 5)], &[(103, 15), (104, 5)]);
+    let features = book.features(5, 1);
+    assert_eq!(features.microprice, Some(101));
+    assert_eq!((features.bid_depth_near_mid, features.ask_depth_near_mid), (Some(0), Some(0)));
+    assert_eq!(book.features(5, 2).bid_depth_near_mid, Some(5));
+}
+
+#[test]
+fn test_book_features_one_sided_and_empty() {
+    let book = book_with_levels(&[(100, 30), (99, 10)], &[]);
+    assert_eq!(
+        book.features(5, 10),
+        BookFeatures {
+            imbalance_bps: Some(10_000),
+            ..Default::default()
+        }
+    );
+
+    assert_eq!(OrderBook::new("AAPL").features(5, 10), BookFeatures::default());
+}
+
+fn shared_order(id: u64, side: Side, price: Price, quantity: u32) -> Arc<RwLock<Order>> {
+    let mut order = Order::new("AAPL".to_string(), side, OrderType::Limit, price, quantity, 1);
+    order.id = id;
+    Arc::new(RwLock::new(order))
+}
+
+#[test]
+fn test_concurrent_book_adds_and_cancels_across_threads() {
+    let book = Arc::new(ConcurrentOrderBook::new("AAPL"));
+
+    let handles: Vec<_> = (0..4u64)
+        .map(|thread_index| {
+            let book = Arc::clone(&book);
+            thread::spawn(move || {
+                for i in 0..250u64 {
+                    let id = thread_index * 1_000 + i;
+                    let (side, price) = if i % 2 == 0 { (Side::Buy, 90 + (i % 10) as Price) } else { (Side::Sell, 110 + (i % 10) as Price) };
+                    book.add_order(shared_order(id, side, price, 10)).unwrap();
+                    if i % 5 == 0 {
+                        assert!(book.cancel_order(id).is_some());
+                    }
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    assert_eq!(book.order_count(), 4 * 200);
+    assert!(book.cancel_order(0).is_none());
+    assert_eq!((book.get_best_bid_price(), book.get_best_ask_price()), (Some(98), Some(111)));
+
+    let depth = book.get_market_depth(20);
+    let volume = |levels: &[(Price, u64)]| levels.iter().map(|&(_, qty)| qty).sum::<u64>();
+    assert_eq!(volume(&depth.bid_levels) + volume(&depth.ask_levels), 4 * 200 * 10);
+    assert_eq!(depth.bid_levels.len(), 4);
+    assert!(depth.bid_levels.windows(2).all(|pair| pair[0].0 > pair[1].0));
+}
+
+#[test]
+fn test_concurrent_book_matches_triggered_stops() {
+    let book = ConcurrentOrderBook::new("AAPL");
+    let trade_ids = AtomicU64::new(1);
+    let callbacks = EngineCallbacks::default();
+
+    book.add_order(shared_order(1, Side::Sell, 100, 2)).unwrap();
+    book.add_order(shared_order(2, Side::Sell, 101, 5)).unwrap();
+    book.add_order(shared_order(3, Side::Buy, 98, 5)).unwrap();
+    let mut stop_limit = Order::new("AAPL".to_string(), Side::Buy, OrderType::StopLimit, 105, 3, 2);
+    stop_limit.id = 4;
+    stop_limit.stop_price = Some(100);
+    book.add_stop_order(Arc::new(RwLock::new(stop_limit))).unwrap();
+    let mut stop_market = Order::new("AAPL".to_string(), Side::Sell, OrderType::StopMarket, 0, 4, 2);
+    stop_market.id = 5;
+    stop_market.stop_price = Some(101);
+    book.add_stop_order(Arc::new(RwLock::new(stop_market))).unwrap();
+
+    // The buy trades at 100 and triggers the stop-limit, which lifts 101
+    // and triggers the stop-market sell into the bids.
+    let result = book.match_order(shared_order(6, Side::Buy, 100, 2), &trade_ids, &callbacks).unwrap();
+    let fills: Vec<(u64, u64, Price, u32)> = result
+        .trades
+        .iter()
+        .map(|t| (t.buy_order_id, t.sell_order_id, t.price, t.quantity))
+        .collect();
+    assert_eq!(fills, vec![(6, 1, 100, 2), (4, 2, 101, 3), (3, 5, 98, 4)]);
+    let triggered: Vec<u64> = result.triggered_stops.iter().map(|o| o.read().id).collect();
+    assert_eq!(triggered, vec![4, 5]);
+
+    assert_eq!((book.get_best_bid_price(), book.get_best_ask_price()), (Some(98), Some(101)));
+    assert_eq!(book.get_order(3).unwrap().read().remaining_quantity(), 1);
+    assert!(book.get_order(4).is_none());
+    assert!(book.get_order(5).is_none());
+}
+
+#[test]
+fn test_concurrent_fok_is_not_split_by_cancels() {
+    for round in 0..50u64 {
+        let book = Arc::new(ConcurrentOrderBook::new("AAPL"));
+        let trade_ids = AtomicU64::new(1);
+        let callbacks = EngineCallbacks::default();
+        for id in 1..=10 {
+            book.add_order(shared_order(id, Side::Sell, 100 + (id % 3) as Price, 1)).unwrap();
+        }
+
+        let canceller = {
+            let book = Arc::clone(&book);
+            thread::spawn(move || {
+                for id in (1..=10).filter(|id| (id + round) % 4 == 0) {
+                    book.cancel_order(id);
+                }
+            })
+        };
+        let mut fok = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 102, 8, 2);
+        fok.id = 100;
+        fok.time_in_force = TimeInForce::FOK;
+        let outcome = book.match_order(Arc::new(RwLock::new(fok)), &trade_ids, &callbacks);
+        canceller.join().unwrap();
+
+        match outcome {
+            Ok(result) => assert_eq!(result.trades.iter().map(|t| t.quantity).sum::<u32>(), 8),
+            Err(error) => {
+                assert_eq!(error, MatchingError::FOKCannotBeFilled);
+                assert_eq!(trade_ids.load(std::sync::atomic::Ordering::Relaxed), 1);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_concurrent_book_matching() {
+    let book = ConcurrentOrderBook::new("AAPL");
+    let trade_ids = AtomicU64::new(1);
+    let callbacks = EngineCallbacks::default();
+
+    book.add_order(shared_order(1, Side::Sell, 101, 5)).unwrap();
+    book.add_order(shared_order(2, Side::Sell, 102, 5)).unwrap();
+    let mut stop = Order::new("AAPL".to_string(), Side::Buy, OrderType::StopLimit, 103, 4, 2);
+    stop.id = 3;
+    stop.stop_price = Some(102);
+    book.add_stop_order(Arc::new(RwLock::new(stop))).unwrap();
+
+    let result = book.match_order(shared_order(4, Side::Buy, 102, 10), &trade_ids, &callbacks).unwrap();
+    let fills: Vec<(u64, u64, Price, u32)> = result
+        .trades
+        .iter()
+        .map(|t| (t.id, t.sell_order_id, t.price, t.quantity))
+        .collect();
+    assert_eq!(fills, vec![(1, 1, 101, 5), (2, 2, 102, 5)]);
+    assert!(result.remaining_order.is_none());
+    assert_eq!(result.filled_orders.len(), 2);
+    assert!(book.get_order(1).is_none());
+    assert_eq!(book.get_last_trade_price(), Some(102));
+
+    assert_eq!(book.get_best_ask_price(), None);
+    assert_eq!(result.triggered_stops.len(), 1);
+    assert_eq!(book.get_order(3).unwrap().read().order_type, OrderType::Limit);
+
+    let mut fok = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100, 50, 3);
+    fok.id = 5;
+    fok.time_in_force = TimeInForce::FOK;
+    let result = book.match_order(Arc::new(RwLock::new(fok)), &trade_ids, &callbacks);
+    assert_eq!(result.unwrap_err(), MatchingError::FOKCannotBeFilled);
+
+    let result = book.match_order(shared_order(6, Side::Buy, 100, 2), &trade_ids, &callbacks).unwrap();
+    assert!(result.trades.is_empty());
+    assert_eq!(book.get_best_bid_price(), Some(103));
+    assert_eq!(result.remaining_order.unwrap().read().status, OrderStatus::New);
+}
+
+#[test]
+fn test_concurrent_market_orders_stop_at_their_protection_price() {
+    let book = ConcurrentOrderBook::new("AAPL");
+    let trade_ids = AtomicU64::new(1);
+    let callbacks = EngineCallbacks::default();
+    for (id, price) in [(1, 10_000), (2, 10_100), (3, 10_300)] {
+        book.add_order(shared_order(id, Side::Sell, price, 5)).unwrap();
+    }
+
+    // 150bps above the best ask of 10_000 takes in 10_100 but not 10_300.
+    let mut market = Order::new("AAPL".to_string(), Side::Buy, OrderType::Market, 0, 15, 2);
+    market.id = 4;
+    market.max_slippage_bps = Some(150);
+    let result = book.match_order(Arc::new(RwLock::new(market)), &trade_ids, &callbacks).unwrap();
+    let prices: Vec<Price> = result.trades.iter().map(|t| t.price).collect();
+    assert_eq!(prices, vec![10_000, 10_100]);
+    assert_eq!(result.cancel_reason, Some(CancelReason::PriceProtection));
+    assert_eq!(book.get_best_ask_price(), Some(10_300));
+
+    // A FOK market order only counts liquidity inside its band.
+    let mut fok = Order::new("AAPL".to_string(), Side::Buy, OrderType::Market, 0, 5, 2);
+    fok.id = 5;
+    fok.time_in_force = TimeInForce::FOK;
+    fok.protection_price = Some(10_200);
+    let result = book.match_order(Arc::new(RwLock::new(fok)), &trade_ids, &callbacks);
+    assert_eq!(result.unwrap_err(), MatchingError::FOKCannotBeFilled);
+    assert_eq!(book.get_order(3).unwrap().read().remaining_quantity(), 5);
+}
+
+#[test]
+fn test_update_after_trade_adjusts_only_traded_order() {
+    let mut level = PriceLevel::new(100);
+    let mut iceberg = Order::new("AAPL".to_string(), Side::Sell, OrderType::Iceberg, 100, 30, 1);
+    iceberg.id = 1;
+    iceberg.display_quantity = Some(10);
+    let mut regular = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100, 15, 2);
+    regular.id = 2;
+    level.add_order(Arc::new(RwLock::new(iceberg)));
+    level.add_order(Arc::new(RwLock::new(regular)));
+    assert_eq!((level.total_volume, level.visible_volume), (45, 25));
+
+    level.update_after_trade(1, 4).unwrap();
+    assert_eq!((level.total_volume, level.visible_volume), (41, 21));
+
+    // Exhausting the slice shows a fresh one and loses priority.
+    level.update_after_trade(1, 6).unwrap();
+    assert_eq!((level.total_volume, level.visible_volume), (35, 25));
+    let queue: Vec<u64> = level.orders.iter().map(|o| o.read().id).collect();
+    assert_eq!(queue, vec![2, 1]);
+
+    level.update_after_trade(2, 5).unwrap();
+    assert_eq!((level.total_volume, level.visible_volume), (30, 20));
+    assert!(level.update_after_trade(3, 1).is_err());
+}
+
+#[test]
+fn test_price_level_queue_index_survives_removals_and_rotation() {
+    let mut level = PriceLevel::new(100);
+    for id in 1..=6 {
+        let mut order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100, 10, id);
+        order.id = id;
+        if id == 2 {
+            order.order_type = OrderType::Iceberg;
+            order.quantity = 20;
+            order.display_quantity = Some(10);
+        }
+        level.add_order(Arc::new(RwLock::new(order)));
+    }
+    let queue = |level: &PriceLevel| -> Vec<u64> { level.orders.iter().map(|o| o.read().id).collect() };
+
+    assert_eq!(level.remove_order(4).unwrap().read().id, 4);
+    assert!(level.remove_order(4).is_none());
+    assert_eq!(level.pop_front().unwrap().read().id, 1);
+    level.update_after_trade(2, 10).unwrap();
+    assert_eq!(queue(&level), vec![3, 5, 6, 2]);
+
+    level.retain_orders(|o| o.read().id != 5);
+    assert_eq!(queue(&level), vec![3, 6, 2]);
+    assert_eq!(level.front().unwrap().read().id, 3);
+
+    for id in [6, 2, 3] {
+        assert_eq!(level.remove_order(id).unwrap().read().id, id);
+    }
+    assert!(level.orders.is_empty());
+    assert!(level.remove_order(5).is_none());
+}
+
+#[test]
+fn test_price_level_counts_and_oldest_order_follow_the_queue() {
+    let mut level = PriceLevel::new(100);
+    for (id, received_at) in [(1, 10), (2, 20), (3, 30)] {
+        let mut order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100, 10, id);
+        order.id = id;
+        order.received_at = received_at;
+        if id == 1 {
+            order.order_type = OrderType::Iceberg;
+            order.quantity = 30;
+            order.display_quantity = Some(10);
+        }
+        level.add_order(Arc::new(RwLock::new(order)));
+    }
+    assert_eq!((level.order_count(), level.oldest_received_at()), (3, Some(10)));
+
+    level.update_after_trade(2, 4).unwrap();
+    assert_eq!((level.order_count(), level.oldest_received_at()), (3, Some(10)));
+
+    // The iceberg shows a fresh slice and goes to the back, but it is
+    // still the order that has rested longest.
+    level.update_after_trade(1, 10).unwrap();
+    assert_eq!(level.front().unwrap().read().id, 2);
+    assert_eq!((level.order_count(), level.oldest_received_at()), (3, Some(10)));
+
+    level.remove_order(1).unwrap();
+    assert_eq!((level.order_count(), level.oldest_received_at()), (2, Some(20)));
+    level.retain_orders(|o| o.read().id != 2);
+    assert_eq!((level.order_count(), level.oldest_received_at()), (1, Some(30)));
+    level.pop_front().unwrap();
+    assert_eq!((level.order_count(), level.oldest_received_at()), (0, None));
+}
+
+#[test]
+fn test_detailed_depth_and_snapshot_levels_agree() {
+    let clock = Arc::new(ManualClock::new(1_000));
+    let mut engine = MatchingEngine::with_clock(MatchingEngineConfig::default(), clock.clone());
+    engine.add_symbol("AAPL");
+    for (price, quantity) in [(101, 5), (101, 7), (102, 4)] {
+        engine.place_order(Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, price, quantity, 1)).unwrap();
+        clock.advance(1_000);
+    }
+    engine.place_order(Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 99, 3, 2)).unwrap();
+    // Takes the first order at 101 and part of the second.
+    engine.place_order(Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 101, 8, 3)).unwrap();
+
+    let depth = engine.detailed_market_depth("AAPL", 5).unwrap();
+    assert_eq!(depth.bid_levels, vec![(99, 3)]);
+    assert_eq!(depth.ask_levels, vec![(101, 4), (102, 4)]);
+    let details: Vec<_> = depth.ask_details.iter().map(|l| (l.price, l.visible, l.orders, l.oldest_ts)).collect();
+    assert_eq!(details, vec![(101, 4, 1, Some(2_000)), (102, 4, 1, Some(3_000))]);
+    assert_eq!(depth.bid_details[0].oldest_ts, Some(4_000));
+    assert!(engine.market_depth("AAPL", 5).unwrap().ask_details.is_empty());
+
+    let snapshot = engine.order_books["AAPL"].create_snapshot();
+    for detail in depth.bid_details.iter().chain(&depth.ask_details) {
+        let levels = if detail.price < 100 { &snapshot.buy_levels } else { &snapshot.sell_levels };
+        assert_eq!(levels[&detail.price].depth_level(), *detail);
+    }
+}
+
+#[test]
+fn test_get_order_finds_resting_and_stop_orders() {
+    let mut book = OrderBook::new("AAPL");
+
+    let mut limit_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 10, 1);
+    limit_order.id = 1;
+    book.add_order(Arc::new(RwLock::new(limit_order))).unwrap();
+
+    let mut stop_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::StopLimit, 110, 10, 1);
+    stop_order.id = 2;
+    stop_order.stop_price = Some(105);
+    let stop_order = Arc::new(RwLock::new(stop_order));
+    book.add_stop_order(Arc::clone(&stop_order)).unwrap();
+
+    assert_eq!(book.get_order(1).unwrap().read().id, 1);
+    assert!(Arc::ptr_eq(&book.get_order(2).unwrap(), &stop_order));
+    assert!(book.contains_order(1));
+    assert!(book.contains_order(2));
+    assert!(!book.contains_order(3));
+    assert!(book.get_order(3).is_none());
+
+    book.update_last_trade_price(106).unwrap();
+    assert!(!book.contains_order(2));
+    assert!(book.get_order(2).is_none());
+}
+
+#[test]
+fn test_client_order_id_index_follows_the_order() {
+    let mut book = OrderBook::new("AAPL");
+    let order = |id, user_id, side, order_type, price, client_order_id: &str| {
+        let mut order = Order::new("AAPL".to_string(), side, order_type, price, 10, user_id);
+        order.id = id;
+        order.client_order_id = Some(client_order_id.to_string());
+        Arc::new(RwLock::new(order))
+    };
+
+    book.add_order(order(1, 1, Side::Buy, OrderType::Limit, 100, "A")).unwrap();
+    book.add_order(order(2, 2, Side::Buy, OrderType::Limit, 100, "A")).unwrap();
+    book.add_order(order(3, 1, Side::Sell, OrderType::Limit, 105, "B")).unwrap();
+    let stop = order(4, 1, Side::Buy, OrderType::StopLimit, 110, "C");
+    stop.write().stop_price = Some(108);
+    book.add_stop_order(stop).unwrap();
+
+    // The same client id is kept apart per user.
+    assert_eq!(book.get_order_by_client_id(1, "A").unwrap().read().id, 1);
+    assert_eq!(book.get_order_by_client_id(2, "A").unwrap().read().id, 2);
+    assert_eq!(book.get_order_by_client_id(1, "C").unwrap().read().id, 4);
+    assert!(book.get_order_by_client_id(3, "A").is_none());
+
+    assert_eq!(book.cancel_order_by_client_id(1, "A").unwrap().read().id, 1);
+    assert!(book.get_order_by_client_id(1, "A").is_none());
+    assert!(book.cancel_order_by_client_id(1, "A").is_none());
+    assert!(book.get_order_by_client_id(2, "A").is_some());
+
+    // A triggered stop leaves the book for the engine to resubmit, and
+    // takes its client id with it.
+    let triggered = book.update_last_trade_price(108).unwrap();
+    assert_eq!(triggered[0].read().id, 4);
+    assert!(book.get_order_by_client_id(1, "C").is_none());
+
+    book.cancel_order(3);
+    assert!(book.get_order_by_client_id(1, "B").is_none());
+}
+
+#[test]
+fn test_client_order_id_is_released_when_the_order_ends() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    let place = |engine: &mut MatchingEngine, side, quantity, user_id, client_order_id: &str| {
+        let mut order = Order::new("AAPL".to_string(), side, OrderType::Limit, 100, quantity, user_id);
+        order.client_order_id = Some(client_order_id.to_string());
+        engine.place_order(order)
+    };
+
+    place(&mut engine, Side::Sell, 10, 1, "S1").unwrap();
+    assert!(matches!(
+        place(&mut engine, Side::Sell, 5, 1, "S1"),
+        Err(MatchingError::DuplicateClientOrderId(id)) if id == "S1"
+    ));
+    // Another user, or another symbol, may use the same id.
+    place(&mut engine, Side::Sell, 5, 2, "S1").unwrap();
+    engine.add_symbol("MSFT");
+    let mut order = Order::new("MSFT".to_string(), Side::Sell, OrderType::Limit, 100, 5, 1);
+    order.client_order_id = Some("S1".to_string());
+    engine.place_order(order).unwrap();
+
+    // Filled away, the id is free again.
+    place(&mut engine, Side::Buy, 10, 3, "B1").unwrap();
+    assert!(engine.get_order_by_client_id("AAPL", 1, "S1").is_none());
+    place(&mut engine, Side::Sell, 4, 1, "S1").unwrap();
+
+    let cancelled = engine.cancel_order_by_client_id("AAPL", 1, "S1").unwrap();
+    assert_eq!(cancelled.read().status, OrderStatus::Canceled);
+    place(&mut engine, Side::Sell, 4, 1, "S1").unwrap();
+    assert_eq!(engine.get_order_by_client_id("AAPL", 1, "S1").unwrap().quantity, 4);
+}
+
+/// Small deterministic generator so a failing sequence can be rerun.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+fn rebuilt_depth(book: &OrderBook, levels: usize) -> MarketDepth {
+    MarketDepth::new(
+        book.buy_levels.iter().rev().take(levels).map(|(&p, l)| (p, l.visible_volume)).collect(),
+        book.sell_levels.iter().take(levels).map(|(&p, l)| (p, l.visible_volume)).collect(),
+    )
+}
+
+#[test]
+fn test_incremental_depth_matches_rebuild() {
+    const LEVELS: usize = 4;
+
+    for seed in 1..=20u64 {
+        let mut rng = XorShift(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        let mut engine = MatchingEngine::new();
+        engine.add_symbol("AAPL");
+        engine.order_books.get_mut("AAPL").unwrap().set_depth_levels(LEVELS);
+        let mut next_id = 1;
+
+        for step in 0..400 {
+            let book = &engine.order_books["AAPL"];
+            let generation = book.depth_generation();
+            let before = book.get_market_depth();
+            let target = 1 + rng.below(next_id);
+
+            match rng.below(10) {
+                0..=5 => {
+                    let side = if rng.below(2) == 0 { Side::Buy } else { Side::Sell };
+                    let order_type = match rng.below(8) {
+                        0 => OrderType::Market,
+                        1 => OrderType::Iceberg,
+                        _ => OrderType::Limit,
+                    };
+                    let mut order =
+                        Order::new("AAPL".to_string(), side, order_type, 90 + rng.below(21) as Price, 1 + rng.below(30) as u32, 1 + rng.below(4));
+                    if order_type == OrderType::Iceberg {
+                        order.display_quantity = Some(1 + order.quantity / 3);
+                    }
+                    if engine.place_order(order).is_ok() {
+                        next_id += 1;
+                    }
+                }
+                6..=7 => {
+                    engine.cancel_order("AAPL", target);
+                }
+                _ => {
+                    let _ = engine.replace_order("AAPL", target, 90 + rng.below(21) as Price, 1 + rng.below(30) as u32);
+                }
+            }
+
+            let book = &engine.order_books["AAPL"];
+            let depth = book.get_market_depth();
+            let rebuilt = rebuilt_depth(book, LEVELS);
+            assert_eq!(depth.bid_levels, rebuilt.bid_levels, "bids diverged at seed {seed} step {step}");
+            assert_eq!(depth.ask_levels, rebuilt.ask_levels, "asks diverged at seed {seed} step {step}");
+            assert_eq!(depth.checksum, rebuilt.checksum, "checksum diverged at seed {seed} step {step}");
+            if book.depth_generation() == generation {
+                assert_eq!(before.bid_levels, depth.bid_levels);
+                assert_eq!(before.ask_levels, depth.ask_levels);
+            }
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use exchange_rs::{
+    matching_engine::{MatchingEngine, MatchingError},
+    order::{Order, OrderType, Side, TimeInForce},
+    participants::{ParticipantError, ParticipantKind, ParticipantLimits, ParticipantRegistry},
+};
+
+const FIRM: u64 = 1;
+const ACCOUNT_A: u64 = 10;
+const ACCOUNT_B: u64 = 11;
+const TRADER_A: u64 = 100;
+const TRADER_B: u64 = 101;
+
+fn hierarchy() -> ParticipantRegistry {
+    let mut registry = ParticipantRegistry::new();
+    registry.register_firm(FIRM, "Acme").unwrap();
+    registry.register_account(ACCOUNT_A, "Acme-A", FIRM).unwrap();
+    registry.register_account(ACCOUNT_B, "Acme-B", FIRM).unwrap();
+    registry.register_trader(TRADER_A, "alice", ACCOUNT_A).unwrap();
+    registry.register_trader(TRADER_B, "bob", ACCOUNT_B).unwrap();
+    registry
+}
+
+fn engine_with_hierarchy() -> MatchingEngine {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    *engine.participants_mut() = hierarchy();
+    engine
+}
+
+fn notional_limit(max_notional: u128) -> ParticipantLimits {
+    ParticipantLimits {
+        max_notional: Some(max_notional),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_registration_enforces_hierarchy() {
+    let mut registry = hierarchy();
+
+    assert_eq!(
+        registry.register_trader(200, "carol", FIRM),
+        Err(ParticipantError::InvalidParent { parent: FIRM, kind: ParticipantKind::Trader })
+    );
+    assert_eq!(
+        registry.register_account(ACCOUNT_A, "dup", FIRM),
+        Err(ParticipantError::AlreadyRegistered { id: ACCOUNT_A })
+    );
+    assert_eq!(
+        registry.register_account(12, "orphan", 999),
+        Err(ParticipantError::UnknownParticipant { id: 999 })
+    );
+
+    assert_eq!(registry.ancestry(TRADER_A), vec![TRADER_A, ACCOUNT_A, FIRM]);
+    assert_eq!(registry.descendants(FIRM), vec![ACCOUNT_A, ACCOUNT_B, TRADER_B, TRADER_A]);
+}
+
+#[test]
+fn test_identity_mapping() {
+    let mut registry = hierarchy();
+    registry.map_fix_identity("ACME", None, TRADER_A).unwrap();
+    registry.map_fix_identity("ACME", Some("BOB"), TRADER_B).unwrap();
+    registry.map_identity("ws:bob-key", TRADER_B).unwrap();
+
+    assert_eq!(registry.resolve_fix_identity("ACME", None), Some(TRADER_A));
+    assert_eq!(registry.resolve_fix_identity("ACME", Some("BOB")), Some(TRADER_B));
+    assert_eq!(registry.resolve_fix_identity("ACME", Some("OTHER")), Some(TRADER_A));
+    assert_eq!(registry.resolve_fix_identity("NOBODY", None), None);
+    assert_eq!(registry.resolve_identity("ws:bob-key"), Some(TRADER_B));
+    assert!(registry.map_identity("ws:firm", FIRM).is_err());
+}
+
+#[test]
+fn test_firm_limit_blocks_trader_with_own_headroom() {
+    let mut engine = engine_with_hierarchy();
+    engine.participants_mut().set_limits(FIRM, notional_limit(2_000)).unwrap();
+    engine.participants_mut().set_limits(TRADER_B, notional_limit(10_000)).unwrap();
+
+    let order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 15, TRADER_A);
+    engine.place_order(order).unwrap();
+
+    let order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 6, TRADER_B);
+    assert_eq!(
+        engine.place_order(order).unwrap_err(),
+        MatchingError::LimitExceeded { participant: FIRM }
+    );
+
+    let order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 5, TRADER_B);
+    engine.place_order(order).unwrap();
+
+    let participants = engine.participants();
+    assert_eq!(participants.exposure(FIRM).unwrap().notional, 2_000);
+    assert_eq!(participants.exposure(ACCOUNT_A).unwrap().notional, 1_500);
+    assert_eq!(participants.exposure(TRADER_B).unwrap().notional, 500);
+}
+
+#[test]
+fn test_cancel_and_ioc_remainder_release_exposure() {
+    let mut engine = engine_with_hierarchy();
+    engine.participants_mut().set_limits(ACCOUNT_A, notional_limit(1_000)).unwrap();
+
+    let order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 10, TRADER_A);
+    let order_id = engine.place_order(order).unwrap().remaining_order.unwrap().read().id;
+    engine.cancel_order("AAPL", order_id);
+    assert_eq!(engine.participants().exposure(FIRM).unwrap().notional, 0);
+
+    let sell_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 100, 4, TRADER_B);
+    engine.place_order(sell_order).unwrap();
+
+    let mut ioc_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 10, TRADER_A);
+    ioc_order.time_in_force = TimeInForce::IOC;
+    engine.place_order(ioc_order).unwrap();
+
+    assert_eq!(engine.participants().exposure(ACCOUNT_A).unwrap().notional, 400);
+    assert_eq!(engine.participants().exposure(FIRM).unwrap().notional, 800);
+}
+
+#[test]
+fn test_unregistered_users_are_not_limited() {
+    let mut engine = engine_with_hierarchy();
+    engine.participants_mut().set_limits(FIRM, notional_limit(0)).unwrap();
+
+    let order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 100, 10, 7);
+    assert!(engine.place_order(order).is_ok());
+}
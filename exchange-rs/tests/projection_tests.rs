@@ -0,0 +1,250 @@
+use exchange_rs::admin::{AdminServer, Ledgers};
+use exchange_rs::credit::CreditLedger;
+use exchange_rs::events::{EngineEvent, EventRing, SequencedEvent};
+use exchange_rs::fees::FeeSchedule;
+use exchange_rs::matching_engine::{MatchingEngine, MatchingEngineConfig, TradeExecutionResult};
+use exchange_rs::order::{Order, OrderType, Side};
+use exchange_rs::positions::Positions;
+use exchange_rs::projection::{EventSourced, ProjectionError};
+use exchange_rs::Price;
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+fn engine() -> (MatchingEngine, EventRing) {
+    let mut engine = MatchingEngine::new();
+    let ring = engine.enable_event_ring(1_000);
+    engine.enable_journal();
+    engine.add_symbol("AAPL");
+    engine.add_symbol("MSFT");
+    (engine, ring)
+}
+
+fn place(
+    engine: &mut MatchingEngine,
+    symbol: &str,
+    side: Side,
+    price: Price,
+    quantity: u32,
+    user_id: u64,
+) -> TradeExecutionResult {
+    let order = Order::new(symbol.to_string(), side, OrderType::Limit, price, quantity, user_id);
+    engine.place_order(order).unwrap()
+}
+
+/// User 1 sells AAPL to users 2 and 3 and buys MSFT from user 2. Returns
+/// the id of the first AAPL trade.
+fn trade(engine: &mut MatchingEngine) -> u64 {
+    place(engine, "AAPL", Side::Sell, 100, 10, 1);
+    let first = place(engine, "AAPL", Side::Buy, 100, 4, 2).trades[0].id;
+    place(engine, "AAPL", Side::Buy, 101, 3, 3);
+    place(engine, "MSFT", Side::Sell, 50, 5, 2);
+    place(engine, "MSFT", Side::Buy, 50, 5, 1);
+    first
+}
+
+fn first_trade(events: &[SequencedEvent]) -> SequencedEvent {
+    events
+        .iter()
+        .find(|event| matches!(event.event, EngineEvent::Trade { .. }))
+        .cloned()
+        .unwrap()
+}
+
+#[test]
+fn test_positions_and_credit_follow_trades_and_busts() {
+    let (mut engine, ring) = engine();
+    engine
+        .set_fee_schedule(
+            "AAPL",
+            FeeSchedule {
+                maker_bps: 0,
+                taker_bps: 0,
+                min_fee: Some(2),
+            },
+        )
+        .unwrap();
+    let events = engine.subscribe_sequenced();
+    let mut positions = EventSourced::<Positions>::new();
+    let mut credit = EventSourced::<CreditLedger>::new();
+
+    let first = trade(&mut engine);
+    positions.drain(&events).unwrap();
+    credit.catch_up(&ring.events()).unwrap();
+    assert_eq!(positions.cursor(), engine.event_sequence());
+    assert_eq!(credit.cursor(), engine.event_sequence());
+
+    let seller = positions.state().get(1, "AAPL").unwrap();
+    assert_eq!((seller.sold, seller.sell_notional, seller.net_quantity()), (7, 700, -7));
+    assert_eq!(positions.state().get(1, "MSFT").unwrap().net_quantity(), 5);
+    assert_eq!(positions.state().get(3, "AAPL").unwrap().buy_notional, 300);
+    assert_eq!(positions.state().for_user(2).count(), 2);
+
+    let exposure = credit.state().get(1).unwrap();
+    assert_eq!((exposure.sell_notional, exposure.buy_notional), (700, 250));
+    assert_eq!(credit.state().get(2).unwrap().fees, 0);
+    assert_eq!(exposure.net(), 250 - 700);
+
+    engine.bust_trade("AAPL", first).unwrap();
+    positions.drain(&events).unwrap();
+    credit.catch_up(&ring.events()).unwrap();
+    assert_eq!(positions.state().get(1, "AAPL").unwrap().sold, 3);
+    assert_eq!(positions.state().get(2, "AAPL").unwrap().net_quantity(), 0);
+    assert_eq!(credit.state().get(1).unwrap().sell_notional, 300);
+}
+
+#[test]
+fn test_rebuild_detects_and_repairs_drift() {
+    let (mut engine, ring) = engine();
+    let events = engine.subscribe_sequenced();
+    let mut positions = EventSourced::<Positions>::new();
+    let mut credit = EventSourced::<CreditLedger>::new();
+    let alerts = positions.subscribe();
+    trade(&mut engine);
+    positions.drain(&events).unwrap();
+    credit.catch_up(&ring.events()).unwrap();
+
+    let report = positions.rebuild(&ring.events()).unwrap();
+    assert_eq!(report.divergence, None);
+    assert_eq!((report.from, report.through), (0, engine.event_sequence()));
+    assert!(alerts.try_recv().is_err());
+
+    // A trade applied outside the stream, as by a consumer that also took
+    // fills from another feed.
+    let duplicated = first_trade(&ring.events());
+    positions.apply_unsequenced(&duplicated.event);
+    credit.apply_unsequenced(&duplicated.event);
+    assert_eq!(positions.state().get(1, "AAPL").unwrap().sold, 11);
+
+    let report = positions.rebuild(&ring.events()).unwrap();
+    let divergence = report.divergence.unwrap();
+    assert_eq!(divergence.sequence, duplicated.sequence);
+    assert_eq!(divergence.keys, vec![(1, "AAPL".to_string()), (2, "AAPL".to_string())]);
+    assert_eq!(alerts.try_recv().unwrap(), divergence);
+    assert_eq!(positions.state().get(1, "AAPL").unwrap().sold, 7);
+    assert_eq!(positions.rebuild(&ring.events()).unwrap().divergence, None);
+
+    // Credit is kept per user, and both users last traded in MSFT, after
+    // the duplicated trade.
+    let last_trade = ring
+        .events()
+        .into_iter()
+        .rfind(|event| matches!(event.event, EngineEvent::Trade { .. }))
+        .unwrap();
+    let divergence = credit.rebuild(&ring.events()).unwrap().divergence.unwrap();
+    assert_eq!((divergence.sequence, divergence.keys), (last_trade.sequence, vec![1, 2]));
+    assert_eq!(credit.state().get(2).unwrap().buy_notional, 400);
+}
+
+#[test]
+fn test_journal_replay_reproduces_the_event_stream() {
+    let (mut engine, ring) = engine();
+    let first = trade(&mut engine);
+    engine.bust_trade("AAPL", first).unwrap();
+    engine.cancel_order("AAPL", 1);
+
+    let replayed =
+        MatchingEngine::replay_events(MatchingEngineConfig::default(), engine.journal().unwrap())
+            .unwrap();
+    let live = ring.events();
+    assert_eq!(replayed.len(), live.len());
+    for (replayed, live) in replayed.iter().zip(&live) {
+        assert_eq!(replayed.sequence, live.sequence);
+        assert_eq!(replayed.event.symbol(), live.event.symbol());
+    }
+
+    let mut positions = EventSourced::<Positions>::new();
+    positions.catch_up(&live).unwrap();
+    positions.apply_unsequenced(&first_trade(&live).event);
+    // The bust was the last event the live state applied to both keys.
+    let bust = live
+        .iter()
+        .find(|event| matches!(event.event, EngineEvent::TradeBusted(_)))
+        .unwrap();
+    let divergence = positions.rebuild(&replayed).unwrap().divergence.unwrap();
+    assert_eq!(divergence.sequence, bust.sequence);
+    assert_eq!(positions.state().get(1, "AAPL").unwrap().sold, 3);
+}
+
+#[test]
+fn test_recovers_from_checkpoint_and_rebuilds_past_the_ring() {
+    let path = std::env::temp_dir().join(format!("positions_{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut engine = MatchingEngine::new();
+    let ring = engine.enable_event_ring(4);
+    engine.add_symbol("AAPL");
+    engine.add_symbol("MSFT");
+    let events = engine.subscribe_sequenced();
+    let unchecked_events = engine.subscribe_sequenced();
+    let mut positions = EventSourced::<Positions>::recover(&path)
+        .unwrap()
+        .with_checkpoint_interval(5);
+    let mut unchecked = EventSourced::<Positions>::new();
+    trade(&mut engine);
+    positions.drain(&events).unwrap();
+    unchecked.drain(&unchecked_events).unwrap();
+    let checkpoint = positions.checkpoint().unwrap().sequence;
+    assert_eq!(checkpoint, engine.event_sequence() / 5 * 5);
+
+    let mut recovered = EventSourced::<Positions>::recover(&path).unwrap();
+    assert_eq!(recovered.cursor(), checkpoint);
+    recovered
+        .catch_up(&ring.events_after(checkpoint).unwrap())
+        .unwrap();
+    assert_eq!(recovered.cursor(), positions.cursor());
+    assert_eq!(recovered.state().get(1, "AAPL"), positions.state().get(1, "AAPL"));
+
+    // The ring no longer reaches the first event, so a rebuild starts at
+    // the checkpoint, and is impossible without one.
+    assert!(ring.events_after(0).is_none());
+    let report = recovered.rebuild(&ring.events()).unwrap();
+    assert_eq!((report.from, report.divergence), (checkpoint, None));
+    assert_eq!(
+        unchecked.rebuild(&ring.events()),
+        Err(ProjectionError::Unavailable {
+            after: 0,
+            through: engine.event_sequence()
+        })
+    );
+
+    assert_eq!(
+        EventSourced::<Positions>::new().apply(&ring.events()[0]),
+        Err(ProjectionError::Gap {
+            expected: 1,
+            found: ring.first_sequence().unwrap()
+        })
+    );
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_admin_rebuilds_positions() {
+    let (mut engine, ring) = engine();
+    let events = engine.subscribe_sequenced();
+    let ledgers = Ledgers {
+        positions: Arc::new(Mutex::new(EventSourced::new())),
+        credit: Arc::new(Mutex::new(EventSourced::new())),
+        events: ring.clone(),
+    };
+    let admin = AdminServer::new().with_ledgers(ledgers.clone());
+    trade(&mut engine);
+    for event in events.try_iter() {
+        ledgers.positions.lock().apply(&event).unwrap();
+        ledgers.credit.lock().apply(&event).unwrap();
+    }
+
+    ledgers
+        .credit
+        .lock()
+        .apply_unsequenced(&first_trade(&ring.events()).event);
+    let response = admin.handle("POST", "/positions/rebuild", b"");
+    assert_eq!(response.status, 200);
+    let reports: serde_json::Value = serde_json::from_str(&response.body).unwrap();
+    assert!(reports["positions"]["divergence"].is_null());
+    assert_eq!(reports["credit"]["divergence"]["keys"], serde_json::json!([1, 2]));
+    assert_eq!(reports["credit"]["through"], engine.event_sequence());
+
+    assert_eq!(admin.handle("GET", "/positions/rebuild", b"").status, 405);
+    assert_eq!(AdminServer::new().handle("POST", "/positions/rebuild", b"").status, 404);
+}
@@ -0,0 +1,128 @@
+use exchange_rs::{
+    matching_engine::MatchingEngine,
+    order::{Order, OrderType, Side},
+    redaction::{AuditSink, FieldClass, RedactionError, RedactionPolicy, TokenizationKey},
+};
+use serde_json::{json, Value};
+
+const USER_IDS: [u64; 2] = [48_211, 73_905];
+
+fn journaled_engine() -> MatchingEngine {
+    let mut engine = MatchingEngine::new();
+    engine.enable_journal();
+    engine.add_symbol("AAPL");
+    for (price, user_id) in [(100, USER_IDS[0]), (101, USER_IDS[1]), (102, USER_IDS[0])] {
+        engine
+            .place_order(Order::new(
+                "AAPL".to_string(),
+                Side::Sell,
+                OrderType::Limit,
+                price,
+                5,
+                user_id,
+            ))
+            .unwrap();
+    }
+    engine.cancel_all_for_user(USER_IDS[1]);
+    engine
+}
+
+fn lines(bytes: Vec<u8>) -> Vec<Value> {
+    String::from_utf8(bytes)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+fn key() -> TokenizationKey {
+    TokenizationKey::from_bytes(b"export-key-2026").unwrap()
+}
+
+#[test]
+fn test_user_id_tokenizes_identically_across_records() {
+    let engine = journaled_engine();
+    let policy = RedactionPolicy::new().tokenize(FieldClass::UserId);
+    let mut export = AuditSink::export("regulator", Vec::new(), policy, Some(key())).unwrap();
+    export.write_journal(engine.journal().unwrap()).unwrap();
+    let records = lines(export.into_inner());
+
+    let placed_by = |sequence: usize| records[sequence]["command"]["PlaceOrder"]["user_id"].clone();
+    let token = placed_by(1);
+    assert!(token.as_str().unwrap().starts_with("tok_"));
+    assert_eq!(placed_by(3), token);
+    assert_ne!(placed_by(2), token);
+    assert_eq!(
+        records[4]["command"]["CancelAllForUser"]["user_id"],
+        placed_by(2)
+    );
+
+    // Another export with the same key joins on the same tokens.
+    let mut second = AuditSink::export(
+        "vendor",
+        Vec::new(),
+        RedactionPolicy::new().tokenize(FieldClass::UserId),
+        Some(key()),
+    )
+    .unwrap();
+    second.write_journal(engine.journal().unwrap()).unwrap();
+    assert_eq!(lines(second.into_inner())[1], records[1]);
+
+    let other_key = TokenizationKey::from_bytes(b"another-key").unwrap();
+    assert_ne!(other_key.tokenize("48211"), key().tokenize("48211"));
+}
+
+#[test]
+fn test_redacted_export_contains_no_raw_identifiers() {
+    let engine = journaled_engine();
+
+    let mut audit = AuditSink::complete("audit", Vec::new());
+    audit.write_journal(engine.journal().unwrap()).unwrap();
+    let audit = String::from_utf8(audit.into_inner()).unwrap();
+    assert!(USER_IDS
+        .iter()
+        .all(|user_id| audit.contains(&user_id.to_string())));
+
+    let policy = RedactionPolicy::new()
+        .tokenize(FieldClass::UserId)
+        .drop(FieldClass::ClientOrderId)
+        .drop(FieldClass::CustomTag);
+    let mut export = AuditSink::export("third-party", Vec::new(), policy, Some(key())).unwrap();
+    export.write_journal(engine.journal().unwrap()).unwrap();
+    export
+        .write_record(&json!({
+            "cl_ord_id": "CLIENT-ORD-1",
+            "account": "ACCT-99",
+            "tags": {"5001": "desk-7"},
+            "symbol": "AAPL",
+        }))
+        .unwrap();
+    let exported = String::from_utf8(export.into_inner()).unwrap();
+
+    for raw in ["48211", "73905", "CLIENT-ORD-1", "ACCT-99", "desk-7"] {
+        assert!(!exported.contains(raw), "export leaks {}", raw);
+    }
+    let last: Value = serde_json::from_str(exported.lines().last().unwrap()).unwrap();
+    assert_eq!(last.as_object().unwrap().len(), 2);
+    assert_eq!(last["symbol"], "AAPL");
+}
+
+#[test]
+fn test_tokenizing_sink_requires_key() {
+    let policy = RedactionPolicy::new().tokenize(FieldClass::UserId);
+    assert!(matches!(
+        AuditSink::export("vendor", Vec::new(), policy, None),
+        Err(RedactionError::MissingKey { sink }) if sink == "vendor"
+    ));
+    assert!(AuditSink::export(
+        "vendor",
+        Vec::new(),
+        RedactionPolicy::new().drop(FieldClass::UserId),
+        None
+    )
+    .is_ok());
+    assert!(matches!(
+        TokenizationKey::from_bytes(b""),
+        Err(RedactionError::EmptyKey)
+    ));
+}
@@ -0,0 +1,393 @@
+use exchange_rs::{
+    clock::{system_clock, ManualClock},
+    matching_engine::{MatchingEngine, MatchingEngineConfig},
+    order::{Order, OrderType, Side},
+    orderbook::{PriceBands, TradingState},
+    replication::{
+        FollowerHandle, ReplicationError, ReplicationFollower, ReplicationLog, ReplicationMetrics,
+        ReplicationPrimary, ReplicationRecord, ReplicationServer,
+    },
+};
+use exchange_rs::Price;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const START: i64 = 1_700_000_000_000_000_000;
+const MILLI: i64 = 1_000_000;
+
+fn log_path(name: &str) -> PathBuf {
+    let path =
+        std::env::temp_dir().join(format!("replication_{}_{}.log", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn primary_at(path: &PathBuf) -> (Arc<ManualClock>, ReplicationPrimary) {
+    let clock = Arc::new(ManualClock::new(START));
+    let engine = MatchingEngine::with_clock(MatchingEngineConfig::default(), clock.clone());
+    let primary = ReplicationPrimary::new(engine, ReplicationLog::open(path).unwrap()).unwrap();
+    (clock, primary)
+}
+
+fn place(
+    primary: &mut ReplicationPrimary,
+    side: Side,
+    price: Price,
+    quantity: u32,
+    user_id: u64,
+) -> u64 {
+    let order = Order::new(
+        "BTC".to_string(),
+        side,
+        OrderType::Limit,
+        price,
+        quantity,
+        user_id,
+    );
+    let result = primary
+        .execute(|engine| engine.place_order(order))
+        .unwrap()
+        .unwrap();
+    result.remaining_order.map_or(0, |order| order.read().id)
+}
+
+fn wait_for(follower: &FollowerHandle, check: impl Fn(&ReplicationMetrics) -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !check(&follower.metrics()) {
+        assert!(!follower.is_finished(), "follower stopped");
+        assert!(
+            Instant::now() < deadline,
+            "follower stuck: {:?}",
+            follower.metrics()
+        );
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+fn serve(primary: &ReplicationPrimary, listener: TcpListener) -> ReplicationServer {
+    ReplicationServer::spawn(listener, primary.source(), Duration::from_millis(10)).unwrap()
+}
+
+#[test]
+fn test_follower_replays_with_primary_timestamps() {
+    let path = log_path("timestamps");
+    let (clock, mut primary) = primary_at(&path);
+    primary.execute(|engine| engine.add_symbol("BTC")).unwrap();
+    clock.advance(5 * MILLI);
+    let resting = place(&mut primary, Side::Sell, 101, 10, 1);
+    clock.advance(7 * MILLI);
+    place(&mut primary, Side::Buy, 101, 4, 2);
+
+    let mut follower = ReplicationFollower::new(MatchingEngineConfig::default());
+    let applied = follower
+        .read_from(BufReader::new(File::open(&path).unwrap()))
+        .unwrap();
+    assert_eq!(applied, 3);
+
+    let book = &follower.engine().order_books["BTC"];
+    let order = book.get_order(resting).unwrap();
+    let original = primary.engine().order_books["BTC"].get_order(resting).unwrap();
+    assert_eq!(order.read().timestamp, original.read().timestamp);
+    assert_eq!(order.read().last_update, START + 12 * MILLI);
+    assert_eq!(follower.metrics().applied_timestamp, START + 12 * MILLI);
+    assert_eq!(
+        follower.engine().state_digest(),
+        primary.engine().state_digest()
+    );
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_follower_resumes_after_reconnect_and_promotes() {
+    let path = log_path("promote");
+    let (clock, mut primary) = primary_at(&path);
+    primary.execute(|engine| engine.add_symbol("BTC")).unwrap();
+    place(&mut primary, Side::Sell, 101, 10, 1);
+    let cancelled = place(&mut primary, Side::Sell, 102, 5, 2);
+    place(&mut primary, Side::Buy, 99, 7, 3);
+
+    let server = serve(&primary, TcpListener::bind("127.0.0.1:0").unwrap());
+    let address = server.local_addr();
+    let follower = ReplicationFollower::new(MatchingEngineConfig::default()).spawn(address);
+    wait_for(&follower, |metrics| metrics.applied_sequence == 4);
+
+    // Entries logged while the stream is up arrive on the same connection.
+    clock.advance(MILLI);
+    place(&mut primary, Side::Buy, 101, 4, 4);
+    wait_for(&follower, |metrics| metrics.applied_sequence == 5);
+    assert_eq!(follower.metrics().connects, 1);
+
+    // The primary keeps going while the stream is down; the follower sees
+    // how far behind it is once it reconnects, and catches up.
+    server.shutdown();
+    wait_for(&follower, |metrics| metrics.disconnects > 0);
+    clock.advance(MILLI);
+    assert!(primary
+        .execute(|engine| engine.cancel_order("BTC", cancelled))
+        .unwrap()
+        .is_some());
+    primary
+        .execute(|engine| engine.replace_order("BTC", 3, 98, 9))
+        .unwrap()
+        .unwrap();
+    let manifest = primary.checkpoint().unwrap();
+
+    let server = serve(&primary, TcpListener::bind(address).unwrap());
+    wait_for(&follower, |metrics| metrics.checkpoints_verified == 1);
+    let metrics = follower.metrics();
+    assert_eq!(metrics.applied_sequence, 7);
+    assert_eq!(metrics.lag(), 0);
+    assert_eq!(metrics.records_applied, 7);
+    assert!(metrics.connects >= 2);
+
+    let follower = follower.stop().unwrap();
+    drop(server);
+    let book = &follower.engine().order_books["BTC"];
+    assert_eq!(book.get_best_ask_price(), Some(101));
+    assert_eq!(book.get_best_bid_price(), Some(98));
+    assert_eq!(book.sell_levels[&101].total_volume, 6);
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async {
+        let gateway = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let gateway_address = gateway.local_addr().unwrap();
+        let promoted = follower.promote(&manifest, system_clock(), vec![gateway]).unwrap();
+        assert_eq!(promoted.acceptors.len(), 1);
+        assert!(promoted.engine.lock().order_books.contains_key("BTC"));
+
+        // The acceptor answers FIX traffic for the promoted engine.
+        let mut client = tokio::net::TcpStream::connect(gateway_address).await.unwrap();
+        tokio::io::AsyncWriteExt::write_all(
+            &mut client,
+            b"8=FIX.4.4\x019=50\x0135=0\x0149=CLIENT\x0156=EXCHANGE\x0134=1\x0152=20240101-12:00:03\x0110=161\x01",
+        )
+        .await
+        .unwrap();
+        let mut reply = [0u8; 256];
+        let read = tokio::time::timeout(
+            Duration::from_secs(5),
+            tokio::io::AsyncReadExt::read(&mut client, &mut reply),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(reply[..read].starts_with(b"8=FIX"));
+    });
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_follower_reports_lag_from_heartbeats() {
+    let path = log_path("lag");
+    let (clock, mut primary) = primary_at(&path);
+    primary.execute(|engine| engine.add_symbol("BTC")).unwrap();
+    clock.advance(3 * MILLI);
+    place(&mut primary, Side::Sell, 101, 10, 1);
+
+    let mut follower = ReplicationFollower::new(MatchingEngineConfig::default());
+    let mut log = BufReader::new(File::open(&path).unwrap());
+    let mut first = String::new();
+    std::io::BufRead::read_line(&mut log, &mut first).unwrap();
+    follower
+        .apply(&serde_json::from_str(&first).unwrap())
+        .unwrap();
+    follower
+        .apply(&ReplicationRecord::Heartbeat {
+            sequence: primary.last_sequence(),
+            timestamp: START + 3 * MILLI,
+        })
+        .unwrap();
+
+    let metrics = follower.metrics();
+    assert_eq!(metrics.lag(), 1);
+    assert_eq!(metrics.lag_nanos(), 3 * MILLI);
+
+    follower.read_from(log).unwrap();
+    assert_eq!(follower.metrics().lag(), 0);
+    assert_eq!(follower.metrics().lag_nanos(), 0);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_log_survives_reopen_and_cuts_torn_record() {
+    let path = log_path("reopen");
+    {
+        let (_, mut primary) = primary_at(&path);
+        primary.execute(|engine| engine.add_symbol("BTC")).unwrap();
+        place(&mut primary, Side::Sell, 101, 10, 1);
+        assert_eq!(primary.last_sequence(), 2);
+        // The engine journal only keeps what the log has not taken.
+        assert!(primary.engine().journal().unwrap().entries().is_empty());
+    }
+
+    let length = fs::metadata(&path).unwrap().len();
+    OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap()
+        .write_all(b"{\"Entry\":{\"sequence\":3,")
+        .unwrap();
+
+    let log = ReplicationLog::open(&path).unwrap();
+    assert_eq!(log.last_sequence(), 2);
+    assert_eq!(fs::metadata(&path).unwrap().len(), length);
+
+    // A fresh engine is not at the log's sequence.
+    let error = ReplicationPrimary::new(MatchingEngine::new(), log)
+        .err()
+        .unwrap();
+    assert!(matches!(
+        error,
+        ReplicationError::LogMismatch { engine: 0, log: 2 }
+    ));
+
+    let mut contents = String::new();
+    File::open(&path)
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    assert_eq!(contents.lines().count(), 2);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_follower_detects_gap_and_refuses_stale_promotion() {
+    let path = log_path("gap");
+    let (_, mut primary) = primary_at(&path);
+    primary.execute(|engine| engine.add_symbol("BTC")).unwrap();
+    place(&mut primary, Side::Sell, 101, 10, 1);
+    place(&mut primary, Side::Buy, 99, 7, 2);
+
+    let mut contents = String::new();
+    File::open(&path)
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    let lines: Vec<&str> = contents.lines().collect();
+
+    let mut follower = ReplicationFollower::new(MatchingEngineConfig::default());
+    let error = follower.read_from(lines[1].as_bytes()).unwrap_err();
+    assert!(matches!(
+        error,
+        ReplicationError::SequenceGap {
+            expected: 1,
+            received: 2
+        }
+    ));
+    assert_eq!(follower.last_applied(), 0);
+
+    follower.read_from(lines[0].as_bytes()).unwrap();
+    let promotion = follower.promote(&primary.manifest(), system_clock(), Vec::new());
+    assert!(matches!(
+        promotion.err(),
+        Some(ReplicationError::Behind {
+            applied: 1,
+            required: 3
+        })
+    ));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_checkpoint_mismatch_stops_the_follower() {
+    let path = log_path("mismatch");
+    let (_, mut primary) = primary_at(&path);
+    primary.execute(|engine| engine.add_symbol("BTC")).unwrap();
+    place(&mut primary, Side::Sell, 101, 10, 1);
+
+    // A checkpoint taken from a primary whose book differs.
+    let other_path = log_path("mismatch_other");
+    let (_, mut other) = primary_at(&other_path);
+    other.execute(|engine| engine.add_symbol("BTC")).unwrap();
+    place(&mut other, Side::Sell, 102, 10, 1);
+    let wrong = other.checkpoint().unwrap();
+
+    let mut follower = ReplicationFollower::new(MatchingEngineConfig::default());
+    follower
+        .read_from(BufReader::new(File::open(&path).unwrap()))
+        .unwrap();
+    follower
+        .apply(&ReplicationRecord::Checkpoint(primary.manifest()))
+        .unwrap();
+    match follower.apply(&ReplicationRecord::Checkpoint(wrong)) {
+        Err(ReplicationError::DigestMismatch {
+            sequence,
+            components,
+        }) => {
+            assert_eq!(sequence, 2);
+            assert!(
+                components.contains(&"book:BTC".to_string()),
+                "{:?}",
+                components
+            );
+        }
+        other => panic!("expected a digest mismatch, got {:?}", other.err()),
+    }
+    assert_eq!(follower.metrics().checkpoints_verified, 1);
+    fs::remove_file(&path).unwrap();
+    fs::remove_file(&other_path).unwrap();
+}
+
+/// Every kind of command the primary journals reaches the follower, which
+/// ends in the same state.
+#[test]
+fn test_follower_replicates_every_command_kind() {
+    let path = log_path("commands");
+    let (clock, mut primary) = primary_at(&path);
+    primary.execute(|engine| engine.add_symbol("BTC")).unwrap();
+    primary.execute(|engine| engine.add_symbol("ETH")).unwrap();
+    let resting = place(&mut primary, Side::Sell, 101, 10, 1);
+    place(&mut primary, Side::Buy, 101, 2, 2);
+    primary
+        .execute(|engine| engine.replace_order("BTC", resting, 103, 6))
+        .unwrap()
+        .unwrap();
+    primary
+        .execute(|engine| engine.submit_quote("ETH", 7, 50, 5, 52, 5))
+        .unwrap()
+        .unwrap();
+    primary
+        .execute(|engine| {
+            engine.set_price_bands(
+                "BTC",
+                PriceBands {
+                    reference_price: Some(100),
+                    static_band_bps: Some(2_000),
+                    dynamic_band_bps: None,
+                    circuit_breaker_bps: None,
+                },
+            )
+        })
+        .unwrap()
+        .unwrap();
+    primary
+        .execute(|engine| engine.set_trading_state("ETH", TradingState::Halted))
+        .unwrap()
+        .unwrap();
+    // A command the engine rejects is still replicated, and rejected again.
+    assert!(primary
+        .execute(|engine| engine.replace_order("BTC", 999, 100, 1))
+        .unwrap()
+        .is_err());
+    clock.advance(MILLI);
+    primary
+        .execute(|engine| engine.cancel_all_for_user(2))
+        .unwrap();
+    primary
+        .execute(|engine| engine.settle_session(START + MILLI))
+        .unwrap();
+    let manifest = primary.checkpoint().unwrap();
+
+    let mut follower = ReplicationFollower::new(MatchingEngineConfig::default());
+    follower
+        .read_from(BufReader::new(File::open(&path).unwrap()))
+        .unwrap();
+    assert_eq!(follower.last_applied(), manifest.sequence);
+    assert_eq!(follower.metrics().checkpoints_verified, 1);
+    fs::remove_file(&path).unwrap();
+}
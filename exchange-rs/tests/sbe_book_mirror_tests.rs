@@ -0,0 +1,196 @@
+use exchange_rs::sbe::book_mirror::{BookMirror, MirrorError};
+use exchange_rs::sbe::parser::{
+    BookChange, BookMessage, SbeMessage, SnapshotEndMessage, SnapshotLevel, SnapshotMessage,
+    SnapshotStartMessage,
+};
+use exchange_rs::sbe::BookChange as ChangeKind;
+use exchange_rs::Price;
+
+const INSTRUMENT: u32 = 7;
+const BID: u8 = 1;
+const ASK: u8 = 0;
+
+fn px(price: f64) -> Price {
+    (price * 1_000_000.0) as Price
+}
+
+fn qty(amount: f64) -> u64 {
+    (amount * 1_000.0) as u64
+}
+
+fn snapshot_cycle(change_id: u64, parts: &[&[(u8, f64, f64)]]) -> Vec<SbeMessage> {
+    let mut messages = vec![SbeMessage::SnapshotStart(SnapshotStartMessage {
+        snapshot_delay: 0,
+    })];
+    for (index, levels) in parts.iter().enumerate() {
+        messages.push(SbeMessage::Snapshot(SnapshotMessage {
+            instrument_id: INSTRUMENT,
+            timestamp_ms: 1_700_000_000_000,
+            change_id,
+            is_book_complete: true,
+            is_last_in_book: index == parts.len() - 1,
+            levels: levels
+                .iter()
+                .map(|&(side, price, amount)| SnapshotLevel {
+                    side,
+                    price,
+                    amount,
+                })
+                .collect(),
+        }));
+    }
+    messages.push(SbeMessage::SnapshotEnd(SnapshotEndMessage));
+    messages
+}
+
+fn delta(
+    prev_change_id: u64,
+    change_id: u64,
+    is_last: bool,
+    changes: &[(u8, ChangeKind, f64, f64)],
+) -> SbeMessage {
+    SbeMessage::Book(BookMessage {
+        instrument_id: INSTRUMENT,
+        timestamp_ms: 1_700_000_000_000 + change_id,
+        prev_change_id,
+        change_id,
+        is_last,
+        changes: changes
+            .iter()
+            .map(|&(side, change, price, amount)| BookChange {
+                side,
+                change: change as u8,
+                price,
+                amount,
+            })
+            .collect(),
+    })
+}
+
+fn seeded() -> BookMirror {
+    let mut mirror = BookMirror::new(INSTRUMENT, "BTC-PERPETUAL");
+    for message in snapshot_cycle(
+        100,
+        &[
+            &[(BID, 49_990.0, 1.0), (ASK, 50_010.0, 2.0)],
+            &[(BID, 49_980.0, 3.0), (ASK, 50_020.0, 0.5)],
+        ],
+    ) {
+        mirror.process(&message).unwrap();
+    }
+    mirror
+}
+
+#[test]
+fn test_snapshot_and_deltas_rebuild_the_book() {
+    let mut mirror = BookMirror::new(INSTRUMENT, "BTC-PERPETUAL");
+    // Changes before the first snapshot have nothing to apply to.
+    let early = delta(98, 99, true, &[(BID, ChangeKind::created, 49_000.0, 1.0)]);
+    assert_eq!(mirror.process(&early), Ok(false));
+    assert!(mirror.needs_snapshot());
+
+    let mut mirror = seeded();
+    assert_eq!(mirror.change_id(), Some(100));
+    assert_eq!(mirror.top_of_book().bid, Some((px(49_990.0), qty(1.0))));
+
+    let recorded = [
+        // Already in the snapshot.
+        delta(99, 100, true, &[(BID, ChangeKind::created, 49_990.0, 1.0)]),
+        delta(
+            100,
+            101,
+            true,
+            &[
+                (BID, ChangeKind::created, 49_995.0, 0.25),
+                (ASK, ChangeKind::changed, 50_010.0, 1.5),
+            ],
+        ),
+        // One change split over two messages.
+        delta(
+            101,
+            102,
+            false,
+            &[(ASK, ChangeKind::deleted, 50_010.0, 0.0)],
+        ),
+        delta(
+            101,
+            102,
+            true,
+            &[
+                (ASK, ChangeKind::created, 50_005.0, 4.0),
+                (BID, ChangeKind::changed, 49_980.0, 0.0),
+            ],
+        ),
+        delta(102, 103, true, &[(BID, ChangeKind::changed, 49_990.0, 2.0)]),
+    ];
+    let applied: Vec<bool> = recorded
+        .iter()
+        .map(|message| mirror.process(message).unwrap())
+        .collect();
+    assert_eq!(applied, vec![false, true, true, true, true]);
+    assert_eq!(mirror.change_id(), Some(103));
+
+    let depth = mirror.get_market_depth();
+    assert_eq!(
+        depth.bid_levels,
+        vec![(px(49_995.0), qty(0.25)), (px(49_990.0), qty(2.0))]
+    );
+    assert_eq!(
+        depth.ask_levels,
+        vec![(px(50_005.0), qty(4.0)), (px(50_020.0), qty(0.5))]
+    );
+    let top = mirror.top_of_book();
+    assert_eq!(
+        (top.bid, top.ask),
+        (
+            Some((px(49_995.0), qty(0.25))),
+            Some((px(50_005.0), qty(4.0)))
+        )
+    );
+    assert_eq!(mirror.market_depth(1).bid_levels.len(), 1);
+}
+
+#[test]
+fn test_gap_needs_a_resync_until_the_next_snapshot() {
+    let mut mirror = seeded();
+    let gap = delta(101, 102, true, &[(BID, ChangeKind::deleted, 49_990.0, 0.0)]);
+    assert_eq!(
+        mirror.process(&gap),
+        Err(MirrorError::ResyncNeeded {
+            instrument_id: INSTRUMENT,
+            expected: 100,
+            got: 101
+        })
+    );
+    assert!(mirror.needs_snapshot());
+    assert_eq!(mirror.process(&gap), Ok(false));
+    // The book is left as it was until a snapshot replaces it.
+    assert_eq!(mirror.get_market_depth().bid_levels.len(), 2);
+
+    for message in snapshot_cycle(102, &[&[(BID, 49_000.0, 1.0)]]) {
+        mirror.process(&message).unwrap();
+    }
+    let next = delta(102, 103, true, &[(ASK, ChangeKind::created, 49_500.0, 2.0)]);
+    assert_eq!(mirror.process(&next), Ok(true));
+    let depth = mirror.get_market_depth();
+    assert_eq!(depth.bid_levels, vec![(px(49_000.0), qty(1.0))]);
+    assert_eq!(depth.ask_levels, vec![(px(49_500.0), qty(2.0))]);
+}
+
+#[test]
+fn test_change_to_an_unknown_level_needs_a_resync() {
+    let mut mirror = seeded();
+    let stray = delta(100, 101, true, &[(ASK, ChangeKind::deleted, 51_000.0, 0.0)]);
+    assert!(matches!(
+        mirror.process(&stray),
+        Err(MirrorError::Inconsistent { change_id: 101, .. })
+    ));
+    assert!(mirror.needs_snapshot());
+
+    // Other instruments' changes are not the mirror's.
+    let mut other = delta(100, 101, true, &[(ASK, ChangeKind::deleted, 51_000.0, 0.0)]);
+    if let SbeMessage::Book(book) = &mut other {
+        book.instrument_id = INSTRUMENT + 1;
+    }
+    assert_eq!(seeded().process(&other), Ok(false));
+}
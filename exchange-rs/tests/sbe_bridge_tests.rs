@@ -0,0 +1,290 @@
+use exchange_rs::matching_engine::MatchingEngine;
+use exchange_rs::order::{Order, OrderType, Side, TriggerSource};
+use exchange_rs::orderbook::{OrderBook, SymbolSpec};
+use exchange_rs::price_utils::{float_to_scaled_price, float_to_scaled_quantity};
+use exchange_rs::sbe::bridge::{BridgeError, MarketDataUpdate, SbeBridge, SbeBridgeConfig, TradeDedupStats};
+use exchange_rs::sbe::parser::{
+    BookChange, BookMessage, InstrumentMessage, SbeMessage, SnapshotLevel, SnapshotMessage, TickerMessage, Trade,
+    TradesMessage,
+};
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+const INSTRUMENT_ID: u32 = 7;
+
+fn bridge_with_window(trade_dedup_window: usize) -> SbeBridge {
+    let bridge = SbeBridge::with_config(SbeBridgeConfig { trade_dedup_window });
+    let instrument = InstrumentMessage {
+        instrument_id: INSTRUMENT_ID,
+        instrument_state: 1,
+        kind: 0,
+        instrument_type: 2,
+        option_type: 0,
+        rfq: 0,
+        settlement_period: None,
+        settlement_period_count: 0,
+        base_currency: "BTC".to_string(),
+        quote_currency: "USD".to_string(),
+        counter_currency: "USD".to_string(),
+        settlement_currency: "BTC".to_string(),
+        size_currency: "USD".to_string(),
+        creation_timestamp_ms: 0,
+        expiration_timestamp_ms: 0,
+        strike_price: None,
+        contract_size: 10.0,
+        min_trade_amount: 10.0,
+        tick_size: 0.5,
+        maker_commission: 0.0,
+        taker_commission: 0.0,
+        block_trade_commission: None,
+        max_liquidation_commission: None,
+        max_leverage: None,
+        instrument_name: "BTC-PERPETUAL".to_string(),
+    };
+    bridge.process_message(SbeMessage::Instrument(instrument)).unwrap();
+    bridge
+}
+
+fn trade(trade_id: u64, trade_seq: u64, price: f64) -> Trade {
+    Trade {
+        direction: 0,
+        price,
+        amount: 10.0,
+        timestamp_ms: 1_700_000_000_000 + trade_seq,
+        mark_price: price,
+        index_price: price,
+        trade_seq,
+        trade_id,
+        tick_direction: 0,
+        liquidation: 0,
+        iv: None,
+        block_trade_id: None,
+        combo_trade_id: None,
+    }
+}
+
+fn trades_message(trades: Vec<Trade>) -> SbeMessage {
+    SbeMessage::Trades(TradesMessage {
+        instrument_id: INSTRUMENT_ID,
+        trades,
+    })
+}
+
+#[test]
+fn test_replayed_trades_message_is_suppressed() {
+    let bridge = bridge_with_window(16);
+    let message = || trades_message(vec![trade(100, 1, 50_000.0), trade(101, 2, 50_010.0)]);
+
+    let updates = bridge.process_message(message()).unwrap();
+    assert_eq!(updates.len(), 1);
+    assert_eq!(updates[0].last_price, Some(50_010.0));
+
+    assert!(bridge.process_message(message()).unwrap().is_empty());
+    assert_eq!(
+        bridge.trade_dedup_stats(),
+        TradeDedupStats {
+            duplicates_suppressed: 2,
+            ..Default::default()
+        }
+    );
+
+    // A batch overlapping the last one only reports the new trade.
+    let updates = bridge
+        .process_message(trades_message(vec![trade(101, 2, 50_010.0), trade(102, 3, 50_020.0)]))
+        .unwrap();
+    assert_eq!(updates[0].last_price, Some(50_020.0));
+    assert_eq!(bridge.trade_dedup_stats().duplicates_suppressed, 3);
+}
+
+#[test]
+fn test_trade_seq_gap_and_regression_are_counted() {
+    let bridge = bridge_with_window(16);
+
+    bridge.process_message(trades_message(vec![trade(1, 10, 100.0)])).unwrap();
+    let updates = bridge
+        .process_message(trades_message(vec![trade(2, 13, 101.0), trade(3, 12, 102.0)]))
+        .unwrap();
+    assert_eq!(updates[0].last_price, Some(102.0));
+
+    assert_eq!(
+        bridge.trade_dedup_stats(),
+        TradeDedupStats {
+            duplicates_suppressed: 0,
+            trade_seq_gaps: 1,
+            trade_seq_out_of_order: 1,
+        }
+    );
+}
+
+#[test]
+fn test_dedup_window_is_bounded() {
+    let bridge = bridge_with_window(2);
+
+    for (trade_id, trade_seq) in [(1, 1), (2, 2), (3, 3)] {
+        bridge.process_message(trades_message(vec![trade(trade_id, trade_seq, 100.0)])).unwrap();
+    }
+
+    // Trade 1 has been evicted, so its redelivery is let through.
+    assert!(bridge.process_message(trades_message(vec![trade(3, 3, 100.0)])).unwrap().is_empty());
+    assert_eq!(bridge.process_message(trades_message(vec![trade(1, 1, 100.0)])).unwrap().len(), 1);
+
+    let unbounded = bridge_with_window(0);
+    for _ in 0..2 {
+        assert_eq!(unbounded.process_message(trades_message(vec![trade(1, 1, 100.0)])).unwrap().len(), 1);
+    }
+    assert_eq!(unbounded.trade_dedup_stats(), TradeDedupStats::default());
+}
+
+fn ticker(mark_price: f64, index_price: f64) -> SbeMessage {
+    SbeMessage::Ticker(TickerMessage {
+        instrument_id: INSTRUMENT_ID,
+        instrument_state: 1,
+        timestamp_ms: 1_700_000_000_000,
+        open_interest: None,
+        min_sell_price: 0.0,
+        max_buy_price: 0.0,
+        last_price: None,
+        index_price,
+        mark_price,
+        best_bid_price: 0.0,
+        best_bid_amount: 0.0,
+        best_ask_price: 0.0,
+        best_ask_amount: 0.0,
+        current_funding: None,
+        funding_8h: None,
+        estimated_delivery_price: None,
+        delivery_price: None,
+        settlement_price: None,
+    })
+}
+
+#[test]
+fn test_ticker_feeds_mark_and_index_prices() {
+    let bridge = bridge_with_window(16);
+    // Tickers are harmless before an engine is connected.
+    assert_eq!(bridge.process_message(ticker(50_000.0, 49_990.0)).unwrap().len(), 1);
+
+    let engine = Arc::new(RwLock::new(MatchingEngine::new()));
+    engine.write().add_symbol("BTC-PERPETUAL");
+    bridge.connect_engine(Arc::clone(&engine)).unwrap();
+    assert_eq!(
+        engine.read().symbol_spec("BTC-PERPETUAL"),
+        Some(SymbolSpec { tick_size: float_to_scaled_price(0.5).unwrap(), lot_size: 10_000, min_qty: 10_000 })
+    );
+
+    let stop_price = float_to_scaled_price(49_000.0).unwrap();
+    // Quantities are scaled, and the instrument trades in lots of 10.0.
+    let mut stop = Order::new("BTC-PERPETUAL".to_string(), Side::Sell, OrderType::StopLimit, stop_price, 10_000, 1);
+    stop.stop_price = Some(stop_price);
+    stop.trigger_source = Some(TriggerSource::MarkPrice);
+    engine.write().place_order(stop).unwrap();
+
+    bridge.process_message(ticker(49_500.0, 48_000.0)).unwrap();
+    {
+        let engine = engine.read();
+        let book = engine.order_books.get("BTC-PERPETUAL").unwrap();
+        assert_eq!(book.mark_price, Some(float_to_scaled_price(49_500.0).unwrap()));
+        assert_eq!(book.index_price, Some(float_to_scaled_price(48_000.0).unwrap()));
+        assert_eq!(book.stop_order_count(), 1);
+    }
+
+    bridge.process_message(ticker(48_900.0, 48_000.0)).unwrap();
+    let engine = engine.read();
+    let book = engine.order_books.get("BTC-PERPETUAL").unwrap();
+    assert_eq!(book.stop_order_count(), 0);
+    assert_eq!(book.get_best_ask_price(), Some(stop_price));
+    assert_eq!(book.last_trade_price, None);
+}
+
+fn book(prev_change_id: u64, change_id: u64, is_last: bool, price: f64) -> SbeMessage {
+    SbeMessage::Book(BookMessage {
+        instrument_id: INSTRUMENT_ID,
+        timestamp_ms: 1_700_000_000_000,
+        prev_change_id,
+        change_id,
+        is_last,
+        changes: vec![BookChange { side: 1, change: 0, price, amount: 10.0 }],
+    })
+}
+
+fn snapshot(change_id: u64) -> SbeMessage {
+    SbeMessage::Snapshot(SnapshotMessage {
+        instrument_id: INSTRUMENT_ID,
+        timestamp_ms: 1_700_000_000_000,
+        change_id,
+        is_book_complete: true,
+        is_last_in_book: true,
+        levels: vec![SnapshotLevel { side: 1, price: 100.0, amount: 10.0 }],
+    })
+}
+
+#[test]
+fn test_book_gap_requires_snapshot() {
+    let bridge = bridge_with_window(16);
+
+    assert_eq!(bridge.process_message(book(9, 10, true, 100.0)).unwrap()[0].best_bid, Some((100.0, 10.0)));
+    // A change split over two messages shares its ids.
+    bridge.process_message(book(10, 11, false, 101.0)).unwrap();
+    bridge.process_message(book(10, 11, true, 102.0)).unwrap();
+    assert!(!bridge.needs_snapshot(INSTRUMENT_ID));
+
+    // Change 12 was lost.
+    assert!(matches!(
+        bridge.process_message(book(12, 13, true, 103.0)),
+        Err(BridgeError::SequenceGap { expected: 11, got: 12 })
+    ));
+    assert!(bridge.needs_snapshot(INSTRUMENT_ID));
+    assert!(bridge.process_message(book(13, 14, true, 104.0)).unwrap().is_empty());
+
+    bridge.process_message(snapshot(20)).unwrap();
+    assert!(!bridge.needs_snapshot(INSTRUMENT_ID));
+    assert!(matches!(
+        bridge.process_message(book(14, 15, true, 105.0)),
+        Err(BridgeError::SequenceGap { expected: 20, got: 14 })
+    ));
+    bridge.process_message(snapshot(20)).unwrap();
+    assert_eq!(bridge.process_message(book(20, 21, true, 106.0)).unwrap()[0].best_bid, Some((106.0, 10.0)));
+}
+
+fn top_of_book(best_bid: Option<(f64, f64)>, best_ask: Option<(f64, f64)>) -> MarketDataUpdate {
+    MarketDataUpdate {
+        instrument_id: INSTRUMENT_ID,
+        symbol: "BTC-PERPETUAL".to_string(),
+        timestamp: 0,
+        best_bid,
+        best_ask,
+        last_price: None,
+        mark_price: None,
+        index_price: None,
+    }
+}
+
+#[test]
+fn test_market_data_maintains_synthetic_top_of_book() {
+    let bridge = bridge_with_window(16);
+    let mut book = OrderBook::new("BTC-PERPETUAL");
+    let price = |p: f64| float_to_scaled_price(p).unwrap();
+
+    bridge
+        .update_orderbook_from_market_data(&mut book, &top_of_book(Some((49_990.0, 2.0)), Some((50_010.0, 3.0))))
+        .unwrap();
+    assert_eq!(book.get_best_bid_price(), Some(price(49_990.0)));
+    assert_eq!(book.get_best_ask_price(), Some(price(50_010.0)));
+    assert_eq!(book.buy_levels[&price(49_990.0)].total_volume, float_to_scaled_quantity(2.0).unwrap() as u64);
+    assert_eq!(book.order_count(), 2);
+
+    // The stale bid is replaced, not stacked under the new one.
+    bridge
+        .update_orderbook_from_market_data(&mut book, &top_of_book(Some((49_980.0, 1.0)), None))
+        .unwrap();
+    assert_eq!(book.get_best_bid_price(), Some(price(49_980.0)));
+    assert_eq!(book.buy_levels.len(), 1);
+    assert_eq!(book.buy_levels[&price(49_980.0)].total_volume, float_to_scaled_quantity(1.0).unwrap() as u64);
+    assert_eq!(book.get_best_ask_price(), Some(price(50_010.0)));
+
+    bridge
+        .update_orderbook_from_market_data(&mut book, &top_of_book(None, Some((50_000.0, 0.0))))
+        .unwrap();
+    assert_eq!(book.get_best_ask_price(), None);
+    assert_eq!(book.order_count(), 1);
+}
@@ -0,0 +1,450 @@
+use exchange_rs::sbe::parser::{
+    BookChange, BookMessage, SbeMessage, SbeMessageParser, SbeParseError, SCHEMA_VERSION,
+};
+use exchange_rs::sbe::publisher::SbePublisher;
+use exchange_rs::sbe::{
+    instrument_codec, instrument_v2_codec, message_header_codec, ticker_codec, Encoder,
+    InstrumentEncoder, InstrumentKind, InstrumentState, InstrumentV2Encoder, Period, ReadBuf,
+    SbeErr, TickStepsListEncoder, TickerEncoder, WriteBuf, Writer,
+};
+
+fn encode_ticker(settlement_price: f64) -> Vec<u8> {
+    let mut message =
+        vec![0; message_header_codec::ENCODED_LENGTH + ticker_codec::SBE_BLOCK_LENGTH as usize];
+    let encoder = TickerEncoder::default().wrap(
+        WriteBuf::new(&mut message),
+        message_header_codec::ENCODED_LENGTH,
+    );
+    let mut encoder = encoder.header(0).parent().unwrap();
+    encoder.instrument_id(42);
+    encoder.instrument_state(InstrumentState::open);
+    encoder.timestamp_ms(1_700_000_000_123);
+    encoder.open_interest(1_234.5);
+    encoder.min_sell_price(48_000.0);
+    encoder.max_buy_price(52_000.0);
+    encoder.last_price(50_001.5);
+    encoder.index_price(50_000.25);
+    encoder.mark_price(50_002.0);
+    encoder.best_bid_price(49_999.5);
+    encoder.best_bid_amount(3.25);
+    encoder.best_ask_price(50_003.0);
+    encoder.best_ask_amount(0.75);
+    encoder.current_funding(0.0001);
+    encoder.funding_8h(0.0008);
+    encoder.estimated_delivery_price(50_000.75);
+    encoder.delivery_price(f64::NAN);
+    encoder.settlement_price(settlement_price);
+    message
+}
+
+#[test]
+fn test_ticker_decodes_what_was_encoded() {
+    let parser = SbeMessageParser::new();
+    let SbeMessage::Ticker(ticker) = parser.parse_message(&encode_ticker(f64::NAN)).unwrap() else {
+        panic!("expected a ticker");
+    };
+
+    assert_eq!(ticker.instrument_id, 42);
+    assert_eq!(ticker.instrument_state, InstrumentState::open as u8);
+    assert_eq!(ticker.timestamp_ms, 1_700_000_000_123);
+    assert_eq!(ticker.open_interest, Some(1_234.5));
+    assert_eq!(
+        (ticker.min_sell_price, ticker.max_buy_price),
+        (48_000.0, 52_000.0)
+    );
+    assert_eq!(ticker.last_price, Some(50_001.5));
+    assert_eq!(
+        (ticker.index_price, ticker.mark_price),
+        (50_000.25, 50_002.0)
+    );
+    assert_eq!(
+        (ticker.best_bid_price, ticker.best_bid_amount),
+        (49_999.5, 3.25)
+    );
+    assert_eq!(
+        (ticker.best_ask_price, ticker.best_ask_amount),
+        (50_003.0, 0.75)
+    );
+    assert_eq!(
+        (ticker.current_funding, ticker.funding_8h),
+        (Some(0.0001), Some(0.0008))
+    );
+    assert_eq!(ticker.estimated_delivery_price, Some(50_000.75));
+    // Optional prices left out are sent as NaN.
+    assert_eq!(
+        (ticker.delivery_price, ticker.settlement_price),
+        (None, None)
+    );
+
+    let SbeMessage::Ticker(ticker) = parser.parse_message(&encode_ticker(49_950.0)).unwrap() else {
+        panic!("expected a ticker");
+    };
+    assert_eq!(ticker.settlement_price, Some(49_950.0));
+}
+
+#[test]
+fn test_truncated_ticker_is_refused() {
+    let message = encode_ticker(f64::NAN);
+    let parser = SbeMessageParser::new();
+    assert!(parser.parse_message(&message[..message.len() - 1]).is_err());
+}
+
+/// Rewrites the header of an encoded single-block message as `version`
+/// with a block of `block_length`, padding or cutting the block to match.
+fn as_version(mut message: Vec<u8>, version: u16, block_length: u16) -> Vec<u8> {
+    message.resize(
+        message_header_codec::ENCODED_LENGTH + block_length as usize,
+        0xAB,
+    );
+    message[0..2].copy_from_slice(&block_length.to_le_bytes());
+    message[6..8].copy_from_slice(&version.to_le_bytes());
+    message
+}
+
+#[test]
+fn test_newer_schema_version_skips_appended_fields() {
+    let message = as_version(
+        encode_ticker(49_950.0),
+        SCHEMA_VERSION + 1,
+        ticker_codec::SBE_BLOCK_LENGTH + 8,
+    );
+    let SbeMessage::Ticker(ticker) = SbeMessageParser::new().parse_message(&message).unwrap()
+    else {
+        panic!("expected a ticker");
+    };
+    assert_eq!(ticker.settlement_price, Some(49_950.0));
+    assert_eq!(ticker.best_ask_amount, 0.75);
+}
+
+#[test]
+fn test_block_too_short_for_the_parser_is_refused() {
+    let parser = SbeMessageParser::new();
+    let older = as_version(
+        encode_ticker(f64::NAN),
+        SCHEMA_VERSION - 1,
+        ticker_codec::SBE_BLOCK_LENGTH - 8,
+    );
+    assert!(matches!(
+        parser.parse_message(&older),
+        Err(SbeParseError::SchemaVersionMismatch { expected: SCHEMA_VERSION, actual }) if actual == SCHEMA_VERSION - 1
+    ));
+
+    // The current version with a short block is malformed, not old.
+    let short = as_version(
+        encode_ticker(f64::NAN),
+        SCHEMA_VERSION,
+        ticker_codec::SBE_BLOCK_LENGTH - 8,
+    );
+    assert!(matches!(
+        parser.parse_message(&short),
+        Err(SbeParseError::DecodingError(_))
+    ));
+}
+
+fn currency(code: &str) -> [u8; 8] {
+    let mut bytes = [0; 8];
+    bytes[..code.len()].copy_from_slice(code.as_bytes());
+    bytes
+}
+
+fn encode_instrument() -> Vec<u8> {
+    let mut message = vec![0; 256];
+    let encoder = InstrumentEncoder::default().wrap(
+        WriteBuf::new(&mut message),
+        message_header_codec::ENCODED_LENGTH,
+    );
+    let mut encoder = encoder.header(0).parent().unwrap();
+    encoder.instrument_id(11);
+    encoder.kind(InstrumentKind::option);
+    encoder.settlement_period(Period::perpetual);
+    encoder.base_currency(currency("ETH"));
+    encoder.quote_currency(currency("USDC"));
+    encoder.creation_timestamp_ms(1_600_000_000_000);
+    encoder.expiration_timestamp_ms(1_900_000_000_000);
+    encoder.strike_price(f64::NAN);
+    encoder.tick_size(0.05);
+    encoder.max_leverage(50.0);
+    encoder.instrument_name(b"ETH-PERPETUAL");
+    let length = encoder.get_limit();
+    message.truncate(length);
+    message
+}
+
+#[test]
+fn test_instrument_layouts_decode_at_their_own_offsets() {
+    let parser = SbeMessageParser::new();
+
+    let message = encode_instrument();
+    assert_eq!(
+        message.len(),
+        message_header_codec::ENCODED_LENGTH + instrument_codec::SBE_BLOCK_LENGTH as usize + 14
+    );
+
+    let SbeMessage::Instrument(instrument) = parser.parse_message(&message).unwrap() else {
+        panic!("expected an instrument");
+    };
+    assert_eq!(instrument.instrument_id, 11);
+    assert_eq!(instrument.kind, InstrumentKind::option as u8);
+    assert_eq!(instrument.settlement_period, Some(Period::perpetual as u8));
+    assert_eq!(
+        (
+            instrument.base_currency.as_str(),
+            instrument.quote_currency.as_str()
+        ),
+        ("ETH", "USDC")
+    );
+    assert_eq!(
+        (
+            instrument.creation_timestamp_ms,
+            instrument.expiration_timestamp_ms
+        ),
+        (1_600_000_000_000, 1_900_000_000_000)
+    );
+    assert_eq!(
+        (instrument.strike_price, instrument.tick_size),
+        (None, 0.05)
+    );
+    assert_eq!(instrument.max_leverage, Some(50.0));
+    assert_eq!(instrument.instrument_name, "ETH-PERPETUAL");
+
+    // Version 2 drops the rfq byte, so every later field sits one byte
+    // earlier, and carries tick steps ahead of the name.
+    let mut message = vec![0; 256];
+    let encoder = InstrumentV2Encoder::default().wrap(
+        WriteBuf::new(&mut message),
+        message_header_codec::ENCODED_LENGTH,
+    );
+    let mut encoder = encoder.header(0).parent().unwrap();
+    encoder.instrument_id(12);
+    encoder.settlement_period(Period::day);
+    encoder.base_currency(currency("BTC"));
+    encoder.creation_timestamp_ms(1_600_000_000_000);
+    encoder.expiration_timestamp_ms(1_700_000_000_000);
+    encoder.strike_price(60_000.0);
+    encoder.tick_size(5.0);
+    encoder.max_leverage(f64::NAN);
+    let mut steps = encoder.tick_steps_list_encoder(2, TickStepsListEncoder::default());
+    for (above_price, tick_size) in [(0.005, 0.0005), (0.1, 0.001)] {
+        steps.advance().unwrap();
+        steps.above_price(above_price);
+        steps.tick_size(tick_size);
+    }
+    let mut encoder = steps.parent().unwrap();
+    encoder.instrument_name(b"BTC-1JAN25-60000-C");
+    let length = encoder.get_limit();
+    assert_eq!(
+        instrument_v2_codec::SBE_BLOCK_LENGTH,
+        instrument_codec::SBE_BLOCK_LENGTH - 1
+    );
+
+    let SbeMessage::InstrumentV2(instrument) = parser.parse_message(&message[..length]).unwrap()
+    else {
+        panic!("expected an instrument");
+    };
+    assert_eq!(instrument.instrument_id, 12);
+    assert_eq!(instrument.settlement_period, Some(Period::day as u8));
+    assert_eq!(instrument.base_currency, "BTC");
+    assert_eq!(
+        (
+            instrument.creation_timestamp_ms,
+            instrument.expiration_timestamp_ms
+        ),
+        (1_600_000_000_000, 1_700_000_000_000)
+    );
+    assert_eq!(
+        (instrument.strike_price, instrument.tick_size),
+        (Some(60_000.0), 5.0)
+    );
+    assert_eq!(instrument.max_leverage, None);
+    assert_eq!(
+        instrument
+            .tick_steps
+            .iter()
+            .map(|step| (step.above_price, step.tick_size))
+            .collect::<Vec<_>>(),
+        vec![(0.005, 0.0005), (0.1, 0.001)]
+    );
+    assert_eq!(instrument.instrument_name, "BTC-1JAN25-60000-C");
+    assert!(parser.parse_message(&message[..length - 1]).is_err());
+}
+
+#[test]
+fn test_datagram_messages_are_walked_in_order() {
+    let mut publisher = SbePublisher::new(1_400);
+    publisher.publish_encoded(&encode_ticker(49_950.0)).unwrap();
+    publisher.publish_encoded(&encode_instrument()).unwrap();
+    publisher
+        .publish_book(&BookMessage {
+            instrument_id: 42,
+            timestamp_ms: 1_700_000_000_000,
+            prev_change_id: 9,
+            change_id: 10,
+            is_last: true,
+            changes: vec![BookChange {
+                side: 1,
+                change: 0,
+                price: 49_999.5,
+                amount: 2.0,
+            }],
+        })
+        .unwrap();
+    publisher.publish_snapshot_end().unwrap();
+    let datagram = publisher.take_datagrams().remove(0);
+
+    let parser = SbeMessageParser::new();
+    let messages: Vec<String> = parser
+        .iter_messages(&datagram)
+        .map(|message| message.unwrap().to_string())
+        .collect();
+    assert_eq!(messages.len(), 4);
+    assert!(messages[0].starts_with("Ticker(id=42"));
+    assert_eq!(messages[1], "Instrument(id=11, name=ETH-PERPETUAL)");
+    assert_eq!(messages[2], "Book(id=42, changes=1)");
+    assert_eq!(parser.parse_datagram(&datagram).unwrap().len(), 4);
+}
+
+#[test]
+fn test_datagram_walk_survives_bad_messages_but_not_a_cut_one() {
+    let parser = SbeMessageParser::new();
+    let older = as_version(
+        encode_ticker(f64::NAN),
+        SCHEMA_VERSION - 1,
+        ticker_codec::SBE_BLOCK_LENGTH - 8,
+    );
+    let ticker = encode_ticker(49_950.0);
+    let mut datagram = [older, ticker.clone()].concat();
+    datagram.extend_from_slice(&ticker[..ticker.len() - 1]);
+
+    let results: Vec<_> = parser.iter_messages(&datagram).collect();
+    assert_eq!(results.len(), 3);
+    assert!(matches!(
+        results[0],
+        Err(SbeParseError::SchemaVersionMismatch { .. })
+    ));
+    assert!(matches!(results[1], Ok(SbeMessage::Ticker(_))));
+    assert!(matches!(results[2], Err(SbeParseError::BufferUnderrun(_))));
+    assert!(parser.parse_datagram(&datagram).is_err());
+
+    // Not even a whole header left.
+    let results: Vec<_> = parser.iter_messages(&ticker[..5]).collect();
+    assert!(matches!(
+        results[..],
+        [Err(SbeParseError::BufferUnderrun(5))]
+    ));
+    assert_eq!(parser.iter_messages(&[]).count(), 0);
+}
+
+#[test]
+fn test_read_buf_reads_past_the_end_are_errors() {
+    let bytes = [1, 2, 3, 4, 5, 6];
+    let buf = ReadBuf::new(&bytes);
+    assert_eq!(buf.try_get_u32_at(2), Ok(0x0605_0403));
+    assert_eq!(buf.try_get_u8_at(5), Ok(6));
+    assert_eq!(
+        buf.try_get_u32_at(3),
+        Err(SbeErr::OutOfBounds { index: 3, len: 6 })
+    );
+    assert_eq!(
+        buf.try_get_u64_at(usize::MAX),
+        Err(SbeErr::OutOfBounds {
+            index: usize::MAX,
+            len: 6
+        })
+    );
+    assert_eq!(buf.try_get_slice_at(6, 0), Ok(&[][..]));
+}
+
+#[test]
+fn test_write_buf_checked_writes_past_the_end_are_errors() {
+    let mut bytes = [0u8; 6];
+    let mut buf = WriteBuf::new(&mut bytes);
+    assert_eq!(buf.remaining(2), 4);
+    assert_eq!(buf.remaining(9), 0);
+    assert_eq!(buf.try_put_u32_at(2, 0x0605_0403), Ok(4));
+    assert_eq!(
+        buf.try_put_u16_at(5, 7),
+        Err(SbeErr::OutOfBounds { index: 5, len: 6 })
+    );
+    assert_eq!(
+        buf.try_put_slice_at(usize::MAX, &[1]),
+        Err(SbeErr::OutOfBounds {
+            index: usize::MAX,
+            len: 6
+        })
+    );
+    assert_eq!(buf.error(), None);
+    assert_eq!(bytes, [0, 0, 3, 4, 5, 6]);
+}
+
+#[test]
+fn test_encoder_over_a_checked_buffer_keeps_the_first_overflow() {
+    // Room for the header and the first two fields only.
+    let mut message = vec![0; message_header_codec::ENCODED_LENGTH + 9];
+    let encoder = TickerEncoder::default().wrap(
+        WriteBuf::checked(&mut message),
+        message_header_codec::ENCODED_LENGTH,
+    );
+    let mut encoder = encoder.header(0).parent().unwrap();
+    assert_eq!(encoder.remaining(), 0);
+    encoder.instrument_id(42);
+    encoder.instrument_state(InstrumentState::open);
+    assert_eq!(encoder.get_buf_mut().error(), None);
+    encoder.timestamp_ms(1_700_000_000_123);
+    encoder.mark_price(50_002.0);
+    assert_eq!(
+        encoder.get_buf_mut().error(),
+        Some(SbeErr::OutOfBounds {
+            index: message_header_codec::ENCODED_LENGTH + 5,
+            len: message_header_codec::ENCODED_LENGTH + 9,
+        })
+    );
+    assert_eq!(
+        ReadBuf::new(&message).try_get_u32_at(message_header_codec::ENCODED_LENGTH),
+        Ok(42)
+    );
+}
+
+#[test]
+#[should_panic]
+fn test_unchecked_write_past_the_end_panics() {
+    let mut bytes = [0u8; 4];
+    WriteBuf::new(&mut bytes).put_u64_at(0, 1);
+}
+
+#[test]
+fn test_malformed_packets_are_refused_without_panicking() {
+    let mut publisher = SbePublisher::new(1_400);
+    publisher
+        .publish_book(&BookMessage {
+            instrument_id: 42,
+            timestamp_ms: 1_700_000_000_000,
+            prev_change_id: 9,
+            change_id: 10,
+            is_last: true,
+            changes: vec![BookChange {
+                side: 1,
+                change: 0,
+                price: 49_999.5,
+                amount: 2.0,
+            }],
+        })
+        .unwrap();
+    let book = publisher.take_datagrams().remove(0);
+
+    let parser = SbeMessageParser::new();
+    for message in [book, encode_ticker(49_950.0), encode_instrument()] {
+        for length in 1..message.len() {
+            let _ = parser.parse_message(&message[..length]);
+            assert!(parser.iter_messages(&message[..length]).any(|m| m.is_err()));
+        }
+        // Lengths and counts claiming more than the packet holds.
+        for index in [0, 1, 12, 13, 41, 42, 43, 44] {
+            let mut corrupt = message.clone();
+            if index < corrupt.len() {
+                corrupt[index] = 0xFF;
+                let _ = parser.parse_message(&corrupt);
+                let _ = parser.parse_datagram(&corrupt);
+            }
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use exchange_rs::sbe::parser::{SbeMessage, SbeMessageParser, SnapshotLevel, SnapshotMessage};
+use exchange_rs::sbe::publisher::{PublishError, SbePublisher};
+use exchange_rs::sbe::snapshot_assembler::SnapshotAssembler;
+use exchange_rs::sbe::{snapshot_codec, snapshot_start_codec};
+
+const BID: u8 = 1;
+const ASK: u8 = 0;
+const MTU: usize = 1400;
+
+fn snapshot(instrument_id: u32, levels_per_side: usize) -> SnapshotMessage {
+    let mut levels = Vec::new();
+    for i in 0..levels_per_side {
+        levels.push(SnapshotLevel {
+            side: BID,
+            price: 50_000.0 - 0.5 * i as f64,
+            amount: 1.0 + i as f64,
+        });
+        levels.push(SnapshotLevel {
+            side: ASK,
+            price: 50_000.5 + 0.5 * i as f64,
+            amount: 2.0 + i as f64,
+        });
+    }
+    SnapshotMessage {
+        instrument_id,
+        timestamp_ms: 1_700_000_000_000,
+        change_id: 42,
+        is_book_complete: true,
+        is_last_in_book: true,
+        levels,
+    }
+}
+
+fn receive(datagrams: &[Vec<u8>]) -> Vec<SbeMessage> {
+    let parser = SbeMessageParser::new();
+    datagrams
+        .iter()
+        .flat_map(|datagram| parser.parse_datagram(datagram).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_large_snapshot_splits_within_mtu_and_reassembles() {
+    let book = snapshot(7, 250);
+    let mut publisher = SbePublisher::new(MTU);
+    publisher.publish_snapshot_start(0).unwrap();
+    publisher.publish_snapshot(&book).unwrap();
+    publisher.publish_snapshot_end().unwrap();
+    let datagrams = publisher.take_datagrams();
+
+    assert!(datagrams.len() > 1);
+    assert!(datagrams.iter().all(|datagram| datagram.len() <= MTU));
+
+    let messages = receive(&datagrams);
+    let parts = messages
+        .iter()
+        .filter(|message| matches!(message, SbeMessage::Snapshot(_)))
+        .count();
+    assert!(parts > 1);
+
+    let mut assembler = SnapshotAssembler::new();
+    let mut assembled = None;
+    for message in &messages {
+        if let Some(book) = assembler.process(message).unwrap() {
+            assert!(assembled.is_none());
+            assembled = Some(book);
+        }
+    }
+    let assembled = assembled.expect("the last part completes the book");
+    assert_eq!(assembled.instrument_id, 7);
+    assert_eq!(assembled.change_id, 42);
+    let side = |side: u8| -> Vec<(f64, f64)> {
+        book.levels
+            .iter()
+            .filter(|level| level.side == side)
+            .map(|level| (level.price, level.amount))
+            .collect()
+    };
+    assert_eq!(assembled.bids, side(BID));
+    assert_eq!(assembled.asks, side(ASK));
+
+    let metrics = publisher.metrics();
+    assert_eq!(metrics.split_messages(), 1);
+    assert_eq!(metrics.datagrams(), datagrams.len() as u64);
+    let sizes = metrics
+        .message_sizes(snapshot_codec::SBE_TEMPLATE_ID)
+        .unwrap();
+    assert_eq!(sizes.count, parts as u64);
+    assert!(sizes.max <= MTU as u64);
+    assert_eq!(
+        metrics
+            .message_sizes(snapshot_start_codec::SBE_TEMPLATE_ID)
+            .unwrap()
+            .count,
+        1
+    );
+}
+
+#[test]
+fn test_small_messages_share_a_datagram() {
+    let mut publisher = SbePublisher::new(MTU);
+    publisher.publish_snapshot_start(0).unwrap();
+    for instrument_id in 1..=10 {
+        publisher
+            .publish_snapshot(&snapshot(instrument_id, 1))
+            .unwrap();
+    }
+    publisher.publish_snapshot_end().unwrap();
+    let datagrams = publisher.take_datagrams();
+
+    assert_eq!(datagrams.len(), 1);
+    let messages = receive(&datagrams);
+    assert_eq!(messages.len(), 12);
+    let mut assembler = SnapshotAssembler::new();
+    let completed = messages
+        .iter()
+        .filter_map(|message| assembler.process(message).unwrap())
+        .count();
+    assert_eq!(completed, 10);
+    assert_eq!(publisher.metrics().split_messages(), 0);
+}
+
+#[test]
+fn test_unsplittable_message_over_mtu_is_refused() {
+    let mut publisher = SbePublisher::new(MTU);
+    let mut oversized = vec![0u8; MTU + 1];
+    oversized[2..4].copy_from_slice(&1003u16.to_le_bytes());
+    assert_eq!(
+        publisher.publish_encoded(&oversized),
+        Err(PublishError::MessageTooLarge {
+            template_id: 1003,
+            size: MTU + 1,
+            mtu: MTU,
+        })
+    );
+
+    let mut tiny = SbePublisher::new(50);
+    assert!(matches!(
+        tiny.publish_snapshot(&snapshot(1, 1)),
+        Err(PublishError::MessageTooLarge {
+            template_id: 1004,
+            ..
+        })
+    ));
+
+    assert_eq!(publisher.metrics().oversized_messages(), 1);
+    assert_eq!(tiny.metrics().oversized_messages(), 1);
+    assert!(publisher.take_datagrams().is_empty());
+}
@@ -0,0 +1,128 @@
+use exchange_rs::sbe::parser::{
+    SbeMessage, SnapshotEndMessage, SnapshotLevel, SnapshotMessage, SnapshotStartMessage,
+};
+use exchange_rs::sbe::snapshot_assembler::{SnapshotAssembler, SnapshotAssemblyError};
+
+const BID: u8 = 1;
+const ASK: u8 = 0;
+
+fn start() -> SbeMessage {
+    SbeMessage::SnapshotStart(SnapshotStartMessage { snapshot_delay: 0 })
+}
+
+fn end() -> SbeMessage {
+    SbeMessage::SnapshotEnd(SnapshotEndMessage)
+}
+
+fn part(
+    instrument_id: u32,
+    change_id: u64,
+    is_last_in_book: bool,
+    levels: &[(u8, f64, f64)],
+) -> SbeMessage {
+    SbeMessage::Snapshot(SnapshotMessage {
+        instrument_id,
+        timestamp_ms: 1_700_000_000_000 + change_id,
+        change_id,
+        is_book_complete: true,
+        is_last_in_book,
+        levels: levels
+            .iter()
+            .map(|&(side, price, amount)| SnapshotLevel {
+                side,
+                price,
+                amount,
+            })
+            .collect(),
+    })
+}
+
+#[test]
+fn test_assembles_book_split_across_messages() {
+    let mut assembler = SnapshotAssembler::new();
+    assert_eq!(assembler.process(&start()), Ok(None));
+
+    let first = part(7, 100, false, &[(BID, 49_990.0, 1.0), (ASK, 50_010.0, 2.0)]);
+    assert_eq!(assembler.process(&first), Ok(None));
+    // Another instrument's book can complete while 7 is still open.
+    let other = assembler
+        .process(&part(9, 5, true, &[(ASK, 10.0, 3.0)]))
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        (other.instrument_id, other.asks.clone()),
+        (9, vec![(10.0, 3.0)])
+    );
+    assert_eq!(assembler.pending_instruments(), 1);
+
+    let last = part(
+        7,
+        100,
+        true,
+        &[
+            (BID, 49_995.0, 0.5),
+            (ASK, 50_005.0, 4.0),
+            (BID, 49_980.0, 3.0),
+        ],
+    );
+    let book = assembler.process(&last).unwrap().unwrap();
+    assert_eq!(book.instrument_id, 7);
+    assert_eq!(book.change_id, 100);
+    assert!(book.is_book_complete);
+    assert_eq!(
+        book.bids,
+        vec![(49_995.0, 0.5), (49_990.0, 1.0), (49_980.0, 3.0)]
+    );
+    assert_eq!(book.asks, vec![(50_005.0, 4.0), (50_010.0, 2.0)]);
+
+    assert_eq!(assembler.process(&end()), Ok(None));
+    assert!(!assembler.is_in_cycle());
+    assert_eq!(assembler.book(7), Some(&book));
+    assert_eq!(assembler.book(9), Some(&other));
+}
+
+#[test]
+fn test_new_start_discards_partial_book() {
+    let mut assembler = SnapshotAssembler::new();
+    assembler.process(&start()).unwrap();
+    assembler
+        .process(&part(7, 100, false, &[(BID, 1.0, 1.0)]))
+        .unwrap();
+
+    assembler.process(&start()).unwrap();
+    assert_eq!(assembler.pending_instruments(), 0);
+    let book = assembler
+        .process(&part(7, 101, true, &[(ASK, 2.0, 1.0)]))
+        .unwrap()
+        .unwrap();
+    assert!(book.bids.is_empty());
+    assert_eq!(book.asks, vec![(2.0, 1.0)]);
+
+    // A cycle ending before a book's last part drops that book too.
+    assembler
+        .process(&part(8, 3, false, &[(BID, 1.0, 1.0)]))
+        .unwrap();
+    assembler.process(&end()).unwrap();
+    assert_eq!(assembler.pending_instruments(), 0);
+    assert_eq!(assembler.book(8), None);
+}
+
+#[test]
+fn test_messages_outside_cycle_are_errors() {
+    let mut assembler = SnapshotAssembler::new();
+    assert_eq!(
+        assembler.process(&end()),
+        Err(SnapshotAssemblyError::EndWithoutStart)
+    );
+    assert_eq!(
+        assembler.process(&part(7, 1, true, &[])),
+        Err(SnapshotAssemblyError::SnapshotWithoutStart(7))
+    );
+
+    assembler.process(&start()).unwrap();
+    assembler.process(&end()).unwrap();
+    assert_eq!(
+        assembler.process(&end()),
+        Err(SnapshotAssemblyError::EndWithoutStart)
+    );
+}
@@ -0,0 +1,133 @@
+use exchange_rs::{
+    matching_engine::{MatchingEngine, MatchingEngineConfig, Trade},
+    order::{Order, OrderType, Side},
+    orderbook::OrderBook,
+    settlement::{
+        calculate_settlement_price, trading_day, SettlementMethod, SettlementStore, NANOS_PER_DAY,
+    },
+};
+use exchange_rs::Price;
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+const CLOSE: i64 = 20_000 * NANOS_PER_DAY + 16 * 3_600_000_000_000;
+const MINUTE: i64 = 60_000_000_000;
+
+fn trade(id: u64, price: Price, quantity: u32, timestamp: i64) -> Trade {
+    Trade {
+        id,
+        buy_order_id: 1,
+        sell_order_id: 2,
+        price,
+        quantity,
+        timestamp,
+        maker_fee: 0,
+        taker_fee: 0,
+        aggressor: None,
+    }
+}
+
+fn scripted_trades() -> Vec<Trade> {
+    vec![
+        trade(1, 100, 10, CLOSE - 30 * MINUTE),
+        trade(2, 104, 5, CLOSE - 4 * MINUTE),
+        trade(3, 101, 15, CLOSE - 2 * MINUTE),
+        trade(4, 120, 1, CLOSE + MINUTE),
+    ]
+}
+
+fn book_with_quotes(bid: Price, ask: Price) -> OrderBook {
+    let mut book = OrderBook::new("AAPL");
+    for (id, side, price) in [(1, Side::Buy, bid), (2, Side::Sell, ask)] {
+        let mut order = Order::new("AAPL".to_string(), side, OrderType::Limit, price, 10, 1);
+        order.id = id;
+        book.add_order(Arc::new(RwLock::new(order))).unwrap();
+    }
+    book
+}
+
+#[test]
+fn test_last_trade_settlement() {
+    let book = OrderBook::new("AAPL");
+    let price = calculate_settlement_price(SettlementMethod::LastTrade, &scripted_trades(), &book, CLOSE);
+    assert_eq!(price, Some(101));
+}
+
+#[test]
+fn test_vwap_settlement_over_final_window() {
+    let book = OrderBook::new("AAPL");
+    let method = SettlementMethod::Vwap { window_ns: 5 * MINUTE };
+    let price = calculate_settlement_price(method, &scripted_trades(), &book, CLOSE);
+    assert_eq!(price, Some((104 * 5 + 101 * 15) / 20));
+
+    let method = SettlementMethod::Vwap { window_ns: MINUTE };
+    assert_eq!(calculate_settlement_price(method, &scripted_trades(), &book, CLOSE), None);
+}
+
+#[test]
+fn test_midpoint_settlement() {
+    let book = book_with_quotes(99, 104);
+    let price = calculate_settlement_price(SettlementMethod::MidpointAtClose, &[], &book, CLOSE);
+    assert_eq!(price, Some(101));
+
+    let empty = OrderBook::new("AAPL");
+    assert_eq!(calculate_settlement_price(SettlementMethod::MidpointAtClose, &[], &empty, CLOSE), None);
+}
+
+#[test]
+fn test_settle_session_carries_previous_settlement_forward() {
+    let mut engine = MatchingEngine::with_config(MatchingEngineConfig {
+        settlement_method: SettlementMethod::LastTrade,
+        ..Default::default()
+    });
+    engine.add_symbol("AAPL");
+    engine.add_symbol("MSFT");
+
+    let sell_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 150, 10, 1);
+    engine.place_order(sell_order).unwrap();
+    let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 150, 10, 2);
+    engine.place_order(buy_order).unwrap();
+
+    let close_time = engine.clock().now_nanos() + 1;
+    let day = trading_day(close_time);
+
+    let published = engine.settle_session(close_time);
+    assert_eq!(published.len(), 1);
+    assert_eq!(published[0].symbol, "AAPL");
+    assert_eq!(published[0].price, 150);
+    assert!(!published[0].carried_forward);
+
+    let next_close = close_time + NANOS_PER_DAY;
+    let published = engine.settle_session(next_close);
+    assert_eq!(published.len(), 1);
+    assert_eq!(published[0].price, 150);
+    assert!(published[0].carried_forward);
+
+    assert_eq!(engine.get_settlement("AAPL", day).unwrap().price, 150);
+    assert!(engine.get_settlement("AAPL", day + 1).unwrap().carried_forward);
+    assert!(engine.get_settlement("MSFT", day).is_none());
+}
+
+#[test]
+fn test_settlement_store_round_trip() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+
+    let sell_order = Order::new("AAPL".to_string(), Side::Sell, OrderType::Limit, 150, 10, 1);
+    engine.place_order(sell_order).unwrap();
+    let buy_order = Order::new("AAPL".to_string(), Side::Buy, OrderType::Limit, 150, 10, 2);
+    engine.place_order(buy_order).unwrap();
+    let close_time = engine.clock().now_nanos() + 1;
+    engine.settle_session(close_time);
+
+    let path = std::env::temp_dir().join(format!("settlements_{}.json", std::process::id()));
+    let path = path.to_str().unwrap();
+    engine.settlement_store().save_to_file(path).unwrap();
+    let store = SettlementStore::load_from_file(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let history = store.history("AAPL");
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].trading_day, trading_day(close_time));
+    assert_eq!(history[0].price, 150);
+}
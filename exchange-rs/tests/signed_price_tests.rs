@@ -0,0 +1,131 @@
+#![cfg(feature = "signed-prices")]
+
+use exchange_rs::matching_engine::MatchingEngine;
+use exchange_rs::order::{Order, OrderType, Side};
+use exchange_rs::price_utils::{price_to_scaled, scaled_to_price};
+use exchange_rs::Price;
+
+const M1: Price = -1_000_000;
+const M2: Price = -2_000_000;
+const P1: Price = 1_000_000;
+
+fn order(side: Side, order_type: OrderType, price: Price, quantity: u32, user_id: u64) -> Order {
+    Order::new(
+        "SPREAD".to_string(),
+        side,
+        order_type,
+        price,
+        quantity,
+        user_id,
+    )
+}
+
+fn engine() -> MatchingEngine {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("SPREAD");
+    engine
+}
+
+#[test]
+fn test_book_quotes_either_side_of_zero() {
+    let mut engine = engine();
+    for (side, price) in [
+        (Side::Buy, M2),
+        (Side::Buy, M1),
+        (Side::Sell, 0),
+        (Side::Sell, P1),
+    ] {
+        let result = engine
+            .place_order(order(side, OrderType::Limit, price, 5, 1))
+            .unwrap();
+        assert!(result.trades.is_empty());
+    }
+
+    let book = &engine.order_books["SPREAD"];
+    assert_eq!(
+        (book.get_best_bid_price(), book.get_best_ask_price()),
+        (Some(M1), Some(0))
+    );
+    let depth = engine.market_depth("SPREAD", 10).unwrap();
+    assert_eq!(depth.bid_levels, vec![(M1, 5), (M2, 5)]);
+    assert_eq!(depth.ask_levels, vec![(0, 5), (P1, 5)]);
+}
+
+#[test]
+fn test_trades_print_below_zero() {
+    let mut engine = engine();
+    engine
+        .place_order(order(Side::Sell, OrderType::Limit, M2, 4, 1))
+        .unwrap();
+    engine
+        .place_order(order(Side::Sell, OrderType::Limit, M1, 4, 1))
+        .unwrap();
+
+    let result = engine
+        .place_order(order(Side::Buy, OrderType::Limit, M1, 6, 2))
+        .unwrap();
+    let fills: Vec<(Price, u32)> = result
+        .trades
+        .iter()
+        .map(|t| (t.price, t.quantity))
+        .collect();
+    assert_eq!(fills, vec![(M2, 4), (M1, 2)]);
+    assert_eq!(engine.last_trade_price("SPREAD"), Some(M1));
+
+    let buy = result
+        .filled_orders
+        .iter()
+        .find(|order| order.read().side == Side::Buy)
+        .unwrap();
+    assert_eq!(buy.read().average_fill_price(), Some(-1_666_666));
+}
+
+#[test]
+fn test_market_order_sweeps_through_zero() {
+    let mut engine = engine();
+    for price in [M1, 0, P1] {
+        engine
+            .place_order(order(Side::Sell, OrderType::Limit, price, 2, 1))
+            .unwrap();
+    }
+
+    let result = engine
+        .place_order(order(Side::Buy, OrderType::Market, 0, 6, 2))
+        .unwrap();
+    let prices: Vec<Price> = result.trades.iter().map(|t| t.price).collect();
+    assert_eq!(prices, vec![M1, 0, P1]);
+    assert_eq!(engine.order_books["SPREAD"].get_best_ask_price(), None);
+}
+
+#[test]
+fn test_sell_stop_below_zero_triggers_as_price_falls() {
+    let mut engine = engine();
+    let mut stop = order(Side::Sell, OrderType::StopLimit, M2, 3, 1);
+    stop.stop_price = Some(M1);
+    assert!(!stop.is_stop_triggered(0));
+    assert!(stop.is_stop_triggered(M1));
+    assert!(stop.is_stop_triggered(M2));
+    engine.place_order(stop).unwrap();
+
+    assert!(engine
+        .update_mark_price("SPREAD", -500_000)
+        .unwrap()
+        .triggered_stops
+        .is_empty());
+    engine
+        .place_order(order(Side::Buy, OrderType::Limit, M2, 5, 2))
+        .unwrap();
+    engine
+        .place_order(order(Side::Sell, OrderType::Limit, -1_500_000, 1, 3))
+        .unwrap();
+    let result = engine
+        .place_order(order(Side::Buy, OrderType::Limit, -1_500_000, 1, 4))
+        .unwrap();
+    assert_eq!(result.triggered_stops.len(), 1);
+}
+
+#[test]
+fn test_negative_float_prices_round_trip() {
+    assert_eq!(price_to_scaled(-12.345), Ok(-12_345_000));
+    assert_eq!(scaled_to_price(M2), -2.0);
+}
@@ -0,0 +1,155 @@
+use exchange_rs::{
+    clock::ManualClock,
+    matching_engine::{MatchingEngine, MatchingEngineConfig},
+    order::{Order, OrderStatus, OrderType, Side},
+    snapshot_diff::{LevelChange, OrderSummary},
+};
+use exchange_rs::Price;
+use serde_json::json;
+use std::sync::Arc;
+
+fn limit(side: Side, price: Price, quantity: u32, user_id: u64) -> Order {
+    Order::new(
+        "AAPL".to_string(),
+        side,
+        OrderType::Limit,
+        price,
+        quantity,
+        user_id,
+    )
+}
+
+fn engine() -> MatchingEngine {
+    let clock = Arc::new(ManualClock::new(1_000));
+    let mut engine = MatchingEngine::with_clock(MatchingEngineConfig::default(), clock);
+    engine.add_symbol("AAPL");
+    engine.place_order(limit(Side::Sell, 101, 5, 1)).unwrap();
+    engine.place_order(limit(Side::Sell, 102, 5, 1)).unwrap();
+    engine.place_order(limit(Side::Buy, 99, 4, 2)).unwrap();
+    let mut stop = Order::new(
+        "AAPL".to_string(),
+        Side::Sell,
+        OrderType::StopLimit,
+        94,
+        2,
+        2,
+    );
+    stop.stop_price = Some(95);
+    engine.place_order(stop).unwrap();
+    engine
+}
+
+#[test]
+fn test_book_diff_after_scripted_operations() {
+    let mut engine = engine();
+    let before = engine.snapshot();
+
+    engine.place_order(limit(Side::Buy, 101, 2, 3)).unwrap();
+    engine.cancel_order("AAPL", 3).unwrap();
+    engine.place_order(limit(Side::Buy, 98, 3, 3)).unwrap();
+    engine.cancel_order("AAPL", 4).unwrap();
+    let after = engine.snapshot();
+
+    let diff = before.order_books()["AAPL"].diff(&after.order_books()["AAPL"]);
+
+    let ids = |orders: &[OrderSummary]| orders.iter().map(|order| order.id).collect::<Vec<_>>();
+    assert_eq!(ids(&diff.orders.added), vec![6]);
+    assert_eq!(ids(&diff.orders.removed), vec![3]);
+    assert_eq!(diff.orders.removed[0].status, OrderStatus::New);
+    assert_eq!(diff.orders.modified.len(), 1);
+    let fill = &diff.orders.modified[0];
+    assert_eq!(fill.id, 1);
+    let fields: Vec<&str> = fill
+        .changes
+        .iter()
+        .map(|change| change.field.as_str())
+        .collect();
+    assert_eq!(
+        fields,
+        vec!["fill_notional", "filled_quantity", "last_update", "status"]
+    );
+    assert_eq!(
+        (&fill.changes[1].before, &fill.changes[1].after),
+        (&json!(0), &json!(2))
+    );
+    assert_eq!(
+        (&fill.changes[3].before, &fill.changes[3].after),
+        (&json!("New"), &json!("PartiallyFilled"))
+    );
+
+    assert_eq!(
+        diff.levels,
+        vec![
+            LevelChange {
+                side: Side::Buy,
+                price: 98,
+                before: 0,
+                after: 3
+            },
+            LevelChange {
+                side: Side::Buy,
+                price: 99,
+                before: 4,
+                after: 0
+            },
+            LevelChange {
+                side: Side::Sell,
+                price: 101,
+                before: 5,
+                after: 3
+            },
+        ]
+    );
+    assert!(diff.stop_orders.added.is_empty() && diff.stop_orders.modified.is_empty());
+    assert_eq!(ids(&diff.stop_orders.removed), vec![4]);
+    assert_eq!(diff.stop_orders.removed[0].stop_price, Some(95));
+
+    assert_eq!(diff.book.len(), 1);
+    assert_eq!(diff.book[0].field, "last_trade_price");
+    assert_eq!(
+        (&diff.book[0].before, &diff.book[0].after),
+        (&json!(null), &json!(101))
+    );
+}
+
+#[test]
+fn test_engine_diff_summary_and_rendering() {
+    let mut engine = engine();
+    let before = engine.snapshot();
+    assert!(before.diff(&engine.snapshot()).books.is_empty());
+
+    engine.add_symbol("MSFT");
+    engine
+        .place_order(Order::new(
+            "MSFT".to_string(),
+            Side::Buy,
+            OrderType::Limit,
+            50,
+            7,
+            4,
+        ))
+        .unwrap();
+    engine.cancel_order("AAPL", 2).unwrap();
+    let diff = before.diff(&engine.snapshot());
+
+    assert_eq!(diff.symbols_added, vec!["MSFT"]);
+    assert!(diff.symbols_removed.is_empty());
+    let symbols: Vec<&str> = diff.books.iter().map(|book| book.symbol.as_str()).collect();
+    assert_eq!(symbols, vec!["AAPL", "MSFT"]);
+    assert_eq!(diff.summary.symbols_changed, 2);
+    assert_eq!(diff.summary.orders_added, 1);
+    assert_eq!(diff.summary.orders_removed, 1);
+    assert_eq!(diff.summary.orders_modified, 0);
+    assert_eq!(diff.summary.levels_changed, 2);
+    assert_eq!(diff.summary.stop_orders_removed, 0);
+
+    let text = diff.to_string();
+    assert!(text.starts_with("+ symbol MSFT\nAAPL\n"));
+    assert!(text.contains("  - order 2 Sell Limit 102 x 5 filled 0 New user 1\n"));
+    assert!(text.contains("  level Buy 50: 0 -> 7\n"));
+    assert!(text.ends_with("2 symbols changed: orders +1 -1 ~0, levels 2, stops +0 -0 ~0\n"));
+
+    let rendered = serde_json::to_value(&diff).unwrap();
+    assert_eq!(rendered["summary"]["orders_removed"], 1);
+    assert_eq!(rendered["books"][1]["orders"]["added"][0]["price"], 50);
+}
@@ -0,0 +1,175 @@
+use std::fs;
+use std::path::PathBuf;
+
+use exchange_rs::matching_engine::MatchingEngine;
+use exchange_rs::order::{Order, OrderType, Side};
+use exchange_rs::snapshot::{
+    OrderBookSnapshot, SnapshotFileError, SnapshotFileHeader, SnapshotFormat, SNAPSHOT_FILE_VERSION,
+};
+
+fn snapshot_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("book_{}_{}.snap", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+/// Several levels a side, an iceberg, client order ids, a partial fill
+/// and resting stops.
+fn populated_snapshot() -> OrderBookSnapshot {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    for (side, price, quantity, user_id) in [
+        (Side::Buy, 99, 10, 1),
+        (Side::Buy, 99, 4, 2),
+        (Side::Buy, 98, 7, 3),
+        (Side::Buy, 95, 2, 1),
+        (Side::Sell, 101, 5, 2),
+        (Side::Sell, 102, 8, 3),
+        (Side::Sell, 104, 1, 4),
+    ] {
+        let mut order = Order::new(
+            "AAPL".to_string(),
+            side,
+            OrderType::Limit,
+            price,
+            quantity,
+            user_id,
+        );
+        order.client_order_id = Some(format!("c-{}-{}", user_id, price));
+        engine.place_order(order).unwrap();
+    }
+    let mut iceberg = Order::new(
+        "AAPL".to_string(),
+        Side::Sell,
+        OrderType::Iceberg,
+        103,
+        20,
+        5,
+    );
+    iceberg.display_quantity = Some(5);
+    engine.place_order(iceberg).unwrap();
+    engine
+        .place_order(Order::new(
+            "AAPL".to_string(),
+            Side::Sell,
+            OrderType::Limit,
+            99,
+            3,
+            6,
+        ))
+        .unwrap();
+
+    for (side, order_type, price, stop_price) in [
+        (Side::Sell, OrderType::StopLimit, 94, 96),
+        (Side::Buy, OrderType::StopMarket, 0, 105),
+    ] {
+        let mut stop = Order::new("AAPL".to_string(), side, order_type, price, 3, 7);
+        stop.stop_price = Some(stop_price);
+        engine.place_order(stop).unwrap();
+    }
+
+    engine.order_books.get("AAPL").unwrap().create_snapshot()
+}
+
+fn as_json(snapshot: &OrderBookSnapshot) -> serde_json::Value {
+    serde_json::to_value(snapshot).unwrap()
+}
+
+#[test]
+fn test_snapshot_round_trips_through_both_formats() {
+    let snapshot = populated_snapshot();
+    assert_eq!(snapshot.stop_orders.len(), 2);
+    assert!(snapshot.buy_levels.len() >= 3 && snapshot.sell_levels.len() >= 3);
+
+    for format in [SnapshotFormat::Json, SnapshotFormat::Bincode] {
+        let path = snapshot_path(&format!("{:?}", format));
+        snapshot.save_to_file(&path, format).unwrap();
+
+        let header = SnapshotFileHeader::read(&path).unwrap();
+        assert_eq!(header.version, SNAPSHOT_FILE_VERSION);
+        assert_eq!(header.format, format);
+        assert_eq!(header.symbol, "AAPL");
+        assert!(header.timestamp > 0);
+
+        let loaded = OrderBookSnapshot::load_from_file(&path).unwrap();
+        assert_eq!(as_json(&loaded), as_json(&snapshot), "{:?}", format);
+
+        let book = loaded.restore();
+        assert_eq!(as_json(&book.create_snapshot()), as_json(&snapshot));
+        assert_eq!(
+            (book.get_best_bid_price(), book.get_best_ask_price()),
+            (Some(99), Some(101))
+        );
+        assert!(book.get_order_by_client_id(3, "c-3-98").is_some());
+        let _ = fs::remove_file(&path);
+    }
+}
+
+#[test]
+fn test_damaged_files_are_refused() {
+    let snapshot = populated_snapshot();
+    let path = snapshot_path("damaged");
+    snapshot
+        .save_to_file(&path, SnapshotFormat::Bincode)
+        .unwrap();
+    let bytes = fs::read(&path).unwrap();
+    // Magic, version, format, symbol length, "AAPL", timestamp, checksum
+    // and payload length.
+    let payload_start = 4 + 2 + 1 + 2 + 4 + 8 + 4 + 8;
+
+    let load = |bytes: &[u8]| {
+        fs::write(&path, bytes).unwrap();
+        OrderBookSnapshot::load_from_file(&path)
+    };
+
+    let mut corrupt = bytes.clone();
+    corrupt[bytes.len() - 10] ^= 0x01;
+    assert!(matches!(
+        load(&corrupt),
+        Err(SnapshotFileError::ChecksumMismatch { .. })
+    ));
+
+    let mut newer = bytes.clone();
+    newer[4..6].copy_from_slice(&(SNAPSHOT_FILE_VERSION + 1).to_le_bytes());
+    assert!(matches!(
+        load(&newer),
+        Err(SnapshotFileError::UnsupportedVersion { found, expected }) if found == SNAPSHOT_FILE_VERSION + 1 && expected == SNAPSHOT_FILE_VERSION
+    ));
+
+    for length in [0, 3, payload_start - 1, bytes.len() - 1] {
+        assert!(
+            matches!(
+                load(&bytes[..length]),
+                Err(SnapshotFileError::Truncated) | Err(SnapshotFileError::BadMagic)
+            ),
+            "cut at {length}"
+        );
+    }
+    assert!(matches!(
+        load(&bytes[..payload_start - 1]),
+        Err(SnapshotFileError::Truncated)
+    ));
+
+    let mut foreign = bytes.clone();
+    foreign[..4].copy_from_slice(b"JUNK");
+    assert!(matches!(load(&foreign), Err(SnapshotFileError::BadMagic)));
+
+    let mut unknown = bytes.clone();
+    unknown[6] = 9;
+    assert!(matches!(
+        load(&unknown),
+        Err(SnapshotFileError::UnknownFormat(9))
+    ));
+
+    // The checksum covers the payload only; the symbol is checked against
+    // the decoded snapshot.
+    let mut renamed = bytes.clone();
+    renamed[9..13].copy_from_slice(b"MSFT");
+    assert!(matches!(
+        load(&renamed),
+        Err(SnapshotFileError::SymbolMismatch { header, payload }) if header == "MSFT" && payload == "AAPL"
+    ));
+
+    assert!(load(&bytes).is_ok());
+    let _ = fs::remove_file(&path);
+}
@@ -0,0 +1,229 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use exchange_rs::journal::{recover, JournalFile, SyncPolicy};
+use exchange_rs::matching_engine::{EngineSnapshot, MatchingEngine, MatchingEngineConfig};
+use exchange_rs::order::{Order, OrderType, Side};
+use exchange_rs::snapshot::{SnapshotFileError, SnapshotFileHeader, SnapshotFormat};
+use exchange_rs::snapshot_scheduler::SnapshotScheduler;
+use parking_lot::Mutex;
+
+fn snapshot_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("snapshots_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn shared_engine() -> Arc<Mutex<MatchingEngine>> {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    engine.add_symbol("MSFT");
+    Arc::new(Mutex::new(engine))
+}
+
+/// The orders of the standard demo, placed straight on the engine.
+async fn run_demo_workload(engine: &Mutex<MatchingEngine>) {
+    let mut stop_order = Order::new(
+        "AAPL".to_string(),
+        Side::Buy,
+        OrderType::StopLimit,
+        1100000,
+        10,
+        3,
+    );
+    stop_order.stop_price = Some(1050000);
+    let orders = [
+        Order::new(
+            "AAPL".to_string(),
+            Side::Sell,
+            OrderType::Limit,
+            1000000,
+            10,
+            1,
+        ),
+        Order::new(
+            "AAPL".to_string(),
+            Side::Buy,
+            OrderType::Limit,
+            1000000,
+            5,
+            2,
+        ),
+        stop_order,
+        Order::new(
+            "AAPL".to_string(),
+            Side::Sell,
+            OrderType::Limit,
+            1050000,
+            5,
+            4,
+        ),
+        Order::new(
+            "MSFT".to_string(),
+            Side::Buy,
+            OrderType::Limit,
+            2500000,
+            7,
+            5,
+        ),
+    ];
+    for order in orders {
+        engine.lock().place_order(order).unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    tokio::time::sleep(Duration::from_millis(300)).await;
+}
+
+#[tokio::test]
+async fn test_scheduled_snapshots_are_rotated_and_loadable() {
+    let dir = snapshot_dir("scheduled");
+    let engine = shared_engine();
+    let mut scheduler =
+        SnapshotScheduler::new(Arc::clone(&engine), Duration::from_millis(100), &dir, 3);
+    assert!(scheduler.snapshot_files().unwrap().is_empty());
+
+    scheduler.start();
+    assert!(scheduler.is_running());
+    run_demo_workload(&engine).await;
+    scheduler.stop().await;
+    assert!(!scheduler.is_running());
+
+    let files = scheduler.snapshot_files().unwrap();
+    let metrics = scheduler.metrics();
+    assert!(!files.is_empty() && files.len() <= 3, "{:?}", files);
+    assert_eq!(metrics.failures, 0);
+    assert_eq!(
+        metrics.files_removed,
+        metrics.snapshots_written - files.len() as u64
+    );
+    assert_eq!(metrics.last_path.as_ref(), files.last());
+    assert_eq!(
+        metrics.last_size_bytes,
+        std::fs::metadata(files.last().unwrap()).unwrap().len()
+    );
+    assert!(metrics.last_capture_ns <= metrics.last_duration_ns);
+    for file in &files {
+        let header = SnapshotFileHeader::read(file).unwrap();
+        assert_eq!(header.format, SnapshotFormat::Bincode);
+        assert_eq!(header.symbol, "");
+        let snapshot = EngineSnapshot::load_from_file(file).unwrap();
+        assert_eq!(snapshot.order_books().len(), 2);
+    }
+
+    // Stopped means no more files until asked for one.
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    assert_eq!(scheduler.snapshot_files().unwrap(), files);
+
+    let latest = scheduler.snapshot_now().await.unwrap();
+    assert_eq!(
+        scheduler.snapshot_files().unwrap().len(),
+        (files.len() + 1).min(3)
+    );
+    assert_eq!(scheduler.snapshot_files().unwrap().last(), Some(&latest));
+    let restored = MatchingEngine::restore(EngineSnapshot::load_from_file(&latest).unwrap());
+    assert_eq!(restored.state_digest(), engine.lock().state_digest());
+    assert_eq!(
+        restored.market_depth("MSFT", 1).unwrap().bid_levels,
+        vec![(2500000, 7)]
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_snapshot_now_without_starting() {
+    let dir = snapshot_dir("manual");
+    let engine = shared_engine();
+    engine
+        .lock()
+        .place_order(Order::new(
+            "AAPL".to_string(),
+            Side::Sell,
+            OrderType::Limit,
+            1000000,
+            10,
+            1,
+        ))
+        .unwrap();
+    let scheduler = SnapshotScheduler::new(Arc::clone(&engine), Duration::from_secs(60), &dir, 2)
+        .with_format(SnapshotFormat::Json);
+
+    let mut written = Vec::new();
+    for _ in 0..4 {
+        written.push(scheduler.snapshot_now().await.unwrap());
+    }
+    assert_eq!(scheduler.snapshot_files().unwrap(), written[2..].to_vec());
+    assert_eq!(scheduler.metrics().snapshots_written, 4);
+    assert_eq!(scheduler.metrics().files_removed, 2);
+
+    let header = SnapshotFileHeader::read(&written[3]).unwrap();
+    assert_eq!(header.format, SnapshotFormat::Json);
+    let snapshot = EngineSnapshot::load_from_file(&written[3]).unwrap();
+    assert_eq!(snapshot.digest(), engine.lock().create_snapshot().digest());
+
+    // A single book's snapshot file is not an engine snapshot.
+    let book_file = dir.join("book.snap");
+    engine.lock().create_snapshot().order_books()["AAPL"]
+        .save_to_file(&book_file, SnapshotFormat::Json)
+        .unwrap();
+    assert!(matches!(
+        EngineSnapshot::load_from_file(&book_file),
+        Err(SnapshotFileError::SymbolMismatch { header, .. }) if header == "AAPL"
+    ));
+    // Nor is it taken for a scheduled snapshot when rotating.
+    assert_eq!(scheduler.snapshot_files().unwrap().len(), 2);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_recover_from_a_scheduled_snapshot_and_the_journal() {
+    let dir = snapshot_dir("recover");
+    std::fs::create_dir_all(&dir).unwrap();
+    let journal_path = dir.join("engine.journal");
+    let engine = {
+        let mut engine = MatchingEngine::new();
+        engine
+            .attach_journal_file(JournalFile::open(&journal_path, SyncPolicy::EveryRecord).unwrap())
+            .unwrap();
+        engine.add_symbol("AAPL");
+        engine.add_symbol("MSFT");
+        Arc::new(Mutex::new(engine))
+    };
+    let mut scheduler =
+        SnapshotScheduler::new(Arc::clone(&engine), Duration::from_millis(100), &dir, 3);
+    scheduler.start();
+    run_demo_workload(&engine).await;
+    scheduler.stop().await;
+
+    // Commands after the last snapshot come back from the journal.
+    let latest = scheduler.snapshot_files().unwrap().pop().unwrap();
+    let sequence = EngineSnapshot::load_from_file(&latest)
+        .unwrap()
+        .journal_sequence();
+    engine
+        .lock()
+        .place_order(Order::new(
+            "MSFT".to_string(),
+            Side::Sell,
+            OrderType::Limit,
+            2600000,
+            3,
+            6,
+        ))
+        .unwrap();
+
+    let recovered = recover(
+        MatchingEngineConfig::default(),
+        &latest,
+        &journal_path,
+        SyncPolicy::Never,
+    )
+    .unwrap();
+    assert_eq!(recovered.state_digest(), engine.lock().state_digest());
+    assert_eq!(recovered.journal().unwrap().compacted_through(), sequence);
+    assert!(sequence > 0);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,162 @@
+use exchange_rs::{
+    clock::ManualClock,
+    tasks::{RestartPolicy, TaskError, TaskHealth, TaskRegistry, TaskSpec, TaskState, TaskStatus},
+};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn status(registry: &TaskRegistry, name: &str) -> TaskStatus {
+    registry
+        .statuses()
+        .into_iter()
+        .find(|status| status.name == name)
+        .unwrap()
+}
+
+/// Polls until `done` holds, failing the test after a second.
+async fn wait_until(mut done: impl FnMut() -> bool) {
+    for _ in 0..200 {
+        if done() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    panic!("condition not reached");
+}
+
+#[tokio::test]
+async fn test_watchdog_restarts_dying_task() {
+    let clock = Arc::new(ManualClock::new(1_000));
+    let registry = TaskRegistry::with_clock(clock.clone());
+    let runs = Arc::new(AtomicU32::new(0));
+
+    let task_runs = Arc::clone(&runs);
+    let spec = TaskSpec::new("feed").restart(RestartPolicy::OnFailure {
+        initial_backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(20),
+    });
+    registry
+        .spawn(spec, move |mut context| {
+            let run = task_runs.fetch_add(1, Ordering::SeqCst) + 1;
+            async move {
+                if run <= 2 {
+                    return Err(format!("died on run {}", run));
+                }
+                context.record_activity();
+                context.shutdown_requested().await;
+                Ok(())
+            }
+        })
+        .unwrap();
+
+    clock.set(5_000);
+    let watchdog = registry.spawn_watchdog(Duration::from_millis(2));
+    wait_until(|| status(&registry, "feed").restarts == 2).await;
+    wait_until(|| runs.load(Ordering::SeqCst) == 3).await;
+
+    let feed = status(&registry, "feed");
+    assert_eq!(feed.state, TaskState::Running);
+    assert_eq!(feed.health, TaskHealth::Healthy);
+    assert_eq!(feed.last_activity, 5_000);
+
+    assert_eq!(registry.shutdown(Duration::from_secs(1)).await, vec!["feed"]);
+    assert_eq!(status(&registry, "feed").state, TaskState::Stopped);
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+    watchdog.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_health_reporting() {
+    let registry = TaskRegistry::new();
+    let degraded = Arc::new(AtomicBool::new(false));
+
+    registry
+        .spawn(TaskSpec::new("exporter"), |_| async { Err("socket closed".to_string()) })
+        .unwrap();
+    registry.spawn(TaskSpec::new("snapshot"), |_| async { Ok(()) }).unwrap();
+    let check_degraded = Arc::clone(&degraded);
+    let spec = TaskSpec::new("expiry").health_check(move || {
+        if check_degraded.load(Ordering::SeqCst) {
+            TaskHealth::Unhealthy("backlog".to_string())
+        } else {
+            TaskHealth::Healthy
+        }
+    });
+    registry
+        .spawn(spec, |mut context| async move {
+            context.shutdown_requested().await;
+            Ok(())
+        })
+        .unwrap();
+
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    registry.check().await;
+
+    let exporter = status(&registry, "exporter");
+    assert_eq!(exporter.state, TaskState::Failed("socket closed".to_string()));
+    assert_eq!(exporter.health, TaskHealth::Unhealthy("socket closed".to_string()));
+    assert_eq!(exporter.restarts, 0);
+    assert_eq!(status(&registry, "snapshot").state, TaskState::Finished);
+
+    assert_eq!(status(&registry, "expiry").health, TaskHealth::Healthy);
+    degraded.store(true, Ordering::SeqCst);
+    assert_eq!(
+        status(&registry, "expiry").health,
+        TaskHealth::Unhealthy("backlog".to_string())
+    );
+
+    let names: Vec<String> = registry.statuses().into_iter().map(|s| s.name).collect();
+    assert_eq!(names, vec!["expiry", "exporter", "snapshot"]);
+    let body = serde_json::to_value(registry.statuses()).unwrap();
+    assert_eq!(body[1]["state"]["Failed"], "socket closed");
+}
+
+#[tokio::test]
+async fn test_shutdown_follows_dependencies() {
+    let registry = TaskRegistry::new();
+    let stopped = Arc::new(parking_lot::Mutex::new(Vec::new()));
+
+    for (name, dependency) in [("engine", None), ("feed", Some("engine")), ("exporter", Some("feed"))] {
+        let mut spec = TaskSpec::new(name);
+        if let Some(dependency) = dependency {
+            spec = spec.depends_on(dependency);
+        }
+        let stopped = Arc::clone(&stopped);
+        registry
+            .spawn(spec, move |mut context| {
+                let stopped = Arc::clone(&stopped);
+                async move {
+                    context.shutdown_requested().await;
+                    stopped.lock().push(name);
+                    Ok(())
+                }
+            })
+            .unwrap();
+    }
+    registry
+        .spawn(TaskSpec::new("stuck"), |_| std::future::pending())
+        .unwrap();
+
+    assert_eq!(
+        registry.spawn(TaskSpec::new("feed"), |_| async { Ok(()) }),
+        Err(TaskError::DuplicateTask {
+            name: "feed".to_string()
+        })
+    );
+    assert_eq!(
+        registry.spawn(TaskSpec::new("sampler").depends_on("stats"), |_| async { Ok(()) }),
+        Err(TaskError::UnknownDependency {
+            name: "sampler".to_string(),
+            dependency: "stats".to_string()
+        })
+    );
+
+    let order = registry.shutdown(Duration::from_millis(50)).await;
+    assert_eq!(order, vec!["stuck", "exporter", "feed", "engine"]);
+    assert_eq!(*stopped.lock(), vec!["exporter", "feed", "engine"]);
+    assert!(registry
+        .statuses()
+        .iter()
+        .all(|status| status.state == TaskState::Stopped));
+}
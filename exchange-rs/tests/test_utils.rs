@@ -1,7 +1,72 @@
+// Shared by several test binaries, none of which use every helper.
+#![allow(dead_code)]
+
+use exchange_rs::fix::encoder;
 use exchange_rs::matching_engine::MatchingEngine;
+use exchange_rs::order::{Order, OrderType, Side, TimeInForce};
+use exchange_rs::Price;
 
 pub fn setup() -> MatchingEngine {
     let mut engine = MatchingEngine::new();
     engine.add_symbol("AAPL");
     engine
 }
+
+/// Frames `body`, `|`-separated fields starting with MsgType, as a FIX.4.4
+/// message.
+pub fn frame(body: &str) -> Vec<u8> {
+    let fields: Vec<(u32, String)> = body
+        .split_terminator('|')
+        .map(|field| {
+            let (tag, value) = field.split_once('=').expect("tag=value");
+            (tag.parse().expect("numeric tag"), value.to_string())
+        })
+        .collect();
+    encoder::frame("FIX.4.4", &fields)
+}
+
+/// Small deterministic generator so a failing workload can be rerun.
+pub struct XorShift(pub u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+/// A random limit, iceberg, IOC or market order around 100 in one of
+/// `symbols`.
+pub fn random_order(rng: &mut XorShift, symbols: &[&str]) -> Order {
+    let symbol = symbols[rng.below(symbols.len() as u64) as usize];
+    let side = if rng.below(2) == 0 {
+        Side::Buy
+    } else {
+        Side::Sell
+    };
+    let order_type = match rng.below(10) {
+        0 => OrderType::Market,
+        1 => OrderType::Iceberg,
+        _ => OrderType::Limit,
+    };
+    let mut order = Order::new(
+        symbol.to_string(),
+        side,
+        order_type,
+        95 + rng.below(11) as Price,
+        1 + rng.below(20) as u32,
+        1 + rng.below(5),
+    );
+    match order_type {
+        OrderType::Iceberg => order.display_quantity = Some(1 + order.quantity / 4),
+        OrderType::Limit if rng.below(8) == 0 => order.time_in_force = TimeInForce::IOC,
+        _ => {}
+    }
+    order
+}
@@ -0,0 +1,304 @@
+use exchange_rs::clock::{Clock, ManualClock};
+use exchange_rs::matching_engine::{MatchingEngine, MatchingEngineConfig};
+use exchange_rs::order::{Order, OrderType, Side, TimeInForce};
+use exchange_rs::timers::{TimerEvent, TimerTarget, TimerWheel, Timers};
+use exchange_rs::Price;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+const START: i64 = 1_700_000_000_000_000_000;
+const MILLI: i64 = 1_000_000;
+const SECOND: i64 = 1_000_000_000;
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+fn wheel() -> TimerWheel {
+    TimerWheel::new(Duration::from_millis(1), START)
+}
+
+fn probe(session: &str) -> TimerEvent {
+    TimerEvent::TestRequestDue {
+        session: session.to_string(),
+    }
+}
+
+fn fired_sessions(wheel: &mut TimerWheel, now: i64) -> Vec<String> {
+    wheel
+        .advance_to(now)
+        .into_iter()
+        .map(|timer| match timer.event {
+            TimerEvent::TestRequestDue { session } => session,
+            other => panic!("unexpected {:?}", other),
+        })
+        .collect()
+}
+
+#[test]
+fn test_timers_fire_in_deadline_order() {
+    let mut wheel = wheel();
+    wheel.schedule(START + 70 * MILLI, probe("c"));
+    wheel.schedule(START + 5 * MILLI, probe("a"));
+    wheel.schedule(START + 5 * SECOND, probe("e"));
+    wheel.schedule(START + 70 * MILLI, probe("d"));
+    wheel.schedule(START + 5 * MILLI + 1, probe("b"));
+
+    assert!(wheel.advance_to(START + 4 * MILLI).is_empty());
+    // A deadline between ticks fires on the next tick, never early.
+    assert_eq!(fired_sessions(&mut wheel, START + 5 * MILLI), ["a"]);
+    assert_eq!(fired_sessions(&mut wheel, START + 6 * MILLI), ["b"]);
+    assert_eq!(fired_sessions(&mut wheel, START + 70 * MILLI), ["c", "d"]);
+    assert!(wheel.advance_to(START + 5 * SECOND - 1).is_empty());
+    assert_eq!(fired_sessions(&mut wheel, START + 5 * SECOND), ["e"]);
+    assert!(wheel.is_empty());
+
+    // Already due: fires on the next advance, even without time passing.
+    wheel.schedule(START, probe("late"));
+    assert_eq!(fired_sessions(&mut wheel, START + 5 * SECOND), ["late"]);
+}
+
+#[test]
+fn test_cancelled_timers_do_not_fire() {
+    let mut wheel = wheel();
+    let near = wheel.schedule(START + 3 * MILLI, probe("near"));
+    wheel.schedule(START + 3 * MILLI, probe("kept"));
+    let far = wheel.schedule(START + 400 * 86_400 * SECOND, probe("far"));
+    assert_eq!(wheel.metrics().overflow, 1);
+
+    assert!(wheel.cancel(near));
+    assert!(wheel.cancel(far));
+    assert!(!wheel.cancel(far));
+    assert_eq!(wheel.len(), 1);
+    assert_eq!(wheel.metrics().overflow, 0);
+
+    assert_eq!(
+        fired_sessions(&mut wheel, START + 500 * 86_400 * SECOND),
+        ["kept"]
+    );
+    let metrics = wheel.metrics();
+    assert_eq!(
+        (metrics.scheduled, metrics.cancelled, metrics.fired),
+        (3, 2, 1)
+    );
+    assert_eq!(metrics.pending, 0);
+}
+
+#[test]
+fn test_clock_jump_fires_every_passed_deadline_in_one_batch() {
+    let mut wheel = wheel();
+    let mut rng = XorShift(7u64.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    let mut deadlines: Vec<i64> = (0..2_000)
+        .map(|_| START + 1 + rng.below(3_000 * 86_400) as i64 * SECOND)
+        .collect();
+    for deadline in &deadlines {
+        wheel.schedule(*deadline, TimerEvent::ExpireOrders);
+    }
+    deadlines.sort();
+
+    let now = START + 3_000 * 86_400 * SECOND;
+    let fired = wheel.advance_to(now);
+    let fired_deadlines: Vec<i64> = fired.iter().map(|timer| timer.deadline).collect();
+    assert_eq!(fired_deadlines, deadlines);
+    assert!(wheel.is_empty());
+
+    let metrics = wheel.metrics();
+    assert_eq!(metrics.last_batch, 2_000);
+    assert_eq!(metrics.peak_pending, 2_000);
+    assert_eq!(metrics.max_lag_nanos, now - deadlines[0]);
+}
+
+/// Random schedules, cancels and clock moves, checked against a sorted map
+/// of live deadlines.
+#[test]
+fn test_wheel_matches_model() {
+    for seed in 1..=20u64 {
+        let mut rng = XorShift(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        let mut wheel = wheel();
+        let mut model = BTreeMap::new();
+        let mut handles = Vec::new();
+        let mut now = START;
+
+        for _ in 0..500 {
+            match rng.below(10) {
+                0..=4 => {
+                    let span = [
+                        10 * MILLI,
+                        5 * SECOND,
+                        3_600 * SECOND,
+                        800 * 86_400 * SECOND,
+                    ][rng.below(4) as usize];
+                    let deadline = now - MILLI + rng.below(span as u64) as i64;
+                    let handle = wheel.schedule(deadline, TimerEvent::ExpireOrders);
+                    model.insert((deadline, handle), ());
+                    handles.push((deadline, handle));
+                }
+                5 | 6 if !handles.is_empty() => {
+                    let (deadline, handle) =
+                        handles.swap_remove(rng.below(handles.len() as u64) as usize);
+                    let live = model.remove(&(deadline, handle)).is_some();
+                    assert_eq!(wheel.cancel(handle), live, "seed {seed}");
+                }
+                _ => {
+                    let step = [MILLI, SECOND, 7_200 * SECOND, 90 * 86_400 * SECOND]
+                        [rng.below(4) as usize];
+                    now += rng.below(step as u64) as i64;
+                    let fired: Vec<_> = wheel
+                        .advance_to(now)
+                        .into_iter()
+                        .map(|timer| (timer.deadline, timer.handle))
+                        .collect();
+                    let tick = now / MILLI * MILLI;
+                    let due: Vec<_> = model
+                        .keys()
+                        .filter(|(deadline, _)| *deadline <= tick)
+                        .copied()
+                        .collect();
+                    for key in &due {
+                        model.remove(key);
+                    }
+                    assert_eq!(fired, due, "seed {seed} at {now}");
+                }
+            }
+            assert_eq!(wheel.len(), model.len(), "seed {seed}");
+        }
+    }
+}
+
+#[test]
+fn test_tick_delivers_one_batch_per_target() {
+    let clock = Arc::new(ManualClock::new(START));
+    let timers = Timers::new(clock.clone());
+    let engine = timers.subscribe(TimerTarget::Engine);
+    let session = timers.subscribe(TimerTarget::Session("a".to_string()));
+
+    timers.schedule(START + 2 * MILLI, TimerEvent::ExpireOrders);
+    timers.schedule(START + MILLI, probe("a"));
+    timers.schedule(START + 3 * MILLI, TimerEvent::ExpireOrders);
+    timers.schedule(START + MILLI, probe("nobody"));
+    timers.schedule_after(Duration::from_secs(1), TimerEvent::ExpireOrders);
+    assert_eq!(timers.pending(), 5);
+
+    clock.advance(10 * MILLI);
+    assert_eq!(timers.tick(), 4);
+
+    let batches: Vec<_> = engine.try_iter().collect();
+    assert_eq!(batches.len(), 1);
+    let deadlines: Vec<i64> = batches[0].iter().map(|timer| timer.deadline).collect();
+    assert_eq!(deadlines, [START + 2 * MILLI, START + 3 * MILLI]);
+    assert_eq!(batches[0][0].fired_at, clock.now_nanos());
+    assert_eq!(batches[0][0].lag_nanos(), 8 * MILLI);
+
+    assert_eq!(session.try_iter().flatten().count(), 1);
+    let metrics = timers.metrics();
+    assert_eq!(metrics.undelivered, 1);
+    assert_eq!(metrics.pending, 1);
+    assert_eq!(metrics.max_lag_nanos, 9 * MILLI);
+}
+
+fn gtd_order(price: Price, expires_in: i64) -> Order {
+    let mut order = Order::new(
+        "AAPL".to_string(),
+        Side::Buy,
+        OrderType::Limit,
+        price,
+        10,
+        1,
+    );
+    order.timestamp = START;
+    order.time_in_force = TimeInForce::GTD;
+    order.expiration_time = START + expires_in;
+    order
+}
+
+#[test]
+fn test_engine_expires_gtd_orders_from_timers() {
+    let clock = Arc::new(ManualClock::new(START));
+    let timers = Timers::new(clock.clone());
+    let mut engine = MatchingEngine::with_clock(MatchingEngineConfig::default(), clock.clone());
+    engine.add_symbol("AAPL");
+    engine.attach_timers(timers.clone());
+
+    let mut ids = Vec::new();
+    for (price, expires_in) in [(100, 30 * SECOND), (101, 10 * SECOND), (102, 10 * SECOND)] {
+        let result = engine.place_order(gtd_order(price, expires_in)).unwrap();
+        ids.push(result.remaining_order.unwrap().read().id);
+    }
+    engine
+        .place_order(Order::new(
+            "AAPL".to_string(),
+            Side::Buy,
+            OrderType::Limit,
+            99,
+            10,
+            1,
+        ))
+        .unwrap();
+    // Orders sharing an expiry share a timer.
+    assert_eq!(timers.pending(), 2);
+
+    clock.advance(10 * SECOND - 1);
+    timers.tick();
+    assert!(engine.run_timers().unwrap().is_empty());
+
+    // Jump past both deadlines at once: each expires at its own deadline.
+    clock.advance(SECOND * 60);
+    timers.tick();
+    let expired: Vec<(u64, i64)> = engine
+        .run_timers()
+        .unwrap()
+        .iter()
+        .map(|order| (order.read().id, order.read().last_update))
+        .collect();
+    let mut first_two = vec![(ids[1], START + 10 * SECOND), (ids[2], START + 10 * SECOND)];
+    first_two.sort();
+    let mut head = expired[..2].to_vec();
+    head.sort();
+    assert_eq!(head, first_two);
+    assert_eq!(expired[2], (ids[0], START + 30 * SECOND));
+    assert_eq!(expired.len(), 3);
+
+    assert_eq!(engine.order_books["AAPL"].get_best_bid_price(), Some(99));
+    assert_eq!(timers.pending(), 0);
+}
+
+#[test]
+fn test_restored_engine_rearms_resting_orders() {
+    let clock = Arc::new(ManualClock::new(START));
+    let mut engine = MatchingEngine::with_clock(MatchingEngineConfig::default(), clock.clone());
+    engine.add_symbol("AAPL");
+    let id = engine
+        .place_order(gtd_order(100, 5 * SECOND))
+        .unwrap()
+        .remaining_order
+        .unwrap()
+        .read()
+        .id;
+
+    let mut restored = MatchingEngine::restore_with_clock(
+        MatchingEngineConfig::default(),
+        clock.clone(),
+        &engine.snapshot(),
+    );
+    let timers = Timers::new(clock.clone());
+    restored.attach_timers(timers.clone());
+    assert_eq!(timers.pending(), 1);
+
+    clock.advance(5 * SECOND);
+    timers.tick();
+    let expired = restored.run_timers().unwrap();
+    assert_eq!(expired.len(), 1);
+    assert_eq!(expired[0].read().id, id);
+}
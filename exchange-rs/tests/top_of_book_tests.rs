@@ -0,0 +1,130 @@
+use exchange_rs::matching_engine::MatchingEngine;
+use exchange_rs::order::{Order, OrderType, Side};
+use exchange_rs::orderbook::OrderBook;
+use exchange_rs::top_of_book::TopOfBook;
+use exchange_rs::Price;
+use parking_lot::RwLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+fn order(id: u64, side: Side, price: Price, quantity: u32) -> Arc<RwLock<Order>> {
+    let mut order = Order::new(
+        "AAPL".to_string(),
+        side,
+        OrderType::Limit,
+        price,
+        quantity,
+        1,
+    );
+    order.id = id;
+    Arc::new(RwLock::new(order))
+}
+
+#[test]
+fn test_top_of_book_follows_the_best_levels() {
+    let mut book = OrderBook::new("AAPL");
+    assert_eq!(book.top_of_book(), TopOfBook::default());
+
+    book.add_order(order(1, Side::Buy, 99, 5)).unwrap();
+    book.add_order(order(2, Side::Buy, 100, 3)).unwrap();
+    book.add_order(order(3, Side::Sell, 102, 7)).unwrap();
+    book.add_order(order(4, Side::Buy, 100, 2)).unwrap();
+    let top = book.top_of_book();
+    assert_eq!(top.bid, Some((100, 5)));
+    assert_eq!(top.ask, Some((102, 7)));
+
+    // A change behind the best levels publishes nothing.
+    book.add_order(order(5, Side::Sell, 105, 1)).unwrap();
+    assert_eq!(book.top_of_book(), top);
+
+    book.cancel_order(2);
+    book.cancel_order(4);
+    let after = book.top_of_book();
+    assert_eq!(after.bid, Some((99, 5)));
+    assert!(after.sequence > top.sequence);
+}
+
+#[test]
+fn test_top_of_book_follows_matching() {
+    let mut engine = MatchingEngine::new();
+    engine.add_symbol("AAPL");
+    let place = |engine: &mut MatchingEngine, side, price, quantity| {
+        let order = Order::new("AAPL".to_string(), side, OrderType::Limit, price, quantity, 1);
+        engine.place_order(order).unwrap()
+    };
+
+    place(&mut engine, Side::Sell, 101, 10);
+    place(&mut engine, Side::Sell, 102, 4);
+    place(&mut engine, Side::Buy, 101, 6);
+    let top = engine.order_books["AAPL"].top_of_book();
+    assert_eq!(top.bid, None);
+    assert_eq!(top.ask, Some((101, 4)));
+
+    place(&mut engine, Side::Buy, 102, 6);
+    let top = engine.order_books["AAPL"].top_of_book();
+    assert_eq!(top.bid, None);
+    assert_eq!(top.ask, Some((102, 2)));
+}
+
+/// One thread churns a book while readers copy its top of book. Every
+/// order at price `p` has quantity `p`, so a size that is not a multiple of
+/// its price would be a torn read.
+#[test]
+fn test_readers_never_see_a_torn_quote() {
+    const STEPS: u64 = 200_000;
+
+    let mut book = OrderBook::new("AAPL");
+    let cell = book.top_of_book_cell();
+    let done = Arc::new(AtomicBool::new(false));
+
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let cell = Arc::clone(&cell);
+            let done = Arc::clone(&done);
+            thread::spawn(move || {
+                let mut last = 0;
+                let mut reads = 0u64;
+                while !done.load(Ordering::Acquire) {
+                    let top = cell.read();
+                    assert!(top.sequence >= last, "sequence went back to {} from {}", top.sequence, last);
+                    last = top.sequence;
+                    for (price, size) in top.bid.into_iter().chain(top.ask) {
+                        assert!(size > 0 && (size as Price).rem_euclid(price) == 0, "torn level {} x {}", price, size);
+                    }
+                    if let (Some((bid, _)), Some((ask, _))) = (top.bid, top.ask) {
+                        assert!(bid < ask, "crossed quote {} / {}", bid, ask);
+                    }
+                    reads += 1;
+                }
+                reads
+            })
+        })
+        .collect();
+
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut resting = Vec::new();
+    for id in 1..=STEPS {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        if resting.len() > 20 && state.is_multiple_of(3) {
+            let index = (state >> 8) as usize % resting.len();
+            book.cancel_order(resting.swap_remove(index));
+            continue;
+        }
+        let (side, price) = if state & 1 == 0 {
+            (Side::Buy, 90 + ((state >> 16) % 10) as Price)
+        } else {
+            (Side::Sell, 101 + ((state >> 16) % 10) as Price)
+        };
+        book.add_order(order(id, side, price, price as u32)).unwrap();
+        resting.push(id);
+    }
+    done.store(true, Ordering::Release);
+
+    for reader in readers {
+        assert!(reader.join().unwrap() > 0);
+    }
+    assert!(book.top_of_book().sequence > 0);
+}